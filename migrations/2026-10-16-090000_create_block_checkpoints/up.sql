@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE block_checkpoints (
+    chain VARCHAR NOT NULL,
+    height BIGINT NOT NULL,
+    block_hash VARCHAR(64) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (chain, height)
+);
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+-- No foreign key: the log must outlive archived HTLCs
+CREATE TABLE htlc_audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    htlc_id VARCHAR NOT NULL,
+    old_state SMALLINT,
+    new_state SMALLINT NOT NULL,
+    actor VARCHAR NOT NULL,
+    reason TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_htlc_audit_log_htlc_id ON htlc_audit_log(htlc_id);
+CREATE INDEX idx_htlc_audit_log_created_at ON htlc_audit_log(created_at);
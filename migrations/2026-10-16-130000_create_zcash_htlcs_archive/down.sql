@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_zcash_htlcs_settled;
+DROP TABLE IF EXISTS zcash_htlcs_archive;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+-- Same columns as zcash_htlcs, in the same order, plus when the row moved
+CREATE TABLE zcash_htlcs_archive (LIKE zcash_htlcs INCLUDING DEFAULTS);
+ALTER TABLE zcash_htlcs_archive ADD PRIMARY KEY (id);
+ALTER TABLE zcash_htlcs_archive ADD COLUMN archived_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+CREATE INDEX idx_zcash_htlcs_settled ON zcash_htlcs(state, updated_at);
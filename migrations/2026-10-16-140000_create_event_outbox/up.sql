@@ -0,0 +1,13 @@
+-- Your SQL goes here
+CREATE TABLE event_outbox (
+    id BIGSERIAL PRIMARY KEY,
+    htlc_id VARCHAR NOT NULL,
+    event_type VARCHAR NOT NULL,
+    payload TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    delivered_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_event_outbox_undelivered ON event_outbox(id) WHERE delivered_at IS NULL;
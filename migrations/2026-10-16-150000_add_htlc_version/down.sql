@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE zcash_htlcs_archive DROP COLUMN IF EXISTS version;
+ALTER TABLE zcash_htlcs DROP COLUMN IF EXISTS version;
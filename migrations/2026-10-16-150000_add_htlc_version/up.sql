@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- Bumped by every update, for compare-and-swap writes
+ALTER TABLE zcash_htlcs ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE zcash_htlcs_archive ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
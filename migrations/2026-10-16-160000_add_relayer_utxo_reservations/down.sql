@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_relayer_utxos_reserved_by;
+ALTER TABLE relayer_utxos DROP COLUMN IF EXISTS reserved_until;
+ALTER TABLE relayer_utxos DROP COLUMN IF EXISTS reserved_by;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- A UTXO is reserved for one in-flight transaction until reserved_until
+ALTER TABLE relayer_utxos ADD COLUMN reserved_by VARCHAR(255);
+ALTER TABLE relayer_utxos ADD COLUMN reserved_until TIMESTAMPTZ;
+
+CREATE INDEX idx_relayer_utxos_reserved_by ON relayer_utxos(reserved_by);
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- zcash_htlcs(p2sh_address) is already indexed
+CREATE INDEX idx_zcash_htlcs_recipient_address ON zcash_htlcs(recipient_address)
+    WHERE recipient_address IS NOT NULL;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_zcash_htlcs_hash_lock_network;
+CREATE INDEX idx_zcash_htlcs_hash_lock ON zcash_htlcs(hash_lock);
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+-- One secret must never unlock two HTLCs on the same network. Existing
+-- duplicates have to be resolved before this migration can run.
+DROP INDEX IF EXISTS idx_zcash_htlcs_hash_lock;
+CREATE UNIQUE INDEX idx_zcash_htlcs_hash_lock_network ON zcash_htlcs(hash_lock, network);
@@ -0,0 +1,12 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_zcash_htlcs_hash_lock_network;
+CREATE UNIQUE INDEX idx_zcash_htlcs_hash_lock_network ON zcash_htlcs(hash_lock, network);
+
+DROP INDEX IF EXISTS idx_relayer_utxos_tenant_id;
+DROP INDEX IF EXISTS idx_htlc_operations_tenant_id;
+DROP INDEX IF EXISTS idx_zcash_htlcs_tenant_id;
+
+ALTER TABLE relayer_utxos DROP COLUMN tenant_id;
+ALTER TABLE htlc_operations DROP COLUMN tenant_id;
+ALTER TABLE zcash_htlcs_archive DROP COLUMN tenant_id;
+ALTER TABLE zcash_htlcs DROP COLUMN tenant_id;
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+-- Rows written before tenants existed belong to the default tenant
+ALTER TABLE zcash_htlcs ADD COLUMN tenant_id VARCHAR(255) NOT NULL DEFAULT 'default';
+ALTER TABLE zcash_htlcs_archive ADD COLUMN tenant_id VARCHAR(255) NOT NULL DEFAULT 'default';
+ALTER TABLE htlc_operations ADD COLUMN tenant_id VARCHAR(255) NOT NULL DEFAULT 'default';
+ALTER TABLE relayer_utxos ADD COLUMN tenant_id VARCHAR(255) NOT NULL DEFAULT 'default';
+
+CREATE INDEX idx_zcash_htlcs_tenant_id ON zcash_htlcs(tenant_id);
+CREATE INDEX idx_htlc_operations_tenant_id ON htlc_operations(tenant_id);
+CREATE INDEX idx_relayer_utxos_tenant_id ON relayer_utxos(tenant_id);
+
+-- Tenants may not see each other's hash locks, so uniqueness is per tenant
+DROP INDEX IF EXISTS idx_zcash_htlcs_hash_lock_network;
+CREATE UNIQUE INDEX idx_zcash_htlcs_hash_lock_network
+    ON zcash_htlcs(tenant_id, hash_lock, network);
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+ALTER TABLE zcash_htlcs ADD COLUMN metadata JSONB;
+ALTER TABLE zcash_htlcs_archive ADD COLUMN metadata JSONB;
+
+-- Serves containment filters (metadata @> '{"order_id": "..."}')
+CREATE INDEX idx_zcash_htlcs_metadata ON zcash_htlcs USING GIN (metadata jsonb_path_ops);
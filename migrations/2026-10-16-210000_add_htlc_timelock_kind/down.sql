@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE zcash_htlcs_archive DROP COLUMN timelock_kind;
+ALTER TABLE zcash_htlcs DROP COLUMN timelock_kind;
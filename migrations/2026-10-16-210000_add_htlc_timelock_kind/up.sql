@@ -0,0 +1,7 @@
+-- Your SQL goes here
+-- Lock times of 500000000 and above are Unix timestamps, as for OP_CHECKLOCKTIMEVERIFY
+ALTER TABLE zcash_htlcs ADD COLUMN timelock_kind VARCHAR(16) NOT NULL DEFAULT 'height';
+ALTER TABLE zcash_htlcs_archive ADD COLUMN timelock_kind VARCHAR(16) NOT NULL DEFAULT 'height';
+
+UPDATE zcash_htlcs SET timelock_kind = 'timestamp' WHERE timelock >= 500000000;
+UPDATE zcash_htlcs_archive SET timelock_kind = 'timestamp' WHERE timelock >= 500000000;
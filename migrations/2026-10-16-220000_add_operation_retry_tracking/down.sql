@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_htlc_operations_next_retry_at;
+ALTER TABLE htlc_operations DROP COLUMN next_retry_at;
+ALTER TABLE htlc_operations DROP COLUMN last_attempt_at;
+ALTER TABLE htlc_operations DROP COLUMN attempt_count;
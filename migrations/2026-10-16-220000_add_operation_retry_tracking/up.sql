@@ -0,0 +1,7 @@
+-- Your SQL goes here
+ALTER TABLE htlc_operations ADD COLUMN attempt_count INT NOT NULL DEFAULT 0;
+ALTER TABLE htlc_operations ADD COLUMN last_attempt_at TIMESTAMPTZ;
+ALTER TABLE htlc_operations ADD COLUMN next_retry_at TIMESTAMPTZ;
+
+CREATE INDEX idx_htlc_operations_next_retry_at ON htlc_operations(next_retry_at)
+    WHERE status = 'failed';
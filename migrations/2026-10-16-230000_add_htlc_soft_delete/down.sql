@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_zcash_htlcs_hash_lock_network;
+-- Fails while a deleted HTLC shares its hash lock with a live one
+CREATE UNIQUE INDEX idx_zcash_htlcs_hash_lock_network
+    ON zcash_htlcs(tenant_id, hash_lock, network);
+
+ALTER TABLE zcash_htlcs_archive DROP COLUMN deleted_at;
+ALTER TABLE zcash_htlcs DROP COLUMN deleted_at;
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+-- Deleted HTLCs stay for their audit history but are hidden from operational queries
+ALTER TABLE zcash_htlcs ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE zcash_htlcs_archive ADD COLUMN deleted_at TIMESTAMPTZ;
+
+-- A deleted HTLC no longer holds its hash lock, so a corrected one can reuse it
+DROP INDEX IF EXISTS idx_zcash_htlcs_hash_lock_network;
+CREATE UNIQUE INDEX idx_zcash_htlcs_hash_lock_network
+    ON zcash_htlcs(tenant_id, hash_lock, network)
+    WHERE deleted_at IS NULL;
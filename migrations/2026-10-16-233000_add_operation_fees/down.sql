@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE htlc_operations DROP COLUMN fee_rate;
+ALTER TABLE htlc_operations DROP COLUMN tx_size;
+ALTER TABLE htlc_operations DROP COLUMN fee_zat;
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+ALTER TABLE htlc_operations ADD COLUMN fee_zat BIGINT;
+ALTER TABLE htlc_operations ADD COLUMN tx_size INT;
+ALTER TABLE htlc_operations ADD COLUMN fee_rate BIGINT;
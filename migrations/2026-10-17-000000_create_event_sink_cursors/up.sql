@@ -0,0 +1,9 @@
+-- Your SQL goes here
+CREATE TABLE event_sink_cursors (
+    sink TEXT PRIMARY KEY,
+    last_event_id BIGINT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
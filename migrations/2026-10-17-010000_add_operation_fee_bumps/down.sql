@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_htlc_operations_bumps_operation_id;
+ALTER TABLE htlc_operations DROP COLUMN bumps_operation_id;
+ALTER TABLE htlc_operations DROP COLUMN broadcast_height;
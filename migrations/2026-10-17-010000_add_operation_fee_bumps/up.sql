@@ -0,0 +1,7 @@
+-- Your SQL goes here
+ALTER TABLE htlc_operations ADD COLUMN broadcast_height BIGINT;
+ALTER TABLE htlc_operations ADD COLUMN bumps_operation_id VARCHAR;
+
+CREATE INDEX idx_htlc_operations_bumps_operation_id
+    ON htlc_operations (bumps_operation_id)
+    WHERE bumps_operation_id IS NOT NULL;
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_zcash_htlcs_needs_intervention;
+ALTER TABLE zcash_htlcs DROP COLUMN last_error;
+ALTER TABLE zcash_htlcs DROP COLUMN next_creation_at;
+ALTER TABLE zcash_htlcs DROP COLUMN creation_attempts;
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+ALTER TABLE zcash_htlcs ADD COLUMN creation_attempts INT NOT NULL DEFAULT 0;
+ALTER TABLE zcash_htlcs ADD COLUMN next_creation_at TIMESTAMPTZ;
+ALTER TABLE zcash_htlcs ADD COLUMN last_error TEXT;
+
+-- HTLCs in the NeedsIntervention state, for the operator queue
+CREATE INDEX idx_zcash_htlcs_needs_intervention
+    ON zcash_htlcs (tenant_id, updated_at)
+    WHERE state = 6;
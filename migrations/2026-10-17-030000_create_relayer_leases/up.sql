@@ -0,0 +1,9 @@
+-- Your SQL goes here
+CREATE TABLE relayer_leases (
+    tenant_id VARCHAR(255) NOT NULL DEFAULT 'default',
+    name TEXT NOT NULL,
+    holder TEXT NOT NULL,
+    acquired_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (tenant_id, name)
+);
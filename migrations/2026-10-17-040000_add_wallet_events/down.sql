@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM event_outbox WHERE htlc_id IS NULL;
+ALTER TABLE event_outbox ALTER COLUMN htlc_id SET NOT NULL;
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- Hot wallet alerts share the outbox with HTLC events but belong to no HTLC
+ALTER TABLE event_outbox ALTER COLUMN htlc_id DROP NOT NULL;
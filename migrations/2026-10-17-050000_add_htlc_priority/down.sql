@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_zcash_htlcs_pending_priority;
+ALTER TABLE zcash_htlcs_archive DROP COLUMN priority;
+ALTER TABLE zcash_htlcs DROP COLUMN priority;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+-- 0 economy, 1 normal, 2 urgent
+ALTER TABLE zcash_htlcs ADD COLUMN priority SMALLINT NOT NULL DEFAULT 1;
+ALTER TABLE zcash_htlcs_archive ADD COLUMN priority SMALLINT NOT NULL DEFAULT 1;
+
+CREATE INDEX idx_zcash_htlcs_pending_priority ON zcash_htlcs (tenant_id, priority DESC, created_at)
+    WHERE state = 0 AND txid IS NULL;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE zcash_htlcs DROP COLUMN last_error_kind;
+ALTER TABLE htlc_operations DROP COLUMN error_kind;
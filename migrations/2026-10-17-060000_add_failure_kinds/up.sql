@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- funding, signing, broadcast, database, node or other
+ALTER TABLE htlc_operations ADD COLUMN error_kind VARCHAR(16);
+ALTER TABLE zcash_htlcs ADD COLUMN last_error_kind VARCHAR(16);
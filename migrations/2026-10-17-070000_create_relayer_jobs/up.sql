@@ -0,0 +1,39 @@
+-- Your SQL goes here
+CREATE TABLE relayer_jobs (
+    id BIGSERIAL PRIMARY KEY,
+    tenant_id VARCHAR(255) NOT NULL DEFAULT 'default',
+    -- fund_htlc, broadcast_redeem or refund_htlc
+    job_type VARCHAR(32) NOT NULL,
+    -- JSON arguments, e.g. {"htlc_id": "..."}
+    payload TEXT NOT NULL,
+    -- queued, claimed or failed; finished jobs are deleted
+    state VARCHAR(16) NOT NULL DEFAULT 'queued',
+    -- At most one unfinished job per type and key
+    dedupe_key TEXT,
+    -- 0 economy, 1 normal, 2 urgent
+    priority SMALLINT NOT NULL DEFAULT 1,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    claimed_by TEXT,
+    -- A queued job runs from then on; a claimed one is handed to another
+    -- worker then unless finished first
+    visible_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_relayer_jobs_dedupe ON relayer_jobs (tenant_id, job_type, dedupe_key)
+    WHERE state IN ('queued', 'claimed');
+CREATE INDEX idx_relayer_jobs_claimable ON relayer_jobs (tenant_id, job_type, priority DESC, id)
+    WHERE state IN ('queued', 'claimed');
+
+-- Work queued before the job table existed
+INSERT INTO relayer_jobs (tenant_id, job_type, payload, dedupe_key, priority)
+SELECT tenant_id, 'fund_htlc', json_build_object('htlc_id', id)::text, id, priority
+FROM zcash_htlcs
+WHERE state = 0 AND txid IS NULL AND deleted_at IS NULL;
+
+INSERT INTO relayer_jobs (tenant_id, job_type, payload, dedupe_key, priority)
+SELECT tenant_id, 'broadcast_redeem', json_build_object('htlc_id', id)::text, id, priority
+FROM zcash_htlcs
+WHERE state = 1 AND signed_redeem_tx IS NOT NULL AND deleted_at IS NULL;
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX idx_event_outbox_created_at;
+
+ALTER TABLE event_sink_cursors
+    ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN last_error TEXT,
+    ADD COLUMN next_attempt_at TIMESTAMPTZ;
+
+DROP TABLE event_deliveries;
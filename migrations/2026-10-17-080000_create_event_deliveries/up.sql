@@ -0,0 +1,21 @@
+-- Your SQL goes here
+-- Delivery state of each event a sink has tried, so one failing event
+-- backs off on its own instead of holding up every later one. Rows at or
+-- below the sink's cursor are settled and removed.
+CREATE TABLE event_deliveries (
+    sink TEXT NOT NULL,
+    event_id BIGINT NOT NULL REFERENCES event_outbox(id) ON DELETE CASCADE,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TIMESTAMPTZ,
+    delivered_at TIMESTAMPTZ,
+    abandoned_at TIMESTAMPTZ,
+    PRIMARY KEY (sink, event_id)
+);
+
+ALTER TABLE event_sink_cursors
+    DROP COLUMN attempts,
+    DROP COLUMN last_error,
+    DROP COLUMN next_attempt_at;
+
+CREATE INDEX idx_event_outbox_created_at ON event_outbox(created_at);
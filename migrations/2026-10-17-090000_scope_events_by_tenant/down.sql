@@ -0,0 +1,19 @@
+-- This file should undo anything in `up.sql`
+-- Fails while two tenants share a sink name
+ALTER TABLE event_deliveries DROP CONSTRAINT event_deliveries_pkey;
+ALTER TABLE event_deliveries ADD PRIMARY KEY (sink, event_id);
+ALTER TABLE event_deliveries DROP COLUMN tenant_id;
+
+ALTER TABLE event_sink_cursors DROP CONSTRAINT event_sink_cursors_pkey;
+ALTER TABLE event_sink_cursors ADD PRIMARY KEY (sink);
+ALTER TABLE event_sink_cursors DROP COLUMN tenant_id;
+
+DROP INDEX IF EXISTS idx_event_outbox_tenant_id;
+DROP INDEX IF EXISTS idx_htlc_audit_log_tenant_id;
+ALTER TABLE event_outbox DROP COLUMN tenant_id;
+ALTER TABLE htlc_audit_log DROP COLUMN tenant_id;
+
+DROP INDEX IF EXISTS idx_zcash_htlcs_hash_lock_network;
+CREATE UNIQUE INDEX idx_zcash_htlcs_hash_lock_network
+    ON zcash_htlcs(tenant_id, hash_lock, network)
+    WHERE deleted_at IS NULL;
@@ -0,0 +1,28 @@
+-- Your SQL goes here
+-- A hash lock names one swap on one network, so no two tenants may lock
+-- funds behind it; only deleted HTLCs give theirs up
+DROP INDEX IF EXISTS idx_zcash_htlcs_hash_lock_network;
+CREATE UNIQUE INDEX idx_zcash_htlcs_hash_lock_network
+    ON zcash_htlcs(hash_lock, network)
+    WHERE deleted_at IS NULL;
+
+-- Audit entries and events belong to the tenant of the HTLC or wallet they
+-- describe, and each tenant's sinks keep cursors of their own
+ALTER TABLE htlc_audit_log ADD COLUMN tenant_id VARCHAR(255) NOT NULL DEFAULT 'default';
+ALTER TABLE event_outbox ADD COLUMN tenant_id VARCHAR(255) NOT NULL DEFAULT 'default';
+
+UPDATE htlc_audit_log a SET tenant_id = h.tenant_id
+    FROM zcash_htlcs h WHERE h.id = a.htlc_id;
+UPDATE event_outbox e SET tenant_id = h.tenant_id
+    FROM zcash_htlcs h WHERE h.id = e.htlc_id;
+
+CREATE INDEX idx_htlc_audit_log_tenant_id ON htlc_audit_log(tenant_id, htlc_id);
+CREATE INDEX idx_event_outbox_tenant_id ON event_outbox(tenant_id, id);
+
+ALTER TABLE event_sink_cursors ADD COLUMN tenant_id VARCHAR(255) NOT NULL DEFAULT 'default';
+ALTER TABLE event_sink_cursors DROP CONSTRAINT event_sink_cursors_pkey;
+ALTER TABLE event_sink_cursors ADD PRIMARY KEY (tenant_id, sink);
+
+ALTER TABLE event_deliveries ADD COLUMN tenant_id VARCHAR(255) NOT NULL DEFAULT 'default';
+ALTER TABLE event_deliveries DROP CONSTRAINT event_deliveries_pkey;
+ALTER TABLE event_deliveries ADD PRIMARY KEY (tenant_id, sink, event_id);
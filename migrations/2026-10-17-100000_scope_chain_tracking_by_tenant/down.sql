@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+-- Fails while several tenants track the same chain
+ALTER TABLE indexer_checkpoints DROP CONSTRAINT indexer_checkpoints_tenant_id_chain_key;
+ALTER TABLE indexer_checkpoints ADD CONSTRAINT indexer_checkpoints_chain_key UNIQUE (chain);
+ALTER TABLE indexer_checkpoints DROP COLUMN tenant_id;
+
+ALTER TABLE block_checkpoints DROP CONSTRAINT block_checkpoints_pkey;
+ALTER TABLE block_checkpoints ADD PRIMARY KEY (chain, height);
+ALTER TABLE block_checkpoints DROP COLUMN tenant_id;
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+-- Each tenant follows the chain for its own HTLCs: the block hashes it
+-- checks for reorgs and the height its scans reached
+ALTER TABLE block_checkpoints ADD COLUMN tenant_id VARCHAR(255) NOT NULL DEFAULT 'default';
+ALTER TABLE block_checkpoints DROP CONSTRAINT block_checkpoints_pkey;
+ALTER TABLE block_checkpoints ADD PRIMARY KEY (tenant_id, chain, height);
+
+ALTER TABLE indexer_checkpoints ADD COLUMN tenant_id VARCHAR(255) NOT NULL DEFAULT 'default';
+ALTER TABLE indexer_checkpoints DROP CONSTRAINT indexer_checkpoints_chain_key;
+ALTER TABLE indexer_checkpoints ADD CONSTRAINT indexer_checkpoints_tenant_id_chain_key
+    UNIQUE (tenant_id, chain);
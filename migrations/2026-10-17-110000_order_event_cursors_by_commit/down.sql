@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+-- Sink names stay digests: the URLs cannot be recovered from them
+ALTER TABLE event_outbox DROP COLUMN commit_horizon;
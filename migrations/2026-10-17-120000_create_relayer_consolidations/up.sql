@@ -0,0 +1,18 @@
+-- Your SQL goes here
+CREATE TABLE relayer_consolidations (
+    -- txid of the consolidation transaction
+    txid VARCHAR(255) PRIMARY KEY,
+    tenant_id VARCHAR(255) NOT NULL DEFAULT 'default',
+    -- Hot wallet address the inputs came from and the output pays
+    address VARCHAR(255) NOT NULL,
+    inputs INTEGER NOT NULL,
+    -- Value of the merged output, in zatoshis
+    amount_zat BIGINT NOT NULL,
+    fee_zat BIGINT NOT NULL,
+    tx_size INTEGER NOT NULL,
+    -- Zatoshis per 1000 bytes
+    fee_rate BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_relayer_consolidations_created ON relayer_consolidations (tenant_id, created_at);
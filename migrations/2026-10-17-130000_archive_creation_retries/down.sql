@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE zcash_htlcs_archive DROP COLUMN last_error;
+ALTER TABLE zcash_htlcs_archive DROP COLUMN next_creation_at;
+ALTER TABLE zcash_htlcs_archive DROP COLUMN creation_attempts;
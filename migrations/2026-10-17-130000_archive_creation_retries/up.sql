@@ -0,0 +1,4 @@
+-- Your SQL goes here
+ALTER TABLE zcash_htlcs_archive ADD COLUMN creation_attempts INT NOT NULL DEFAULT 0;
+ALTER TABLE zcash_htlcs_archive ADD COLUMN next_creation_at TIMESTAMPTZ;
+ALTER TABLE zcash_htlcs_archive ADD COLUMN last_error TEXT;
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE htlc_operations DROP COLUMN last_checked_at;
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE htlc_operations ADD COLUMN last_checked_at TIMESTAMPTZ;
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE zcash_htlcs_archive DROP COLUMN last_error_kind;
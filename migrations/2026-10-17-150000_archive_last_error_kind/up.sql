@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE zcash_htlcs_archive ADD COLUMN last_error_kind VARCHAR(16);
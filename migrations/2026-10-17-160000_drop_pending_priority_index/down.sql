@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+CREATE INDEX idx_zcash_htlcs_pending_priority ON zcash_htlcs (tenant_id, priority DESC, created_at)
+    WHERE state = 0 AND txid IS NULL;
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- Funding work is found through relayer_jobs now
+DROP INDEX IF EXISTS idx_zcash_htlcs_pending_priority;
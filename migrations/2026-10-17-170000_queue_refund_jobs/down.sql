@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM relayer_jobs WHERE job_type = 'refund_htlc' AND state = 'queued';
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+-- Refunds are queued when an HTLC locks; queue them for HTLCs already locked
+INSERT INTO relayer_jobs (tenant_id, job_type, payload, dedupe_key, priority)
+SELECT tenant_id, 'refund_htlc', json_build_object('htlc_id', id)::text, id, priority
+FROM zcash_htlcs
+WHERE state = 1 AND deleted_at IS NULL
+ON CONFLICT (tenant_id, job_type, dedupe_key) WHERE state IN ('queued', 'claimed')
+DO NOTHING;
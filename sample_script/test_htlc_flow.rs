@@ -1,7 +1,11 @@
 use std::sync::Arc;
 use tracing::{info, Level};
-use zcash_htlc_builder::{database::Database, HTLCParams, ZcashConfig, ZcashHTLCClient, UTXO};
+use zcash_htlc_builder::{
+    database::Database, FeePriority, HTLCParams, KeyMap, ZcashConfig, ZcashHTLCClient, UTXO,
+};
 
+// Funding values are placeholders consumed by the commented-out Step 5
+#[allow(unused_variables, clippy::useless_vec)]
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt().with_max_level(Level::INFO).init();
@@ -15,26 +19,27 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         config.database_max_connections,
     )?);
 
-    let client = ZcashHTLCClient::new(config, database);
+    let client = ZcashHTLCClient::new(config, database)?;
 
     // ==================== Step 1: Generate Keys ====================
     info!("\n📝 Step 1: Generating Keys");
 
     let recipient_privkey = client.generate_privkey();
     let recipient_pubkey = client.derive_pubkey(&recipient_privkey)?;
-    info!("  👤 Recipient Private Key: {}", recipient_privkey);
+    info!("  👤 Recipient Private Key: {}", recipient_privkey.as_str());
     info!("  👤 Recipient Public Key:  {}", recipient_pubkey);
 
     let refund_privkey = client.generate_privkey();
     let refund_pubkey = client.derive_pubkey(&refund_privkey)?;
-    info!("  🏦 Relayer Private Key:  {}", refund_privkey);
+    info!("  🏦 Relayer Private Key:  {}", refund_privkey.as_str());
     info!("  🏦 Relayer Public Key:   {}", refund_pubkey);
 
     // ==================== Step 2: Generate Secret & Hash Lock ====================
     info!("\n🔐 Step 2: Generating Secret and Hash Lock");
 
-    let secret = hex::encode(rand::random::<[u8; 32]>());
-    let hash_lock = client.generate_hash_lock(&secret);
+    let secret_bytes = rand::random::<[u8; 32]>();
+    let hash_lock = client.hash_lock(&secret_bytes);
+    let secret = hex::encode(secret_bytes);
     info!("  🗝️  Secret:    {}", secret);
     info!("  🔒 Hash Lock: {}", hash_lock);
 
@@ -50,6 +55,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         hash_lock: hash_lock.clone(),
         timelock,
         amount: "0.001".to_string(), // 0.001 ZEC
+        metadata: None,
+        priority: FeePriority::Normal,
+        htlc_id: None,
     };
 
     info!("  💰 Amount:    {} ZEC", params.amount);
@@ -68,7 +76,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }];
 
     let change_address = "your-change-address";
-    let funding_privkeys = vec!["your-funding-privkey"];
+    let funding_keys = KeyMap::new().with_key("your-funding-address", "your-funding-privkey");
 
     info!("  ⚠️  Note: Update funding_utxos with real values before creating HTLC");
 
@@ -81,7 +89,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         params,
         funding_utxos,
         change_address,
-        funding_privkeys,
+        &funding_keys,
     ).await?;
 
     info!("  ✅ HTLC Created!");
@@ -97,7 +105,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("    \"htlc_id\": \"<htlc-id-from-step-5>\",");
     info!("    \"secret\": \"{}\",", secret);
     info!("    \"recipient_address\": \"<user-zcash-address>\",");
-    info!("    \"recipient_privkey\": \"{}\"", recipient_privkey);
+    info!(
+        "    \"recipient_privkey\": \"{}\"",
+        recipient_privkey.as_str()
+    );
     info!("  }}");
 
     // ==================== Summary ====================
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::extract::{Query, State};
+use axum::http::header::AUTHORIZATION;
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tracing::info;
+
+use crate::database::{Database, DatabaseError, HTLCSearchMatch};
+use crate::{ApiTokenConfig, TenantContext};
+
+const DEFAULT_SEARCH_LIMIT: u32 = 20;
+const MAX_SEARCH_LIMIT: u32 = 100;
+
+/// Routes for support tooling, served by [`serve`] or merged into an
+/// embedder's own axum app. Every request must carry one of `tokens` as
+/// `Authorization: Bearer <token>` and sees only the HTLCs of that token's
+/// tenant; others get a 401.
+///
+/// - `GET /htlcs/search?q=<term>&limit=<n>` runs
+///   [`Database::search_htlcs`], answering 400 for terms that are too short
+pub fn router(database: Database, tokens: &[ApiTokenConfig]) -> Router {
+    let state = ApiState {
+        database,
+        tokens: ApiTokens::new(tokens),
+    };
+    Router::new()
+        .route("/htlcs/search", get(search_htlcs))
+        .with_state(Arc::new(state))
+}
+
+/// Serve [`router`] on `addr` until the task is dropped
+pub async fn serve(
+    addr: SocketAddr,
+    database: Database,
+    tokens: &[ApiTokenConfig],
+) -> std::io::Result<()> {
+    info!(
+        "🌐 Serving the HTLC API on {} for {} token(s)",
+        addr,
+        tokens.len()
+    );
+    axum::Server::bind(&addr)
+        .serve(router(database, tokens).into_make_service())
+        .await
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+}
+
+struct ApiState {
+    database: Database,
+    tokens: ApiTokens,
+}
+
+impl ApiState {
+    /// The database acting for the tenant of the request's bearer token
+    fn authorize(&self, headers: &HeaderMap) -> Result<Database, (StatusCode, String)> {
+        let tenant = self.tokens.tenant(headers).ok_or((
+            StatusCode::UNAUTHORIZED,
+            "missing or unknown bearer token".to_string(),
+        ))?;
+        Ok(self.database.clone().with_tenant(tenant.clone()))
+    }
+}
+
+/// Tenants by the SHA-256 of their tokens, so looking a token up takes
+/// no longer for a near miss than for a wild guess
+struct ApiTokens(HashMap<[u8; 32], TenantContext>);
+
+impl ApiTokens {
+    fn new(tokens: &[ApiTokenConfig]) -> Self {
+        Self(
+            tokens
+                .iter()
+                .map(|token| (digest(&token.token), token.tenant()))
+                .collect(),
+        )
+    }
+
+    fn tenant(&self, headers: &HeaderMap) -> Option<&TenantContext> {
+        let token = headers
+            .get(AUTHORIZATION)?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")?;
+        self.0.get(&digest(token))
+    }
+}
+
+fn digest(token: &str) -> [u8; 32] {
+    Sha256::digest(token.as_bytes()).into()
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchParams {
+    q: String,
+    limit: Option<u32>,
+}
+
+async fn search_htlcs(
+    State(state): State<Arc<ApiState>>,
+    headers: HeaderMap,
+    Query(params): Query<SearchParams>,
+) -> Result<Json<Vec<HTLCSearchMatch>>, (StatusCode, String)> {
+    let database = state.authorize(&headers)?;
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .min(MAX_SEARCH_LIMIT);
+
+    database
+        .run(move |db| db.search_htlcs(&params.q, limit))
+        .await
+        .map(Json)
+        .map_err(api_error)
+}
+
+fn api_error(e: DatabaseError) -> (StatusCode, String) {
+    let status = match e {
+        DatabaseError::SearchTermTooShort(_) => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens_select_their_tenant() {
+        let tokens = ApiTokens::new(&[
+            ApiTokenConfig {
+                token: "s3cret".to_string(),
+                tenant_id: Some("acme".to_string()),
+            },
+            ApiTokenConfig {
+                token: "other".to_string(),
+                tenant_id: None,
+            },
+        ]);
+        let tenant = |authorization: Option<&str>| {
+            let mut headers = HeaderMap::new();
+            if let Some(value) = authorization {
+                headers.insert(AUTHORIZATION, value.parse().unwrap());
+            }
+            tokens
+                .tenant(&headers)
+                .map(|tenant| tenant.id().to_string())
+        };
+
+        assert_eq!(tenant(Some("Bearer s3cret")).as_deref(), Some("acme"));
+        assert_eq!(tenant(Some("Bearer other")).as_deref(), Some("default"));
+        assert_eq!(tenant(Some("Bearer s3cre")), None);
+        assert_eq!(tenant(Some("s3cret")), None);
+        assert_eq!(tenant(None), None);
+    }
+}
@@ -1,6 +1,11 @@
-use std::{env, sync::Arc};
+use std::{env, io::BufRead, sync::Arc};
 use tracing::{info, Level};
-use zcash_htlc_builder::{database::Database, HTLCParams, ZcashConfig, ZcashHTLCClient};
+use zcash_htlc_builder::{
+    database::{Database, ExportFormat},
+    keystore::DEFAULT_KEYSTORE_DIR,
+    FeePriority, HTLCParams, Keystore, ZcashConfig, ZcashHTLCClient,
+};
+use zeroize::Zeroizing;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -23,7 +28,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         // "utxos" => list_utxos(&args).await?,
         "keygen" => generate_keys(&args)?,
         "hashlock" => generate_hashlock(&args)?,
+        "sign-message" => sign_message(&args).await?,
+        "verify-message" => verify_message(&args)?,
+        "keystore-new" => keystore_new(&args)?,
+        "keystore-import" => keystore_import(&args)?,
+        "keystore-list" => keystore_list(&args)?,
+        "import-wif" => import_wif(&args)?,
+        "export-wif" => export_wif(&args)?,
         "broadcast" => broadcast_tx(&args).await?,
+        "export-htlcs" => export_htlcs(&args)?,
+        "import-htlcs" => import_htlcs(&args)?,
+        "db" => manage_migrations(&args)?,
+        "search" => search_htlcs(&args)?,
+        "fees" => fee_summary(&args)?,
+        "dead-letters" => list_dead_letters(&args)?,
+        "requeue" => requeue_htlc(&args)?,
+        #[cfg(feature = "api")]
+        "serve-api" => serve_api(&args).await?,
         _ => {
             println!("❌ Unknown command: {}", command);
             print_usage();
@@ -34,6 +55,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 fn build_client(config_path: Option<&str>) -> Result<ZcashHTLCClient, Box<dyn std::error::Error>> {
+    let config = load_config(config_path)?;
+    let database = Arc::new(open_database(&config)?);
+
+    Ok(ZcashHTLCClient::new(config, database)?)
+}
+
+fn load_config(config_path: Option<&str>) -> Result<ZcashConfig, Box<dyn std::error::Error>> {
     let config = if let Some(path) = config_path {
         info!("📄 Loading config from: {}", path);
         if path.ends_with(".json") {
@@ -53,12 +81,62 @@ fn build_client(config_path: Option<&str>) -> Result<ZcashHTLCClient, Box<dyn st
         ZcashConfig::from_default_locations()?
     };
 
-    let database = Arc::new(Database::new(
-        &config.database_url,
-        config.database_max_connections,
-    )?);
+    Ok(config)
+}
+
+fn open_database(config: &ZcashConfig) -> Result<Database, Box<dyn std::error::Error>> {
+    let mut database =
+        Database::new(&config.database_url, config.database_max_connections)?.with_actor("cli");
+    if let Some(cipher) = config.column_cipher()? {
+        database = database.with_column_cipher(cipher);
+    }
+    if let Some(replica_url) = &config.database_replica_url {
+        database = database.with_read_replica(replica_url, config.database_max_connections);
+    }
+
+    Ok(database)
+}
+
+/// Keys given as `keystore:<id>` are decrypted from the keystore and
+/// `ledger:<path>` keys are signed on a connected Ledger device; anything
+/// else is treated as a hex private key
+fn with_key_backend(
+    client: ZcashHTLCClient,
+    key: &str,
+) -> Result<(ZcashHTLCClient, String), Box<dyn std::error::Error>> {
+    if let Some(id) = key.strip_prefix("keystore:") {
+        let keystore = open_keystore(&client)?;
+        let passphrase = read_passphrase()?;
+        info!("🔐 Signing with keystore key {}", id);
+        return Ok((client.with_keystore(keystore, passphrase), id.to_string()));
+    }
+
+    let Some(path) = key.strip_prefix("ledger:") else {
+        return Ok((client, key.to_string()));
+    };
+
+    #[cfg(feature = "ledger-hid")]
+    {
+        let ledger = zcash_htlc_builder::LedgerSigner::connect_hid()?.with_confirm_prompt(
+            |path, sighash| {
+                println!("👉 Confirm on your Ledger:");
+                println!("  Key:     {}", path);
+                println!("  Sighash: {}", hex::encode(sighash));
+            },
+        );
+        info!("🔐 Signing with Ledger key {}", path);
+        Ok((client.with_signer(Arc::new(ledger)), path.to_string()))
+    }
 
-    Ok(ZcashHTLCClient::new(config, database))
+    #[cfg(not(feature = "ledger-hid"))]
+    {
+        let _ = client;
+        Err(format!(
+            "cannot use Ledger key {}: rebuild with --features ledger-hid",
+            path
+        )
+        .into())
+    }
 }
 
 async fn create_htlc(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
@@ -73,8 +151,9 @@ async fn create_htlc(args: &[String]) -> Result<(), Box<dyn std::error::Error>>
     let refund_privkey = client.generate_privkey();
     let refund_pubkey = client.derive_pubkey(&refund_privkey)?;
 
-    let secret = hex::encode(rand::random::<[u8; 32]>());
-    let hash_lock = client.generate_hash_lock(&secret);
+    let secret_bytes = rand::random::<[u8; 32]>();
+    let hash_lock = client.hash_lock(&secret_bytes);
+    let secret = hex::encode(secret_bytes);
 
     info!("🔑 Recipient pubkey: {}", recipient_pubkey);
     info!("🔑 Refund pubkey: {}", refund_pubkey);
@@ -87,6 +166,9 @@ async fn create_htlc(args: &[String]) -> Result<(), Box<dyn std::error::Error>>
         hash_lock,
         timelock: 100000,
         amount: "0.01".to_string(),
+        metadata: None,
+        priority: FeePriority::Normal,
+        htlc_id: None,
     };
 
     info!("📝 HTLC Parameters generated successfully");
@@ -96,7 +178,7 @@ async fn create_htlc(args: &[String]) -> Result<(), Box<dyn std::error::Error>>
 async fn redeem_htlc(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
     if args.len() < 6 {
         println!(
-            "Usage: zcash-htlc-cli redeem <htlc_id> <secret> <address> <privkey> [config_file]"
+            "Usage: zcash-htlc-cli redeem <htlc_id> <secret_hex> <address> <privkey> [config_file]"
         );
         return Ok(());
     }
@@ -108,10 +190,11 @@ async fn redeem_htlc(args: &[String]) -> Result<(), Box<dyn std::error::Error>>
     let config_path = args.get(6).map(|s| s.as_str());
 
     let client = build_client(config_path)?;
+    let (client, key_id) = with_key_backend(client, privkey)?;
 
     info!("🔓 Redeeming HTLC: {}", htlc_id);
     let txid = client
-        .redeem_htlc(htlc_id, secret, address, privkey)
+        .redeem_htlc(htlc_id, secret, address, &key_id)
         .await?;
 
     info!("✅ Redeemed! TXID: {}", txid);
@@ -130,9 +213,10 @@ async fn refund_htlc(args: &[String]) -> Result<(), Box<dyn std::error::Error>>
     let config_path = args.get(5).map(|s| s.as_str());
 
     let client = build_client(config_path)?;
+    let (client, key_id) = with_key_backend(client, privkey)?;
 
     info!("♻️ Refunding HTLC: {}", htlc_id);
-    let txid = client.refund_htlc(htlc_id, address, privkey).await?;
+    let txid = client.refund_htlc(htlc_id, address, &key_id).await?;
 
     info!("✅ Refunded! TXID: {}", txid);
     Ok(())
@@ -203,7 +287,7 @@ fn generate_keys(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
     let pubkey = client.derive_pubkey(&privkey)?;
 
     println!("🔑 Generated Keys:");
-    println!("  Private Key: {}", privkey);
+    println!("  Private Key: {}", privkey.as_str());
     println!("  Public Key:  {}", pubkey);
 
     Ok(())
@@ -211,7 +295,7 @@ fn generate_keys(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
 
 fn generate_hashlock(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
     if args.len() < 3 {
-        println!("Usage: zcash-htlc-cli hashlock <secret> [config_file]");
+        println!("Usage: zcash-htlc-cli hashlock <secret_hex> [config_file]");
         return Ok(());
     }
 
@@ -219,7 +303,7 @@ fn generate_hashlock(args: &[String]) -> Result<(), Box<dyn std::error::Error>>
     let config_path = args.get(3).map(|s| s.as_str());
 
     let client = build_client(config_path)?;
-    let hash_lock = client.generate_hash_lock(secret);
+    let hash_lock = client.hash_lock_from_hex(secret)?;
 
     println!("🔒 Hash Lock:");
     println!("  Secret:    {}", secret);
@@ -228,19 +312,473 @@ fn generate_hashlock(args: &[String]) -> Result<(), Box<dyn std::error::Error>>
     Ok(())
 }
 
+async fn sign_message(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() < 4 {
+        println!("Usage: zcash-htlc-cli sign-message <key> <message> [config_file]");
+        return Ok(());
+    }
+
+    let key = &args[2];
+    let message = &args[3];
+    let config_path = args.get(4).map(|s| s.as_str());
+
+    let client = build_client(config_path)?;
+    let (client, key_id) = with_key_backend(client, key)?;
+    let signature = client.sign_message(message, &key_id).await?;
+
+    println!("✍️  Signed Message:");
+    println!("  Message:   {}", message);
+    println!("  Signature: {}", signature);
+
+    Ok(())
+}
+
+fn verify_message(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() < 5 {
+        println!(
+            "Usage: zcash-htlc-cli verify-message <address> <signature> <message> [config_file]"
+        );
+        return Ok(());
+    }
+
+    let address = &args[2];
+    let signature = &args[3];
+    let message = &args[4];
+    let config_path = args.get(5).map(|s| s.as_str());
+
+    let client = build_client(config_path)?;
+
+    if client.verify_message(address, message, signature)? {
+        println!("✅ Signature is valid for {}", address);
+    } else {
+        println!("❌ Signature does not match {}", address);
+    }
+
+    Ok(())
+}
+
+/// CSV for `.csv` files, JSON otherwise
+fn export_format(path: &str) -> ExportFormat {
+    if path.ends_with(".csv") {
+        ExportFormat::Csv
+    } else {
+        ExportFormat::Json
+    }
+}
+
+fn export_htlcs(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() < 3 {
+        println!("Usage: zcash-htlc-cli export-htlcs <file.json|file.csv> [config_file]");
+        return Ok(());
+    }
+
+    let path = &args[2];
+    let client = build_client(args.get(3).map(|s| s.as_str()))?;
+
+    let file = std::fs::File::create(path)?;
+    let count = client
+        .database()
+        .ok_or("exports need a Postgres database")?
+        .export_htlcs(export_format(path), file)?;
+
+    println!("✅ Exported {} HTLC(s) to {}", count, path);
+    Ok(())
+}
+
+fn import_htlcs(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() < 3 {
+        println!("Usage: zcash-htlc-cli import-htlcs <file.json|file.csv> [config_file]");
+        return Ok(());
+    }
+
+    let path = &args[2];
+    let client = build_client(args.get(3).map(|s| s.as_str()))?;
+
+    let file = std::fs::File::open(path)?;
+    let count = client
+        .database()
+        .ok_or("exports need a Postgres database")?
+        .import_htlcs(export_format(path), file)?;
+
+    println!("✅ Imported {} new HTLC(s) from {}", count, path);
+    Ok(())
+}
+
+fn search_htlcs(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() < 3 {
+        println!("Usage: zcash-htlc-cli search <txid|htlc_id|p2sh_address prefix> [config_file]");
+        return Ok(());
+    }
+
+    let config = load_config(args.get(3).map(|s| s.as_str()))?;
+    let matches = open_database(&config)?.search_htlcs(&args[2], 20)?;
+    if matches.is_empty() {
+        println!("🔍 No HTLCs match {}", args[2]);
+    }
+
+    for found in matches {
+        let kind = if found.exact { "exact" } else { "prefix" };
+        println!(
+            "🔍 {} ({:?}, {} {:?} match)",
+            found.htlc.id, found.htlc.state, kind, found.field
+        );
+        println!("  P2SH:    {}", found.htlc.p2sh_address);
+        println!(
+            "  Txid:    {}",
+            found.htlc.txid.as_deref().unwrap_or("not funded")
+        );
+        println!("  Amount:  {} ZEC", found.htlc.amount);
+        println!("  Created: {}", found.htlc.created_at);
+    }
+    Ok(())
+}
+
+/// `fees [days] [config_file]`, 30 days by default
+fn fee_summary(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let days = args.get(2).and_then(|arg| arg.parse::<i64>().ok());
+    let config_path = args.get(if days.is_some() { 3 } else { 2 });
+    let config = load_config(config_path.map(|s| s.as_str()))?;
+
+    let until = chrono::Utc::now();
+    let since = until - chrono::Duration::days(days.unwrap_or(30));
+    let summary = open_database(&config)?.get_fee_summary(since, until)?;
+    if summary.is_empty() {
+        println!("💸 No fees recorded since {}", since);
+    }
+
+    for fees in summary {
+        println!(
+            "💸 {}: {} operation(s), {} zat in fees",
+            fees.operation_type.as_str(),
+            fees.operations,
+            fees.total_fee_zat
+        );
+        println!("  Avg size:     {:.0} bytes", fees.avg_tx_size);
+        println!(
+            "  Fee rate:     {:.0} avg, {:.0} median, {}-{} zat/kB",
+            fees.avg_fee_rate, fees.median_fee_rate, fees.min_fee_rate, fees.max_fee_rate
+        );
+    }
+    Ok(())
+}
+
+/// `dead-letters [config_file]`
+fn list_dead_letters(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let config = load_config(args.get(2).map(|s| s.as_str()))?;
+    let dead_letters = open_database(&config)?.get_htlcs_needing_intervention(100)?;
+    if dead_letters.is_empty() {
+        println!("✅ No HTLCs need intervention");
+    }
+
+    for dead_letter in dead_letters {
+        println!(
+            "🚨 {} after {} attempt(s), since {}",
+            dead_letter.htlc.id, dead_letter.creation_attempts, dead_letter.htlc.updated_at
+        );
+        println!("  Amount:     {} ZEC", dead_letter.htlc.amount);
+        println!(
+            "  Last error: {}",
+            dead_letter.last_error.as_deref().unwrap_or("unknown")
+        );
+        if let Some(kind) = dead_letter.last_error_kind {
+            println!("  Error kind: {}", kind.as_str());
+        }
+    }
+    Ok(())
+}
+
+/// `requeue <htlc_id> [config_file]`
+fn requeue_htlc(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() < 3 {
+        println!("Usage: zcash-htlc-cli requeue <htlc_id> [config_file]");
+        return Ok(());
+    }
+
+    let config = load_config(args.get(3).map(|s| s.as_str()))?;
+    open_database(&config)?.requeue_htlc(&args[2])?;
+    println!("🔁 HTLC {} is queued for creation again", args[2]);
+    Ok(())
+}
+
+/// `serve-api <host:port> [config_file]`
+#[cfg(feature = "api")]
+async fn serve_api(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() < 3 {
+        println!("Usage: zcash-htlc-cli serve-api <host:port> [config_file]");
+        return Ok(());
+    }
+
+    let addr = args[2].parse()?;
+    let config = load_config(args.get(3).map(|s| s.as_str()))?;
+    let tokens = config.api_tokens.clone().unwrap_or_default();
+    if tokens.is_empty() {
+        return Err("serve-api needs at least one entry in api_tokens".into());
+    }
+    let database = open_database(&config)?;
+
+    zcash_htlc_builder::api::serve(addr, database, &tokens).await?;
+    Ok(())
+}
+
+fn open_keystore(client: &ZcashHTLCClient) -> Result<Keystore, Box<dyn std::error::Error>> {
+    let dir = client
+        .config()
+        .keystore_dir
+        .as_deref()
+        .unwrap_or(DEFAULT_KEYSTORE_DIR);
+
+    Ok(Keystore::open(dir)?)
+}
+
+/// Keystore passphrase from `ZCASH_KEYSTORE_PASSPHRASE`, else from stdin
+fn read_passphrase() -> Result<String, Box<dyn std::error::Error>> {
+    if let Ok(passphrase) = env::var("ZCASH_KEYSTORE_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+
+    println!("🔑 Keystore passphrase:");
+    read_hidden_line()
+}
+
+/// Read a line from the terminal without echoing it
+#[cfg(unix)]
+fn read_hidden_line() -> Result<String, Box<dyn std::error::Error>> {
+    let fd = libc::STDIN_FILENO;
+    let mut term = std::mem::MaybeUninit::<libc::termios>::uninit();
+    // Not a terminal, e.g. piped input: nothing is echoed anyway
+    if unsafe { libc::tcgetattr(fd, term.as_mut_ptr()) } != 0 {
+        return read_line();
+    }
+    let original = unsafe { term.assume_init() };
+    let mut hidden = original;
+    hidden.c_lflag &= !libc::ECHO;
+    hidden.c_lflag |= libc::ECHONL;
+    if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &hidden) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+
+    let line = read_line();
+    unsafe { libc::tcsetattr(fd, libc::TCSANOW, &original) };
+    line
+}
+
+#[cfg(not(unix))]
+fn read_hidden_line() -> Result<String, Box<dyn std::error::Error>> {
+    Err("cannot hide the passphrase on this platform, set ZCASH_KEYSTORE_PASSPHRASE".into())
+}
+
+fn read_line() -> Result<String, Box<dyn std::error::Error>> {
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn keystore_new(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() < 3 {
+        println!("Usage: zcash-htlc-cli keystore-new <key_id> [config_file]");
+        return Ok(());
+    }
+
+    let id = &args[2];
+    let client = build_client(args.get(3).map(|s| s.as_str()))?;
+    let keystore = open_keystore(&client)?;
+
+    let pubkey = keystore.generate(id, &read_passphrase()?)?;
+
+    println!("🔑 Stored new key:");
+    println!("  Key ID:     keystore:{}", id);
+    println!("  Public Key: {}", pubkey);
+
+    Ok(())
+}
+
+fn keystore_import(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() < 3 {
+        println!("Usage: zcash-htlc-cli keystore-import <key_id> [config_file]");
+        return Ok(());
+    }
+
+    let id = &args[2];
+    let client = build_client(args.get(3).map(|s| s.as_str()))?;
+    let keystore = open_keystore(&client)?;
+
+    println!("🗝️  Private key (hex or WIF):");
+    let privkey = Zeroizing::new(read_hidden_line()?);
+    let privkey = if hex::decode(privkey.as_str()).is_ok() {
+        privkey
+    } else {
+        client.import_wif(&privkey)?
+    };
+    let pubkey = keystore.import(id, &privkey, &read_passphrase()?)?;
+
+    println!("🔑 Imported key:");
+    println!("  Key ID:     keystore:{}", id);
+    println!("  Public Key: {}", pubkey);
+
+    Ok(())
+}
+
+fn keystore_list(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let client = build_client(args.get(2).map(|s| s.as_str()))?;
+    let keystore = open_keystore(&client)?;
+
+    println!("🔑 Keystore keys:");
+    for id in keystore.list()? {
+        println!("  • keystore:{} ({})", id, keystore.pubkey(&id)?);
+    }
+
+    Ok(())
+}
+
+/// `import-wif [--show-private-key] [config_file]`; the hex key is only
+/// printed when asked for, `keystore-import` stores it instead
+fn import_wif(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let show_private_key = args.iter().any(|arg| arg == "--show-private-key");
+    let config_file = args[2..]
+        .iter()
+        .find(|arg| *arg != "--show-private-key")
+        .map(|s| s.as_str());
+    let client = build_client(config_file)?;
+
+    println!("🗝️  WIF private key (zcashd dumpprivkey):");
+    let privkey = client.import_wif(&Zeroizing::new(read_hidden_line()?))?;
+    let pubkey = client.derive_pubkey(&privkey)?;
+
+    println!("🔑 Imported key:");
+    if show_private_key {
+        println!("  Private Key: {}", privkey.as_str());
+    }
+    println!("  Public Key:  {}", pubkey);
+    println!("  Address:     {}", client.pubkey_to_address(&pubkey)?);
+    if !show_private_key {
+        println!();
+        println!("Store the key with keystore-import, or pass --show-private-key to print it");
+    }
+
+    Ok(())
+}
+
+fn export_wif(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let client = build_client(args.get(2).map(|s| s.as_str()))?;
+
+    println!("🗝️  Private key (hex):");
+    let privkey = Zeroizing::new(read_hidden_line()?);
+    let wif = client.export_wif(&privkey)?;
+    let pubkey = client.derive_pubkey(&privkey)?;
+
+    println!("🔑 Exported key (zcashd importprivkey):");
+    println!("  WIF:     {}", wif.as_str());
+    println!("  Address: {}", client.pubkey_to_address(&pubkey)?);
+
+    Ok(())
+}
+
+/// `db status|migrate|revert [steps] [--dry-run] [config_file]`
+fn manage_migrations(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let usage =
+        "Usage: zcash-htlc-cli db <status|migrate|revert [steps]> [--dry-run] [config_file]";
+    let Some(action) = args.get(2) else {
+        println!("{}", usage);
+        return Ok(());
+    };
+
+    let dry_run = args.iter().any(|arg| arg == "--dry-run");
+    let mut rest = args[3..]
+        .iter()
+        .filter(|arg| *arg != "--dry-run")
+        .peekable();
+    let steps = match (action.as_str(), rest.peek().map(|arg| arg.parse::<usize>())) {
+        ("revert", Some(Ok(steps))) => {
+            rest.next();
+            steps
+        }
+        _ => 1,
+    };
+    let config = load_config(rest.next().map(|s| s.as_str()))?;
+    let database = open_database(&config)?;
+    let (apply, revert) = match dry_run {
+        true => ("Would apply", "Would revert"),
+        false => ("Applied", "Reverted"),
+    };
+
+    match action.as_str() {
+        "status" => {
+            for migration in database.migration_status()? {
+                let mark = if migration.applied { "✅" } else { "⏳" };
+                println!("{} {}", mark, migration.name);
+            }
+        }
+        "migrate" => {
+            let applied = database.apply_migrations(dry_run)?;
+            if applied.is_empty() {
+                println!("✅ No pending migrations");
+            }
+            for name in applied {
+                println!("⬆️ {} {}", apply, name);
+            }
+        }
+        "revert" => {
+            for name in database.revert_migrations(steps, dry_run)? {
+                println!("⬇️ {} {}", revert, name);
+            }
+        }
+        _ => println!("{}", usage),
+    }
+
+    Ok(())
+}
+
 fn print_usage() {
     println!("Zcash HTLC Builder CLI");
     println!();
     println!("Usage: zcash-htlc-cli <command> [args...] [config_file]");
     println!();
     println!("Commands:");
-    println!("  create [config_file]                           - Create a new HTLC");
-    println!("  redeem <htlc_id> <secret> <addr> <key> [cfg]  - Redeem an HTLC");
+    println!(
+        "  create [config_file]                           - Create a new HTLC with a random secret"
+    );
+    println!("  redeem <htlc_id> <secret_hex> <addr> <key> [cfg] - Redeem an HTLC");
     println!("  refund <htlc_id> <addr> <key> [cfg]           - Refund an HTLC");
     println!("  balance <address> [config_file]                - Check balance");
     println!("  utxos <address> [config_file]                  - List UTXOs");
     println!("  keygen [config_file]                           - Generate keypair");
-    println!("  hashlock <secret> [config_file]                - Generate hash lock");
+    println!("  hashlock <secret_hex> [config_file]            - Hash lock of a hex secret");
+    println!("  sign-message <key> <message> [cfg]             - Sign a message");
+    println!("  verify-message <addr> <sig> <message> [cfg]    - Verify a signed message");
+    println!("  keystore-new <key_id> [config_file]            - Generate an encrypted key");
+    println!("  keystore-import <key_id> [config_file]         - Encrypt a key read from stdin");
+    println!("  keystore-list [config_file]                    - List keystore keys");
+    println!(
+        "  import-wif [--show-private-key] [cfg]          - Convert a WIF key from stdin to hex"
+    );
+    println!(
+        "  export-wif [config_file]                       - Convert a hex key from stdin to WIF"
+    );
+    println!("  export-htlcs <file> [config_file]              - Export HTLCs to JSON or CSV");
+    println!("  import-htlcs <file> [config_file]              - Import HTLCs from an export");
+    println!(
+        "  db status [config_file]                        - List applied and pending migrations"
+    );
+    println!("  db migrate [--dry-run] [config_file]           - Apply pending migrations");
+    println!("  db revert [steps] [--dry-run] [config_file]    - Revert the last migrations");
+    println!(
+        "  search <prefix> [config_file]                  - Find HTLCs by id, txid or address"
+    );
+    println!(
+        "  fees [days] [config_file]                      - Summarize fees paid (default 30 days)"
+    );
+    println!("  dead-letters [config_file]                     - List HTLCs needing intervention");
+    println!(
+        "  requeue <htlc_id> [config_file]                - Retry creating an HTLC needing intervention"
+    );
+    println!("  serve-api <host:port> [config_file]            - Serve the search API");
+    println!("                                                   (requires --features api)");
+    println!();
+    println!("Keys:");
+    println!("  <key> is a hex private key, keystore:<id> for an encrypted keystore key");
+    println!("  (passphrase from ZCASH_KEYSTORE_PASSPHRASE or stdin), or ledger:<path>");
+    println!("  (e.g. ledger:m/44'/133'/0'/0/0) to sign on a Ledger device");
+    println!("  (requires --features ledger-hid)");
     println!();
     println!("Config file:");
     println!("  Use zcash-config.toml or zcash-config.json by default");
@@ -1,102 +1,907 @@
-use std::sync::Arc;
-use tokio::time::{interval, Duration};
-use tracing::{error, info};
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use bitcoin::blockdata::script::Script;
+use futures_util::StreamExt;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::env;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::SystemTime;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+use tokio::time::{interval, Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
 use zcash_htlc_builder::{
-    database::Database, HTLCParams, HTLCState, ZcashConfig, ZcashHTLCClient, UTXO,
+    database::{
+        Database, DatabaseError, DatabaseHealth, HTLCNotification, Job, JobType,
+        PENDING_HTLC_CHANNEL, SIGNED_REDEEM_CHANNEL,
+    },
+    hd::{HDError, HDWallet},
+    keystore::DEFAULT_KEYSTORE_DIR,
+    operation_retry_delay, zatoshis_to_zec, zec_to_zatoshis, CircuitBreakerConfig,
+    ConsolidationConfig, Cosigner, EventSink, FailureKind, FeeBumpConfig, HTLCClientError,
+    HTLCOperation, HTLCOperationType, HTLCParams, HTLCScriptBuilder, HTLCState, HotWalletSelection,
+    KeyCache, KeyMap, Keystore, KeystoreSigner, LocalSigner, NodeEvent, OperationStatus,
+    OutboxDispatcher, RelayerConfig, RemoteSigner, SignerError, TenantContext,
+    ThresholdCoordinator, TimelockKind, TxBuilderError, UtxoReservation, WalletThresholdConfig,
+    WebhookConfig, WebhookSink, ZcashConfig, ZcashHTLC, ZcashHTLCClient, UTXO,
 };
+use zeroize::Zeroizing;
 
-struct AutomatedRelayer {
-    client: ZcashHTLCClient,
-    database: Arc<Database>,
-    hot_wallet_privkey: String,
-    hot_wallet_address: String,
+/// How often settled HTLCs are moved to the archive, and how many per query
+const ARCHIVE_INTERVAL: Duration = Duration::from_secs(3600);
+const ARCHIVE_BATCH: u32 = 500;
+
+/// Days outbox events are kept without `event_retention_days`
+const DEFAULT_EVENT_RETENTION_DAYS: u64 = 30;
+
+/// How long hot wallet UTXOs stay reserved for a transaction that neither
+/// commits nor releases them, e.g. after a crash
+const UTXO_RESERVATION_TTL: chrono::Duration = chrono::Duration::minutes(10);
+
+/// Consolidations are not urgent, so fees are judged by the rate for
+/// confirmation within this many blocks
+const CONSOLIDATION_CONF_TARGET: u32 = 25;
+const DEFAULT_CONSOLIDATION_INPUTS: usize = 50;
+const DEFAULT_CONSOLIDATION_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// How long a claimed job stays hidden from other workers; one left
+/// unfinished by a crash is picked up again after it. Claims are renewed
+/// while work on them goes on.
+const JOB_VISIBILITY_TIMEOUT: Duration = Duration::from_secs(600);
+/// Longest a refund job not due yet waits before its HTLC is looked at
+/// again, as block times vary
+const REFUND_RECHECK_SECS: u64 = 3600;
+
+/// Blocks past the timelock before an HTLC is refunded, unless
+/// `refund_margin_blocks` says otherwise
+const DEFAULT_REFUND_MARGIN_BLOCKS: u64 = 2;
+/// Zcash's target block spacing, for applying the refund margin to
+/// timestamp timelocks
+const BLOCK_SPACING_SECS: u64 = 75;
+/// Lease replicas of one tenant compete for when `leader_lease_secs` is set
+const LEADER_LEASE: &str = "relayer";
+
+/// What `/healthz` and `/readyz` report, updated by the batch loop
+struct RelayerStatus {
+    /// Last time the loop came round, whether or not it ran a batch
+    last_cycle_at: chrono::DateTime<chrono::Utc>,
+    last_batch_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Node sync state as of the last check, `None` if the node was
+    /// unreachable
+    node: Option<NodeSync>,
+    /// Whether this replica holds the leader lease, `None` without leader
+    /// election
+    leader: Option<bool>,
+    /// Hot wallet thresholds breached as of the last UTXO sync; new HTLCs
+    /// are not funded meanwhile
+    wallet_shortfall: Option<String>,
+    /// Whether batches are paused by the circuit breaker
+    circuit_open: bool,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+struct NodeSync {
+    synced: bool,
+    blocks: u64,
+    target_height: u64,
+}
+
+/// Hot wallet key: inline hex, or a `keystore:<id>` decrypted on demand
+enum HotKey {
+    Inline(Zeroizing<String>),
+    Keystore {
+        signer: Arc<KeystoreSigner>,
+        id: String,
+    },
+}
+
+impl HotKey {
+    fn load(
+        privkey: String,
+        config: &ZcashConfig,
+        key_cache: &Option<Arc<KeyCache>>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let Some(id) = privkey.strip_prefix("keystore:") else {
+            return Ok(HotKey::Inline(Zeroizing::new(privkey)));
+        };
+
+        let keystore = Keystore::open(
+            config
+                .keystore_dir
+                .as_deref()
+                .unwrap_or(DEFAULT_KEYSTORE_DIR),
+        )?;
+        let passphrase = env::var("ZCASH_KEYSTORE_PASSPHRASE")
+            .map_err(|_| "ZCASH_KEYSTORE_PASSPHRASE is required for a keystore hot key")?;
+        let signer = KeystoreSigner::new(keystore, passphrase);
+        let signer = match key_cache {
+            Some(cache) => {
+                info!("🔐 Caching hot key {} for {:?}", id, cache.ttl());
+                signer.with_cache(cache.clone())
+            }
+            None => signer,
+        };
+        Ok(HotKey::Keystore {
+            signer: Arc::new(signer),
+            id: id.to_string(),
+        })
+    }
+
+    fn privkey(&self) -> Result<Zeroizing<String>, SignerError> {
+        match self {
+            HotKey::Inline(privkey) => Ok(privkey.clone()),
+            HotKey::Keystore { signer, id } => signer.unlock(id),
+        }
+    }
+
+    fn cosigner(&self) -> Cosigner {
+        match self {
+            HotKey::Inline(privkey) => Cosigner::new("hot", Arc::new(LocalSigner::new()), privkey),
+            HotKey::Keystore { signer, id } => Cosigner::new("hot", signer.clone(), id),
+        }
+    }
+}
+
+struct HotWallet {
+    address: String,
+    key: HotKey,
+    /// Public key of `key`, derived once at startup so that finding the
+    /// wallet an HTLC refunds to does not unlock every key
+    pubkey: String,
+}
+
+impl HotWallet {
+    /// The wallet's key, for signing inputs spending its UTXOs
+    fn keys(&self) -> Result<KeyMap, SignerError> {
+        Ok(KeyMap::new().with_key(&self.address, &self.key.privkey()?))
+    }
+}
+
+/// This replica's claim on [`LEADER_LEASE`]
+struct LeaderLease {
+    holder: String,
+    ttl: Duration,
+}
+
+/// Broadcasts of the last rolling minute, so a backlog drained after
+/// downtime is spread over several batches instead of flooding the mempool
+/// and the node's or explorer's rate limits
+#[derive(Default)]
+struct BroadcastLimiter {
+    sent: Mutex<VecDeque<Instant>>,
+}
+
+impl BroadcastLimiter {
+    /// Take a broadcast slot, or `false` if the last minute used all
+    /// `max_per_minute` of them
+    fn try_acquire(&self, max_per_minute: u32) -> bool {
+        self.try_acquire_at(max_per_minute, Instant::now())
+    }
+
+    fn try_acquire_at(&self, max_per_minute: u32, now: Instant) -> bool {
+        let mut sent = self.sent.lock().unwrap();
+        while sent
+            .front()
+            .is_some_and(|at| now.duration_since(*at) >= Duration::from_secs(60))
+        {
+            sent.pop_front();
+        }
+        if sent.len() >= max_per_minute as usize {
+            return false;
+        }
+        sent.push_back(now);
+        true
+    }
+}
+
+/// Node failures in a row, and while the circuit is open, when the node
+/// may be probed again
+#[derive(Default)]
+struct CircuitBreaker {
+    failures: AtomicU32,
+    open_until: Mutex<Option<Instant>>,
+}
+
+impl CircuitBreaker {
+    /// Count a failure of `kind`, opening the circuit for `cooldown` at
+    /// `threshold`. Only an unreachable or misbehaving node counts; a
+    /// rejected transaction shows the node is answering. Returns the
+    /// failures in a row if this one opened it.
+    fn record_failure(&self, kind: FailureKind, threshold: u32, cooldown: Duration) -> Option<u32> {
+        if kind != FailureKind::Node {
+            return None;
+        }
+        let failures = self.failures.fetch_add(1, Ordering::SeqCst) + 1;
+        let mut open_until = self.open_until.lock().unwrap();
+        if failures < threshold || open_until.is_some() {
+            return None;
+        }
+        *open_until = Some(Instant::now() + cooldown);
+        Some(failures)
+    }
+
+    /// Start counting afresh once the node answered, so failures hours
+    /// apart on a quiet relayer are not taken for an outage
+    fn record_success(&self) {
+        self.failures.store(0, Ordering::SeqCst);
+    }
+
+    fn open_until(&self) -> Option<Instant> {
+        *self.open_until.lock().unwrap()
+    }
+
+    fn close(&self) {
+        *self.open_until.lock().unwrap() = None;
+        self.failures.store(0, Ordering::SeqCst);
+    }
+}
+
+/// Keyed digests of the settings that only take effect on a restart, by
+/// their path in the config file. Many hold credentials, so only digests
+/// under a key of this process are kept, never the values themselves.
+struct RestartSettings {
+    key: Zeroizing<[u8; 32]>,
+    digests: BTreeMap<&'static str, [u8; 32]>,
+}
+
+impl RestartSettings {
+    fn new(config: &ZcashConfig) -> Self {
+        let key = Zeroizing::new(rand::random::<[u8; 32]>());
+        let digests = restart_setting_digests(&key, config);
+        Self { key, digests }
+    }
+
+    /// Paths of the restart-only settings `config` changes
+    fn changed(&self, config: &ZcashConfig) -> Vec<&'static str> {
+        restart_setting_digests(&self.key, config)
+            .into_iter()
+            .filter(|(name, digest)| self.digests.get(name) != Some(digest))
+            .map(|(name, _)| name)
+            .collect()
+    }
+}
+
+/// Digest of every setting [`AutomatedRelayer::reload_config`] does not
+/// apply. `fee_mode` and the relayer's tunables are left out.
+fn restart_setting_digests(
+    key: &[u8; 32],
+    config: &ZcashConfig,
+) -> BTreeMap<&'static str, [u8; 32]> {
+    macro_rules! digests {
+        ($($name:literal => $value:expr),* $(,)?) => {
+            BTreeMap::from([$(($name, setting_digest(key, &$value))),*])
+        };
+    }
+    let relayer = config.relayer.as_ref();
+    digests! {
+        "network" => config.network,
+        "rpc_url" => config.rpc_url,
+        "rpc_fallback_urls" => config.rpc_fallback_urls,
+        "rpc_retry" => config.rpc_retry,
+        "rpc_request_timeout_secs" => config.rpc_request_timeout_secs,
+        "rpc_call_timeout_secs" => config.rpc_call_timeout_secs,
+        "rpc_tls" => config.rpc_tls,
+        "rpc_tx_cache_size" => config.rpc_tx_cache_size,
+        "rpc_proxy" => config.rpc_proxy,
+        "rpc_http_pool" => config.rpc_http_pool,
+        "zmq_endpoint" => config.zmq_endpoint,
+        "rpc_user" => config.rpc_user,
+        "rpc_password" => config.rpc_password,
+        "rpc_cookie_file" => config.rpc_cookie_file,
+        "explorer_api" => config.explorer_api,
+        "explorer" => config.explorer,
+        "explorer_fallbacks" => config.explorer_fallbacks,
+        "dual_broadcast" => config.dual_broadcast,
+        "consensus_branch_id" => config.consensus_branch_id,
+        "tx_expiry_delta" => config.tx_expiry_delta,
+        "database_url" => config.database_url,
+        "database_max_connections" => config.database_max_connections,
+        "database_encryption_key" => config.database_encryption_key,
+        "database_replica_url" => config.database_replica_url,
+        "database_max_replication_lag_secs" => config.database_max_replication_lag_secs,
+        "remote_signer" => config.remote_signer,
+        "keystore_dir" => config.keystore_dir,
+        "api_tokens" => config.api_tokens,
+        "relayer.hot_wallet_privkey" => relayer.map(|r| &r.hot_wallet_privkey),
+        "relayer.hot_wallet_address" => relayer.map(|r| &r.hot_wallet_address),
+        "relayer.min_confirmations" => relayer.map(|r| r.min_confirmations),
+        "relayer.hd_mnemonic" => relayer.map(|r| &r.hd_mnemonic),
+        "relayer.hd_derivation_path" => relayer.map(|r| &r.hd_derivation_path),
+        "relayer.threshold_signing" => relayer.map(|r| &r.threshold_signing),
+        "relayer.key_cache_ttl_secs" => relayer.map(|r| r.key_cache_ttl_secs),
+        "relayer.archive_after_days" => relayer.map(|r| r.archive_after_days),
+        "relayer.event_retention_days" => relayer.map(|r| r.event_retention_days),
+        "relayer.event_webhook_url" => relayer.map(|r| &r.event_webhook_url),
+        "relayer.event_webhook_secret" => relayer.map(|r| &r.event_webhook_secret),
+        "relayer.event_webhooks" => relayer.map(|r| &r.event_webhooks),
+        "relayer.tenant_id" => relayer.map(|r| &r.tenant_id),
+        "relayer.readiness_addr" => relayer.map(|r| &r.readiness_addr),
+        "relayer.stuck_after_secs" => relayer.map(|r| r.stuck_after_secs),
+        "relayer.leader_lease_secs" => relayer.map(|r| r.leader_lease_secs),
+        "relayer.watchtower" => relayer.map(|r| r.watchtower),
+        "relayer.hot_wallets" => relayer.map(|r| &r.hot_wallets),
+        "relayer.hot_wallet_selection" => relayer.map(|r| &r.hot_wallet_selection),
+    }
+}
+
+/// HMAC-SHA256 of a setting's JSON form, serialized straight into the MAC
+/// so no copy of the value is left behind
+fn setting_digest<T: serde::Serialize>(key: &[u8; 32], value: &T) -> [u8; 32] {
+    struct MacWriter<'a>(&'a mut Hmac<Sha256>);
+
+    impl std::io::Write for MacWriter<'_> {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.update(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    // Writing into the MAC cannot fail, nor can serializing a parsed config
+    let _ = serde_json::to_writer(MacWriter(&mut mac), value);
+    mac.finalize().into_bytes().into()
+}
+
+/// Settings applied again from the config file when it changes or on
+/// SIGHUP, between batches; the others take a restart
+#[derive(Clone)]
+struct Tunables {
     max_tx_per_batch: u32,
+    max_retry_attempts: u32,
     poll_interval: Duration,
     network_fee: String,
+    consolidation: Option<ConsolidationConfig>,
+    fee_bump: Option<FeeBumpConfig>,
+    wallet_thresholds: Option<WalletThresholdConfig>,
+    max_broadcasts_per_minute: Option<u32>,
+    refund_margin_blocks: u64,
+    circuit_breaker: Option<CircuitBreakerConfig>,
+}
+
+impl Tunables {
+    fn from_config(config: &RelayerConfig, threshold_signing: bool) -> Result<Self, String> {
+        if config.poll_interval_secs == 0 {
+            return Err("poll_interval_secs must be at least 1".to_string());
+        }
+        if config.max_broadcasts_per_minute == Some(0) {
+            return Err("max_broadcasts_per_minute must be at least 1".to_string());
+        }
+        if let Some(breaker) = &config.circuit_breaker {
+            if breaker.failure_threshold == 0 {
+                return Err("circuit_breaker.failure_threshold must be at least 1".to_string());
+            }
+        }
+
+        // Threshold wallet outputs need every cosigner for a child
+        let fee_bump = match (&config.fee_bump, threshold_signing) {
+            (Some(_), true) => {
+                warn!("⚠️ fee_bump is not supported with threshold_signing, ignoring it");
+                None
+            }
+            (fee_bump, _) => fee_bump.clone(),
+        };
+
+        Ok(Self {
+            max_tx_per_batch: config.max_tx_per_batch,
+            max_retry_attempts: config.max_retry_attempts,
+            poll_interval: Duration::from_secs(config.poll_interval_secs),
+            network_fee: config.network_fee_zec.clone(),
+            consolidation: config.consolidation.clone(),
+            fee_bump,
+            wallet_thresholds: config.wallet_thresholds.clone(),
+            max_broadcasts_per_minute: config.max_broadcasts_per_minute,
+            refund_margin_blocks: config
+                .refund_margin_blocks
+                .unwrap_or(DEFAULT_REFUND_MARGIN_BLOCKS),
+            circuit_breaker: config.circuit_breaker.clone(),
+        })
+    }
+}
+
+/// Failure of a relayer batch, by the stage it failed at. Startup errors
+/// stay boxed, as the relayer exits on them anyway.
+#[derive(Debug, thiserror::Error)]
+enum RelayerError {
+    #[error("Funding failed: {0}")]
+    Funding(String),
+
+    #[error("Signing failed: {0}")]
+    Signing(String),
+
+    #[error("Broadcast failed: {0}")]
+    Broadcast(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] DatabaseError),
+
+    #[error("Node error: {0}")]
+    Node(String),
+
+    #[error("{0}")]
+    Other(String),
+}
+
+impl RelayerError {
+    fn kind(&self) -> FailureKind {
+        match self {
+            RelayerError::Funding(_) => FailureKind::Funding,
+            RelayerError::Signing(_) => FailureKind::Signing,
+            RelayerError::Broadcast(_) => FailureKind::Broadcast,
+            RelayerError::Database(_) => FailureKind::Database,
+            RelayerError::Node(_) => FailureKind::Node,
+            RelayerError::Other(_) => FailureKind::Other,
+        }
+    }
+}
+
+impl From<HTLCClientError> for RelayerError {
+    fn from(error: HTLCClientError) -> Self {
+        let kind = error.failure_kind();
+        if let HTLCClientError::DatabaseError(error) = error {
+            return RelayerError::Database(error);
+        }
+        let message = error.to_string();
+        match kind {
+            FailureKind::Funding => RelayerError::Funding(message),
+            FailureKind::Signing => RelayerError::Signing(message),
+            FailureKind::Broadcast => RelayerError::Broadcast(message),
+            FailureKind::Node => RelayerError::Node(message),
+            FailureKind::Database | FailureKind::Other => RelayerError::Other(message),
+        }
+    }
+}
+
+impl From<SignerError> for RelayerError {
+    fn from(error: SignerError) -> Self {
+        RelayerError::Signing(error.to_string())
+    }
+}
+
+impl From<HDError> for RelayerError {
+    fn from(error: HDError) -> Self {
+        RelayerError::Signing(error.to_string())
+    }
+}
+
+struct AutomatedRelayer {
+    client: ZcashHTLCClient,
+    database: Arc<Database>,
+    key_cache: Option<Arc<KeyCache>>,
+    /// `hot_wallet_address` first, then `hot_wallets`
+    hot_wallets: Vec<HotWallet>,
+    wallet_selection: HotWalletSelection,
+    /// HTLCs funded so far, for round-robin selection
+    wallet_turn: AtomicUsize,
+    hd_wallet: Option<HDWallet>,
+    threshold_wallet: Option<(Script, ThresholdCoordinator)>,
+    tunables: RwLock<Tunables>,
+    /// Config file the tunables are reloaded from, and its modification
+    /// time when last read
+    config_path: &'static str,
+    config_modified: Mutex<Option<SystemTime>>,
+    /// Settings the relayer started with that a reload does not apply
+    restart_settings: RestartSettings,
+    min_confirmations: u32,
+    archive_after: Option<chrono::Duration>,
+    event_retention: chrono::Duration,
+    watchtower: bool,
+    /// Claims jobs and holds the leader lease as this replica
+    worker_id: String,
+    leader_lease: Option<LeaderLease>,
+    broadcast_limiter: BroadcastLimiter,
+    circuit: CircuitBreaker,
+    outbox: Vec<JoinHandle<()>>,
+    readiness: Option<JoinHandle<()>>,
+    status: Arc<Mutex<RelayerStatus>>,
+    /// Cancelled on SIGINT or SIGTERM: no new work is started, but HTLCs
+    /// already being processed are finished
+    stopping: CancellationToken,
+    /// Reservations whose transaction has not been built yet, released on
+    /// shutdown should one be left behind
+    reservations: Mutex<HashSet<String>>,
 }
 
 impl AutomatedRelayer {
-    async fn new(config: ZcashConfig) -> Result<Self, Box<dyn std::error::Error>> {
+    async fn new(
+        mut config: ZcashConfig,
+        config_path: &'static str,
+        stopping: CancellationToken,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let restart_settings = RestartSettings::new(&config);
         let relayer_config = config
             .relayer
-            .clone()
+            .take()
             .ok_or("Relayer config missing in zcash-config.toml")?;
+        let tunables =
+            Tunables::from_config(&relayer_config, relayer_config.threshold_signing.is_some())?;
+        let config_modified = config_modified_at(config_path);
+
+        let mut database = Database::new(&config.database_url, config.database_max_connections)?
+            .with_actor("relayer");
+        if let Some(cipher) = config.column_cipher()? {
+            info!("🔐 Encrypting HTLC secrets at rest");
+            database = database.with_column_cipher(cipher);
+        }
+        // The relayer acts on what it reads, so it never reads from
+        // database_replica_url, which may lag behind its own writes
+        if let Some(secs) = config.database_max_replication_lag_secs {
+            database = database.with_max_replication_lag(Duration::from_secs(secs));
+        }
+        if let Some(tenant_id) = &relayer_config.tenant_id {
+            info!("🏢 Relaying for tenant {}", tenant_id);
+            database = database.with_tenant(TenantContext::new(tenant_id));
+        }
+        let database = Arc::new(database);
+
+        let hd_wallet = match relayer_config.hd_mnemonic.map(Zeroizing::new) {
+            Some(phrase) => {
+                let wallet = HDWallet::from_mnemonic(&phrase, "", config.network)?;
+                let wallet = match &relayer_config.hd_derivation_path {
+                    Some(path) => wallet.with_base_path(path)?,
+                    None => wallet,
+                };
+                Some(wallet)
+            }
+            None => None,
+        };
+
+        let key_cache = relayer_config
+            .key_cache_ttl_secs
+            .map(|ttl| Arc::new(KeyCache::new(Duration::from_secs(ttl))));
+
+        let hot_key = HotKey::load(relayer_config.hot_wallet_privkey, &config, &key_cache)?;
+
+        let threshold_wallet = match relayer_config.threshold_signing {
+            Some(_) if !relayer_config.hot_wallets.is_empty() => {
+                return Err("hot_wallets cannot be combined with threshold_signing".into());
+            }
+            Some(threshold) => {
+                let script_builder = HTLCScriptBuilder::new(config.network);
+                let wallet_script = script_builder
+                    .build_multisig_script(threshold.threshold, &threshold.pubkeys)?;
+
+                let wallet_address = script_builder.script_to_p2sh_address(&wallet_script)?;
+                if wallet_address != relayer_config.hot_wallet_address {
+                    return Err(format!(
+                        "hot_wallet_address does not match the {}-of-{} wallet {}",
+                        threshold.threshold,
+                        threshold.pubkeys.len(),
+                        wallet_address
+                    )
+                    .into());
+                }
+
+                let coordinator =
+                    ThresholdCoordinator::new(hot_key.cosigner()).with_standby(Cosigner::new(
+                        "standby",
+                        Arc::new(RemoteSigner::from_config(&threshold.standby_signer)?),
+                        &threshold.standby_key_id,
+                    ));
+
+                info!(
+                    "🔐 Funding from {}-of-{} threshold wallet {}",
+                    threshold.threshold,
+                    threshold.pubkeys.len(),
+                    wallet_address
+                );
+                Some((wallet_script, coordinator))
+            }
+            None => None,
+        };
+
+        let legacy_webhook = relayer_config.event_webhook_url.map(|url| WebhookConfig {
+            url,
+            secret: relayer_config.event_webhook_secret.clone(),
+            auth_token: None,
+            name: None,
+        });
+        let mut webhooks = Vec::new();
+        for webhook in legacy_webhook.iter().chain(&relayer_config.event_webhooks) {
+            let mut sink = WebhookSink::new(&webhook.url);
+            // Receivers must be able to tell events from forgeries
+            let Some(secret) = &webhook.secret else {
+                return Err(format!(
+                    "webhook {} has no secret to sign events with",
+                    sink.endpoint()
+                )
+                .into());
+            };
+            sink = sink.with_secret(secret);
+            if let Some(token) = &webhook.auth_token {
+                sink = sink.with_auth_token(token);
+            }
+            if let Some(name) = &webhook.name {
+                sink = sink.with_name(name);
+            }
+            webhooks.push(sink);
+        }
+        let outbox = webhooks
+            .into_iter()
+            .map(|sink| {
+                info!(
+                    "📬 Publishing HTLC events to {} as {}",
+                    sink.endpoint(),
+                    sink.name()
+                );
+                OutboxDispatcher::new(database.clone(), Arc::new(sink)).spawn()
+            })
+            .collect();
+
+        let status = Arc::new(Mutex::new(RelayerStatus {
+            last_cycle_at: chrono::Utc::now(),
+            last_batch_at: None,
+            node: None,
+            leader: None,
+            wallet_shortfall: None,
+            circuit_open: false,
+        }));
+        let stuck_after = chrono::Duration::seconds(
+            relayer_config
+                .stuck_after_secs
+                .unwrap_or(relayer_config.poll_interval_secs * 10) as i64,
+        );
+        let readiness = match &relayer_config.readiness_addr {
+            Some(addr) => {
+                Some(serve_health(addr, database.clone(), status.clone(), stuck_after).await?)
+            }
+            None => None,
+        };
+
+        let host = env::var("HOSTNAME").unwrap_or_else(|_| "relayer".to_string());
+        let worker_id = format!("{}-{}", host, uuid::Uuid::new_v4());
+        let leader_lease = relayer_config.leader_lease_secs.map(|secs| {
+            let ttl = Duration::from_secs(secs);
+            if ttl <= Duration::from_secs(relayer_config.poll_interval_secs * 2) {
+                warn!(
+                    "⚠️  leader_lease_secs should be well above poll_interval_secs, \
+                     or leadership will change hands between batches"
+                );
+            }
+            info!("🗳️  Leader election enabled as {}", worker_id);
+            LeaderLease {
+                holder: worker_id.clone(),
+                ttl,
+            }
+        });
 
-        let database = Arc::new(Database::new(
-            &config.database_url,
-            config.database_max_connections,
-        )?);
+        let mut hot_keys = vec![(relayer_config.hot_wallet_address, hot_key)];
+        for wallet in relayer_config.hot_wallets {
+            hot_keys.push((
+                wallet.address,
+                HotKey::load(wallet.privkey, &config, &key_cache)?,
+            ));
+        }
 
-        let client = ZcashHTLCClient::new(config, database.clone());
+        let block_events = block_notifications(&config);
+        let client = ZcashHTLCClient::new(config, database.clone())?;
+        let client = match &block_events {
+            Some(events) => client.with_block_notifications(events.clone()),
+            None => client,
+        };
+        let client = match relayer_config.fee_priorities {
+            Some(rates) => client.with_fee_priorities(rates),
+            None => client,
+        };
+        let hot_wallets = hot_keys
+            .into_iter()
+            .map(|(address, key)| {
+                let pubkey = client.derive_pubkey(&key.privkey()?)?;
+                Ok(HotWallet {
+                    address,
+                    key,
+                    pubkey,
+                })
+            })
+            .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
 
         Ok(Self {
             client,
             database,
-            hot_wallet_privkey: relayer_config.hot_wallet_privkey,
-            hot_wallet_address: relayer_config.hot_wallet_address,
-            max_tx_per_batch: relayer_config.max_tx_per_batch,
-            poll_interval: Duration::from_secs(relayer_config.poll_interval_secs),
-            network_fee: relayer_config.network_fee_zec,
+            key_cache,
+            hot_wallets,
+            wallet_selection: relayer_config.hot_wallet_selection,
+            wallet_turn: AtomicUsize::new(0),
+            hd_wallet,
+            threshold_wallet,
+            tunables: RwLock::new(tunables),
+            config_path,
+            config_modified: Mutex::new(config_modified),
+            restart_settings,
+            min_confirmations: relayer_config.min_confirmations,
+            archive_after: relayer_config
+                .archive_after_days
+                .map(|days| chrono::Duration::days(days as i64)),
+            event_retention: chrono::Duration::days(
+                relayer_config
+                    .event_retention_days
+                    .unwrap_or(DEFAULT_EVENT_RETENTION_DAYS) as i64,
+            ),
+            watchtower: relayer_config.watchtower,
+            worker_id,
+            leader_lease,
+            broadcast_limiter: BroadcastLimiter::default(),
+            circuit: CircuitBreaker::default(),
+            outbox,
+            readiness,
+            status,
+            stopping,
+            reservations: Mutex::new(HashSet::new()),
         })
     }
 
-    async fn process_pending_htlc_creations(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let pending = self
-            .database
-            .get_pending_htlcs_for_creation(self.max_tx_per_batch)?;
-
-        for htlc in pending {
-            info!("🔨 Processing HTLC creation: {}", htlc.id);
-
-            let funding_utxos = self.get_relayer_utxos().await?;
+    /// Fund the HTLCs of claimed `fund_htlc` jobs
+    async fn process_pending_htlc_creations(&self) -> Result<(), RelayerError> {
+        let jobs = self.claim_jobs(JobType::FundHtlc).await?;
+        if jobs.is_empty() {
+            return Ok(());
+        }
+        if let Some(shortfall) = &self.status.lock().unwrap().wallet_shortfall {
+            warn!(
+                "⏸️  Not funding {} pending HTLC(s) until the hot wallet is topped up: {}",
+                jobs.len(),
+                shortfall
+            );
+            return Ok(());
+        }
 
-            if funding_utxos.is_empty() {
-                error!("❌ No UTXOs available in hot wallet!");
+        for job in jobs {
+            if self.halted() {
+                break;
+            }
+            let Some(htlc) = self.job_htlc(&job).await? else {
+                continue;
+            };
+            // Funded by its creator after all
+            if htlc.state != HTLCState::Pending || htlc.txid.is_some() {
+                self.complete_job(&job).await;
                 continue;
             }
+            info!("🔨 Processing HTLC creation: {}", htlc.id);
 
-            let amount: f64 = htlc.amount.parse().unwrap_or(0.0);
-            let fee: f64 = self.network_fee.parse().unwrap_or(0.0001);
+            let amount = zec_to_zatoshis(&htlc.amount).unwrap_or(0);
+            let fee = zec_to_zatoshis(&self.tunables().network_fee).unwrap_or(10_000);
             let required = amount + fee;
 
-            let selected_utxos = self.select_utxos(&funding_utxos, required)?;
+            let refund_pubkey = match self.refund_pubkey_for(&htlc) {
+                Ok(pubkey) => pubkey,
+                Err(e) => {
+                    error!("❌ Cannot pick a refund key for HTLC {}: {}", htlc.id, e);
+                    self.retry_job(&job, &e.to_string()).await;
+                    continue;
+                }
+            };
+
+            let (wallet, reservation) = match self.reserve_funding(required).await {
+                Ok(funding) => funding,
+                Err(e) => {
+                    let e = match e {
+                        DatabaseError::InsufficientUtxos { .. } => {
+                            RelayerError::Funding(e.to_string())
+                        }
+                        e => RelayerError::Database(e),
+                    };
+                    error!("❌ Cannot fund HTLC {} from hot wallet: {}", htlc.id, e);
+                    self.record_failure(e.kind(), &e.to_string()).await;
+                    self.retry_job(&job, &e.to_string()).await;
+                    continue;
+                }
+            };
+            let reservation_id = reservation.id;
+            let selected_utxos: Vec<UTXO> = reservation.utxos.into_iter().map(Into::into).collect();
+            if !self.may_broadcast("HTLC creations").await {
+                self.reservations.lock().unwrap().remove(&reservation_id);
+                let released = self
+                    .database
+                    .run(move |db| db.release_reservation(&reservation_id))
+                    .await;
+                if let Err(e) = released {
+                    error!("Failed to release UTXOs: {}", e);
+                }
+                break;
+            }
 
             let params = HTLCParams {
                 recipient_pubkey: htlc.recipient_pubkey,
-                refund_pubkey: htlc.refund_pubkey,
+                refund_pubkey,
                 hash_lock: htlc.hash_lock,
                 timelock: htlc.timelock,
                 amount: htlc.amount,
+                metadata: htlc.metadata,
+                priority: htlc.priority,
+                htlc_id: Some(htlc.id.clone()),
             };
 
-            match self
-                .client
-                .create_htlc(
-                    params,
-                    selected_utxos.clone(),
-                    &self.hot_wallet_address,
-                    vec![&self.hot_wallet_privkey],
-                )
-                .await
-            {
+            let creation = self.with_heartbeat(&job, async {
+                match &self.threshold_wallet {
+                    Some((wallet_script, coordinator)) => {
+                        self.client
+                            .create_htlc_with_cosigners(
+                                params,
+                                selected_utxos.clone(),
+                                &wallet.address,
+                                wallet_script,
+                                coordinator,
+                            )
+                            .await
+                    }
+                    None => match wallet.keys() {
+                        Ok(keys) => {
+                            self.client
+                                .create_htlc(params, selected_utxos.clone(), &wallet.address, &keys)
+                                .await
+                        }
+                        Err(e) => Err(e.into()),
+                    },
+                }
+            });
+            let creation = creation.await;
+            self.reservations.lock().unwrap().remove(&reservation_id);
+
+            match creation {
                 Ok(result) => {
                     info!(
                         "✅ HTLC created: {} with txid: {}",
                         result.htlc_id, result.txid
                     );
 
-                    for utxo in selected_utxos {
-                        if let Err(e) =
-                            self.database
-                                .mark_utxo_spent(&utxo.txid, utxo.vout, &result.txid)
-                        {
-                            error!("Failed to mark UTXO spent: {}", e);
-                        }
+                    let spending_txid = result.txid;
+                    let committed = self
+                        .database
+                        .run(move |db| db.commit_reservation(&reservation_id, &spending_txid))
+                        .await;
+                    if let Err(e) = committed {
+                        error!("Failed to mark UTXOs spent: {}", e);
                     }
+                    self.circuit.record_success();
+                    self.complete_job(&job).await;
                 }
                 Err(e) => {
+                    let e = RelayerError::from(e);
                     error!("❌ Failed to create HTLC {}: {}", htlc.id, e);
-                    let _ = self.database.update_htlc_state(&htlc.id, HTLCState::Failed);
+                    self.record_failure(e.kind(), &e.to_string()).await;
+                    let released = self
+                        .database
+                        .run(move |db| db.release_reservation(&reservation_id))
+                        .await;
+                    if let Err(e) = released {
+                        error!("Failed to release UTXOs: {}", e);
+                    }
+                    // An HTLC funded after all is no longer Pending and
+                    // its job is done
+                    let (id, error, kind, max_attempts) = (
+                        htlc.id.clone(),
+                        e.to_string(),
+                        e.kind(),
+                        self.tunables().max_retry_attempts,
+                    );
+                    let recorded = self
+                        .database
+                        .run(move |db| db.record_creation_failure(&id, &error, kind, max_attempts))
+                        .await;
+                    match recorded {
+                        Ok(HTLCState::NeedsIntervention) => {
+                            error!(
+                                "🚨 HTLC {} needs intervention after {} failed attempts",
+                                htlc.id, max_attempts
+                            );
+                            self.fail_job(&job, &e.to_string()).await;
+                        }
+                        Ok(HTLCState::Pending) => self.retry_job(&job, &e.to_string()).await,
+                        Ok(_) => self.complete_job(&job).await,
+                        Err(record_error) => {
+                            error!("Failed to record HTLC creation failure: {}", record_error);
+                            self.retry_job(&job, &e.to_string()).await;
+                        }
+                    }
                 }
             }
         }
@@ -104,28 +909,62 @@ impl AutomatedRelayer {
         Ok(())
     }
 
-    async fn process_pending_redemptions(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let pending = self
-            .database
-            .get_htlcs_with_signed_redeem_tx(self.max_tx_per_batch)?;
+    /// Broadcast the pre-signed redeems of claimed `broadcast_redeem` jobs
+    /// once their HTLC is locked
+    async fn process_pending_redemptions(&self) -> Result<(), RelayerError> {
+        let jobs = self.claim_jobs(JobType::BroadcastRedeem).await?;
 
-        for htlc in pending {
-            if let Some(signed_tx) = htlc.signed_redeem_tx {
-                info!(
-                    "🔓 Broadcasting pre-signed redemption for HTLC: {}",
-                    htlc.id
-                );
+        for job in jobs {
+            if self.halted() {
+                break;
+            }
+            let Some(htlc) = self.job_htlc(&job).await? else {
+                continue;
+            };
+            let signed_tx = match (htlc.state, htlc.signed_redeem_tx) {
+                (HTLCState::Locked, Some(signed_tx)) => signed_tx,
+                // Queued again on lock; one queued before is retried
+                (HTLCState::Pending, _) => {
+                    self.retry_job(&job, "funding not confirmed yet").await;
+                    continue;
+                }
+                _ => {
+                    self.complete_job(&job).await;
+                    continue;
+                }
+            };
+            if !self.may_broadcast("redemptions").await {
+                break;
+            }
+            info!(
+                "🔓 Broadcasting pre-signed redemption for HTLC: {}",
+                htlc.id
+            );
 
-                match self.client.broadcast_raw_tx(&signed_tx).await {
-                    Ok(txid) => {
-                        info!("✅ HTLC redeemed: {} with txid: {}", htlc.id, txid);
-                        let _ = self
-                            .database
-                            .update_htlc_state(&htlc.id, HTLCState::Redeemed);
-                    }
-                    Err(e) => {
-                        error!("❌ Failed to broadcast redemption for {}: {}", htlc.id, e);
-                    }
+            match self.client.broadcast_raw_tx(&signed_tx).await {
+                Ok(txid) => {
+                    info!("✅ HTLC redeemed: {} with txid: {}", htlc.id, txid);
+                    let (id, version) = (htlc.id.clone(), htlc.version);
+                    let reason = format!("redeemed in {}", txid);
+                    let updated = self
+                        .database
+                        .run(move |db| {
+                            db.update_htlc_state_if_version(
+                                &id,
+                                HTLCState::Redeemed,
+                                version,
+                                &reason,
+                            )
+                        })
+                        .await;
+                    warn_on_conflict(updated);
+                    self.circuit.record_success();
+                    self.complete_job(&job).await;
+                }
+                Err(e) => {
+                    error!("❌ Failed to broadcast redemption for {}: {}", htlc.id, e);
+                    self.record_failure(e.failure_kind(), &e.to_string()).await;
+                    self.retry_job(&job, &e.to_string()).await;
                 }
             }
         }
@@ -133,23 +972,120 @@ impl AutomatedRelayer {
         Ok(())
     }
 
-    async fn process_expired_htlcs(&self) -> Result<(), Box<dyn std::error::Error>> {
+    /// Refund the HTLCs of claimed `refund_htlc` jobs, queued when they
+    /// lock, once their timelock has passed. Jobs not due yet wait for it.
+    async fn process_expired_htlcs(&self) -> Result<(), RelayerError> {
+        let jobs = self.claim_jobs(JobType::RefundHtlc).await?;
+        if jobs.is_empty() {
+            return Ok(());
+        }
         let current_block = self.client.get_current_block_height().await?;
-        let expired = self.database.get_expired_htlcs(current_block)?;
+        let current_mtp = self.client.get_median_time_past().await?;
+        let margin = self.tunables().refund_margin_blocks;
+
+        for job in jobs {
+            if self.halted() {
+                break;
+            }
+            let Some(htlc) = self.job_htlc(&job).await? else {
+                continue;
+            };
+            if htlc.state != HTLCState::Locked {
+                self.complete_job(&job).await;
+                continue;
+            }
+            let wait = refund_wait_secs(
+                htlc.timelock,
+                htlc.timelock_kind,
+                current_block,
+                current_mtp,
+                margin,
+            );
+            if let Some(wait) = wait {
+                let until = chrono::Utc::now()
+                    + chrono::Duration::seconds(wait.min(REFUND_RECHECK_SECS) as i64);
+                self.defer_job(&job, until).await;
+                continue;
+            }
+            // A refund that failed to broadcast is retried on its own
+            // schedule, and its job waits for it. One given up on, after
+            // too many attempts or because it expired or lost its inputs,
+            // is rebuilt here with a fresh fee and expiry.
+            let id = htlc.id.clone();
+            let operations = self
+                .database
+                .run(move |db| db.get_operations_by_htlc(&id))
+                .await?;
+            let max_attempts = self.tunables().max_retry_attempts;
+            let retrying = operations
+                .iter()
+                .filter(|op| {
+                    matches!(op.operation_type, HTLCOperationType::Refund)
+                        && op.status == OperationStatus::Failed
+                        && op.attempt_count < max_attempts
+                })
+                .find_map(|op| op.next_retry_at);
+            if let Some(next_retry_at) = retrying {
+                self.defer_job(&job, next_retry_at).await;
+                continue;
+            }
 
-        for htlc in expired {
             info!("♻️ Processing refund for expired HTLC: {}", htlc.id);
 
+            // A redeem may still be in the mempool, its secret revealed
+            // within the margin; a refund would conflict with it
+            match self.client.is_htlc_output_spent(&htlc.id).await {
+                Ok(true) => {
+                    info!("⏭️ Skipping refund of {}: output already spent", htlc.id);
+                    self.complete_job(&job).await;
+                    continue;
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    error!("❌ Failed to check HTLC {} output: {}", htlc.id, e);
+                    self.record_failure(e.failure_kind(), &e.to_string()).await;
+                    self.retry_job(&job, &e.to_string()).await;
+                    continue;
+                }
+            }
+
+            let (refund_key, refund_address) = match self.refund_key_for(&htlc) {
+                Ok(Some(key)) => key,
+                Ok(None) => {
+                    let reason = format!(
+                        "No hot wallet key matches refund public key {}",
+                        htlc.refund_pubkey
+                    );
+                    error!("❌ Cannot refund HTLC {}: {}", htlc.id, reason);
+                    self.fail_job(&job, &reason).await;
+                    continue;
+                }
+                Err(e) => {
+                    error!("❌ No refund key for HTLC {}: {}", htlc.id, e);
+                    self.retry_job(&job, &e.to_string()).await;
+                    continue;
+                }
+            };
+            if !self.may_broadcast("refunds").await {
+                break;
+            }
+
             match self
                 .client
-                .refund_htlc(&htlc.id, &self.hot_wallet_address, &self.hot_wallet_privkey)
+                .refund_htlc(&htlc.id, refund_address, &refund_key)
                 .await
             {
                 Ok(txid) => {
                     info!("✅ HTLC refunded: {} with txid: {}", htlc.id, txid);
+                    self.circuit.record_success();
+                    self.complete_job(&job).await;
                 }
+                // A refund operation that failed to broadcast is retried on
+                // its own, and the job waits for it when claimed again
                 Err(e) => {
                     error!("❌ Failed to refund HTLC {}: {}", htlc.id, e);
+                    self.record_failure(e.failure_kind(), &e.to_string()).await;
+                    self.retry_job(&job, &e.to_string()).await;
                 }
             }
         }
@@ -157,76 +1093,1384 @@ impl AutomatedRelayer {
         Ok(())
     }
 
-    async fn get_relayer_utxos(&self) -> Result<Vec<UTXO>, Box<dyn std::error::Error>> {
-        let utxos = self
+    /// Broadcast failed operations again once their backoff has passed, up
+    /// to `max_retry_attempts` attempts each or until the node refuses the
+    /// transaction for good. Attempts are recorded in the database, so the
+    /// schedule survives restarts; refunds given up on are rebuilt by
+    /// [`Self::process_expired_htlcs`].
+    async fn retry_failed_operations(&self) -> Result<(), RelayerError> {
+        let tunables = self.tunables();
+        let (max_attempts, batch) = (
+            tunables.max_retry_attempts,
+            tunables.max_tx_per_batch as usize,
+        );
+        let due = self
             .database
-            .get_unspent_relayer_utxos(&self.hot_wallet_address)?;
-        Ok(utxos.into_iter().map(Into::into).collect())
-    }
-
-    fn select_utxos(
-        &self,
-        utxos: &[UTXO],
-        required_amount: f64,
-    ) -> Result<Vec<UTXO>, Box<dyn std::error::Error>> {
-        let mut selected = Vec::new();
-        let mut total = 0.0;
-
-        for utxo in utxos {
-            let amount: f64 = utxo.amount.parse()?;
-            selected.push(utxo.clone());
-            total += amount;
+            .run(move |db| db.get_operations_due_for_retry(chrono::Utc::now(), max_attempts, batch))
+            .await?;
 
-            if total >= required_amount {
-                return Ok(selected);
+        for operation in due {
+            if self.halted() || !self.may_broadcast("retries").await {
+                break;
+            }
+            match self.client.retry_operation(&operation).await {
+                Ok(txid) => {
+                    info!(
+                        "✅ Operation {} for HTLC {} broadcast with txid: {}",
+                        operation.id, operation.htlc_id, txid
+                    );
+                    self.circuit.record_success();
+                }
+                Err(e)
+                    if !e.is_rebroadcastable() || operation.attempt_count + 1 >= max_attempts =>
+                {
+                    error!(
+                        "❌ Giving up on operation {} for HTLC {} after {} attempt(s): {}",
+                        operation.id,
+                        operation.htlc_id,
+                        operation.attempt_count + 1,
+                        e
+                    );
+                    self.record_failure(e.failure_kind(), &e.to_string()).await;
+                }
+                Err(e) => {
+                    warn!("⚠️ Retry of operation {} failed: {}", operation.id, e);
+                    self.record_failure(e.failure_kind(), &e.to_string()).await;
+                }
             }
         }
 
-        Err("Insufficient UTXOs".into())
+        Ok(())
     }
 
-    async fn sync_utxos(&self) -> Result<(), Box<dyn std::error::Error>> {
-        info!("🔄 Syncing relayer UTXOs...");
-
-        let balance = self
+    /// Speed up broadcast operations stuck unconfirmed, one ZIP-317 weight
+    /// step at a time. A refund whose transaction expired unconfirmed is
+    /// rebuilt with a higher fee and a new expiry; a funding transaction
+    /// unconfirmed `after_blocks` after it or its last bump was broadcast
+    /// gets a child spending its change back to the hot wallet. Redeems
+    /// pay the recipient and are left alone.
+    async fn bump_stuck_operations(&self, config: &FeeBumpConfig) -> Result<(), RelayerError> {
+        let tip = self.client.get_current_block_height().await?;
+        let batch = self.tunables().max_tx_per_batch as usize;
+        let unconfirmed = self
             .database
-            .get_total_relayer_balance(&self.hot_wallet_address)?;
-        info!("💰 Current relayer balance: {} ZEC", balance);
+            .run(move |db| db.get_unconfirmed_operations(batch))
+            .await?;
+
+        for operation in unconfirmed {
+            if self.halted() {
+                break;
+            }
+            let Some(txid) = operation.txid.clone() else {
+                continue;
+            };
+            let id = operation.id.clone();
+            let confirmations = match self.client.get_transaction_confirmations(&txid).await {
+                Ok(confirmations) => confirmations,
+                Err(e) => {
+                    warn!("⚠️ Cannot check confirmations of {}: {}", txid, e);
+                    continue;
+                }
+            };
+
+            // Marked confirmed by the confirmation tracker once deep enough
+            if confirmations > 0 {
+                continue;
+            }
+            let Some(broadcast_height) = operation.broadcast_height else {
+                self.database
+                    .run(move |db| db.record_broadcast_height(&id, tip))
+                    .await?;
+                continue;
+            };
+            let more = match operation.operation_type {
+                HTLCOperationType::Refund => {
+                    self.replace_stuck_refund(&operation, tip, config).await?
+                }
+                HTLCOperationType::Create => {
+                    self.bump_stuck_creation(&operation, broadcast_height, tip, config)
+                        .await?
+                }
+                HTLCOperationType::Redeem | HTLCOperationType::FeeBump => true,
+            };
+            if !more {
+                break;
+            }
+        }
 
         Ok(())
     }
 
-    async fn run(&self) {
-        info!("🚀 Automated Relayer started");
-        info!("💼 Hot wallet: {}", self.hot_wallet_address);
-        info!("⏱️  Poll interval: {:?}", self.poll_interval);
-
-        let mut ticker = interval(self.poll_interval);
+    /// Rebuild the refund `operation` once nodes dropped its transaction
+    /// for expiring unconfirmed; until then they would refuse a
+    /// replacement. Returns `false` once the broadcast budget is spent.
+    async fn replace_stuck_refund(
+        &self,
+        operation: &HTLCOperation,
+        tip: u64,
+        config: &FeeBumpConfig,
+    ) -> Result<bool, RelayerError> {
+        match self.client.operation_expiry_height(operation) {
+            Ok(Some(expiry_height)) if tip >= expiry_height as u64 => {}
+            Ok(_) => return Ok(true),
+            Err(e) => {
+                warn!("⚠️ Cannot read refund {}: {}", operation.id, e);
+                return Ok(true);
+            }
+        }
+        let htlc_id = operation.htlc_id.clone();
+        let htlc = self
+            .database
+            .run(move |db| db.get_htlc_by_id(&htlc_id))
+            .await?;
+        let (refund_key, refund_address) = match self.refund_key_for(&htlc) {
+            Ok(Some(key)) => key,
+            Ok(None) => {
+                warn!(
+                    "⚠️ Cannot replace refund {}: no hot wallet key matches refund public key {}",
+                    operation.id, htlc.refund_pubkey
+                );
+                return Ok(true);
+            }
+            Err(e) => {
+                warn!("⚠️ Cannot replace refund {}: {}", operation.id, e);
+                return Ok(true);
+            }
+        };
+        if !self.may_broadcast("fee bumps").await {
+            return Ok(false);
+        }
+        info!(
+            "🐌 Refund {} of HTLC {} expired unconfirmed, replacing it",
+            operation.id, htlc.id
+        );
 
+        match self
+            .client
+            .replace_refund(operation, refund_address, &refund_key, config.max_fee_zat)
+            .await
+        {
+            Ok(_) => {}
+            Err(HTLCClientError::TxBuilderError(TxBuilderError::FeeNotIncreased { .. })) => {
+                info!(
+                    "💸 Refund {} pays the most fee allowed already",
+                    operation.id
+                )
+            }
+            Err(e) => warn!("⚠️ Cannot replace refund {}: {}", operation.id, e),
+        }
+        Ok(true)
+    }
+
+    /// Give the funding `operation` another child once it, or its last
+    /// bump, sat unconfirmed `after_blocks`, marking the output the child
+    /// spends as spent. Returns `false` once the broadcast budget is spent.
+    async fn bump_stuck_creation(
+        &self,
+        operation: &HTLCOperation,
+        broadcast_height: u64,
+        tip: u64,
+        config: &FeeBumpConfig,
+    ) -> Result<bool, RelayerError> {
+        let id = operation.id.clone();
+        let bumps = self.database.run(move |db| db.get_fee_bumps(&id)).await?;
+        // A bump not yet seen in the mempool is retried on its own
+        let Some(since) = bumps
+            .last()
+            .map_or(Some(broadcast_height), |bump| bump.broadcast_height)
+        else {
+            return Ok(true);
+        };
+        if tip < since + config.after_blocks {
+            return Ok(true);
+        }
+        if !self.may_broadcast("fee bumps").await {
+            return Ok(false);
+        }
+        info!(
+            "🐌 Operation {} unconfirmed for {} blocks after {} bump(s), bumping it",
+            operation.id,
+            tip - broadcast_height,
+            bumps.len()
+        );
+
+        // The output to spend pays whichever wallet funded it
+        for wallet in &self.hot_wallets {
+            let bumped = match wallet.keys() {
+                Ok(keys) => {
+                    self.client
+                        .bump_fee(
+                            operation,
+                            &bumps,
+                            &wallet.address,
+                            &keys,
+                            config.max_fee_zat,
+                        )
+                        .await
+                }
+                Err(e) => Err(e.into()),
+            };
+            match bumped {
+                Err(HTLCClientError::NoOutputToBump(_)) => continue,
+                Err(HTLCClientError::TxBuilderError(TxBuilderError::FeeNotIncreased {
+                    ..
+                })) => info!(
+                    "💸 Operation {} pays the most fee allowed already",
+                    operation.id
+                ),
+                Err(e) => warn!("⚠️ Cannot bump operation {}: {}", operation.id, e),
+                Ok((txid, spent)) => {
+                    self.database
+                        .run(move |db| db.mark_utxo_spent(&spent.txid, spent.vout, &txid))
+                        .await?;
+                }
+            }
+            break;
+        }
+        Ok(true)
+    }
+
+    /// Whether to stop taking on work: on shutdown, or once the circuit
+    /// breaker opens or the leader lease is lost mid-batch
+    fn halted(&self) -> bool {
+        self.stopping.is_cancelled()
+            || self.circuit.open_until().is_some()
+            || self.status.lock().unwrap().leader == Some(false)
+    }
+
+    /// Count a node failure towards the circuit breaker. Once
+    /// `failure_threshold` follow each other, batches pause for the
+    /// cooldown and a `relayer.circuit_open` alert is queued.
+    async fn record_failure(&self, kind: FailureKind, error: &str) {
+        let Some(config) = self.tunables().circuit_breaker else {
+            return;
+        };
+        let cooldown = Duration::from_secs(config.cooldown_secs);
+        let Some(failures) = self
+            .circuit
+            .record_failure(kind, config.failure_threshold, cooldown)
+        else {
+            return;
+        };
+
+        error!(
+            "🔌 {} node failures in a row, pausing batches for {:?}: {}",
+            failures, cooldown, error
+        );
+        self.status.lock().unwrap().circuit_open = true;
+        let payload = serde_json::json!({
+            "failures": failures,
+            "last_error": error,
+            "cooldown_secs": config.cooldown_secs,
+        });
+        let queued = self
+            .database
+            .run(move |db| db.queue_relayer_event("relayer.circuit_open", payload))
+            .await;
+        if let Err(e) = queued {
+            error!("Failed to queue circuit breaker alert: {}", e);
+        }
+    }
+
+    /// Whether batches may run. An open circuit closes once its cooldown
+    /// has passed, as the node has just answered the `node_ready` probe,
+    /// and a `relayer.circuit_closed` alert is queued.
+    async fn circuit_closed(&self) -> bool {
+        match self.circuit.open_until() {
+            None => return true,
+            Some(until) if Instant::now() < until => {
+                info!("🔌 Circuit open, skipping batch");
+                return false;
+            }
+            Some(_) => {}
+        }
+
+        info!("🔌 Node answering again, resuming batches");
+        self.circuit.close();
+        self.status.lock().unwrap().circuit_open = false;
+        let queued = self
+            .database
+            .run(|db| db.queue_relayer_event("relayer.circuit_closed", serde_json::json!({})))
+            .await;
+        if let Err(e) = queued {
+            error!("Failed to queue circuit breaker alert: {}", e);
+        }
+        true
+    }
+
+    /// Claim up to a batch of due `job_type` jobs for this replica
+    async fn claim_jobs(&self, job_type: JobType) -> Result<Vec<Job>, RelayerError> {
+        let (worker, batch) = (self.worker_id.clone(), self.tunables().max_tx_per_batch);
+        let jobs = self
+            .database
+            .run(move |db| db.claim_jobs(job_type, &worker, JOB_VISIBILITY_TIMEOUT, batch))
+            .await?;
+        Ok(jobs)
+    }
+
+    /// HTLC a claimed job is for; a job whose HTLC is gone is completed.
+    /// The claim is renewed first, and a job whose claim passed to another
+    /// worker while earlier jobs of the batch took long is skipped.
+    async fn job_htlc(&self, job: &Job) -> Result<Option<ZcashHTLC>, RelayerError> {
+        if !self.renew_job(job).await {
+            warn!(
+                "⚠️  Job {} was claimed by another worker, skipping it",
+                job.id
+            );
+            return Ok(None);
+        }
+        let id = job.htlc_id().unwrap_or_default().to_string();
+        match self.database.run(move |db| db.get_htlc_by_id(&id)).await {
+            Ok(htlc) => Ok(Some(htlc)),
+            Err(DatabaseError::HTLCNotFound(_)) => {
+                warn!("⚠️  Job {} is for an unknown HTLC, dropping it", job.id);
+                self.complete_job(job).await;
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Extend this replica's claim on a job; `false` if it was lost
+    async fn renew_job(&self, job: &Job) -> bool {
+        let (id, worker) = (job.id, self.worker_id.clone());
+        match self
+            .database
+            .run(move |db| db.renew_job(id, &worker, JOB_VISIBILITY_TIMEOUT))
+            .await
+        {
+            Ok(renewed) => renewed,
+            Err(e) => {
+                error!("Failed to renew job {}: {}", job.id, e);
+                false
+            }
+        }
+    }
+
+    /// Await `work` on a claimed job, renewing the claim meanwhile so a
+    /// slow step, e.g. collecting cosigner signatures, does not hand the
+    /// job to another worker
+    async fn with_heartbeat<T>(&self, job: &Job, work: impl std::future::Future<Output = T>) -> T {
+        tokio::pin!(work);
+        let mut heartbeat = interval(JOB_VISIBILITY_TIMEOUT / 3);
+        heartbeat.tick().await;
         loop {
-            ticker.tick().await;
+            tokio::select! {
+                output = &mut work => return output,
+                _ = heartbeat.tick() => {
+                    if !self.renew_job(job).await {
+                        warn!("⚠️  Lost the claim on job {} while working on it", job.id);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn complete_job(&self, job: &Job) {
+        let (id, worker) = (job.id, self.worker_id.clone());
+        let completed = self
+            .database
+            .run(move |db| db.complete_job(id, &worker))
+            .await;
+        if let Err(e) = completed {
+            error!("Failed to complete job {}: {}", job.id, e);
+        }
+    }
+
+    /// Queue a job again after the same backoff as failed operations
+    async fn retry_job(&self, job: &Job, error: &str) {
+        let (id, worker, error) = (job.id, self.worker_id.clone(), error.to_string());
+        let delay = operation_retry_delay(job.attempts + 1);
+        let retried = self
+            .database
+            .run(move |db| db.retry_job(id, &worker, &error, delay))
+            .await;
+        if let Err(e) = retried {
+            error!("Failed to requeue job {}: {}", job.id, e);
+        }
+    }
+
+    /// Queue a job that is not due yet again for `until`
+    async fn defer_job(&self, job: &Job, until: chrono::DateTime<chrono::Utc>) {
+        let (id, worker) = (job.id, self.worker_id.clone());
+        let deferred = self
+            .database
+            .run(move |db| db.defer_job(id, &worker, until))
+            .await;
+        if let Err(e) = deferred {
+            error!("Failed to defer job {}: {}", job.id, e);
+        }
+    }
+
+    async fn fail_job(&self, job: &Job, error: &str) {
+        let (id, worker, error) = (job.id, self.worker_id.clone(), error.to_string());
+        let failed = self
+            .database
+            .run(move |db| db.fail_job(id, &worker, &error))
+            .await;
+        if let Err(e) = failed {
+            error!("Failed to record job {} failure: {}", job.id, e);
+        }
+    }
+
+    /// Hand the jobs this replica claimed but did not get to back to the
+    /// queue, e.g. when the broadcast limit was reached
+    async fn release_jobs(&self) {
+        let worker = self.worker_id.clone();
+        match self.database.run(move |db| db.release_jobs(&worker)).await {
+            Ok(0) => {}
+            Ok(released) => info!("↩️  Released {} unfinished job(s)", released),
+            Err(e) => error!("Failed to release jobs: {}", e),
+        }
+    }
+
+    /// Take a slot under `max_broadcasts_per_minute` for broadcasting
+    /// `what`; without one it waits for a later batch. The leader lease is
+    /// renewed first, so a replica whose lease ran out during a long batch
+    /// stops broadcasting, and abandons the batch, before a new leader
+    /// starts its own.
+    async fn may_broadcast(&self, what: &str) -> bool {
+        if !self.hold_lease().await {
+            warn!("⚠️ Leader lease lost, abandoning the batch before {}", what);
+            return false;
+        }
+        match self.tunables().max_broadcasts_per_minute {
+            Some(max) if !self.broadcast_limiter.try_acquire(max) => {
+                info!(
+                    "🚦 Broadcast limit reached, deferring {} to a later batch",
+                    what
+                );
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Refund public key a new HTLC is created with: the one it was queued
+    /// with, except that an HTLC refunding to the primary hot wallet gets a
+    /// key derived from its hash lock when an HD wallet is configured
+    fn refund_pubkey_for(&self, htlc: &ZcashHTLC) -> Result<String, RelayerError> {
+        let Some(hd_wallet) = &self.hd_wallet else {
+            return Ok(htlc.refund_pubkey.clone());
+        };
+        if htlc.refund_pubkey != self.hot_wallets[0].pubkey {
+            return Ok(htlc.refund_pubkey.clone());
+        }
+        Ok(self
+            .client
+            .derive_pubkey(&hd_wallet.derive_for_label(&htlc.hash_lock)?)?)
+    }
+
+    /// Key refunding `htlc` and the hot wallet to refund to: the key derived
+    /// from its hash lock when an HD wallet is configured and it matches the
+    /// HTLC's refund public key, else the wallet whose key does. `None` when
+    /// no key of this relayer matches, as no retry will change that.
+    fn refund_key_for(
+        &self,
+        htlc: &ZcashHTLC,
+    ) -> Result<Option<(Zeroizing<String>, &str)>, RelayerError> {
+        if let Some(hd_wallet) = &self.hd_wallet {
+            let privkey = hd_wallet.derive_for_label(&htlc.hash_lock)?;
+            if self.client.derive_pubkey(&privkey)? == htlc.refund_pubkey {
+                return Ok(Some((privkey, &self.hot_wallets[0].address)));
+            }
+        }
+        match self
+            .hot_wallets
+            .iter()
+            .find(|wallet| wallet.pubkey == htlc.refund_pubkey)
+        {
+            Some(wallet) => Ok(Some((wallet.key.privkey()?, &wallet.address))),
+            None => Ok(None),
+        }
+    }
+
+    /// Pick a hot wallet for an HTLC needing `required` zatoshis and reserve UTXOs
+    /// of it covering that, so that no other batch or relayer instance spends
+    /// them concurrently. Wallets that cannot cover it are passed over.
+    async fn reserve_funding(
+        &self,
+        required: u64,
+    ) -> Result<(&HotWallet, UtxoReservation), DatabaseError> {
+        let balances = match self.wallet_selection {
+            HotWalletSelection::Balance => {
+                let addresses: Vec<String> = self
+                    .hot_wallets
+                    .iter()
+                    .map(|wallet| wallet.address.clone())
+                    .collect();
+                self.database
+                    .run(move |db| {
+                        addresses
+                            .iter()
+                            .map(|address| {
+                                Ok(db
+                                    .get_unspent_relayer_utxos(address)?
+                                    .iter()
+                                    .filter_map(|utxo| zec_to_zatoshis(&utxo.amount))
+                                    .sum())
+                            })
+                            .collect::<Result<Vec<u64>, DatabaseError>>()
+                    })
+                    .await?
+            }
+            HotWalletSelection::RoundRobin => vec![0; self.hot_wallets.len()],
+        };
+        let turn = self.wallet_turn.fetch_add(1, Ordering::Relaxed);
+
+        let mut last_error = None;
+        for index in self.wallet_selection.order(&balances, turn) {
+            let wallet = &self.hot_wallets[index];
+            let address = wallet.address.clone();
+            match self
+                .database
+                .run(move |db| db.reserve_utxos(&address, required, UTXO_RESERVATION_TTL))
+                .await
+            {
+                Ok(reservation) => {
+                    self.reservations
+                        .lock()
+                        .unwrap()
+                        .insert(reservation.id.clone());
+                    return Ok((wallet, reservation));
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.expect("at least the primary hot wallet"))
+    }
+
+    /// Reconcile each hot wallet's UTXOs in the database with the chain. If
+    /// the chain cannot be queried the database view is kept for this batch.
+    async fn sync_utxos(&self) -> Result<(), RelayerError> {
+        info!("🔄 Syncing relayer UTXOs...");
+
+        for wallet in &self.hot_wallets {
+            match self.client.get_utxos(&wallet.address).await {
+                Ok(chain) => {
+                    let address = wallet.address.clone();
+                    self.database
+                        .run(move |db| db.sync_relayer_utxos(&address, &chain))
+                        .await?;
+                }
+                Err(e) => warn!(
+                    "⚠️  Could not fetch UTXOs of {}, using known ones: {}",
+                    wallet.address, e
+                ),
+            }
+
+            let address = wallet.address.clone();
+            let balance = self
+                .database
+                .run(move |db| db.get_total_relayer_balance(&address))
+                .await?;
+            info!(
+                "💰 Current balance of {}: {} ZEC",
+                wallet.address,
+                zatoshis_to_zec(balance)
+            );
+        }
+
+        if let Some(thresholds) = &self.tunables().wallet_thresholds {
+            self.check_wallet_thresholds(thresholds).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Pause creations and queue a `wallet.low_funds` event when the
+    /// spendable UTXOs of all hot wallets together fall short of
+    /// `thresholds`, and resume with a `wallet.funds_restored` event once
+    /// they no longer do
+    async fn check_wallet_thresholds(
+        &self,
+        thresholds: &WalletThresholdConfig,
+    ) -> Result<(), RelayerError> {
+        let addresses: Vec<String> = self
+            .hot_wallets
+            .iter()
+            .map(|wallet| wallet.address.clone())
+            .collect();
+        let lookup = addresses.clone();
+        let utxos = self
+            .database
+            .run(move |db| {
+                let mut utxos = Vec::new();
+                for address in &lookup {
+                    utxos.extend(db.get_unspent_relayer_utxos(address)?);
+                }
+                Ok(utxos)
+            })
+            .await?;
+        let shortfall = thresholds.shortfall(&utxos);
+
+        let previous = self.status.lock().unwrap().wallet_shortfall.clone();
+        if previous.is_some() == shortfall.is_some() {
+            self.status.lock().unwrap().wallet_shortfall = shortfall;
+            return Ok(());
+        }
+
+        let balance: u64 = utxos
+            .iter()
+            .filter_map(|utxo| zec_to_zatoshis(&utxo.amount))
+            .sum();
+        // `address` is the primary hot wallet, as before more could be set
+        let mut payload = serde_json::json!({
+            "address": addresses[0],
+            "addresses": addresses,
+            "balance_zec": zatoshis_to_zec(balance),
+            "utxos": utxos.len(),
+        });
+        let event_type = match &shortfall {
+            Some(reason) => {
+                error!(
+                    "🚨 Hot wallets running low ({}), pausing HTLC creations",
+                    reason
+                );
+                payload["reason"] = reason.as_str().into();
+                "wallet.low_funds"
+            }
+            None => {
+                info!("💰 Hot wallets topped up, resuming HTLC creations");
+                "wallet.funds_restored"
+            }
+        };
+
+        self.status.lock().unwrap().wallet_shortfall = shortfall;
+        self.database
+            .run(move |db| db.queue_wallet_event(event_type, payload))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Move HTLCs settled longer than `archive_after` ago to the archive
+    async fn archive_settled_htlcs(&self) -> Result<(), RelayerError> {
+        let Some(archive_after) = self.archive_after else {
+            return Ok(());
+        };
+
+        let settled_before = chrono::Utc::now() - archive_after;
+        self.database
+            .run(move |db| {
+                while db.archive_settled_htlcs(settled_before, ARCHIVE_BATCH)?
+                    == ARCHIVE_BATCH as usize
+                {}
+                Ok(())
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Delete outbox events older than `event_retention` that every
+    /// webhook is done with
+    async fn prune_events(&self) -> Result<(), RelayerError> {
+        let queued_before = chrono::Utc::now() - self.event_retention;
+        self.database
+            .run(move |db| {
+                while db.prune_events(queued_before, ARCHIVE_BATCH)? == ARCHIVE_BATCH as usize {}
+                Ok(())
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Consolidate each hot wallet in turn
+    async fn consolidate_utxos(&self, config: &ConsolidationConfig) -> Result<(), RelayerError> {
+        for wallet in &self.hot_wallets {
+            if self.halted() {
+                break;
+            }
+            self.consolidate_wallet(wallet, config).await?;
+        }
+        Ok(())
+    }
+
+    /// Merge the smallest UTXOs of `wallet` into one when it is fragmented
+    /// and fees are at or below the configured rate
+    async fn consolidate_wallet(
+        &self,
+        wallet: &HotWallet,
+        config: &ConsolidationConfig,
+    ) -> Result<(), RelayerError> {
+        let address = wallet.address.clone();
+        let unspent = self
+            .database
+            .run(move |db| db.get_unspent_relayer_utxos(&address))
+            .await?;
+        if !config.is_fragmented(&unspent) {
+            return Ok(());
+        }
+
+        let fee_per_kb = self
+            .client
+            .estimate_fee(CONSOLIDATION_CONF_TARGET)
+            .await?
+            .fee_per_kb;
+        if fee_per_kb > config.max_fee_per_kb {
+            info!(
+                "⏳ Postponing consolidation of {} UTXO(s): fee rate {} zat/kB above {}",
+                unspent.len(),
+                fee_per_kb,
+                config.max_fee_per_kb
+            );
+            return Ok(());
+        }
+
+        let address = wallet.address.clone();
+        let count = config.max_inputs.unwrap_or(DEFAULT_CONSOLIDATION_INPUTS);
+        let reservation = self
+            .database
+            .run(move |db| db.reserve_smallest_utxos(&address, count, UTXO_RESERVATION_TTL))
+            .await?;
+        let reservation_id = reservation.id;
+        let utxos: Vec<UTXO> = reservation.utxos.into_iter().map(Into::into).collect();
+
+        // The others are held by in-flight HTLC creations
+        if utxos.len() < 2 {
+            self.database
+                .run(move |db| db.release_reservation(&reservation_id))
+                .await?;
+            return Ok(());
+        }
+        if !self.may_broadcast("consolidation").await {
+            self.database
+                .run(move |db| db.release_reservation(&reservation_id))
+                .await?;
+            return Ok(());
+        }
+        self.reservations
+            .lock()
+            .unwrap()
+            .insert(reservation_id.clone());
+
+        let consolidation = match &self.threshold_wallet {
+            Some((wallet_script, coordinator)) => {
+                self.client
+                    .consolidate_utxos_with_cosigners(
+                        &utxos,
+                        &wallet.address,
+                        wallet_script,
+                        coordinator,
+                        fee_per_kb,
+                    )
+                    .await
+            }
+            None => match wallet.keys() {
+                Ok(keys) => {
+                    self.client
+                        .consolidate_utxos(&utxos, &wallet.address, &keys, fee_per_kb)
+                        .await
+                }
+                Err(e) => Err(e.into()),
+            },
+        };
+        self.reservations.lock().unwrap().remove(&reservation_id);
+
+        match consolidation {
+            Ok(consolidation) => {
+                self.database
+                    .run(move |db| db.commit_consolidation(&reservation_id, &consolidation))
+                    .await?;
+                Ok(())
+            }
+            Err(e) => {
+                self.database
+                    .run(move |db| db.release_reservation(&reservation_id))
+                    .await?;
+                Err(e.into())
+            }
+        }
+    }
+
+    async fn run(&self) {
+        info!("🚀 Automated Relayer started");
+        for wallet in &self.hot_wallets {
+            info!("💼 Hot wallet: {}", wallet.address);
+        }
+        if self.hot_wallets.len() > 1 {
+            info!("🔀 Selecting hot wallets by {:?}", self.wallet_selection);
+        }
+        let mut poll_interval = self.tunables().poll_interval;
+        info!("⏱️  Poll interval: {:?}", poll_interval);
+
+        let mut ticker = interval(poll_interval);
+        let mut next_archive = Instant::now();
+        let mut next_consolidation = Instant::now();
+        let blocks = self.client.subscribe_blocks();
+        tokio::pin!(blocks);
+        let mut wakeups = match self
+            .database
+            .subscribe(&[PENDING_HTLC_CHANNEL, SIGNED_REDEEM_CHANNEL])
+        {
+            Ok(wakeups) => Some(wakeups),
+            Err(e) => {
+                warn!(
+                    "⚠️  Database notifications unavailable, polling only: {}",
+                    e
+                );
+                None
+            }
+        };
+        let mut hangups = hangup_signals();
+
+        loop {
+            // A new block can confirm funding or unlock refunds, and new
+            // HTLCs or signed redemptions need work, so handle them right
+            // away; the ticker is only the fallback
+            let mut hangup = false;
+            tokio::select! {
+                _ = self.stopping.cancelled() => break,
+                _ = ticker.tick() => {}
+                Some(block) = blocks.next() => {
+                    info!("🧱 New block {} at height {}", block.hash, block.height);
+                }
+                Some(wakeup) = next_wakeup(&mut wakeups) => {
+                    info!("🔔 {} for HTLC {}", wakeup.channel, wakeup.htlc_id);
+                }
+                Some(()) = next_hangup(&mut hangups) => hangup = true,
+            }
+            // Edits to the config file apply from the next batch on
+            if self.config_changed() || hangup {
+                info!("🔁 Reloading {}", self.config_path);
+                self.reload_config();
+            }
+            let tunables = self.tunables();
+            if tunables.poll_interval != poll_interval {
+                poll_interval = tunables.poll_interval;
+                info!("⏱️  Poll interval: {:?}", poll_interval);
+                ticker = interval(poll_interval);
+            }
+            // One batch handles a whole burst of notifications
+            if let Some(wakeups) = &mut wakeups {
+                while wakeups.try_recv().is_ok() {}
+            }
+
+            self.status.lock().unwrap().last_cycle_at = chrono::Utc::now();
+            if !self.node_ready().await || !self.hold_lease().await || !self.circuit_closed().await
+            {
+                continue;
+            }
 
             info!("🔄 Processing batch...");
 
+            match self.client.track_chain_tip().await {
+                Ok(Some(reorg)) => info!(
+                    "⏪ Reorg at block {}: {} operation(s) reconfirmed, {} reverted",
+                    reorg.fork_height,
+                    reorg.reconfirmed.len(),
+                    reorg.reverted.len()
+                ),
+                Ok(None) => {}
+                Err(e) => error!("❌ Error checking for reorgs: {}", e),
+            }
+
             if let Err(e) = self.sync_utxos().await {
                 error!("❌ Error syncing UTXOs: {}", e);
             }
 
-            if let Err(e) = self.process_pending_htlc_creations().await {
-                error!("❌ Error processing HTLC creations: {}", e);
+            // Before refunds, so none is attempted for an HTLC the
+            // counterparty already redeemed
+            if self.watchtower {
+                match self.client.scan_for_htlcs().await {
+                    Ok(report) if !report.activity.is_empty() => info!(
+                        "👁️  Blocks {}-{}: {} HTLC output(s) funded or spent",
+                        report.from_height,
+                        report.to_height,
+                        report.activity.len()
+                    ),
+                    Ok(_) => {}
+                    Err(e) => error!("❌ Error scanning for HTLC spends: {}", e),
+                }
             }
 
+            // Before redemptions and refunds, which only act on HTLCs whose
+            // funding has confirmed
+            let batch = self.tunables().max_tx_per_batch as usize;
+            match self
+                .client
+                .track_confirmations(self.min_confirmations, batch)
+                .await
+            {
+                Ok(report) if !report.confirmed.is_empty() => info!(
+                    "✅ {} operation(s) confirmed, {} HTLC(s) locked",
+                    report.confirmed.len(),
+                    report.locked.len()
+                ),
+                Ok(_) => {}
+                Err(e) => error!("❌ Error tracking confirmations: {}", e),
+            }
+
+            // Redeems and refunds must land around the timelock, so they
+            // take broadcast slots before new HTLCs are funded
             if let Err(e) = self.process_pending_redemptions().await {
                 error!("❌ Error processing redemptions: {}", e);
+                self.record_failure(e.kind(), &e.to_string()).await;
             }
 
             if let Err(e) = self.process_expired_htlcs().await {
                 error!("❌ Error processing refunds: {}", e);
+                self.record_failure(e.kind(), &e.to_string()).await;
+            }
+
+            if let Err(e) = self.retry_failed_operations().await {
+                error!("❌ Error retrying failed operations: {}", e);
+                self.record_failure(e.kind(), &e.to_string()).await;
+            }
+
+            if let Err(e) = self.process_pending_htlc_creations().await {
+                error!("❌ Error processing HTLC creations: {}", e);
+                self.record_failure(e.kind(), &e.to_string()).await;
+            }
+            self.release_jobs().await;
+
+            if let Some(fee_bump) = &self.tunables().fee_bump {
+                if let Err(e) = self.bump_stuck_operations(fee_bump).await {
+                    error!("❌ Error bumping stuck operations: {}", e);
+                }
+            }
+
+            if let Some(cache) = &self.key_cache {
+                cache.purge_expired();
+            }
+
+            if Instant::now() >= next_archive && !self.stopping.is_cancelled() {
+                if let Err(e) = self.archive_settled_htlcs().await {
+                    error!("❌ Error archiving settled HTLCs: {}", e);
+                }
+                if let Err(e) = self.prune_events().await {
+                    error!("❌ Error pruning delivered events: {}", e);
+                }
+                next_archive = Instant::now() + ARCHIVE_INTERVAL;
             }
 
+            if let Some(consolidation) = &self.tunables().consolidation {
+                if Instant::now() >= next_consolidation && !self.stopping.is_cancelled() {
+                    if let Err(e) = self.consolidate_utxos(consolidation).await {
+                        error!("❌ Error consolidating UTXOs: {}", e);
+                    }
+                    next_consolidation = Instant::now()
+                        + consolidation
+                            .interval_secs
+                            .map_or(DEFAULT_CONSOLIDATION_INTERVAL, Duration::from_secs);
+                }
+            }
+
+            let mut status = self.status.lock().unwrap();
+            status.last_cycle_at = chrono::Utc::now();
+            status.last_batch_at = Some(status.last_cycle_at);
+            drop(status);
             info!("✅ Batch complete");
         }
     }
+
+    fn tunables(&self) -> Tunables {
+        self.tunables.read().unwrap().clone()
+    }
+
+    /// Whether the config file was modified since it was last read
+    fn config_changed(&self) -> bool {
+        let modified = config_modified_at(self.config_path);
+        let mut last = self.config_modified.lock().unwrap();
+        if modified == *last {
+            return false;
+        }
+        *last = modified;
+        true
+    }
+
+    /// Apply the tunables, fee mode and fee priorities of the config file,
+    /// warning about changed settings that take a restart. Work in flight
+    /// is not affected, and an unreadable or invalid file keeps the current
+    /// settings.
+    fn reload_config(&self) {
+        let config = ZcashConfig::from_file(self.config_path).map_err(|e| e.to_string());
+        let reloaded = config.and_then(|config| {
+            let relayer_config = config
+                .relayer
+                .as_ref()
+                .ok_or_else(|| "Relayer config missing".to_string())?;
+            let tunables = Tunables::from_config(relayer_config, self.threshold_wallet.is_some())?;
+            Ok((
+                tunables,
+                relayer_config.fee_priorities,
+                config.fee_mode.unwrap_or_default(),
+                self.restart_settings.changed(&config),
+            ))
+        });
+        let (tunables, fee_priorities, fee_mode, changed) = match reloaded {
+            Ok(reloaded) => reloaded,
+            Err(e) => {
+                error!(
+                    "❌ Cannot reload {}, keeping current settings: {}",
+                    self.config_path, e
+                );
+                return;
+            }
+        };
+
+        // Creations paused for thresholds that no longer apply resume
+        if tunables.wallet_thresholds.is_none() {
+            self.status.lock().unwrap().wallet_shortfall = None;
+        }
+        if !changed.is_empty() {
+            warn!(
+                "⚠️ {} changed in {}, restart the relayer to apply",
+                changed.join(", "),
+                self.config_path
+            );
+        }
+        self.client
+            .set_fee_priorities(fee_priorities.unwrap_or_default());
+        self.client.set_fee_mode(fee_mode);
+        info!(
+            "🔁 Config reloaded: {} transaction(s) per batch, polling every {:?}",
+            tunables.max_tx_per_batch, tunables.poll_interval
+        );
+        *self.tunables.write().unwrap() = tunables;
+    }
+
+    /// Whether the node is on our network and synced; batches built against
+    /// a syncing node would see stale UTXOs and block heights
+    async fn node_ready(&self) -> bool {
+        let info = self.client.get_blockchain_info().await;
+        self.status.lock().unwrap().node = info.as_ref().ok().map(|info| NodeSync {
+            synced: info.is_synced(),
+            blocks: info.blocks,
+            target_height: info.target_height(),
+        });
+
+        if info.is_ok() {
+            self.circuit.record_success();
+        }
+        match info {
+            Ok(info) if info.is_synced() => true,
+            Ok(info) => {
+                warn!(
+                    "⏳ Node still syncing ({}/{} blocks, {:.1}%), skipping batch",
+                    info.blocks,
+                    info.target_height(),
+                    info.verificationprogress * 100.0
+                );
+                false
+            }
+            Err(e) => {
+                error!("❌ Node health check failed: {}", e);
+                self.record_failure(e.failure_kind(), &e.to_string()).await;
+                false
+            }
+        }
+    }
+
+    /// Acquire or renew the leader lease; without leader election every
+    /// replica is the leader. A database error counts as losing the lease,
+    /// as another replica may take it over once it expires.
+    async fn hold_lease(&self) -> bool {
+        let Some(lease) = &self.leader_lease else {
+            return true;
+        };
+
+        let holder = lease.holder.clone();
+        let ttl = lease.ttl;
+        let leader = match self
+            .database
+            .run(move |db| db.try_acquire_lease(LEADER_LEASE, &holder, ttl))
+            .await
+        {
+            Ok(leader) => leader,
+            Err(e) => {
+                error!("❌ Failed to renew leader lease: {}", e);
+                false
+            }
+        };
+
+        let was_leader = self.status.lock().unwrap().leader.replace(leader);
+        if was_leader != Some(leader) {
+            if leader {
+                info!("👑 Became leader, processing batches");
+            } else {
+                info!("💤 Another replica is leader, standing by");
+            }
+        }
+        leader
+    }
+
+    /// Release leftover reservations and wipe cached keys before exiting
+    async fn shutdown(&self) {
+        self.release_jobs().await;
+        let leftover: Vec<String> = self.reservations.lock().unwrap().drain().collect();
+        for reservation_id in leftover {
+            let released = self
+                .database
+                .run(move |db| db.release_reservation(&reservation_id))
+                .await;
+            if let Err(e) = released {
+                error!("Failed to release UTXOs: {}", e);
+            }
+        }
+        if let Some(cache) = &self.key_cache {
+            cache.purge();
+        }
+        // Let a standby take over now rather than when the lease expires
+        if let Some(lease) = &self.leader_lease {
+            let holder = lease.holder.clone();
+            let released = self
+                .database
+                .run(move |db| db.release_lease(LEADER_LEASE, &holder))
+                .await;
+            if let Err(e) = released {
+                error!("Failed to release leader lease: {}", e);
+            }
+        }
+        // Undelivered events stay queued for the next start
+        for outbox in &self.outbox {
+            outbox.abort();
+        }
+        if let Some(readiness) = &self.readiness {
+            readiness.abort();
+        }
+        info!("👋 Relayer stopped");
+    }
+}
+
+/// Resolves on SIGINT, or SIGTERM as sent by systemd and Kubernetes
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(e) => {
+                warn!("⚠️  SIGTERM handler unavailable: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// SIGHUP, which asks for a config reload; `None` where unavailable
+#[cfg(unix)]
+fn hangup_signals() -> Option<tokio::signal::unix::Signal> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::hangup()) {
+        Ok(hangups) => Some(hangups),
+        Err(e) => {
+            warn!("⚠️  SIGHUP handler unavailable: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn hangup_signals() -> Option<()> {
+    None
+}
+
+/// Next SIGHUP, or never without a handler
+#[cfg(unix)]
+async fn next_hangup(hangups: &mut Option<tokio::signal::unix::Signal>) -> Option<()> {
+    match hangups {
+        Some(hangups) => hangups.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(not(unix))]
+async fn next_hangup(_: &mut Option<()>) -> Option<()> {
+    std::future::pending().await
+}
+
+/// Modification time of the config file, `None` if it cannot be read
+fn config_modified_at(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Next database notification, or never without a subscription
+async fn next_wakeup(
+    wakeups: &mut Option<mpsc::UnboundedReceiver<HTLCNotification>>,
+) -> Option<HTLCNotification> {
+    match wakeups {
+        Some(wakeups) => wakeups.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Serve probes on `addr`, answering with JSON and 200 or 503:
+///
+/// - `/healthz` fails once the batch loop has not come round for
+///   `stuck_after`, so a watchdog restarts a stuck relayer
+/// - `/readyz` fails while the database is unhealthy
+///   or the node is unreachable or syncing
+async fn serve_health(
+    addr: &str,
+    database: Arc<Database>,
+    status: Arc<Mutex<RelayerStatus>>,
+    stuck_after: chrono::Duration,
+) -> std::io::Result<JoinHandle<()>> {
+    let listener = std::net::TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+    let server = axum::Server::from_tcp(listener)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?
+        .serve(health_router(database, status, stuck_after).into_make_service());
+    info!("🩺 Health endpoints listening on {}", addr);
+
+    Ok(tokio::spawn(async move {
+        if let Err(e) = server.await {
+            error!("❌ Health endpoints stopped: {}", e);
+        }
+    }))
+}
+
+/// `/healthz` for liveness and `/readyz` for readiness, each answering 503
+/// with the same report while failing
+fn health_router(
+    database: Arc<Database>,
+    status: Arc<Mutex<RelayerStatus>>,
+    stuck_after: chrono::Duration,
+) -> Router {
+    let live = {
+        let status = status.clone();
+        move || async move { health_response(liveness(&status.lock().unwrap(), stuck_after)) }
+    };
+    let ready = move || async move {
+        let database = database.run(|db| db.health_check()).await;
+        health_response(readiness(database, &status.lock().unwrap()))
+    };
+
+    Router::new()
+        .route("/healthz", get(live))
+        .route("/readyz", get(ready))
+}
+
+fn health_response(
+    (healthy, body): (bool, serde_json::Value),
+) -> (StatusCode, Json<serde_json::Value>) {
+    let status = if healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(body))
+}
+
+/// Whether the batch loop came round within `stuck_after`
+fn liveness(status: &RelayerStatus, stuck_after: chrono::Duration) -> (bool, serde_json::Value) {
+    let healthy = chrono::Utc::now() - status.last_cycle_at <= stuck_after;
+    if !healthy {
+        warn!("⚠️  Batch loop stuck since {}", status.last_cycle_at);
+    }
+
+    let body = serde_json::json!({
+        "healthy": healthy,
+        "last_cycle_at": status.last_cycle_at,
+        "last_batch_at": status.last_batch_at,
+        "leader": status.leader,
+    });
+    (healthy, body)
+}
+
+/// Whether the database is healthy and the node synced, so batches can run
+fn readiness(
+    database: Result<DatabaseHealth, DatabaseError>,
+    status: &RelayerStatus,
+) -> (bool, serde_json::Value) {
+    let (database_healthy, database) = match database {
+        Ok(health) => {
+            if !health.healthy {
+                warn!("⚠️  Database unhealthy: {:?}", health);
+            }
+            (health.healthy, serde_json::json!(health))
+        }
+        Err(e) => {
+            error!("❌ Database health check failed: {}", e);
+            (
+                false,
+                serde_json::json!({"healthy": false, "error": e.to_string()}),
+            )
+        }
+    };
+
+    let node_synced = status.node.as_ref().is_some_and(|node| node.synced);
+    let healthy = database_healthy && node_synced;
+
+    let body = serde_json::json!({
+        "healthy": healthy,
+        "database": database,
+        "node": status.node,
+        "last_batch_at": status.last_batch_at,
+        "leader": status.leader,
+        "wallet_shortfall": status.wallet_shortfall,
+        "circuit_open": status.circuit_open,
+    });
+    (healthy, body)
+}
+
+/// Log a failed state update; losing a race to another relayer instance is
+/// expected and only warned about
+fn warn_on_conflict(updated: Result<u32, DatabaseError>) {
+    match updated {
+        Ok(_) => {}
+        Err(e @ DatabaseError::VersionConflict { .. }) => {
+            warn!("⚠️  Skipping update: {}", e);
+        }
+        Err(e) => error!("Failed to update HTLC state: {}", e),
+    }
+}
+
+/// Estimated seconds until an HTLC with `timelock` may be refunded, `None`
+/// once it may. Refunds wait until the timelock is `margin` blocks behind
+/// the tip, or as many block spacings behind its median time past, so a
+/// redeem broadcast just before expiry is not raced.
+fn refund_wait_secs(
+    timelock: u64,
+    kind: TimelockKind,
+    tip: u64,
+    mtp: u64,
+    margin: u64,
+) -> Option<u64> {
+    let (now, due) = match kind {
+        TimelockKind::BlockHeight => (tip, timelock + margin),
+        TimelockKind::Timestamp => (mtp, timelock + margin * BLOCK_SPACING_SECS),
+    };
+    if now > due {
+        return None;
+    }
+    let wait = due + 1 - now;
+    Some(match kind {
+        TimelockKind::BlockHeight => wait * BLOCK_SPACING_SECS,
+        TimelockKind::Timestamp => wait,
+    })
+}
+
+/// Start the ZMQ subscriber when `zmq_endpoint` is configured
+#[cfg(feature = "zmq")]
+fn block_notifications(config: &ZcashConfig) -> Option<broadcast::Sender<NodeEvent>> {
+    let endpoint = config.zmq_endpoint.as_ref()?;
+    let subscriber = zcash_htlc_builder::ZmqSubscriber::new(endpoint, 64);
+    let events = subscriber.events();
+    subscriber.spawn();
+    Some(events)
+}
+
+#[cfg(not(feature = "zmq"))]
+fn block_notifications(config: &ZcashConfig) -> Option<broadcast::Sender<NodeEvent>> {
+    if config.zmq_endpoint.is_some() {
+        warn!("⚠️  zmq_endpoint is ignored: built without the zmq feature");
+    }
+    None
 }
 
 #[tokio::main]
@@ -236,10 +2480,244 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .init();
 
     info!("Loading configuration...");
-    let config = ZcashConfig::from_default_locations()?;
+    let config_path = ZcashConfig::default_location()
+        .ok_or("No config file found. Create zcash-config.toml in project root")?;
+    let config = ZcashConfig::from_file(config_path)?;
+
+    let stopping = CancellationToken::new();
+    let relayer = AutomatedRelayer::new(config, config_path, stopping.clone()).await?;
 
-    let relayer = AutomatedRelayer::new(config).await?;
-    relayer.run().await;
+    let run = relayer.run();
+    tokio::pin!(run);
+    tokio::select! {
+        _ = &mut run => {}
+        _ = shutdown_signal() => {
+            info!("🛑 Shutdown requested, finishing in-flight work");
+            stopping.cancel();
+            // A second signal stops right away; reservations of an
+            // interrupted batch then expire after UTXO_RESERVATION_TTL
+            tokio::select! {
+                _ = &mut run => {}
+                _ = shutdown_signal() => warn!("⚠️  Stopping without finishing the batch"),
+            }
+        }
+    }
+    relayer.shutdown().await;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(node: Option<bool>, last_cycle_secs_ago: i64) -> RelayerStatus {
+        RelayerStatus {
+            last_cycle_at: chrono::Utc::now() - chrono::Duration::seconds(last_cycle_secs_ago),
+            last_batch_at: None,
+            node: node.map(|synced| NodeSync {
+                synced,
+                blocks: 100,
+                target_height: 100,
+            }),
+            leader: None,
+            wallet_shortfall: None,
+            circuit_open: false,
+        }
+    }
+
+    #[test]
+    fn test_broadcast_limiter_frees_slots_after_a_minute() {
+        let limiter = BroadcastLimiter::default();
+        let start = Instant::now();
+
+        assert!(limiter.try_acquire_at(2, start));
+        assert!(limiter.try_acquire_at(2, start + Duration::from_secs(30)));
+        assert!(!limiter.try_acquire_at(2, start + Duration::from_secs(59)));
+        // The first slot is free again, the second not yet
+        assert!(limiter.try_acquire_at(2, start + Duration::from_secs(60)));
+        assert!(!limiter.try_acquire_at(2, start + Duration::from_secs(60)));
+        // A lower limit after a reload applies at once
+        assert!(!limiter.try_acquire_at(1, start + Duration::from_secs(91)));
+        assert!(limiter.try_acquire_at(3, start + Duration::from_secs(91)));
+    }
+
+    #[test]
+    fn test_circuit_opens_after_node_failures_in_a_row() {
+        let circuit = CircuitBreaker::default();
+        let cooldown = Duration::from_secs(30);
+
+        assert_eq!(circuit.record_failure(FailureKind::Node, 2, cooldown), None);
+        // Rejected transactions neither count nor break the run
+        assert_eq!(
+            circuit.record_failure(FailureKind::Broadcast, 2, cooldown),
+            None
+        );
+        assert_eq!(
+            circuit.record_failure(FailureKind::Node, 2, cooldown),
+            Some(2)
+        );
+        assert!(circuit.open_until().is_some());
+        // Opened once until closed
+        assert_eq!(circuit.record_failure(FailureKind::Node, 2, cooldown), None);
+
+        circuit.close();
+        assert!(circuit.open_until().is_none());
+        assert_eq!(circuit.record_failure(FailureKind::Node, 2, cooldown), None);
+    }
+
+    #[test]
+    fn test_circuit_stays_closed_when_the_node_answers_between_failures() {
+        let circuit = CircuitBreaker::default();
+        let cooldown = Duration::from_secs(30);
+
+        for _ in 0..3 {
+            assert_eq!(circuit.record_failure(FailureKind::Node, 2, cooldown), None);
+            circuit.record_success();
+        }
+        assert!(circuit.open_until().is_none());
+    }
+
+    #[test]
+    fn test_refunds_wait_for_the_margin_past_the_timelock() {
+        let height = TimelockKind::BlockHeight;
+        assert_eq!(refund_wait_secs(100, height, 90, 0, 2), Some(13 * 75));
+        assert_eq!(refund_wait_secs(100, height, 102, 0, 2), Some(75));
+        assert_eq!(refund_wait_secs(100, height, 103, 0, 2), None);
+
+        let timestamp = TimelockKind::Timestamp;
+        let timelock = 1_790_000_000;
+        assert_eq!(
+            refund_wait_secs(timelock, timestamp, 0, timelock + 100, 2),
+            Some(51)
+        );
+        assert_eq!(
+            refund_wait_secs(timelock, timestamp, u64::MAX, timelock + 151, 2),
+            None
+        );
+    }
+
+    #[test]
+    fn test_liveness_fails_once_the_loop_is_stuck() {
+        let stuck_after = chrono::Duration::seconds(60);
+
+        let (healthy, body) = liveness(&status(None, 10), stuck_after);
+        assert!(healthy);
+        assert_eq!(body["healthy"], true);
+        let (healthy, body) = liveness(&status(Some(true), 120), stuck_after);
+        assert!(!healthy);
+        assert_eq!(body["healthy"], false);
+        assert_eq!(
+            health_response((healthy, body)).0,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+
+    #[test]
+    fn test_readiness_needs_a_healthy_database_and_synced_node() {
+        let database = |healthy| {
+            Ok(DatabaseHealth {
+                healthy,
+                latency_ms: 1,
+                pending_migrations: Vec::new(),
+                replication_lag_secs: None,
+            })
+        };
+
+        let (healthy, body) = readiness(database(true), &status(Some(true), 0));
+        assert!(healthy);
+        assert_eq!(body["database"]["latency_ms"], 1);
+        assert_eq!(health_response((healthy, body)).0, StatusCode::OK);
+
+        assert!(!readiness(database(false), &status(Some(true), 0)).0);
+        assert!(!readiness(database(true), &status(Some(false), 0)).0);
+        assert!(!readiness(database(true), &status(None, 0)).0);
+        let (healthy, body) = readiness(
+            Err(DatabaseError::WorkerError("pool closed".to_string())),
+            &status(Some(true), 0),
+        );
+        assert!(!healthy);
+        assert_eq!(body["database"]["healthy"], false);
+    }
+
+    fn relayer_config(overrides: serde_json::Value) -> RelayerConfig {
+        let mut config = serde_json::json!({
+            "hot_wallet_privkey": "11".repeat(32),
+            "hot_wallet_address": "tmBsTi2xWTjUdEXnuTceL7fecEQKeWaPDJd",
+            "max_tx_per_batch": 10,
+            "poll_interval_secs": 30,
+            "max_retry_attempts": 5,
+            "min_confirmations": 1,
+            "network_fee_zec": "0.0001",
+        });
+        for (name, value) in overrides.as_object().unwrap() {
+            config[name] = value.clone();
+        }
+        serde_json::from_value(config).unwrap()
+    }
+
+    #[test]
+    fn test_tunables_reject_invalid_settings() {
+        let tunables =
+            Tunables::from_config(&relayer_config(serde_json::json!({})), false).unwrap();
+        assert_eq!(tunables.poll_interval, Duration::from_secs(30));
+        assert_eq!(tunables.refund_margin_blocks, DEFAULT_REFUND_MARGIN_BLOCKS);
+
+        for invalid in [
+            serde_json::json!({"poll_interval_secs": 0}),
+            serde_json::json!({"max_broadcasts_per_minute": 0}),
+            serde_json::json!({"circuit_breaker": {"failure_threshold": 0, "cooldown_secs": 60}}),
+        ] {
+            assert!(
+                Tunables::from_config(&relayer_config(invalid.clone()), false).is_err(),
+                "{}",
+                invalid
+            );
+        }
+
+        // Threshold wallet outputs cannot be fee bumped
+        let fee_bump = relayer_config(serde_json::json!({"fee_bump": {"after_blocks": 3}}));
+        assert!(Tunables::from_config(&fee_bump, false)
+            .unwrap()
+            .fee_bump
+            .is_some());
+        assert!(Tunables::from_config(&fee_bump, true)
+            .unwrap()
+            .fee_bump
+            .is_none());
+    }
+
+    #[test]
+    fn test_reload_reports_only_restart_only_changes() {
+        let config = |relayer: serde_json::Value| {
+            let mut config = ZcashConfig::new(
+                zcash_htlc_builder::ZcashNetwork::Testnet,
+                "http://127.0.0.1:18232".to_string(),
+                "postgres://localhost/htlc".to_string(),
+            );
+            config.relayer = Some(relayer_config(relayer));
+            config
+        };
+        let before = RestartSettings::new(&config(serde_json::json!({})));
+
+        let reloadable = config(serde_json::json!({"max_tx_per_batch": 50}));
+        let mut reloadable_fee_mode = reloadable.clone();
+        reloadable_fee_mode.fee_mode = Some(zcash_htlc_builder::FeeMode::Dynamic {
+            conf_target: 2,
+            max_fee_per_kb: None,
+        });
+        assert!(before.changed(&reloadable_fee_mode).is_empty());
+
+        let mut restart = config(serde_json::json!({"min_confirmations": 3}));
+        restart.rpc_url = "http://10.0.0.2:18232".to_string();
+        assert_eq!(
+            before.changed(&restart),
+            vec!["relayer.min_confirmations", "rpc_url"]
+        );
+
+        // Secrets are compared by digest alone
+        let mut rotated = config(serde_json::json!({}));
+        rotated.rpc_password = Some("rotated".to_string());
+        assert_eq!(before.changed(&rotated), vec!["rpc_password"]);
+    }
+}
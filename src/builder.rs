@@ -1,19 +1,39 @@
-use bitcoin::blockdata::script::Script;
+use bitcoin::blockdata::opcodes;
+use bitcoin::blockdata::script::{Instruction, Script};
 use bitcoin::blockdata::transaction::{OutPoint, Transaction, TxIn, TxOut};
-use bitcoin::consensus::encode;
 use bitcoin::hash_types::Txid;
 use bitcoin::{PackedLockTime, Sequence, Witness};
 use std::str::FromStr;
 
-use crate::models::{HTLCParams, ZcashNetwork, UTXO};
+use crate::models::{
+    zec_to_zatoshis, HTLCParams, PartiallySignedTx, TemplateInput, TxReport, UnsignedTxTemplate,
+    ZcashNetwork, UTXO,
+};
 use crate::script::HTLCScriptBuilder;
+use crate::transaction::{v4_size, ZcashTransaction};
 
 const DUST_THRESHOLD: u64 = 546;
-const DEFAULT_FEE_RATE: u64 = 1000;
-
+/// Zatoshis per 1000 bytes, also the floor of dynamic rates
+pub const DEFAULT_FEE_RATE: u64 = 1000;
+const MAX_PUBKEYS_PER_MULTISIG: usize = 20;
+/// Size assumed for an input before it is signed
+const ESTIMATED_INPUT_SIZE: usize = 180;
+/// ZIP-317 fee per logical action, in zatoshis
+pub const MARGINAL_FEE: u64 = 5_000;
+/// Logical actions every transaction pays for at the least (ZIP-317)
+const GRACE_ACTIONS: u64 = 2;
+const P2PKH_STANDARD_INPUT_SIZE: usize = 150;
+const P2PKH_STANDARD_OUTPUT_SIZE: usize = 34;
+/// Multiple of its conventional fee beyond which a transaction weighs no
+/// more in ZIP-317 block assembly
+pub const MAX_WEIGHT_RATIO: u64 = 4;
+
+#[derive(Clone)]
 pub struct TransactionBuilder {
     network: ZcashNetwork,
     script_builder: HTLCScriptBuilder,
+    fee_rate: u64,
+    expiry_height: u32,
 }
 
 impl TransactionBuilder {
@@ -21,15 +41,39 @@ impl TransactionBuilder {
         Self {
             network,
             script_builder: HTLCScriptBuilder::new(network),
+            fee_rate: DEFAULT_FEE_RATE,
+            expiry_height: 0,
         }
     }
 
+    /// Pay `fee_per_kb` zatoshis per 1000 bytes, at least the default rate
+    pub fn with_fee_rate(mut self, fee_per_kb: u64) -> Self {
+        self.fee_rate = fee_per_kb.max(DEFAULT_FEE_RATE);
+        self
+    }
+
+    pub fn fee_rate(&self) -> u64 {
+        self.fee_rate
+    }
+
+    /// Let built transactions be mined up to `height` only, so one that
+    /// stalls leaves the mempool instead of blocking a replacement; 0 for
+    /// no expiry (the default)
+    pub fn with_expiry_height(mut self, height: u32) -> Self {
+        self.expiry_height = height;
+        self
+    }
+
+    pub fn expiry_height(&self) -> u32 {
+        self.expiry_height
+    }
+
     pub fn build_htlc_tx(
         &self,
         params: &HTLCParams,
         utxos: Vec<UTXO>,
         change_address: &str,
-    ) -> Result<(Transaction, Script), TxBuilderError> {
+    ) -> Result<(ZcashTransaction, Script), TxBuilderError> {
         let amount_sat = self.parse_amount(&params.amount)?;
 
         if amount_sat < DUST_THRESHOLD {
@@ -43,32 +87,11 @@ impl TransactionBuilder {
 
         let script_pubkey = self.script_builder.p2sh_script_pubkey(&redeem_script);
 
-        let inputs: Vec<TxIn> = utxos
-            .iter()
-            .map(|utxo| {
-                let txid = Txid::from_str(&utxo.txid).map_err(|_| TxBuilderError::InvalidTxid)?;
-
-                Ok(TxIn {
-                    previous_output: OutPoint {
-                        txid,
-                        vout: utxo.vout,
-                    },
-                    script_sig: Script::new(),
-                    sequence: Sequence(0xFFFFFFFF),
-                    witness: Witness::default(),
-                })
-            })
-            .collect::<Result<Vec<_>, TxBuilderError>>()?;
-
-        let total_input: u64 = utxos
-            .iter()
-            .map(|utxo| self.parse_amount(&utxo.amount))
-            .collect::<Result<Vec<_>, _>>()?
-            .iter()
-            .sum();
+        let inputs = self.unsigned_inputs(&utxos)?;
+        let total_input = self.total_amount(&utxos)?;
 
         let estimated_size = self.estimate_tx_size(inputs.len(), 2);
-        let fee = (estimated_size as u64 * DEFAULT_FEE_RATE) / 1000;
+        let fee = (estimated_size as u64 * self.fee_rate) / 1000;
 
         if total_input < amount_sat + fee {
             return Err(TxBuilderError::InsufficientFunds {
@@ -98,7 +121,82 @@ impl TransactionBuilder {
             output: outputs,
         };
 
-        Ok((tx, redeem_script))
+        Ok((self.with_expiry(tx), redeem_script))
+    }
+
+    /// Spend all of `utxos` to a single output paying `address`, the fee
+    /// taken from it, so later transactions need fewer inputs
+    pub fn build_consolidation_tx(
+        &self,
+        utxos: &[UTXO],
+        address: &str,
+    ) -> Result<ZcashTransaction, TxBuilderError> {
+        let inputs = self.unsigned_inputs(utxos)?;
+        let total_input = self.total_amount(utxos)?;
+
+        let estimated_size = self.estimate_tx_size(inputs.len(), 1);
+        let fee = (estimated_size as u64 * self.fee_rate) / 1000;
+
+        if total_input < fee {
+            return Err(TxBuilderError::InsufficientFunds {
+                required: fee,
+                available: total_input,
+            });
+        }
+        let value = total_input - fee;
+        if value <= DUST_THRESHOLD {
+            return Err(TxBuilderError::AmountTooSmall);
+        }
+
+        Ok(self.with_expiry(Transaction {
+            version: 4,
+            lock_time: PackedLockTime(0),
+            input: inputs,
+            output: vec![TxOut {
+                value,
+                script_pubkey: self.address_to_script_pubkey(address)?,
+            }],
+        }))
+    }
+
+    /// Spend `output` of a stuck package, a parent transaction and any
+    /// earlier children, to `address`, paying enough that the package,
+    /// which paid `package_fee` so far, and the child together pay
+    /// `target_fee`; the child pays at least its own conventional fee.
+    /// Miners take them all to collect the child's fee (child pays for
+    /// parent).
+    pub fn build_cpfp_tx(
+        &self,
+        output: &UTXO,
+        package_fee: u64,
+        target_fee: u64,
+        address: &str,
+    ) -> Result<ZcashTransaction, TxBuilderError> {
+        if package_fee >= target_fee {
+            return Err(TxBuilderError::FeeNotIncreased {
+                current: package_fee,
+                requested: target_fee,
+            });
+        }
+
+        let fee = (target_fee - package_fee).max(estimated_conventional_fee(1, 1));
+        let value = self.parse_amount(&output.amount)?;
+        if value < fee + DUST_THRESHOLD {
+            return Err(TxBuilderError::InsufficientFunds {
+                required: fee + DUST_THRESHOLD,
+                available: value,
+            });
+        }
+
+        Ok(self.with_expiry(Transaction {
+            version: 4,
+            lock_time: PackedLockTime(0),
+            input: self.unsigned_inputs(std::slice::from_ref(output))?,
+            output: vec![TxOut {
+                value: value - fee,
+                script_pubkey: self.address_to_script_pubkey(address)?,
+            }],
+        }))
     }
 
     pub fn build_redeem_tx(
@@ -109,12 +207,12 @@ impl TransactionBuilder {
         _secret: &str,
         _redeem_script: &Script,
         recipient_address: &str,
-    ) -> Result<Transaction, TxBuilderError> {
+    ) -> Result<ZcashTransaction, TxBuilderError> {
         let txid = Txid::from_str(htlc_txid).map_err(|_| TxBuilderError::InvalidTxid)?;
 
         let amount_sat = self.parse_amount(htlc_amount)?;
         let estimated_size = self.estimate_tx_size(1, 1);
-        let fee = (estimated_size as u64 * DEFAULT_FEE_RATE) / 1000;
+        let fee = (estimated_size as u64 * self.fee_rate) / 1000;
 
         if amount_sat <= fee {
             return Err(TxBuilderError::AmountTooSmall);
@@ -143,7 +241,7 @@ impl TransactionBuilder {
             output: vec![output],
         };
 
-        Ok(tx)
+        Ok(self.with_expiry(tx))
     }
 
     pub fn build_refund_tx(
@@ -154,12 +252,12 @@ impl TransactionBuilder {
         timelock: u64,
         _redeem_script: &Script,
         refund_address: &str,
-    ) -> Result<Transaction, TxBuilderError> {
+    ) -> Result<ZcashTransaction, TxBuilderError> {
         let txid = Txid::from_str(htlc_txid).map_err(|_| TxBuilderError::InvalidTxid)?;
 
         let amount_sat = self.parse_amount(htlc_amount)?;
         let estimated_size = self.estimate_tx_size(1, 1);
-        let fee = (estimated_size as u64 * DEFAULT_FEE_RATE) / 1000;
+        let fee = (estimated_size as u64 * self.fee_rate) / 1000;
 
         if amount_sat <= fee {
             return Err(TxBuilderError::AmountTooSmall);
@@ -188,30 +286,217 @@ impl TransactionBuilder {
             output: vec![output],
         };
 
-        Ok(tx)
+        Ok(self.with_expiry(tx))
     }
 
-    pub fn serialize_tx(&self, tx: &Transaction) -> String {
-        hex::encode(encode::serialize(tx))
+    /// Hex of the v4 encoding the node accepts
+    pub fn serialize_tx(&self, tx: &ZcashTransaction) -> String {
+        hex::encode(tx.serialize())
     }
 
-    pub fn deserialize_tx(&self, hex: &str) -> Result<Transaction, TxBuilderError> {
+    pub fn deserialize_tx(&self, hex: &str) -> Result<ZcashTransaction, TxBuilderError> {
         let bytes = hex::decode(hex).map_err(|_| TxBuilderError::InvalidHex)?;
 
-        encode::deserialize(&bytes).map_err(|e| TxBuilderError::DeserializationError(e.to_string()))
+        ZcashTransaction::deserialize(&bytes)
+            .map_err(|e| TxBuilderError::DeserializationError(e.to_string()))
     }
 
-    fn parse_amount(&self, amount_str: &str) -> Result<u64, TxBuilderError> {
-        let amount_f64: f64 = amount_str
-            .parse()
-            .map_err(|_| TxBuilderError::InvalidAmount)?;
+    fn with_expiry(&self, tx: Transaction) -> ZcashTransaction {
+        ZcashTransaction::new(tx, self.expiry_height)
+    }
 
-        let zatoshis = (amount_f64 * 100_000_000.0).round() as u64;
-        Ok(zatoshis)
+    /// Rebuild an unconfirmed spend with a larger fee.
+    ///
+    /// The same inputs are spent; the extra fee is taken from the output
+    /// paying `change_address` (the change output of a creation, or the
+    /// payout of a redeem/refund), never from the others, and a transaction
+    /// without such an output is an error. Change that would fall below the
+    /// dust threshold is dropped. The returned transaction is unsigned and
+    /// must be signed again; it keeps the original's expiry height.
+    pub fn rebuild_with_higher_fee(
+        &self,
+        original_tx: &ZcashTransaction,
+        prevouts: &[UTXO],
+        new_fee: u64,
+        change_address: &str,
+    ) -> Result<ZcashTransaction, TxBuilderError> {
+        let current_fee = self.tx_report(original_tx, prevouts)?.fee;
+        if new_fee <= current_fee {
+            return Err(TxBuilderError::FeeNotIncreased {
+                current: current_fee,
+                requested: new_fee,
+            });
+        }
+
+        let change_script = self.address_to_script_pubkey(change_address)?;
+        let change = original_tx
+            .output
+            .iter()
+            .position(|output| output.script_pubkey == change_script)
+            .ok_or(TxBuilderError::NoChangeOutput)?;
+
+        let mut tx = original_tx.clone();
+        for input in &mut tx.input {
+            input.script_sig = Script::new();
+        }
+
+        let bump = new_fee - current_fee;
+        let available = tx.output[change].value;
+        if available < bump + DUST_THRESHOLD {
+            if tx.output.len() == 1 {
+                return Err(TxBuilderError::InsufficientFunds {
+                    required: bump + DUST_THRESHOLD,
+                    available,
+                });
+            }
+            tx.output.remove(change);
+        } else {
+            tx.output[change].value -= bump;
+        }
+
+        Ok(tx)
     }
 
+    /// Bundle an unsigned transaction with its prevout data for offline signing.
+    ///
+    /// `inputs` must describe the outputs being spent, in input order.
+    pub fn export_unsigned_template(
+        &self,
+        tx: &ZcashTransaction,
+        inputs: Vec<TemplateInput>,
+    ) -> Result<UnsignedTxTemplate, TxBuilderError> {
+        if tx.input.len() != inputs.len() {
+            return Err(TxBuilderError::MismatchedPrevouts {
+                inputs: tx.input.len(),
+                prevouts: inputs.len(),
+            });
+        }
+
+        for (txin, input) in tx.input.iter().zip(&inputs) {
+            let txid = Txid::from_str(&input.txid).map_err(|_| TxBuilderError::InvalidTxid)?;
+            if txin.previous_output
+                != (OutPoint {
+                    txid,
+                    vout: input.vout,
+                })
+            {
+                return Err(TxBuilderError::PrevoutMismatch(format!(
+                    "{}:{}",
+                    input.txid, input.vout
+                )));
+            }
+        }
+
+        Ok(UnsignedTxTemplate {
+            network: self.network,
+            unsigned_tx_hex: self.serialize_tx(tx),
+            inputs,
+        })
+    }
+
+    /// Create a partially signed transaction that parties can sign in turn
+    pub fn create_psbt(
+        &self,
+        tx: &ZcashTransaction,
+        inputs: Vec<TemplateInput>,
+    ) -> Result<PartiallySignedTx, TxBuilderError> {
+        let template = self.export_unsigned_template(tx, inputs)?;
+        Ok(PartiallySignedTx::from_template(template))
+    }
+
+    /// Report serialized size, sigop count and fee rate for a built transaction.
+    ///
+    /// `prevouts` must list the outputs being spent, in input order.
+    pub fn tx_report(
+        &self,
+        tx: &Transaction,
+        prevouts: &[UTXO],
+    ) -> Result<TxReport, TxBuilderError> {
+        if tx.input.len() != prevouts.len() {
+            return Err(TxBuilderError::MismatchedPrevouts {
+                inputs: tx.input.len(),
+                prevouts: prevouts.len(),
+            });
+        }
+
+        let total_input: u64 = prevouts
+            .iter()
+            .map(|utxo| self.parse_amount(&utxo.amount))
+            .collect::<Result<Vec<_>, _>>()?
+            .iter()
+            .sum();
+        let total_output: u64 = tx.output.iter().map(|out| out.value).sum();
+
+        if total_input < total_output {
+            return Err(TxBuilderError::InsufficientFunds {
+                required: total_output,
+                available: total_input,
+            });
+        }
+
+        let mut sigops = 0;
+        for (input, prevout) in tx.input.iter().zip(prevouts) {
+            sigops += count_sigops(&input.script_sig, false);
+
+            let prev_script = hex::decode(&prevout.script_pubkey)
+                .map(Script::from)
+                .map_err(|_| TxBuilderError::InvalidHex)?;
+            if prev_script.is_p2sh() {
+                if let Some(redeem_script) = last_push(&input.script_sig) {
+                    sigops += count_sigops(&redeem_script, true);
+                }
+            }
+        }
+        for output in &tx.output {
+            sigops += count_sigops(&output.script_pubkey, false);
+        }
+
+        let size = v4_size(tx);
+        let fee = total_input - total_output;
+
+        Ok(TxReport {
+            size,
+            sigops,
+            fee,
+            fee_rate: fee * 1000 / size as u64,
+        })
+    }
+
+    fn unsigned_inputs(&self, utxos: &[UTXO]) -> Result<Vec<TxIn>, TxBuilderError> {
+        utxos
+            .iter()
+            .map(|utxo| {
+                let txid = Txid::from_str(&utxo.txid).map_err(|_| TxBuilderError::InvalidTxid)?;
+
+                Ok(TxIn {
+                    previous_output: OutPoint {
+                        txid,
+                        vout: utxo.vout,
+                    },
+                    script_sig: Script::new(),
+                    sequence: Sequence(0xFFFFFFFF),
+                    witness: Witness::default(),
+                })
+            })
+            .collect()
+    }
+
+    fn total_amount(&self, utxos: &[UTXO]) -> Result<u64, TxBuilderError> {
+        utxos
+            .iter()
+            .map(|utxo| self.parse_amount(&utxo.amount))
+            .sum()
+    }
+
+    fn parse_amount(&self, amount_str: &str) -> Result<u64, TxBuilderError> {
+        zec_to_zatoshis(amount_str).ok_or(TxBuilderError::InvalidAmount)
+    }
+
+    /// v4 size of a transaction spending P2PKH or HTLC inputs: header,
+    /// version group, lock time, expiry, value balance and empty shielded
+    /// vectors, then about 180 bytes per input and 34 per output
     fn estimate_tx_size(&self, num_inputs: usize, num_outputs: usize) -> usize {
-        10 + (num_inputs * 180) + (num_outputs * 34)
+        29 + (num_inputs * ESTIMATED_INPUT_SIZE) + (num_outputs * 34)
     }
 
     fn address_to_script_pubkey(&self, address: &str) -> Result<Script, TxBuilderError> {
@@ -248,6 +533,91 @@ impl TransactionBuilder {
     }
 }
 
+/// Count signature operations in a script. With `accurate` set, multisig
+/// ops count their actual key count (as for P2SH redeem scripts); otherwise
+/// each counts as the maximum of 20.
+fn count_sigops(script: &Script, accurate: bool) -> usize {
+    let mut count = 0;
+    let mut last_opcode = None;
+
+    for instruction in script.instructions().flatten() {
+        if let Instruction::Op(op) = instruction {
+            if op == opcodes::all::OP_CHECKSIG || op == opcodes::all::OP_CHECKSIGVERIFY {
+                count += 1;
+            } else if op == opcodes::all::OP_CHECKMULTISIG
+                || op == opcodes::all::OP_CHECKMULTISIGVERIFY
+            {
+                count += match last_opcode {
+                    Some(n) if accurate && (0x51..=0x60).contains(&n) => (n - 0x50) as usize,
+                    _ => MAX_PUBKEYS_PER_MULTISIG,
+                };
+            }
+            last_opcode = Some(op.to_u8());
+        } else {
+            last_opcode = None;
+        }
+    }
+
+    count
+}
+
+fn last_push(script: &Script) -> Option<Script> {
+    script
+        .instructions()
+        .flatten()
+        .filter_map(|instruction| match instruction {
+            Instruction::PushBytes(bytes) => Some(Script::from(bytes.to_vec())),
+            Instruction::Op(_) => None,
+        })
+        .last()
+}
+
+/// ZIP-317 conventional fee of a transparent transaction: [`MARGINAL_FEE`]
+/// for each logical action, and for at least two. Unsigned inputs count at
+/// the size they are expected to have once signed.
+pub fn conventional_fee(tx: &Transaction) -> u64 {
+    let input_size = tx
+        .input
+        .iter()
+        .map(|input| {
+            if input.script_sig.is_empty() {
+                ESTIMATED_INPUT_SIZE
+            } else {
+                bitcoin::consensus::encode::serialize(input).len()
+            }
+        })
+        .sum();
+    let output_size = tx
+        .output
+        .iter()
+        .map(|output| bitcoin::consensus::encode::serialize(output).len())
+        .sum();
+    actions_fee(input_size, output_size)
+}
+
+/// [`conventional_fee`] of a transaction spending `num_inputs` unsigned
+/// inputs to `num_outputs` P2PKH outputs
+pub fn estimated_conventional_fee(num_inputs: usize, num_outputs: usize) -> u64 {
+    actions_fee(
+        num_inputs * ESTIMATED_INPUT_SIZE,
+        num_outputs * P2PKH_STANDARD_OUTPUT_SIZE,
+    )
+}
+
+/// Multiple of its conventional fee one step above what `fee` buys a
+/// transaction, or `None` if it pays [`MAX_WEIGHT_RATIO`] times already
+pub fn next_weight_ratio(fee: u64, conventional_fee: u64) -> Option<u64> {
+    let ratio = fee / conventional_fee.max(1);
+    (ratio < MAX_WEIGHT_RATIO).then_some(ratio + 1)
+}
+
+fn actions_fee(input_size: usize, output_size: usize) -> u64 {
+    let actions = ((input_size + P2PKH_STANDARD_INPUT_SIZE - 1) / P2PKH_STANDARD_INPUT_SIZE)
+        .max((output_size + P2PKH_STANDARD_OUTPUT_SIZE - 1) / P2PKH_STANDARD_OUTPUT_SIZE)
+        as u64;
+    MARGINAL_FEE * actions.max(GRACE_ACTIONS)
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum TxBuilderError {
     #[error("Invalid amount format")]
@@ -270,4 +640,257 @@ pub enum TxBuilderError {
     ScriptError(String),
     #[error("Deserialization error: {0}")]
     DeserializationError(String),
+    #[error("Mismatched prevouts: {inputs} inputs, {prevouts} prevouts")]
+    MismatchedPrevouts { inputs: usize, prevouts: usize },
+    #[error("New fee {requested} does not exceed current fee {current}")]
+    FeeNotIncreased { current: u64, requested: u64 },
+    #[error("Prevout does not match transaction input: {0}")]
+    PrevoutMismatch(String),
+    #[error("Transaction has no output paying the change address")]
+    NoChangeOutput,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prevout(amount: &str, script_pubkey: &Script) -> UTXO {
+        UTXO {
+            txid: "11".repeat(32),
+            vout: 0,
+            amount: amount.to_string(),
+            script_pubkey: hex::encode(script_pubkey.as_bytes()),
+            confirmations: 1,
+        }
+    }
+
+    #[test]
+    fn test_tx_report_counts_p2sh_redeem_sigops() {
+        let builder = TransactionBuilder::new(ZcashNetwork::Testnet);
+        let script_builder = HTLCScriptBuilder::new(ZcashNetwork::Testnet);
+
+        let params = HTLCParams {
+            recipient_pubkey: format!("02{}", "a".repeat(64)),
+            refund_pubkey: format!("03{}", "b".repeat(64)),
+            hash_lock: "a".repeat(64),
+            timelock: 100,
+            amount: "1.0".to_string(),
+            metadata: None,
+            priority: Default::default(),
+            htlc_id: None,
+        };
+        let redeem_script = script_builder.build_htlc_script(&params).unwrap();
+        let p2sh = script_builder.p2sh_script_pubkey(&redeem_script);
+
+        let script_sig = bitcoin::blockdata::script::Builder::new()
+            .push_slice(&[0u8; 71])
+            .push_opcode(opcodes::OP_FALSE)
+            .push_slice(redeem_script.as_bytes())
+            .into_script();
+
+        let tx = Transaction {
+            version: 4,
+            lock_time: PackedLockTime(0),
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                script_sig,
+                sequence: Sequence(0xFFFFFFFF),
+                witness: Witness::default(),
+            }],
+            output: vec![TxOut {
+                value: 99_990_000,
+                script_pubkey: p2sh.clone(),
+            }],
+        };
+
+        let report = builder.tx_report(&tx, &[prevout("1.0", &p2sh)]).unwrap();
+        assert_eq!(report.size, v4_size(&tx));
+        assert_eq!(report.sigops, 2);
+        assert_eq!(report.fee, 10_000);
+        assert_eq!(report.fee_rate, 10_000 * 1000 / report.size as u64);
+    }
+
+    #[test]
+    fn test_tx_report_rejects_mismatched_prevouts() {
+        let builder = TransactionBuilder::new(ZcashNetwork::Testnet);
+        let tx = Transaction {
+            version: 4,
+            lock_time: PackedLockTime(0),
+            input: vec![],
+            output: vec![],
+        };
+
+        let result = builder.tx_report(&tx, &[prevout("1.0", &Script::new())]);
+        assert!(matches!(
+            result,
+            Err(TxBuilderError::MismatchedPrevouts { .. })
+        ));
+    }
+
+    #[test]
+    fn test_consolidation_sweeps_inputs_into_one_output() {
+        let builder = TransactionBuilder::new(ZcashNetwork::Testnet).with_fee_rate(2000);
+        let address =
+            HTLCScriptBuilder::new(ZcashNetwork::Testnet).pubkey_to_p2pkh_address(&[0x02; 33]);
+        let utxos: Vec<UTXO> = (0..3)
+            .map(|vout| UTXO {
+                vout,
+                ..prevout("0.001", &Script::new())
+            })
+            .collect();
+
+        let tx = builder.build_consolidation_tx(&utxos, &address).unwrap();
+        assert_eq!(tx.input.len(), 3);
+        assert_eq!(tx.output.len(), 1);
+        assert_eq!(tx.output[0].value, 300_000 - (29 + 3 * 180 + 34) * 2);
+        assert!(tx.output[0].script_pubkey.is_p2pkh());
+
+        assert!(matches!(
+            builder.build_consolidation_tx(&[prevout("0.000009", &Script::new())], &address),
+            Err(TxBuilderError::AmountTooSmall)
+        ));
+    }
+
+    #[test]
+    fn test_cpfp_pays_for_parent() {
+        let builder = TransactionBuilder::new(ZcashNetwork::Testnet);
+        let address =
+            HTLCScriptBuilder::new(ZcashNetwork::Testnet).pubkey_to_p2pkh_address(&[0x02; 33]);
+        let change = prevout("0.01", &Script::new());
+
+        // A package that paid 10 000 zat, bumped to a total of 40 000
+        let child = builder
+            .build_cpfp_tx(&change, 10_000, 40_000, &address)
+            .unwrap();
+        assert_eq!(child.input.len(), 1);
+        assert_eq!(child.output[0].value, 1_000_000 - 30_000);
+
+        // The child pays its own way at the least
+        let child = builder
+            .build_cpfp_tx(&change, 10_000, 12_000, &address)
+            .unwrap();
+        assert_eq!(child.output[0].value, 1_000_000 - 10_000);
+
+        assert!(matches!(
+            builder.build_cpfp_tx(&change, 40_000, 40_000, &address),
+            Err(TxBuilderError::FeeNotIncreased { .. })
+        ));
+    }
+
+    #[test]
+    fn test_conventional_fee_counts_logical_actions() {
+        let output = |n: usize| -> Vec<TxOut> {
+            (0..n)
+                .map(|_| TxOut {
+                    value: 1_000,
+                    script_pubkey: Script::from(vec![0x76; 25]),
+                })
+                .collect()
+        };
+        let unsigned_input = TxIn {
+            previous_output: OutPoint::default(),
+            script_sig: Script::new(),
+            sequence: Sequence(0xFFFFFFFF),
+            witness: Witness::default(),
+        };
+        let tx = |inputs: usize, outputs: usize| Transaction {
+            version: 4,
+            lock_time: PackedLockTime(0),
+            input: vec![unsigned_input.clone(); inputs],
+            output: output(outputs),
+        };
+
+        // The grace actions cover one input and output
+        assert_eq!(conventional_fee(&tx(1, 1)), 10_000);
+        assert_eq!(estimated_conventional_fee(1, 1), 10_000);
+        // 180 byte inputs take 1.2 actions each, 34 byte outputs one
+        assert_eq!(conventional_fee(&tx(5, 2)), 30_000);
+        assert_eq!(conventional_fee(&tx(1, 3)), 15_000);
+
+        assert_eq!(next_weight_ratio(10_000, 10_000), Some(2));
+        assert_eq!(next_weight_ratio(35_000, 10_000), Some(4));
+        assert_eq!(next_weight_ratio(40_000, 10_000), None);
+    }
+
+    #[test]
+    fn test_rebuild_with_higher_fee_reduces_change() {
+        let builder = TransactionBuilder::new(ZcashNetwork::Testnet);
+        let htlc_script = Script::from(vec![0xa9]);
+        let change_address = "tmBsTi2xWTjUdEXnuTceL7fecEQKeWaPDJd";
+        let change_script = builder.address_to_script_pubkey(change_address).unwrap();
+
+        let tx: ZcashTransaction = Transaction {
+            version: 4,
+            lock_time: PackedLockTime(0),
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                script_sig: Script::from(vec![0x51]),
+                sequence: Sequence(0xFFFFFFFF),
+                witness: Witness::default(),
+            }],
+            output: vec![
+                TxOut {
+                    value: 49_990_000,
+                    script_pubkey: change_script,
+                },
+                TxOut {
+                    value: 50_000_000,
+                    script_pubkey: htlc_script.clone(),
+                },
+            ],
+        }
+        .into();
+        let prevouts = [prevout("1.0", &Script::new())];
+
+        let bumped = builder
+            .rebuild_with_higher_fee(&tx, &prevouts, 30_000, change_address)
+            .unwrap();
+        assert_eq!(bumped.output[0].value, 49_970_000);
+        assert_eq!(bumped.output[1].value, 50_000_000);
+        assert!(bumped.input[0].script_sig.is_empty());
+        assert_eq!(builder.tx_report(&bumped, &prevouts).unwrap().fee, 30_000);
+
+        assert!(matches!(
+            builder.rebuild_with_higher_fee(&tx, &prevouts, 10_000, change_address),
+            Err(TxBuilderError::FeeNotIncreased { .. })
+        ));
+
+        let dropped = builder
+            .rebuild_with_higher_fee(&tx, &prevouts, 49_999_800, change_address)
+            .unwrap();
+        assert_eq!(dropped.output.len(), 1);
+        assert_eq!(dropped.output[0].script_pubkey, htlc_script);
+    }
+
+    #[test]
+    fn test_rebuild_with_higher_fee_requires_change_output() {
+        let builder = TransactionBuilder::new(ZcashNetwork::Testnet);
+        let tx: ZcashTransaction = Transaction {
+            version: 4,
+            lock_time: PackedLockTime(0),
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                script_sig: Script::new(),
+                sequence: Sequence(0xFFFFFFFF),
+                witness: Witness::default(),
+            }],
+            output: vec![TxOut {
+                value: 99_990_000,
+                script_pubkey: Script::from(vec![0xa9]),
+            }],
+        }
+        .into();
+        let prevouts = [prevout("1.0", &Script::new())];
+
+        // The HTLC output is never shrunk to pay the fee
+        assert!(matches!(
+            builder.rebuild_with_higher_fee(
+                &tx,
+                &prevouts,
+                20_000,
+                "tmBsTi2xWTjUdEXnuTceL7fecEQKeWaPDJd"
+            ),
+            Err(TxBuilderError::NoChangeOutput)
+        ));
+    }
 }
@@ -1,12 +1,26 @@
 use diesel::pg::PgConnection;
 use diesel::r2d2::{self, ConnectionManager, Pool, PoolError};
-use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
-use tracing::info;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tracing::{info, warn};
+
+use crate::{HTLCState, TenantContext};
+
+use super::encryption::ColumnCipher;
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
 
+/// Actor recorded in the audit log unless [`Database::with_actor`] is used
+pub const DEFAULT_AUDIT_ACTOR: &str = "zcash-htlc-builder";
+
 pub type DbPool = Pool<ConnectionManager<PgConnection>>;
 
+/// How long a lookup waits for a replica connection before falling back to
+/// the primary
+const REPLICA_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
 #[derive(Debug, thiserror::Error)]
 pub enum DatabaseError {
     #[error("Database connection error: {0}")]
@@ -21,13 +35,70 @@ pub enum DatabaseError {
     #[error("HTLC not found: {0}")]
     HTLCNotFound(String),
 
+    #[error("Hash lock {0} is already used by another HTLC on this network")]
+    DuplicateHashLock(String),
+
     #[error("Operation not found: {0}")]
     OperationNotFound(String),
+
+    #[error("Database subscription failed: {0}")]
+    SubscriptionError(String),
+
+    #[error("Database worker failed: {0}")]
+    WorkerError(String),
+
+    #[error("Insufficient UTXOs: {required} zatoshis required, {available} zatoshis available")]
+    InsufficientUtxos { required: u64, available: u64 },
+
+    #[error("Column encryption error: {0}")]
+    EncryptionError(String),
+
+    #[error("Export error: {0}")]
+    ExportError(String),
+
+    #[error("Unsupported export schema version: {0}")]
+    UnsupportedExportVersion(u32),
+
+    #[error(
+        "HTLC {htlc_id} was changed concurrently: expected version {expected}, found {actual}"
+    )]
+    VersionConflict {
+        htlc_id: String,
+        expected: u32,
+        actual: u32,
+    },
+
+    #[error("Search term too short: at least {0} characters are needed")]
+    SearchTermTooShort(usize),
+
+    #[error("Illegal transition of HTLC {htlc_id} from {from:?} to {to:?}")]
+    IllegalTransition {
+        htlc_id: String,
+        from: HTLCState,
+        to: HTLCState,
+    },
+
+    #[error("HTLC {htlc_id} cannot be deleted: it is {state:?} and may hold funds")]
+    NotDeletable { htlc_id: String, state: HTLCState },
 }
 
 #[derive(Clone)]
 pub struct Database {
     pool: DbPool,
+    /// Read-only replica serving lookups, if configured
+    replica: Option<DbPool>,
+    /// For connections outside the pool, e.g. to `LISTEN`
+    pub(crate) url: String,
+    /// One permit per pooled connection, bounding blocking workers
+    workers: Arc<Semaphore>,
+    /// Recorded in the audit log with every HTLC change
+    pub(crate) actor: String,
+    /// Encrypts HTLC secrets and signed redeem transactions at rest
+    pub(crate) cipher: Option<Arc<ColumnCipher>>,
+    /// Owner of every HTLC, operation and relayer UTXO read or written
+    pub(crate) tenant: TenantContext,
+    /// Replication lag beyond which [`Database::health_check`] fails
+    pub(crate) max_replication_lag: Option<Duration>,
 }
 
 impl Database {
@@ -35,7 +106,80 @@ impl Database {
         let manager = ConnectionManager::<PgConnection>::new(database_url);
         let pool = Pool::builder().max_size(max_connections).build(manager)?;
 
-        Ok(Database { pool })
+        Ok(Database {
+            pool,
+            replica: None,
+            url: database_url.to_string(),
+            workers: Arc::new(Semaphore::new(max_connections as usize)),
+            actor: DEFAULT_AUDIT_ACTOR.to_string(),
+            cipher: None,
+            tenant: TenantContext::default(),
+            max_replication_lag: None,
+        })
+    }
+
+    /// Name recorded as the actor of HTLC changes in the audit log, e.g.
+    /// `relayer` or an operator's name
+    pub fn with_actor(mut self, actor: &str) -> Self {
+        self.actor = actor.to_string();
+        self
+    }
+
+    /// Encrypt HTLC secrets and signed redeem transactions with `cipher`
+    /// before they are written, and decrypt them transparently on read
+    pub fn with_column_cipher(mut self, cipher: ColumnCipher) -> Self {
+        self.cipher = Some(Arc::new(cipher));
+        self
+    }
+
+    /// Serve lookups (`get_htlc_by_*`, `query_htlcs`, operation and audit
+    /// log reads) from the read-only replica at `replica_url`, falling back
+    /// to the primary while it is unreachable. Work the relayer picks up and
+    /// all writes stay on the primary, which never lags behind itself.
+    pub fn with_read_replica(mut self, replica_url: &str, max_connections: u32) -> Self {
+        let manager = ConnectionManager::<PgConnection>::new(replica_url);
+        // Unchecked, so a replica that is down does not stop startup
+        let pool = Pool::builder()
+            .max_size(max_connections)
+            .connection_timeout(REPLICA_CONNECT_TIMEOUT)
+            .build_unchecked(manager);
+        self.replica = Some(pool);
+        self
+    }
+
+    /// Scope all HTLC, operation and relayer UTXO queries to `tenant`. Rows
+    /// of other tenants sharing the database are neither read nor changed.
+    pub fn with_tenant(mut self, tenant: TenantContext) -> Self {
+        self.tenant = tenant;
+        self
+    }
+
+    pub fn tenant(&self) -> &TenantContext {
+        &self.tenant
+    }
+
+    /// Run blocking database `work` on tokio's blocking threads so it never
+    /// stalls the async runtime. At most one worker per pooled connection
+    /// runs at a time; further calls wait for a free one.
+    pub async fn run<T, F>(&self, work: F) -> Result<T, DatabaseError>
+    where
+        F: FnOnce(&Database) -> Result<T, DatabaseError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let permit = self
+            .workers
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|e| DatabaseError::WorkerError(e.to_string()))?;
+        let database = self.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            work(&database)
+        })
+        .await
+        .map_err(|e| DatabaseError::WorkerError(e.to_string()))?
     }
 
     pub fn get_connection(
@@ -44,11 +188,23 @@ impl Database {
         Ok(self.pool.get()?)
     }
 
+    /// Connection for reads that tolerate replication lag: from the replica
+    /// if one is configured and reachable, from the primary otherwise
+    pub fn get_read_connection(
+        &self,
+    ) -> Result<r2d2::PooledConnection<ConnectionManager<PgConnection>>, DatabaseError> {
+        if let Some(replica) = &self.replica {
+            match replica.get() {
+                Ok(conn) => return Ok(conn),
+                Err(e) => warn!("⚠️ Read replica unavailable, using primary: {}", e),
+            }
+        }
+        self.get_connection()
+    }
+
     pub fn run_migrations(&self) -> Result<(), DatabaseError> {
         info!("🔄 Running database migrations...");
-        let mut conn = self.get_connection()?;
-        conn.run_pending_migrations(MIGRATIONS)
-            .map_err(|e| DatabaseError::MigrationError(e.to_string()))?;
+        self.apply_migrations(false)?;
         info!("✅ Migrations completed");
         Ok(())
     }
@@ -0,0 +1,104 @@
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use rand::RngCore;
+
+use super::connections::DatabaseError;
+
+/// Marks a column value encrypted by [`ColumnCipher`], followed by the hex
+/// nonce and ciphertext
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 12;
+
+/// AES-256-GCM encryption of sensitive HTLC columns (the secret and the
+/// signed redeem transaction). Values written before a key was configured
+/// stay readable as plaintext and are encrypted on their next update.
+pub struct ColumnCipher {
+    cipher: Aes256Gcm,
+}
+
+impl ColumnCipher {
+    /// `key` must be 32 bytes
+    pub fn new(key: &[u8]) -> Result<Self, DatabaseError> {
+        let cipher = Aes256Gcm::new_from_slice(key)
+            .map_err(|_| DatabaseError::EncryptionError("key must be 32 bytes".to_string()))?;
+        Ok(Self { cipher })
+    }
+
+    pub fn from_hex(key_hex: &str) -> Result<Self, DatabaseError> {
+        let key = zeroize::Zeroizing::new(
+            hex::decode(key_hex).map_err(|e| DatabaseError::EncryptionError(e.to_string()))?,
+        );
+        Self::new(&key)
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, DatabaseError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let ciphertext = self
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+            .map_err(|_| DatabaseError::EncryptionError("encryption failed".to_string()))?;
+
+        Ok(format!(
+            "{}{}{}",
+            ENCRYPTED_PREFIX,
+            hex::encode(nonce),
+            hex::encode(ciphertext)
+        ))
+    }
+
+    /// Plaintext of `value`, passing through values that were never encrypted
+    pub fn decrypt(&self, value: &str) -> Result<String, DatabaseError> {
+        let Some(sealed) = value.strip_prefix(ENCRYPTED_PREFIX) else {
+            return Ok(value.to_string());
+        };
+
+        let sealed =
+            hex::decode(sealed).map_err(|e| DatabaseError::EncryptionError(e.to_string()))?;
+        if sealed.len() < NONCE_LEN {
+            return Err(DatabaseError::EncryptionError(
+                "ciphertext too short".to_string(),
+            ));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| {
+                DatabaseError::EncryptionError("wrong key or corrupt value".to_string())
+            })?;
+        String::from_utf8(plaintext).map_err(|e| DatabaseError::EncryptionError(e.to_string()))
+    }
+
+    pub fn is_encrypted(value: &str) -> bool {
+        value.starts_with(ENCRYPTED_PREFIX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_column_cipher_round_trip() {
+        let cipher = ColumnCipher::new(&[7u8; 32]).unwrap();
+
+        let sealed = cipher.encrypt("6d792d736563726574").unwrap();
+        assert!(ColumnCipher::is_encrypted(&sealed));
+        assert!(!sealed.contains("6d792d736563726574"));
+        assert_ne!(sealed, cipher.encrypt("6d792d736563726574").unwrap());
+        assert_eq!(cipher.decrypt(&sealed).unwrap(), "6d792d736563726574");
+
+        // Rows written before encryption was enabled
+        assert_eq!(cipher.decrypt("0400008085").unwrap(), "0400008085");
+
+        let other = ColumnCipher::new(&[8u8; 32]).unwrap();
+        assert!(matches!(
+            other.decrypt(&sealed),
+            Err(DatabaseError::EncryptionError(_))
+        ));
+        assert!(ColumnCipher::new(&[7u8; 16]).is_err());
+    }
+}
@@ -0,0 +1,282 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+use crate::{
+    FeePriority, HTLCOperation, HTLCSecret, HTLCState, TimelockKind, ZcashHTLC, ZcashNetwork,
+};
+
+use super::connections::DatabaseError;
+
+/// Version written into every export. Bump it whenever the layout of
+/// [`HTLCRecord`] changes, and keep importing older versions.
+pub const EXPORT_SCHEMA_VERSION: u32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    /// One row per HTLC, its operations as a JSON array in the last column
+    Csv,
+}
+
+/// An HTLC with all of its operations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HTLCRecord {
+    pub htlc: ZcashHTLC,
+    pub operations: Vec<HTLCOperation>,
+}
+
+/// HTLC records as moved between databases by
+/// [`Database::export_htlcs`](super::Database::export_htlcs) and
+/// [`Database::import_htlcs`](super::Database::import_htlcs)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HTLCExport {
+    pub schema_version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub records: Vec<HTLCRecord>,
+}
+
+impl HTLCExport {
+    pub fn new(records: Vec<HTLCRecord>) -> Self {
+        Self {
+            schema_version: EXPORT_SCHEMA_VERSION,
+            exported_at: Utc::now(),
+            records,
+        }
+    }
+
+    pub fn write<W: Write>(&self, format: ExportFormat, writer: W) -> Result<(), DatabaseError> {
+        match format {
+            ExportFormat::Json => serde_json::to_writer_pretty(writer, self).map_err(export_error),
+            ExportFormat::Csv => {
+                let mut csv = csv::Writer::from_writer(writer);
+                for record in &self.records {
+                    csv.serialize(CsvRecord::new(self.schema_version, record)?)
+                        .map_err(export_error)?;
+                }
+                csv.flush().map_err(export_error)
+            }
+        }
+    }
+
+    /// Parse an export, refusing versions newer than this build understands
+    pub fn read<R: Read>(format: ExportFormat, reader: R) -> Result<Self, DatabaseError> {
+        let export = match format {
+            ExportFormat::Json => serde_json::from_reader(reader).map_err(export_error)?,
+            ExportFormat::Csv => {
+                let mut schema_version = EXPORT_SCHEMA_VERSION;
+                let mut records = Vec::new();
+                for row in csv::Reader::from_reader(reader).deserialize::<CsvRecord>() {
+                    let row = row.map_err(export_error)?;
+                    schema_version = schema_version.max(row.schema_version);
+                    records.push(row.into_record()?);
+                }
+
+                HTLCExport {
+                    schema_version,
+                    exported_at: Utc::now(),
+                    records,
+                }
+            }
+        };
+
+        if export.schema_version > EXPORT_SCHEMA_VERSION {
+            return Err(DatabaseError::UnsupportedExportVersion(
+                export.schema_version,
+            ));
+        }
+        Ok(export)
+    }
+}
+
+/// Flat form of an [`HTLCRecord`], as the csv crate cannot nest structs
+#[derive(Serialize, Deserialize)]
+struct CsvRecord {
+    schema_version: u32,
+    id: String,
+    txid: Option<String>,
+    p2sh_address: String,
+    hash_lock: String,
+    secret: Option<HTLCSecret>,
+    timelock: u64,
+    recipient_pubkey: String,
+    refund_pubkey: String,
+    amount: String,
+    network: ZcashNetwork,
+    state: HTLCState,
+    vout: Option<u32>,
+    script_hex: String,
+    redeem_script_hex: String,
+    signed_redeem_tx: Option<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    /// As JSON; operations carry retry tracking from schema version 6
+    operations: String,
+    /// Added in schema version 2
+    #[serde(default)]
+    version: u32,
+    /// Added in schema version 3, as JSON
+    #[serde(default)]
+    metadata: Option<String>,
+    /// Added in schema version 4
+    #[serde(default)]
+    recipient_address: Option<String>,
+    /// Added in schema version 5; derived from `timelock` when missing
+    #[serde(default)]
+    timelock_kind: Option<TimelockKind>,
+    /// Added in schema version 7
+    #[serde(default)]
+    deleted_at: Option<DateTime<Utc>>,
+    /// Added in schema version 10
+    #[serde(default)]
+    priority: FeePriority,
+}
+
+impl CsvRecord {
+    fn new(schema_version: u32, record: &HTLCRecord) -> Result<Self, DatabaseError> {
+        let htlc = record.htlc.clone();
+        Ok(Self {
+            schema_version,
+            id: htlc.id,
+            txid: htlc.txid,
+            p2sh_address: htlc.p2sh_address,
+            hash_lock: htlc.hash_lock,
+            secret: htlc.secret,
+            timelock: htlc.timelock,
+            timelock_kind: Some(htlc.timelock_kind),
+            recipient_pubkey: htlc.recipient_pubkey,
+            refund_pubkey: htlc.refund_pubkey,
+            amount: htlc.amount,
+            network: htlc.network,
+            state: htlc.state,
+            vout: htlc.vout,
+            script_hex: htlc.script_hex,
+            redeem_script_hex: htlc.redeem_script_hex,
+            recipient_address: htlc.recipient_address,
+            signed_redeem_tx: htlc.signed_redeem_tx,
+            created_at: htlc.created_at,
+            updated_at: htlc.updated_at,
+            version: htlc.version,
+            deleted_at: htlc.deleted_at,
+            priority: htlc.priority,
+            metadata: htlc
+                .metadata
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()
+                .map_err(export_error)?,
+            operations: serde_json::to_string(&record.operations).map_err(export_error)?,
+        })
+    }
+
+    fn into_record(self) -> Result<HTLCRecord, DatabaseError> {
+        Ok(HTLCRecord {
+            operations: serde_json::from_str(&self.operations).map_err(export_error)?,
+            htlc: ZcashHTLC {
+                id: self.id,
+                txid: self.txid,
+                p2sh_address: self.p2sh_address,
+                hash_lock: self.hash_lock,
+                secret: self.secret,
+                timelock: self.timelock,
+                timelock_kind: self
+                    .timelock_kind
+                    .unwrap_or_else(|| TimelockKind::for_timelock(self.timelock)),
+                recipient_pubkey: self.recipient_pubkey,
+                refund_pubkey: self.refund_pubkey,
+                amount: self.amount,
+                network: self.network,
+                state: self.state,
+                vout: self.vout,
+                script_hex: self.script_hex,
+                redeem_script_hex: self.redeem_script_hex,
+                recipient_address: self.recipient_address,
+                signed_redeem_tx: self.signed_redeem_tx,
+                created_at: self.created_at,
+                updated_at: self.updated_at,
+                version: self.version,
+                deleted_at: self.deleted_at,
+                priority: self.priority,
+                metadata: self
+                    .metadata
+                    .as_deref()
+                    .map(serde_json::from_str)
+                    .transpose()
+                    .map_err(export_error)?,
+            },
+        })
+    }
+}
+
+fn export_error(e: impl std::fmt::Display) -> DatabaseError {
+    DatabaseError::ExportError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{test_htlc, test_operation};
+    use crate::{HTLCOperationType, OperationStatus};
+
+    fn sample_record() -> HTLCRecord {
+        let now = Utc::now();
+        HTLCRecord {
+            htlc: ZcashHTLC {
+                txid: Some("ab".repeat(32)),
+                state: HTLCState::Locked,
+                vout: Some(0),
+                recipient_address: Some("tmBsTi2xWTjUdEXnuTceL7fecEQKeWaPDJd".to_string()),
+                version: 3,
+                metadata: Some(serde_json::json!({"order_id": "A-17"})),
+                ..test_htlc("htlc-1")
+            },
+            operations: vec![HTLCOperation {
+                txid: Some("ab".repeat(32)),
+                signed_tx_hex: None,
+                broadcast_at: Some(now),
+                status: OperationStatus::Broadcast,
+                attempt_count: 1,
+                last_attempt_at: Some(now),
+                ..test_operation("op-1", "htlc-1", HTLCOperationType::Create)
+            }],
+        }
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let export = HTLCExport::new(vec![sample_record()]);
+
+        for format in [ExportFormat::Json, ExportFormat::Csv] {
+            let mut buf = Vec::new();
+            export.write(format, &mut buf).unwrap();
+
+            let imported = HTLCExport::read(format, buf.as_slice()).unwrap();
+            assert_eq!(imported.schema_version, EXPORT_SCHEMA_VERSION);
+            assert_eq!(imported.records.len(), 1);
+            let record = &imported.records[0];
+            assert_eq!(record.htlc.id, "htlc-1");
+            assert_eq!(record.htlc.state, HTLCState::Locked);
+            assert_eq!(record.htlc.vout, Some(0));
+            assert_eq!(record.htlc.version, 3);
+            assert_eq!(
+                record.htlc.recipient_address.as_deref(),
+                Some("tmBsTi2xWTjUdEXnuTceL7fecEQKeWaPDJd")
+            );
+            assert_eq!(
+                record.htlc.metadata,
+                Some(serde_json::json!({"order_id": "A-17"}))
+            );
+            assert!(record.htlc.secret.is_none());
+            assert_eq!(record.operations[0].status, OperationStatus::Broadcast);
+            assert_eq!(record.operations[0].attempt_count, 1);
+        }
+
+        let mut future = export;
+        future.schema_version = EXPORT_SCHEMA_VERSION + 1;
+        let json = serde_json::to_vec(&future).unwrap();
+        assert!(matches!(
+            HTLCExport::read(ExportFormat::Json, json.as_slice()),
+            Err(DatabaseError::UnsupportedExportVersion(_))
+        ));
+    }
+}
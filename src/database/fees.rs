@@ -0,0 +1,95 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Double, Text, Timestamptz};
+use serde::Serialize;
+
+use crate::HTLCOperationType;
+
+use super::connections::{Database, DatabaseError};
+
+/// Fees of the operations that reached the chain, per operation type
+const FEE_SUMMARY_QUERY: &str = "SELECT operation_type, \
+        COUNT(*) AS operations, \
+        SUM(fee_zat)::int8 AS total_fee_zat, \
+        AVG(tx_size)::float8 AS avg_tx_size, \
+        AVG(fee_rate)::float8 AS avg_fee_rate, \
+        (percentile_cont(0.5) WITHIN GROUP (ORDER BY fee_rate))::float8 AS median_fee_rate, \
+        MIN(fee_rate) AS min_fee_rate, \
+        MAX(fee_rate) AS max_fee_rate \
+    FROM htlc_operations \
+    WHERE tenant_id = $1 AND fee_zat IS NOT NULL \
+        AND status IN ('broadcast', 'confirmed') \
+        AND created_at >= $2 AND created_at < $3 \
+    GROUP BY operation_type \
+    ORDER BY operation_type";
+
+/// Fee spend of one operation type, from [`Database::get_fee_summary`].
+/// Fee rates are in zatoshis per 1000 bytes.
+#[derive(Debug, Clone, Serialize)]
+pub struct FeeSummary {
+    pub operation_type: HTLCOperationType,
+    pub operations: u64,
+    pub total_fee_zat: u64,
+    pub avg_tx_size: f64,
+    pub avg_fee_rate: f64,
+    pub median_fee_rate: f64,
+    pub min_fee_rate: u64,
+    pub max_fee_rate: u64,
+}
+
+#[derive(QueryableByName)]
+struct FeeSummaryRow {
+    #[diesel(sql_type = Text)]
+    operation_type: String,
+    #[diesel(sql_type = BigInt)]
+    operations: i64,
+    #[diesel(sql_type = BigInt)]
+    total_fee_zat: i64,
+    #[diesel(sql_type = Double)]
+    avg_tx_size: f64,
+    #[diesel(sql_type = Double)]
+    avg_fee_rate: f64,
+    #[diesel(sql_type = Double)]
+    median_fee_rate: f64,
+    #[diesel(sql_type = BigInt)]
+    min_fee_rate: i64,
+    #[diesel(sql_type = BigInt)]
+    max_fee_rate: i64,
+}
+
+impl Database {
+    /// Fees paid by operations created in `[since, until)` that were
+    /// broadcast, grouped by operation type, from the read replica if one
+    /// is configured. Operations signed before fees were recorded are left
+    /// out.
+    pub fn get_fee_summary(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<FeeSummary>, DatabaseError> {
+        let mut conn = self.get_read_connection()?;
+
+        let rows = diesel::sql_query(FEE_SUMMARY_QUERY)
+            .bind::<Text, _>(self.tenant.id())
+            .bind::<Timestamptz, _>(since)
+            .bind::<Timestamptz, _>(until)
+            .load::<FeeSummaryRow>(&mut conn)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| FeeSummary {
+                operation_type: row
+                    .operation_type
+                    .parse()
+                    .unwrap_or(HTLCOperationType::Create),
+                operations: row.operations as u64,
+                total_fee_zat: row.total_fee_zat as u64,
+                avg_tx_size: row.avg_tx_size,
+                avg_fee_rate: row.avg_fee_rate,
+                median_fee_rate: row.median_fee_rate,
+                min_fee_rate: row.min_fee_rate as u64,
+                max_fee_rate: row.max_fee_rate as u64,
+            })
+            .collect())
+    }
+}
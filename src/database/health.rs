@@ -0,0 +1,80 @@
+use diesel::prelude::*;
+use diesel::sql_types::{Double, Nullable};
+use diesel_migrations::MigrationHarness;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+use super::connections::{Database, DatabaseError, MIGRATIONS};
+
+/// Seconds since the last replayed transaction on a standby that has not
+/// caught up with its primary; 0 once caught up and NULL on a primary
+const REPLICATION_LAG_QUERY: &str = "SELECT CASE \
+        WHEN pg_last_wal_receive_lsn() = pg_last_wal_replay_lsn() THEN 0 \
+        ELSE EXTRACT(EPOCH FROM now() - pg_last_xact_replay_timestamp()) \
+    END::float8 AS lag";
+
+/// Result of [`Database::health_check`]
+#[derive(Debug, Clone, Serialize)]
+pub struct DatabaseHealth {
+    /// No pending migrations and replication lag within the configured bound
+    pub healthy: bool,
+    /// Round trip of a trivial query over a pooled connection
+    pub latency_ms: u64,
+    /// Migrations embedded in this build that have not run yet
+    pub pending_migrations: Vec<String>,
+    /// Only checked with [`Database::with_max_replication_lag`]; `None` on a
+    /// primary
+    pub replication_lag_secs: Option<f64>,
+}
+
+#[derive(QueryableByName)]
+struct ReplicationLag {
+    #[diesel(sql_type = Nullable<Double>)]
+    lag: Option<f64>,
+}
+
+impl Database {
+    /// Report unhealthy when a standby `database_url` lags its primary by
+    /// more than `max_lag`
+    pub fn with_max_replication_lag(mut self, max_lag: Duration) -> Self {
+        self.max_replication_lag = Some(max_lag);
+        self
+    }
+
+    /// Check that a pooled connection answers, all migrations have run and,
+    /// if a bound is configured, replication lag stays within it. An
+    /// unreachable database fails; everything else is reported.
+    pub fn health_check(&self) -> Result<DatabaseHealth, DatabaseError> {
+        let started = Instant::now();
+        let mut conn = self.get_connection()?;
+        diesel::sql_query("SELECT 1").execute(&mut conn)?;
+        let latency = started.elapsed();
+
+        let pending_migrations: Vec<String> = conn
+            .pending_migrations(MIGRATIONS)
+            .map_err(|e| DatabaseError::MigrationError(e.to_string()))?
+            .iter()
+            .map(|migration| migration.name().to_string())
+            .collect();
+
+        let replication_lag_secs = match self.max_replication_lag {
+            Some(_) => {
+                diesel::sql_query(REPLICATION_LAG_QUERY)
+                    .get_result::<ReplicationLag>(&mut conn)?
+                    .lag
+            }
+            None => None,
+        };
+        let lagging = match (replication_lag_secs, self.max_replication_lag) {
+            (Some(lag), Some(max_lag)) => lag > max_lag.as_secs_f64(),
+            _ => false,
+        };
+
+        Ok(DatabaseHealth {
+            healthy: pending_migrations.is_empty() && !lagging,
+            latency_ms: latency.as_millis() as u64,
+            pending_migrations,
+            replication_lag_secs,
+        })
+    }
+}
@@ -0,0 +1,388 @@
+use chrono::{DateTime, Utc};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Double, Integer, Nullable, SmallInt, Text, Timestamptz};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::models::schema::relayer_jobs::dsl;
+use crate::FeePriority;
+
+use super::connections::{Database, DatabaseError};
+
+/// Queue a job for an HTLC at the HTLC's priority, unless one of the same
+/// type is already queued or claimed for it
+const ENQUEUE_HTLC_JOB_QUERY: &str = "INSERT INTO relayer_jobs \
+        (tenant_id, job_type, payload, dedupe_key, priority) \
+    SELECT tenant_id, $2, json_build_object('htlc_id', id)::text, id, priority \
+    FROM zcash_htlcs WHERE tenant_id = $1 AND id = $3 \
+    ON CONFLICT (tenant_id, job_type, dedupe_key) WHERE state IN ('queued', 'claimed') \
+    DO NOTHING";
+
+/// Queue the jobs an HTLC's state calls for, each unless one of its type
+/// is already queued or claimed: funding while it is Pending without a
+/// funding transaction, and once it is Locked, broadcasting its stored
+/// redeem and refunding it, which waits for the timelock
+const ENQUEUE_DUE_JOBS_QUERY: &str = "INSERT INTO relayer_jobs \
+        (tenant_id, job_type, payload, dedupe_key, priority) \
+    SELECT tenant_id, due.job_type, json_build_object('htlc_id', id)::text, id, priority \
+    FROM zcash_htlcs \
+    CROSS JOIN LATERAL (VALUES \
+        (CASE WHEN state = 0 AND txid IS NULL THEN 'fund_htlc' END), \
+        (CASE WHEN state = 1 AND signed_redeem_tx IS NOT NULL THEN 'broadcast_redeem' END), \
+        (CASE WHEN state = 1 THEN 'refund_htlc' END)) AS due (job_type) \
+    WHERE tenant_id = $1 AND id = $2 AND deleted_at IS NULL AND due.job_type IS NOT NULL \
+    ON CONFLICT (tenant_id, job_type, dedupe_key) WHERE state IN ('queued', 'claimed') \
+    DO NOTHING";
+
+/// Claim due jobs of one type, most urgent first, including claimed ones
+/// whose worker let the visibility timeout pass. Rows another worker is
+/// claiming are skipped rather than waited for.
+const CLAIM_JOBS_QUERY: &str = "UPDATE relayer_jobs SET \
+        state = 'claimed', \
+        claimed_by = $3, \
+        visible_at = NOW() + make_interval(secs => $4), \
+        updated_at = NOW() \
+    WHERE id IN ( \
+        SELECT id FROM relayer_jobs \
+        WHERE tenant_id = $1 AND job_type = $2 \
+            AND state IN ('queued', 'claimed') AND visible_at <= NOW() \
+        ORDER BY priority DESC, id \
+        LIMIT $5 \
+        FOR UPDATE SKIP LOCKED) \
+    RETURNING id, payload, priority, attempts, last_error, created_at";
+
+/// Kind of work a [`Job`] asks the relayer for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobType {
+    /// Fund an HTLC that became Pending without a funding transaction
+    FundHtlc,
+    /// Broadcast a redeem stored by [`Database::store_signed_redeem_tx`]
+    /// once its HTLC is locked
+    BroadcastRedeem,
+    /// Refund an HTLC once its timelock has passed, queued when it locks
+    RefundHtlc,
+}
+
+impl JobType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobType::FundHtlc => "fund_htlc",
+            JobType::BroadcastRedeem => "broadcast_redeem",
+            JobType::RefundHtlc => "refund_htlc",
+        }
+    }
+}
+
+/// Job claimed with [`Database::claim_jobs`]. Finish it with
+/// [`Database::complete_job`], [`Database::retry_job`] or
+/// [`Database::fail_job`] before the visibility timeout, or another worker
+/// claims it again.
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    pub id: i64,
+    pub job_type: JobType,
+    pub payload: serde_json::Value,
+    pub priority: FeePriority,
+    /// Failed attempts so far
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Job {
+    /// HTLC the job is for, if any
+    pub fn htlc_id(&self) -> Option<&str> {
+        self.payload.get("htlc_id").and_then(|id| id.as_str())
+    }
+}
+
+#[derive(QueryableByName)]
+struct ClaimedJobRow {
+    #[diesel(sql_type = BigInt)]
+    id: i64,
+    #[diesel(sql_type = Text)]
+    payload: String,
+    #[diesel(sql_type = SmallInt)]
+    priority: i16,
+    #[diesel(sql_type = Integer)]
+    attempts: i32,
+    #[diesel(sql_type = Nullable<Text>)]
+    last_error: Option<String>,
+    #[diesel(sql_type = Timestamptz)]
+    created_at: DateTime<Utc>,
+}
+
+impl Database {
+    /// Queue a `job_type` job for `htlc_id`. Returns `false` if one is
+    /// already queued or claimed for it.
+    pub fn enqueue_htlc_job(
+        &self,
+        job_type: JobType,
+        htlc_id: &str,
+    ) -> Result<bool, DatabaseError> {
+        let mut conn = self.get_connection()?;
+        let queued = enqueue_htlc_job(&mut conn, self.tenant.id(), job_type, htlc_id)?;
+
+        if queued > 0 {
+            info!("📥 Queued {} job for HTLC {}", job_type.as_str(), htlc_id);
+        }
+        Ok(queued > 0)
+    }
+
+    /// Claim up to `limit` due `job_type` jobs for `worker`, hiding them
+    /// from other workers for `visibility_timeout`
+    pub fn claim_jobs(
+        &self,
+        job_type: JobType,
+        worker: &str,
+        visibility_timeout: Duration,
+        limit: u32,
+    ) -> Result<Vec<Job>, DatabaseError> {
+        let mut conn = self.get_connection()?;
+
+        let mut rows = diesel::sql_query(CLAIM_JOBS_QUERY)
+            .bind::<Text, _>(self.tenant.id())
+            .bind::<Text, _>(job_type.as_str())
+            .bind::<Text, _>(worker)
+            .bind::<Double, _>(visibility_timeout.as_secs_f64())
+            .bind::<BigInt, _>(limit as i64)
+            .load::<ClaimedJobRow>(&mut conn)?;
+        // RETURNING does not keep the subquery's order
+        rows.sort_by_key(|row| (std::cmp::Reverse(row.priority), row.id));
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Job {
+                id: row.id,
+                job_type,
+                payload: serde_json::from_str(&row.payload).unwrap_or_default(),
+                priority: FeePriority::from_i16(row.priority),
+                attempts: row.attempts as u32,
+                last_error: row.last_error,
+                created_at: row.created_at,
+            })
+            .collect())
+    }
+
+    /// Delete a job `worker` finished. Returns `false` if its claim had
+    /// already passed to another worker.
+    pub fn complete_job(&self, job_id: i64, worker: &str) -> Result<bool, DatabaseError> {
+        let mut conn = self.get_connection()?;
+
+        let deleted = diesel::delete(
+            dsl::relayer_jobs
+                .filter(dsl::tenant_id.eq(self.tenant.id()))
+                .filter(dsl::id.eq(job_id))
+                .filter(dsl::state.eq("claimed"))
+                .filter(dsl::claimed_by.eq(worker)),
+        )
+        .execute(&mut conn)?;
+        if deleted == 0 {
+            warn!("⚠️  Job {} was no longer claimed by {}", job_id, worker);
+        }
+        Ok(deleted > 0)
+    }
+
+    /// Extend `worker`'s claim on a job to `visibility_timeout` from now,
+    /// e.g. while slow work on it goes on. Returns `false` if the claim
+    /// already passed to another worker, which must then be left the job.
+    pub fn renew_job(
+        &self,
+        job_id: i64,
+        worker: &str,
+        visibility_timeout: Duration,
+    ) -> Result<bool, DatabaseError> {
+        let mut conn = self.get_connection()?;
+
+        let now = Utc::now();
+        let timeout = chrono::Duration::seconds(visibility_timeout.as_secs() as i64);
+        let renewed = diesel::update(
+            dsl::relayer_jobs
+                .filter(dsl::tenant_id.eq(self.tenant.id()))
+                .filter(dsl::id.eq(job_id))
+                .filter(dsl::state.eq("claimed"))
+                .filter(dsl::claimed_by.eq(worker)),
+        )
+        .set((dsl::visible_at.eq(now + timeout), dsl::updated_at.eq(now)))
+        .execute(&mut conn)?;
+
+        Ok(renewed > 0)
+    }
+
+    /// Queue a job that is not due yet again for `until`, without counting
+    /// an attempt
+    pub fn defer_job(
+        &self,
+        job_id: i64,
+        worker: &str,
+        until: DateTime<Utc>,
+    ) -> Result<bool, DatabaseError> {
+        let mut conn = self.get_connection()?;
+
+        let deferred = diesel::update(
+            dsl::relayer_jobs
+                .filter(dsl::tenant_id.eq(self.tenant.id()))
+                .filter(dsl::id.eq(job_id))
+                .filter(dsl::state.eq("claimed"))
+                .filter(dsl::claimed_by.eq(worker)),
+        )
+        .set((
+            dsl::state.eq("queued"),
+            dsl::claimed_by.eq(None::<String>),
+            dsl::visible_at.eq(until),
+            dsl::updated_at.eq(Utc::now()),
+        ))
+        .execute(&mut conn)?;
+
+        Ok(deferred > 0)
+    }
+
+    /// Record a failed attempt at a job and queue it again after `delay`.
+    /// Returns the number of failed attempts.
+    pub fn retry_job(
+        &self,
+        job_id: i64,
+        worker: &str,
+        error: &str,
+        delay: chrono::Duration,
+    ) -> Result<u32, DatabaseError> {
+        let mut conn = self.get_connection()?;
+
+        let now = Utc::now();
+        let attempts: Option<i32> = diesel::update(
+            dsl::relayer_jobs
+                .filter(dsl::tenant_id.eq(self.tenant.id()))
+                .filter(dsl::id.eq(job_id))
+                .filter(dsl::state.eq("claimed"))
+                .filter(dsl::claimed_by.eq(worker)),
+        )
+        .set((
+            dsl::state.eq("queued"),
+            dsl::claimed_by.eq(None::<String>),
+            dsl::attempts.eq(dsl::attempts + 1),
+            dsl::last_error.eq(error),
+            dsl::visible_at.eq(now + delay),
+            dsl::updated_at.eq(now),
+        ))
+        .returning(dsl::attempts)
+        .get_result(&mut conn)
+        .optional()?;
+
+        Ok(attempts.unwrap_or_default() as u32)
+    }
+
+    /// Give up on a job after a final failure; it stays in the table as
+    /// `failed` for inspection
+    pub fn fail_job(&self, job_id: i64, worker: &str, error: &str) -> Result<(), DatabaseError> {
+        let mut conn = self.get_connection()?;
+
+        diesel::update(
+            dsl::relayer_jobs
+                .filter(dsl::tenant_id.eq(self.tenant.id()))
+                .filter(dsl::id.eq(job_id))
+                .filter(dsl::state.eq("claimed"))
+                .filter(dsl::claimed_by.eq(worker)),
+        )
+        .set((
+            dsl::state.eq("failed"),
+            dsl::claimed_by.eq(None::<String>),
+            dsl::attempts.eq(dsl::attempts + 1),
+            dsl::last_error.eq(error),
+            dsl::updated_at.eq(Utc::now()),
+        ))
+        .execute(&mut conn)?;
+
+        warn!("🪦 Job {} failed: {}", job_id, error);
+        Ok(())
+    }
+
+    /// Hand every job `worker` still claims back to the queue, due at once,
+    /// without counting an attempt. Returns how many were released.
+    pub fn release_jobs(&self, worker: &str) -> Result<usize, DatabaseError> {
+        let mut conn = self.get_connection()?;
+
+        let now = Utc::now();
+        let released = diesel::update(
+            dsl::relayer_jobs
+                .filter(dsl::tenant_id.eq(self.tenant.id()))
+                .filter(dsl::state.eq("claimed"))
+                .filter(dsl::claimed_by.eq(worker)),
+        )
+        .set((
+            dsl::state.eq("queued"),
+            dsl::claimed_by.eq(None::<String>),
+            dsl::visible_at.eq(now),
+            dsl::updated_at.eq(now),
+        ))
+        .execute(&mut conn)?;
+
+        Ok(released)
+    }
+}
+
+/// Queue a `job_type` job for `htlc_id` on `conn`, e.g. in the transaction
+/// creating the work
+pub(crate) fn enqueue_htlc_job(
+    conn: &mut PgConnection,
+    tenant_id: &str,
+    job_type: JobType,
+    htlc_id: &str,
+) -> QueryResult<usize> {
+    diesel::sql_query(ENQUEUE_HTLC_JOB_QUERY)
+        .bind::<Text, _>(tenant_id)
+        .bind::<Text, _>(job_type.as_str())
+        .bind::<Text, _>(htlc_id)
+        .execute(conn)
+}
+
+/// Queue on `conn` the jobs the current state of `htlc_id` calls for, see
+/// [`ENQUEUE_DUE_JOBS_QUERY`], e.g. in the transaction changing it
+pub(crate) fn enqueue_due_jobs(
+    conn: &mut PgConnection,
+    tenant_id: &str,
+    htlc_id: &str,
+) -> QueryResult<usize> {
+    diesel::sql_query(ENQUEUE_DUE_JOBS_QUERY)
+        .bind::<Text, _>(tenant_id)
+        .bind::<Text, _>(htlc_id)
+        .execute(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_type_names_match_serde() {
+        for job_type in [
+            JobType::FundHtlc,
+            JobType::BroadcastRedeem,
+            JobType::RefundHtlc,
+        ] {
+            assert_eq!(serde_json::to_value(job_type).unwrap(), job_type.as_str());
+        }
+    }
+
+    #[test]
+    fn test_job_htlc_id_from_payload() {
+        let job = Job {
+            id: 1,
+            job_type: JobType::FundHtlc,
+            payload: serde_json::json!({"htlc_id": "htlc-1"}),
+            priority: FeePriority::Normal,
+            attempts: 0,
+            last_error: None,
+            created_at: Utc::now(),
+        };
+        assert_eq!(job.htlc_id(), Some("htlc-1"));
+
+        let job = Job {
+            payload: serde_json::json!({}),
+            ..job
+        };
+        assert_eq!(job.htlc_id(), None);
+    }
+}
@@ -0,0 +1,68 @@
+use diesel::prelude::*;
+use diesel::sql_types::{Double, Text};
+use std::time::Duration;
+
+use crate::models::schema::relayer_leases;
+
+use super::connections::{Database, DatabaseError};
+
+/// Take the lease if it is free, expired or already ours, extending it.
+/// Expiry is judged by the database clock, so replicas need not agree on
+/// the time.
+const ACQUIRE_LEASE_QUERY: &str = "INSERT INTO relayer_leases \
+        (tenant_id, name, holder, acquired_at, expires_at) \
+    VALUES ($1, $2, $3, NOW(), NOW() + make_interval(secs => $4)) \
+    ON CONFLICT (tenant_id, name) DO UPDATE SET \
+        holder = EXCLUDED.holder, \
+        expires_at = EXCLUDED.expires_at, \
+        acquired_at = CASE WHEN relayer_leases.holder = EXCLUDED.holder \
+            THEN relayer_leases.acquired_at ELSE EXCLUDED.acquired_at END \
+    WHERE relayer_leases.holder = EXCLUDED.holder OR relayer_leases.expires_at < NOW() \
+    RETURNING holder";
+
+#[derive(QueryableByName)]
+struct LeaseHolder {
+    #[diesel(sql_type = Text)]
+    #[allow(dead_code)]
+    holder: String,
+}
+
+impl Database {
+    /// Acquire or renew the lease `name` for `holder` until `ttl` from now.
+    /// Returns whether `holder` holds it; only one holder at a time does
+    /// until its lease runs out unrenewed.
+    pub fn try_acquire_lease(
+        &self,
+        name: &str,
+        holder: &str,
+        ttl: Duration,
+    ) -> Result<bool, DatabaseError> {
+        let mut conn = self.get_connection()?;
+
+        let acquired = diesel::sql_query(ACQUIRE_LEASE_QUERY)
+            .bind::<Text, _>(self.tenant.id())
+            .bind::<Text, _>(name)
+            .bind::<Text, _>(holder)
+            .bind::<Double, _>(ttl.as_secs_f64())
+            .get_result::<LeaseHolder>(&mut conn)
+            .optional()?;
+
+        Ok(acquired.is_some())
+    }
+
+    /// Give up the lease `name` if `holder` holds it, so another holder
+    /// can take over without waiting for it to expire
+    pub fn release_lease(&self, name: &str, holder: &str) -> Result<(), DatabaseError> {
+        let mut conn = self.get_connection()?;
+
+        diesel::delete(
+            relayer_leases::table
+                .filter(relayer_leases::tenant_id.eq(self.tenant.id()))
+                .filter(relayer_leases::name.eq(name))
+                .filter(relayer_leases::holder.eq(holder)),
+        )
+        .execute(&mut conn)?;
+
+        Ok(())
+    }
+}
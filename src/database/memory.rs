@@ -0,0 +1,745 @@
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::{
+    operation_retry_delay, FailureKind, HTLCOperation, HTLCSpend, HTLCState, OperationStatus,
+    TenantContext, ZcashHTLC, ZcashNetwork,
+};
+
+use super::connections::DatabaseError;
+use super::storage::Storage;
+
+/// [`Storage`] in process memory, e.g. to unit-test HTLC flows without
+/// Postgres. Clones and [`Storage::with_tenant`] views share one store.
+/// Unlike [`Database`](super::Database) it keeps no audit log or event
+/// outbox, and secrets are not encrypted.
+#[derive(Clone, Default)]
+pub struct MemoryStorage {
+    tenant: TenantContext,
+    state: Arc<Mutex<MemoryState>>,
+}
+
+#[derive(Default)]
+struct MemoryState {
+    /// By id, with the tenant owning them
+    htlcs: HashMap<String, (String, ZcashHTLC)>,
+    operations: HashMap<String, (String, HTLCOperation)>,
+    /// When each operation's transaction was last checked for confirmations
+    checked: HashMap<String, DateTime<Utc>>,
+    /// By tenant and chain
+    checkpoints: HashMap<(String, String), u32>,
+    block_hashes: HashMap<(String, String), BTreeMap<u64, String>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// HTLCs of this store's tenant, oldest first
+    pub fn htlcs(&self) -> Vec<ZcashHTLC> {
+        let state = self.lock();
+        let mut htlcs: Vec<ZcashHTLC> = state
+            .htlcs
+            .values()
+            .filter(|(tenant, _)| tenant == self.tenant.id())
+            .map(|(_, htlc)| htlc.clone())
+            .collect();
+        htlcs.sort_by_key(|htlc| htlc.created_at);
+        htlcs
+    }
+
+    /// Operations of this store's tenant, oldest first
+    pub fn operations(&self) -> Vec<HTLCOperation> {
+        let state = self.lock();
+        let mut operations: Vec<HTLCOperation> = state
+            .operations
+            .values()
+            .filter(|(tenant, _)| tenant == self.tenant.id())
+            .map(|(_, operation)| operation.clone())
+            .collect();
+        operations.sort_by_key(|operation| operation.created_at);
+        operations
+    }
+
+    /// Key of this store's tenant's checkpoints of `chain`
+    fn chain_key(&self, chain: &str) -> (String, String) {
+        (self.tenant.id().to_string(), chain.to_string())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MemoryState> {
+        // Every update checks before it writes, so a panicking holder
+        // cannot leave a half-applied change behind
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl MemoryState {
+    fn htlc(&mut self, tenant: &str, htlc_id: &str) -> Result<&mut ZcashHTLC, DatabaseError> {
+        match self.htlcs.get_mut(htlc_id) {
+            Some((owner, htlc)) if owner == tenant && htlc.deleted_at.is_none() => Ok(htlc),
+            _ => Err(DatabaseError::HTLCNotFound(htlc_id.to_string())),
+        }
+    }
+
+    /// `htlc_id`, checked to be allowed to move to `next`
+    fn transition(
+        &mut self,
+        tenant: &str,
+        htlc_id: &str,
+        next: HTLCState,
+    ) -> Result<&mut ZcashHTLC, DatabaseError> {
+        let htlc = self.htlc(tenant, htlc_id)?;
+        if !htlc.state.can_transition_to(next) {
+            return Err(DatabaseError::IllegalTransition {
+                htlc_id: htlc_id.to_string(),
+                from: htlc.state,
+                to: next,
+            });
+        }
+        Ok(htlc)
+    }
+
+    fn fund(
+        &mut self,
+        tenant: &str,
+        htlc_id: &str,
+        txid: &str,
+        vout: u32,
+    ) -> Result<(), DatabaseError> {
+        let htlc = self.transition(tenant, htlc_id, HTLCState::Locked)?;
+        htlc.txid = Some(txid.to_string());
+        htlc.vout = Some(vout);
+        htlc.state = HTLCState::Locked;
+        htlc.updated_at = Utc::now();
+        htlc.version += 1;
+        Ok(())
+    }
+
+    /// Operations of other tenants are left alone, as by an update that
+    /// matches no rows
+    fn operation(&mut self, tenant: &str, operation_id: &str) -> Option<&mut HTLCOperation> {
+        match self.operations.get_mut(operation_id) {
+            Some((owner, operation)) if owner == tenant => Some(operation),
+            _ => None,
+        }
+    }
+
+    fn mark_confirmed(&mut self, tenant: &str, operation_id: &str, block_height: u64) {
+        if let Some(operation) = self.operation(tenant, operation_id) {
+            let now = Utc::now();
+            operation.status = OperationStatus::Confirmed;
+            operation.block_height = Some(block_height);
+            operation.confirmed_at = Some(now);
+            operation.updated_at = now;
+        }
+    }
+
+    fn mark_broadcast(&mut self, tenant: &str, operation_id: &str, txid: &str) {
+        if let Some(operation) = self.operation(tenant, operation_id) {
+            let now = Utc::now();
+            operation.txid = Some(txid.to_string());
+            operation.status = OperationStatus::Broadcast;
+            operation.broadcast_at = Some(now);
+            operation.next_retry_at = None;
+            operation.updated_at = now;
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Storage for MemoryStorage {
+    fn tenant(&self) -> &TenantContext {
+        &self.tenant
+    }
+
+    fn with_tenant(&self, tenant: TenantContext) -> Arc<dyn Storage> {
+        Arc::new(Self {
+            tenant,
+            state: self.state.clone(),
+        })
+    }
+
+    async fn create_htlc_with_operation(
+        &self,
+        htlc: &ZcashHTLC,
+        operation: &HTLCOperation,
+    ) -> Result<String, DatabaseError> {
+        let tenant = self.tenant.id();
+        let mut state = self.lock();
+
+        // Hash locks are unique across tenants, as in Postgres
+        let taken = state.htlcs.values().any(|(_, existing)| {
+            existing.deleted_at.is_none()
+                && existing.hash_lock == htlc.hash_lock
+                && existing.network == htlc.network
+        });
+        let queued = state.htlcs.get_mut(&htlc.id);
+
+        let htlc_id = match queued {
+            Some((owner, queued))
+                if owner == tenant && queued.deleted_at.is_none() && htlc.fills(queued) =>
+            {
+                queued.p2sh_address = htlc.p2sh_address.clone();
+                queued.timelock_kind = htlc.timelock_kind;
+                queued.refund_pubkey = htlc.refund_pubkey.clone();
+                queued.script_hex = htlc.script_hex.clone();
+                queued.redeem_script_hex = htlc.redeem_script_hex.clone();
+                queued.metadata = htlc.metadata.clone();
+                queued.updated_at = Utc::now();
+                queued.version += 1;
+                queued.id.clone()
+            }
+            Some(_) => return Err(DatabaseError::DuplicateHashLock(htlc.hash_lock.clone())),
+            None if taken => {
+                return Err(DatabaseError::DuplicateHashLock(htlc.hash_lock.clone()));
+            }
+            None => {
+                state
+                    .htlcs
+                    .insert(htlc.id.clone(), (tenant.to_string(), htlc.clone()));
+                htlc.id.clone()
+            }
+        };
+
+        let mut operation = operation.clone();
+        operation.htlc_id = htlc_id.clone();
+        state
+            .operations
+            .insert(operation.id.clone(), (tenant.to_string(), operation));
+        Ok(htlc_id)
+    }
+
+    async fn record_htlc_funding(
+        &self,
+        htlc_id: &str,
+        operation_id: &str,
+        txid: &str,
+        vout: u32,
+    ) -> Result<(), DatabaseError> {
+        let mut state = self.lock();
+        let htlc = state.htlc(self.tenant.id(), htlc_id)?;
+        htlc.txid = Some(txid.to_string());
+        htlc.vout = Some(vout);
+        htlc.updated_at = Utc::now();
+        htlc.version += 1;
+        state.mark_broadcast(self.tenant.id(), operation_id, txid);
+        Ok(())
+    }
+
+    async fn record_funding_confirmed(
+        &self,
+        htlc_id: &str,
+        operation_id: &str,
+        txid: &str,
+        vout: u32,
+        block_height: u64,
+    ) -> Result<bool, DatabaseError> {
+        let mut state = self.lock();
+        let current = state.htlc(self.tenant.id(), htlc_id)?.state;
+        let lock = current != HTLCState::Locked && current.can_transition_to(HTLCState::Locked);
+        if lock {
+            state.fund(self.tenant.id(), htlc_id, txid, vout)?;
+        }
+        state.mark_confirmed(self.tenant.id(), operation_id, block_height);
+        Ok(lock)
+    }
+
+    async fn update_htlc_txid(
+        &self,
+        htlc_id: &str,
+        txid: &str,
+        vout: u32,
+    ) -> Result<(), DatabaseError> {
+        self.lock().fund(self.tenant.id(), htlc_id, txid, vout)
+    }
+
+    async fn record_htlc_settlement(
+        &self,
+        htlc_id: &str,
+        state: HTLCState,
+        secret: Option<&str>,
+        operation_id: &str,
+        txid: &str,
+    ) -> Result<(), DatabaseError> {
+        let mut store = self.lock();
+        let htlc = store.transition(self.tenant.id(), htlc_id, state)?;
+        htlc.state = state;
+        if let Some(secret) = secret {
+            htlc.secret = Some(secret.into());
+        }
+        htlc.updated_at = Utc::now();
+        htlc.version += 1;
+        store.mark_broadcast(self.tenant.id(), operation_id, txid);
+        Ok(())
+    }
+
+    async fn record_htlc_spend(
+        &self,
+        htlc_id: &str,
+        spend: &HTLCSpend,
+        _spending_txid: &str,
+    ) -> Result<bool, DatabaseError> {
+        let mut store = self.lock();
+        let (state, secret) = match spend {
+            HTLCSpend::Redeem { secret } => (HTLCState::Redeemed, Some(secret)),
+            HTLCSpend::Refund => (HTLCState::Refunded, None),
+        };
+        let htlc = store.htlc(self.tenant.id(), htlc_id)?;
+        let (current, outpoint) = (htlc.state, (htlc.txid.clone(), htlc.vout));
+        if matches!(current, HTLCState::Redeemed | HTLCState::Refunded) {
+            return Ok(false);
+        }
+        if let (HTLCState::Pending, (Some(txid), Some(vout))) = (current, outpoint) {
+            store.fund(self.tenant.id(), htlc_id, &txid, vout)?;
+        }
+        let htlc = store.transition(self.tenant.id(), htlc_id, state)?;
+        htlc.state = state;
+        if let Some(secret) = secret {
+            htlc.secret = Some(secret.clone());
+        }
+        htlc.updated_at = Utc::now();
+        htlc.version += 1;
+        Ok(true)
+    }
+
+    async fn get_htlc_by_id(&self, htlc_id: &str) -> Result<ZcashHTLC, DatabaseError> {
+        Ok(self.lock().htlc(self.tenant.id(), htlc_id)?.clone())
+    }
+
+    async fn get_unsettled_htlcs(
+        &self,
+        network: ZcashNetwork,
+    ) -> Result<Vec<ZcashHTLC>, DatabaseError> {
+        let mut htlcs: Vec<ZcashHTLC> = self
+            .htlcs()
+            .into_iter()
+            .filter(|htlc| htlc.network == network && htlc.deleted_at.is_none())
+            .filter(|htlc| matches!(htlc.state, HTLCState::Pending | HTLCState::Locked))
+            .collect();
+        htlcs.reverse();
+        Ok(htlcs)
+    }
+
+    async fn create_operation(&self, operation: &HTLCOperation) -> Result<(), DatabaseError> {
+        let tenant = self.tenant.id().to_string();
+        self.lock()
+            .operations
+            .insert(operation.id.clone(), (tenant, operation.clone()));
+        Ok(())
+    }
+
+    async fn record_operation_broadcast(
+        &self,
+        operation_id: &str,
+        txid: &str,
+    ) -> Result<(), DatabaseError> {
+        self.lock()
+            .mark_broadcast(self.tenant.id(), operation_id, txid);
+        Ok(())
+    }
+
+    async fn record_operation_replaced(
+        &self,
+        operation_id: &str,
+        replacement_id: &str,
+        txid: &str,
+    ) -> Result<(), DatabaseError> {
+        let mut store = self.lock();
+        store.mark_broadcast(self.tenant.id(), replacement_id, txid);
+        let operation = store
+            .operation(self.tenant.id(), operation_id)
+            .ok_or_else(|| DatabaseError::OperationNotFound(operation_id.to_string()))?;
+        operation.status = OperationStatus::Failed;
+        operation.error_message = Some(format!("replaced by {}", txid));
+        operation.next_retry_at = None;
+        operation.updated_at = Utc::now();
+        Ok(())
+    }
+
+    async fn record_operation_attempt(
+        &self,
+        operation_id: &str,
+        error: &str,
+        kind: FailureKind,
+        retry: bool,
+    ) -> Result<u32, DatabaseError> {
+        let mut state = self.lock();
+        let operation = state
+            .operation(self.tenant.id(), operation_id)
+            .ok_or_else(|| DatabaseError::OperationNotFound(operation_id.to_string()))?;
+
+        let now = Utc::now();
+        operation.attempt_count += 1;
+        operation.status = OperationStatus::Failed;
+        operation.error_message = Some(error.to_string());
+        operation.error_kind = Some(kind);
+        operation.last_attempt_at = Some(now);
+        operation.next_retry_at =
+            retry.then(|| now + operation_retry_delay(operation.attempt_count));
+        operation.updated_at = now;
+        Ok(operation.attempt_count)
+    }
+
+    async fn update_operation_confirmed(
+        &self,
+        operation_id: &str,
+        block_height: u64,
+    ) -> Result<(), DatabaseError> {
+        let mut state = self.lock();
+        let tenant = self.tenant.id();
+        state.mark_confirmed(tenant, operation_id, block_height);
+
+        // A redeem or refund a reorg undid settles its HTLC again
+        let Some(operation) = state.operation(tenant, operation_id) else {
+            return Ok(());
+        };
+        let (htlc_id, settled) = (
+            operation.htlc_id.clone(),
+            operation.operation_type.settles_to(),
+        );
+        if let Some(settled) = settled {
+            if let Ok(htlc) = state.htlc(tenant, &htlc_id) {
+                if htlc.state == HTLCState::Locked {
+                    htlc.state = settled;
+                    htlc.updated_at = Utc::now();
+                    htlc.version += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_unconfirmed_operations(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<HTLCOperation>, DatabaseError> {
+        let state = self.lock();
+        let mut operations: Vec<HTLCOperation> = state
+            .operations
+            .values()
+            .filter(|(owner, operation)| {
+                owner == self.tenant.id()
+                    && operation.status == OperationStatus::Broadcast
+                    && operation.txid.is_some()
+            })
+            .map(|(_, operation)| operation.clone())
+            .collect();
+        operations.sort_by_key(|operation| {
+            (
+                state.checked.get(&operation.id).copied(),
+                operation.broadcast_at,
+            )
+        });
+        operations.truncate(limit);
+        Ok(operations)
+    }
+
+    async fn record_operations_checked(
+        &self,
+        operation_ids: &[String],
+    ) -> Result<(), DatabaseError> {
+        let mut state = self.lock();
+        let now = Utc::now();
+        for operation_id in operation_ids {
+            if state.operation(self.tenant.id(), operation_id).is_some() {
+                state.checked.insert(operation_id.clone(), now);
+            }
+        }
+        Ok(())
+    }
+
+    async fn update_operation_unconfirmed(&self, operation_id: &str) -> Result<(), DatabaseError> {
+        let mut state = self.lock();
+        let Some((tenant, operation)) = state
+            .operations
+            .get_mut(operation_id)
+            .filter(|(owner, _)| owner == self.tenant.id())
+        else {
+            return Ok(());
+        };
+        operation.status = OperationStatus::Broadcast;
+        operation.block_height = None;
+        operation.confirmed_at = None;
+        operation.updated_at = Utc::now();
+
+        let (tenant, htlc_id) = (tenant.clone(), operation.htlc_id.clone());
+        let operation_type = operation.operation_type.clone();
+        if let Ok(htlc) = state.htlc(&tenant, &htlc_id) {
+            if let Some(previous) = htlc.state.reorg_revert(&operation_type) {
+                htlc.state = previous;
+                htlc.updated_at = Utc::now();
+                htlc.version += 1;
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_operations_confirmed_since(
+        &self,
+        block_height: u64,
+    ) -> Result<Vec<HTLCOperation>, DatabaseError> {
+        Ok(self
+            .lock()
+            .operations
+            .values()
+            .filter(|(owner, _)| owner == self.tenant.id())
+            .map(|(_, operation)| operation)
+            .filter(|operation| operation.status == OperationStatus::Confirmed)
+            .filter(|operation| operation.block_height >= Some(block_height))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_checkpoint(&self, chain: &str) -> Result<Option<u32>, DatabaseError> {
+        Ok(self.lock().checkpoints.get(&self.chain_key(chain)).copied())
+    }
+
+    async fn save_checkpoint(&self, chain: &str, block_height: u32) -> Result<(), DatabaseError> {
+        self.lock()
+            .checkpoints
+            .insert(self.chain_key(chain), block_height);
+        Ok(())
+    }
+
+    async fn record_block_hashes(
+        &self,
+        chain: &str,
+        hashes: &[(u64, String)],
+        keep_from: u64,
+    ) -> Result<(), DatabaseError> {
+        let mut state = self.lock();
+        let recorded = state.block_hashes.entry(self.chain_key(chain)).or_default();
+        recorded.extend(hashes.iter().cloned());
+        *recorded = recorded.split_off(&keep_from);
+        Ok(())
+    }
+
+    async fn get_recent_block_hashes(
+        &self,
+        chain: &str,
+        limit: i64,
+    ) -> Result<Vec<(u64, String)>, DatabaseError> {
+        Ok(self
+            .lock()
+            .block_hashes
+            .get(&self.chain_key(chain))
+            .map(|hashes| {
+                hashes
+                    .iter()
+                    .rev()
+                    .take(limit.max(0) as usize)
+                    .map(|(height, hash)| (*height, hash.clone()))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn rewind_block_hashes(
+        &self,
+        chain: &str,
+        fork_height: u64,
+    ) -> Result<(), DatabaseError> {
+        let key = self.chain_key(chain);
+        let mut state = self.lock();
+        if let Some(hashes) = state.block_hashes.get_mut(&key) {
+            hashes.split_off(&fork_height);
+        }
+        if let Some(checkpoint) = state.checkpoints.get_mut(&key) {
+            if *checkpoint as u64 >= fork_height {
+                *checkpoint = (fork_height as u32).saturating_sub(1);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{test_htlc, test_operation};
+    use crate::HTLCOperationType;
+
+    fn htlc(id: &str, hash_lock: &str) -> ZcashHTLC {
+        ZcashHTLC {
+            hash_lock: hash_lock.to_string(),
+            ..test_htlc(id)
+        }
+    }
+
+    fn operation(id: &str, operation_type: HTLCOperationType) -> HTLCOperation {
+        test_operation(id, "", operation_type)
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_follows_htlc_lifecycle() {
+        let storage = MemoryStorage::new();
+        let fund = operation("op-fund", HTLCOperationType::Create);
+        let htlc_id = storage
+            .create_htlc_with_operation(&htlc("htlc-1", "aa"), &fund)
+            .await
+            .unwrap();
+        // Still pending, so built again rather than duplicated, with the
+        // funder's own refund key; but only when named and matching
+        let rebuilt = ZcashHTLC {
+            refund_pubkey: "02".repeat(33),
+            ..htlc("htlc-1", "aa")
+        };
+        assert_eq!(
+            storage
+                .create_htlc_with_operation(&rebuilt, &fund)
+                .await
+                .unwrap(),
+            htlc_id
+        );
+        let other_amount = ZcashHTLC {
+            amount: "2".to_string(),
+            ..htlc("htlc-1", "aa")
+        };
+        for clash in [htlc("htlc-2", "aa"), other_amount] {
+            assert!(matches!(
+                storage.create_htlc_with_operation(&clash, &fund).await,
+                Err(DatabaseError::DuplicateHashLock(_))
+            ));
+        }
+        assert_eq!(
+            storage
+                .get_htlc_by_id(&htlc_id)
+                .await
+                .unwrap()
+                .refund_pubkey,
+            "02".repeat(33)
+        );
+
+        storage
+            .record_htlc_funding(&htlc_id, "op-fund", "ab", 0)
+            .await
+            .unwrap();
+        assert!(matches!(
+            storage
+                .create_htlc_with_operation(&htlc("htlc-1", "aa"), &fund)
+                .await,
+            Err(DatabaseError::DuplicateHashLock(_))
+        ));
+        // Locked only once the funding confirms
+        let funded = storage.get_htlc_by_id(&htlc_id).await.unwrap();
+        assert_eq!(funded.state, HTLCState::Pending);
+        assert!(storage
+            .record_funding_confirmed(&htlc_id, "op-fund", "ab", 1, 100)
+            .await
+            .unwrap());
+        assert!(!storage
+            .record_funding_confirmed(&htlc_id, "op-fund", "ab", 1, 100)
+            .await
+            .unwrap());
+        assert_eq!(
+            storage.get_htlc_by_id(&htlc_id).await.unwrap().vout,
+            Some(1)
+        );
+        let redeem = operation("op-redeem", HTLCOperationType::Redeem);
+        let redeem = HTLCOperation {
+            htlc_id: htlc_id.clone(),
+            ..redeem
+        };
+        storage.create_operation(&redeem).await.unwrap();
+        assert_eq!(
+            storage
+                .record_operation_attempt("op-redeem", "rejected", FailureKind::Broadcast, true)
+                .await
+                .unwrap(),
+            1
+        );
+        storage
+            .record_htlc_settlement(&htlc_id, HTLCState::Redeemed, Some("00"), "op-redeem", "cd")
+            .await
+            .unwrap();
+
+        let settled = storage.get_htlc_by_id(&htlc_id).await.unwrap();
+        assert_eq!(settled.state, HTLCState::Redeemed);
+        assert_eq!(settled.secret.as_deref(), Some("00"));
+        assert_eq!(settled.version, 4);
+        assert!(matches!(
+            storage
+                .record_htlc_settlement(&htlc_id, HTLCState::Refunded, None, "op-redeem", "ef")
+                .await,
+            Err(DatabaseError::IllegalTransition { .. })
+        ));
+        let operations = storage.operations();
+        assert!(operations.iter().all(|op| op.next_retry_at.is_none()));
+        assert!(operations.iter().all(|op| match op.id.as_str() {
+            "op-fund" => op.status == OperationStatus::Confirmed && op.block_height == Some(100),
+            _ => op.status == OperationStatus::Broadcast,
+        }));
+
+        // A reorg undoing the redeem locks the HTLC again until the redeem
+        // confirms anew
+        storage
+            .update_operation_confirmed("op-redeem", 102)
+            .await
+            .unwrap();
+        storage
+            .update_operation_unconfirmed("op-redeem")
+            .await
+            .unwrap();
+        let reverted = storage.get_htlc_by_id(&htlc_id).await.unwrap();
+        assert_eq!(reverted.state, HTLCState::Locked);
+        assert_eq!(reverted.secret.as_deref(), Some("00"));
+        storage
+            .update_operation_confirmed("op-redeem", 103)
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.get_htlc_by_id(&htlc_id).await.unwrap().state,
+            HTLCState::Redeemed
+        );
+
+        let other = storage.with_tenant(TenantContext::new("acme"));
+        assert!(matches!(
+            other.get_htlc_by_id(&htlc_id).await,
+            Err(DatabaseError::HTLCNotFound(_))
+        ));
+        // Another tenant neither sees nor reverts this tenant's
+        // confirmations, and tracks its own chain
+        assert!(other
+            .get_operations_confirmed_since(0)
+            .await
+            .unwrap()
+            .is_empty());
+        other
+            .update_operation_unconfirmed("op-redeem")
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.get_htlc_by_id(&htlc_id).await.unwrap().state,
+            HTLCState::Redeemed
+        );
+        storage
+            .record_block_hashes("testnet", &[(103, "aa".into()), (104, "bb".into())], 104)
+            .await
+            .unwrap();
+        other.save_checkpoint("testnet", 7).await.unwrap();
+        assert_eq!(
+            storage
+                .get_recent_block_hashes("testnet", 10)
+                .await
+                .unwrap(),
+            vec![(104, "bb".to_string())]
+        );
+        assert!(other
+            .get_recent_block_hashes("testnet", 10)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(storage.get_checkpoint("testnet").await.unwrap(), None);
+        // Hash locks are unique across tenants
+        assert!(matches!(
+            other
+                .create_htlc_with_operation(&htlc("htlc-acme", "aa"), &fund)
+                .await,
+            Err(DatabaseError::DuplicateHashLock(_))
+        ));
+    }
+}
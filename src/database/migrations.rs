@@ -0,0 +1,93 @@
+use diesel::migration::{Migration, MigrationSource, MigrationVersion};
+use diesel::pg::Pg;
+use diesel_migrations::MigrationHarness;
+use serde::Serialize;
+use tracing::info;
+
+use super::connections::{Database, DatabaseError, MIGRATIONS};
+
+/// One migration embedded in this build, as reported by
+/// [`Database::migration_status`]
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationStatus {
+    /// Directory name, e.g. `2026-10-16-190000_add_tenant_id`
+    pub name: String,
+    pub applied: bool,
+}
+
+impl Database {
+    /// Every embedded migration, oldest first, and whether it has run
+    pub fn migration_status(&self) -> Result<Vec<MigrationStatus>, DatabaseError> {
+        let mut conn = self.get_connection()?;
+        let applied = conn.applied_migrations().map_err(migration_error)?;
+
+        Ok(embedded_migrations()?
+            .iter()
+            .map(|migration| MigrationStatus {
+                name: migration.name().to_string(),
+                applied: applied.contains(&migration.name().version()),
+            })
+            .collect())
+    }
+
+    /// Run all pending migrations, oldest first, returning their names.
+    /// With `dry_run`, only report what would run.
+    pub fn apply_migrations(&self, dry_run: bool) -> Result<Vec<String>, DatabaseError> {
+        let mut conn = self.get_connection()?;
+        let pending: Vec<String> = conn
+            .pending_migrations(MIGRATIONS)
+            .map_err(migration_error)?
+            .iter()
+            .map(|migration| migration.name().to_string())
+            .collect();
+        if dry_run || pending.is_empty() {
+            return Ok(pending);
+        }
+
+        conn.run_pending_migrations(MIGRATIONS)
+            .map_err(migration_error)?;
+        for name in &pending {
+            info!("⬆️ Applied migration {}", name);
+        }
+        Ok(pending)
+    }
+
+    /// Revert the last `steps` applied migrations, newest first, returning
+    /// their names. With `dry_run`, only report what would be reverted.
+    pub fn revert_migrations(
+        &self,
+        steps: usize,
+        dry_run: bool,
+    ) -> Result<Vec<String>, DatabaseError> {
+        let mut conn = self.get_connection()?;
+        let mut applied = conn.applied_migrations().map_err(migration_error)?;
+        applied.sort();
+        let embedded = embedded_migrations()?;
+        let name_of = |version: &MigrationVersion| {
+            embedded
+                .iter()
+                .find(|migration| migration.name().version() == *version)
+                .map_or_else(|| version.to_string(), |m| m.name().to_string())
+        };
+
+        let reverting: Vec<String> = applied.iter().rev().take(steps).map(name_of).collect();
+        if dry_run {
+            return Ok(reverting);
+        }
+
+        for name in &reverting {
+            conn.revert_last_migration(MIGRATIONS)
+                .map_err(migration_error)?;
+            info!("⬇️ Reverted migration {}", name);
+        }
+        Ok(reverting)
+    }
+}
+
+fn embedded_migrations() -> Result<Vec<Box<dyn Migration<Pg>>>, DatabaseError> {
+    MigrationSource::<Pg>::migrations(&MIGRATIONS).map_err(migration_error)
+}
+
+fn migration_error(e: impl std::fmt::Display) -> DatabaseError {
+    DatabaseError::MigrationError(e.to_string())
+}
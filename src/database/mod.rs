@@ -1,5 +1,32 @@
 pub mod connections;
+pub mod encryption;
+pub mod export;
+pub mod fees;
+pub mod health;
+pub mod jobs;
+pub mod lease;
+pub mod memory;
+pub mod migrations;
 pub mod model;
+pub mod notify;
 pub mod operations;
+#[cfg(test)]
+mod pg_tests;
+pub mod query;
+pub mod search;
+pub mod storage;
+pub mod utxo_sync;
 
 pub use connections::{Database, DatabaseError, DbPool, MIGRATIONS};
+pub use encryption::ColumnCipher;
+pub use export::{ExportFormat, HTLCExport, HTLCRecord, EXPORT_SCHEMA_VERSION};
+pub use fees::FeeSummary;
+pub use health::DatabaseHealth;
+pub use jobs::{Job, JobType};
+pub use memory::MemoryStorage;
+pub use migrations::MigrationStatus;
+pub use notify::{HTLCNotification, PENDING_HTLC_CHANNEL, SIGNED_REDEEM_CHANNEL};
+pub use query::HTLCQuery;
+pub use search::{HTLCSearchMatch, HTLCSummary, SearchField, MIN_SEARCH_TERM_LEN};
+pub use storage::Storage;
+pub use utxo_sync::UtxoSyncReport;
@@ -1,13 +1,15 @@
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 
+use crate::models::REDACTED;
 use crate::{
-    schema::{htlc_operations, indexer_checkpoints, zcash_htlcs},
-    HTLCOperation, HTLCOperationType, HTLCState, OperationStatus, RelayerUTXO, ZcashHTLC,
+    schema::{event_outbox, htlc_audit_log, htlc_operations, indexer_checkpoints, zcash_htlcs},
+    Consolidation, FailureKind, FeePriority, HTLCAuditEntry, HTLCEvent, HTLCOperation,
+    HTLCOperationType, HTLCSecret, HTLCState, OperationStatus, RelayerUTXO, ZcashHTLC,
     ZcashNetwork,
 };
 
-#[derive(Debug, Clone, Queryable, Selectable, Insertable, AsChangeset)]
+#[derive(Clone, Queryable, Selectable, Insertable, AsChangeset)]
 #[diesel(table_name = zcash_htlcs)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct DbZcashHTLC {
@@ -25,9 +27,45 @@ pub struct DbZcashHTLC {
     pub vout: Option<i32>,
     pub script_hex: String,
     pub redeem_script_hex: String,
+    pub recipient_address: Option<String>,
     pub signed_redeem_tx: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub version: i32,
+    pub metadata: Option<serde_json::Value>,
+    pub timelock_kind: String,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub priority: i16,
+}
+
+impl std::fmt::Debug for DbZcashHTLC {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DbZcashHTLC")
+            .field("id", &self.id)
+            .field("txid", &self.txid)
+            .field("p2sh_address", &self.p2sh_address)
+            .field("hash_lock", &self.hash_lock)
+            .field("secret", &self.secret.as_ref().map(|_| REDACTED))
+            .field("timelock", &self.timelock)
+            .field("recipient_pubkey", &self.recipient_pubkey)
+            .field("refund_pubkey", &self.refund_pubkey)
+            .field("amount", &self.amount)
+            .field("network", &self.network)
+            .field("state", &self.state)
+            .field("vout", &self.vout)
+            .field("script_hex", &self.script_hex)
+            .field("redeem_script_hex", &self.redeem_script_hex)
+            .field("recipient_address", &self.recipient_address)
+            .field("signed_redeem_tx", &self.signed_redeem_tx)
+            .field("created_at", &self.created_at)
+            .field("updated_at", &self.updated_at)
+            .field("version", &self.version)
+            .field("metadata", &self.metadata)
+            .field("timelock_kind", &self.timelock_kind)
+            .field("deleted_at", &self.deleted_at)
+            .field("priority", &self.priority)
+            .finish()
+    }
 }
 
 #[derive(Debug, Clone, Insertable)]
@@ -44,6 +82,11 @@ pub struct NewZcashHTLC {
     pub state: i16,
     pub script_hex: String,
     pub redeem_script_hex: String,
+    pub recipient_address: Option<String>,
+    pub tenant_id: String,
+    pub metadata: Option<serde_json::Value>,
+    pub timelock_kind: String,
+    pub priority: i16,
 }
 
 #[derive(Debug, Clone, Queryable, Selectable, Insertable, AsChangeset)]
@@ -63,6 +106,15 @@ pub struct DbHTLCOperation {
     pub error_message: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub attempt_count: i32,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    pub next_retry_at: Option<DateTime<Utc>>,
+    pub fee_zat: Option<i64>,
+    pub tx_size: Option<i32>,
+    pub fee_rate: Option<i64>,
+    pub broadcast_height: Option<i64>,
+    pub bumps_operation_id: Option<String>,
+    pub error_kind: Option<String>,
 }
 
 #[derive(Debug, Clone, Insertable)]
@@ -72,7 +124,56 @@ pub struct NewHTLCOperation {
     pub htlc_id: String,
     pub operation_type: String,
     pub raw_tx_hex: Option<String>,
+    pub signed_tx_hex: Option<String>,
     pub status: String,
+    pub tenant_id: String,
+    pub fee_zat: Option<i64>,
+    pub tx_size: Option<i32>,
+    pub fee_rate: Option<i64>,
+    pub bumps_operation_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = htlc_audit_log)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DbHTLCAuditEntry {
+    pub id: i64,
+    pub htlc_id: String,
+    pub old_state: Option<i16>,
+    pub new_state: i16,
+    pub actor: String,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = htlc_audit_log)]
+pub struct NewHTLCAuditEntry {
+    pub htlc_id: String,
+    pub old_state: Option<i16>,
+    pub new_state: i16,
+    pub actor: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = event_outbox)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DbHTLCEvent {
+    pub id: i64,
+    pub htlc_id: Option<String>,
+    pub event_type: String,
+    pub payload: String,
+    pub attempts: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = event_outbox)]
+pub struct NewHTLCEvent {
+    pub htlc_id: Option<String>,
+    pub event_type: String,
+    pub payload: String,
 }
 
 #[derive(Debug, Clone, Queryable, Selectable)]
@@ -112,6 +213,51 @@ pub struct NewRelayerUTXO {
     pub script_pubkey: String,
     pub confirmations: i32,
     pub address: String,
+    pub tenant_id: String,
+}
+
+#[derive(Queryable, Selectable, Insertable, Debug)]
+#[diesel(table_name = crate::models::schema::relayer_consolidations)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DbConsolidation {
+    pub txid: String,
+    pub address: String,
+    pub inputs: i32,
+    pub amount_zat: i64,
+    pub fee_zat: i64,
+    pub tx_size: i32,
+    pub fee_rate: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<&Consolidation> for DbConsolidation {
+    fn from(consolidation: &Consolidation) -> Self {
+        DbConsolidation {
+            txid: consolidation.txid.clone(),
+            address: consolidation.address.clone(),
+            inputs: consolidation.inputs as i32,
+            amount_zat: consolidation.amount_zat as i64,
+            fee_zat: consolidation.fee_zat as i64,
+            tx_size: consolidation.tx_size as i32,
+            fee_rate: consolidation.fee_rate as i64,
+            created_at: consolidation.created_at,
+        }
+    }
+}
+
+impl From<DbConsolidation> for Consolidation {
+    fn from(db: DbConsolidation) -> Self {
+        Consolidation {
+            txid: db.txid,
+            address: db.address,
+            inputs: db.inputs as u32,
+            amount_zat: db.amount_zat as u64,
+            fee_zat: db.fee_zat as u64,
+            tx_size: db.tx_size as u32,
+            fee_rate: db.fee_rate as u64,
+            created_at: db.created_at,
+        }
+    }
 }
 
 impl From<DbRelayerUTXO> for RelayerUTXO {
@@ -139,19 +285,55 @@ impl From<DbZcashHTLC> for ZcashHTLC {
             txid: db.txid,
             p2sh_address: db.p2sh_address,
             hash_lock: db.hash_lock,
-            secret: db.secret,
+            secret: db.secret.map(HTLCSecret::new),
             timelock: db.timelock as u64,
             recipient_pubkey: db.recipient_pubkey,
             refund_pubkey: db.refund_pubkey,
             amount: db.amount,
-            network: ZcashNetwork::from_str(&db.network),
+            network: db.network.parse().unwrap_or(ZcashNetwork::Testnet),
             state: HTLCState::from_i16(db.state),
             vout: db.vout.map(|v| v as u32),
             script_hex: db.script_hex,
             redeem_script_hex: db.redeem_script_hex,
+            recipient_address: db.recipient_address,
             signed_redeem_tx: db.signed_redeem_tx,
             created_at: db.created_at,
             updated_at: db.updated_at,
+            version: db.version as u32,
+            metadata: db.metadata,
+            timelock_kind: db.timelock_kind.parse().unwrap_or_default(),
+            deleted_at: db.deleted_at,
+            priority: FeePriority::from_i16(db.priority),
+        }
+    }
+}
+
+impl From<&ZcashHTLC> for DbZcashHTLC {
+    fn from(htlc: &ZcashHTLC) -> Self {
+        DbZcashHTLC {
+            id: htlc.id.clone(),
+            txid: htlc.txid.clone(),
+            p2sh_address: htlc.p2sh_address.clone(),
+            hash_lock: htlc.hash_lock.clone(),
+            secret: htlc.secret.as_deref().map(str::to_string),
+            timelock: htlc.timelock as i64,
+            recipient_pubkey: htlc.recipient_pubkey.clone(),
+            refund_pubkey: htlc.refund_pubkey.clone(),
+            amount: htlc.amount.clone(),
+            network: htlc.network.as_str().to_string(),
+            state: htlc.state as i16,
+            vout: htlc.vout.map(|v| v as i32),
+            script_hex: htlc.script_hex.clone(),
+            redeem_script_hex: htlc.redeem_script_hex.clone(),
+            recipient_address: htlc.recipient_address.clone(),
+            signed_redeem_tx: htlc.signed_redeem_tx.clone(),
+            created_at: htlc.created_at,
+            updated_at: htlc.updated_at,
+            version: htlc.version as i32,
+            metadata: htlc.metadata.clone(),
+            timelock_kind: htlc.timelock_kind.as_str().to_string(),
+            deleted_at: htlc.deleted_at,
+            priority: htlc.priority as i16,
         }
     }
 }
@@ -161,17 +343,87 @@ impl From<DbHTLCOperation> for HTLCOperation {
         HTLCOperation {
             id: db.id,
             htlc_id: db.htlc_id,
-            operation_type: HTLCOperationType::from_str(&db.operation_type),
+            operation_type: db
+                .operation_type
+                .parse()
+                .unwrap_or(HTLCOperationType::Create),
             txid: db.txid,
             raw_tx_hex: db.raw_tx_hex,
             signed_tx_hex: db.signed_tx_hex,
             broadcast_at: db.broadcast_at,
             confirmed_at: db.confirmed_at,
             block_height: db.block_height.map(|b| b as u64),
-            status: OperationStatus::from_str(&db.status),
+            status: db.status.parse().unwrap_or(OperationStatus::Pending),
             error_message: db.error_message,
             created_at: db.created_at,
             updated_at: db.updated_at,
+            attempt_count: db.attempt_count as u32,
+            last_attempt_at: db.last_attempt_at,
+            next_retry_at: db.next_retry_at,
+            fee_zat: db.fee_zat.map(|fee| fee as u64),
+            tx_size: db.tx_size.map(|size| size as u32),
+            fee_rate: db.fee_rate.map(|rate| rate as u64),
+            broadcast_height: db.broadcast_height.map(|height| height as u64),
+            bumps_operation_id: db.bumps_operation_id,
+            error_kind: db
+                .error_kind
+                .map(|kind| kind.parse().unwrap_or(FailureKind::Other)),
+        }
+    }
+}
+
+impl From<&HTLCOperation> for DbHTLCOperation {
+    fn from(operation: &HTLCOperation) -> Self {
+        DbHTLCOperation {
+            id: operation.id.clone(),
+            htlc_id: operation.htlc_id.clone(),
+            operation_type: operation.operation_type.as_str().to_string(),
+            txid: operation.txid.clone(),
+            raw_tx_hex: operation.raw_tx_hex.clone(),
+            signed_tx_hex: operation.signed_tx_hex.clone(),
+            broadcast_at: operation.broadcast_at,
+            confirmed_at: operation.confirmed_at,
+            block_height: operation.block_height.map(|b| b as i64),
+            status: operation.status.as_str().to_string(),
+            error_message: operation.error_message.clone(),
+            created_at: operation.created_at,
+            updated_at: operation.updated_at,
+            attempt_count: operation.attempt_count as i32,
+            last_attempt_at: operation.last_attempt_at,
+            next_retry_at: operation.next_retry_at,
+            fee_zat: operation.fee_zat.map(|fee| fee as i64),
+            tx_size: operation.tx_size.map(|size| size as i32),
+            fee_rate: operation.fee_rate.map(|rate| rate as i64),
+            broadcast_height: operation.broadcast_height.map(|height| height as i64),
+            bumps_operation_id: operation.bumps_operation_id.clone(),
+            error_kind: operation.error_kind.map(|kind| kind.as_str().to_string()),
+        }
+    }
+}
+
+impl From<DbHTLCAuditEntry> for HTLCAuditEntry {
+    fn from(db: DbHTLCAuditEntry) -> Self {
+        HTLCAuditEntry {
+            id: db.id,
+            htlc_id: db.htlc_id,
+            old_state: db.old_state.map(HTLCState::from_i16),
+            new_state: HTLCState::from_i16(db.new_state),
+            actor: db.actor,
+            reason: db.reason,
+            created_at: db.created_at,
+        }
+    }
+}
+
+impl From<DbHTLCEvent> for HTLCEvent {
+    fn from(db: DbHTLCEvent) -> Self {
+        HTLCEvent {
+            id: db.id,
+            htlc_id: db.htlc_id,
+            event_type: db.event_type,
+            payload: serde_json::from_str(&db.payload).unwrap_or(serde_json::Value::Null),
+            attempts: db.attempts as u32,
+            created_at: db.created_at,
         }
     }
 }
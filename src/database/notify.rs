@@ -0,0 +1,114 @@
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use std::thread;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use super::connections::{Database, DatabaseError};
+
+/// Notified with the HTLC id when an HTLC waiting to be funded is created
+pub const PENDING_HTLC_CHANNEL: &str = "htlc_pending";
+/// Notified with the HTLC id when a signed redeem transaction is stored
+pub const SIGNED_REDEEM_CHANNEL: &str = "htlc_redeem_signed";
+
+/// How often the listening connection is checked for notifications
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// A `NOTIFY` received on one of the subscribed channels
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HTLCNotification {
+    pub channel: String,
+    pub htlc_id: String,
+}
+
+impl Database {
+    /// Receive `NOTIFY`s sent on `channels` (e.g. [`PENDING_HTLC_CHANNEL`])
+    /// over a dedicated connection outside the pool, reconnecting when it
+    /// drops. Notifications sent while reconnecting are lost, so keep
+    /// polling as a fallback. Listening stops once the receiver is dropped.
+    pub fn subscribe(
+        &self,
+        channels: &[&str],
+    ) -> Result<mpsc::UnboundedReceiver<HTLCNotification>, DatabaseError> {
+        let channels: Vec<String> = channels.iter().map(|c| c.to_string()).collect();
+        let conn = listen(&self.url, &channels)?;
+        let url = self.url.clone();
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        thread::Builder::new()
+            .name("db-notify".to_string())
+            .spawn(move || forward_notifications(conn, &url, &channels, sender))
+            .map_err(|e| DatabaseError::SubscriptionError(e.to_string()))?;
+
+        Ok(receiver)
+    }
+}
+
+fn listen(url: &str, channels: &[String]) -> Result<PgConnection, DatabaseError> {
+    let mut conn = PgConnection::establish(url)
+        .map_err(|e| DatabaseError::SubscriptionError(e.to_string()))?;
+    for channel in channels {
+        diesel::sql_query(format!("LISTEN \"{}\"", channel)).execute(&mut conn)?;
+    }
+
+    info!("👂 Listening for database notifications on {:?}", channels);
+    Ok(conn)
+}
+
+fn forward_notifications(
+    mut conn: PgConnection,
+    url: &str,
+    channels: &[String],
+    sender: mpsc::UnboundedSender<HTLCNotification>,
+) {
+    loop {
+        let mut failed = false;
+        for notification in conn.notifications_iter() {
+            let notification = match notification {
+                Ok(notification) => notification,
+                Err(e) => {
+                    warn!("⚠️ Database notification connection failed: {}", e);
+                    failed = true;
+                    break;
+                }
+            };
+            let forwarded = sender.send(HTLCNotification {
+                channel: notification.channel,
+                htlc_id: notification.payload,
+            });
+            if forwarded.is_err() {
+                return;
+            }
+        }
+
+        if failed {
+            conn = loop {
+                thread::sleep(RECONNECT_DELAY);
+                if sender.is_closed() {
+                    return;
+                }
+                match listen(url, channels) {
+                    Ok(conn) => break conn,
+                    Err(e) => warn!("⚠️ Reconnecting database listener failed: {}", e),
+                }
+            };
+        }
+
+        if sender.is_closed() {
+            return;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Send `htlc_id` on `channel`, delivered when the surrounding transaction
+/// commits
+pub(crate) fn notify(conn: &mut PgConnection, channel: &str, htlc_id: &str) -> QueryResult<usize> {
+    diesel::sql_query("SELECT pg_notify($1, $2)")
+        .bind::<Text, _>(channel)
+        .bind::<Text, _>(htlc_id)
+        .execute(conn)
+}
@@ -1,75 +1,354 @@
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use diesel::pg::PgConnection;
 use diesel::prelude::*;
 use diesel::r2d2::{ConnectionManager, Pool};
 use diesel_migrations::{embed_migrations, EmbeddedMigrations};
-use tracing::info;
+use std::collections::HashMap;
+use tracing::{info, warn};
 
 use crate::database::model::{
-    DbHTLCOperation, DbRelayerUTXO, DbZcashHTLC, NewHTLCOperation, NewRelayerUTXO, NewZcashHTLC,
+    DbConsolidation, DbHTLCAuditEntry, DbHTLCEvent, DbHTLCOperation, DbRelayerUTXO, DbZcashHTLC,
+    NewHTLCAuditEntry, NewHTLCEvent, NewHTLCOperation, NewRelayerUTXO, NewZcashHTLC,
+};
+use crate::{
+    event_retry_delay, operation_retry_delay, zatoshis_to_zec, zec_to_zatoshis, Consolidation,
+    DeadLetteredHTLC, FailureKind, HTLCAuditEntry, HTLCEvent, HTLCOperation, HTLCOperationType,
+    HTLCSpend, HTLCState, OperationStatus, RelayerUTXO, TimelockKind, UtxoReservation, ZcashHTLC,
+    ZcashNetwork,
 };
-use crate::{HTLCOperation, HTLCState, OperationStatus, RelayerUTXO, ZcashHTLC, ZcashNetwork};
 
 use super::connections::{Database, DatabaseError};
+use super::encryption::ColumnCipher;
+use super::export::{ExportFormat, HTLCExport, HTLCRecord};
+use super::jobs::enqueue_due_jobs;
+use super::notify::{notify, PENDING_HTLC_CHANNEL, SIGNED_REDEEM_CHANNEL};
+use super::query::HTLCQuery;
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
 
 pub type DbPool = Pool<ConnectionManager<PgConnection>>;
 
+/// Unique index on `zcash_htlcs(tenant_id, hash_lock, network)`
+const HASH_LOCK_INDEX: &str = "idx_zcash_htlcs_hash_lock_network";
+
+/// Rows per multi-row relayer UTXO insert, well below Postgres' limit of
+/// 65535 bind parameters
+const UTXO_INSERT_BATCH: usize = 1000;
+
+/// Move a sink's cursor up to the newest event before the first one it
+/// has neither received nor given up on, or that is not yet settled (see
+/// [`SET_COMMIT_HORIZON_QUERY`]). Ids below a settled event cannot appear
+/// any more, so the cursor never passes an event still being committed.
+const ADVANCE_SINK_CURSOR_QUERY: &str = "UPDATE event_sink_cursors c SET \
+        last_event_id = COALESCE( \
+            (SELECT MAX(e.id) FROM event_outbox e \
+            WHERE e.tenant_id = c.tenant_id AND e.id > c.last_event_id AND e.id < COALESCE( \
+                (SELECT MIN(b.id) FROM event_outbox b \
+                WHERE b.tenant_id = c.tenant_id AND b.id > c.last_event_id AND ( \
+                    b.commit_horizon > pg_snapshot_xmin(pg_current_snapshot())::text::int8 \
+                    OR NOT EXISTS ( \
+                        SELECT 1 FROM event_deliveries d \
+                        WHERE d.tenant_id = c.tenant_id AND d.sink = c.sink \
+                            AND d.event_id = b.id \
+                            AND (d.delivered_at IS NOT NULL OR d.abandoned_at IS NOT NULL)))), \
+                9223372036854775807)), \
+            c.last_event_id), \
+        updated_at = NOW() \
+    WHERE c.tenant_id = $1 AND c.sink = $2 \
+    RETURNING c.last_event_id";
+
+/// Where a new sink of tenant `$1` starts: the newest event before the
+/// first one not delivered anywhere or not yet settled
+const SINK_CURSOR_START_QUERY: &str = "SELECT COALESCE(MAX(e.id), 0) AS last_event_id \
+    FROM event_outbox e \
+    WHERE e.tenant_id = $1 AND e.id < COALESCE( \
+        (SELECT MIN(b.id) FROM event_outbox b \
+        WHERE b.tenant_id = $1 AND (b.delivered_at IS NULL \
+            OR b.commit_horizon > pg_snapshot_xmin(pg_current_snapshot())::text::int8)), \
+        9223372036854775807)";
+
+/// Stamp event `$1` with the next transaction id to be assigned. It runs
+/// after the event drew its id, and every transaction queueing an event
+/// takes its transaction id before drawing one, so any transaction that
+/// may still commit a lower id is below the horizon. Once no running
+/// transaction is (`pg_snapshot_xmin`), the event is settled.
+const SET_COMMIT_HORIZON_QUERY: &str = "UPDATE event_outbox \
+    SET commit_horizon = pg_snapshot_xmax(pg_current_snapshot())::text::int8 \
+    WHERE id = $1";
+
+/// Outbox events of a tenant old enough to prune that every sink of the
+/// tenant has passed. Without any sink nothing was delivered, so nothing
+/// goes.
+const PRUNE_EVENTS_QUERY: &str = "DELETE FROM event_outbox WHERE id IN ( \
+        SELECT id FROM event_outbox \
+        WHERE tenant_id = $1 AND created_at < $2 \
+            AND id <= (SELECT COALESCE(MIN(last_event_id), 0) FROM event_sink_cursors \
+                WHERE tenant_id = $1) \
+        ORDER BY id \
+        LIMIT $3)";
+
+/// Columns copied from `zcash_htlcs` into `zcash_htlcs_archive`
+const ARCHIVED_COLUMNS: &str = "id, txid, p2sh_address, hash_lock, secret, timelock, \
+    recipient_pubkey, refund_pubkey, amount, network, state, vout, script_hex, \
+    redeem_script_hex, created_at, updated_at, recipient_address, signed_redeem_tx, version, \
+    tenant_id, metadata, timelock_kind, deleted_at, priority, creation_attempts, \
+    next_creation_at, last_error, last_error_kind";
+
 impl Database {
     pub fn create_htlc(&self, htlc: &ZcashHTLC) -> Result<(), DatabaseError> {
-        use crate::models::schema::zcash_htlcs;
+        let mut conn = self.get_connection()?;
+
+        conn.transaction(|conn| {
+            insert_htlc(conn, self.tenant.id(), htlc)?;
+            audit(
+                conn,
+                self.tenant.id(),
+                &htlc.id,
+                None,
+                htlc.state,
+                &self.actor,
+                "created",
+            )?;
+            // Queue it for the relayer to fund and wake it. HTLCs created
+            // with their own funding operation are funded by their creator
+            // instead.
+            enqueue_due_jobs(conn, self.tenant.id(), &htlc.id)?;
+            if htlc.state == HTLCState::Pending && htlc.txid.is_none() {
+                notify(conn, PENDING_HTLC_CHANNEL, &htlc.id)?;
+            }
+            Ok::<_, DatabaseError>(())
+        })?;
+
+        info!("📝 Created HTLC record: {}", htlc.id);
+        Ok(())
+    }
 
+    /// Insert an HTLC and the operation creating it together, so a crash
+    /// cannot leave one without the other. If `htlc.id` names a pending HTLC
+    /// waiting to be funded, as queued by [`Database::create_htlc`] for the
+    /// relayer, that one is filled in instead, provided `htlc`
+    /// [fills](ZcashHTLC::fills) it; otherwise, or when another HTLC has the
+    /// hash lock, this fails with `DuplicateHashLock`. Returns the id of the
+    /// HTLC the operation was recorded for.
+    pub fn create_htlc_with_operation(
+        &self,
+        htlc: &ZcashHTLC,
+        operation: &HTLCOperation,
+    ) -> Result<String, DatabaseError> {
         let mut conn = self.get_connection()?;
 
-        let new_htlc = NewZcashHTLC {
-            id: htlc.id.clone(),
-            p2sh_address: htlc.p2sh_address.clone(),
-            hash_lock: htlc.hash_lock.clone(),
-            timelock: htlc.timelock as i64,
-            recipient_pubkey: htlc.recipient_pubkey.clone(),
-            refund_pubkey: htlc.refund_pubkey.clone(),
-            amount: htlc.amount.clone(),
-            network: htlc.network.as_str().to_string(),
-            state: htlc.state as i16,
-            script_hex: htlc.script_hex.clone(),
-            redeem_script_hex: htlc.redeem_script_hex.clone(),
-        };
+        let htlc_id = conn.transaction(|conn| {
+            let htlc_id = match claim_pending_htlc(conn, self.tenant.id(), htlc)? {
+                Some(htlc_id) => {
+                    let state = HTLCState::Pending;
+                    let reason = "built for funding";
+                    audit(
+                        conn,
+                        self.tenant.id(),
+                        &htlc_id,
+                        Some(state),
+                        state,
+                        &self.actor,
+                        reason,
+                    )?;
+                    htlc_id
+                }
+                None => {
+                    insert_htlc(conn, self.tenant.id(), htlc)?;
+                    audit(
+                        conn,
+                        self.tenant.id(),
+                        &htlc.id,
+                        None,
+                        htlc.state,
+                        &self.actor,
+                        "created",
+                    )?;
+                    htlc.id.clone()
+                }
+            };
+
+            let mut operation = operation.clone();
+            operation.htlc_id = htlc_id.clone();
+            insert_operation(conn, self.tenant.id(), &operation)?;
+            Ok::<_, DatabaseError>(htlc_id)
+        })?;
 
-        diesel::insert_into(zcash_htlcs::table)
-            .values(&new_htlc)
-            .execute(&mut conn)?;
+        info!(
+            "📝 Created HTLC record {} with operation {}",
+            htlc_id, operation.id
+        );
+        Ok(htlc_id)
+    }
 
-        info!("📝 Created HTLC record: {}", htlc.id);
+    /// Record the funding output of an HTLC and mark the funding operation
+    /// broadcast, in one transaction. The HTLC stays pending until
+    /// [`Database::record_funding_confirmed`] locks it.
+    pub fn record_htlc_funding(
+        &self,
+        htlc_id: &str,
+        operation_id: &str,
+        txid: &str,
+        vout: u32,
+    ) -> Result<(), DatabaseError> {
+        let mut conn = self.get_connection()?;
+
+        conn.transaction(|conn| {
+            set_htlc_outpoint(conn, self.tenant.id(), htlc_id, txid, vout)?;
+            set_operation_broadcast(conn, self.tenant.id(), operation_id, txid)?;
+            Ok::<_, DatabaseError>(())
+        })?;
+
+        info!("🔄 Updated HTLC {} with txid: {}", htlc_id, txid);
+        Ok(())
+    }
+
+    /// Mark a funding operation confirmed at `block_height` and lock its
+    /// HTLC at `txid:vout`, in one transaction. Returns `false` if the HTLC
+    /// was already locked or has moved on, e.g. been redeemed.
+    pub fn record_funding_confirmed(
+        &self,
+        htlc_id: &str,
+        operation_id: &str,
+        txid: &str,
+        vout: u32,
+        block_height: u64,
+    ) -> Result<bool, DatabaseError> {
+        let mut conn = self.get_connection()?;
+
+        let locked = conn.transaction(|conn| {
+            let current = locked_state(conn, self.tenant.id(), htlc_id)?;
+            let lock = current != HTLCState::Locked && current.can_transition_to(HTLCState::Locked);
+            if lock {
+                self.fund_htlc(conn, htlc_id, txid, vout)?;
+            }
+            set_operation_confirmed(conn, self.tenant.id(), operation_id, block_height)?;
+            Ok::<_, DatabaseError>(lock)
+        })?;
+
+        if locked {
+            info!("🔒 HTLC {} locked at {}:{}", htlc_id, txid, vout);
+        }
+        Ok(locked)
+    }
+
+    /// Move an HTLC to its final `state`, keeping the revealed `secret` of a
+    /// redeem, and mark the spending operation broadcast, in one transaction
+    pub fn record_htlc_settlement(
+        &self,
+        htlc_id: &str,
+        state: HTLCState,
+        secret: Option<&str>,
+        operation_id: &str,
+        txid: &str,
+    ) -> Result<(), DatabaseError> {
+        let mut conn = self.get_connection()?;
+
+        let reason = format!("{} in {}", state.as_str(), txid);
+        conn.transaction(|conn| {
+            self.move_htlc(conn, htlc_id, state, &reason)?;
+            if let Some(secret) = secret {
+                set_htlc_secret(conn, htlc_id, &self.seal(secret)?)?;
+            }
+            set_operation_broadcast(conn, self.tenant.id(), operation_id, txid)?;
+            Ok::<_, DatabaseError>(())
+        })?;
+
+        info!("🔄 Updated HTLC {} state to: {:?}", htlc_id, state);
         Ok(())
     }
 
+    /// Settle an HTLC whose output `spending_txid` spent on chain without
+    /// this client building it, such as the counterparty's redeem: Redeemed
+    /// keeping the revealed secret, with an `htlc.secret_revealed` event
+    /// carrying it, or Refunded. A Pending HTLC still waiting for its
+    /// funding to confirm is locked at its recorded outpoint first, as the
+    /// spend proves the funding. Returns `false` if it was already settled.
+    pub fn record_htlc_spend(
+        &self,
+        htlc_id: &str,
+        spend: &HTLCSpend,
+        spending_txid: &str,
+    ) -> Result<bool, DatabaseError> {
+        let mut conn = self.get_connection()?;
+
+        let (state, secret) = match spend {
+            HTLCSpend::Redeem { secret } => (HTLCState::Redeemed, Some(secret)),
+            HTLCSpend::Refund => (HTLCState::Refunded, None),
+        };
+        let reason = format!("{} on chain in {}", state.as_str(), spending_txid);
+        let settled = conn.transaction(|conn| {
+            let current = locked_state(conn, self.tenant.id(), htlc_id)?;
+            if matches!(current, HTLCState::Redeemed | HTLCState::Refunded) {
+                return Ok(false);
+            }
+            if current == HTLCState::Pending {
+                use crate::models::schema::zcash_htlcs::dsl;
+
+                let outpoint = dsl::zcash_htlcs
+                    .filter(dsl::id.eq(htlc_id))
+                    .filter(dsl::tenant_id.eq(self.tenant.id()))
+                    .select((dsl::txid, dsl::vout))
+                    .first::<(Option<String>, Option<i32>)>(conn)?;
+                if let (Some(txid), Some(vout)) = outpoint {
+                    self.fund_htlc(conn, htlc_id, &txid, vout as u32)?;
+                }
+            }
+            self.move_htlc(conn, htlc_id, state, &reason)?;
+            if let Some(secret) = secret {
+                set_htlc_secret(conn, htlc_id, &self.seal(secret)?)?;
+                let payload = serde_json::json!({
+                    "htlc_id": htlc_id,
+                    "secret": secret,
+                    "spending_txid": spending_txid,
+                });
+                queue_event(
+                    conn,
+                    self.tenant.id(),
+                    Some(htlc_id),
+                    "htlc.secret_revealed",
+                    &payload,
+                )?;
+            }
+            Ok::<_, DatabaseError>(true)
+        })?;
+
+        if settled {
+            info!("👁️  HTLC {} {}", htlc_id, reason);
+        }
+        Ok(settled)
+    }
+
     pub fn get_htlc_by_id(&self, htlc_id: &str) -> Result<ZcashHTLC, DatabaseError> {
         use crate::models::schema::zcash_htlcs::dsl;
 
-        let mut conn = self.get_connection()?;
+        let mut conn = self.get_read_connection()?;
 
         let htlc = dsl::zcash_htlcs
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
+            .filter(dsl::deleted_at.is_null())
             .filter(dsl::id.eq(htlc_id))
             .select(DbZcashHTLC::as_select())
             .first::<DbZcashHTLC>(&mut conn)
             .map_err(|_| DatabaseError::HTLCNotFound(htlc_id.to_string()))?;
 
-        Ok(htlc.into())
+        self.open_htlc(htlc)
     }
 
     pub fn get_htlc_by_txid(&self, txid: &str) -> Result<ZcashHTLC, DatabaseError> {
         use crate::models::schema::zcash_htlcs::dsl;
 
-        let mut conn = self.get_connection()?;
+        let mut conn = self.get_read_connection()?;
 
         let htlc = dsl::zcash_htlcs
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
+            .filter(dsl::deleted_at.is_null())
             .filter(dsl::txid.eq(txid))
             .select(DbZcashHTLC::as_select())
             .first::<DbZcashHTLC>(&mut conn)
             .map_err(|_| DatabaseError::HTLCNotFound(txid.to_string()))?;
 
-        Ok(htlc.into())
+        self.open_htlc(htlc)
     }
 
     pub fn get_htlc_by_hash_lock(
@@ -78,15 +357,79 @@ impl Database {
     ) -> Result<Option<ZcashHTLC>, DatabaseError> {
         use crate::models::schema::zcash_htlcs::dsl;
 
-        let mut conn = self.get_connection()?;
+        let mut conn = self.get_read_connection()?;
 
         let htlc = dsl::zcash_htlcs
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
+            .filter(dsl::deleted_at.is_null())
             .filter(dsl::hash_lock.eq(hash_lock))
             .select(DbZcashHTLC::as_select())
             .first::<DbZcashHTLC>(&mut conn)
             .optional()?;
 
-        Ok(htlc.map(Into::into))
+        htlc.map(|htlc| self.open_htlc(htlc)).transpose()
+    }
+
+    /// HTLCs locked at the P2SH `address`, newest first
+    pub fn get_htlcs_by_p2sh_address(
+        &self,
+        address: &str,
+    ) -> Result<Vec<ZcashHTLC>, DatabaseError> {
+        use crate::models::schema::zcash_htlcs::dsl;
+
+        let mut conn = self.get_read_connection()?;
+
+        let htlcs = dsl::zcash_htlcs
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::p2sh_address.eq(address))
+            .order(dsl::created_at.desc())
+            .select(DbZcashHTLC::as_select())
+            .load::<DbZcashHTLC>(&mut conn)?;
+
+        htlcs.into_iter().map(|htlc| self.open_htlc(htlc)).collect()
+    }
+
+    /// HTLCs paying out to `address`, as set by
+    /// [`Database::update_htlc_recipient`], newest first
+    pub fn get_htlcs_by_recipient_address(
+        &self,
+        address: &str,
+    ) -> Result<Vec<ZcashHTLC>, DatabaseError> {
+        use crate::models::schema::zcash_htlcs::dsl;
+
+        let mut conn = self.get_read_connection()?;
+
+        let htlcs = dsl::zcash_htlcs
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::recipient_address.eq(address))
+            .order(dsl::created_at.desc())
+            .select(DbZcashHTLC::as_select())
+            .load::<DbZcashHTLC>(&mut conn)?;
+
+        htlcs.into_iter().map(|htlc| self.open_htlc(htlc)).collect()
+    }
+
+    /// HTLCs whose metadata contains `filter`, e.g.
+    /// `json!({"order_id": "A-17"})`, newest first
+    pub fn get_htlcs_by_metadata(
+        &self,
+        filter: &serde_json::Value,
+    ) -> Result<Vec<ZcashHTLC>, DatabaseError> {
+        use crate::models::schema::zcash_htlcs::dsl;
+
+        let mut conn = self.get_read_connection()?;
+
+        let htlcs = dsl::zcash_htlcs
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::metadata.contains(filter))
+            .order(dsl::created_at.desc())
+            .select(DbZcashHTLC::as_select())
+            .load::<DbZcashHTLC>(&mut conn)?;
+
+        htlcs.into_iter().map(|htlc| self.open_htlc(htlc)).collect()
     }
 
     pub fn update_htlc_txid(
@@ -95,271 +438,1590 @@ impl Database {
         txid: &str,
         vout: u32,
     ) -> Result<(), DatabaseError> {
-        use crate::models::schema::zcash_htlcs::dsl;
-
         let mut conn = self.get_connection()?;
-
-        diesel::update(dsl::zcash_htlcs.filter(dsl::id.eq(htlc_id)))
-            .set((
-                dsl::txid.eq(txid),
-                dsl::vout.eq(vout as i32),
-                dsl::state.eq(HTLCState::Locked as i16),
-                dsl::updated_at.eq(Utc::now()),
-            ))
-            .execute(&mut conn)?;
+        conn.transaction(|conn| self.fund_htlc(conn, htlc_id, txid, vout))?;
 
         info!("🔄 Updated HTLC {} with txid: {}", htlc_id, txid);
         Ok(())
     }
 
     pub fn update_htlc_state(&self, htlc_id: &str, state: HTLCState) -> Result<(), DatabaseError> {
-        use crate::models::schema::zcash_htlcs::dsl;
+        self.update_htlc_state_with_reason(htlc_id, state, "state updated")
+    }
 
+    /// Move an HTLC to `state`, recording `reason` in its audit log
+    pub fn update_htlc_state_with_reason(
+        &self,
+        htlc_id: &str,
+        state: HTLCState,
+        reason: &str,
+    ) -> Result<(), DatabaseError> {
         let mut conn = self.get_connection()?;
-
-        diesel::update(dsl::zcash_htlcs.filter(dsl::id.eq(htlc_id)))
-            .set((dsl::state.eq(state as i16), dsl::updated_at.eq(Utc::now())))
-            .execute(&mut conn)?;
+        conn.transaction(|conn| self.move_htlc(conn, htlc_id, state, reason))?;
 
         info!("🔄 Updated HTLC {} state to: {:?}", htlc_id, state);
         Ok(())
     }
 
+    /// Move an HTLC to `state` only if it is still at `expected_version`,
+    /// i.e. nobody changed it since it was read. Returns the new version;
+    /// a lost race fails with [`DatabaseError::VersionConflict`].
+    pub fn update_htlc_state_if_version(
+        &self,
+        htlc_id: &str,
+        state: HTLCState,
+        expected_version: u32,
+        reason: &str,
+    ) -> Result<u32, DatabaseError> {
+        let mut conn = self.get_connection()?;
+
+        conn.transaction(|conn| {
+            check_version(conn, self.tenant.id(), htlc_id, expected_version)?;
+            self.move_htlc(conn, htlc_id, state, reason)
+        })?;
+
+        info!(
+            "🔄 Updated HTLC {} state to: {:?} (version {})",
+            htlc_id,
+            state,
+            expected_version + 1
+        );
+        Ok(expected_version + 1)
+    }
+
     pub fn update_htlc_secret(&self, htlc_id: &str, secret: &str) -> Result<(), DatabaseError> {
+        let mut conn = self.get_connection()?;
+
+        conn.transaction(|conn| {
+            let state = locked_state(conn, self.tenant.id(), htlc_id)?;
+            set_htlc_secret(conn, htlc_id, &self.seal(secret)?)?;
+            audit(
+                conn,
+                self.tenant.id(),
+                htlc_id,
+                Some(state),
+                state,
+                &self.actor,
+                "secret revealed",
+            )?;
+            Ok::<_, DatabaseError>(())
+        })?;
+
+        info!("🔐 Updated HTLC {} with secret", htlc_id);
+        Ok(())
+    }
+
+    /// Hide an erroneous or test HTLC from every operational query and free
+    /// its hash lock, keeping the row and its audit log. Admin listings see
+    /// it with [`HTLCQuery::with_include_deleted`].
+    ///
+    /// Only HTLCs that never locked funds can be deleted: unfunded
+    /// `Pending` ones, `Failed` and `NeedsIntervention`. Others fail with
+    /// `NotDeletable`, as hiding them would stop the relayer from redeeming
+    /// or refunding what they hold.
+    pub fn soft_delete_htlc(&self, htlc_id: &str, reason: &str) -> Result<(), DatabaseError> {
         use crate::models::schema::zcash_htlcs::dsl;
 
         let mut conn = self.get_connection()?;
 
-        diesel::update(dsl::zcash_htlcs.filter(dsl::id.eq(htlc_id)))
-            .set((dsl::secret.eq(secret), dsl::updated_at.eq(Utc::now())))
-            .execute(&mut conn)?;
+        conn.transaction(|conn| {
+            let (state, txid) = dsl::zcash_htlcs
+                .filter(dsl::id.eq(htlc_id))
+                .filter(dsl::tenant_id.eq(self.tenant.id()))
+                .filter(dsl::deleted_at.is_null())
+                .select((dsl::state, dsl::txid))
+                .for_update()
+                .first::<(i16, Option<String>)>(conn)
+                .optional()?
+                .map(|(state, txid)| (HTLCState::from_i16(state), txid))
+                .ok_or_else(|| DatabaseError::HTLCNotFound(htlc_id.to_string()))?;
+            let deletable = match state {
+                HTLCState::Pending => txid.is_none(),
+                HTLCState::Failed | HTLCState::NeedsIntervention => true,
+                _ => false,
+            };
+            if !deletable {
+                return Err(DatabaseError::NotDeletable {
+                    htlc_id: htlc_id.to_string(),
+                    state,
+                });
+            }
+            diesel::update(dsl::zcash_htlcs.filter(dsl::id.eq(htlc_id)))
+                .set((
+                    dsl::deleted_at.eq(Utc::now()),
+                    dsl::updated_at.eq(Utc::now()),
+                    dsl::version.eq(dsl::version + 1),
+                ))
+                .execute(conn)?;
+            let reason = format!("deleted: {}", reason);
+            audit(
+                conn,
+                self.tenant.id(),
+                htlc_id,
+                Some(state),
+                state,
+                &self.actor,
+                &reason,
+            )?;
+            Ok::<_, DatabaseError>(())
+        })?;
+
+        info!("🗑️ Soft-deleted HTLC: {}", htlc_id);
+        Ok(())
+    }
 
-        info!("🔐 Updated HTLC {} with secret", htlc_id);
+    /// Undo [`Database::soft_delete_htlc`]. Fails with `DuplicateHashLock`
+    /// if another HTLC took over the hash lock in the meantime.
+    pub fn restore_htlc(&self, htlc_id: &str) -> Result<(), DatabaseError> {
+        use crate::models::schema::zcash_htlcs::dsl;
+
+        let mut conn = self.get_connection()?;
+
+        conn.transaction(|conn| {
+            let restored = diesel::update(
+                dsl::zcash_htlcs
+                    .filter(dsl::id.eq(htlc_id))
+                    .filter(dsl::tenant_id.eq(self.tenant.id()))
+                    .filter(dsl::deleted_at.is_not_null()),
+            )
+            .set((
+                dsl::deleted_at.eq(None::<DateTime<Utc>>),
+                dsl::updated_at.eq(Utc::now()),
+                dsl::version.eq(dsl::version + 1),
+            ))
+            .returning((dsl::state, dsl::hash_lock))
+            .get_result::<(i16, String)>(conn)
+            .optional();
+
+            let (state, _) = match restored {
+                Ok(Some(restored)) => restored,
+                Ok(None) => return Err(DatabaseError::HTLCNotFound(htlc_id.to_string())),
+                Err(e) => return Err(duplicate_hash_lock(e, htlc_id)),
+            };
+            let state = HTLCState::from_i16(state);
+            audit(
+                conn,
+                self.tenant.id(),
+                htlc_id,
+                Some(state),
+                state,
+                &self.actor,
+                "restored",
+            )?;
+            enqueue_due_jobs(conn, self.tenant.id(), htlc_id)?;
+            Ok::<_, DatabaseError>(())
+        })?;
+
+        info!("♻️ Restored HTLC: {}", htlc_id);
         Ok(())
     }
 
-    pub fn get_pending_htlcs(
+    /// Count a failed attempt to fund a Pending HTLC. It is retried after
+    /// [`operation_retry_delay`] until `max_attempts` attempts failed, then
+    /// moved to `NeedsIntervention` for an operator. HTLCs no longer Pending,
+    /// e.g. funded after all, are left alone. Returns the HTLC's state.
+    pub fn record_creation_failure(
         &self,
-        network: ZcashNetwork,
-    ) -> Result<Vec<ZcashHTLC>, DatabaseError> {
+        htlc_id: &str,
+        error: &str,
+        kind: FailureKind,
+        max_attempts: u32,
+    ) -> Result<HTLCState, DatabaseError> {
         use crate::models::schema::zcash_htlcs::dsl;
 
         let mut conn = self.get_connection()?;
 
-        let htlcs = dsl::zcash_htlcs
-            .filter(dsl::network.eq(network.as_str()))
-            .filter(dsl::state.eq(HTLCState::Locked as i16))
-            .select(DbZcashHTLC::as_select())
-            .load::<DbZcashHTLC>(&mut conn)?;
+        let state = conn.transaction(|conn| {
+            let state = locked_state(conn, self.tenant.id(), htlc_id)?;
+            if state != HTLCState::Pending {
+                return Ok(state);
+            }
+
+            let now = Utc::now();
+            let attempts: i32 = diesel::update(dsl::zcash_htlcs.filter(dsl::id.eq(htlc_id)))
+                .set((
+                    dsl::creation_attempts.eq(dsl::creation_attempts + 1),
+                    dsl::last_error.eq(error),
+                    dsl::last_error_kind.eq(kind.as_str()),
+                    dsl::updated_at.eq(now),
+                ))
+                .returning(dsl::creation_attempts)
+                .get_result(conn)?;
+
+            if attempts as u32 >= max_attempts {
+                let reason = format!("creation failed {} times: {}", attempts, error);
+                self.move_htlc(conn, htlc_id, HTLCState::NeedsIntervention, &reason)?;
+                return Ok(HTLCState::NeedsIntervention);
+            }
+            diesel::update(dsl::zcash_htlcs.filter(dsl::id.eq(htlc_id)))
+                .set(dsl::next_creation_at.eq(now + operation_retry_delay(attempts as u32)))
+                .execute(conn)?;
+            Ok::<_, DatabaseError>(state)
+        })?;
+
+        Ok(state)
+    }
+
+    /// HTLCs waiting in `NeedsIntervention`, longest waiting first, from
+    /// the read replica if one is configured
+    pub fn get_htlcs_needing_intervention(
+        &self,
+        limit: u32,
+    ) -> Result<Vec<DeadLetteredHTLC>, DatabaseError> {
+        use crate::models::schema::zcash_htlcs::dsl;
+
+        let mut conn = self.get_read_connection()?;
 
-        Ok(htlcs.into_iter().map(Into::into).collect())
+        let rows = dsl::zcash_htlcs
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::state.eq(HTLCState::NeedsIntervention as i16))
+            .order(dsl::updated_at.asc())
+            .limit(limit as i64)
+            .select((
+                DbZcashHTLC::as_select(),
+                dsl::creation_attempts,
+                dsl::last_error,
+                dsl::last_error_kind,
+            ))
+            .load::<(DbZcashHTLC, i32, Option<String>, Option<String>)>(&mut conn)?;
+
+        rows.into_iter()
+            .map(|(htlc, attempts, last_error, last_error_kind)| {
+                Ok(DeadLetteredHTLC {
+                    htlc: self.open_htlc(htlc)?,
+                    creation_attempts: attempts as u32,
+                    last_error,
+                    last_error_kind: last_error_kind
+                        .map(|kind| kind.parse().unwrap_or(FailureKind::Other)),
+                })
+            })
+            .collect()
     }
 
-    pub fn get_expired_htlcs(&self, current_block: u64) -> Result<Vec<ZcashHTLC>, DatabaseError> {
+    /// Put an HTLC in `NeedsIntervention` back in the creation queue with
+    /// a fresh set of attempts, e.g. after topping up the hot wallet
+    pub fn requeue_htlc(&self, htlc_id: &str) -> Result<(), DatabaseError> {
         use crate::models::schema::zcash_htlcs::dsl;
 
         let mut conn = self.get_connection()?;
 
-        let htlcs = dsl::zcash_htlcs
-            .filter(dsl::state.eq(HTLCState::Locked as i16))
-            .filter(dsl::timelock.lt(current_block as i64))
-            .select(DbZcashHTLC::as_select())
-            .load::<DbZcashHTLC>(&mut conn)?;
-
-        Ok(htlcs.into_iter().map(Into::into).collect())
+        conn.transaction(|conn| {
+            let state = locked_state(conn, self.tenant.id(), htlc_id)?;
+            if state != HTLCState::NeedsIntervention {
+                return Err(DatabaseError::IllegalTransition {
+                    htlc_id: htlc_id.to_string(),
+                    from: state,
+                    to: HTLCState::Pending,
+                });
+            }
+            diesel::update(dsl::zcash_htlcs.filter(dsl::id.eq(htlc_id)))
+                .set((
+                    dsl::creation_attempts.eq(0),
+                    dsl::next_creation_at.eq(None::<DateTime<Utc>>),
+                    dsl::last_error.eq(None::<String>),
+                    dsl::last_error_kind.eq(None::<String>),
+                ))
+                .execute(conn)?;
+            self.move_htlc(conn, htlc_id, HTLCState::Pending, "requeued")?;
+            Ok::<_, DatabaseError>(())
+        })?;
+
+        info!("🔁 Requeued HTLC: {}", htlc_id);
+        Ok(())
     }
 
-    pub fn create_operation(&self, operation: &HTLCOperation) -> Result<(), DatabaseError> {
-        use crate::models::schema::htlc_operations;
+    /// Move up to `limit` Redeemed or Refunded HTLCs last changed before
+    /// `settled_before` into `zcash_htlcs_archive` and delete their
+    /// operations, keeping the tables the relayer polls small. Their audit
+    /// log stays. Returns how many HTLCs were archived.
+    pub fn archive_settled_htlcs(
+        &self,
+        settled_before: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<usize, DatabaseError> {
+        use crate::models::schema::{htlc_operations, zcash_htlcs};
+        use diesel::sql_types::{Array, Text};
 
         let mut conn = self.get_connection()?;
 
-        let new_op = NewHTLCOperation {
-            id: operation.id.clone(),
-            htlc_id: operation.htlc_id.clone(),
-            operation_type: operation.operation_type.as_str().to_string(),
-            raw_tx_hex: operation.raw_tx_hex.clone(),
-            status: operation.status.as_str().to_string(),
-        };
+        let archived = conn.transaction(|conn| {
+            let ids = zcash_htlcs::table
+                .filter(zcash_htlcs::tenant_id.eq(self.tenant.id()))
+                .filter(
+                    zcash_htlcs::state
+                        .eq_any([HTLCState::Redeemed as i16, HTLCState::Refunded as i16]),
+                )
+                .filter(zcash_htlcs::updated_at.lt(settled_before))
+                .order(zcash_htlcs::updated_at.asc())
+                .limit(limit as i64)
+                .select(zcash_htlcs::id)
+                .for_update()
+                .load::<String>(conn)?;
+            if ids.is_empty() {
+                return Ok::<_, DatabaseError>(0);
+            }
+
+            diesel::sql_query(
+                "INSERT INTO htlc_audit_log (htlc_id, old_state, new_state, actor, reason) \
+                 SELECT id, state, state, $2, 'archived' FROM zcash_htlcs WHERE id = ANY($1)",
+            )
+            .bind::<Array<Text>, _>(&ids)
+            .bind::<Text, _>(&self.actor)
+            .execute(conn)?;
+            diesel::sql_query(format!(
+                "INSERT INTO zcash_htlcs_archive ({0}) SELECT {0} FROM zcash_htlcs WHERE id = ANY($1)",
+                ARCHIVED_COLUMNS
+            ))
+            .bind::<Array<Text>, _>(&ids)
+            .execute(conn)?;
+
+            diesel::delete(htlc_operations::table.filter(htlc_operations::htlc_id.eq_any(&ids)))
+                .execute(conn)?;
+            Ok(
+                diesel::delete(zcash_htlcs::table.filter(zcash_htlcs::id.eq_any(&ids)))
+                    .execute(conn)?,
+            )
+        })?;
+
+        if archived > 0 {
+            info!("🗄️ Archived {} settled HTLC(s)", archived);
+        }
+        Ok(archived)
+    }
 
-        diesel::insert_into(htlc_operations::table)
-            .values(&new_op)
-            .execute(&mut conn)?;
+    /// Write every HTLC with its operations to `writer`, e.g. to move them
+    /// to another environment or keep a backup. Archived HTLCs are included,
+    /// without the operations archiving deleted.
+    pub fn export_htlcs<W: std::io::Write>(
+        &self,
+        format: ExportFormat,
+        writer: W,
+    ) -> Result<usize, DatabaseError> {
+        use crate::models::schema::{htlc_operations, zcash_htlcs, zcash_htlcs_archive as archive};
 
-        info!("📝 Created operation record: {}", operation.id);
-        Ok(())
+        let mut conn = self.get_connection()?;
+
+        // One snapshot, so operations match the HTLCs they belong to
+        let (htlcs, operations) = conn.build_transaction().repeatable_read().run(|conn| {
+            let mut htlcs = zcash_htlcs::table
+                .filter(zcash_htlcs::tenant_id.eq(self.tenant.id()))
+                .select(DbZcashHTLC::as_select())
+                .load::<DbZcashHTLC>(conn)?;
+            htlcs.extend(
+                archive::table
+                    .filter(archive::tenant_id.eq(self.tenant.id()))
+                    .select((
+                        archive::id,
+                        archive::txid,
+                        archive::p2sh_address,
+                        archive::hash_lock,
+                        archive::secret,
+                        archive::timelock,
+                        archive::recipient_pubkey,
+                        archive::refund_pubkey,
+                        archive::amount,
+                        archive::network,
+                        archive::state,
+                        archive::vout,
+                        archive::script_hex,
+                        archive::redeem_script_hex,
+                        archive::recipient_address,
+                        archive::signed_redeem_tx,
+                        archive::created_at,
+                        archive::updated_at,
+                        archive::version,
+                        archive::metadata,
+                        archive::timelock_kind,
+                        archive::deleted_at,
+                        archive::priority,
+                    ))
+                    .load::<DbZcashHTLC>(conn)?,
+            );
+            htlcs.sort_by_key(|htlc| htlc.created_at);
+            let operations = htlc_operations::table
+                .filter(htlc_operations::tenant_id.eq(self.tenant.id()))
+                .order(htlc_operations::created_at.asc())
+                .select(DbHTLCOperation::as_select())
+                .load::<DbHTLCOperation>(conn)?;
+            Ok::<_, DatabaseError>((htlcs, operations))
+        })?;
+
+        let mut by_htlc: HashMap<String, Vec<HTLCOperation>> = HashMap::new();
+        for operation in operations {
+            by_htlc
+                .entry(operation.htlc_id.clone())
+                .or_default()
+                .push(operation.into());
+        }
+        let records = htlcs
+            .into_iter()
+            .map(|htlc| {
+                Ok(HTLCRecord {
+                    operations: by_htlc.remove(&htlc.id).unwrap_or_default(),
+                    htlc: self.open_htlc(htlc)?,
+                })
+            })
+            .collect::<Result<Vec<_>, DatabaseError>>()?;
+
+        let count = records.len();
+        HTLCExport::new(records).write(format, writer)?;
+
+        info!("📦 Exported {} HTLC(s)", count);
+        Ok(count)
     }
 
-    pub fn update_operation_signed(
+    /// Restore HTLCs and their operations written by [`Database::export_htlcs`]
+    /// into this database's tenant. HTLCs that already exist are left
+    /// untouched; everything else goes in one transaction. Returns how many
+    /// HTLCs were added.
+    pub fn import_htlcs<R: std::io::Read>(
         &self,
-        operation_id: &str,
-        signed_tx_hex: &str,
-    ) -> Result<(), DatabaseError> {
-        use crate::models::schema::htlc_operations::dsl;
+        format: ExportFormat,
+        reader: R,
+    ) -> Result<usize, DatabaseError> {
+        use crate::models::schema::{htlc_operations, zcash_htlcs};
 
+        let export = HTLCExport::read(format, reader)?;
         let mut conn = self.get_connection()?;
 
-        diesel::update(dsl::htlc_operations.filter(dsl::id.eq(operation_id)))
-            .set((
-                dsl::signed_tx_hex.eq(signed_tx_hex),
-                dsl::status.eq(OperationStatus::Signed.as_str()),
-                dsl::updated_at.eq(Utc::now()),
-            ))
-            .execute(&mut conn)?;
+        let imported = conn.transaction(|conn| {
+            let mut imported = 0;
+            for record in &export.records {
+                let mut htlc = DbZcashHTLC::from(&record.htlc);
+                htlc.secret = htlc.secret.as_deref().map(|s| self.seal(s)).transpose()?;
+                htlc.signed_redeem_tx = htlc
+                    .signed_redeem_tx
+                    .as_deref()
+                    .map(|tx| self.seal(tx))
+                    .transpose()?;
+
+                let inserted = diesel::insert_into(zcash_htlcs::table)
+                    .values((&htlc, zcash_htlcs::tenant_id.eq(self.tenant.id())))
+                    .on_conflict(zcash_htlcs::id)
+                    .do_nothing()
+                    .execute(conn)
+                    .map_err(|e| duplicate_hash_lock(e, &htlc.hash_lock))?;
+                if inserted == 0 {
+                    continue;
+                }
+
+                let reason = format!("imported from export v{}", export.schema_version);
+                audit(
+                    conn,
+                    self.tenant.id(),
+                    &record.htlc.id,
+                    None,
+                    record.htlc.state,
+                    &self.actor,
+                    &reason,
+                )?;
+                for operation in &record.operations {
+                    diesel::insert_into(htlc_operations::table)
+                        .values((
+                            &DbHTLCOperation::from(operation),
+                            htlc_operations::tenant_id.eq(self.tenant.id()),
+                        ))
+                        .on_conflict_do_nothing()
+                        .execute(conn)?;
+                }
+                enqueue_due_jobs(conn, self.tenant.id(), &record.htlc.id)?;
+                imported += 1;
+            }
+            Ok::<_, DatabaseError>(imported)
+        })?;
 
-        info!("✍️ Signed operation: {}", operation_id);
+        info!(
+            "📦 Imported {} of {} HTLC(s)",
+            imported,
+            export.records.len()
+        );
+        Ok(imported)
+    }
+
+    /// Queue an event about the hot wallet rather than an HTLC, such as a
+    /// `wallet.low_funds` alert, for delivery with the tenant's HTLC events
+    pub fn queue_wallet_event(
+        &self,
+        event_type: &str,
+        payload: serde_json::Value,
+    ) -> Result<(), DatabaseError> {
+        let mut conn = self.get_connection()?;
+        queue_event(&mut conn, self.tenant.id(), None, event_type, &payload)?;
         Ok(())
     }
 
-    pub fn update_operation_broadcast(
+    /// Queue an alert about the relayer itself, such as
+    /// `relayer.circuit_open`, for delivery with the tenant's HTLC events
+    pub fn queue_relayer_event(
         &self,
-        operation_id: &str,
-        txid: &str,
+        event_type: &str,
+        payload: serde_json::Value,
     ) -> Result<(), DatabaseError> {
-        use crate::models::schema::htlc_operations::dsl;
+        let mut conn = self.get_connection()?;
+        queue_event(&mut conn, self.tenant.id(), None, event_type, &payload)?;
+        Ok(())
+    }
+
+    /// This tenant's outbox events not yet delivered, oldest first
+    pub fn get_undelivered_events(&self, limit: u32) -> Result<Vec<HTLCEvent>, DatabaseError> {
+        use crate::models::schema::event_outbox::dsl;
 
         let mut conn = self.get_connection()?;
 
-        diesel::update(dsl::htlc_operations.filter(dsl::id.eq(operation_id)))
-            .set((
-                dsl::txid.eq(txid),
-                dsl::status.eq(OperationStatus::Broadcast.as_str()),
-                dsl::broadcast_at.eq(Utc::now()),
-                dsl::updated_at.eq(Utc::now()),
-            ))
-            .execute(&mut conn)?;
+        let events = dsl::event_outbox
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
+            .filter(dsl::delivered_at.is_null())
+            .order(dsl::id.asc())
+            .limit(limit as i64)
+            .select(DbHTLCEvent::as_select())
+            .load::<DbHTLCEvent>(&mut conn)?;
+
+        Ok(events.into_iter().map(Into::into).collect())
+    }
+
+    pub fn mark_event_delivered(&self, event_id: i64) -> Result<(), DatabaseError> {
+        use crate::models::schema::event_outbox::dsl;
+
+        let mut conn = self.get_connection()?;
+
+        diesel::update(
+            dsl::event_outbox
+                .filter(dsl::id.eq(event_id))
+                .filter(dsl::tenant_id.eq(self.tenant.id())),
+        )
+        .set(dsl::delivered_at.eq(Utc::now()))
+        .execute(&mut conn)?;
 
-        info!("📡 Broadcast operation: {}", operation_id);
         Ok(())
     }
 
-    pub fn update_operation_confirmed(
-        &self,
-        operation_id: &str,
-        block_height: u64,
-    ) -> Result<(), DatabaseError> {
-        use crate::models::schema::htlc_operations::dsl;
+    /// Count a failed delivery of `event_id`, keeping it queued
+    pub fn mark_event_failed(&self, event_id: i64, error: &str) -> Result<(), DatabaseError> {
+        use crate::models::schema::event_outbox::dsl;
 
         let mut conn = self.get_connection()?;
 
-        diesel::update(dsl::htlc_operations.filter(dsl::id.eq(operation_id)))
-            .set((
-                dsl::status.eq(OperationStatus::Confirmed.as_str()),
-                dsl::block_height.eq(block_height as i64),
-                dsl::confirmed_at.eq(Utc::now()),
-                dsl::updated_at.eq(Utc::now()),
-            ))
-            .execute(&mut conn)?;
+        diesel::update(
+            dsl::event_outbox
+                .filter(dsl::id.eq(event_id))
+                .filter(dsl::tenant_id.eq(self.tenant.id())),
+        )
+        .set((
+            dsl::attempts.eq(dsl::attempts + 1),
+            dsl::last_error.eq(error),
+        ))
+        .execute(&mut conn)?;
 
-        info!("✅ Confirmed operation: {}", operation_id);
         Ok(())
     }
 
-    pub fn update_operation_failed(
+    /// Up to `limit` events `sink` has not received yet, oldest first,
+    /// leaving out those backing off after a failed delivery. Each tenant's
+    /// sinks only see the tenant's events; a sink seen for the first time
+    /// starts at the tenant's oldest event not delivered anywhere.
+    pub fn get_events_for_sink(
         &self,
-        operation_id: &str,
-        error: &str,
-    ) -> Result<(), DatabaseError> {
-        use crate::models::schema::htlc_operations::dsl;
+        sink: &str,
+        limit: u32,
+    ) -> Result<Vec<HTLCEvent>, DatabaseError> {
+        use crate::models::schema::{event_deliveries, event_outbox, event_sink_cursors};
 
         let mut conn = self.get_connection()?;
 
-        diesel::update(dsl::htlc_operations.filter(dsl::id.eq(operation_id)))
-            .set((
-                dsl::status.eq(OperationStatus::Failed.as_str()),
-                dsl::error_message.eq(error),
-                dsl::updated_at.eq(Utc::now()),
-            ))
-            .execute(&mut conn)?;
+        let tenant = self.tenant.id();
+        let cursor = event_sink_cursors::table
+            .find((tenant, sink))
+            .select(event_sink_cursors::last_event_id)
+            .first::<i64>(&mut conn)
+            .optional()?;
+        let last_event_id = match cursor {
+            Some(last_event_id) => last_event_id,
+            None => {
+                let start = diesel::sql_query(SINK_CURSOR_START_QUERY)
+                    .bind::<diesel::sql_types::Text, _>(tenant)
+                    .get_result::<SinkCursor>(&mut conn)?
+                    .last_event_id;
+
+                diesel::insert_into(event_sink_cursors::table)
+                    .values((
+                        event_sink_cursors::tenant_id.eq(tenant),
+                        event_sink_cursors::sink.eq(sink),
+                        event_sink_cursors::last_event_id.eq(start),
+                    ))
+                    .on_conflict_do_nothing()
+                    .execute(&mut conn)?;
+                info!("📬 Delivering events after {} to {}", start, sink);
+                start
+            }
+        };
 
-        info!("❌ Failed operation: {} - {}", operation_id, error);
-        Ok(())
+        let held_back = event_deliveries::table
+            .filter(event_deliveries::tenant_id.eq(tenant))
+            .filter(event_deliveries::sink.eq(sink))
+            .filter(
+                event_deliveries::delivered_at
+                    .is_not_null()
+                    .or(event_deliveries::abandoned_at.is_not_null())
+                    .or(event_deliveries::next_attempt_at
+                        .gt(Utc::now())
+                        .assume_not_null()),
+            )
+            .select(event_deliveries::event_id);
+        let events = event_outbox::table
+            .filter(event_outbox::tenant_id.eq(tenant))
+            .filter(event_outbox::id.gt(last_event_id))
+            .filter(event_outbox::id.ne_all(held_back))
+            .order(event_outbox::id.asc())
+            .limit(limit as i64)
+            .select(DbHTLCEvent::as_select())
+            .load::<DbHTLCEvent>(&mut conn)?;
+
+        Ok(events.into_iter().map(Into::into).collect())
     }
 
-    pub fn get_operation_by_id(&self, operation_id: &str) -> Result<HTLCOperation, DatabaseError> {
-        use crate::models::schema::htlc_operations::dsl;
+    /// Record that `sink` received `event_id`
+    pub fn mark_event_delivered_to(&self, sink: &str, event_id: i64) -> Result<(), DatabaseError> {
+        use crate::models::schema::{event_deliveries, event_outbox};
 
         let mut conn = self.get_connection()?;
 
-        let operation = dsl::htlc_operations
-            .filter(dsl::id.eq(operation_id))
-            .select(DbHTLCOperation::as_select())
-            .first::<DbHTLCOperation>(&mut conn)
-            .map_err(|_| DatabaseError::OperationNotFound(operation_id.to_string()))?;
+        conn.transaction(|conn| {
+            let now = Utc::now();
+            diesel::insert_into(event_deliveries::table)
+                .values((
+                    event_deliveries::tenant_id.eq(self.tenant.id()),
+                    event_deliveries::sink.eq(sink),
+                    event_deliveries::event_id.eq(event_id),
+                    event_deliveries::delivered_at.eq(now),
+                ))
+                .on_conflict((
+                    event_deliveries::tenant_id,
+                    event_deliveries::sink,
+                    event_deliveries::event_id,
+                ))
+                .do_update()
+                .set((
+                    event_deliveries::delivered_at.eq(now),
+                    event_deliveries::next_attempt_at.eq(None::<DateTime<Utc>>),
+                ))
+                .execute(conn)?;
+            diesel::update(
+                event_outbox::table
+                    .filter(event_outbox::id.eq(event_id))
+                    .filter(event_outbox::tenant_id.eq(self.tenant.id()))
+                    .filter(event_outbox::delivered_at.is_null()),
+            )
+            .set(event_outbox::delivered_at.eq(now))
+            .execute(conn)?;
+            advance_sink_cursor(conn, self.tenant.id(), sink)?;
+            Ok::<_, DatabaseError>(())
+        })
+    }
+
+    /// Count a failed delivery of `event_id` to `sink` and hold the event
+    /// back from it for [`event_retry_delay`], returning how often it failed.
+    /// Later events go on to the sink meanwhile.
+    pub fn mark_event_failed_for(
+        &self,
+        sink: &str,
+        event_id: i64,
+        error: &str,
+    ) -> Result<u32, DatabaseError> {
+        use crate::models::schema::{event_deliveries, event_outbox};
+
+        let mut conn = self.get_connection()?;
+
+        conn.transaction(|conn| {
+            let attempts: i32 = diesel::insert_into(event_deliveries::table)
+                .values((
+                    event_deliveries::tenant_id.eq(self.tenant.id()),
+                    event_deliveries::sink.eq(sink),
+                    event_deliveries::event_id.eq(event_id),
+                    event_deliveries::attempts.eq(1),
+                    event_deliveries::last_error.eq(error),
+                ))
+                .on_conflict((
+                    event_deliveries::tenant_id,
+                    event_deliveries::sink,
+                    event_deliveries::event_id,
+                ))
+                .do_update()
+                .set((
+                    event_deliveries::attempts.eq(event_deliveries::attempts + 1),
+                    event_deliveries::last_error.eq(error),
+                ))
+                .returning(event_deliveries::attempts)
+                .get_result(conn)?;
+
+            diesel::update(
+                event_deliveries::table
+                    .filter(event_deliveries::tenant_id.eq(self.tenant.id()))
+                    .filter(event_deliveries::sink.eq(sink))
+                    .filter(event_deliveries::event_id.eq(event_id)),
+            )
+            .set(
+                event_deliveries::next_attempt_at
+                    .eq(Utc::now() + event_retry_delay(attempts as u32)),
+            )
+            .execute(conn)?;
+
+            diesel::update(
+                event_outbox::table
+                    .filter(event_outbox::id.eq(event_id))
+                    .filter(event_outbox::tenant_id.eq(self.tenant.id())),
+            )
+            .set((
+                event_outbox::attempts.eq(event_outbox::attempts + 1),
+                event_outbox::last_error.eq(error),
+            ))
+            .execute(conn)?;
+            Ok::<_, DatabaseError>(attempts as u32)
+        })
+    }
+
+    /// Stop retrying `event_id` for `sink`, e.g. after it failed too often;
+    /// the delivery row keeps the last error until the sink moves past it
+    pub fn abandon_event_for(&self, sink: &str, event_id: i64) -> Result<(), DatabaseError> {
+        use crate::models::schema::event_deliveries;
+
+        let mut conn = self.get_connection()?;
+
+        conn.transaction(|conn| {
+            diesel::update(
+                event_deliveries::table
+                    .filter(event_deliveries::tenant_id.eq(self.tenant.id()))
+                    .filter(event_deliveries::sink.eq(sink))
+                    .filter(event_deliveries::event_id.eq(event_id)),
+            )
+            .set((
+                event_deliveries::abandoned_at.eq(Utc::now()),
+                event_deliveries::next_attempt_at.eq(None::<DateTime<Utc>>),
+            ))
+            .execute(conn)?;
+            advance_sink_cursor(conn, self.tenant.id(), sink)?;
+            Ok::<_, DatabaseError>(())
+        })?;
+
+        warn!("🪦 Gave up delivering event {} to {}", event_id, sink);
+        Ok(())
+    }
+
+    /// Delete up to `limit` of this tenant's outbox events queued before
+    /// `queued_before` that every sink of the tenant has received or given
+    /// up on. Returns how many were
+    /// deleted.
+    pub fn prune_events(
+        &self,
+        queued_before: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<usize, DatabaseError> {
+        use diesel::sql_types::{BigInt, Text, Timestamptz};
+
+        let mut conn = self.get_connection()?;
+
+        let pruned = diesel::sql_query(PRUNE_EVENTS_QUERY)
+            .bind::<Text, _>(self.tenant.id())
+            .bind::<Timestamptz, _>(queued_before)
+            .bind::<BigInt, _>(limit as i64)
+            .execute(&mut conn)?;
+
+        if pruned > 0 {
+            info!("🧹 Pruned {} delivered event(s)", pruned);
+        }
+        Ok(pruned)
+    }
+
+    /// Audit log of `htlc_id`, oldest change first
+    pub fn get_audit_log(&self, htlc_id: &str) -> Result<Vec<HTLCAuditEntry>, DatabaseError> {
+        use crate::models::schema::htlc_audit_log::dsl;
+
+        let mut conn = self.get_read_connection()?;
+
+        let entries = dsl::htlc_audit_log
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
+            .filter(dsl::htlc_id.eq(htlc_id))
+            .order(dsl::id.asc())
+            .select(DbHTLCAuditEntry::as_select())
+            .load::<DbHTLCAuditEntry>(&mut conn)?;
+
+        Ok(entries.into_iter().map(Into::into).collect())
+    }
+
+    /// Audit log entries of this tenant's HTLCs recorded in `[since, until)`, oldest
+    /// first
+    pub fn get_audit_log_between(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<HTLCAuditEntry>, DatabaseError> {
+        use crate::models::schema::htlc_audit_log::dsl;
+
+        let mut conn = self.get_read_connection()?;
+
+        let entries = dsl::htlc_audit_log
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
+            .filter(dsl::created_at.ge(since))
+            .filter(dsl::created_at.lt(until))
+            .order(dsl::id.asc())
+            .select(DbHTLCAuditEntry::as_select())
+            .load::<DbHTLCAuditEntry>(&mut conn)?;
+
+        Ok(entries.into_iter().map(Into::into).collect())
+    }
+
+    /// `value` encrypted for storage if a column cipher is configured
+    fn seal(&self, value: &str) -> Result<String, DatabaseError> {
+        match &self.cipher {
+            Some(cipher) => cipher.encrypt(value),
+            None => Ok(value.to_string()),
+        }
+    }
+
+    fn unseal(&self, value: String) -> Result<String, DatabaseError> {
+        match &self.cipher {
+            Some(cipher) => cipher.decrypt(&value),
+            None if ColumnCipher::is_encrypted(&value) => Err(DatabaseError::EncryptionError(
+                "column is encrypted but no key is configured".to_string(),
+            )),
+            None => Ok(value),
+        }
+    }
+
+    /// HTLC read from the database, with its sensitive columns decrypted
+    pub(super) fn open_htlc(&self, mut htlc: DbZcashHTLC) -> Result<ZcashHTLC, DatabaseError> {
+        htlc.secret = htlc.secret.map(|s| self.unseal(s)).transpose()?;
+        htlc.signed_redeem_tx = htlc
+            .signed_redeem_tx
+            .map(|tx| self.unseal(tx))
+            .transpose()?;
+        Ok(htlc.into())
+    }
+
+    /// Lock an HTLC at its funding output and audit the change
+    fn fund_htlc(
+        &self,
+        conn: &mut PgConnection,
+        htlc_id: &str,
+        txid: &str,
+        vout: u32,
+    ) -> Result<(), DatabaseError> {
+        let from = set_htlc_funding(conn, self.tenant.id(), htlc_id, txid, vout)?;
+        let reason = format!("funded by {}:{}", txid, vout);
+        audit(
+            conn,
+            self.tenant.id(),
+            htlc_id,
+            Some(from),
+            HTLCState::Locked,
+            &self.actor,
+            &reason,
+        )?;
+        enqueue_due_jobs(conn, self.tenant.id(), htlc_id)?;
+        Ok(())
+    }
+
+    /// Move an HTLC to `state` and audit the change
+    fn move_htlc(
+        &self,
+        conn: &mut PgConnection,
+        htlc_id: &str,
+        state: HTLCState,
+        reason: &str,
+    ) -> Result<(), DatabaseError> {
+        let from = set_htlc_state(conn, self.tenant.id(), htlc_id, state)?;
+        audit(
+            conn,
+            self.tenant.id(),
+            htlc_id,
+            Some(from),
+            state,
+            &self.actor,
+            reason,
+        )?;
+        if matches!(state, HTLCState::Pending | HTLCState::Locked) {
+            enqueue_due_jobs(conn, self.tenant.id(), htlc_id)?;
+        }
+        Ok(())
+    }
+
+    pub fn get_pending_htlcs(
+        &self,
+        network: ZcashNetwork,
+    ) -> Result<Vec<ZcashHTLC>, DatabaseError> {
+        let mut conn = self.get_connection()?;
+        self.load_htlcs(
+            &mut conn,
+            &HTLCQuery::new()
+                .with_network(network)
+                .with_states([HTLCState::Locked]),
+        )
+    }
+
+    /// HTLCs a block scan should watch: awaiting funding or still locked
+    pub fn get_unsettled_htlcs(
+        &self,
+        network: ZcashNetwork,
+    ) -> Result<Vec<ZcashHTLC>, DatabaseError> {
+        let mut conn = self.get_connection()?;
+        self.load_htlcs(
+            &mut conn,
+            &HTLCQuery::new()
+                .with_network(network)
+                .with_states([HTLCState::Pending, HTLCState::Locked]),
+        )
+    }
+
+    /// Locked HTLCs with a block height timelock below `current_block`;
+    /// see [`Database::get_expired_htlcs_at`] for timestamp timelocks
+    pub fn get_expired_htlcs(&self, current_block: u64) -> Result<Vec<ZcashHTLC>, DatabaseError> {
+        use crate::models::schema::zcash_htlcs::dsl;
+
+        let mut conn = self.get_connection()?;
+
+        let htlcs = dsl::zcash_htlcs
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::state.eq(HTLCState::Locked as i16))
+            .filter(dsl::timelock_kind.eq(TimelockKind::BlockHeight.as_str()))
+            .filter(dsl::timelock.lt(current_block as i64))
+            .select(DbZcashHTLC::as_select())
+            .load::<DbZcashHTLC>(&mut conn)?;
+
+        htlcs.into_iter().map(|htlc| self.open_htlc(htlc)).collect()
+    }
+
+    /// Locked HTLCs whose timelock has passed: block height timelocks below
+    /// `current_height`, timestamp timelocks below the median time past
+    /// `current_mtp` of the chain tip. Urgent ones come first, then the
+    /// oldest.
+    pub fn get_expired_htlcs_at(
+        &self,
+        current_height: u64,
+        current_mtp: u64,
+    ) -> Result<Vec<ZcashHTLC>, DatabaseError> {
+        use crate::models::schema::zcash_htlcs::dsl;
+
+        let mut conn = self.get_connection()?;
+
+        let htlcs = dsl::zcash_htlcs
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::state.eq(HTLCState::Locked as i16))
+            .filter(
+                dsl::timelock_kind
+                    .eq(TimelockKind::BlockHeight.as_str())
+                    .and(dsl::timelock.lt(current_height as i64))
+                    .or(dsl::timelock_kind
+                        .eq(TimelockKind::Timestamp.as_str())
+                        .and(dsl::timelock.lt(current_mtp as i64))),
+            )
+            .order((dsl::priority.desc(), dsl::created_at.asc()))
+            .select(DbZcashHTLC::as_select())
+            .load::<DbZcashHTLC>(&mut conn)?;
+
+        htlcs.into_iter().map(|htlc| self.open_htlc(htlc)).collect()
+    }
+
+    pub fn create_operation(&self, operation: &HTLCOperation) -> Result<(), DatabaseError> {
+        let mut conn = self.get_connection()?;
+        insert_operation(&mut conn, self.tenant.id(), operation)?;
+
+        info!("📝 Created operation record: {}", operation.id);
+        Ok(())
+    }
+
+    pub fn update_operation_signed(
+        &self,
+        operation_id: &str,
+        signed_tx_hex: &str,
+    ) -> Result<(), DatabaseError> {
+        use crate::models::schema::htlc_operations::dsl;
+
+        let mut conn = self.get_connection()?;
+
+        diesel::update(
+            dsl::htlc_operations
+                .filter(dsl::id.eq(operation_id))
+                .filter(dsl::tenant_id.eq(self.tenant.id())),
+        )
+        .set((
+            dsl::signed_tx_hex.eq(signed_tx_hex),
+            dsl::status.eq(OperationStatus::Signed.as_str()),
+            dsl::updated_at.eq(Utc::now()),
+        ))
+        .execute(&mut conn)?;
+
+        info!("✍️ Signed operation: {}", operation_id);
+        Ok(())
+    }
+
+    pub fn update_operation_broadcast(
+        &self,
+        operation_id: &str,
+        txid: &str,
+    ) -> Result<(), DatabaseError> {
+        let mut conn = self.get_connection()?;
+        set_operation_broadcast(&mut conn, self.tenant.id(), operation_id, txid)?;
+
+        info!("📡 Broadcast operation: {}", operation_id);
+        Ok(())
+    }
+
+    /// Record that `replacement_id`, spending the same outputs as
+    /// `operation_id` at a higher fee, was broadcast as `txid`, and mark
+    /// the operation it replaces failed for good, in one transaction
+    pub fn record_operation_replaced(
+        &self,
+        operation_id: &str,
+        replacement_id: &str,
+        txid: &str,
+    ) -> Result<(), DatabaseError> {
+        use crate::models::schema::htlc_operations::dsl;
+
+        let mut conn = self.get_connection()?;
+
+        conn.transaction(|conn| {
+            set_operation_broadcast(conn, self.tenant.id(), replacement_id, txid)?;
+            let replaced = diesel::update(
+                dsl::htlc_operations
+                    .filter(dsl::id.eq(operation_id))
+                    .filter(dsl::tenant_id.eq(self.tenant.id())),
+            )
+            .set((
+                dsl::status.eq(OperationStatus::Failed.as_str()),
+                dsl::error_message.eq(format!("replaced by {}", txid)),
+                dsl::next_retry_at.eq(None::<DateTime<Utc>>),
+                dsl::updated_at.eq(Utc::now()),
+            ))
+            .execute(conn)?;
+            if replaced == 0 {
+                return Err(DatabaseError::OperationNotFound(operation_id.to_string()));
+            }
+            Ok(())
+        })?;
+
+        info!(
+            "🔁 Operation {} replaced by {} in {}",
+            operation_id, replacement_id, txid
+        );
+        Ok(())
+    }
+
+    /// Mark an operation confirmed. A redeem or refund whose HTLC a reorg
+    /// moved back to Locked settles it again.
+    pub fn update_operation_confirmed(
+        &self,
+        operation_id: &str,
+        block_height: u64,
+    ) -> Result<(), DatabaseError> {
+        use crate::models::schema::htlc_operations::dsl;
+
+        let mut conn = self.get_connection()?;
+
+        conn.transaction(|conn| {
+            set_operation_confirmed(conn, self.tenant.id(), operation_id, block_height)?;
+
+            let Some((htlc_id, operation_type)) = dsl::htlc_operations
+                .filter(dsl::id.eq(operation_id))
+                .filter(dsl::tenant_id.eq(self.tenant.id()))
+                .select((dsl::htlc_id, dsl::operation_type))
+                .first::<(String, String)>(conn)
+                .optional()?
+            else {
+                return Ok(());
+            };
+            let Some(settled) = operation_type
+                .parse::<HTLCOperationType>()
+                .ok()
+                .and_then(|operation_type| operation_type.settles_to())
+            else {
+                return Ok(());
+            };
+            match locked_state(conn, self.tenant.id(), &htlc_id) {
+                Ok(HTLCState::Locked) => {
+                    let reason = format!("operation {} confirmed again", operation_id);
+                    self.move_htlc(conn, &htlc_id, settled, &reason)
+                }
+                Ok(_) | Err(DatabaseError::HTLCNotFound(_)) => Ok(()),
+                Err(e) => Err(e),
+            }
+        })?;
+
+        info!("✅ Confirmed operation: {}", operation_id);
+        Ok(())
+    }
+
+    pub fn update_operation_failed(
+        &self,
+        operation_id: &str,
+        error: &str,
+        kind: FailureKind,
+    ) -> Result<(), DatabaseError> {
+        use crate::models::schema::htlc_operations::dsl;
+
+        let mut conn = self.get_connection()?;
+
+        diesel::update(
+            dsl::htlc_operations
+                .filter(dsl::id.eq(operation_id))
+                .filter(dsl::tenant_id.eq(self.tenant.id())),
+        )
+        .set((
+            dsl::status.eq(OperationStatus::Failed.as_str()),
+            dsl::error_message.eq(error),
+            dsl::error_kind.eq(kind.as_str()),
+            dsl::updated_at.eq(Utc::now()),
+        ))
+        .execute(&mut conn)?;
+
+        info!(
+            "❌ Failed operation: {} - {} ({})",
+            operation_id,
+            error,
+            kind.as_str()
+        );
+        Ok(())
+    }
+
+    /// Count a failed broadcast of `operation_id` and, if `retry`, schedule
+    /// its next attempt with [`operation_retry_delay`]. Without a next
+    /// attempt it is never retried. Returns the attempts so far.
+    pub fn record_operation_attempt(
+        &self,
+        operation_id: &str,
+        error: &str,
+        kind: FailureKind,
+        retry: bool,
+    ) -> Result<u32, DatabaseError> {
+        use crate::models::schema::htlc_operations::dsl;
+
+        let mut conn = self.get_connection()?;
+
+        let attempts = conn.transaction(|conn| {
+            let operation = dsl::htlc_operations
+                .filter(dsl::id.eq(operation_id))
+                .filter(dsl::tenant_id.eq(self.tenant.id()));
+
+            let attempts = operation
+                .select(dsl::attempt_count)
+                .for_update()
+                .first::<i32>(conn)
+                .optional()?
+                .ok_or_else(|| DatabaseError::OperationNotFound(operation_id.to_string()))?
+                as u32
+                + 1;
+
+            let now = Utc::now();
+            diesel::update(operation)
+                .set((
+                    dsl::status.eq(OperationStatus::Failed.as_str()),
+                    dsl::error_message.eq(error),
+                    dsl::error_kind.eq(kind.as_str()),
+                    dsl::attempt_count.eq(attempts as i32),
+                    dsl::last_attempt_at.eq(now),
+                    dsl::next_retry_at.eq(retry.then(|| now + operation_retry_delay(attempts))),
+                    dsl::updated_at.eq(now),
+                ))
+                .execute(conn)?;
+
+            Ok::<_, DatabaseError>(attempts)
+        })?;
+
+        warn!(
+            "🔁 Operation {} failed at {} (attempt {}): {}",
+            operation_id,
+            kind.as_str(),
+            attempts,
+            error
+        );
+        Ok(attempts)
+    }
+
+    /// Failed operations of HTLCs not deleted whose next attempt is due at
+    /// `now`, skipping those already tried `max_attempts` times, oldest
+    /// schedule first
+    pub fn get_operations_due_for_retry(
+        &self,
+        now: DateTime<Utc>,
+        max_attempts: u32,
+        limit: usize,
+    ) -> Result<Vec<HTLCOperation>, DatabaseError> {
+        use crate::models::schema::htlc_operations::dsl;
+        use crate::models::schema::zcash_htlcs;
+
+        let mut conn = self.get_connection()?;
+
+        let operations = dsl::htlc_operations
+            .inner_join(zcash_htlcs::table)
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
+            .filter(zcash_htlcs::deleted_at.is_null())
+            .filter(dsl::status.eq(OperationStatus::Failed.as_str()))
+            .filter(dsl::next_retry_at.le(now))
+            .filter(dsl::attempt_count.lt(max_attempts as i32))
+            .order(dsl::next_retry_at.asc())
+            .limit(limit as i64)
+            .select(DbHTLCOperation::as_select())
+            .load::<DbHTLCOperation>(&mut conn)?;
+
+        Ok(operations.into_iter().map(Into::into).collect())
+    }
+
+    /// Broadcast operations not yet seen confirmed: those never checked
+    /// first, then those checked longest ago, so that transactions that
+    /// will never confirm do not crowd out the rest. Ties go to urgent HTLCs,
+    /// then the longest waiting.
+    pub fn get_unconfirmed_operations(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<HTLCOperation>, DatabaseError> {
+        use crate::models::schema::htlc_operations::dsl;
+        use crate::models::schema::zcash_htlcs;
+
+        let mut conn = self.get_connection()?;
+
+        let operations = dsl::htlc_operations
+            .inner_join(zcash_htlcs::table)
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
+            .filter(dsl::status.eq(OperationStatus::Broadcast.as_str()))
+            .filter(dsl::txid.is_not_null())
+            .order((
+                dsl::last_checked_at.asc().nulls_first(),
+                zcash_htlcs::priority.desc(),
+                dsl::broadcast_at.asc(),
+            ))
+            .limit(limit as i64)
+            .select(DbHTLCOperation::as_select())
+            .load::<DbHTLCOperation>(&mut conn)?;
+
+        Ok(operations.into_iter().map(Into::into).collect())
+    }
+
+    /// Remember that the node was just asked about these operations'
+    /// transactions, moving them to the back of
+    /// [`Database::get_unconfirmed_operations`]
+    pub fn record_operations_checked(&self, operation_ids: &[String]) -> Result<(), DatabaseError> {
+        use crate::models::schema::htlc_operations::dsl;
+
+        let mut conn = self.get_connection()?;
+
+        diesel::update(
+            dsl::htlc_operations
+                .filter(dsl::tenant_id.eq(self.tenant.id()))
+                .filter(dsl::id.eq_any(operation_ids)),
+        )
+        .set(dsl::last_checked_at.eq(Utc::now()))
+        .execute(&mut conn)?;
+        Ok(())
+    }
+
+    /// Remember `height` as the tip when an operation's transaction was
+    /// first seen unconfirmed; later calls keep the first height
+    pub fn record_broadcast_height(
+        &self,
+        operation_id: &str,
+        height: u64,
+    ) -> Result<(), DatabaseError> {
+        use crate::models::schema::htlc_operations::dsl;
+
+        let mut conn = self.get_connection()?;
+
+        diesel::update(
+            dsl::htlc_operations
+                .filter(dsl::id.eq(operation_id))
+                .filter(dsl::tenant_id.eq(self.tenant.id()))
+                .filter(dsl::broadcast_height.is_null()),
+        )
+        .set((
+            dsl::broadcast_height.eq(height as i64),
+            dsl::updated_at.eq(Utc::now()),
+        ))
+        .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    /// Fee bumps of `operation_id`, oldest first, each a child of the one
+    /// before
+    pub fn get_fee_bumps(&self, operation_id: &str) -> Result<Vec<HTLCOperation>, DatabaseError> {
+        use crate::models::schema::htlc_operations::dsl;
+
+        let mut conn = self.get_connection()?;
+
+        let bumps = dsl::htlc_operations
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
+            .filter(dsl::bumps_operation_id.eq(operation_id))
+            .filter(dsl::operation_type.eq(HTLCOperationType::FeeBump.as_str()))
+            .order(dsl::created_at.asc())
+            .select(DbHTLCOperation::as_select())
+            .load::<DbHTLCOperation>(&mut conn)?;
+
+        Ok(bumps.into_iter().map(Into::into).collect())
+    }
+
+    pub fn get_operation_by_id(&self, operation_id: &str) -> Result<HTLCOperation, DatabaseError> {
+        use crate::models::schema::htlc_operations::dsl;
+
+        let mut conn = self.get_read_connection()?;
+
+        let operation = dsl::htlc_operations
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
+            .filter(dsl::id.eq(operation_id))
+            .select(DbHTLCOperation::as_select())
+            .first::<DbHTLCOperation>(&mut conn)
+            .map_err(|_| DatabaseError::OperationNotFound(operation_id.to_string()))?;
+
+        Ok(operation.into())
+    }
+
+    pub fn get_operations_by_htlc(
+        &self,
+        htlc_id: &str,
+    ) -> Result<Vec<HTLCOperation>, DatabaseError> {
+        use crate::models::schema::htlc_operations::dsl;
+
+        let mut conn = self.get_read_connection()?;
+
+        let operations = dsl::htlc_operations
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
+            .filter(dsl::htlc_id.eq(htlc_id))
+            .order(dsl::created_at.desc())
+            .select(DbHTLCOperation::as_select())
+            .load::<DbHTLCOperation>(&mut conn)?;
+
+        Ok(operations.into_iter().map(Into::into).collect())
+    }
+
+    pub fn save_checkpoint(&self, chain: &str, block_height: u32) -> Result<(), DatabaseError> {
+        use crate::models::schema::indexer_checkpoints::dsl;
+
+        let mut conn = self.get_connection()?;
+
+        diesel::insert_into(dsl::indexer_checkpoints)
+            .values((
+                dsl::tenant_id.eq(self.tenant.id()),
+                dsl::chain.eq(chain),
+                dsl::last_block.eq(block_height as i32),
+                dsl::updated_at.eq(Utc::now()),
+            ))
+            .on_conflict((dsl::tenant_id, dsl::chain))
+            .do_update()
+            .set((
+                dsl::last_block.eq(block_height as i32),
+                dsl::updated_at.eq(Utc::now()),
+            ))
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
+    pub fn get_checkpoint(&self, chain: &str) -> Result<Option<u32>, DatabaseError> {
+        use crate::models::schema::indexer_checkpoints::dsl;
+
+        let mut conn = self.get_connection()?;
+
+        let result = dsl::indexer_checkpoints
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
+            .filter(dsl::chain.eq(chain))
+            .select(dsl::last_block)
+            .first::<i32>(&mut conn)
+            .optional()?;
+
+        Ok(result.map(|b| b as u32))
+    }
+
+    /// Record the hashes of the blocks at their heights, replacing older
+    /// ones, and drop those below `keep_from`, too deep to be reorganized,
+    /// all in one transaction
+    pub fn record_block_hashes(
+        &self,
+        chain: &str,
+        hashes: &[(u64, String)],
+        keep_from: u64,
+    ) -> Result<(), DatabaseError> {
+        use crate::models::schema::block_checkpoints::dsl;
+
+        let mut conn = self.get_connection()?;
+
+        conn.transaction(|conn| {
+            for (height, block_hash) in hashes {
+                diesel::insert_into(dsl::block_checkpoints)
+                    .values((
+                        dsl::tenant_id.eq(self.tenant.id()),
+                        dsl::chain.eq(chain),
+                        dsl::height.eq(*height as i64),
+                        dsl::block_hash.eq(block_hash),
+                    ))
+                    .on_conflict((dsl::tenant_id, dsl::chain, dsl::height))
+                    .do_update()
+                    .set((
+                        dsl::block_hash.eq(block_hash),
+                        dsl::created_at.eq(Utc::now()),
+                    ))
+                    .execute(conn)?;
+            }
+
+            diesel::delete(
+                dsl::block_checkpoints
+                    .filter(dsl::tenant_id.eq(self.tenant.id()))
+                    .filter(dsl::chain.eq(chain))
+                    .filter(dsl::height.lt(keep_from as i64)),
+            )
+            .execute(conn)
+        })?;
+
+        Ok(())
+    }
+
+    /// Up to `limit` recorded `(height, hash)` pairs, newest first
+    pub fn get_recent_block_hashes(
+        &self,
+        chain: &str,
+        limit: i64,
+    ) -> Result<Vec<(u64, String)>, DatabaseError> {
+        use crate::models::schema::block_checkpoints::dsl;
+
+        let mut conn = self.get_connection()?;
+
+        let hashes = dsl::block_checkpoints
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
+            .filter(dsl::chain.eq(chain))
+            .order(dsl::height.desc())
+            .limit(limit)
+            .select((dsl::height, dsl::block_hash))
+            .load::<(i64, String)>(&mut conn)?;
+
+        Ok(hashes
+            .into_iter()
+            .map(|(height, hash)| (height as u64, hash))
+            .collect())
+    }
+
+    /// Forget block hashes from `fork_height` up and move the indexer
+    /// checkpoint back below it
+    pub fn rewind_block_hashes(&self, chain: &str, fork_height: u64) -> Result<(), DatabaseError> {
+        use crate::models::schema::{block_checkpoints, indexer_checkpoints};
+
+        let mut conn = self.get_connection()?;
+
+        conn.transaction(|conn| {
+            diesel::delete(
+                block_checkpoints::table
+                    .filter(block_checkpoints::tenant_id.eq(self.tenant.id()))
+                    .filter(block_checkpoints::chain.eq(chain))
+                    .filter(block_checkpoints::height.ge(fork_height as i64)),
+            )
+            .execute(conn)?;
+
+            diesel::update(
+                indexer_checkpoints::table
+                    .filter(indexer_checkpoints::tenant_id.eq(self.tenant.id()))
+                    .filter(indexer_checkpoints::chain.eq(chain))
+                    .filter(indexer_checkpoints::last_block.ge(fork_height as i32)),
+            )
+            .set((
+                indexer_checkpoints::last_block.eq(fork_height as i32 - 1),
+                indexer_checkpoints::updated_at.eq(Utc::now()),
+            ))
+            .execute(conn)
+        })?;
 
-        Ok(operation.into())
+        info!(
+            "⏪ Rewound {} checkpoints below block {}",
+            chain, fork_height
+        );
+        Ok(())
     }
 
-    pub fn get_operations_by_htlc(
+    /// This tenant's operations confirmed at or above `block_height`
+    pub fn get_operations_confirmed_since(
         &self,
-        htlc_id: &str,
+        block_height: u64,
     ) -> Result<Vec<HTLCOperation>, DatabaseError> {
         use crate::models::schema::htlc_operations::dsl;
 
         let mut conn = self.get_connection()?;
 
         let operations = dsl::htlc_operations
-            .filter(dsl::htlc_id.eq(htlc_id))
-            .order(dsl::created_at.desc())
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
+            .filter(dsl::status.eq(OperationStatus::Confirmed.as_str()))
+            .filter(dsl::block_height.ge(block_height as i64))
             .select(DbHTLCOperation::as_select())
             .load::<DbHTLCOperation>(&mut conn)?;
 
         Ok(operations.into_iter().map(Into::into).collect())
     }
 
-    pub fn save_checkpoint(&self, chain: &str, block_height: u32) -> Result<(), DatabaseError> {
-        use crate::models::schema::indexer_checkpoints::dsl;
+    /// Move a confirmed operation back to broadcast after its block was
+    /// reorganized away, and its HTLC back to the state it had before the
+    /// operation confirmed (see [`HTLCState::reorg_revert`])
+    pub fn update_operation_unconfirmed(&self, operation_id: &str) -> Result<(), DatabaseError> {
+        use crate::models::schema::htlc_operations::dsl;
 
         let mut conn = self.get_connection()?;
-
-        diesel::insert_into(dsl::indexer_checkpoints)
-            .values((
-                dsl::chain.eq(chain),
-                dsl::last_block.eq(block_height as i32),
-                dsl::updated_at.eq(Utc::now()),
-            ))
-            .on_conflict(dsl::chain)
-            .do_update()
-            .set((
-                dsl::last_block.eq(block_height as i32),
-                dsl::updated_at.eq(Utc::now()),
-            ))
-            .execute(&mut conn)?;
-
+        let tenant = self.tenant.id();
+
+        let reverted = conn.transaction(|conn| {
+            let operation = dsl::htlc_operations
+                .filter(dsl::id.eq(operation_id))
+                .filter(dsl::tenant_id.eq(tenant));
+            let Some((htlc_id, operation_type)) = operation
+                .select((dsl::htlc_id, dsl::operation_type))
+                .for_update()
+                .first::<(String, String)>(conn)
+                .optional()?
+            else {
+                return Ok(None);
+            };
+
+            diesel::update(operation)
+                .set((
+                    dsl::status.eq(OperationStatus::Broadcast.as_str()),
+                    dsl::block_height.eq(None::<i64>),
+                    dsl::confirmed_at.eq(None::<chrono::DateTime<Utc>>),
+                    dsl::updated_at.eq(Utc::now()),
+                ))
+                .execute(conn)?;
+
+            let Ok(operation_type) = operation_type.parse::<HTLCOperationType>() else {
+                return Ok(None);
+            };
+            let state = match locked_state(conn, tenant, &htlc_id) {
+                Ok(state) => state,
+                Err(DatabaseError::HTLCNotFound(_)) => return Ok(None),
+                Err(e) => return Err(e),
+            };
+            let Some(previous) = state.reorg_revert(&operation_type) else {
+                return Ok(None);
+            };
+
+            revert_htlc_state(conn, &htlc_id, previous)?;
+            audit(
+                conn,
+                tenant,
+                &htlc_id,
+                Some(state),
+                previous,
+                &self.actor,
+                &format!("operation {} reorganized out of the chain", operation_id),
+            )?;
+            Ok::<_, DatabaseError>(Some((htlc_id, previous)))
+        })?;
+
+        info!("⏪ Unconfirmed operation: {}", operation_id);
+        if let Some((htlc_id, state)) = reverted {
+            info!("⏪ HTLC {} back to {}", htlc_id, state.as_str());
+        }
         Ok(())
     }
 
-    pub fn get_checkpoint(&self, chain: &str) -> Result<Option<u32>, DatabaseError> {
-        use crate::models::schema::indexer_checkpoints::dsl;
-
-        let mut conn = self.get_connection()?;
-
-        let result = dsl::indexer_checkpoints
-            .filter(dsl::chain.eq(chain))
-            .select(dsl::last_block)
-            .first::<i32>(&mut conn)
-            .optional()?;
-
-        Ok(result.map(|b| b as u32))
-    }
-
     pub fn create_relayer_utxo(&self, utxo: &RelayerUTXO) -> Result<(), DatabaseError> {
         use crate::models::schema::relayer_utxos;
 
         let mut conn = self.get_connection()?;
 
-        let new_utxo = NewRelayerUTXO {
-            id: utxo.id.clone(),
-            txid: utxo.txid.clone(),
-            vout: utxo.vout as i32,
-            amount: utxo.amount.clone(),
-            script_pubkey: utxo.script_pubkey.clone(),
-            confirmations: utxo.confirmations as i32,
-            address: utxo.address.clone(),
-        };
-
         diesel::insert_into(relayer_utxos::table)
-            .values(&new_utxo)
+            .values(&new_relayer_utxo(self.tenant.id(), utxo))
             .on_conflict((relayer_utxos::txid, relayer_utxos::vout))
             .do_nothing()
             .execute(&mut conn)?;
@@ -368,6 +2030,34 @@ impl Database {
         Ok(())
     }
 
+    /// Insert many UTXOs with one multi-row statement per
+    /// `UTXO_INSERT_BATCH` rows, all in one transaction. UTXOs already known
+    /// by txid and vout are skipped; returns how many were new.
+    pub fn create_relayer_utxos(&self, utxos: &[RelayerUTXO]) -> Result<usize, DatabaseError> {
+        use crate::models::schema::relayer_utxos;
+
+        let mut conn = self.get_connection()?;
+
+        let rows: Vec<NewRelayerUTXO> = utxos
+            .iter()
+            .map(|utxo| new_relayer_utxo(self.tenant.id(), utxo))
+            .collect();
+        let created = conn.transaction(|conn| {
+            let mut created = 0;
+            for batch in rows.chunks(UTXO_INSERT_BATCH) {
+                created += diesel::insert_into(relayer_utxos::table)
+                    .values(batch)
+                    .on_conflict((relayer_utxos::txid, relayer_utxos::vout))
+                    .do_nothing()
+                    .execute(conn)?;
+            }
+            Ok::<_, DatabaseError>(created)
+        })?;
+
+        info!("📦 Created {} of {} relayer UTXO(s)", created, utxos.len());
+        Ok(created)
+    }
+
     pub fn get_unspent_relayer_utxos(
         &self,
         address: &str,
@@ -377,9 +2067,15 @@ impl Database {
         let mut conn = self.get_connection()?;
 
         let utxos = dsl::relayer_utxos
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
             .filter(dsl::address.eq(address))
             .filter(dsl::spent.eq(false))
             .filter(dsl::confirmations.ge(1))
+            .filter(
+                dsl::reserved_until
+                    .is_null()
+                    .or(dsl::reserved_until.lt(Utc::now())),
+            )
             .order(dsl::amount.desc())
             .select(DbRelayerUTXO::as_select())
             .load::<DbRelayerUTXO>(&mut conn)?;
@@ -387,6 +2083,235 @@ impl Database {
         Ok(utxos.into_iter().map(Into::into).collect())
     }
 
+    /// Reserve confirmed UTXOs of `address` worth at least `zatoshis` for
+    /// `ttl`, largest first. Rows locked by a concurrent reservation are
+    /// skipped rather than waited for, so parallel batches never pick the
+    /// same UTXO.
+    pub fn reserve_utxos(
+        &self,
+        address: &str,
+        zatoshis: u64,
+        ttl: chrono::Duration,
+    ) -> Result<UtxoReservation, DatabaseError> {
+        let mut conn = self.get_connection()?;
+
+        let reservation_id = uuid::Uuid::new_v4().to_string();
+        let now = Utc::now();
+        let expires_at = now + ttl;
+
+        let utxos = conn.transaction(|conn| {
+            let mut candidates = self.lock_reservable_utxos(conn, address, now)?;
+            // Amounts are decimal strings, so order them numerically here
+            candidates.sort_by_key(|utxo| std::cmp::Reverse(utxo_zatoshis(utxo)));
+
+            let mut selected = Vec::new();
+            let mut total = 0;
+            for utxo in candidates {
+                if total >= zatoshis {
+                    break;
+                }
+                total += utxo_zatoshis(&utxo);
+                selected.push(utxo);
+            }
+            if total < zatoshis {
+                return Err(DatabaseError::InsufficientUtxos {
+                    required: zatoshis,
+                    available: total,
+                });
+            }
+
+            mark_reserved(conn, &selected, &reservation_id, expires_at)?;
+            Ok(selected)
+        })?;
+
+        info!(
+            "🔒 Reserved {} UTXO(s) for {} ZEC until {}",
+            utxos.len(),
+            zatoshis_to_zec(zatoshis),
+            expires_at
+        );
+        Ok(UtxoReservation {
+            id: reservation_id,
+            utxos: utxos.into_iter().map(Into::into).collect(),
+            expires_at,
+        })
+    }
+
+    /// Reserve up to `count` of the smallest confirmed UTXOs of `address`
+    /// for `ttl`, to be merged by a consolidation transaction
+    pub fn reserve_smallest_utxos(
+        &self,
+        address: &str,
+        count: usize,
+        ttl: chrono::Duration,
+    ) -> Result<UtxoReservation, DatabaseError> {
+        let mut conn = self.get_connection()?;
+
+        let reservation_id = uuid::Uuid::new_v4().to_string();
+        let now = Utc::now();
+        let expires_at = now + ttl;
+
+        let utxos = conn.transaction(|conn| {
+            let mut selected = self.lock_reservable_utxos(conn, address, now)?;
+            selected.sort_by_key(utxo_zatoshis);
+            selected.truncate(count);
+
+            mark_reserved(conn, &selected, &reservation_id, expires_at)?;
+            Ok::<_, DatabaseError>(selected)
+        })?;
+
+        info!(
+            "🔒 Reserved {} UTXO(s) for consolidation until {}",
+            utxos.len(),
+            expires_at
+        );
+        Ok(UtxoReservation {
+            id: reservation_id,
+            utxos: utxos.into_iter().map(Into::into).collect(),
+            expires_at,
+        })
+    }
+
+    /// Confirmed, unspent and unreserved UTXOs of `address`, locked for the
+    /// rest of the transaction; rows another reservation holds are skipped
+    fn lock_reservable_utxos(
+        &self,
+        conn: &mut PgConnection,
+        address: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<DbRelayerUTXO>, DatabaseError> {
+        use crate::models::schema::relayer_utxos::dsl;
+
+        Ok(dsl::relayer_utxos
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
+            .filter(dsl::address.eq(address))
+            .filter(dsl::spent.eq(false))
+            .filter(dsl::confirmations.ge(1))
+            .filter(
+                dsl::reserved_until
+                    .is_null()
+                    .or(dsl::reserved_until.lt(now)),
+            )
+            .select(DbRelayerUTXO::as_select())
+            .for_update()
+            .skip_locked()
+            .load::<DbRelayerUTXO>(conn)?)
+    }
+
+    /// Mark the UTXOs of a reservation spent in `spent_in_tx`
+    pub fn commit_reservation(
+        &self,
+        reservation_id: &str,
+        spent_in_tx: &str,
+    ) -> Result<(), DatabaseError> {
+        use crate::models::schema::relayer_utxos::dsl;
+
+        let mut conn = self.get_connection()?;
+
+        let spent = diesel::update(
+            dsl::relayer_utxos
+                .filter(dsl::tenant_id.eq(self.tenant.id()))
+                .filter(dsl::reserved_by.eq(reservation_id)),
+        )
+        .set((
+            dsl::spent.eq(true),
+            dsl::spent_in_tx.eq(spent_in_tx),
+            dsl::reserved_by.eq(None::<String>),
+            dsl::reserved_until.eq(None::<DateTime<Utc>>),
+            dsl::updated_at.eq(Utc::now()),
+        ))
+        .execute(&mut conn)?;
+
+        info!(
+            "✅ Marked {} reserved UTXO(s) spent in tx {}",
+            spent, spent_in_tx
+        );
+        Ok(())
+    }
+
+    /// Mark the UTXOs of a reservation spent in `consolidation` and record
+    /// it, in one transaction
+    pub fn commit_consolidation(
+        &self,
+        reservation_id: &str,
+        consolidation: &Consolidation,
+    ) -> Result<(), DatabaseError> {
+        use crate::models::schema::relayer_consolidations;
+        use crate::models::schema::relayer_utxos::dsl;
+
+        let mut conn = self.get_connection()?;
+
+        conn.transaction(|conn| {
+            diesel::update(
+                dsl::relayer_utxos
+                    .filter(dsl::tenant_id.eq(self.tenant.id()))
+                    .filter(dsl::reserved_by.eq(reservation_id)),
+            )
+            .set((
+                dsl::spent.eq(true),
+                dsl::spent_in_tx.eq(&consolidation.txid),
+                dsl::reserved_by.eq(None::<String>),
+                dsl::reserved_until.eq(None::<DateTime<Utc>>),
+                dsl::updated_at.eq(Utc::now()),
+            ))
+            .execute(conn)?;
+
+            diesel::insert_into(relayer_consolidations::table)
+                .values((
+                    DbConsolidation::from(consolidation),
+                    relayer_consolidations::tenant_id.eq(self.tenant.id()),
+                ))
+                .execute(conn)?;
+            Ok::<_, DatabaseError>(())
+        })?;
+
+        info!(
+            "🧹 Recorded consolidation {} of {} UTXO(s) for {} zat",
+            consolidation.txid, consolidation.inputs, consolidation.fee_zat
+        );
+        Ok(())
+    }
+
+    /// Consolidations of the hot wallets, newest first
+    pub fn get_consolidations(&self, limit: i64) -> Result<Vec<Consolidation>, DatabaseError> {
+        use crate::models::schema::relayer_consolidations;
+
+        let mut conn = self.get_read_connection()?;
+
+        let consolidations = relayer_consolidations::table
+            .filter(relayer_consolidations::tenant_id.eq(self.tenant.id()))
+            .order(relayer_consolidations::created_at.desc())
+            .limit(limit)
+            .select(DbConsolidation::as_select())
+            .load(&mut conn)?;
+
+        Ok(consolidations.into_iter().map(Into::into).collect())
+    }
+
+    /// Make the UTXOs of a reservation available again, e.g. after the
+    /// transaction using them failed
+    pub fn release_reservation(&self, reservation_id: &str) -> Result<(), DatabaseError> {
+        use crate::models::schema::relayer_utxos::dsl;
+
+        let mut conn = self.get_connection()?;
+
+        diesel::update(
+            dsl::relayer_utxos
+                .filter(dsl::tenant_id.eq(self.tenant.id()))
+                .filter(dsl::reserved_by.eq(reservation_id))
+                .filter(dsl::spent.eq(false)),
+        )
+        .set((
+            dsl::reserved_by.eq(None::<String>),
+            dsl::reserved_until.eq(None::<DateTime<Utc>>),
+            dsl::updated_at.eq(Utc::now()),
+        ))
+        .execute(&mut conn)?;
+
+        info!("🔓 Released UTXO reservation {}", reservation_id);
+        Ok(())
+    }
+
     pub fn mark_utxo_spent(
         &self,
         txid: &str,
@@ -399,6 +2324,7 @@ impl Database {
 
         diesel::update(
             dsl::relayer_utxos
+                .filter(dsl::tenant_id.eq(self.tenant.id()))
                 .filter(dsl::txid.eq(txid))
                 .filter(dsl::vout.eq(vout as i32)),
         )
@@ -428,6 +2354,7 @@ impl Database {
 
         diesel::update(
             dsl::relayer_utxos
+                .filter(dsl::tenant_id.eq(self.tenant.id()))
                 .filter(dsl::txid.eq(txid))
                 .filter(dsl::vout.eq(vout as i32)),
         )
@@ -440,60 +2367,24 @@ impl Database {
         Ok(())
     }
 
-    pub fn get_total_relayer_balance(&self, address: &str) -> Result<f64, DatabaseError> {
+    /// Zatoshis held by the unspent UTXOs of `address`
+    pub fn get_total_relayer_balance(&self, address: &str) -> Result<u64, DatabaseError> {
         use crate::models::schema::relayer_utxos::dsl;
 
-        let mut conn = self.get_connection()?;
+        let mut conn = self.get_read_connection()?;
 
         let utxos: Vec<String> = dsl::relayer_utxos
+            .filter(dsl::tenant_id.eq(self.tenant.id()))
             .filter(dsl::address.eq(address))
             .filter(dsl::spent.eq(false))
             .select(dsl::amount)
             .load(&mut conn)?;
 
-        let total: f64 = utxos.iter().filter_map(|s| s.parse::<f64>().ok()).sum();
+        let total = utxos.iter().filter_map(|s| zec_to_zatoshis(s)).sum();
 
         Ok(total)
     }
 
-    pub fn get_pending_htlcs_for_creation(
-        &self,
-        limit: u32,
-    ) -> Result<Vec<ZcashHTLC>, DatabaseError> {
-        use crate::models::schema::zcash_htlcs::dsl;
-
-        let mut conn = self.get_connection()?;
-
-        let htlcs = dsl::zcash_htlcs
-            .filter(dsl::state.eq(HTLCState::Pending as i16))
-            .filter(dsl::txid.is_null())
-            .order(dsl::created_at.asc())
-            .limit(limit as i64)
-            .select(DbZcashHTLC::as_select())
-            .load::<DbZcashHTLC>(&mut conn)?;
-
-        Ok(htlcs.into_iter().map(Into::into).collect())
-    }
-
-    pub fn get_htlcs_with_signed_redeem_tx(
-        &self,
-        limit: u32,
-    ) -> Result<Vec<ZcashHTLC>, DatabaseError> {
-        use crate::models::schema::zcash_htlcs::dsl;
-
-        let mut conn = self.get_connection()?;
-
-        let htlcs = dsl::zcash_htlcs
-            .filter(dsl::state.eq(HTLCState::Locked as i16))
-            .filter(dsl::signed_redeem_tx.is_not_null())
-            .order(dsl::created_at.asc())
-            .limit(limit as i64)
-            .select(DbZcashHTLC::as_select())
-            .load::<DbZcashHTLC>(&mut conn)?;
-
-        Ok(htlcs.into_iter().map(Into::into).collect())
-    }
-
     // ==================== HTLC Recipient Operations ====================
 
     pub fn update_htlc_recipient(
@@ -505,12 +2396,27 @@ impl Database {
 
         let mut conn = self.get_connection()?;
 
-        diesel::update(dsl::zcash_htlcs.filter(dsl::id.eq(htlc_id)))
-            .set((
-                dsl::recipient_address.eq(recipient_address),
-                dsl::updated_at.eq(Utc::now()),
-            ))
-            .execute(&mut conn)?;
+        conn.transaction(|conn| {
+            let state = locked_state(conn, self.tenant.id(), htlc_id)?;
+            diesel::update(dsl::zcash_htlcs.filter(dsl::id.eq(htlc_id)))
+                .set((
+                    dsl::recipient_address.eq(recipient_address),
+                    dsl::updated_at.eq(Utc::now()),
+                    dsl::version.eq(dsl::version + 1),
+                ))
+                .execute(conn)?;
+            let reason = format!("recipient address set to {}", recipient_address);
+            audit(
+                conn,
+                self.tenant.id(),
+                htlc_id,
+                Some(state),
+                state,
+                &self.actor,
+                &reason,
+            )?;
+            Ok::<_, DatabaseError>(())
+        })?;
 
         Ok(())
     }
@@ -522,16 +2428,501 @@ impl Database {
     ) -> Result<(), DatabaseError> {
         use crate::models::schema::zcash_htlcs::dsl;
 
+        let signed_tx = self.seal(signed_tx)?;
         let mut conn = self.get_connection()?;
 
-        diesel::update(dsl::zcash_htlcs.filter(dsl::id.eq(htlc_id)))
-            .set((
-                dsl::signed_redeem_tx.eq(signed_tx),
-                dsl::updated_at.eq(Utc::now()),
-            ))
-            .execute(&mut conn)?;
+        conn.transaction(|conn| {
+            let state = locked_state(conn, self.tenant.id(), htlc_id)?;
+            diesel::update(dsl::zcash_htlcs.filter(dsl::id.eq(htlc_id)))
+                .set((
+                    dsl::signed_redeem_tx.eq(&signed_tx),
+                    dsl::updated_at.eq(Utc::now()),
+                    dsl::version.eq(dsl::version + 1),
+                ))
+                .execute(conn)?;
+            audit(
+                conn,
+                self.tenant.id(),
+                htlc_id,
+                Some(state),
+                state,
+                &self.actor,
+                "signed redeem transaction stored",
+            )?;
+            // Broadcast once the HTLC is locked, or else when it locks
+            enqueue_due_jobs(conn, self.tenant.id(), htlc_id)?;
+            notify(conn, SIGNED_REDEEM_CHANNEL, htlc_id)?;
+            Ok::<_, DatabaseError>(())
+        })?;
 
         info!("✍️ Stored signed redeem tx for HTLC: {}", htlc_id);
         Ok(())
     }
 }
+
+fn insert_htlc(
+    conn: &mut PgConnection,
+    tenant: &str,
+    htlc: &ZcashHTLC,
+) -> Result<(), DatabaseError> {
+    use crate::models::schema::zcash_htlcs;
+
+    let new_htlc = NewZcashHTLC {
+        id: htlc.id.clone(),
+        p2sh_address: htlc.p2sh_address.clone(),
+        hash_lock: htlc.hash_lock.clone(),
+        timelock: htlc.timelock as i64,
+        recipient_pubkey: htlc.recipient_pubkey.clone(),
+        refund_pubkey: htlc.refund_pubkey.clone(),
+        amount: htlc.amount.clone(),
+        network: htlc.network.as_str().to_string(),
+        state: htlc.state as i16,
+        script_hex: htlc.script_hex.clone(),
+        redeem_script_hex: htlc.redeem_script_hex.clone(),
+        recipient_address: htlc.recipient_address.clone(),
+        tenant_id: tenant.to_string(),
+        metadata: htlc.metadata.clone(),
+        timelock_kind: htlc.timelock_kind.as_str().to_string(),
+        priority: htlc.priority as i16,
+    };
+
+    diesel::insert_into(zcash_htlcs::table)
+        .values(&new_htlc)
+        .execute(conn)
+        .map_err(|e| duplicate_hash_lock(e, &htlc.hash_lock))?;
+    Ok(())
+}
+
+/// Fill in the HTLC of `tenant` queued as `htlc.id` with the contract
+/// actually built, returning its id. `None` if no HTLC has that id yet;
+/// `DuplicateHashLock` if `htlc` does not [fill](ZcashHTLC::fills) it.
+fn claim_pending_htlc(
+    conn: &mut PgConnection,
+    tenant: &str,
+    htlc: &ZcashHTLC,
+) -> Result<Option<String>, DatabaseError> {
+    use crate::models::schema::zcash_htlcs::dsl;
+
+    let queued = dsl::zcash_htlcs
+        .filter(dsl::tenant_id.eq(tenant))
+        .filter(dsl::deleted_at.is_null())
+        .filter(dsl::id.eq(&htlc.id))
+        .select(DbZcashHTLC::as_select())
+        .for_update()
+        .first::<DbZcashHTLC>(conn)
+        .optional()?;
+    let Some(queued) = queued else {
+        return Ok(None);
+    };
+    let queued = ZcashHTLC::from(queued);
+    if !htlc.fills(&queued) {
+        return Err(DatabaseError::DuplicateHashLock(htlc.hash_lock.clone()));
+    }
+
+    diesel::update(dsl::zcash_htlcs.filter(dsl::id.eq(&queued.id)))
+        .set((
+            dsl::p2sh_address.eq(&htlc.p2sh_address),
+            dsl::timelock_kind.eq(htlc.timelock_kind.as_str()),
+            dsl::refund_pubkey.eq(&htlc.refund_pubkey),
+            dsl::script_hex.eq(&htlc.script_hex),
+            dsl::redeem_script_hex.eq(&htlc.redeem_script_hex),
+            dsl::metadata.eq(&htlc.metadata),
+            dsl::updated_at.eq(Utc::now()),
+            dsl::version.eq(dsl::version + 1),
+        ))
+        .execute(conn)?;
+
+    Ok(Some(queued.id))
+}
+
+/// `DuplicateHashLock` for a violation of the hash lock uniqueness index
+fn duplicate_hash_lock(e: diesel::result::Error, hash_lock: &str) -> DatabaseError {
+    use diesel::result::{DatabaseErrorKind, Error};
+
+    match &e {
+        Error::DatabaseError(DatabaseErrorKind::UniqueViolation, info)
+            if info.constraint_name() == Some(HASH_LOCK_INDEX) =>
+        {
+            DatabaseError::DuplicateHashLock(hash_lock.to_string())
+        }
+        _ => e.into(),
+    }
+}
+
+fn insert_operation(
+    conn: &mut PgConnection,
+    tenant: &str,
+    operation: &HTLCOperation,
+) -> QueryResult<usize> {
+    use crate::models::schema::htlc_operations;
+
+    let new_op = NewHTLCOperation {
+        id: operation.id.clone(),
+        htlc_id: operation.htlc_id.clone(),
+        operation_type: operation.operation_type.as_str().to_string(),
+        raw_tx_hex: operation.raw_tx_hex.clone(),
+        signed_tx_hex: operation.signed_tx_hex.clone(),
+        status: operation.status.as_str().to_string(),
+        tenant_id: tenant.to_string(),
+        fee_zat: operation.fee_zat.map(|fee| fee as i64),
+        tx_size: operation.tx_size.map(|size| size as i32),
+        fee_rate: operation.fee_rate.map(|rate| rate as i64),
+        bumps_operation_id: operation.bumps_operation_id.clone(),
+    };
+
+    diesel::insert_into(htlc_operations::table)
+        .values(&new_op)
+        .execute(conn)
+}
+
+/// Current state of `htlc_id`, whose row stays locked until the
+/// surrounding transaction ends. HTLCs of other tenants are not found, so
+/// updates by id after this check stay within `tenant`.
+fn locked_state(
+    conn: &mut PgConnection,
+    tenant: &str,
+    htlc_id: &str,
+) -> Result<HTLCState, DatabaseError> {
+    use crate::models::schema::zcash_htlcs::dsl;
+
+    dsl::zcash_htlcs
+        .filter(dsl::id.eq(htlc_id))
+        .filter(dsl::tenant_id.eq(tenant))
+        .filter(dsl::deleted_at.is_null())
+        .select(dsl::state)
+        .for_update()
+        .first::<i16>(conn)
+        .optional()?
+        .map(HTLCState::from_i16)
+        .ok_or_else(|| DatabaseError::HTLCNotFound(htlc_id.to_string()))
+}
+
+fn new_relayer_utxo(tenant: &str, utxo: &RelayerUTXO) -> NewRelayerUTXO {
+    NewRelayerUTXO {
+        id: utxo.id.clone(),
+        txid: utxo.txid.clone(),
+        vout: utxo.vout as i32,
+        amount: utxo.amount.clone(),
+        script_pubkey: utxo.script_pubkey.clone(),
+        confirmations: utxo.confirmations as i32,
+        address: utxo.address.clone(),
+        tenant_id: tenant.to_string(),
+    }
+}
+
+fn utxo_zatoshis(utxo: &DbRelayerUTXO) -> u64 {
+    zec_to_zatoshis(&utxo.amount).unwrap_or(0)
+}
+
+/// Hold `utxos` for `reservation_id` until `expires_at`
+fn mark_reserved(
+    conn: &mut PgConnection,
+    utxos: &[DbRelayerUTXO],
+    reservation_id: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<(), DatabaseError> {
+    use crate::models::schema::relayer_utxos::dsl;
+
+    let ids: Vec<&str> = utxos.iter().map(|utxo| utxo.id.as_str()).collect();
+    diesel::update(dsl::relayer_utxos.filter(dsl::id.eq_any(&ids)))
+        .set((
+            dsl::reserved_by.eq(reservation_id),
+            dsl::reserved_until.eq(expires_at),
+            dsl::updated_at.eq(Utc::now()),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Fail unless `htlc_id` is at `expected` version, locking its row until
+/// the surrounding transaction ends
+fn check_version(
+    conn: &mut PgConnection,
+    tenant: &str,
+    htlc_id: &str,
+    expected: u32,
+) -> Result<(), DatabaseError> {
+    use crate::models::schema::zcash_htlcs::dsl;
+
+    let actual = dsl::zcash_htlcs
+        .filter(dsl::id.eq(htlc_id))
+        .filter(dsl::tenant_id.eq(tenant))
+        .filter(dsl::deleted_at.is_null())
+        .select(dsl::version)
+        .for_update()
+        .first::<i32>(conn)
+        .optional()?
+        .ok_or_else(|| DatabaseError::HTLCNotFound(htlc_id.to_string()))? as u32;
+
+    if actual != expected {
+        return Err(DatabaseError::VersionConflict {
+            htlc_id: htlc_id.to_string(),
+            expected,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+/// Current state of `htlc_id`, failing unless it may move to `next`
+fn check_transition(
+    conn: &mut PgConnection,
+    tenant: &str,
+    htlc_id: &str,
+    next: HTLCState,
+) -> Result<HTLCState, DatabaseError> {
+    let from = locked_state(conn, tenant, htlc_id)?;
+    if !from.can_transition_to(next) {
+        return Err(DatabaseError::IllegalTransition {
+            htlc_id: htlc_id.to_string(),
+            from,
+            to: next,
+        });
+    }
+    Ok(from)
+}
+
+/// Lock an HTLC at its funding output, returning its previous state
+fn set_htlc_funding(
+    conn: &mut PgConnection,
+    tenant: &str,
+    htlc_id: &str,
+    txid: &str,
+    vout: u32,
+) -> Result<HTLCState, DatabaseError> {
+    use crate::models::schema::zcash_htlcs::dsl;
+
+    let from = check_transition(conn, tenant, htlc_id, HTLCState::Locked)?;
+    diesel::update(dsl::zcash_htlcs.filter(dsl::id.eq(htlc_id)))
+        .set((
+            dsl::txid.eq(txid),
+            dsl::vout.eq(vout as i32),
+            dsl::state.eq(HTLCState::Locked as i16),
+            dsl::updated_at.eq(Utc::now()),
+            dsl::version.eq(dsl::version + 1),
+        ))
+        .execute(conn)?;
+
+    Ok(from)
+}
+
+/// Move an HTLC to `state`, returning its previous state
+fn set_htlc_state(
+    conn: &mut PgConnection,
+    tenant: &str,
+    htlc_id: &str,
+    state: HTLCState,
+) -> Result<HTLCState, DatabaseError> {
+    use crate::models::schema::zcash_htlcs::dsl;
+
+    let from = check_transition(conn, tenant, htlc_id, state)?;
+    diesel::update(dsl::zcash_htlcs.filter(dsl::id.eq(htlc_id)))
+        .set((
+            dsl::state.eq(state as i16),
+            dsl::updated_at.eq(Utc::now()),
+            dsl::version.eq(dsl::version + 1),
+        ))
+        .execute(conn)?;
+
+    Ok(from)
+}
+
+/// Move an HTLC back to `state` after a reorg, bypassing the forward-only
+/// transition check
+fn revert_htlc_state(
+    conn: &mut PgConnection,
+    htlc_id: &str,
+    state: HTLCState,
+) -> QueryResult<usize> {
+    use crate::models::schema::zcash_htlcs::dsl;
+
+    diesel::update(dsl::zcash_htlcs.filter(dsl::id.eq(htlc_id)))
+        .set((
+            dsl::state.eq(state as i16),
+            dsl::updated_at.eq(Utc::now()),
+            dsl::version.eq(dsl::version + 1),
+        ))
+        .execute(conn)
+}
+
+/// Append a change of `htlc_id` to its audit log and, if its state
+/// changed, queue an event for it in the outbox
+fn audit(
+    conn: &mut PgConnection,
+    tenant: &str,
+    htlc_id: &str,
+    old_state: Option<HTLCState>,
+    new_state: HTLCState,
+    actor: &str,
+    reason: &str,
+) -> QueryResult<usize> {
+    use crate::models::schema::htlc_audit_log;
+
+    diesel::insert_into(htlc_audit_log::table)
+        .values((
+            &NewHTLCAuditEntry {
+                htlc_id: htlc_id.to_string(),
+                old_state: old_state.map(|state| state as i16),
+                new_state: new_state as i16,
+                actor: actor.to_string(),
+                reason: reason.to_string(),
+            },
+            htlc_audit_log::tenant_id.eq(tenant),
+        ))
+        .execute(conn)?;
+
+    let event_type = match old_state {
+        None => "htlc.created".to_string(),
+        Some(old_state) if old_state != new_state => format!("htlc.{}", new_state.as_str()),
+        Some(_) => return Ok(1),
+    };
+    let payload = serde_json::json!({
+        "htlc_id": htlc_id,
+        "old_state": old_state.map(|state| state.as_str()),
+        "new_state": new_state.as_str(),
+        "actor": actor,
+        "reason": reason,
+    });
+
+    queue_event(conn, tenant, Some(htlc_id), &event_type, &payload)
+}
+
+#[derive(QueryableByName)]
+struct SinkCursor {
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    last_event_id: i64,
+}
+
+fn advance_sink_cursor(
+    conn: &mut PgConnection,
+    tenant: &str,
+    sink: &str,
+) -> Result<(), DatabaseError> {
+    use crate::models::schema::event_deliveries;
+    use diesel::sql_types::Text;
+
+    let cursor = diesel::sql_query(ADVANCE_SINK_CURSOR_QUERY)
+        .bind::<Text, _>(tenant)
+        .bind::<Text, _>(sink)
+        .get_result::<SinkCursor>(conn)
+        .optional()?;
+    if let Some(cursor) = cursor {
+        diesel::delete(
+            event_deliveries::table
+                .filter(event_deliveries::tenant_id.eq(tenant))
+                .filter(event_deliveries::sink.eq(sink))
+                .filter(event_deliveries::event_id.le(cursor.last_event_id)),
+        )
+        .execute(conn)?;
+    }
+    Ok(())
+}
+
+pub(super) fn queue_event(
+    conn: &mut PgConnection,
+    tenant: &str,
+    htlc_id: Option<&str>,
+    event_type: &str,
+    payload: &serde_json::Value,
+) -> QueryResult<usize> {
+    use crate::models::schema::event_outbox;
+
+    // Take a transaction id before drawing an event id, see
+    // SET_COMMIT_HORIZON_QUERY
+    diesel::sql_query("SELECT pg_current_xact_id()").execute(conn)?;
+    let event_id: i64 = diesel::insert_into(event_outbox::table)
+        .values((
+            &NewHTLCEvent {
+                htlc_id: htlc_id.map(str::to_string),
+                event_type: event_type.to_string(),
+                payload: payload.to_string(),
+            },
+            event_outbox::tenant_id.eq(tenant),
+        ))
+        .returning(event_outbox::id)
+        .get_result(conn)?;
+    diesel::sql_query(SET_COMMIT_HORIZON_QUERY)
+        .bind::<diesel::sql_types::BigInt, _>(event_id)
+        .execute(conn)
+}
+
+fn set_htlc_secret(conn: &mut PgConnection, htlc_id: &str, secret: &str) -> QueryResult<usize> {
+    use crate::models::schema::zcash_htlcs::dsl;
+
+    diesel::update(dsl::zcash_htlcs.filter(dsl::id.eq(htlc_id)))
+        .set((
+            dsl::secret.eq(secret),
+            dsl::updated_at.eq(Utc::now()),
+            dsl::version.eq(dsl::version + 1),
+        ))
+        .execute(conn)
+}
+
+/// Record an HTLC's funding output, leaving its state alone
+fn set_htlc_outpoint(
+    conn: &mut PgConnection,
+    tenant: &str,
+    htlc_id: &str,
+    txid: &str,
+    vout: u32,
+) -> QueryResult<usize> {
+    use crate::models::schema::zcash_htlcs::dsl;
+
+    diesel::update(
+        dsl::zcash_htlcs
+            .filter(dsl::id.eq(htlc_id))
+            .filter(dsl::tenant_id.eq(tenant)),
+    )
+    .set((
+        dsl::txid.eq(txid),
+        dsl::vout.eq(vout as i32),
+        dsl::updated_at.eq(Utc::now()),
+        dsl::version.eq(dsl::version + 1),
+    ))
+    .execute(conn)
+}
+
+fn set_operation_confirmed(
+    conn: &mut PgConnection,
+    tenant: &str,
+    operation_id: &str,
+    block_height: u64,
+) -> QueryResult<usize> {
+    use crate::models::schema::htlc_operations::dsl;
+
+    diesel::update(
+        dsl::htlc_operations
+            .filter(dsl::id.eq(operation_id))
+            .filter(dsl::tenant_id.eq(tenant)),
+    )
+    .set((
+        dsl::status.eq(OperationStatus::Confirmed.as_str()),
+        dsl::block_height.eq(block_height as i64),
+        dsl::confirmed_at.eq(Utc::now()),
+        dsl::updated_at.eq(Utc::now()),
+    ))
+    .execute(conn)
+}
+
+fn set_operation_broadcast(
+    conn: &mut PgConnection,
+    tenant: &str,
+    operation_id: &str,
+    txid: &str,
+) -> QueryResult<usize> {
+    use crate::models::schema::htlc_operations::dsl;
+
+    diesel::update(
+        dsl::htlc_operations
+            .filter(dsl::id.eq(operation_id))
+            .filter(dsl::tenant_id.eq(tenant)),
+    )
+    .set((
+        dsl::txid.eq(txid),
+        dsl::status.eq(OperationStatus::Broadcast.as_str()),
+        dsl::broadcast_at.eq(Utc::now()),
+        dsl::next_retry_at.eq(None::<DateTime<Utc>>),
+        dsl::updated_at.eq(Utc::now()),
+    ))
+    .execute(conn)
+}
@@ -0,0 +1,1028 @@
+//! Tests against a real Postgres, for the SQL the in-memory storage cannot
+//! stand in for. They run only with `ZCASH_HTLC_TEST_DATABASE_URL` set to a
+//! server the tests may create databases on, e.g.
+//! `postgres://postgres@localhost/postgres`; each test works in a database
+//! of its own and drops it afterwards.
+
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::PgConnection;
+use std::time::Duration;
+
+use super::{Database, DatabaseError, ExportFormat, HTLCQuery, JobType, SearchField};
+use crate::test_utils::{test_htlc, test_operation};
+use crate::{
+    Consolidation, FailureKind, HTLCOperation, HTLCOperationType, HTLCSpend, HTLCState,
+    OperationStatus, RelayerUTXO, TenantContext, ZcashHTLC, ZcashNetwork,
+};
+
+const TEST_DATABASE_URL_VAR: &str = "ZCASH_HTLC_TEST_DATABASE_URL";
+
+/// Migrated database of one test, dropped with it
+struct TestDatabase {
+    database: Database,
+    server_url: String,
+    name: String,
+}
+
+impl TestDatabase {
+    /// `None`, skipping the test, without a test server
+    fn create() -> Option<Self> {
+        let server_url = std::env::var(TEST_DATABASE_URL_VAR).ok()?;
+        let name = format!("htlc_test_{}", uuid::Uuid::new_v4().simple());
+
+        let mut conn = PgConnection::establish(&server_url).expect("test server unreachable");
+        diesel::sql_query(format!("CREATE DATABASE {}", name))
+            .execute(&mut conn)
+            .unwrap();
+
+        let (server, _) = server_url.rsplit_once('/').unwrap();
+        let database = Database::new(&format!("{}/{}", server, name), 4).unwrap();
+        database.run_migrations().unwrap();
+
+        Some(Self {
+            database,
+            server_url,
+            name,
+        })
+    }
+
+    /// Connection of its own to the database, outside the pool
+    fn connect(&self) -> PgConnection {
+        let (server, _) = self.server_url.rsplit_once('/').unwrap();
+        PgConnection::establish(&format!("{}/{}", server, self.name)).unwrap()
+    }
+
+    /// Handle on the same database acting for `tenant`
+    fn tenant(&self, tenant: &str) -> Database {
+        self.database
+            .clone()
+            .with_tenant(TenantContext::new(tenant))
+    }
+}
+
+impl Drop for TestDatabase {
+    fn drop(&mut self) {
+        if let Ok(mut conn) = PgConnection::establish(&self.server_url) {
+            let _ = diesel::sql_query(format!(
+                "DROP DATABASE IF EXISTS {} WITH (FORCE)",
+                self.name
+            ))
+            .execute(&mut conn);
+        }
+    }
+}
+
+/// Unfunded HTLC `id` with a hash lock of its own
+fn htlc(id: &str, n: u8) -> ZcashHTLC {
+    ZcashHTLC {
+        hash_lock: format!("{:064x}", n),
+        ..test_htlc(id)
+    }
+}
+
+#[test]
+fn test_job_claims() {
+    let Some(test) = TestDatabase::create() else {
+        return;
+    };
+    let db = &test.database;
+
+    db.create_htlc(&htlc("htlc-1", 1)).unwrap();
+    assert!(!db.enqueue_htlc_job(JobType::FundHtlc, "htlc-1").unwrap());
+
+    // A claim past its visibility timeout passes to the next worker
+    let jobs = db
+        .claim_jobs(JobType::FundHtlc, "worker-a", Duration::ZERO, 10)
+        .unwrap();
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(jobs[0].htlc_id(), Some("htlc-1"));
+    let visibility = Duration::from_secs(60);
+    let jobs = db
+        .claim_jobs(JobType::FundHtlc, "worker-b", visibility, 10)
+        .unwrap();
+    assert_eq!(jobs.len(), 1);
+    assert!(db
+        .claim_jobs(JobType::FundHtlc, "worker-a", visibility, 10)
+        .unwrap()
+        .is_empty());
+    assert!(!db.complete_job(jobs[0].id, "worker-a").unwrap());
+
+    let attempts = db
+        .retry_job(
+            jobs[0].id,
+            "worker-b",
+            "node down",
+            chrono::Duration::zero(),
+        )
+        .unwrap();
+    assert_eq!(attempts, 1);
+    let jobs = db
+        .claim_jobs(JobType::FundHtlc, "worker-a", visibility, 10)
+        .unwrap();
+    assert_eq!(jobs[0].attempts, 1);
+    assert!(db.complete_job(jobs[0].id, "worker-a").unwrap());
+    assert!(db.enqueue_htlc_job(JobType::FundHtlc, "htlc-1").unwrap());
+}
+
+/// HTLCs with a due `job_type` job, claimed only for an instant
+fn queued_jobs(db: &Database, job_type: JobType) -> Vec<String> {
+    let mut ids: Vec<String> = db
+        .claim_jobs(job_type, "peek", Duration::ZERO, 100)
+        .unwrap()
+        .iter()
+        .filter_map(|job| job.htlc_id().map(str::to_string))
+        .collect();
+    ids.sort();
+    ids
+}
+
+#[test]
+fn test_jobs_follow_htlc_state() {
+    let Some(test) = TestDatabase::create() else {
+        return;
+    };
+    let db = &test.database;
+
+    db.create_htlc(&htlc("htlc-1", 1)).unwrap();
+    assert_eq!(queued_jobs(db, JobType::FundHtlc), ["htlc-1"]);
+
+    // A redeem stored early waits for the lock, which also queues the refund
+    db.store_signed_redeem_tx("htlc-1", "00").unwrap();
+    assert!(queued_jobs(db, JobType::BroadcastRedeem).is_empty());
+    assert!(queued_jobs(db, JobType::RefundHtlc).is_empty());
+    db.update_htlc_txid("htlc-1", &"ab".repeat(32), 0).unwrap();
+    assert_eq!(queued_jobs(db, JobType::BroadcastRedeem), ["htlc-1"]);
+    assert_eq!(queued_jobs(db, JobType::RefundHtlc), ["htlc-1"]);
+
+    // Pending again, or restored, an unfunded HTLC is funded again
+    db.create_htlc(&ZcashHTLC {
+        state: HTLCState::Failed,
+        ..htlc("htlc-2", 2)
+    })
+    .unwrap();
+    db.create_htlc(&htlc("htlc-3", 3)).unwrap();
+    db.soft_delete_htlc("htlc-3", "mistake").unwrap();
+    let mut conn = test.connect();
+    diesel::sql_query("DELETE FROM relayer_jobs")
+        .execute(&mut conn)
+        .unwrap();
+    db.update_htlc_state("htlc-2", HTLCState::Pending).unwrap();
+    db.restore_htlc("htlc-3").unwrap();
+    assert_eq!(queued_jobs(db, JobType::FundHtlc), ["htlc-2", "htlc-3"]);
+
+    let mut exported = Vec::new();
+    db.export_htlcs(ExportFormat::Json, &mut exported).unwrap();
+    let imported = TestDatabase::create().unwrap();
+    let target = &imported.database;
+    target
+        .import_htlcs(ExportFormat::Json, exported.as_slice())
+        .unwrap();
+    assert_eq!(queued_jobs(target, JobType::FundHtlc), ["htlc-2", "htlc-3"]);
+    assert_eq!(queued_jobs(target, JobType::RefundHtlc), ["htlc-1"]);
+}
+
+#[test]
+fn test_job_renewal_and_deferral() {
+    let Some(test) = TestDatabase::create() else {
+        return;
+    };
+    let db = &test.database;
+
+    db.create_htlc(&htlc("htlc-1", 1)).unwrap();
+    let visibility = Duration::from_secs(60);
+    let job = db
+        .claim_jobs(JobType::FundHtlc, "worker-a", Duration::ZERO, 10)
+        .unwrap()
+        .remove(0);
+
+    // A renewed claim stays hidden from other workers
+    assert!(db.renew_job(job.id, "worker-a", visibility).unwrap());
+    assert!(db
+        .claim_jobs(JobType::FundHtlc, "worker-b", visibility, 10)
+        .unwrap()
+        .is_empty());
+
+    // A claim that passed on cannot be renewed or deferred by its old worker
+    db.renew_job(job.id, "worker-a", Duration::ZERO).unwrap();
+    let job = db
+        .claim_jobs(JobType::FundHtlc, "worker-b", visibility, 10)
+        .unwrap()
+        .remove(0);
+    assert!(!db.renew_job(job.id, "worker-a", visibility).unwrap());
+    let later = Utc::now() + chrono::Duration::hours(1);
+    assert!(!db.defer_job(job.id, "worker-a", later).unwrap());
+
+    // A deferred job waits without counting an attempt
+    assert!(db.defer_job(job.id, "worker-b", later).unwrap());
+    assert!(db
+        .claim_jobs(JobType::FundHtlc, "worker-a", visibility, 10)
+        .unwrap()
+        .is_empty());
+    let mut conn = test.connect();
+    diesel::sql_query("UPDATE relayer_jobs SET visible_at = NOW()")
+        .execute(&mut conn)
+        .unwrap();
+    let job = db
+        .claim_jobs(JobType::FundHtlc, "worker-a", visibility, 10)
+        .unwrap()
+        .remove(0);
+    assert_eq!(job.attempts, 0);
+}
+
+#[test]
+fn test_event_sink_cursors() {
+    let Some(test) = TestDatabase::create() else {
+        return;
+    };
+    let db = &test.database;
+
+    db.create_htlc(&htlc("htlc-1", 1)).unwrap();
+    db.create_htlc(&htlc("htlc-2", 2)).unwrap();
+
+    let events = db.get_events_for_sink("sink-a", 10).unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(db.get_events_for_sink("sink-b", 10).unwrap().len(), 2);
+    db.mark_event_delivered_to("sink-a", events[0].id).unwrap();
+
+    // Each sink moves on its own
+    let pending = db.get_events_for_sink("sink-a", 10).unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].id, events[1].id);
+    assert_eq!(db.get_events_for_sink("sink-b", 10).unwrap().len(), 2);
+    // A sink seen for the first time skips what was delivered anywhere
+    assert_eq!(db.get_events_for_sink("sink-c", 10).unwrap().len(), 1);
+
+    // A failed event is held back until its retry is due, without holding
+    // up the events after it
+    assert_eq!(
+        db.mark_event_failed_for("sink-a", events[1].id, "timeout")
+            .unwrap(),
+        1
+    );
+    db.create_htlc(&htlc("htlc-3", 3)).unwrap();
+    let pending = db.get_events_for_sink("sink-a", 10).unwrap();
+    assert_eq!(pending.len(), 1);
+    assert!(pending[0].id > events[1].id);
+    db.mark_event_delivered_to("sink-a", pending[0].id).unwrap();
+    assert!(db.get_events_for_sink("sink-a", 10).unwrap().is_empty());
+    assert_eq!(db.get_events_for_sink("sink-b", 10).unwrap().len(), 3);
+
+    // Nothing is pruned while a sink still owes an event
+    let later = chrono::Utc::now() + chrono::Duration::hours(1);
+    assert_eq!(db.prune_events(later, 100).unwrap(), 0);
+    db.abandon_event_for("sink-a", events[1].id).unwrap();
+    for sink in ["sink-b", "sink-c"] {
+        for event in db.get_events_for_sink(sink, 10).unwrap() {
+            db.mark_event_delivered_to(sink, event.id).unwrap();
+        }
+    }
+    assert_eq!(
+        db.prune_events(chrono::Utc::now() - chrono::Duration::hours(1), 100)
+            .unwrap(),
+        0
+    );
+    assert_eq!(db.prune_events(later, 100).unwrap(), 3);
+    assert!(db.get_events_for_sink("sink-a", 10).unwrap().is_empty());
+}
+
+#[test]
+fn test_sink_cursor_waits_for_events_still_committing() {
+    let Some(test) = TestDatabase::create() else {
+        return;
+    };
+    let db = &test.database;
+
+    // An event draws its id, but its transaction commits only after a later
+    // event was delivered
+    let mut slow = test.connect();
+    diesel::sql_query("BEGIN").execute(&mut slow).unwrap();
+    super::operations::queue_event(
+        &mut slow,
+        "default",
+        None,
+        "wallet.low_funds",
+        &serde_json::json!({}),
+    )
+    .unwrap();
+    db.queue_wallet_event("wallet.funds_restored", serde_json::json!({}))
+        .unwrap();
+
+    let events = db.get_events_for_sink("sink", 10).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event_type, "wallet.funds_restored");
+    db.mark_event_delivered_to("sink", events[0].id).unwrap();
+
+    diesel::sql_query("COMMIT").execute(&mut slow).unwrap();
+    let events = db.get_events_for_sink("sink", 10).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event_type, "wallet.low_funds");
+    db.mark_event_delivered_to("sink", events[0].id).unwrap();
+    assert!(db.get_events_for_sink("sink", 10).unwrap().is_empty());
+}
+
+#[test]
+fn test_soft_delete() {
+    let Some(test) = TestDatabase::create() else {
+        return;
+    };
+    let db = &test.database;
+
+    db.create_htlc(&htlc("htlc-1", 1)).unwrap();
+    db.soft_delete_htlc("htlc-1", "test entry").unwrap();
+    assert!(matches!(
+        db.get_htlc_by_id("htlc-1"),
+        Err(DatabaseError::HTLCNotFound(_))
+    ));
+
+    // Its hash lock is free again while it is deleted
+    db.create_htlc(&htlc("htlc-2", 1)).unwrap();
+    assert!(matches!(
+        db.restore_htlc("htlc-1"),
+        Err(DatabaseError::DuplicateHashLock(_))
+    ));
+
+    db.soft_delete_htlc("htlc-2", "duplicate").unwrap();
+    db.restore_htlc("htlc-1").unwrap();
+
+    // HTLCs that may hold funds stay, as do their retries
+    // Pending, with its funding broadcast
+    db.create_htlc(&htlc("htlc-3", 3)).unwrap();
+    db.create_operation(&test_operation(
+        "op-fund",
+        "htlc-3",
+        HTLCOperationType::Create,
+    ))
+    .unwrap();
+    db.record_htlc_funding("htlc-3", "op-fund", &"ab".repeat(32), 0)
+        .unwrap();
+    db.create_htlc(&ZcashHTLC {
+        state: HTLCState::Locked,
+        ..htlc("htlc-4", 4)
+    })
+    .unwrap();
+    for id in ["htlc-3", "htlc-4"] {
+        assert!(matches!(
+            db.soft_delete_htlc(id, "mistake"),
+            Err(DatabaseError::NotDeletable { .. })
+        ));
+    }
+    db.create_htlc(&ZcashHTLC {
+        state: HTLCState::Failed,
+        ..htlc("htlc-5", 5)
+    })
+    .unwrap();
+    db.create_operation(&test_operation(
+        "op-create",
+        "htlc-5",
+        HTLCOperationType::Create,
+    ))
+    .unwrap();
+    db.record_operation_attempt("op-create", "rejected", FailureKind::Node, true)
+        .unwrap();
+    let due = chrono::Utc::now() + chrono::Duration::days(1);
+    assert_eq!(
+        db.get_operations_due_for_retry(due, 5, 10).unwrap().len(),
+        1
+    );
+    db.soft_delete_htlc("htlc-5", "test entry").unwrap();
+    assert!(db
+        .get_operations_due_for_retry(due, 5, 10)
+        .unwrap()
+        .is_empty());
+    assert_eq!(db.get_htlc_by_id("htlc-1").unwrap().id, "htlc-1");
+    assert!(db
+        .get_audit_log("htlc-1")
+        .unwrap()
+        .iter()
+        .any(|entry| entry.reason == "deleted: test entry"));
+}
+
+#[test]
+fn test_spend_settles_an_htlc_awaiting_confirmations() {
+    let Some(test) = TestDatabase::create() else {
+        return;
+    };
+    let db = &test.database;
+
+    // Funded by the relayer, still short of `min_confirmations`
+    db.create_htlc(&htlc("htlc-1", 1)).unwrap();
+    db.create_operation(&test_operation(
+        "op-fund",
+        "htlc-1",
+        HTLCOperationType::Create,
+    ))
+    .unwrap();
+    let funding_txid = "ab".repeat(32);
+    db.record_htlc_funding("htlc-1", "op-fund", &funding_txid, 1)
+        .unwrap();
+
+    let spend = HTLCSpend::Redeem {
+        secret: "5e".repeat(32).into(),
+    };
+    assert!(db
+        .record_htlc_spend("htlc-1", &spend, &"cd".repeat(32))
+        .unwrap());
+    let settled = db.get_htlc_by_id("htlc-1").unwrap();
+    assert_eq!(settled.state, HTLCState::Redeemed);
+    assert_eq!(
+        settled.secret.as_ref().map(|secret| secret.as_str()),
+        Some("5e".repeat(32).as_str())
+    );
+    let states: Vec<HTLCState> = db
+        .get_audit_log("htlc-1")
+        .unwrap()
+        .iter()
+        .map(|entry| entry.new_state)
+        .collect();
+    assert_eq!(
+        states,
+        vec![HTLCState::Pending, HTLCState::Locked, HTLCState::Redeemed]
+    );
+    assert!(db
+        .get_undelivered_events(10)
+        .unwrap()
+        .iter()
+        .any(|event| event.event_type == "htlc.secret_revealed"));
+
+    // The funding confirming later only marks its operation
+    assert!(!db
+        .record_funding_confirmed("htlc-1", "op-fund", &funding_txid, 1, 100)
+        .unwrap());
+    assert_eq!(
+        db.get_htlc_by_id("htlc-1").unwrap().state,
+        HTLCState::Redeemed
+    );
+}
+
+#[test]
+fn test_unconfirmed_operations_rotate() {
+    let Some(test) = TestDatabase::create() else {
+        return;
+    };
+    let db = &test.database;
+
+    db.create_htlc(&htlc("htlc-1", 1)).unwrap();
+    for (id, txid) in [("op-1", "aa"), ("op-2", "bb"), ("op-3", "cc")] {
+        db.create_operation(&test_operation(id, "htlc-1", HTLCOperationType::Redeem))
+            .unwrap();
+        db.update_operation_broadcast(id, &txid.repeat(32)).unwrap();
+    }
+    let next = |limit| -> Vec<String> {
+        db.get_unconfirmed_operations(limit)
+            .unwrap()
+            .into_iter()
+            .map(|operation| operation.id)
+            .collect()
+    };
+
+    assert_eq!(next(2), vec!["op-1", "op-2"]);
+    db.record_operations_checked(&next(2)).unwrap();
+    assert_eq!(next(2), vec!["op-3", "op-1"]);
+    db.record_operations_checked(&["op-3".to_string()]).unwrap();
+    assert_eq!(next(3), vec!["op-1", "op-2", "op-3"]);
+}
+
+#[test]
+fn test_reorg_reverts_a_redeem() {
+    let Some(test) = TestDatabase::create() else {
+        return;
+    };
+    let db = &test.database;
+
+    let locked = ZcashHTLC {
+        txid: Some("ab".repeat(32)),
+        vout: Some(0),
+        state: HTLCState::Locked,
+        ..htlc("htlc-1", 1)
+    };
+    db.create_htlc(&locked).unwrap();
+    db.create_operation(&test_operation(
+        "op-redeem",
+        "htlc-1",
+        HTLCOperationType::Redeem,
+    ))
+    .unwrap();
+    db.record_htlc_settlement("htlc-1", HTLCState::Redeemed, Some("00"), "op-redeem", "cd")
+        .unwrap();
+    db.update_operation_confirmed("op-redeem", 100).unwrap();
+
+    // Another tenant's reorg handling leaves this tenant's operations alone
+    let acme = test.tenant("acme");
+    assert!(acme.get_operations_confirmed_since(0).unwrap().is_empty());
+    acme.update_operation_unconfirmed("op-redeem").unwrap();
+    assert_eq!(
+        db.get_htlc_by_id("htlc-1").unwrap().state,
+        HTLCState::Redeemed
+    );
+
+    db.update_operation_unconfirmed("op-redeem").unwrap();
+    let reverted = db.get_htlc_by_id("htlc-1").unwrap();
+    assert_eq!(reverted.state, HTLCState::Locked);
+    assert_eq!(reverted.secret.as_deref(), Some("00"));
+    let log = db.get_audit_log("htlc-1").unwrap();
+    let revert = log.last().unwrap();
+    assert_eq!(revert.old_state, Some(HTLCState::Redeemed));
+    assert_eq!(revert.new_state, HTLCState::Locked);
+    assert_eq!(
+        revert.reason,
+        "operation op-redeem reorganized out of the chain"
+    );
+
+    // Confirmed again in the new chain
+    db.update_operation_confirmed("op-redeem", 101).unwrap();
+    assert_eq!(
+        db.get_htlc_by_id("htlc-1").unwrap().state,
+        HTLCState::Redeemed
+    );
+}
+
+#[test]
+fn test_block_checkpoints_per_tenant() {
+    let Some(test) = TestDatabase::create() else {
+        return;
+    };
+    let db = &test.database;
+    let acme = test.tenant("acme");
+
+    let hashes = |heights: std::ops::RangeInclusive<u64>| -> Vec<(u64, String)> {
+        heights
+            .map(|height| (height, format!("h{}", height)))
+            .collect()
+    };
+    db.record_block_hashes("testnet", &hashes(100..=103), 101)
+        .unwrap();
+    acme.record_block_hashes("testnet", &hashes(100..=101), 0)
+        .unwrap();
+    db.save_checkpoint("testnet", 103).unwrap();
+    acme.save_checkpoint("testnet", 101).unwrap();
+    assert_eq!(
+        db.get_recent_block_hashes("testnet", 10).unwrap(),
+        hashes(101..=103).into_iter().rev().collect::<Vec<_>>()
+    );
+
+    acme.rewind_block_hashes("testnet", 101).unwrap();
+    assert_eq!(acme.get_checkpoint("testnet").unwrap(), Some(100));
+    assert_eq!(
+        acme.get_recent_block_hashes("testnet", 10).unwrap().len(),
+        1
+    );
+    assert_eq!(db.get_checkpoint("testnet").unwrap(), Some(103));
+    assert_eq!(db.get_recent_block_hashes("testnet", 10).unwrap().len(), 3);
+}
+
+#[test]
+fn test_consolidation_spends_its_reservation() {
+    let Some(test) = TestDatabase::create() else {
+        return;
+    };
+    let db = &test.database;
+    let utxos: Vec<RelayerUTXO> = (0..3)
+        .map(|vout| RelayerUTXO {
+            id: format!("utxo-{}", vout),
+            txid: "aa".repeat(32),
+            vout,
+            amount: format!("0.0000{}", vout + 1),
+            script_pubkey: String::new(),
+            confirmations: 6,
+            address: "tmWallet".to_string(),
+            spent: false,
+            spent_in_tx: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        })
+        .collect();
+    db.create_relayer_utxos(&utxos).unwrap();
+
+    let reservation = db
+        .reserve_smallest_utxos("tmWallet", 2, chrono::Duration::minutes(5))
+        .unwrap();
+    let consolidation = Consolidation {
+        txid: "bb".repeat(32),
+        address: "tmWallet".to_string(),
+        inputs: 2,
+        amount_zat: 2_000,
+        fee_zat: 1_000,
+        tx_size: 340,
+        fee_rate: 2_941,
+        created_at: Utc::now(),
+    };
+    db.commit_consolidation(&reservation.id, &consolidation)
+        .unwrap();
+
+    let unspent = db.get_unspent_relayer_utxos("tmWallet").unwrap();
+    assert_eq!(unspent.len(), 1);
+    assert_eq!(unspent[0].vout, 2);
+    let recorded = db.get_consolidations(10).unwrap();
+    assert_eq!(recorded.len(), 1);
+    assert_eq!(recorded[0].txid, consolidation.txid);
+    assert_eq!(recorded[0].fee_zat, 1_000);
+    assert!(test
+        .tenant("acme")
+        .get_consolidations(10)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_refund_replacement_and_fee_bumps() {
+    let Some(test) = TestDatabase::create() else {
+        return;
+    };
+    let db = &test.database;
+    db.create_htlc_with_operation(
+        &htlc("htlc-1", 1),
+        &test_operation("create", "htlc-1", HTLCOperationType::Create),
+    )
+    .unwrap();
+    for (id, operation_type, bumps) in [
+        ("refund", HTLCOperationType::Refund, None),
+        ("replacement", HTLCOperationType::Refund, Some("refund")),
+        ("bump-1", HTLCOperationType::FeeBump, Some("create")),
+        ("bump-2", HTLCOperationType::FeeBump, Some("create")),
+    ] {
+        db.create_operation(&HTLCOperation {
+            bumps_operation_id: bumps.map(str::to_string),
+            created_at: Utc::now(),
+            ..test_operation(id, "htlc-1", operation_type)
+        })
+        .unwrap();
+    }
+    db.update_operation_broadcast("refund", &"01".repeat(32))
+        .unwrap();
+
+    db.record_operation_replaced("refund", "replacement", &"02".repeat(32))
+        .unwrap();
+    let original = db.get_operation_by_id("refund").unwrap();
+    assert_eq!(original.status, OperationStatus::Failed);
+    assert_eq!(original.next_retry_at, None);
+    let replacement = db.get_operation_by_id("replacement").unwrap();
+    assert_eq!(replacement.status, OperationStatus::Broadcast);
+    assert_eq!(replacement.txid, Some("02".repeat(32)));
+    assert!(matches!(
+        db.record_operation_replaced("missing", "replacement", "tx"),
+        Err(DatabaseError::OperationNotFound(_))
+    ));
+
+    let bumps: Vec<String> = db
+        .get_fee_bumps("create")
+        .unwrap()
+        .into_iter()
+        .map(|bump| bump.id)
+        .collect();
+    assert_eq!(bumps, ["bump-1", "bump-2"]);
+    assert!(db.get_fee_bumps("refund").unwrap().is_empty());
+}
+
+#[test]
+fn test_lease_renewal_fails_once_taken_over() {
+    let Some(test) = TestDatabase::create() else {
+        return;
+    };
+    let db = &test.database;
+    let ttl = Duration::from_millis(500);
+
+    assert!(db.try_acquire_lease("relayer", "a", ttl).unwrap());
+    assert!(!db.try_acquire_lease("relayer", "b", ttl).unwrap());
+    assert!(db.try_acquire_lease("relayer", "a", ttl).unwrap());
+
+    std::thread::sleep(Duration::from_millis(600));
+    assert!(db.try_acquire_lease("relayer", "b", ttl).unwrap());
+    assert!(!db.try_acquire_lease("relayer", "a", ttl).unwrap());
+}
+
+#[test]
+fn test_export_includes_archived_htlcs() {
+    let Some(test) = TestDatabase::create() else {
+        return;
+    };
+    let db = &test.database;
+
+    db.create_htlc(&htlc("htlc-1", 1)).unwrap();
+    let redeemed = ZcashHTLC {
+        state: HTLCState::Redeemed,
+        ..htlc("htlc-2", 2)
+    };
+    db.create_htlc(&redeemed).unwrap();
+    let mut conn = test.connect();
+    diesel::sql_query(
+        "UPDATE zcash_htlcs SET creation_attempts = 2, last_error = 'no funds', \
+         last_error_kind = 'funding' WHERE id = 'htlc-2'",
+    )
+    .execute(&mut conn)
+    .unwrap();
+    let later = chrono::Utc::now() + chrono::Duration::hours(1);
+    assert_eq!(db.archive_settled_htlcs(later, 10).unwrap(), 1);
+
+    // The retry history of a creation outlives its HTLC
+    let archived = diesel::sql_query(
+        "SELECT id FROM zcash_htlcs_archive \
+         WHERE creation_attempts = 2 AND last_error = 'no funds' \
+         AND last_error_kind = 'funding'",
+    )
+    .execute(&mut conn)
+    .unwrap();
+    assert_eq!(archived, 1);
+
+    let mut exported = Vec::new();
+    assert_eq!(
+        db.export_htlcs(ExportFormat::Json, &mut exported).unwrap(),
+        2
+    );
+    let restored = TestDatabase::create().unwrap();
+    let target = &restored.database;
+    assert_eq!(
+        target
+            .import_htlcs(ExportFormat::Json, exported.as_slice())
+            .unwrap(),
+        2
+    );
+    assert_eq!(
+        target.get_htlc_by_id("htlc-2").unwrap().state,
+        HTLCState::Redeemed
+    );
+}
+
+#[test]
+fn test_funding_claims_only_the_named_queued_htlc() {
+    let Some(test) = TestDatabase::create() else {
+        return;
+    };
+    let db = &test.database;
+
+    db.create_htlc(&htlc("htlc-1", 1)).unwrap();
+    let fund = test_operation("op-fund", "", HTLCOperationType::Create);
+    for clash in [
+        htlc("htlc-2", 1),
+        ZcashHTLC {
+            timelock: 1,
+            ..htlc("htlc-1", 1)
+        },
+    ] {
+        assert!(matches!(
+            db.create_htlc_with_operation(&clash, &fund),
+            Err(DatabaseError::DuplicateHashLock(_))
+        ));
+    }
+
+    let built = ZcashHTLC {
+        refund_pubkey: "02".repeat(33),
+        ..htlc("htlc-1", 1)
+    };
+    assert_eq!(
+        db.create_htlc_with_operation(&built, &fund).unwrap(),
+        "htlc-1"
+    );
+    let filled = db.get_htlc_by_id("htlc-1").unwrap();
+    assert_eq!(filled.refund_pubkey, "02".repeat(33));
+    assert_eq!(db.get_operations_by_htlc("htlc-1").unwrap().len(), 1);
+}
+
+#[test]
+fn test_tenant_filters() {
+    let Some(test) = TestDatabase::create() else {
+        return;
+    };
+    let (alice, bob) = (test.tenant("alice"), test.tenant("bob"));
+
+    alice.create_htlc(&htlc("htlc-1", 1)).unwrap();
+    assert!(alice.get_htlc_by_id("htlc-1").is_ok());
+    assert!(matches!(
+        bob.get_htlc_by_id("htlc-1"),
+        Err(DatabaseError::HTLCNotFound(_))
+    ));
+    assert!(matches!(
+        bob.soft_delete_htlc("htlc-1", "not mine"),
+        Err(DatabaseError::HTLCNotFound(_))
+    ));
+    assert!(!bob.enqueue_htlc_job(JobType::FundHtlc, "htlc-1").unwrap());
+
+    let visibility = Duration::from_secs(60);
+    assert!(bob
+        .claim_jobs(JobType::FundHtlc, "worker", visibility, 10)
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        alice
+            .claim_jobs(JobType::FundHtlc, "worker", visibility, 10)
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(bob.release_jobs("worker").unwrap(), 0);
+
+    // Hash locks stay unique across tenants
+    assert!(matches!(
+        bob.create_htlc(&htlc("htlc-2", 1)),
+        Err(DatabaseError::DuplicateHashLock(_))
+    ));
+
+    // Audit entries and events stay with their tenant, and each tenant's
+    // sinks keep cursors of their own
+    assert!(!alice.get_audit_log("htlc-1").unwrap().is_empty());
+    assert!(bob.get_audit_log("htlc-1").unwrap().is_empty());
+    bob.queue_wallet_event("wallet.low_funds", serde_json::json!({}))
+        .unwrap();
+
+    let alice_events = alice.get_events_for_sink("sink", 10).unwrap();
+    assert_eq!(alice_events.len(), 1);
+    assert_eq!(alice_events[0].htlc_id.as_deref(), Some("htlc-1"));
+    let bob_events = bob.get_events_for_sink("sink", 10).unwrap();
+    assert_eq!(bob_events.len(), 1);
+    assert_eq!(bob_events[0].event_type, "wallet.low_funds");
+
+    alice
+        .mark_event_delivered_to("sink", alice_events[0].id)
+        .unwrap();
+    assert!(alice.get_events_for_sink("sink", 10).unwrap().is_empty());
+    assert_eq!(bob.get_events_for_sink("sink", 10).unwrap().len(), 1);
+    assert_eq!(bob.get_undelivered_events(10).unwrap().len(), 1);
+}
+
+#[test]
+fn test_htlc_query_combines_filters() {
+    let Some(test) = TestDatabase::create() else {
+        return;
+    };
+    let db = &test.database;
+    let started = chrono::Utc::now();
+
+    let matching = |id: &str, n: u8, state: HTLCState| ZcashHTLC {
+        state,
+        amount: "1".to_string(),
+        ..htlc(id, n)
+    };
+    db.create_htlc(&matching("htlc-1", 1, HTLCState::Locked))
+        .unwrap();
+    db.create_htlc(&matching("htlc-2", 2, HTLCState::Expired))
+        .unwrap();
+    db.create_htlc(&matching("htlc-3", 3, HTLCState::Locked))
+        .unwrap();
+    db.create_htlc(&matching("redeemed", 4, HTLCState::Redeemed))
+        .unwrap();
+    db.create_htlc(&ZcashHTLC {
+        network: ZcashNetwork::Mainnet,
+        ..matching("mainnet", 5, HTLCState::Locked)
+    })
+    .unwrap();
+    db.create_htlc(&ZcashHTLC {
+        amount: "2.5".to_string(),
+        ..matching("large", 6, HTLCState::Locked)
+    })
+    .unwrap();
+    db.create_htlc(&ZcashHTLC {
+        amount: "0.1".to_string(),
+        ..matching("small", 7, HTLCState::Locked)
+    })
+    .unwrap();
+    test.tenant("acme")
+        .create_htlc(&matching("other-tenant", 8, HTLCState::Locked))
+        .unwrap();
+    db.create_htlc(&htlc("deleted", 9)).unwrap();
+    db.soft_delete_htlc("deleted", "test entry").unwrap();
+    let ended = chrono::Utc::now() + chrono::Duration::seconds(1);
+
+    let matches = |query: HTLCQuery| -> Vec<String> {
+        db.query_htlcs(&query)
+            .unwrap()
+            .into_iter()
+            .map(|htlc| htlc.id)
+            .collect()
+    };
+    let query = HTLCQuery::new()
+        .with_states([HTLCState::Locked, HTLCState::Expired])
+        .with_network(ZcashNetwork::Testnet)
+        .with_created_between(started, ended)
+        .with_min_zatoshis(50_000_000)
+        .with_max_zatoshis(200_000_000);
+    assert_eq!(matches(query.clone()), ["htlc-3", "htlc-2", "htlc-1"]);
+    assert_eq!(
+        matches(query.clone().with_limit(1).with_offset(1)),
+        ["htlc-2"]
+    );
+    assert!(
+        matches(query.with_created_between(started - chrono::Duration::days(1), started))
+            .is_empty()
+    );
+
+    // Unfiltered queries see every HTLC of the tenant but deleted ones
+    assert_eq!(matches(HTLCQuery::new()).len(), 7);
+    let with_deleted = matches(HTLCQuery::new().with_include_deleted(true));
+    assert_eq!(with_deleted.len(), 8);
+    assert_eq!(with_deleted[0], "deleted");
+}
+
+#[test]
+fn test_search_ranks_before_limiting() {
+    let Some(test) = TestDatabase::create() else {
+        return;
+    };
+    let db = &test.database;
+
+    // Oldest first: an id match, a funding txid match and an operation
+    // txid match
+    db.create_htlc(&ZcashHTLC {
+        p2sh_address: "t2Ab12Xyz".to_string(),
+        ..htlc("ab12cd34-0000-4000-8000-000000000000", 1)
+    })
+    .unwrap();
+    let funding_txid = format!("ab12{}", "f".repeat(60));
+    db.create_htlc(&htlc("htlc-funded", 2)).unwrap();
+    db.create_operation(&test_operation(
+        "op-fund",
+        "htlc-funded",
+        HTLCOperationType::Create,
+    ))
+    .unwrap();
+    db.record_htlc_funding("htlc-funded", "op-fund", &funding_txid, 0)
+        .unwrap();
+    db.create_htlc(&htlc("htlc-redeemed", 3)).unwrap();
+    db.create_operation(&test_operation(
+        "op-redeem",
+        "htlc-redeemed",
+        HTLCOperationType::Redeem,
+    ))
+    .unwrap();
+    db.update_operation_broadcast("op-redeem", &format!("ab12{}", "e".repeat(60)))
+        .unwrap();
+    // Newer, but neither visible nor this tenant's
+    db.create_htlc(&htlc("ab12dead-0000-4000-8000-000000000000", 4))
+        .unwrap();
+    db.soft_delete_htlc("ab12dead-0000-4000-8000-000000000000", "test entry")
+        .unwrap();
+    test.tenant("acme")
+        .create_htlc(&htlc("ab12acme-0000-4000-8000-000000000000", 5))
+        .unwrap();
+
+    let found = |term: &str, limit: u32| -> Vec<(String, SearchField, bool)> {
+        db.search_htlcs(term, limit)
+            .unwrap()
+            .into_iter()
+            .map(|m| (m.htlc.id, m.field, m.exact))
+            .collect()
+    };
+    assert_eq!(
+        found("AB12", 10),
+        [
+            (
+                "ab12cd34-0000-4000-8000-000000000000".to_string(),
+                SearchField::Id,
+                false
+            ),
+            ("htlc-funded".to_string(), SearchField::Txid, false),
+            (
+                "htlc-redeemed".to_string(),
+                SearchField::OperationTxid,
+                false
+            ),
+        ]
+    );
+    // The best match survives the limit, however old
+    assert_eq!(found("ab12", 1)[0].1, SearchField::Id);
+    assert_eq!(
+        found(&funding_txid, 10),
+        [("htlc-funded".to_string(), SearchField::Txid, true)]
+    );
+    assert_eq!(found("t2Ab12", 10)[0].1, SearchField::P2shAddress);
+    assert!(found("t2ab12", 10).is_empty());
+    assert!(matches!(
+        db.search_htlcs("ab1", 10),
+        Err(DatabaseError::SearchTermTooShort(_))
+    ));
+}
+
+#[test]
+fn test_amount_filters_compare_zatoshis() {
+    let Some(test) = TestDatabase::create() else {
+        return;
+    };
+    let db = &test.database;
+
+    // One zatoshi apart, which a float comparison cannot tell apart
+    for (id, n, amount) in [
+        ("htlc-1", 1, "20999999.99999999"),
+        ("htlc-2", 2, "21000000"),
+    ] {
+        db.create_htlc(&ZcashHTLC {
+            amount: amount.to_string(),
+            ..htlc(id, n)
+        })
+        .unwrap();
+    }
+
+    let matches = |query: HTLCQuery| -> Vec<String> {
+        db.query_htlcs(&query)
+            .unwrap()
+            .into_iter()
+            .map(|htlc| htlc.id)
+            .collect()
+    };
+    assert_eq!(
+        matches(HTLCQuery::new().with_min_zatoshis(2_100_000_000_000_000)),
+        ["htlc-2"]
+    );
+    assert_eq!(
+        matches(HTLCQuery::new().with_max_zatoshis(2_099_999_999_999_999)),
+        ["htlc-1"]
+    );
+}
@@ -0,0 +1,151 @@
+use chrono::{DateTime, Utc};
+use diesel::dsl::sql;
+use diesel::pg::{Pg, PgConnection};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Bool};
+
+use crate::database::model::DbZcashHTLC;
+use crate::models::schema::zcash_htlcs;
+use crate::{HTLCState, ZcashHTLC, ZcashNetwork};
+
+use super::connections::{Database, DatabaseError};
+
+/// Filters over HTLCs, combined with AND and run as one query by
+/// [`Database::query_htlcs`]. Unset filters match everything but
+/// soft-deleted HTLCs; results are newest first.
+#[derive(Debug, Clone, Default)]
+pub struct HTLCQuery {
+    states: Vec<HTLCState>,
+    network: Option<ZcashNetwork>,
+    created_since: Option<DateTime<Utc>>,
+    created_until: Option<DateTime<Utc>>,
+    min_zatoshis: Option<u64>,
+    max_zatoshis: Option<u64>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+    include_deleted: bool,
+}
+
+impl HTLCQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// HTLCs in any of `states`
+    pub fn with_states(mut self, states: impl IntoIterator<Item = HTLCState>) -> Self {
+        self.states = states.into_iter().collect();
+        self
+    }
+
+    pub fn with_network(mut self, network: ZcashNetwork) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    /// HTLCs created in `[since, until)`
+    pub fn with_created_between(mut self, since: DateTime<Utc>, until: DateTime<Utc>) -> Self {
+        self.created_since = Some(since);
+        self.created_until = Some(until);
+        self
+    }
+
+    /// HTLCs of at least `zatoshis`
+    pub fn with_min_zatoshis(mut self, zatoshis: u64) -> Self {
+        self.min_zatoshis = Some(zatoshis);
+        self
+    }
+
+    /// HTLCs of at most `zatoshis`
+    pub fn with_max_zatoshis(mut self, zatoshis: u64) -> Self {
+        self.max_zatoshis = Some(zatoshis);
+        self
+    }
+
+    pub fn with_limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Skip the first `offset` matches, to page through results
+    pub fn with_offset(mut self, offset: u32) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Also match HTLCs hidden by
+    /// [`Database::soft_delete_htlc`], e.g. for admin listings
+    pub fn with_include_deleted(mut self, include_deleted: bool) -> Self {
+        self.include_deleted = include_deleted;
+        self
+    }
+
+    /// The filters of this query over `tenant`'s HTLCs
+    fn build<'a>(&self, tenant: &'a str) -> zcash_htlcs::BoxedQuery<'a, Pg> {
+        let mut query = zcash_htlcs::table
+            .filter(zcash_htlcs::tenant_id.eq(tenant))
+            .order(zcash_htlcs::created_at.desc())
+            .into_boxed();
+
+        if !self.include_deleted {
+            query = query.filter(zcash_htlcs::deleted_at.is_null());
+        }
+        if !self.states.is_empty() {
+            let states: Vec<i16> = self.states.iter().map(|state| *state as i16).collect();
+            query = query.filter(zcash_htlcs::state.eq_any(states));
+        }
+        if let Some(network) = self.network {
+            query = query.filter(zcash_htlcs::network.eq(network.as_str()));
+        }
+        if let Some(since) = self.created_since {
+            query = query.filter(zcash_htlcs::created_at.ge(since));
+        }
+        if let Some(until) = self.created_until {
+            query = query.filter(zcash_htlcs::created_at.lt(until));
+        }
+        // Amounts are decimal ZEC strings, so compare them in exact zatoshis
+        if let Some(min) = self.min_zatoshis {
+            query = query.filter(
+                sql::<Bool>("zcash_htlcs.amount::numeric * 100000000 >= ")
+                    .bind::<BigInt, _>(min as i64),
+            );
+        }
+        if let Some(max) = self.max_zatoshis {
+            query = query.filter(
+                sql::<Bool>("zcash_htlcs.amount::numeric * 100000000 <= ")
+                    .bind::<BigInt, _>(max as i64),
+            );
+        }
+        if let Some(limit) = self.limit {
+            query = query.limit(limit as i64);
+        }
+        if let Some(offset) = self.offset {
+            query = query.offset(offset as i64);
+        }
+
+        query
+    }
+}
+
+impl Database {
+    /// HTLCs matching all filters of `query`, from the read replica if one
+    /// is configured
+    pub fn query_htlcs(&self, query: &HTLCQuery) -> Result<Vec<ZcashHTLC>, DatabaseError> {
+        let mut conn = self.get_read_connection()?;
+        self.load_htlcs(&mut conn, query)
+    }
+
+    /// Run `query` on `conn`, e.g. a primary connection for work that must
+    /// not see a lagging replica
+    pub(super) fn load_htlcs(
+        &self,
+        conn: &mut PgConnection,
+        query: &HTLCQuery,
+    ) -> Result<Vec<ZcashHTLC>, DatabaseError> {
+        let htlcs = query
+            .build(self.tenant.id())
+            .select(DbZcashHTLC::as_select())
+            .load::<DbZcashHTLC>(conn)?;
+
+        htlcs.into_iter().map(|htlc| self.open_htlc(htlc)).collect()
+    }
+}
@@ -0,0 +1,200 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Bool, Integer, Nullable, SmallInt, Text, Timestamptz};
+use serde::Serialize;
+
+use crate::{HTLCState, TimelockKind, ZcashNetwork};
+
+use super::connections::{Database, DatabaseError};
+
+/// Shortest term [`Database::search_htlcs`] accepts, so a stray keystroke
+/// does not match every HTLC
+pub const MIN_SEARCH_TERM_LEN: usize = 4;
+
+/// HTLCs with an identifier starting with the term, each with its best
+/// ranked match, ranked and limited together. `$1` is the tenant, `$2` the
+/// lowercase `LIKE` pattern for hex ids and txids and `$3` the lowercase
+/// term, `$4` and `$5` the same for case-sensitive P2SH addresses, `$6` the
+/// limit. `field` numbers follow [`SearchField`].
+const SEARCH_QUERY: &str = "WITH matches AS ( \
+        SELECT id AS htlc_id, 0 AS field, id = $3 AS exact FROM zcash_htlcs \
+        WHERE tenant_id = $1 AND id LIKE $2 \
+        UNION ALL \
+        SELECT id, 1, txid = $3 FROM zcash_htlcs \
+        WHERE tenant_id = $1 AND txid LIKE $2 \
+        UNION ALL \
+        SELECT htlc_id, 2, txid = $3 FROM htlc_operations \
+        WHERE tenant_id = $1 AND txid LIKE $2 \
+        UNION ALL \
+        SELECT id, 3, p2sh_address = $5 FROM zcash_htlcs \
+        WHERE tenant_id = $1 AND p2sh_address LIKE $4), \
+    best AS ( \
+        SELECT DISTINCT ON (htlc_id) htlc_id, field, exact FROM matches \
+        ORDER BY htlc_id, exact DESC, field) \
+    SELECT h.id, h.txid, h.vout, h.p2sh_address, h.hash_lock, h.network, h.state, \
+        h.amount, h.timelock, h.timelock_kind, h.recipient_address, h.created_at, \
+        h.updated_at, best.field, best.exact \
+    FROM best JOIN zcash_htlcs h ON h.id = best.htlc_id \
+    WHERE h.tenant_id = $1 AND h.deleted_at IS NULL \
+    ORDER BY best.exact DESC, best.field, h.created_at DESC, h.id \
+    LIMIT $6";
+
+/// Identifier a search term matched, in ranking order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchField {
+    Id,
+    Txid,
+    /// Txid of one of the HTLC's operations, such as its redeem or refund
+    OperationTxid,
+    P2shAddress,
+}
+
+impl SearchField {
+    fn from_i32(value: i32) -> Self {
+        match value {
+            0 => SearchField::Id,
+            1 => SearchField::Txid,
+            2 => SearchField::OperationTxid,
+            _ => SearchField::P2shAddress,
+        }
+    }
+}
+
+/// What a search shows of an HTLC: where it is on chain and how far it
+/// got, without its secret, signed redeem transaction or integrator
+/// metadata
+#[derive(Debug, Clone, Serialize)]
+pub struct HTLCSummary {
+    pub id: String,
+    pub txid: Option<String>,
+    pub vout: Option<u32>,
+    pub p2sh_address: String,
+    pub hash_lock: String,
+    pub network: ZcashNetwork,
+    pub state: HTLCState,
+    pub amount: String,
+    pub timelock: u64,
+    pub timelock_kind: TimelockKind,
+    pub recipient_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// HTLC found by [`Database::search_htlcs`] and what it was found by
+#[derive(Debug, Clone, Serialize)]
+pub struct HTLCSearchMatch {
+    pub field: SearchField,
+    /// Whether the term was the whole identifier rather than a prefix of it
+    pub exact: bool,
+    pub htlc: HTLCSummary,
+}
+
+#[derive(QueryableByName)]
+struct SearchRow {
+    #[diesel(sql_type = Text)]
+    id: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    txid: Option<String>,
+    #[diesel(sql_type = Nullable<Integer>)]
+    vout: Option<i32>,
+    #[diesel(sql_type = Text)]
+    p2sh_address: String,
+    #[diesel(sql_type = Text)]
+    hash_lock: String,
+    #[diesel(sql_type = Text)]
+    network: String,
+    #[diesel(sql_type = SmallInt)]
+    state: i16,
+    #[diesel(sql_type = Text)]
+    amount: String,
+    #[diesel(sql_type = BigInt)]
+    timelock: i64,
+    #[diesel(sql_type = Text)]
+    timelock_kind: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    recipient_address: Option<String>,
+    #[diesel(sql_type = Timestamptz)]
+    created_at: DateTime<Utc>,
+    #[diesel(sql_type = Timestamptz)]
+    updated_at: DateTime<Utc>,
+    #[diesel(sql_type = Integer)]
+    field: i32,
+    #[diesel(sql_type = Bool)]
+    exact: bool,
+}
+
+impl From<SearchRow> for HTLCSearchMatch {
+    fn from(row: SearchRow) -> Self {
+        HTLCSearchMatch {
+            field: SearchField::from_i32(row.field),
+            exact: row.exact,
+            htlc: HTLCSummary {
+                id: row.id,
+                txid: row.txid,
+                vout: row.vout.map(|vout| vout as u32),
+                p2sh_address: row.p2sh_address,
+                hash_lock: row.hash_lock,
+                network: row.network.parse().unwrap_or(ZcashNetwork::Testnet),
+                state: HTLCState::from_i16(row.state),
+                amount: row.amount,
+                timelock: row.timelock as u64,
+                timelock_kind: row.timelock_kind.parse().unwrap_or_default(),
+                recipient_address: row.recipient_address,
+                created_at: row.created_at,
+                updated_at: row.updated_at,
+            },
+        }
+    }
+}
+
+impl Database {
+    /// Up to `limit` HTLCs whose id, funding txid, operation txid or P2SH
+    /// address starts with `term`, for tracing user reports. Ids and txids
+    /// match regardless of case. Exact matches rank first, then by
+    /// [`SearchField`], then newest first.
+    pub fn search_htlcs(
+        &self,
+        term: &str,
+        limit: u32,
+    ) -> Result<Vec<HTLCSearchMatch>, DatabaseError> {
+        let term = term.trim();
+        if term.chars().count() < MIN_SEARCH_TERM_LEN {
+            return Err(DatabaseError::SearchTermTooShort(MIN_SEARCH_TERM_LEN));
+        }
+        let pattern = format!("{}%", escape_like(term));
+        let hex_term = term.to_ascii_lowercase();
+        let hex_pattern = pattern.to_ascii_lowercase();
+
+        let mut conn = self.get_read_connection()?;
+
+        let rows = diesel::sql_query(SEARCH_QUERY)
+            .bind::<Text, _>(self.tenant.id())
+            .bind::<Text, _>(&hex_pattern)
+            .bind::<Text, _>(&hex_term)
+            .bind::<Text, _>(&pattern)
+            .bind::<Text, _>(term)
+            .bind::<BigInt, _>(limit as i64)
+            .load::<SearchRow>(&mut conn)?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+}
+
+/// `term` with the `LIKE` wildcards `%` and `_` matched literally
+fn escape_like(term: &str) -> String {
+    term.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_like_matches_wildcards_literally() {
+        assert_eq!(escape_like("a%b_c\\"), "a\\%b\\_c\\\\");
+        assert_eq!(escape_like("t2UNzU"), "t2UNzU");
+    }
+}
@@ -0,0 +1,421 @@
+use std::sync::Arc;
+
+use crate::{
+    FailureKind, HTLCOperation, HTLCSpend, HTLCState, TenantContext, ZcashHTLC, ZcashNetwork,
+};
+
+use super::connections::{Database, DatabaseError};
+
+/// Persistence of HTLCs, their operations and chain checkpoints as used by
+/// [`ZcashHTLCClient`](crate::ZcashHTLCClient): [`Database`] in production,
+/// [`MemoryStorage`](super::MemoryStorage) in tests, or an embedder's own
+/// store. Methods behave as their namesakes on [`Database`].
+#[async_trait::async_trait]
+pub trait Storage: Send + Sync {
+    /// Tenant whose HTLCs, operations and checkpoints this store reads and
+    /// writes
+    fn tenant(&self) -> &TenantContext;
+
+    /// The same store, acting for `tenant`
+    fn with_tenant(&self, tenant: TenantContext) -> Arc<dyn Storage>;
+
+    /// The Postgres database behind this store, if any, for what only it
+    /// offers such as exports
+    fn as_database(&self) -> Option<&Database> {
+        None
+    }
+
+    /// Insert an HTLC and the operation creating it together, filling in a
+    /// pending HTLC with the same hash lock instead if one awaits funding.
+    /// Returns the id of the HTLC the operation was recorded for.
+    async fn create_htlc_with_operation(
+        &self,
+        htlc: &ZcashHTLC,
+        operation: &HTLCOperation,
+    ) -> Result<String, DatabaseError>;
+
+    /// Record the funding output of an HTLC, still pending, and mark the
+    /// funding operation broadcast
+    async fn record_htlc_funding(
+        &self,
+        htlc_id: &str,
+        operation_id: &str,
+        txid: &str,
+        vout: u32,
+    ) -> Result<(), DatabaseError>;
+
+    /// Mark a funding operation confirmed and lock its HTLC at `txid:vout`,
+    /// returning `false` if the HTLC was already locked or has moved on
+    async fn record_funding_confirmed(
+        &self,
+        htlc_id: &str,
+        operation_id: &str,
+        txid: &str,
+        vout: u32,
+        block_height: u64,
+    ) -> Result<bool, DatabaseError>;
+
+    /// Lock an HTLC at a funding output found on chain
+    async fn update_htlc_txid(
+        &self,
+        htlc_id: &str,
+        txid: &str,
+        vout: u32,
+    ) -> Result<(), DatabaseError>;
+
+    /// Move an HTLC to its final `state`, keeping the revealed `secret` of a
+    /// redeem, and mark the spending operation broadcast
+    async fn record_htlc_settlement(
+        &self,
+        htlc_id: &str,
+        state: HTLCState,
+        secret: Option<&str>,
+        operation_id: &str,
+        txid: &str,
+    ) -> Result<(), DatabaseError>;
+
+    /// Settle an HTLC spent on chain by someone else's transaction,
+    /// returning `false` if it was already settled
+    async fn record_htlc_spend(
+        &self,
+        htlc_id: &str,
+        spend: &HTLCSpend,
+        spending_txid: &str,
+    ) -> Result<bool, DatabaseError>;
+
+    async fn get_htlc_by_id(&self, htlc_id: &str) -> Result<ZcashHTLC, DatabaseError>;
+
+    /// HTLCs awaiting funding or still locked
+    async fn get_unsettled_htlcs(
+        &self,
+        network: ZcashNetwork,
+    ) -> Result<Vec<ZcashHTLC>, DatabaseError>;
+
+    async fn create_operation(&self, operation: &HTLCOperation) -> Result<(), DatabaseError>;
+
+    /// Record that the transaction of an operation settling no HTLC, such
+    /// as a fee bump, was broadcast as `txid`
+    async fn record_operation_broadcast(
+        &self,
+        operation_id: &str,
+        txid: &str,
+    ) -> Result<(), DatabaseError>;
+
+    /// Record that `replacement_id`, spending the same outputs as
+    /// `operation_id` at a higher fee, was broadcast as `txid`, and mark
+    /// the operation it replaces failed for good
+    async fn record_operation_replaced(
+        &self,
+        operation_id: &str,
+        replacement_id: &str,
+        txid: &str,
+    ) -> Result<(), DatabaseError>;
+
+    /// Count a failed broadcast and, if `retry`, schedule the next attempt,
+    /// returning the attempts so far
+    async fn record_operation_attempt(
+        &self,
+        operation_id: &str,
+        error: &str,
+        kind: FailureKind,
+        retry: bool,
+    ) -> Result<u32, DatabaseError>;
+
+    async fn update_operation_confirmed(
+        &self,
+        operation_id: &str,
+        block_height: u64,
+    ) -> Result<(), DatabaseError>;
+
+    /// Broadcast operations not yet seen confirmed, those checked least
+    /// recently first
+    async fn get_unconfirmed_operations(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<HTLCOperation>, DatabaseError>;
+
+    /// Move operations whose transactions were just checked to the back of
+    /// [`Storage::get_unconfirmed_operations`]
+    async fn record_operations_checked(
+        &self,
+        operation_ids: &[String],
+    ) -> Result<(), DatabaseError>;
+
+    /// Move a confirmed operation back to broadcast, and its HTLC back to the state the operation's confirmation moved it from
+    async fn update_operation_unconfirmed(&self, operation_id: &str) -> Result<(), DatabaseError>;
+
+    /// Confirmed operations at `block_height` or above
+    async fn get_operations_confirmed_since(
+        &self,
+        block_height: u64,
+    ) -> Result<Vec<HTLCOperation>, DatabaseError>;
+
+    async fn get_checkpoint(&self, chain: &str) -> Result<Option<u32>, DatabaseError>;
+
+    async fn save_checkpoint(&self, chain: &str, block_height: u32) -> Result<(), DatabaseError>;
+
+    /// Record `(height, hash)` pairs, replacing older hashes at those
+    /// heights, and drop hashes below `keep_from` in the same write
+    async fn record_block_hashes(
+        &self,
+        chain: &str,
+        hashes: &[(u64, String)],
+        keep_from: u64,
+    ) -> Result<(), DatabaseError>;
+
+    /// Up to `limit` recorded `(height, hash)` pairs, newest first
+    async fn get_recent_block_hashes(
+        &self,
+        chain: &str,
+        limit: i64,
+    ) -> Result<Vec<(u64, String)>, DatabaseError>;
+
+    /// Forget block hashes from `fork_height` up and move the checkpoint
+    /// back below it
+    async fn rewind_block_hashes(&self, chain: &str, fork_height: u64)
+        -> Result<(), DatabaseError>;
+}
+
+/// Runs every call on a blocking worker through [`Database::run`]
+#[async_trait::async_trait]
+impl Storage for Database {
+    fn tenant(&self) -> &TenantContext {
+        Database::tenant(self)
+    }
+
+    fn with_tenant(&self, tenant: TenantContext) -> Arc<dyn Storage> {
+        Arc::new(self.clone().with_tenant(tenant))
+    }
+
+    fn as_database(&self) -> Option<&Database> {
+        Some(self)
+    }
+
+    async fn create_htlc_with_operation(
+        &self,
+        htlc: &ZcashHTLC,
+        operation: &HTLCOperation,
+    ) -> Result<String, DatabaseError> {
+        let (htlc, operation) = (htlc.clone(), operation.clone());
+        self.run(move |db| db.create_htlc_with_operation(&htlc, &operation))
+            .await
+    }
+
+    async fn record_htlc_funding(
+        &self,
+        htlc_id: &str,
+        operation_id: &str,
+        txid: &str,
+        vout: u32,
+    ) -> Result<(), DatabaseError> {
+        let (htlc_id, operation_id, txid) = (
+            htlc_id.to_string(),
+            operation_id.to_string(),
+            txid.to_string(),
+        );
+        self.run(move |db| db.record_htlc_funding(&htlc_id, &operation_id, &txid, vout))
+            .await
+    }
+
+    async fn record_funding_confirmed(
+        &self,
+        htlc_id: &str,
+        operation_id: &str,
+        txid: &str,
+        vout: u32,
+        block_height: u64,
+    ) -> Result<bool, DatabaseError> {
+        let (htlc_id, operation_id, txid) = (
+            htlc_id.to_string(),
+            operation_id.to_string(),
+            txid.to_string(),
+        );
+        self.run(move |db| {
+            db.record_funding_confirmed(&htlc_id, &operation_id, &txid, vout, block_height)
+        })
+        .await
+    }
+
+    async fn update_htlc_txid(
+        &self,
+        htlc_id: &str,
+        txid: &str,
+        vout: u32,
+    ) -> Result<(), DatabaseError> {
+        let (htlc_id, txid) = (htlc_id.to_string(), txid.to_string());
+        self.run(move |db| db.update_htlc_txid(&htlc_id, &txid, vout))
+            .await
+    }
+
+    async fn record_htlc_settlement(
+        &self,
+        htlc_id: &str,
+        state: HTLCState,
+        secret: Option<&str>,
+        operation_id: &str,
+        txid: &str,
+    ) -> Result<(), DatabaseError> {
+        let (htlc_id, secret, operation_id, txid) = (
+            htlc_id.to_string(),
+            secret.map(str::to_string),
+            operation_id.to_string(),
+            txid.to_string(),
+        );
+        self.run(move |db| {
+            db.record_htlc_settlement(&htlc_id, state, secret.as_deref(), &operation_id, &txid)
+        })
+        .await
+    }
+
+    async fn record_htlc_spend(
+        &self,
+        htlc_id: &str,
+        spend: &HTLCSpend,
+        spending_txid: &str,
+    ) -> Result<bool, DatabaseError> {
+        let (htlc_id, spend, spending_txid) = (
+            htlc_id.to_string(),
+            spend.clone(),
+            spending_txid.to_string(),
+        );
+        self.run(move |db| db.record_htlc_spend(&htlc_id, &spend, &spending_txid))
+            .await
+    }
+
+    async fn get_htlc_by_id(&self, htlc_id: &str) -> Result<ZcashHTLC, DatabaseError> {
+        let htlc_id = htlc_id.to_string();
+        self.run(move |db| db.get_htlc_by_id(&htlc_id)).await
+    }
+
+    async fn get_unsettled_htlcs(
+        &self,
+        network: ZcashNetwork,
+    ) -> Result<Vec<ZcashHTLC>, DatabaseError> {
+        self.run(move |db| db.get_unsettled_htlcs(network)).await
+    }
+
+    async fn create_operation(&self, operation: &HTLCOperation) -> Result<(), DatabaseError> {
+        let operation = operation.clone();
+        self.run(move |db| db.create_operation(&operation)).await
+    }
+
+    async fn record_operation_broadcast(
+        &self,
+        operation_id: &str,
+        txid: &str,
+    ) -> Result<(), DatabaseError> {
+        let (operation_id, txid) = (operation_id.to_string(), txid.to_string());
+        self.run(move |db| db.update_operation_broadcast(&operation_id, &txid))
+            .await
+    }
+
+    async fn record_operation_replaced(
+        &self,
+        operation_id: &str,
+        replacement_id: &str,
+        txid: &str,
+    ) -> Result<(), DatabaseError> {
+        let (operation_id, replacement_id, txid) = (
+            operation_id.to_string(),
+            replacement_id.to_string(),
+            txid.to_string(),
+        );
+        self.run(move |db| db.record_operation_replaced(&operation_id, &replacement_id, &txid))
+            .await
+    }
+
+    async fn record_operation_attempt(
+        &self,
+        operation_id: &str,
+        error: &str,
+        kind: FailureKind,
+        retry: bool,
+    ) -> Result<u32, DatabaseError> {
+        let (operation_id, error) = (operation_id.to_string(), error.to_string());
+        self.run(move |db| db.record_operation_attempt(&operation_id, &error, kind, retry))
+            .await
+    }
+
+    async fn update_operation_confirmed(
+        &self,
+        operation_id: &str,
+        block_height: u64,
+    ) -> Result<(), DatabaseError> {
+        let operation_id = operation_id.to_string();
+        self.run(move |db| db.update_operation_confirmed(&operation_id, block_height))
+            .await
+    }
+
+    async fn get_unconfirmed_operations(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<HTLCOperation>, DatabaseError> {
+        self.run(move |db| db.get_unconfirmed_operations(limit))
+            .await
+    }
+
+    async fn record_operations_checked(
+        &self,
+        operation_ids: &[String],
+    ) -> Result<(), DatabaseError> {
+        let operation_ids = operation_ids.to_vec();
+        self.run(move |db| db.record_operations_checked(&operation_ids))
+            .await
+    }
+
+    async fn update_operation_unconfirmed(&self, operation_id: &str) -> Result<(), DatabaseError> {
+        let operation_id = operation_id.to_string();
+        self.run(move |db| db.update_operation_unconfirmed(&operation_id))
+            .await
+    }
+
+    async fn get_operations_confirmed_since(
+        &self,
+        block_height: u64,
+    ) -> Result<Vec<HTLCOperation>, DatabaseError> {
+        self.run(move |db| db.get_operations_confirmed_since(block_height))
+            .await
+    }
+
+    async fn get_checkpoint(&self, chain: &str) -> Result<Option<u32>, DatabaseError> {
+        let chain = chain.to_string();
+        self.run(move |db| db.get_checkpoint(&chain)).await
+    }
+
+    async fn save_checkpoint(&self, chain: &str, block_height: u32) -> Result<(), DatabaseError> {
+        let chain = chain.to_string();
+        self.run(move |db| db.save_checkpoint(&chain, block_height))
+            .await
+    }
+
+    async fn record_block_hashes(
+        &self,
+        chain: &str,
+        hashes: &[(u64, String)],
+        keep_from: u64,
+    ) -> Result<(), DatabaseError> {
+        let (chain, hashes) = (chain.to_string(), hashes.to_vec());
+        self.run(move |db| db.record_block_hashes(&chain, &hashes, keep_from))
+            .await
+    }
+
+    async fn get_recent_block_hashes(
+        &self,
+        chain: &str,
+        limit: i64,
+    ) -> Result<Vec<(u64, String)>, DatabaseError> {
+        let chain = chain.to_string();
+        self.run(move |db| db.get_recent_block_hashes(&chain, limit))
+            .await
+    }
+
+    async fn rewind_block_hashes(
+        &self,
+        chain: &str,
+        fork_height: u64,
+    ) -> Result<(), DatabaseError> {
+        let chain = chain.to_string();
+        self.run(move |db| db.rewind_block_hashes(&chain, fork_height))
+            .await
+    }
+}
@@ -0,0 +1,245 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use tracing::info;
+
+use crate::database::model::NewRelayerUTXO;
+use crate::models::schema::relayer_utxos;
+use crate::UTXO;
+
+use super::connections::{Database, DatabaseError};
+
+/// What [`Database::sync_relayer_utxos`] changed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct UtxoSyncReport {
+    /// UTXOs on chain that were not in `relayer_utxos` yet
+    pub discovered: usize,
+    /// Known UTXOs whose confirmation count changed
+    pub confirmations_updated: usize,
+    /// Confirmed UTXOs gone from the chain without the relayer spending
+    /// them, e.g. swept by another wallet holding the key
+    pub spent_externally: usize,
+    /// UTXOs taken for spent externally that are back on chain, e.g. after
+    /// a reorg or a lagging explorer
+    pub restored: usize,
+}
+
+impl UtxoSyncReport {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Known row of the synced address, as far as syncing cares
+#[derive(Debug, Queryable)]
+struct KnownUtxo {
+    id: String,
+    txid: String,
+    vout: i32,
+    confirmations: i32,
+    spent: bool,
+    reserved_until: Option<DateTime<Utc>>,
+}
+
+/// Changes bringing the known rows in line with the chain
+#[derive(Debug, Default)]
+struct UtxoSyncPlan<'a> {
+    discovered: Vec<&'a UTXO>,
+    /// Row id and the confirmations it now has
+    confirmations: Vec<(String, u32)>,
+    spent: Vec<String>,
+    restored: Vec<(String, u32)>,
+}
+
+impl Database {
+    /// Reconcile the `relayer_utxos` of `address` with `chain`, its unspent
+    /// outputs as seen by the node or an explorer, in one transaction: new
+    /// outputs are inserted, confirmations updated, and confirmed rows no
+    /// longer on chain marked spent. Reserved rows are left to their
+    /// reservation.
+    pub fn sync_relayer_utxos(
+        &self,
+        address: &str,
+        chain: &[UTXO],
+    ) -> Result<UtxoSyncReport, DatabaseError> {
+        let mut conn = self.get_connection()?;
+
+        let report = conn.transaction(|conn| {
+            // Rows spent by the relayer itself name their spending tx and
+            // are settled; only unspent or externally spent rows can change
+            let known: Vec<KnownUtxo> = relayer_utxos::table
+                .filter(relayer_utxos::tenant_id.eq(self.tenant.id()))
+                .filter(relayer_utxos::address.eq(address))
+                .filter(
+                    relayer_utxos::spent
+                        .eq(false)
+                        .or(relayer_utxos::spent_in_tx.is_null()),
+                )
+                .select((
+                    relayer_utxos::id,
+                    relayer_utxos::txid,
+                    relayer_utxos::vout,
+                    relayer_utxos::confirmations,
+                    relayer_utxos::spent,
+                    relayer_utxos::reserved_until,
+                ))
+                .for_update()
+                .load(conn)?;
+            let plan = plan_sync(&known, chain, Utc::now());
+
+            let rows: Vec<NewRelayerUTXO> = plan
+                .discovered
+                .iter()
+                .map(|utxo| NewRelayerUTXO {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    txid: utxo.txid.clone(),
+                    vout: utxo.vout as i32,
+                    amount: utxo.amount.clone(),
+                    script_pubkey: utxo.script_pubkey.clone(),
+                    confirmations: utxo.confirmations as i32,
+                    address: address.to_string(),
+                    tenant_id: self.tenant.id().to_string(),
+                })
+                .collect();
+            let discovered = if rows.is_empty() {
+                0
+            } else {
+                diesel::insert_into(relayer_utxos::table)
+                    .values(&rows)
+                    .on_conflict((relayer_utxos::txid, relayer_utxos::vout))
+                    .do_nothing()
+                    .execute(conn)?
+            };
+
+            for (id, confirmations) in &plan.confirmations {
+                diesel::update(relayer_utxos::table.filter(relayer_utxos::id.eq(id)))
+                    .set((
+                        relayer_utxos::confirmations.eq(*confirmations as i32),
+                        relayer_utxos::updated_at.eq(Utc::now()),
+                    ))
+                    .execute(conn)?;
+            }
+            diesel::update(relayer_utxos::table.filter(relayer_utxos::id.eq_any(&plan.spent)))
+                .set((
+                    relayer_utxos::spent.eq(true),
+                    relayer_utxos::updated_at.eq(Utc::now()),
+                ))
+                .execute(conn)?;
+            for (id, confirmations) in &plan.restored {
+                diesel::update(relayer_utxos::table.filter(relayer_utxos::id.eq(id)))
+                    .set((
+                        relayer_utxos::spent.eq(false),
+                        relayer_utxos::confirmations.eq(*confirmations as i32),
+                        relayer_utxos::updated_at.eq(Utc::now()),
+                    ))
+                    .execute(conn)?;
+            }
+
+            Ok::<_, DatabaseError>(UtxoSyncReport {
+                discovered,
+                confirmations_updated: plan.confirmations.len(),
+                spent_externally: plan.spent.len(),
+                restored: plan.restored.len(),
+            })
+        })?;
+
+        if !report.is_empty() {
+            info!(
+                "📦 Synced UTXOs of {}: {} discovered, {} confirmations updated, {} spent externally, {} restored",
+                address,
+                report.discovered,
+                report.confirmations_updated,
+                report.spent_externally,
+                report.restored
+            );
+        }
+        Ok(report)
+    }
+}
+
+/// Compare the `known` rows with the `chain` outputs. Unconfirmed rows
+/// missing from the chain are left alone, as an explorer may not have seen
+/// them yet.
+fn plan_sync<'a>(known: &[KnownUtxo], chain: &'a [UTXO], now: DateTime<Utc>) -> UtxoSyncPlan<'a> {
+    let on_chain: HashMap<(&str, u32), &UTXO> = chain
+        .iter()
+        .map(|utxo| ((utxo.txid.as_str(), utxo.vout), utxo))
+        .collect();
+    let known_outpoints: HashSet<(&str, u32)> = known
+        .iter()
+        .map(|row| (row.txid.as_str(), row.vout as u32))
+        .collect();
+
+    let mut plan = UtxoSyncPlan {
+        discovered: chain
+            .iter()
+            .filter(|utxo| !known_outpoints.contains(&(utxo.txid.as_str(), utxo.vout)))
+            .collect(),
+        ..Default::default()
+    };
+    for row in known {
+        match on_chain.get(&(row.txid.as_str(), row.vout as u32)) {
+            Some(utxo) if row.spent => plan.restored.push((row.id.clone(), utxo.confirmations)),
+            Some(utxo) if row.confirmations as u32 != utxo.confirmations => plan
+                .confirmations
+                .push((row.id.clone(), utxo.confirmations)),
+            Some(_) => {}
+            None => {
+                let reserved = row.reserved_until.is_some_and(|until| until > now);
+                if !row.spent && row.confirmations >= 1 && !reserved {
+                    plan.spent.push(row.id.clone());
+                }
+            }
+        }
+    }
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_sync_reconciles_known_rows_with_chain() {
+        let now = Utc::now();
+        let row = |id: &str, confirmations: i32, spent: bool| KnownUtxo {
+            id: id.to_string(),
+            txid: id.repeat(64),
+            vout: 0,
+            confirmations,
+            spent,
+            reserved_until: None,
+        };
+        let utxo = |id: &str, confirmations: u32| UTXO {
+            txid: id.repeat(64),
+            vout: 0,
+            amount: "1.0".to_string(),
+            script_pubkey: "76a9".to_string(),
+            confirmations,
+        };
+
+        let mut reserved = row("d", 3, false);
+        reserved.reserved_until = Some(now + chrono::Duration::minutes(5));
+        let known = vec![
+            row("a", 1, false),
+            row("b", 6, false),
+            row("c", 2, true),
+            reserved,
+            row("e", 0, false),
+            row("f", 6, false),
+        ];
+        let chain = vec![utxo("a", 2), utxo("c", 4), utxo("f", 6), utxo("g", 0)];
+
+        let plan = plan_sync(&known, &chain, now);
+        let discovered: Vec<&str> = plan
+            .discovered
+            .iter()
+            .map(|utxo| utxo.txid.as_str())
+            .collect();
+        assert_eq!(discovered, vec![chain[3].txid.as_str()]);
+        assert_eq!(plan.confirmations, vec![("a".to_string(), 2)]);
+        assert_eq!(plan.spent, vec!["b".to_string()]);
+        assert_eq!(plan.restored, vec![("c".to_string(), 4)]);
+    }
+}
@@ -0,0 +1,811 @@
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, RequestBuilder};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::rpc::{request_error, RpcClientError};
+use crate::telemetry;
+use crate::{zatoshis_to_zec, ExplorerConfig, ExplorerKind, UTXO};
+
+/// Block explorer API answering address queries the node cannot, since
+/// zcashd keeps no address index
+#[async_trait::async_trait]
+pub trait Explorer: Send + Sync {
+    /// Base URL of the API
+    fn url(&self) -> &str;
+
+    /// Unspent transparent outputs of `address`
+    async fn get_utxos(&self, address: &str) -> Result<Vec<UTXO>, RpcClientError>;
+
+    /// Balance of `address` in ZEC
+    async fn get_balance(&self, address: &str) -> Result<String, RpcClientError>;
+
+    /// Push a raw transaction to the explorer's peers, returning its txid
+    async fn broadcast(&self, tx_hex: &str) -> Result<String, RpcClientError>;
+}
+
+/// Explorer for `config`, sending requests through `client`
+pub fn from_config(config: &ExplorerConfig, client: Client) -> Box<dyn Explorer> {
+    let mut headers = custom_headers(config);
+
+    match config.kind {
+        ExplorerKind::Insight => {
+            if let Some(key) = &config.api_key {
+                match HeaderValue::from_str(key) {
+                    Ok(value) => {
+                        headers.insert(API_KEY_HEADER, value);
+                    }
+                    Err(_) => warn!("⚠️ Ignoring api_key of {}: not a valid header", config.url),
+                }
+            }
+            Box::new(
+                InsightExplorer::new(client, &config.url)
+                    .with_headers(headers)
+                    .with_pagination(Pagination::from_config(config)),
+            )
+        }
+        ExplorerKind::Blockchair => Box::new(
+            BlockchairExplorer::new(client, &config.url, config.api_key.clone())
+                .with_headers(headers)
+                .with_pagination(Pagination::from_config(config)),
+        ),
+    }
+}
+
+/// Page size of UTXO queries, and the most UTXOs fetched for one address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pagination {
+    pub page_size: usize,
+    pub max_items: usize,
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        Self {
+            page_size: 100,
+            max_items: 10_000,
+        }
+    }
+}
+
+impl Pagination {
+    fn from_config(config: &ExplorerConfig) -> Self {
+        let default = Self::default();
+        Self {
+            page_size: config.page_size.unwrap_or(default.page_size).max(1),
+            max_items: config.max_utxos.unwrap_or(default.max_items),
+        }
+    }
+
+    /// Fetch pages of `(offset, limit)` until one comes back short. Pages
+    /// repeating earlier UTXOs mean the server ignores the offset and has
+    /// already sent everything.
+    async fn collect<'a>(
+        &self,
+        url: &str,
+        fetch_page: impl Fn(usize, usize) -> ExplorerFuture<'a, Vec<UTXO>>,
+    ) -> Result<Vec<UTXO>, RpcClientError> {
+        let mut utxos: Vec<UTXO> = Vec::new();
+
+        loop {
+            let page = fetch_page(utxos.len(), self.page_size).await?;
+            let more = page.len() >= self.page_size;
+
+            let repeated = page.first().is_some_and(|first| {
+                utxos
+                    .iter()
+                    .any(|utxo| utxo.txid == first.txid && utxo.vout == first.vout)
+            });
+            if repeated {
+                break;
+            }
+            utxos.extend(page);
+
+            if utxos.len() >= self.max_items {
+                warn!(
+                    "⚠️ Stopping at {} UTXOs from {}; raise max_utxos to fetch more",
+                    self.max_items, url
+                );
+                utxos.truncate(self.max_items);
+                break;
+            }
+            if !more {
+                break;
+            }
+        }
+
+        Ok(utxos)
+    }
+}
+
+/// Header carrying `api_key` for explorers without a key parameter
+const API_KEY_HEADER: &str = "x-api-key";
+
+fn custom_headers(config: &ExplorerConfig) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+
+    for (name, value) in config.headers.iter().flatten() {
+        match (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            (Ok(name), Ok(mut value)) => {
+                value.set_sensitive(true);
+                headers.insert(name, value);
+            }
+            _ => warn!(
+                "⚠️ Ignoring invalid header {} for explorer {}",
+                name, config.url
+            ),
+        }
+    }
+
+    headers
+}
+
+/// Token bucket holding up to `burst` requests, refilled at `per_sec`
+/// (at least one request every 1000 seconds)
+pub struct RateLimiter {
+    per_sec: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(per_sec: f64, burst: u32) -> Self {
+        let burst = f64::from(burst.max(1));
+        Self {
+            per_sec: per_sec.max(0.001),
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Wait until a request may be sent
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap();
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * self.per_sec).min(self.burst);
+                bucket.refilled_at = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.per_sec)
+            };
+
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Explorer whose requests wait for a shared [`RateLimiter`]
+pub struct RateLimitedExplorer {
+    inner: Box<dyn Explorer>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimitedExplorer {
+    pub fn new(inner: Box<dyn Explorer>, limiter: Arc<RateLimiter>) -> Self {
+        Self { inner, limiter }
+    }
+}
+
+#[async_trait::async_trait]
+impl Explorer for RateLimitedExplorer {
+    fn url(&self) -> &str {
+        self.inner.url()
+    }
+
+    async fn get_utxos(&self, address: &str) -> Result<Vec<UTXO>, RpcClientError> {
+        self.limiter.acquire().await;
+        self.inner.get_utxos(address).await
+    }
+
+    async fn get_balance(&self, address: &str) -> Result<String, RpcClientError> {
+        self.limiter.acquire().await;
+        self.inner.get_balance(address).await
+    }
+
+    async fn broadcast(&self, tx_hex: &str) -> Result<String, RpcClientError> {
+        self.limiter.acquire().await;
+        self.inner.broadcast(tx_hex).await
+    }
+}
+
+type ExplorerFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, RpcClientError>> + Send + 'a>>;
+
+/// Explorers asked in order, moving on to the next one on HTTP errors,
+/// unreachable hosts or responses that do not match the expected schema
+pub struct FallbackExplorer {
+    explorers: Vec<Box<dyn Explorer>>,
+    answered_by: Mutex<Option<String>>,
+}
+
+impl FallbackExplorer {
+    pub fn new(explorers: Vec<Box<dyn Explorer>>) -> Self {
+        Self {
+            explorers,
+            answered_by: Mutex::new(None),
+        }
+    }
+
+    /// URL of the explorer that answered the last query
+    pub fn answered_by(&self) -> Option<String> {
+        self.answered_by.lock().unwrap().clone()
+    }
+
+    async fn first_answer<'a, T>(
+        &'a self,
+        operation: &'static str,
+        query: impl Fn(&'a dyn Explorer) -> ExplorerFuture<'a, T>,
+    ) -> Result<T, RpcClientError> {
+        let mut last_error = None;
+
+        for explorer in &self.explorers {
+            let started = Instant::now();
+            let result = query(explorer.as_ref()).await;
+            telemetry::record_explorer(operation, explorer.url(), started.elapsed(), &result);
+
+            match result {
+                Ok(answer) => {
+                    *self.answered_by.lock().unwrap() = Some(explorer.url().to_string());
+                    return Ok(answer);
+                }
+                Err(e) => {
+                    warn!("⚠️ Explorer {} failed: {}", explorer.url(), e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error
+            .unwrap_or_else(|| RpcClientError::ExplorerError("no explorer configured".to_string())))
+    }
+}
+
+#[async_trait::async_trait]
+impl Explorer for FallbackExplorer {
+    /// URL of the primary explorer
+    fn url(&self) -> &str {
+        self.explorers.first().map_or("", |explorer| explorer.url())
+    }
+
+    async fn get_utxos(&self, address: &str) -> Result<Vec<UTXO>, RpcClientError> {
+        self.first_answer("get_utxos", |explorer| explorer.get_utxos(address))
+            .await
+    }
+
+    async fn get_balance(&self, address: &str) -> Result<String, RpcClientError> {
+        self.first_answer("get_balance", |explorer| explorer.get_balance(address))
+            .await
+    }
+
+    async fn broadcast(&self, tx_hex: &str) -> Result<String, RpcClientError> {
+        self.first_answer("broadcast", |explorer| explorer.broadcast(tx_hex))
+            .await
+    }
+}
+
+async fn get_json<T: DeserializeOwned>(
+    client: &Client,
+    url: &str,
+    headers: &HeaderMap,
+    query: &[(&str, &str)],
+) -> Result<T, RpcClientError> {
+    send_json(client.get(url).headers(headers.clone()).query(query)).await
+}
+
+async fn send_json<T: DeserializeOwned>(request: RequestBuilder) -> Result<T, RpcClientError> {
+    let response = request.send().await.map_err(request_error)?;
+
+    if !response.status().is_success() {
+        return Err(RpcClientError::ExplorerError(format!(
+            "HTTP {} from explorer",
+            response.status()
+        )));
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|e| RpcClientError::ParseError(e.to_string()))
+}
+
+/// Insight API, as served by zcashd-backed explorers such as
+/// `explorer.testnet.z.cash/api`
+pub struct InsightExplorer {
+    client: Client,
+    url: String,
+    headers: HeaderMap,
+    pagination: Pagination,
+}
+
+#[derive(Deserialize)]
+struct InsightUtxo {
+    txid: String,
+    vout: u32,
+    #[serde(rename = "scriptPubKey")]
+    script_pubkey: String,
+    satoshis: u64,
+    #[serde(default)]
+    confirmations: u32,
+}
+
+#[derive(Deserialize)]
+struct InsightTxSent {
+    txid: String,
+}
+
+impl InsightExplorer {
+    pub fn new(client: Client, url: &str) -> Self {
+        Self {
+            client,
+            url: url.trim_end_matches('/').to_string(),
+            headers: HeaderMap::new(),
+            pagination: Pagination::default(),
+        }
+    }
+
+    /// Send `headers`, e.g. API keys, with every request
+    pub fn with_headers(mut self, headers: HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    pub fn with_pagination(mut self, pagination: Pagination) -> Self {
+        self.pagination = pagination;
+        self
+    }
+
+    /// UTXOs `from..to`; servers without paging return all of them
+    async fn utxo_page(
+        &self,
+        address: &str,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<UTXO>, RpcClientError> {
+        let (from, to) = (offset.to_string(), (offset + limit).to_string());
+        let utxos: Vec<InsightUtxo> = get_json(
+            &self.client,
+            &format!("{}/addr/{}/utxo", self.url, address),
+            &self.headers,
+            &[("from", from.as_str()), ("to", to.as_str())],
+        )
+        .await?;
+
+        Ok(utxos
+            .into_iter()
+            .map(|u| UTXO {
+                txid: u.txid,
+                vout: u.vout,
+                amount: zatoshis_to_zec(u.satoshis),
+                script_pubkey: u.script_pubkey,
+                confirmations: u.confirmations,
+            })
+            .collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl Explorer for InsightExplorer {
+    fn url(&self) -> &str {
+        &self.url
+    }
+
+    async fn get_utxos(&self, address: &str) -> Result<Vec<UTXO>, RpcClientError> {
+        self.pagination
+            .collect(&self.url, |offset, limit| {
+                Box::pin(self.utxo_page(address, offset, limit))
+            })
+            .await
+    }
+
+    async fn get_balance(&self, address: &str) -> Result<String, RpcClientError> {
+        let zatoshis: u64 = get_json(
+            &self.client,
+            &format!("{}/addr/{}/balance", self.url, address),
+            &self.headers,
+            &[],
+        )
+        .await?;
+
+        Ok(zatoshis_to_zec(zatoshis))
+    }
+
+    async fn broadcast(&self, tx_hex: &str) -> Result<String, RpcClientError> {
+        let sent: InsightTxSent = send_json(
+            self.client
+                .post(format!("{}/tx/send", self.url))
+                .headers(self.headers.clone())
+                .json(&serde_json::json!({ "rawtx": tx_hex })),
+        )
+        .await?;
+
+        Ok(sent.txid)
+    }
+}
+
+/// Blockchair API, e.g. `https://api.blockchair.com/zcash`
+pub struct BlockchairExplorer {
+    client: Client,
+    url: String,
+    /// Sent as the `key` query parameter
+    api_key: Option<String>,
+    headers: HeaderMap,
+    pagination: Pagination,
+}
+
+#[derive(Deserialize)]
+struct BlockchairResponse {
+    data: HashMap<String, BlockchairDashboard>,
+    context: BlockchairContext,
+}
+
+#[derive(Deserialize)]
+struct BlockchairContext {
+    /// Best block height
+    state: u64,
+}
+
+#[derive(Deserialize)]
+struct BlockchairDashboard {
+    address: BlockchairAddress,
+    #[serde(default)]
+    utxo: Vec<BlockchairUtxo>,
+}
+
+#[derive(Deserialize)]
+struct BlockchairAddress {
+    script_hex: String,
+    balance: u64,
+}
+
+#[derive(Deserialize)]
+struct BlockchairPushResponse {
+    data: BlockchairPushed,
+}
+
+#[derive(Deserialize)]
+struct BlockchairPushed {
+    transaction_hash: String,
+}
+
+#[derive(Deserialize)]
+struct BlockchairUtxo {
+    /// -1 while unconfirmed
+    block_id: i64,
+    transaction_hash: String,
+    index: u32,
+    value: u64,
+}
+
+impl BlockchairExplorer {
+    pub fn new(client: Client, url: &str, api_key: Option<String>) -> Self {
+        Self {
+            client,
+            url: url.trim_end_matches('/').to_string(),
+            api_key,
+            headers: HeaderMap::new(),
+            pagination: Pagination::default(),
+        }
+    }
+
+    /// Send `headers` with every request
+    pub fn with_headers(mut self, headers: HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    pub fn with_pagination(mut self, pagination: Pagination) -> Self {
+        self.pagination = pagination;
+        self
+    }
+
+    /// Address dashboard with UTXOs `offset..offset + limit`, and the tip
+    async fn dashboard(
+        &self,
+        address: &str,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(BlockchairDashboard, u64), RpcClientError> {
+        // Limits and offsets are given for transactions, then UTXOs
+        let limit = format!("0,{}", limit);
+        let offset = format!("0,{}", offset);
+        let mut query = vec![("limit", limit.as_str()), ("offset", offset.as_str())];
+        if let Some(key) = &self.api_key {
+            query.push(("key", key.as_str()));
+        }
+
+        let mut response: BlockchairResponse = get_json(
+            &self.client,
+            &format!("{}/dashboards/address/{}", self.url, address),
+            &self.headers,
+            &query,
+        )
+        .await?;
+
+        let dashboard = response.data.remove(address).ok_or_else(|| {
+            RpcClientError::ParseError(format!("no dashboard for {} in response", address))
+        })?;
+        Ok((dashboard, response.context.state))
+    }
+
+    async fn utxo_page(
+        &self,
+        address: &str,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<UTXO>, RpcClientError> {
+        let (dashboard, tip) = self.dashboard(address, offset, limit).await?;
+        let script_pubkey = dashboard.address.script_hex;
+
+        Ok(dashboard
+            .utxo
+            .into_iter()
+            .map(|u| UTXO {
+                txid: u.transaction_hash,
+                vout: u.index,
+                amount: zatoshis_to_zec(u.value),
+                script_pubkey: script_pubkey.clone(),
+                confirmations: match u64::try_from(u.block_id) {
+                    Ok(height) if height <= tip => (tip - height + 1) as u32,
+                    _ => 0,
+                },
+            })
+            .collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl Explorer for BlockchairExplorer {
+    fn url(&self) -> &str {
+        &self.url
+    }
+
+    async fn get_utxos(&self, address: &str) -> Result<Vec<UTXO>, RpcClientError> {
+        self.pagination
+            .collect(&self.url, |offset, limit| {
+                Box::pin(self.utxo_page(address, offset, limit))
+            })
+            .await
+    }
+
+    async fn get_balance(&self, address: &str) -> Result<String, RpcClientError> {
+        let (dashboard, _) = self.dashboard(address, 0, 0).await?;
+        Ok(zatoshis_to_zec(dashboard.address.balance))
+    }
+
+    async fn broadcast(&self, tx_hex: &str) -> Result<String, RpcClientError> {
+        let mut request = self
+            .client
+            .post(format!("{}/push/transaction", self.url))
+            .headers(self.headers.clone())
+            .form(&[("data", tx_hex)]);
+        if let Some(key) = &self.api_key {
+            request = request.query(&[("key", key.as_str())]);
+        }
+
+        let pushed: BlockchairPushResponse = send_json(request).await?;
+        Ok(pushed.data.transaction_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockServer;
+    use serde_json::{json, Value};
+
+    /// Explorer answering every request with `body`
+    async fn mock_explorer(body: Value) -> String {
+        MockServer::respond("200 OK", body).await.url
+    }
+
+    /// Explorer answering requests containing `required`, lowercase, in
+    /// their headers or body with `body`, and others with 401
+    async fn keyed_mock_explorer(required: &'static str, body: Value) -> String {
+        let body = body.to_string();
+        MockServer::start(move |request| {
+            let text = format!("{}\r\n\r\n{}", request.head, request.body).to_lowercase();
+            if text.contains(required) {
+                ("200 OK", body.clone())
+            } else {
+                ("401 Unauthorized", String::new())
+            }
+        })
+        .await
+        .url
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_on_schema_mismatch() {
+        let address = "t1Vz5Fh7LRpnL4DjWW3v8aTBZBnGCCe1w6j";
+        let broken = mock_explorer(json!({"error": "rate limited"})).await;
+        let backup = mock_explorer(json!(150_000_000u64)).await;
+
+        let explorer = FallbackExplorer::new(vec![
+            Box::new(InsightExplorer::new(Client::new(), &broken)),
+            Box::new(InsightExplorer::new(Client::new(), &backup)),
+        ]);
+        assert_eq!(explorer.get_balance(address).await.unwrap(), "1.50000000");
+        assert_eq!(explorer.answered_by(), Some(backup));
+
+        let all_down =
+            FallbackExplorer::new(vec![Box::new(InsightExplorer::new(Client::new(), &broken))]);
+        assert!(matches!(
+            all_down.get_balance(address).await,
+            Err(RpcClientError::ParseError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sends_api_key_and_custom_headers() {
+        let address = "t1Vz5Fh7LRpnL4DjWW3v8aTBZBnGCCe1w6j";
+        let url = keyed_mock_explorer("x-api-key: secret", json!(100_000_000u64)).await;
+
+        let config = ExplorerConfig::new(ExplorerKind::Insight, &url);
+        assert!(matches!(
+            from_config(&config, Client::new())
+                .get_balance(address)
+                .await,
+            Err(RpcClientError::ExplorerError(_))
+        ));
+
+        let keyed = config.clone().with_api_key("secret");
+        assert_eq!(
+            from_config(&keyed, Client::new())
+                .get_balance(address)
+                .await
+                .unwrap(),
+            "1.00000000"
+        );
+
+        let url = keyed_mock_explorer("authorization: bearer token", json!(0u64)).await;
+        let config = ExplorerConfig::new(ExplorerKind::Insight, &url)
+            .with_header("Authorization", "Bearer token");
+        assert!(from_config(&config, Client::new())
+            .get_balance(address)
+            .await
+            .is_ok());
+        assert!(!format!("{:?}", config).contains("token"));
+    }
+
+    #[tokio::test]
+    async fn test_paginates_utxos_up_to_the_limit() {
+        let utxo = |n: usize| UTXO {
+            txid: format!("{:064x}", n),
+            vout: 0,
+            amount: "0.00010000".to_string(),
+            script_pubkey: "76a914".to_string(),
+            confirmations: 1,
+        };
+        let pages = |total: usize| {
+            move |offset: usize, limit: usize| -> ExplorerFuture<'static, Vec<UTXO>> {
+                Box::pin(async move { Ok((offset..total.min(offset + limit)).map(utxo).collect()) })
+            }
+        };
+        let pagination = Pagination {
+            page_size: 100,
+            max_items: 1_000,
+        };
+
+        let utxos = pagination.collect("mock", pages(250)).await.unwrap();
+        assert_eq!(utxos.len(), 250);
+        assert_eq!(utxos[249].txid, utxo(249).txid);
+        assert_eq!(
+            pagination.collect("mock", pages(200)).await.unwrap().len(),
+            200
+        );
+        assert_eq!(
+            pagination
+                .collect("mock", pages(5_000))
+                .await
+                .unwrap()
+                .len(),
+            1_000
+        );
+
+        // A server ignoring the offset sends its one page again
+        let unpaged = |_: usize, _: usize| -> ExplorerFuture<'static, Vec<UTXO>> {
+            Box::pin(async move { Ok((0..150).map(utxo).collect()) })
+        };
+        assert_eq!(
+            pagination.collect("mock", unpaged).await.unwrap().len(),
+            150
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter_spaces_requests() {
+        let limiter = RateLimiter::new(20.0, 2);
+        let start = Instant::now();
+
+        // The burst goes out at once, the next two at 20 per second
+        for _ in 0..4 {
+            limiter.acquire().await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[tokio::test]
+    async fn test_explorer_formats() {
+        let address = "t1Vz5Fh7LRpnL4DjWW3v8aTBZBnGCCe1w6j";
+
+        let insight = InsightExplorer::new(
+            Client::new(),
+            &mock_explorer(json!([{
+                "address": address,
+                "txid": "11".repeat(32),
+                "vout": 1,
+                "scriptPubKey": "76a914",
+                "amount": 0.5,
+                "satoshis": 50_000_000u64,
+                "confirmations": 7
+            }]))
+            .await,
+        );
+        let utxos = insight.get_utxos(address).await.unwrap();
+        assert_eq!(utxos.len(), 1);
+        assert_eq!(utxos[0].amount, "0.50000000");
+        assert_eq!(utxos[0].confirmations, 7);
+
+        let blockchair = BlockchairExplorer::new(
+            Client::new(),
+            &mock_explorer(json!({
+                "data": {address: {
+                    "address": {"type": "pubkeyhash", "script_hex": "76a914", "balance": 75_000_000u64},
+                    "utxo": [
+                        {"block_id": 2_500_000, "transaction_hash": "22".repeat(32), "index": 0, "value": 50_000_000u64},
+                        {"block_id": -1, "transaction_hash": "33".repeat(32), "index": 2, "value": 25_000_000u64}
+                    ]
+                }},
+                "context": {"code": 200, "state": 2_500_009}
+            }))
+            .await,
+            None,
+        );
+        let utxos = blockchair.get_utxos(address).await.unwrap();
+        assert_eq!(utxos[0].confirmations, 10);
+        assert_eq!(utxos[0].script_pubkey, "76a914");
+        assert_eq!(utxos[1].confirmations, 0);
+        assert_eq!(blockchair.get_balance(address).await.unwrap(), "0.75000000");
+    }
+
+    #[tokio::test]
+    async fn test_pushes_transactions() {
+        let txid = "44".repeat(32);
+
+        let insight =
+            InsightExplorer::new(Client::new(), &mock_explorer(json!({ "txid": txid })).await);
+        assert_eq!(insight.broadcast("0400").await.unwrap(), txid);
+
+        let blockchair = BlockchairExplorer::new(
+            Client::new(),
+            &keyed_mock_explorer(
+                "data=0400",
+                json!({"data": {"transaction_hash": txid}, "context": {"code": 200}}),
+            )
+            .await,
+            None,
+        );
+        assert_eq!(blockchair.broadcast("0400").await.unwrap(), txid);
+    }
+}
@@ -0,0 +1,171 @@
+use bip39::Mnemonic;
+use bitcoin::secp256k1::Secp256k1;
+use bitcoin::util::bip32::{ChildNumber, DerivationPath, ExtendedPrivKey};
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+use zeroize::Zeroizing;
+
+use crate::ZcashNetwork;
+
+/// BIP44 external chain for Zcash mainnet (coin type 133)
+pub const MAINNET_DERIVATION_PATH: &str = "m/44'/133'/0'/0";
+
+/// BIP44 external chain for testnet (coin type 1)
+pub const TESTNET_DERIVATION_PATH: &str = "m/44'/1'/0'/0";
+
+/// BIP39 seed wallet deriving transparent keys along a BIP32 path. Derived
+/// keys are returned in [`Zeroizing`] buffers that are wiped on drop.
+pub struct HDWallet {
+    secp: Secp256k1<bitcoin::secp256k1::All>,
+    master: ExtendedPrivKey,
+    base_path: DerivationPath,
+}
+
+impl HDWallet {
+    /// Generate a new English mnemonic with 12, 15, 18, 21 or 24 words
+    pub fn generate_mnemonic(word_count: usize) -> Result<Zeroizing<String>, HDError> {
+        Self::generate_mnemonic_with_rng(word_count, &mut rand::thread_rng())
+    }
+
+    /// Generate a mnemonic from `rng` instead of OS entropy, for reproducible
+    /// test fixtures
+    pub fn generate_mnemonic_with_rng<R: RngCore + CryptoRng + ?Sized>(
+        word_count: usize,
+        rng: &mut R,
+    ) -> Result<Zeroizing<String>, HDError> {
+        if !(12..=24).contains(&word_count) || word_count % 3 != 0 {
+            return Err(HDError::InvalidWordCount(word_count));
+        }
+
+        let mut entropy = Zeroizing::new(vec![0u8; word_count / 3 * 4]);
+        rng.fill_bytes(&mut entropy);
+
+        let mnemonic = Mnemonic::from_entropy(&entropy)
+            .map_err(|e| HDError::InvalidMnemonic(e.to_string()))?;
+        Ok(Zeroizing::new(mnemonic.to_string()))
+    }
+
+    /// Restore a wallet from a mnemonic, deriving along the network's default path
+    pub fn from_mnemonic(
+        phrase: &str,
+        passphrase: &str,
+        network: ZcashNetwork,
+    ) -> Result<Self, HDError> {
+        let mnemonic =
+            Mnemonic::parse(phrase).map_err(|e| HDError::InvalidMnemonic(e.to_string()))?;
+        let seed = Zeroizing::new(mnemonic.to_seed(passphrase));
+
+        let master = ExtendedPrivKey::new_master(network.to_bitcoin_network(), seed.as_slice())
+            .map_err(|e| HDError::DerivationError(e.to_string()))?;
+
+        let default_path = match network {
+            ZcashNetwork::Mainnet => MAINNET_DERIVATION_PATH,
+            ZcashNetwork::Testnet => TESTNET_DERIVATION_PATH,
+        };
+
+        Ok(Self {
+            secp: Secp256k1::new(),
+            master,
+            base_path: DerivationPath::from_str(default_path)
+                .map_err(|e| HDError::InvalidPath(e.to_string()))?,
+        })
+    }
+
+    /// Derive children under `path` instead of the network default
+    pub fn with_base_path(mut self, path: &str) -> Result<Self, HDError> {
+        self.base_path =
+            DerivationPath::from_str(path).map_err(|e| HDError::InvalidPath(e.to_string()))?;
+        Ok(self)
+    }
+
+    /// Hex private key at `<base_path>/index`
+    pub fn derive_privkey(&self, index: u32) -> Result<Zeroizing<String>, HDError> {
+        let child = ChildNumber::from_normal_idx(index)
+            .map_err(|e| HDError::DerivationError(e.to_string()))?;
+        let path = self.base_path.child(child);
+
+        self.derive_path(&path)
+    }
+
+    /// Hex private key at an absolute derivation path
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<Zeroizing<String>, HDError> {
+        let xpriv = self
+            .master
+            .derive_priv(&self.secp, path)
+            .map_err(|e| HDError::DerivationError(e.to_string()))?;
+
+        let bytes = Zeroizing::new(xpriv.private_key.secret_bytes());
+        Ok(Zeroizing::new(hex::encode(bytes.as_slice())))
+    }
+
+    /// Hex private key for a per-HTLC label such as its hash lock. The
+    /// child index is taken from the label's SHA-256, so the key can be
+    /// re-derived later without storing any state.
+    pub fn derive_for_label(&self, label: &str) -> Result<Zeroizing<String>, HDError> {
+        self.derive_privkey(Self::index_for_label(label))
+    }
+
+    pub fn index_for_label(label: &str) -> u32 {
+        let digest = Sha256::digest(label.as_bytes());
+        u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]) & 0x7FFF_FFFF
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum HDError {
+    #[error("Invalid mnemonic: {0}")]
+    InvalidMnemonic(String),
+
+    #[error("Invalid mnemonic word count: {0}")]
+    InvalidWordCount(usize),
+
+    #[error("Invalid derivation path: {0}")]
+    InvalidPath(String),
+
+    #[error("Key derivation failed: {0}")]
+    DerivationError(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn test_generate_mnemonic_roundtrip() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let phrase = HDWallet::generate_mnemonic(24).unwrap();
+        assert_eq!(phrase.split_whitespace().count(), 24);
+        assert!(HDWallet::from_mnemonic(phrase.as_str(), "", ZcashNetwork::Testnet).is_ok());
+        assert!(HDWallet::generate_mnemonic(13).is_err());
+
+        assert_eq!(
+            HDWallet::generate_mnemonic_with_rng(12, &mut StdRng::seed_from_u64(1)).unwrap(),
+            HDWallet::generate_mnemonic_with_rng(12, &mut StdRng::seed_from_u64(1)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_derivation_is_deterministic() {
+        let wallet = HDWallet::from_mnemonic(PHRASE, "", ZcashNetwork::Mainnet).unwrap();
+        let again = HDWallet::from_mnemonic(PHRASE, "", ZcashNetwork::Mainnet).unwrap();
+
+        assert_eq!(
+            wallet.derive_privkey(0).unwrap(),
+            again.derive_privkey(0).unwrap()
+        );
+        assert_ne!(
+            wallet.derive_privkey(0).unwrap(),
+            wallet.derive_privkey(1).unwrap()
+        );
+        assert_eq!(
+            wallet.derive_for_label("hash").unwrap(),
+            wallet
+                .derive_privkey(HDWallet::index_for_label("hash"))
+                .unwrap()
+        );
+    }
+}
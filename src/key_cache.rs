@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::info;
+use zeroize::{Zeroize, Zeroizing};
+
+const KEY_LEN: usize = 32;
+
+/// Short-lived cache of decrypted private keys. Each key lives on its own
+/// mlock'd page so it is never written to swap, and is wiped once its TTL
+/// runs out or the cache is purged.
+pub struct KeyCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, CachedKey>>,
+}
+
+struct CachedKey {
+    key: LockedKey,
+    expires_at: Instant,
+}
+
+impl KeyCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Hex private key cached under `key_id`, if it has not expired
+    pub fn get(&self, key_id: &str) -> Option<Zeroizing<String>> {
+        let mut entries = self.entries.lock().unwrap();
+
+        match entries.get(key_id) {
+            Some(entry) if entry.expires_at > Instant::now() => {
+                Some(Zeroizing::new(hex::encode(entry.key.as_bytes())))
+            }
+            Some(_) => {
+                entries.remove(key_id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Cache a hex private key under `key_id` for one TTL
+    pub fn insert(&self, key_id: &str, privkey_hex: &str) -> Result<(), KeyCacheError> {
+        let privkey =
+            Zeroizing::new(hex::decode(privkey_hex).map_err(|_| KeyCacheError::InvalidKey)?);
+        if privkey.len() != KEY_LEN {
+            return Err(KeyCacheError::InvalidKey);
+        }
+
+        let entry = CachedKey {
+            key: LockedKey::new(&privkey)?,
+            expires_at: Instant::now() + self.ttl,
+        };
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key_id.to_string(), entry);
+
+        Ok(())
+    }
+
+    /// Wipe keys whose TTL has run out
+    pub fn purge_expired(&self) {
+        let now = Instant::now();
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|_, entry| entry.expires_at > now);
+    }
+
+    /// Wipe every cached key, e.g. on shutdown
+    pub fn purge(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        if !entries.is_empty() {
+            info!("🧹 Purging {} cached key(s)", entries.len());
+        }
+        entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Drop for KeyCache {
+    fn drop(&mut self) {
+        self.purge();
+    }
+}
+
+/// Key bytes on a page of their own, locked in RAM. The buffer spans two
+/// pages so that one whole page can be locked without sharing it with other
+/// allocations, whose unlock would otherwise unlock this key too.
+struct LockedKey {
+    // Declared first so the page is unlocked before the buffer is freed
+    _guard: region::LockGuard,
+    buffer: Vec<u8>,
+    offset: usize,
+}
+
+impl LockedKey {
+    fn new(key: &[u8]) -> Result<Self, KeyCacheError> {
+        let page = region::page::size();
+        let mut buffer = vec![0u8; 2 * page];
+        let offset = (page - buffer.as_ptr() as usize % page) % page;
+
+        let guard = region::lock(buffer[offset..].as_ptr(), page)
+            .map_err(|e| KeyCacheError::LockFailed(e.to_string()))?;
+        buffer[offset..offset + KEY_LEN].copy_from_slice(key);
+
+        Ok(Self {
+            _guard: guard,
+            buffer,
+            offset,
+        })
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.buffer[self.offset..self.offset + KEY_LEN]
+    }
+}
+
+impl Drop for LockedKey {
+    fn drop(&mut self) {
+        self.buffer.zeroize();
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum KeyCacheError {
+    #[error("Invalid private key")]
+    InvalidKey,
+
+    #[error("Failed to lock key memory: {0}")]
+    LockFailed(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_cache_ttl_and_purge() {
+        let privkey = "0101010101010101010101010101010101010101010101010101010101010101";
+
+        let cache = KeyCache::new(Duration::from_secs(60));
+        cache.insert("hot", privkey).unwrap();
+        assert_eq!(cache.get("hot").unwrap().as_str(), privkey);
+        assert!(cache.get("cold").is_none());
+        assert!(matches!(
+            cache.insert("bad", "abcd"),
+            Err(KeyCacheError::InvalidKey)
+        ));
+
+        cache.purge();
+        assert!(cache.is_empty());
+        assert!(cache.get("hot").is_none());
+
+        let expired = KeyCache::new(Duration::ZERO);
+        expired.insert("hot", privkey).unwrap();
+        assert!(expired.get("hot").is_none());
+        assert!(expired.is_empty());
+    }
+}
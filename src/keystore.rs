@@ -0,0 +1,362 @@
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use zeroize::Zeroizing;
+
+use crate::key_cache::KeyCache;
+use crate::signer::{LocalSigner, Signer, SignerError};
+
+/// Keystore directory used when `keystore_dir` is not configured
+pub const DEFAULT_KEYSTORE_DIR: &str = "./keystore";
+
+const KEYSTORE_VERSION: u32 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Argon2id cost parameters, stored alongside each key so they can be
+/// raised later without breaking existing files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+/// On-disk format of one encrypted private key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedKey {
+    pub version: u32,
+    pub id: String,
+    pub pubkey: String,
+    pub kdf: KdfParams,
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+/// Directory of passphrase-encrypted private keys (argon2id + AES-256-GCM),
+/// one `<id>.json` file per key
+pub struct Keystore {
+    dir: PathBuf,
+    kdf: KdfParams,
+}
+
+impl Keystore {
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self, KeystoreError> {
+        fs::create_dir_all(dir.as_ref()).map_err(|e| KeystoreError::Io(e.to_string()))?;
+
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+            kdf: KdfParams::default(),
+        })
+    }
+
+    /// KDF cost for newly stored keys
+    pub fn with_kdf_params(mut self, kdf: KdfParams) -> Self {
+        self.kdf = kdf;
+        self
+    }
+
+    /// Generate a new key under `id`, returning its public key
+    pub fn generate(&self, id: &str, passphrase: &str) -> Result<String, KeystoreError> {
+        let mut privkey = Zeroizing::new([0u8; 32]);
+        rand::thread_rng().fill_bytes(privkey.as_mut_slice());
+
+        self.import(
+            id,
+            &Zeroizing::new(hex::encode(privkey.as_slice())),
+            passphrase,
+        )
+    }
+
+    /// Encrypt and store an existing hex private key, returning its public key
+    pub fn import(
+        &self,
+        id: &str,
+        privkey_hex: &str,
+        passphrase: &str,
+    ) -> Result<String, KeystoreError> {
+        let path = self.key_path(id)?;
+        if path.exists() {
+            return Err(KeystoreError::AlreadyExists(id.to_string()));
+        }
+
+        let privkey =
+            Zeroizing::new(hex::decode(privkey_hex).map_err(|_| KeystoreError::InvalidKey)?);
+        let mut secret =
+            secp256k1::SecretKey::from_slice(&privkey).map_err(|_| KeystoreError::InvalidKey)?;
+        let pubkey = secp256k1::PublicKey::from_secret_key(secp256k1::SECP256K1, &secret);
+        secret.non_secure_erase();
+
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let cipher = cipher(passphrase, &salt, self.kdf)?;
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), privkey.as_slice())
+            .map_err(|_| KeystoreError::Crypto("encryption failed".to_string()))?;
+
+        let entry = EncryptedKey {
+            version: KEYSTORE_VERSION,
+            id: id.to_string(),
+            pubkey: hex::encode(pubkey.serialize()),
+            kdf: self.kdf,
+            salt: hex::encode(salt),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        };
+
+        let json =
+            serde_json::to_string_pretty(&entry).map_err(|e| KeystoreError::Io(e.to_string()))?;
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        // Readable by the owner only, from the moment the file exists
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::AlreadyExists => KeystoreError::AlreadyExists(id.to_string()),
+            _ => KeystoreError::Io(e.to_string()),
+        })?;
+        file.write_all(json.as_bytes())
+            .map_err(|e| KeystoreError::Io(e.to_string()))?;
+
+        Ok(entry.pubkey)
+    }
+
+    /// Decrypt the hex private key stored under `id`
+    pub fn unlock(&self, id: &str, passphrase: &str) -> Result<Zeroizing<String>, KeystoreError> {
+        let entry = self.load(id)?;
+
+        let salt = hex::decode(&entry.salt).map_err(|e| KeystoreError::Corrupt(e.to_string()))?;
+        let nonce = hex::decode(&entry.nonce).map_err(|e| KeystoreError::Corrupt(e.to_string()))?;
+        let ciphertext =
+            hex::decode(&entry.ciphertext).map_err(|e| KeystoreError::Corrupt(e.to_string()))?;
+
+        if nonce.len() != NONCE_LEN {
+            return Err(KeystoreError::Corrupt("bad nonce length".to_string()));
+        }
+
+        let cipher = cipher(passphrase, &salt, entry.kdf)?;
+        let privkey = Zeroizing::new(
+            cipher
+                .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+                .map_err(|_| KeystoreError::WrongPassphrase)?,
+        );
+
+        Ok(Zeroizing::new(hex::encode(privkey.as_slice())))
+    }
+
+    /// Public key of `id`, readable without the passphrase
+    pub fn pubkey(&self, id: &str) -> Result<String, KeystoreError> {
+        Ok(self.load(id)?.pubkey)
+    }
+
+    /// Ids of all stored keys
+    pub fn list(&self) -> Result<Vec<String>, KeystoreError> {
+        let mut ids = Vec::new();
+
+        for entry in fs::read_dir(&self.dir).map_err(|e| KeystoreError::Io(e.to_string()))? {
+            let path = entry.map_err(|e| KeystoreError::Io(e.to_string()))?.path();
+            if path.extension().and_then(|e| e.to_str()) == Some("json") {
+                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                    ids.push(stem.to_string());
+                }
+            }
+        }
+
+        ids.sort();
+        Ok(ids)
+    }
+
+    fn load(&self, id: &str) -> Result<EncryptedKey, KeystoreError> {
+        let path = self.key_path(id)?;
+        let content =
+            fs::read_to_string(&path).map_err(|_| KeystoreError::NotFound(id.to_string()))?;
+
+        serde_json::from_str(&content).map_err(|e| KeystoreError::Corrupt(e.to_string()))
+    }
+
+    fn key_path(&self, id: &str) -> Result<PathBuf, KeystoreError> {
+        let valid = !id.is_empty()
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(KeystoreError::InvalidId(id.to_string()));
+        }
+
+        Ok(self.dir.join(format!("{}.json", id)))
+    }
+}
+
+fn cipher(passphrase: &str, salt: &[u8], kdf: KdfParams) -> Result<Aes256Gcm, KeystoreError> {
+    let params = Params::new(kdf.m_cost, kdf.t_cost, kdf.p_cost, Some(32))
+        .map_err(|e| KeystoreError::Crypto(e.to_string()))?;
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut_slice())
+        .map_err(|e| KeystoreError::Crypto(e.to_string()))?;
+
+    Aes256Gcm::new_from_slice(key.as_slice()).map_err(|e| KeystoreError::Crypto(e.to_string()))
+}
+
+/// Signer whose key ids are keystore ids, decrypted on demand with one
+/// passphrase for the whole keystore
+pub struct KeystoreSigner {
+    keystore: Keystore,
+    passphrase: Zeroizing<String>,
+    local: LocalSigner,
+    cache: Option<Arc<KeyCache>>,
+}
+
+impl KeystoreSigner {
+    pub fn new(keystore: Keystore, passphrase: String) -> Self {
+        Self {
+            keystore,
+            passphrase: Zeroizing::new(passphrase),
+            local: LocalSigner::new(),
+            cache: None,
+        }
+    }
+
+    /// Keep decrypted keys in `cache` instead of decrypting on every signature
+    pub fn with_cache(mut self, cache: Arc<KeyCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Decrypted hex private key for `key_id`, from the cache when possible
+    pub fn unlock(&self, key_id: &str) -> Result<Zeroizing<String>, SignerError> {
+        if let Some(privkey) = self.cache.as_ref().and_then(|cache| cache.get(key_id)) {
+            return Ok(privkey);
+        }
+
+        let privkey = self
+            .keystore
+            .unlock(key_id, &self.passphrase)
+            .map_err(|e| SignerError::KeyUnavailable(e.to_string()))?;
+
+        if let Some(cache) = &self.cache {
+            cache
+                .insert(key_id, &privkey)
+                .map_err(|e| SignerError::KeyUnavailable(e.to_string()))?;
+        }
+
+        Ok(privkey)
+    }
+}
+
+#[async_trait::async_trait]
+impl Signer for KeystoreSigner {
+    async fn sign_sighash(&self, key_id: &str, sighash: &[u8; 32]) -> Result<Vec<u8>, SignerError> {
+        let privkey = self.unlock(key_id)?;
+
+        self.local.sign_sighash(&privkey, sighash).await
+    }
+
+    async fn derive_pubkey(&self, key_id: &str) -> Result<String, SignerError> {
+        self.keystore
+            .pubkey(key_id)
+            .map_err(|e| SignerError::KeyUnavailable(e.to_string()))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum KeystoreError {
+    #[error("Key not found: {0}")]
+    NotFound(String),
+
+    #[error("Key already exists: {0}")]
+    AlreadyExists(String),
+
+    #[error("Invalid key id: {0}")]
+    InvalidId(String),
+
+    #[error("Invalid private key")]
+    InvalidKey,
+
+    #[error("Wrong passphrase")]
+    WrongPassphrase,
+
+    #[error("Corrupt keystore entry: {0}")]
+    Corrupt(String),
+
+    #[error("Crypto error: {0}")]
+    Crypto(String),
+
+    #[error("IO error: {0}")]
+    Io(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAST_KDF: KdfParams = KdfParams {
+        m_cost: 64,
+        t_cost: 1,
+        p_cost: 1,
+    };
+
+    fn temp_keystore() -> Keystore {
+        let dir = std::env::temp_dir().join(format!("zcash-keystore-{}", uuid::Uuid::new_v4()));
+        Keystore::open(dir).unwrap().with_kdf_params(FAST_KDF)
+    }
+
+    #[test]
+    fn test_keystore_roundtrip() {
+        let keystore = temp_keystore();
+        let privkey = "0101010101010101010101010101010101010101010101010101010101010101";
+
+        let pubkey = keystore.import("refund-1", privkey, "hunter2").unwrap();
+        assert_eq!(keystore.pubkey("refund-1").unwrap(), pubkey);
+        assert_eq!(
+            keystore.unlock("refund-1", "hunter2").unwrap().as_str(),
+            privkey
+        );
+        assert_eq!(keystore.list().unwrap(), vec!["refund-1".to_string()]);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(keystore.key_path("refund-1").unwrap())
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        assert!(matches!(
+            keystore.unlock("refund-1", "wrong"),
+            Err(KeystoreError::WrongPassphrase)
+        ));
+        assert!(matches!(
+            keystore.import("refund-1", privkey, "hunter2"),
+            Err(KeystoreError::AlreadyExists(_))
+        ));
+        assert!(matches!(
+            keystore.pubkey("../etc/passwd"),
+            Err(KeystoreError::InvalidId(_))
+        ));
+
+        fs::remove_dir_all(&keystore.dir).unwrap();
+    }
+}
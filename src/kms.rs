@@ -0,0 +1,227 @@
+use aws_sdk_kms::error::SdkError;
+use aws_sdk_kms::operation::get_public_key::GetPublicKeyError;
+use aws_sdk_kms::operation::sign::SignError;
+use aws_sdk_kms::primitives::Blob;
+use aws_sdk_kms::types::{MessageType, SigningAlgorithmSpec};
+use aws_sdk_kms::Client;
+use secp256k1::{ecdsa::Signature, PublicKey};
+
+use crate::signer::{Signer, SignerError};
+
+/// DER contents of the `id-ecPublicKey` algorithm OID, 1.2.840.10045.2.1
+const EC_PUBLIC_KEY_OID: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+/// DER contents of the `secp256k1` curve OID, 1.3.132.0.10
+const SECP256K1_OID: &[u8] = &[0x2b, 0x81, 0x04, 0x00, 0x0a];
+
+const DER_SEQUENCE: u8 = 0x30;
+const DER_OID: u8 = 0x06;
+const DER_BIT_STRING: u8 = 0x03;
+
+/// Signer backed by AWS KMS asymmetric keys with key spec `ECC_SECG_P256K1`.
+/// Key ids are anything KMS accepts as a `KeyId`: a key id, key ARN, alias
+/// name or alias ARN. Private keys never leave KMS.
+pub struct KmsSigner {
+    client: Client,
+}
+
+impl KmsSigner {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+
+    /// Build a client from the standard AWS environment (env vars, profile,
+    /// instance role, ...)
+    pub async fn from_env() -> Self {
+        let config = aws_config::load_from_env().await;
+        Self::new(Client::new(&config))
+    }
+}
+
+#[async_trait::async_trait]
+impl Signer for KmsSigner {
+    async fn sign_sighash(&self, key_id: &str, sighash: &[u8; 32]) -> Result<Vec<u8>, SignerError> {
+        let output = self
+            .client
+            .sign()
+            .key_id(key_id)
+            .message(Blob::new(sighash.to_vec()))
+            .message_type(MessageType::Digest)
+            .signing_algorithm(SigningAlgorithmSpec::EcdsaSha256)
+            .send()
+            .await
+            .map_err(sign_error)?;
+
+        let der = output
+            .signature()
+            .ok_or(SignerError::InvalidSignature)?
+            .as_ref();
+
+        normalize_signature(der)
+    }
+
+    async fn derive_pubkey(&self, key_id: &str) -> Result<String, SignerError> {
+        let output = self
+            .client
+            .get_public_key()
+            .key_id(key_id)
+            .send()
+            .await
+            .map_err(get_public_key_error)?;
+
+        let spki = output
+            .public_key()
+            .ok_or(SignerError::InvalidPublicKey)?
+            .as_ref();
+
+        pubkey_from_spki(spki)
+    }
+}
+
+/// KMS does not enforce low-S, which Zcash relay policy requires
+fn normalize_signature(der: &[u8]) -> Result<Vec<u8>, SignerError> {
+    let mut signature = Signature::from_der(der).map_err(|_| SignerError::InvalidSignature)?;
+    signature.normalize_s();
+
+    Ok(signature.serialize_der().to_vec())
+}
+
+/// Compressed hex pubkey from a DER SubjectPublicKeyInfo, which must be
+/// that of a secp256k1 EC key
+fn pubkey_from_spki(spki: &[u8]) -> Result<String, SignerError> {
+    let point = spki_point(spki).ok_or(SignerError::InvalidPublicKey)?;
+    let pubkey = PublicKey::from_slice(point).map_err(|_| SignerError::InvalidPublicKey)?;
+
+    Ok(hex::encode(pubkey.serialize()))
+}
+
+/// SEC1 point of `SEQUENCE { SEQUENCE { id-ecPublicKey, secp256k1 },
+/// BIT STRING }`, with nothing trailing
+fn spki_point(spki: &[u8]) -> Option<&[u8]> {
+    let (info, rest) = der_element(spki, DER_SEQUENCE)?;
+    if !rest.is_empty() {
+        return None;
+    }
+
+    let (algorithm, info) = der_element(info, DER_SEQUENCE)?;
+    let (key_type, algorithm) = der_element(algorithm, DER_OID)?;
+    let (curve, algorithm) = der_element(algorithm, DER_OID)?;
+    if key_type != EC_PUBLIC_KEY_OID || curve != SECP256K1_OID || !algorithm.is_empty() {
+        return None;
+    }
+
+    let (bits, info) = der_element(info, DER_BIT_STRING)?;
+    match bits.split_first() {
+        // No unused bits in the last byte
+        Some((0, point)) if info.is_empty() => Some(point),
+        _ => None,
+    }
+}
+
+/// Contents of the DER element with `tag` at the start of `input`, and the
+/// bytes after it
+fn der_element(input: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    let (&found, input) = input.split_first()?;
+    if found != tag {
+        return None;
+    }
+
+    let (&first, mut input) = input.split_first()?;
+    let len = if first < 0x80 {
+        first as usize
+    } else {
+        // Long form: the low bits count the length bytes that follow
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 2 || input.len() < count {
+            return None;
+        }
+        let (bytes, rest) = input.split_at(count);
+        input = rest;
+        bytes
+            .iter()
+            .fold(0, |len, &byte| (len << 8) | byte as usize)
+    };
+
+    (input.len() >= len).then(|| input.split_at(len))
+}
+
+fn sign_error(err: SdkError<SignError>) -> SignerError {
+    match err.into_service_error() {
+        e @ (SignError::NotFoundException(_)
+        | SignError::DisabledException(_)
+        | SignError::KmsInvalidStateException(_)
+        | SignError::InvalidKeyUsageException(_)) => SignerError::KeyUnavailable(e.to_string()),
+        e => SignerError::RemoteError(e.to_string()),
+    }
+}
+
+fn get_public_key_error(err: SdkError<GetPublicKeyError>) -> SignerError {
+    match err.into_service_error() {
+        e @ (GetPublicKeyError::NotFoundException(_)
+        | GetPublicKeyError::DisabledException(_)
+        | GetPublicKeyError::KmsInvalidStateException(_)
+        | GetPublicKeyError::InvalidArnException(_)) => SignerError::KeyUnavailable(e.to_string()),
+        e => SignerError::RemoteError(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1::{Message, Secp256k1, SecretKey};
+
+    /// SPKI header for an uncompressed secp256k1 key
+    const SPKI_PREFIX: &str = "3056301006072a8648ce3d020106052b8104000a034200";
+
+    #[test]
+    fn test_kms_pubkey_and_high_s_normalization() {
+        let secp = Secp256k1::new();
+        let key = SecretKey::from_slice(&[5u8; 32]).unwrap();
+        let pubkey = PublicKey::from_secret_key(&secp, &key);
+
+        let mut spki = hex::decode(SPKI_PREFIX).unwrap();
+        spki.extend(pubkey.serialize_uncompressed());
+        assert_eq!(
+            pubkey_from_spki(&spki).unwrap(),
+            hex::encode(pubkey.serialize())
+        );
+
+        let message = Message::from_digest_slice(&[2u8; 32]).unwrap();
+        let low_s = secp.sign_ecdsa(&message, &key);
+
+        // Flip s to its high-S twin, as KMS may return either
+        let mut compact = low_s.serialize_compact();
+        let order = hex::decode("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141")
+            .unwrap();
+        let mut borrow = 0i16;
+        for i in (0..32).rev() {
+            let diff = order[i] as i16 - compact[32 + i] as i16 - borrow;
+            compact[32 + i] = diff.rem_euclid(256) as u8;
+            borrow = (diff < 0) as i16;
+        }
+        let high_s = Signature::from_compact(&compact).unwrap();
+        assert_ne!(high_s, low_s);
+
+        let normalized = normalize_signature(&high_s.serialize_der()).unwrap();
+        assert_eq!(normalized, low_s.serialize_der().to_vec());
+    }
+
+    #[test]
+    fn test_spki_must_be_a_secp256k1_key() {
+        let secp = Secp256k1::new();
+        let key = SecretKey::from_slice(&[5u8; 32]).unwrap();
+        let point = PublicKey::from_secret_key(&secp, &key).serialize_uncompressed();
+
+        // The same point under the P-256 curve OID
+        let mut p256 = hex::decode("3059301306072a8648ce3d020106082a8648ce3d030107034200").unwrap();
+        p256.extend(point);
+        assert!(pubkey_from_spki(&p256).is_err());
+
+        let mut spki = hex::decode(SPKI_PREFIX).unwrap();
+        spki.extend(point);
+        let mut trailing = spki.clone();
+        trailing.push(0);
+        assert!(pubkey_from_spki(&trailing).is_err());
+        assert!(pubkey_from_spki(&spki[..spki.len() - 1]).is_err());
+        assert!(pubkey_from_spki(&point).is_err());
+    }
+}
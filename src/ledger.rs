@@ -0,0 +1,246 @@
+use bitcoin::util::bip32::DerivationPath;
+use ledger_transport::{APDUCommand, Exchange};
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::signer::{Signer, SignerError};
+
+/// APDU class of the Zcash transparent app
+pub const CLA: u8 = 0x85;
+
+const INS_GET_ADDR_SECP256K1: u8 = 0x01;
+const INS_SIGN_SECP256K1: u8 = 0x02;
+
+const P1_INIT: u8 = 0x00;
+const P1_LAST: u8 = 0x02;
+
+const SW_OK: u16 = 0x9000;
+const SW_USER_REJECTED: u16 = 0x6986;
+
+/// Length of the `r || s || v` prefix the device returns before the DER signature
+const RSV_LEN: usize = 65;
+const PUBKEY_LEN: usize = 33;
+const PATH_DEPTH: usize = 5;
+
+type ConfirmPrompt = Box<dyn Fn(&str, &[u8; 32]) + Send + Sync>;
+
+/// Signer backed by a Ledger device running the Zcash transparent app.
+/// Key ids are BIP32 paths such as `m/44'/133'/0'/0/0`, so private keys
+/// never leave the device. Every signature must be approved on the device.
+pub struct LedgerSigner<E> {
+    transport: E,
+    confirm_prompt: Option<ConfirmPrompt>,
+    /// Signing takes two APDUs; concurrent batch signing must not interleave them
+    session: tokio::sync::Mutex<()>,
+}
+
+impl<E> LedgerSigner<E>
+where
+    E: Exchange + Send + Sync,
+    E::Error: Display,
+{
+    pub fn new(transport: E) -> Self {
+        Self {
+            transport,
+            confirm_prompt: None,
+            session: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Called with the derivation path and sighash right before a signing
+    /// request is sent, so the caller can tell the operator what to approve
+    pub fn with_confirm_prompt<F>(mut self, prompt: F) -> Self
+    where
+        F: Fn(&str, &[u8; 32]) + Send + Sync + 'static,
+    {
+        self.confirm_prompt = Some(Box::new(prompt));
+        self
+    }
+
+    async fn send(&self, ins: u8, p1: u8, data: Vec<u8>) -> Result<Vec<u8>, SignerError> {
+        let command = APDUCommand {
+            cla: CLA,
+            ins,
+            p1,
+            p2: 0x00,
+            data,
+        };
+
+        let answer = self
+            .transport
+            .exchange(&command)
+            .await
+            .map_err(|e| SignerError::DeviceError(e.to_string()))?;
+
+        match answer.retcode() {
+            SW_OK => Ok(answer.data().to_vec()),
+            SW_USER_REJECTED => Err(SignerError::Rejected),
+            code => Err(SignerError::DeviceError(format!(
+                "device returned status 0x{:04x}",
+                code
+            ))),
+        }
+    }
+}
+
+#[cfg(feature = "ledger-hid")]
+impl LedgerSigner<ledger_transport_hid::TransportNativeHID> {
+    /// Connect to the first Ledger found over USB HID
+    pub fn connect_hid() -> Result<Self, SignerError> {
+        let api = hidapi::HidApi::new().map_err(|e| SignerError::DeviceError(e.to_string()))?;
+        let transport = ledger_transport_hid::TransportNativeHID::new(&api)
+            .map_err(|e| SignerError::DeviceError(e.to_string()))?;
+
+        Ok(Self::new(transport))
+    }
+}
+
+#[async_trait::async_trait]
+impl<E> Signer for LedgerSigner<E>
+where
+    E: Exchange + Send + Sync,
+    E::Error: Display,
+{
+    async fn sign_sighash(&self, key_id: &str, sighash: &[u8; 32]) -> Result<Vec<u8>, SignerError> {
+        let path = serialize_path(key_id)?;
+        let _session = self.session.lock().await;
+
+        if let Some(prompt) = &self.confirm_prompt {
+            prompt(key_id, sighash);
+        }
+
+        self.send(INS_SIGN_SECP256K1, P1_INIT, path).await?;
+        let response = self
+            .send(INS_SIGN_SECP256K1, P1_LAST, sighash.to_vec())
+            .await?;
+
+        if response.len() <= RSV_LEN {
+            return Err(SignerError::InvalidSignature);
+        }
+
+        Ok(response[RSV_LEN..].to_vec())
+    }
+
+    async fn derive_pubkey(&self, key_id: &str) -> Result<String, SignerError> {
+        let path = serialize_path(key_id)?;
+        let _session = self.session.lock().await;
+        let response = self.send(INS_GET_ADDR_SECP256K1, P1_INIT, path).await?;
+
+        if response.len() < PUBKEY_LEN {
+            return Err(SignerError::InvalidPublicKey);
+        }
+
+        Ok(hex::encode(&response[..PUBKEY_LEN]))
+    }
+}
+
+/// Encode a BIP44 path as the five little-endian u32 components the app expects
+fn serialize_path(key_id: &str) -> Result<Vec<u8>, SignerError> {
+    let path = DerivationPath::from_str(key_id)
+        .map_err(|e| SignerError::DeviceError(format!("invalid derivation path: {}", e)))?;
+
+    if path.len() != PATH_DEPTH {
+        return Err(SignerError::DeviceError(format!(
+            "derivation path must have {} components, got {}",
+            PATH_DEPTH,
+            path.len()
+        )));
+    }
+
+    Ok(path
+        .into_iter()
+        .flat_map(|child| u32::from(*child).to_le_bytes())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ledger_transport::APDUAnswer;
+    use secp256k1::{Message, Secp256k1, SecretKey};
+    use std::ops::Deref;
+    use std::sync::Mutex;
+
+    /// Emulates the device with a fixed key, recording every APDU it receives
+    struct MockDevice {
+        key: SecretKey,
+        reject: bool,
+        received: Mutex<Vec<(u8, u8, Vec<u8>)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Exchange for MockDevice {
+        type Error = String;
+        type AnswerType = Vec<u8>;
+
+        async fn exchange<I>(&self, command: &APDUCommand<I>) -> Result<APDUAnswer<Vec<u8>>, String>
+        where
+            I: Deref<Target = [u8]> + Send + Sync,
+        {
+            self.received
+                .lock()
+                .unwrap()
+                .push((command.ins, command.p1, command.data.to_vec()));
+
+            let secp = Secp256k1::new();
+            let mut answer = match (command.ins, command.p1) {
+                (INS_GET_ADDR_SECP256K1, _) => self.key.public_key(&secp).serialize().to_vec(),
+                (INS_SIGN_SECP256K1, P1_LAST) if self.reject => {
+                    return Ok(APDUAnswer::from_answer(vec![0x69, 0x86]).unwrap())
+                }
+                (INS_SIGN_SECP256K1, P1_LAST) => {
+                    let message = Message::from_digest_slice(&command.data).unwrap();
+                    let mut out = vec![0u8; RSV_LEN];
+                    out.extend(
+                        secp.sign_ecdsa(&message, &self.key)
+                            .serialize_der()
+                            .to_vec(),
+                    );
+                    out
+                }
+                _ => Vec::new(),
+            };
+            answer.extend([0x90, 0x00]);
+
+            Ok(APDUAnswer::from_answer(answer).unwrap())
+        }
+    }
+
+    fn device(reject: bool) -> MockDevice {
+        MockDevice {
+            key: SecretKey::from_slice(&[7u8; 32]).unwrap(),
+            reject,
+            received: Mutex::new(Vec::new()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ledger_sign_sighash() {
+        let signer = LedgerSigner::new(device(false));
+        let path = "m/44'/133'/0'/0/3";
+
+        let sig = signer.sign_sighash(path, &[1u8; 32]).await.unwrap();
+        let pubkey = signer.derive_pubkey(path).await.unwrap();
+
+        let secp = Secp256k1::new();
+        let pubkey = secp256k1::PublicKey::from_slice(&hex::decode(pubkey).unwrap()).unwrap();
+        let sig = secp256k1::ecdsa::Signature::from_der(&sig).unwrap();
+        let message = Message::from_digest_slice(&[1u8; 32]).unwrap();
+        assert!(secp.verify_ecdsa(&message, &sig, &pubkey).is_ok());
+
+        let received = signer.transport.received.lock().unwrap();
+        assert_eq!(received[0].1, P1_INIT);
+        assert_eq!(received[0].2.len(), PATH_DEPTH * 4);
+        assert_eq!(&received[0].2[..4], &(0x8000_0000u32 | 44).to_le_bytes());
+        assert_eq!(received[1].2, vec![1u8; 32]);
+    }
+
+    #[tokio::test]
+    async fn test_ledger_user_rejection() {
+        let signer = LedgerSigner::new(device(true));
+
+        let result = signer.sign_sighash("m/44'/133'/0'/0/0", &[1u8; 32]).await;
+        assert!(matches!(result, Err(SignerError::Rejected)));
+        assert!(signer.sign_sighash("m/44'/133'", &[1u8; 32]).await.is_err());
+    }
+}
@@ -1,37 +1,113 @@
+#[cfg(feature = "api")]
+pub mod api;
 pub mod builder;
 pub mod config;
 pub mod database;
+pub mod explorer;
+pub mod hd;
+pub mod key_cache;
+pub mod keystore;
+#[cfg(feature = "aws-kms")]
+pub mod kms;
+#[cfg(feature = "ledger")]
+pub mod ledger;
 pub mod models;
+pub mod outbox;
+pub mod policy;
+pub mod remote_signer;
 pub mod rpc;
+pub mod scanner;
 pub mod script;
+pub mod sighash;
 pub mod signer;
+pub mod telemetry;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_signer;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+pub mod threshold;
+pub mod transaction;
+#[cfg(feature = "zmq")]
+pub mod zmq;
 
 use chrono::Utc;
-use std::sync::Arc;
-use tracing::info;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
 use uuid::Uuid;
+use zeroize::Zeroizing;
 
 pub use builder::{TransactionBuilder, TxBuilderError};
 pub use config::{ConfigError, ZcashConfig};
+pub use explorer::{
+    BlockchairExplorer, Explorer, FallbackExplorer, InsightExplorer, Pagination,
+    RateLimitedExplorer, RateLimiter,
+};
+pub use key_cache::{KeyCache, KeyCacheError};
+pub use keystore::{Keystore, KeystoreError, KeystoreSigner};
+#[cfg(feature = "aws-kms")]
+pub use kms::KmsSigner;
+#[cfg(feature = "ledger")]
+pub use ledger::LedgerSigner;
 pub use models::*;
-pub use rpc::{RpcClientError, ZcashRpcClient};
+pub use outbox::{
+    webhook_signature, EventSink, OutboxDispatcher, OutboxError, WebhookSink, SIGNATURE_HEADER,
+};
+pub use policy::{AddressAllowlist, SigningPolicy, SigningRequest};
+pub use remote_signer::RemoteSigner;
+pub use rpc::{ConfirmationWait, RpcClientError, ZcashRpcClient};
+pub use scanner::WatchSet;
 pub use script::{HTLCScriptBuilder, HTLCScriptError};
-pub use signer::{SignerError, TransactionSigner};
-
-use crate::database::{Database, DatabaseError};
+pub use signer::{
+    canonicalize_signature, malleated_signatures, CanonicalSignature, KeyMap, LocalSigner,
+    MalleabilityWarning, MalleatedSignature, Signer, SignerError, TransactionSigner,
+};
+#[cfg(any(test, feature = "test-utils"))]
+pub use test_signer::TestSigner;
+pub use threshold::{Cosigner, ThresholdCoordinator};
+pub use tokio_util::sync::CancellationToken;
+pub use transaction::ZcashTransaction;
+#[cfg(feature = "zmq")]
+pub use zmq::ZmqSubscriber;
+
+use crate::database::{Database, DatabaseError, Storage};
+
+/// Block hashes kept to detect reorgs; deeper ones are reported at the
+/// oldest kept height
+const REORG_WINDOW: u64 = 100;
+/// Most blocks one `scan_for_htlcs` call walks
+const SCAN_BATCH_BLOCKS: u64 = 500;
+/// Blocks past the next one that built transactions stay valid for, unless
+/// configured
+const DEFAULT_TX_EXPIRY_DELTA: u32 = 40;
 
 pub struct ZcashHTLCClient {
     config: ZcashConfig,
-    database: Arc<Database>,
+    storage: Arc<dyn Storage>,
     rpc_client: ZcashRpcClient,
     tx_builder: TransactionBuilder,
+    fee_mode: RwLock<FeeMode>,
+    fee_priorities: RwLock<FeePriorityConfig>,
     signer: TransactionSigner,
     script_builder: HTLCScriptBuilder,
+    block_events: Option<broadcast::Sender<NodeEvent>>,
 }
 
 impl ZcashHTLCClient {
-    /// Create new client from configuration
-    pub fn new(config: ZcashConfig, database: Arc<Database>) -> Self {
+    /// Create new client from configuration, failing with a
+    /// [`ConfigError`] on unreadable or invalid `rpc_tls` certificates or an
+    /// invalid `rpc_proxy`
+    pub fn new(config: ZcashConfig, database: Arc<Database>) -> Result<Self, HTLCClientError> {
+        Self::try_with_storage(config, database)
+    }
+
+    /// Create a client persisting HTLCs to `storage` instead of Postgres,
+    /// e.g. a [`MemoryStorage`](crate::database::MemoryStorage) in tests
+    pub fn try_with_storage(
+        config: ZcashConfig,
+        storage: Arc<dyn Storage>,
+    ) -> Result<Self, HTLCClientError> {
         let rpc_client = ZcashRpcClient::new(
             config.rpc_url.clone(),
             config.rpc_user.clone(),
@@ -39,64 +115,245 @@ impl ZcashHTLCClient {
             config.network,
         );
 
-        let rpc_client = if let Some(explorer) = &config.explorer_api {
-            rpc_client.with_custom_explorer(explorer.clone())
+        let rpc_client = match (&config.explorer, &config.explorer_api) {
+            (Some(explorer), _) => rpc_client.with_explorer(explorer.clone()),
+            (None, Some(url)) => rpc_client.with_custom_explorer(url.clone()),
+            (None, None) => rpc_client,
+        };
+        let rpc_client = match &config.explorer_fallbacks {
+            Some(explorers) => rpc_client.with_explorer_fallbacks(explorers.clone()),
+            None => rpc_client,
+        };
+        let rpc_client = rpc_client.with_dual_broadcast(config.dual_broadcast.unwrap_or(false));
+        let rpc_client = if let Some(urls) = &config.rpc_fallback_urls {
+            rpc_client.with_fallback_urls(urls.clone())
         } else {
             rpc_client
         };
+        let rpc_client = if let Some(path) = &config.rpc_cookie_file {
+            rpc_client.with_cookie_file(path)
+        } else {
+            rpc_client
+        };
+        let rpc_client = if let Some(retry) = &config.rpc_retry {
+            rpc_client.with_retry_policy(retry.clone())
+        } else {
+            rpc_client
+        };
+        let rpc_client = rpc_client.with_timeouts(
+            config
+                .rpc_request_timeout_secs
+                .map_or(rpc::DEFAULT_REQUEST_TIMEOUT, Duration::from_secs),
+            config
+                .rpc_call_timeout_secs
+                .map_or(rpc::DEFAULT_CALL_TIMEOUT, Duration::from_secs),
+        );
+        let rpc_client = match config.rpc_tx_cache_size {
+            Some(capacity) => rpc_client.with_tx_cache(capacity),
+            None => rpc_client,
+        };
+        let rpc_client = match &config.rpc_http_pool {
+            Some(pool) => rpc_client.with_http_pool(pool),
+            None => rpc_client,
+        };
+        let rpc_client = match &config.rpc_tls {
+            Some(tls) => rpc_client
+                .with_tls(tls)
+                .map_err(|e| ConfigError::InvalidConfig(format!("rpc_tls: {}", e)))?,
+            None => rpc_client,
+        };
+        let rpc_client = match &config.rpc_proxy {
+            Some(proxy) => rpc_client
+                .with_proxy(proxy)
+                .map_err(|e| ConfigError::InvalidConfig(format!("rpc_proxy: {}", e)))?,
+            None => rpc_client,
+        };
 
         let tx_builder = TransactionBuilder::new(config.network);
+        let fee_mode = config.fee_mode.unwrap_or_default();
         let script_builder = HTLCScriptBuilder::new(config.network);
         let signer = TransactionSigner::new(script_builder.clone());
+        let signer = if let Some(branch_id) = config.consensus_branch_id {
+            signer.with_branch_id(branch_id)
+        } else {
+            signer
+        };
+        let signer = if let Some(remote) = &config.remote_signer {
+            signer.with_backend(Arc::new(RemoteSigner::from_config(remote)?))
+        } else {
+            signer
+        };
 
-        Self {
+        Ok(Self {
             config,
-            database,
+            storage,
             rpc_client,
             tx_builder,
+            fee_mode: RwLock::new(fee_mode),
+            fee_priorities: RwLock::new(FeePriorityConfig::default()),
             signer,
             script_builder: script_builder.clone(),
-        }
+            block_events: None,
+        })
+    }
+
+    /// Sign with a custom key custody backend (HSM, remote signer, test
+    /// double) instead of in-process hex private keys. Key arguments of the
+    /// HTLC operations are then interpreted as that backend's key ids.
+    pub fn with_signer(mut self, backend: Arc<dyn Signer>) -> Self {
+        self.signer = self.signer.with_backend(backend);
+        self
+    }
+
+    /// Consult `policy` before every transaction signature, e.g. an
+    /// [`AddressAllowlist`] of the operator's known destinations
+    pub fn with_signing_policy(mut self, policy: Arc<dyn SigningPolicy>) -> Self {
+        self.signer = self.signer.with_policy(policy);
+        self
+    }
+
+    /// Sign HTLC inputs with a sighash type other than `SIGHASH_ALL`
+    pub fn with_sighash_type(mut self, sighash_type: bitcoin::EcdsaSighashType) -> Self {
+        self.signer = self.signer.with_sighash_type(sighash_type);
+        self
+    }
+
+    /// Sign with keys from an encrypted keystore, passing keystore ids
+    /// instead of hex private keys to the HTLC operations
+    pub fn with_keystore(self, keystore: Keystore, passphrase: String) -> Self {
+        self.with_signer(Arc::new(KeystoreSigner::new(keystore, passphrase)))
+    }
+
+    /// Track confirmations from node notifications, e.g. a
+    /// [`ZmqSubscriber`](crate::zmq::ZmqSubscriber), instead of polling alone.
+    /// Detected reorgs are published on the same channel.
+    pub fn with_block_notifications(mut self, events: broadcast::Sender<NodeEvent>) -> Self {
+        self.rpc_client = self.rpc_client.with_block_notifications(events.clone());
+        self.block_events = Some(events);
+        self
+    }
+
+    /// Price the transactions of HTLCs of each [`FeePriority`] at its rate
+    /// in `rates`, overriding the fee mode for priorities that have one
+    pub fn with_fee_priorities(self, rates: FeePriorityConfig) -> Self {
+        self.set_fee_priorities(rates);
+        self
+    }
+
+    /// Replace the rates of [`with_fee_priorities`](Self::with_fee_priorities)
+    /// for transactions built from now on, e.g. after a config reload
+    pub fn set_fee_priorities(&self, rates: FeePriorityConfig) {
+        *self.fee_priorities.write().unwrap() = rates;
+    }
+
+    /// Price transactions built from now on by `mode` instead of the
+    /// configured `fee_mode`, e.g. after a config reload
+    pub fn set_fee_mode(&self, mode: FeeMode) {
+        *self.fee_mode.write().unwrap() = mode;
+    }
+
+    /// Rate configured for transactions of HTLCs of `priority`, if any
+    pub fn fee_priority_rate(&self, priority: FeePriority) -> Option<u64> {
+        self.fee_priorities.read().unwrap().rate(priority)
+    }
+
+    /// Act for `tenant`: every HTLC, operation and relayer UTXO this client
+    /// reads or writes belongs to it, isolated from other tenants sharing
+    /// the database
+    pub fn with_tenant(mut self, tenant: TenantContext) -> Self {
+        self.storage = self.storage.with_tenant(tenant);
+        self
     }
 
     // ==================== HTLC Operations ====================
 
-    /// Create a new HTLC
+    /// Create a new HTLC, signing each funding UTXO with the key
+    /// `funding_keys` holds for its script pubkey or address
     pub async fn create_htlc(
         &self,
         params: HTLCParams,
         funding_utxos: Vec<UTXO>,
         change_address: &str,
-        funding_privkeys: Vec<&str>,
+        funding_keys: &KeyMap,
     ) -> Result<HTLCCreationResult, HTLCClientError> {
         info!("🔨 Creating HTLC for {} ZEC", params.amount);
 
         // Build HTLC transaction
-        let (tx, redeem_script) =
-            self.tx_builder
-                .build_htlc_tx(&params, funding_utxos.clone(), change_address)?;
+        let (tx, redeem_script) = self
+            .priced_tx_builder(params.priority)
+            .await?
+            .build_htlc_tx(&params, funding_utxos.clone(), change_address)?;
+        self.check_address(change_address).await?;
 
         // Generate P2SH address
         let p2sh_address = self.script_builder.script_to_p2sh_address(&redeem_script)?;
         info!("📍 P2SH address: {}", p2sh_address);
 
         // Build script pubkeys for signing
-        let input_scripts: Vec<_> = funding_utxos
-            .iter()
-            .map(|utxo| {
-                hex::decode(&utxo.script_pubkey)
-                    .map(bitcoin::blockdata::script::Script::from)
-                    .map_err(|_| HTLCClientError::InvalidScript)
-            })
-            .collect::<Result<Vec<_>, _>>()?;
+        let (input_scripts, input_amounts) = prevout_scripts_and_amounts(&funding_utxos)?;
 
         // Sign transaction
         let signed_tx = self
             .signer
-            .sign_htlc_creation(tx, input_scripts, funding_privkeys)?;
+            .sign_htlc_creation(tx, input_scripts, input_amounts, funding_keys)
+            .await?;
 
         let tx_hex = self.tx_builder.serialize_tx(&signed_tx);
-        let htlc_id = Uuid::new_v4().to_string();
+        let report = self.tx_builder.tx_report(&signed_tx, &funding_utxos)?;
+
+        self.store_and_broadcast_creation(&params, &redeem_script, p2sh_address, tx_hex, report)
+            .await
+    }
+
+    /// Create an HTLC funded from an m-of-n P2SH wallet whose redeem script
+    /// is `wallet_script`, gathering each funding signature from the
+    /// coordinator's cosigners before broadcast
+    pub async fn create_htlc_with_cosigners(
+        &self,
+        params: HTLCParams,
+        funding_utxos: Vec<UTXO>,
+        change_address: &str,
+        wallet_script: &bitcoin::blockdata::script::Script,
+        coordinator: &ThresholdCoordinator,
+    ) -> Result<HTLCCreationResult, HTLCClientError> {
+        info!(
+            "🔨 Creating HTLC for {} ZEC from a threshold wallet",
+            params.amount
+        );
+
+        let (tx, redeem_script) = self
+            .priced_tx_builder(params.priority)
+            .await?
+            .build_htlc_tx(&params, funding_utxos.clone(), change_address)?;
+        self.check_address(change_address).await?;
+
+        let p2sh_address = self.script_builder.script_to_p2sh_address(&redeem_script)?;
+        info!("📍 P2SH address: {}", p2sh_address);
+
+        let psbt = self
+            .tx_builder
+            .create_psbt(&tx, multisig_inputs(&funding_utxos, wallet_script))?;
+
+        let tx_hex = coordinator.sign_and_finalize(&self.signer, psbt).await?;
+        let signed_tx = self.tx_builder.deserialize_tx(&tx_hex)?;
+        let report = self.tx_builder.tx_report(&signed_tx, &funding_utxos)?;
+
+        self.store_and_broadcast_creation(&params, &redeem_script, p2sh_address, tx_hex, report)
+            .await
+    }
+
+    async fn store_and_broadcast_creation(
+        &self,
+        params: &HTLCParams,
+        redeem_script: &bitcoin::blockdata::script::Script,
+        p2sh_address: String,
+        tx_hex: String,
+        report: TxReport,
+    ) -> Result<HTLCCreationResult, HTLCClientError> {
+        let htlc_id = params
+            .htlc_id
+            .clone()
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
 
         // Create database record
         let htlc = ZcashHTLC {
@@ -106,6 +363,7 @@ impl ZcashHTLCClient {
             hash_lock: params.hash_lock.clone(),
             secret: None,
             timelock: params.timelock,
+            timelock_kind: TimelockKind::for_timelock(params.timelock),
             recipient_pubkey: params.recipient_pubkey.clone(),
             refund_pubkey: params.refund_pubkey.clone(),
             amount: params.amount.clone(),
@@ -114,13 +372,16 @@ impl ZcashHTLCClient {
             vout: None,
             script_hex: hex::encode(redeem_script.as_bytes()),
             redeem_script_hex: hex::encode(redeem_script.as_bytes()),
+            recipient_address: None,
             signed_redeem_tx: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            version: 0,
+            metadata: params.metadata.clone(),
+            deleted_at: None,
+            priority: params.priority,
         };
 
-        self.database.create_htlc(&htlc)?;
-
         // Create operation record
         let operation_id = Uuid::new_v4().to_string();
         let operation = HTLCOperation {
@@ -137,17 +398,30 @@ impl ZcashHTLCClient {
             error_message: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            attempt_count: 0,
+            last_attempt_at: None,
+            next_retry_at: None,
+            fee_zat: Some(report.fee),
+            tx_size: Some(report.size as u32),
+            fee_rate: Some(report.fee_rate),
+            broadcast_height: None,
+            bumps_operation_id: None,
+            error_kind: None,
         };
 
-        self.database.create_operation(&operation)?;
+        let operation_id = operation.id.clone();
+        let htlc_id = self
+            .storage
+            .create_htlc_with_operation(&htlc, &operation)
+            .await?;
 
         // Broadcast transaction
-        let txid = self.rpc_client.send_raw_transaction(&tx_hex).await?;
+        let txid = self.broadcast_raw_tx(&tx_hex).await?;
 
         // Update database
-        self.database.update_htlc_txid(&htlc_id, &txid, 0)?;
-        self.database
-            .update_operation_broadcast(&operation.id, &txid)?;
+        self.storage
+            .record_htlc_funding(&htlc_id, &operation_id, &txid, 0)
+            .await?;
 
         info!("✅ HTLC created with txid: {}", txid);
 
@@ -170,7 +444,10 @@ impl ZcashHTLCClient {
         info!("🔓 Redeeming HTLC: {}", htlc_id);
 
         // Load HTLC from database
-        let htlc = self.database.get_htlc_by_id(htlc_id)?;
+        let htlc = self.load_htlc(htlc_id).await?;
+        if htlc.state == HTLCState::Pending {
+            return Err(HTLCClientError::HTLCNotLocked);
+        }
 
         // Verify secret
         if !self.script_builder.verify_secret(secret, &htlc.hash_lock) {
@@ -186,21 +463,29 @@ impl ZcashHTLCClient {
         let redeem_script = bitcoin::blockdata::script::Script::from(redeem_script_bytes);
 
         // Build redeem transaction
-        let tx = self.tx_builder.build_redeem_tx(
-            &txid,
-            vout,
-            &htlc.amount,
-            secret,
-            &redeem_script,
-            recipient_address,
-        )?;
+        let tx = self
+            .priced_tx_builder(htlc.priority)
+            .await?
+            .build_redeem_tx(
+                &txid,
+                vout,
+                &htlc.amount,
+                secret,
+                &redeem_script,
+                recipient_address,
+            )?;
+        self.check_address(recipient_address).await?;
+
+        let amount = zec_to_zatoshis(&htlc.amount).ok_or(HTLCClientError::InvalidAmount)?;
 
         // Sign transaction
-        let signed_tx =
-            self.signer
-                .sign_htlc_redeem(tx, 0, &redeem_script, secret, recipient_privkey)?;
+        let signed_tx = self
+            .signer
+            .sign_htlc_redeem(tx, 0, &redeem_script, amount, secret, recipient_privkey)
+            .await?;
 
         let tx_hex = self.tx_builder.serialize_tx(&signed_tx);
+        let report = self.htlc_spend_report(&signed_tx, &htlc.amount, &redeem_script)?;
 
         // Create operation record
         let operation_id = Uuid::new_v4().to_string();
@@ -218,19 +503,32 @@ impl ZcashHTLCClient {
             error_message: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            attempt_count: 0,
+            last_attempt_at: None,
+            next_retry_at: None,
+            fee_zat: Some(report.fee),
+            tx_size: Some(report.size as u32),
+            fee_rate: Some(report.fee_rate),
+            broadcast_height: None,
+            bumps_operation_id: None,
+            error_kind: None,
         };
 
-        self.database.create_operation(&operation)?;
+        self.storage.create_operation(&operation).await?;
 
         // Broadcast transaction
-        let redeem_txid = self.rpc_client.send_raw_transaction(&tx_hex).await?;
+        let redeem_txid = self.broadcast_operation(&operation_id, &tx_hex).await?;
 
         // Update database
-        self.database
-            .update_htlc_state(htlc_id, HTLCState::Redeemed)?;
-        self.database.update_htlc_secret(htlc_id, secret)?;
-        self.database
-            .update_operation_broadcast(&operation_id, &redeem_txid)?;
+        self.storage
+            .record_htlc_settlement(
+                htlc_id,
+                HTLCState::Redeemed,
+                Some(secret),
+                &operation_id,
+                &redeem_txid,
+            )
+            .await?;
 
         info!("✅ HTLC redeemed with txid: {}", redeem_txid);
 
@@ -247,16 +545,22 @@ impl ZcashHTLCClient {
         info!("♻️ Refunding HTLC: {}", htlc_id);
 
         // Load HTLC from database
-        let htlc = self.database.get_htlc_by_id(htlc_id)?;
+        let htlc = self.load_htlc(htlc_id).await?;
+        if htlc.state == HTLCState::Pending {
+            return Err(HTLCClientError::HTLCNotLocked);
+        }
 
         let txid = htlc.txid.ok_or(HTLCClientError::HTLCNotLocked)?;
         let vout = htlc.vout.ok_or(HTLCClientError::HTLCNotLocked)?;
 
-        // Check timelock
-        let current_block = self.rpc_client.get_block_count().await?;
-        if current_block < htlc.timelock {
+        // Check timelock against the block height or the chain's clock
+        let current = match htlc.timelock_kind {
+            TimelockKind::BlockHeight => self.rpc_client.get_block_count().await?,
+            TimelockKind::Timestamp => self.get_median_time_past().await?,
+        };
+        if current < htlc.timelock {
             return Err(HTLCClientError::TimelockNotExpired {
-                current: current_block,
+                current,
                 required: htlc.timelock,
             });
         }
@@ -267,21 +571,29 @@ impl ZcashHTLCClient {
         let redeem_script = bitcoin::blockdata::script::Script::from(redeem_script_bytes);
 
         // Build refund transaction
-        let tx = self.tx_builder.build_refund_tx(
-            &txid,
-            vout,
-            &htlc.amount,
-            htlc.timelock,
-            &redeem_script,
-            refund_address,
-        )?;
+        let tx = self
+            .priced_tx_builder(htlc.priority)
+            .await?
+            .build_refund_tx(
+                &txid,
+                vout,
+                &htlc.amount,
+                htlc.timelock,
+                &redeem_script,
+                refund_address,
+            )?;
+        self.check_address(refund_address).await?;
+
+        let amount = zec_to_zatoshis(&htlc.amount).ok_or(HTLCClientError::InvalidAmount)?;
 
         // Sign transaction
         let signed_tx = self
             .signer
-            .sign_htlc_refund(tx, 0, &redeem_script, refund_privkey)?;
+            .sign_htlc_refund(tx, 0, &redeem_script, amount, refund_privkey)
+            .await?;
 
         let tx_hex = self.tx_builder.serialize_tx(&signed_tx);
+        let report = self.htlc_spend_report(&signed_tx, &htlc.amount, &redeem_script)?;
 
         // Create operation record
         let operation_id = Uuid::new_v4().to_string();
@@ -299,49 +611,1039 @@ impl ZcashHTLCClient {
             error_message: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            attempt_count: 0,
+            last_attempt_at: None,
+            next_retry_at: None,
+            fee_zat: Some(report.fee),
+            tx_size: Some(report.size as u32),
+            fee_rate: Some(report.fee_rate),
+            broadcast_height: None,
+            bumps_operation_id: None,
+            error_kind: None,
         };
 
-        self.database.create_operation(&operation)?;
+        self.storage.create_operation(&operation).await?;
 
         // Broadcast transaction
-        let refund_txid = self.rpc_client.send_raw_transaction(&tx_hex).await?;
+        let refund_txid = self.broadcast_operation(&operation_id, &tx_hex).await?;
 
         // Update database
-        self.database
-            .update_htlc_state(htlc_id, HTLCState::Refunded)?;
-        self.database
-            .update_operation_broadcast(&operation_id, &refund_txid)?;
+        self.storage
+            .record_htlc_settlement(
+                htlc_id,
+                HTLCState::Refunded,
+                None,
+                &operation_id,
+                &refund_txid,
+            )
+            .await?;
 
         info!("✅ HTLC refunded with txid: {}", refund_txid);
 
         Ok(refund_txid)
     }
 
+    /// Merge `utxos` into one output paying `address` at `fee_per_kb`
+    /// zatoshis per 1000 bytes, signing each input with the key `keys`
+    /// holds for it, and broadcast it. Returns the consolidation for
+    /// [`Database::commit_consolidation`](crate::database::Database::commit_consolidation).
+    pub async fn consolidate_utxos(
+        &self,
+        utxos: &[UTXO],
+        address: &str,
+        keys: &KeyMap,
+        fee_per_kb: u64,
+    ) -> Result<Consolidation, HTLCClientError> {
+        let tx = self
+            .next_block_tx_builder()
+            .await?
+            .with_fee_rate(fee_per_kb)
+            .build_consolidation_tx(utxos, address)?;
+        self.check_address(address).await?;
+
+        let (input_scripts, input_amounts) = prevout_scripts_and_amounts(utxos)?;
+        let signed_tx = self
+            .signer
+            .sign_htlc_creation(tx, input_scripts, input_amounts, keys)
+            .await?;
+
+        self.broadcast_consolidation(&signed_tx, utxos, address)
+            .await
+    }
+
+    /// [`Self::consolidate_utxos`] for UTXOs of an m-of-n P2SH wallet whose
+    /// redeem script is `wallet_script`, signed by the coordinator's
+    /// cosigners
+    pub async fn consolidate_utxos_with_cosigners(
+        &self,
+        utxos: &[UTXO],
+        address: &str,
+        wallet_script: &bitcoin::blockdata::script::Script,
+        coordinator: &ThresholdCoordinator,
+        fee_per_kb: u64,
+    ) -> Result<Consolidation, HTLCClientError> {
+        let tx = self
+            .next_block_tx_builder()
+            .await?
+            .with_fee_rate(fee_per_kb)
+            .build_consolidation_tx(utxos, address)?;
+        self.check_address(address).await?;
+
+        let psbt = self
+            .tx_builder
+            .create_psbt(&tx, multisig_inputs(utxos, wallet_script))?;
+        let tx_hex = coordinator.sign_and_finalize(&self.signer, psbt).await?;
+        let signed_tx = self.tx_builder.deserialize_tx(&tx_hex)?;
+
+        self.broadcast_consolidation(&signed_tx, utxos, address)
+            .await
+    }
+
+    /// Speed up the stuck transaction of `operation` with a child spending
+    /// its output paying `address` back to `address`, or, after earlier
+    /// bumps, with a child of the last of them, `bumps` holding those
+    /// oldest first. The child pays enough for the whole package to weigh
+    /// one ZIP-317 step more, or the most for urgent HTLCs, with fees of at
+    /// most `max_fee_zat` in all.
+    /// The child is recorded as a `FeeBump` operation of the same HTLC;
+    /// returns its txid and the output it spent.
+    pub async fn bump_fee(
+        &self,
+        operation: &HTLCOperation,
+        bumps: &[HTLCOperation],
+        address: &str,
+        keys: &KeyMap,
+        max_fee_zat: Option<u64>,
+    ) -> Result<(String, UTXO), HTLCClientError> {
+        let mut package_fee = 0;
+        let mut package_conventional_fee = 0;
+        let mut tip = None;
+        for stuck in std::iter::once(operation).chain(bumps) {
+            let (Some(tx_hex), Some(txid), Some(fee)) = (
+                stuck.signed_tx_hex.as_deref(),
+                stuck.txid.as_deref(),
+                stuck.fee_zat,
+            ) else {
+                return Err(HTLCClientError::OperationNotSigned(stuck.id.clone()));
+            };
+            let tx = self.tx_builder.deserialize_tx(tx_hex)?;
+            package_fee += fee;
+            package_conventional_fee += builder::conventional_fee(&tx);
+            tip = Some((tx, txid));
+        }
+        let Some((tip, tip_txid)) = tip else {
+            return Err(HTLCClientError::OperationNotSigned(operation.id.clone()));
+        };
+
+        let (vout, output) = tip
+            .output
+            .iter()
+            .enumerate()
+            .find(|(_, output)| {
+                self.script_builder
+                    .script_pubkey_to_address(&output.script_pubkey)
+                    .as_deref()
+                    == Some(address)
+            })
+            .ok_or_else(|| HTLCClientError::NoOutputToBump(operation.id.clone()))?;
+        let output = UTXO {
+            txid: tip_txid.to_string(),
+            vout: vout as u32,
+            amount: zatoshis_to_zec(output.value),
+            script_pubkey: hex::encode(output.script_pubkey.as_bytes()),
+            confirmations: 0,
+        };
+
+        package_conventional_fee += builder::estimated_conventional_fee(1, 1);
+        let priority = self.load_htlc(&operation.htlc_id).await?.priority;
+        let ratio = bump_weight_ratio(priority, package_fee, package_conventional_fee)?;
+        let target_fee = ratio * package_conventional_fee;
+        let target_fee = max_fee_zat.map_or(target_fee, |max| target_fee.min(max));
+        let child = self.next_block_tx_builder().await?.build_cpfp_tx(
+            &output,
+            package_fee,
+            target_fee,
+            address,
+        )?;
+        let prevouts = [output];
+        let (input_scripts, input_amounts) = prevout_scripts_and_amounts(&prevouts)?;
+        let signed_tx = self
+            .signer
+            .sign_htlc_creation(child, input_scripts, input_amounts, keys)
+            .await?;
+        let tx_hex = self.tx_builder.serialize_tx(&signed_tx);
+        let report = self.tx_builder.tx_report(&signed_tx, &prevouts)?;
+
+        let bump = HTLCOperation {
+            id: Uuid::new_v4().to_string(),
+            htlc_id: operation.htlc_id.clone(),
+            operation_type: HTLCOperationType::FeeBump,
+            txid: None,
+            raw_tx_hex: Some(tx_hex.clone()),
+            signed_tx_hex: Some(tx_hex.clone()),
+            broadcast_at: None,
+            confirmed_at: None,
+            block_height: None,
+            status: OperationStatus::Signed,
+            error_message: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            attempt_count: 0,
+            last_attempt_at: None,
+            next_retry_at: None,
+            fee_zat: Some(report.fee),
+            tx_size: Some(report.size as u32),
+            fee_rate: Some(report.fee_rate),
+            broadcast_height: None,
+            bumps_operation_id: Some(operation.id.clone()),
+            error_kind: None,
+        };
+        self.storage.create_operation(&bump).await?;
+
+        let txid = self.broadcast_operation(&bump.id, &tx_hex).await?;
+        self.storage
+            .record_operation_broadcast(&bump.id, &txid)
+            .await?;
+
+        info!(
+            "🚀 Bumped operation {} with child {} paying {} zat ({}x conventional for the package)",
+            operation.id, txid, report.fee, ratio
+        );
+        let [spent] = prevouts;
+        Ok((txid, spent))
+    }
+
+    /// Replace the refund `operation`, whose transaction expired
+    /// unconfirmed, with one paying `refund_address` a ZIP-317 weight step
+    /// more (the most for urgent HTLCs), at most `max_fee_zat`, and
+    /// expiring afresh. zcashd has no
+    /// replace-by-fee, so a refund gets a higher fee once nodes dropped the
+    /// original. The replacement is recorded as a refund operation bumping
+    /// the original, which is marked replaced; returns its txid.
+    pub async fn replace_refund(
+        &self,
+        operation: &HTLCOperation,
+        refund_address: &str,
+        refund_privkey: &str,
+        max_fee_zat: Option<u64>,
+    ) -> Result<String, HTLCClientError> {
+        let original_hex = operation
+            .signed_tx_hex
+            .as_deref()
+            .ok_or_else(|| HTLCClientError::OperationNotSigned(operation.id.clone()))?;
+        let htlc = self.load_htlc(&operation.htlc_id).await?;
+        let redeem_script_bytes =
+            hex::decode(&htlc.redeem_script_hex).map_err(|_| HTLCClientError::InvalidScript)?;
+        let redeem_script = bitcoin::blockdata::script::Script::from(redeem_script_bytes);
+
+        let original = self.tx_builder.deserialize_tx(original_hex)?;
+        let prevouts = self.htlc_prevouts(&original, &htlc.amount, &redeem_script);
+        let current_fee = self.tx_builder.tx_report(&original, &prevouts)?.fee;
+        let conventional_fee = builder::conventional_fee(&original);
+        let ratio = bump_weight_ratio(htlc.priority, current_fee, conventional_fee)?;
+        let fee = ratio * conventional_fee;
+        let fee = max_fee_zat.map_or(fee, |max| fee.min(max));
+
+        let tx_builder = self.next_block_tx_builder().await?;
+        let mut tx =
+            tx_builder.rebuild_with_higher_fee(&original, &prevouts, fee, refund_address)?;
+        tx.expiry_height = tx_builder.expiry_height();
+        let amount = zec_to_zatoshis(&htlc.amount).ok_or(HTLCClientError::InvalidAmount)?;
+        let signed_tx = self
+            .signer
+            .sign_htlc_refund(tx, 0, &redeem_script, amount, refund_privkey)
+            .await?;
+        let tx_hex = self.tx_builder.serialize_tx(&signed_tx);
+        let report = self.htlc_spend_report(&signed_tx, &htlc.amount, &redeem_script)?;
+
+        let replacement = HTLCOperation {
+            id: Uuid::new_v4().to_string(),
+            htlc_id: operation.htlc_id.clone(),
+            operation_type: HTLCOperationType::Refund,
+            txid: None,
+            raw_tx_hex: Some(tx_hex.clone()),
+            signed_tx_hex: Some(tx_hex.clone()),
+            broadcast_at: None,
+            confirmed_at: None,
+            block_height: None,
+            status: OperationStatus::Signed,
+            error_message: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            attempt_count: 0,
+            last_attempt_at: None,
+            next_retry_at: None,
+            fee_zat: Some(report.fee),
+            tx_size: Some(report.size as u32),
+            fee_rate: Some(report.fee_rate),
+            broadcast_height: None,
+            bumps_operation_id: Some(operation.id.clone()),
+            error_kind: None,
+        };
+        self.storage.create_operation(&replacement).await?;
+
+        let txid = self.broadcast_operation(&replacement.id, &tx_hex).await?;
+        self.storage
+            .record_operation_replaced(&operation.id, &replacement.id, &txid)
+            .await?;
+
+        info!(
+            "🚀 Replaced refund {} of HTLC {} with {} paying {} zat ({}x conventional)",
+            operation.id, operation.htlc_id, txid, report.fee, ratio
+        );
+        Ok(txid)
+    }
+
+    /// Height after which the signed transaction of `operation` can no
+    /// longer be mined, `None` if it never expires
+    pub fn operation_expiry_height(
+        &self,
+        operation: &HTLCOperation,
+    ) -> Result<Option<u32>, HTLCClientError> {
+        let tx_hex = operation
+            .signed_tx_hex
+            .as_deref()
+            .ok_or_else(|| HTLCClientError::OperationNotSigned(operation.id.clone()))?;
+        let expiry_height = self.tx_builder.deserialize_tx(tx_hex)?.expiry_height;
+        Ok((expiry_height != 0).then_some(expiry_height))
+    }
+
+    async fn broadcast_consolidation(
+        &self,
+        signed_tx: &ZcashTransaction,
+        utxos: &[UTXO],
+        address: &str,
+    ) -> Result<Consolidation, HTLCClientError> {
+        let report = self.tx_builder.tx_report(signed_tx, utxos)?;
+        let txid = self
+            .broadcast_raw_tx(&self.tx_builder.serialize_tx(signed_tx))
+            .await?;
+
+        info!(
+            "🧹 Consolidated {} UTXO(s) in {} for {} zat ({} zat/kB)",
+            utxos.len(),
+            txid,
+            report.fee,
+            report.fee_rate
+        );
+        Ok(Consolidation {
+            txid,
+            address: address.to_string(),
+            inputs: utxos.len() as u32,
+            amount_zat: signed_tx.output.iter().map(|output| output.value).sum(),
+            fee_zat: report.fee,
+            tx_size: report.size as u32,
+            fee_rate: report.fee_rate,
+            created_at: Utc::now(),
+        })
+    }
+
+    /// Second opinion from the node on an address that already decoded
+    /// locally, catching e.g. addresses of another network. Skipped, with a
+    /// warning, when the node does not offer `validateaddress`.
+    async fn check_address(&self, address: &str) -> Result<(), HTLCClientError> {
+        match self.rpc_client.validate_address(address).await {
+            Ok(validation) if validation.isvalid => Ok(()),
+            Ok(_) => Err(HTLCClientError::AddressRejected(address.to_string())),
+            Err(RpcClientError::RpcError(e)) if e.code == rpc::RPC_METHOD_NOT_FOUND => {
+                warn!(
+                    "⚠️ Node cannot validate addresses, skipping check of {}",
+                    address
+                );
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Broadcast a signed transaction. One the node already has, e.g. from
+    /// an earlier attempt whose response was lost, counts as broadcast.
     pub async fn broadcast_raw_tx(&self, tx_hex: &str) -> Result<String, HTLCClientError> {
-        Ok(self.rpc_client.send_raw_transaction(tx_hex).await?)
+        match self.rpc_client.send_raw_transaction(tx_hex).await {
+            Err(RpcClientError::AlreadyBroadcast { txid }) => {
+                info!("♻️ Transaction {} was already broadcast", txid);
+                Ok(txid)
+            }
+            result => Ok(result?),
+        }
+    }
+
+    /// Fee, size and fee rate of a signed redeem or refund spending an HTLC
+    /// output of `amount` locked to `redeem_script`
+    fn htlc_spend_report(
+        &self,
+        signed_tx: &bitcoin::Transaction,
+        amount: &str,
+        redeem_script: &bitcoin::blockdata::script::Script,
+    ) -> Result<TxReport, HTLCClientError> {
+        let prevouts = self.htlc_prevouts(signed_tx, amount, redeem_script);
+        Ok(self.tx_builder.tx_report(signed_tx, &prevouts)?)
+    }
+
+    /// Outputs a redeem or refund spends, each an HTLC output of `amount`
+    /// locked to `redeem_script`
+    fn htlc_prevouts(
+        &self,
+        tx: &bitcoin::Transaction,
+        amount: &str,
+        redeem_script: &bitcoin::blockdata::script::Script,
+    ) -> Vec<UTXO> {
+        tx.input
+            .iter()
+            .map(|input| UTXO {
+                txid: input.previous_output.txid.to_string(),
+                vout: input.previous_output.vout,
+                amount: amount.to_string(),
+                script_pubkey: hex::encode(
+                    self.script_builder
+                        .p2sh_script_pubkey(redeem_script)
+                        .as_bytes(),
+                ),
+                confirmations: 0,
+            })
+            .collect()
+    }
+
+    /// Broadcast the signed transaction of `operation_id`, recording a
+    /// failure so that [`Self::retry_operation`] can pick it up later unless
+    /// the transaction can never be broadcast as it is
+    async fn broadcast_operation(
+        &self,
+        operation_id: &str,
+        tx_hex: &str,
+    ) -> Result<String, HTLCClientError> {
+        let error = match self.broadcast_raw_tx(tx_hex).await {
+            Ok(txid) => return Ok(txid),
+            Err(e) => e,
+        };
+
+        let recorded = self
+            .storage
+            .record_operation_attempt(
+                operation_id,
+                &error.to_string(),
+                error.failure_kind(),
+                error.is_rebroadcastable(),
+            )
+            .await;
+        if let Err(e) = recorded {
+            warn!(
+                "⚠️ Failed to record attempt of operation {}: {}",
+                operation_id, e
+            );
+        }
+
+        Err(error)
+    }
+
+    /// Broadcast the signed transaction of a failed operation again, e.g.
+    /// one of [`Database::get_operations_due_for_retry`], and settle its
+    /// HTLC once the node accepts it
+    pub async fn retry_operation(
+        &self,
+        operation: &HTLCOperation,
+    ) -> Result<String, HTLCClientError> {
+        let tx_hex = operation
+            .signed_tx_hex
+            .as_deref()
+            .ok_or_else(|| HTLCClientError::OperationNotSigned(operation.id.clone()))?;
+        info!(
+            "🔁 Retrying operation {} (attempt {})",
+            operation.id,
+            operation.attempt_count + 1
+        );
+
+        let txid = self.broadcast_operation(&operation.id, tx_hex).await?;
+
+        let (htlc_id, operation_id) = (&operation.htlc_id, &operation.id);
+        match operation.operation_type {
+            HTLCOperationType::Create => {
+                self.storage
+                    .record_htlc_funding(htlc_id, operation_id, &txid, 0)
+                    .await?
+            }
+            // The secret travels in the redeem's input
+            HTLCOperationType::Redeem => {
+                let htlc = self.load_htlc(htlc_id).await?;
+                let secret = self.revealed_secret(&htlc, tx_hex);
+                self.storage
+                    .record_htlc_settlement(
+                        htlc_id,
+                        HTLCState::Redeemed,
+                        secret.as_deref(),
+                        operation_id,
+                        &txid,
+                    )
+                    .await?
+            }
+            // A replacement refund finds its HTLC refunded already
+            HTLCOperationType::Refund => match &operation.bumps_operation_id {
+                Some(original_id) => {
+                    self.storage
+                        .record_operation_replaced(original_id, operation_id, &txid)
+                        .await?
+                }
+                None => {
+                    self.storage
+                        .record_htlc_settlement(
+                            htlc_id,
+                            HTLCState::Refunded,
+                            None,
+                            operation_id,
+                            &txid,
+                        )
+                        .await?
+                }
+            },
+            HTLCOperationType::FeeBump => {
+                self.storage
+                    .record_operation_broadcast(operation_id, &txid)
+                    .await?
+            }
+        }
+
+        info!(
+            "✅ Operation {} broadcast with txid: {}",
+            operation.id, txid
+        );
+        Ok(txid)
     }
 
     // ==================== Query Methods ====================
 
     /// Get HTLC by ID
-    pub fn get_htlc(&self, htlc_id: &str) -> Result<ZcashHTLC, HTLCClientError> {
-        Ok(self.database.get_htlc_by_id(htlc_id)?)
+    pub async fn get_htlc(&self, htlc_id: &str) -> Result<ZcashHTLC, HTLCClientError> {
+        self.load_htlc(htlc_id).await
+    }
+
+    async fn load_htlc(&self, htlc_id: &str) -> Result<ZcashHTLC, HTLCClientError> {
+        Ok(self.storage.get_htlc_by_id(htlc_id).await?)
+    }
+
+    /// Get UTXOs for address from the block explorer
+    pub async fn get_utxos(&self, address: &str) -> Result<Vec<UTXO>, HTLCClientError> {
+        Ok(self.rpc_client.get_utxos(address).await?)
+    }
+
+    /// Get address balance in ZEC from the block explorer
+    pub async fn get_balance(&self, address: &str) -> Result<String, HTLCClientError> {
+        Ok(self.rpc_client.get_balance(address).await?)
+    }
+
+    /// [`get_utxos`](Self::get_utxos) with the URL of the explorer that
+    /// answered, `None` if the node's address index did
+    pub async fn get_utxos_with_source(
+        &self,
+        address: &str,
+    ) -> Result<(Vec<UTXO>, Option<String>), HTLCClientError> {
+        Ok(self.rpc_client.get_utxos_with_source(address).await?)
+    }
+
+    /// [`get_balance`](Self::get_balance) with the URL of the explorer that
+    /// answered, `None` if the node's address index did
+    pub async fn get_balance_with_source(
+        &self,
+        address: &str,
+    ) -> Result<(String, Option<String>), HTLCClientError> {
+        Ok(self.rpc_client.get_balance_with_source(address).await?)
+    }
+
+    /// Whether the HTLC output has been spent, including by a transaction
+    /// still in the mempool such as an in-flight redeem. A refund attempted
+    /// then would only conflict with it. Fails with
+    /// [`HTLCClientError::FundingOutputUnknown`] if the node does not know
+    /// the funding output at all, e.g. after it was reorged out.
+    pub async fn is_htlc_output_spent(&self, htlc_id: &str) -> Result<bool, HTLCClientError> {
+        let htlc = self.load_htlc(htlc_id).await?;
+        let txid = htlc.txid.ok_or(HTLCClientError::HTLCNotLocked)?;
+        let vout = htlc.vout.ok_or(HTLCClientError::HTLCNotLocked)?;
+
+        if self
+            .rpc_client
+            .get_tx_out(&txid, vout, true)
+            .await?
+            .is_some()
+        {
+            return Ok(false);
+        }
+
+        // gettxout is null for outputs the node never saw as well
+        let unknown = || HTLCClientError::FundingOutputUnknown(format!("{}:{}", txid, vout));
+        let funding = match self.rpc_client.get_raw_transaction(&txid).await {
+            Ok(funding) => funding,
+            Err(RpcClientError::RpcError(error))
+                if error.code == rpc::RPC_INVALID_ADDRESS_OR_KEY =>
+            {
+                return Err(unknown())
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if !funding.vout.iter().any(|output| output.n == vout) {
+            return Err(unknown());
+        }
+
+        info!("👀 HTLC {} output {}:{} already spent", htlc_id, txid, vout);
+        Ok(true)
+    }
+
+    /// Record the chain tip's block hash and check that the recorded hashes
+    /// are still on the active chain. After a reorg, operations confirmed in
+    /// replaced blocks are re-validated and a
+    /// [`NodeEvent::ReorgDetected`] is published.
+    pub async fn track_chain_tip(&self) -> Result<Option<ReorgReport>, HTLCClientError> {
+        let chain = self.config.network.as_str();
+        let tip = self.rpc_client.get_block_count().await?;
+        let checkpoints = self
+            .storage
+            .get_recent_block_hashes(chain, REORG_WINDOW as i64)
+            .await?;
+
+        let fork_height = self.rpc_client.find_fork_height(&checkpoints, tip).await?;
+        let report = match fork_height {
+            Some(fork_height) => Some(self.handle_reorg(fork_height, tip).await?),
+            None => None,
+        };
+
+        let last_recorded = match (fork_height, checkpoints.first()) {
+            (Some(fork_height), _) => fork_height.checked_sub(1),
+            (None, Some((height, _))) => Some(*height),
+            (None, None) => None,
+        };
+        let first_missing = last_recorded
+            .map_or(tip, |height| height + 1)
+            .max(tip.saturating_sub(REORG_WINDOW - 1));
+        let mut hashes = Vec::new();
+        for height in first_missing..=tip {
+            hashes.push((height, self.rpc_client.get_block_hash(height).await?));
+        }
+        self.storage
+            .record_block_hashes(chain, &hashes, tip.saturating_sub(REORG_WINDOW - 1))
+            .await?;
+
+        Ok(report)
+    }
+
+    async fn handle_reorg(
+        &self,
+        fork_height: u64,
+        tip: u64,
+    ) -> Result<ReorgReport, HTLCClientError> {
+        warn!(
+            "⚠️ Chain reorganization: blocks from {} replaced (tip {})",
+            fork_height, tip
+        );
+        // Cached confirmation counts may predate the reorg
+        self.rpc_client.clear_tx_cache();
+        let chain = self.config.network.as_str();
+        let confirmed = self
+            .storage
+            .get_operations_confirmed_since(fork_height)
+            .await?;
+
+        let mut report = ReorgReport {
+            fork_height,
+            tip,
+            reconfirmed: Vec::new(),
+            reverted: Vec::new(),
+        };
+        // Only a definite answer moves an operation. Other errors abort
+        // before the recorded hashes are rewound, so the next call detects
+        // the same reorg and tries again.
+        for operation in confirmed {
+            let confirmations = match &operation.txid {
+                Some(txid) => match self.rpc_client.get_transaction_confirmations(txid).await {
+                    Ok(confirmations) => confirmations,
+                    Err(RpcClientError::RpcError(error))
+                        if error.code == rpc::RPC_INVALID_ADDRESS_OR_KEY =>
+                    {
+                        0
+                    }
+                    Err(e) => return Err(e.into()),
+                },
+                None => 0,
+            };
+
+            if confirmations > 0 {
+                let block_height = tip + 1 - confirmations as u64;
+                self.storage
+                    .update_operation_confirmed(&operation.id, block_height)
+                    .await?;
+                report.reconfirmed.push(operation.id);
+            } else {
+                self.storage
+                    .update_operation_unconfirmed(&operation.id)
+                    .await?;
+                report.reverted.push(operation.id);
+            }
+        }
+        self.storage.rewind_block_hashes(chain, fork_height).await?;
+
+        if let Some(events) = &self.block_events {
+            // No receivers is fine; the report is returned as well
+            let _ = events.send(NodeEvent::ReorgDetected { fork_height });
+        }
+
+        Ok(report)
+    }
+
+    /// Mark broadcast operations confirmed once their transaction has
+    /// `min_confirmations` (at least one), checking up to `limit` of them,
+    /// those checked least recently first. A confirmed funding transaction
+    /// locks its HTLC at the output paying it. A transaction the node no
+    /// longer knows, e.g. dropped from its mempool, fails its operation to
+    /// be broadcast again by [`Self::retry_operation`]; other errors skip it
+    /// until the next call.
+    pub async fn track_confirmations(
+        &self,
+        min_confirmations: u32,
+        limit: usize,
+    ) -> Result<ConfirmationReport, HTLCClientError> {
+        let tip = self.rpc_client.get_block_count().await?;
+        let unconfirmed = self.storage.get_unconfirmed_operations(limit).await?;
+
+        let checked: Vec<String> = unconfirmed
+            .iter()
+            .map(|operation| operation.id.clone())
+            .collect();
+        self.storage.record_operations_checked(&checked).await?;
+
+        let mut report = ConfirmationReport {
+            tip,
+            ..Default::default()
+        };
+        for operation in unconfirmed {
+            let Some(txid) = operation.txid.as_deref() else {
+                continue;
+            };
+            let confirmations = match self.rpc_client.get_transaction_confirmations(txid).await {
+                Ok(confirmations) => confirmations,
+                Err(RpcClientError::RpcError(error))
+                    if error.code == rpc::RPC_INVALID_ADDRESS_OR_KEY =>
+                {
+                    warn!(
+                        "⚠️ Node no longer knows {} of operation {}",
+                        txid, operation.id
+                    );
+                    self.storage
+                        .record_operation_attempt(
+                            &operation.id,
+                            "transaction unknown to the node",
+                            FailureKind::Broadcast,
+                            true,
+                        )
+                        .await?;
+                    report.dropped.push(operation.id);
+                    continue;
+                }
+                Err(e) => {
+                    warn!("⚠️ Cannot check confirmations of {}: {}", txid, e);
+                    continue;
+                }
+            };
+            if confirmations < min_confirmations.max(1) {
+                continue;
+            }
+            let block_height = (tip + 1).saturating_sub(confirmations as u64);
+
+            if let HTLCOperationType::Create = operation.operation_type {
+                let Some(vout) = self.funding_vout(&operation).await? else {
+                    warn!(
+                        "⚠️ Funding operation {} pays no output of HTLC {}",
+                        operation.id, operation.htlc_id
+                    );
+                    continue;
+                };
+                let locked = self
+                    .storage
+                    .record_funding_confirmed(
+                        &operation.htlc_id,
+                        &operation.id,
+                        txid,
+                        vout,
+                        block_height,
+                    )
+                    .await?;
+                if locked {
+                    report.locked.push(operation.htlc_id.clone());
+                }
+            } else {
+                self.storage
+                    .update_operation_confirmed(&operation.id, block_height)
+                    .await?;
+            }
+            report.confirmed.push(operation.id);
+        }
+
+        Ok(report)
+    }
+
+    /// Output of a funding operation's transaction paying its HTLC, or the
+    /// recorded one if the transaction is not stored
+    async fn funding_vout(
+        &self,
+        operation: &HTLCOperation,
+    ) -> Result<Option<u32>, HTLCClientError> {
+        let htlc = self.load_htlc(&operation.htlc_id).await?;
+        let Some(tx_hex) = operation.signed_tx_hex.as_deref() else {
+            return Ok(htlc.vout);
+        };
+        let redeem_script = hex::decode(&htlc.redeem_script_hex)
+            .map(bitcoin::blockdata::script::Script::from)
+            .map_err(|_| HTLCClientError::InvalidScript)?;
+        let script_pubkey = self.script_builder.p2sh_script_pubkey(&redeem_script);
+
+        let tx = self.tx_builder.deserialize_tx(tx_hex)?;
+        Ok(tx
+            .output
+            .iter()
+            .position(|output| output.script_pubkey == script_pubkey)
+            .map(|vout| vout as u32))
+    }
+
+    /// Fee rate for confirmation within `conf_target` blocks
+    pub async fn estimate_fee(&self, conf_target: u32) -> Result<FeeEstimate, HTLCClientError> {
+        Ok(self.rpc_client.estimate_fee(conf_target).await?)
+    }
+
+    /// Transaction builder for the next block: the signer follows the
+    /// consensus branch the node reports for it, unless one is configured,
+    /// and built transactions expire `tx_expiry_delta` blocks after it
+    async fn next_block_tx_builder(&self) -> Result<TransactionBuilder, HTLCClientError> {
+        let info = self.rpc_client.get_blockchain_info().await?;
+        if self.config.consensus_branch_id.is_none() {
+            let branch_id = info
+                .next_block_branch_id()
+                .ok_or(HTLCClientError::UnknownConsensusBranch)?;
+            self.signer.set_branch_id(branch_id);
+        }
+
+        let delta = self
+            .config
+            .tx_expiry_delta
+            .unwrap_or(DEFAULT_TX_EXPIRY_DELTA);
+        let expiry_height = if delta == 0 {
+            0
+        } else {
+            info.blocks as u32 + 1 + delta
+        };
+        Ok(self.tx_builder.clone().with_expiry_height(expiry_height))
+    }
+
+    /// [`Self::next_block_tx_builder`] paying the rate configured for
+    /// `priority`, or else that of the fee mode. A failed estimate falls
+    /// back to the default rate.
+    async fn priced_tx_builder(
+        &self,
+        priority: FeePriority,
+    ) -> Result<TransactionBuilder, HTLCClientError> {
+        let tx_builder = self.next_block_tx_builder().await?;
+        if let Some(fee_per_kb) = self.fee_priority_rate(priority) {
+            info!(
+                "💸 Fee rate {} zat/kB ({} priority)",
+                fee_per_kb,
+                priority.as_str()
+            );
+            return Ok(tx_builder.with_fee_rate(fee_per_kb));
+        }
+        let FeeMode::Dynamic {
+            conf_target,
+            max_fee_per_kb,
+        } = *self.fee_mode.read().unwrap()
+        else {
+            return Ok(tx_builder);
+        };
+
+        Ok(match self.rpc_client.estimate_fee(conf_target).await {
+            Ok(estimate) => {
+                let fee_per_kb =
+                    max_fee_per_kb.map_or(estimate.fee_per_kb, |max| estimate.fee_per_kb.min(max));
+                info!(
+                    "💸 Fee rate {} zat/kB ({:?} confidence)",
+                    fee_per_kb, estimate.confidence
+                );
+                tx_builder.with_fee_rate(fee_per_kb)
+            }
+            Err(e) => {
+                warn!("⚠️ Fee estimation failed: {}, using the default rate", e);
+                tx_builder
+            }
+        })
     }
 
-    // Get UTXOs for address
-    // pub async fn get_utxos(&self, address: &str) -> Result<Vec<UTXO>, HTLCClientError> {
-    //     Ok(self.rpc_client.get_utxos(address).await?)
-    // }
+    /// Walk blocks after the scan checkpoint for outputs paying unsettled
+    /// HTLCs and spends of them, using only the node's data. HTLCs funded
+    /// without a recorded outpoint get it from the scan. Spends are read as
+    /// a redeem or refund from their scriptSig and settle the HTLC, keeping
+    /// a revealed preimage. Without a checkpoint, scanning starts
+    /// `REORG_WINDOW` blocks below the tip.
+    pub async fn scan_for_htlcs(&self) -> Result<ScanReport, HTLCClientError> {
+        let chain = self.config.network.as_str();
+        let tip = self.rpc_client.get_block_count().await?;
+        let from_height = match self.storage.get_checkpoint(chain).await? {
+            Some(checkpoint) => checkpoint as u64 + 1,
+            None => tip.saturating_sub(REORG_WINDOW),
+        };
+        let to_height = tip.min(from_height + SCAN_BATCH_BLOCKS - 1);
 
-    // /// Get address balance
-    // pub async fn get_balance(&self, address: &str) -> Result<String, HTLCClientError> {
-    //     Ok(self.rpc_client.get_balance(address).await?)
-    // }
+        let mut report = ScanReport {
+            from_height,
+            to_height,
+            activity: Vec::new(),
+        };
+        if from_height > tip {
+            return Ok(report);
+        }
+
+        let network = self.config.network;
+        let htlcs = self.storage.get_unsettled_htlcs(network).await?;
+        let mut watch = WatchSet::new();
+        for htlc in &htlcs {
+            let redeem_script = hex::decode(&htlc.redeem_script_hex)
+                .map(bitcoin::blockdata::script::Script::from)
+                .map_err(|_| HTLCClientError::InvalidScript)?;
+            let script_pubkey = self.script_builder.p2sh_script_pubkey(&redeem_script);
+            watch.watch_script(&htlc.id, &hex::encode(script_pubkey.as_bytes()));
+
+            if let (Some(txid), Some(vout)) = (&htlc.txid, htlc.vout) {
+                watch.watch_outpoint(&htlc.id, txid, vout);
+            }
+        }
+
+        if !watch.is_empty() {
+            report.activity = self
+                .rpc_client
+                .scan_blocks(from_height, to_height, &mut watch)
+                .await?;
+        }
+
+        for activity in &report.activity {
+            match activity {
+                HTLCActivity::Funded {
+                    htlc_id,
+                    txid,
+                    vout,
+                    height,
+                    ..
+                } => {
+                    info!(
+                        "🔎 HTLC {} funded by {}:{} at {}",
+                        htlc_id, txid, vout, height
+                    );
+                    let unfunded = htlcs
+                        .iter()
+                        .any(|htlc| htlc.id == *htlc_id && htlc.txid.is_none());
+                    if unfunded {
+                        self.storage.update_htlc_txid(htlc_id, txid, *vout).await?;
+                    }
+                }
+                HTLCActivity::Spent {
+                    htlc_id,
+                    spending_txid,
+                    script_sig_hex,
+                    height,
+                    ..
+                } => {
+                    info!(
+                        "🔎 HTLC {} spent by {} at {}",
+                        htlc_id, spending_txid, height
+                    );
+                    let Some(htlc) = htlcs.iter().find(|htlc| htlc.id == *htlc_id) else {
+                        continue;
+                    };
+                    match self.classify_spend(htlc, script_sig_hex.as_deref()) {
+                        Some(spend) => {
+                            // A spend the store refuses, e.g. of an HTLC
+                            // that failed, must not stall the scan
+                            if let Err(e) = self
+                                .storage
+                                .record_htlc_spend(htlc_id, &spend, spending_txid)
+                                .await
+                            {
+                                warn!("⚠️  Could not settle HTLC {}: {}", htlc_id, e);
+                            }
+                        }
+                        None => warn!(
+                            "⚠️  HTLC {} spent by {} through neither branch",
+                            htlc_id, spending_txid
+                        ),
+                    }
+                }
+            }
+        }
+
+        self.storage
+            .save_checkpoint(chain, to_height as u32)
+            .await?;
+        Ok(report)
+    }
+
+    /// Preimage of `htlc` revealed by an input of the signed transaction
+    /// `tx_hex`, if it redeems it
+    fn revealed_secret(&self, htlc: &ZcashHTLC, tx_hex: &str) -> Option<HTLCSecret> {
+        let tx = ZcashTransaction::deserialize(&hex::decode(tx_hex).ok()?).ok()?;
+        tx.transaction.input.iter().find_map(|input| {
+            match self.classify_spend(htlc, Some(&hex::encode(input.script_sig.as_bytes())))? {
+                HTLCSpend::Redeem { secret } => Some(secret),
+                HTLCSpend::Refund => None,
+            }
+        })
+    }
+
+    /// How `htlc` was spent by an input with `script_sig_hex`. A redeem
+    /// counts only if its preimage matches the hash lock.
+    fn classify_spend(&self, htlc: &ZcashHTLC, script_sig_hex: Option<&str>) -> Option<HTLCSpend> {
+        let script_sig =
+            bitcoin::blockdata::script::Script::from(hex::decode(script_sig_hex?).ok()?);
+        let redeem_script =
+            bitcoin::blockdata::script::Script::from(hex::decode(&htlc.redeem_script_hex).ok()?);
+
+        match self
+            .script_builder
+            .classify_spend(&script_sig, &redeem_script)?
+        {
+            HTLCSpend::Redeem { secret }
+                if !self.script_builder.verify_secret(&secret, &htlc.hash_lock) =>
+            {
+                None
+            }
+            spend => Some(spend),
+        }
+    }
+
+    /// Node chain and sync status; errors if the node is on another network
+    pub async fn get_blockchain_info(&self) -> Result<BlockchainInfo, HTLCClientError> {
+        Ok(self.rpc_client.get_blockchain_info().await?)
+    }
+
+    /// Headers of new blocks as they connect; see
+    /// [`ZcashRpcClient::subscribe_blocks`]
+    pub fn subscribe_blocks(&self) -> impl futures_util::Stream<Item = BlockHeader> + '_ {
+        self.rpc_client.subscribe_blocks()
+    }
 
     pub async fn get_current_block_height(&self) -> Result<u64, HTLCClientError> {
         Ok(self.rpc_client.get_block_count().await?)
     }
 
+    /// Confirmations of `txid`, 0 while it is in the mempool
+    pub async fn get_transaction_confirmations(&self, txid: &str) -> Result<u32, HTLCClientError> {
+        Ok(self.rpc_client.get_transaction_confirmations(txid).await?)
+    }
+
+    /// Median time past of the chain tip, the clock timestamp timelocks
+    /// are checked against. Taken from `getblockchaininfo` when the node
+    /// reports it, and from the tip's headers otherwise.
+    pub async fn get_median_time_past(&self) -> Result<u64, HTLCClientError> {
+        let info = self.rpc_client.get_blockchain_info().await?;
+        if let Some(median_time_past) = info.mediantime {
+            return Ok(median_time_past);
+        }
+        Ok(self
+            .rpc_client
+            .get_median_time_past(&info.bestblockhash)
+            .await?)
+    }
+
     /// Wait for transaction confirmation
     pub async fn wait_for_confirmation(
         &self,
@@ -354,23 +1656,91 @@ impl ZcashHTLCClient {
             .await?)
     }
 
+    /// Wait for confirmation with a custom poll interval, deadline,
+    /// cancellation token or progress callback
+    pub async fn wait_for_confirmation_with(
+        &self,
+        txid: &str,
+        confirmations: u32,
+        wait: &ConfirmationWait,
+    ) -> Result<u32, HTLCClientError> {
+        Ok(self
+            .rpc_client
+            .wait_for_confirmations_with(txid, confirmations, wait)
+            .await?)
+    }
+
     // ==================== Key Management ====================
 
-    /// Generate new private key
-    pub fn generate_privkey(&self) -> String {
+    /// Generate new private key, wiped from memory when dropped
+    pub fn generate_privkey(&self) -> Zeroizing<String> {
         self.signer.generate_privkey()
     }
 
+    /// Generate a private key from an injected RNG, e.g. a seeded one for
+    /// reproducible tests. Production code should use [`generate_privkey`](Self::generate_privkey).
+    pub fn generate_privkey_with_rng<R: rand::RngCore + rand::CryptoRng + ?Sized>(
+        &self,
+        rng: &mut R,
+    ) -> Zeroizing<String> {
+        self.signer.generate_privkey_with_rng(rng)
+    }
+
     /// Derive public key from private key
     pub fn derive_pubkey(&self, privkey: &str) -> Result<String, HTLCClientError> {
         Ok(self.signer.derive_pubkey(privkey)?)
     }
 
+    /// SHA-256 hash lock of a raw secret
+    pub fn hash_lock(&self, secret: &[u8]) -> String {
+        self.signer.hash_lock(secret)
+    }
+
+    /// Hash lock of a hex-encoded secret, rejecting anything that is not hex
+    pub fn hash_lock_from_hex(&self, secret_hex: &str) -> Result<String, HTLCClientError> {
+        Ok(self.signer.hash_lock_from_hex(secret_hex)?)
+    }
+
     /// Generate hash lock from secret
+    #[deprecated(
+        note = "falls back to hashing the ASCII bytes of non-hex secrets; use hash_lock or hash_lock_from_hex"
+    )]
+    #[allow(deprecated)]
     pub fn generate_hash_lock(&self, secret: &str) -> String {
         self.signer.generate_hash_lock(secret)
     }
 
+    /// Hex private key from zcashd `dumpprivkey` output
+    pub fn import_wif(&self, wif: &str) -> Result<Zeroizing<String>, HTLCClientError> {
+        Ok(self.signer.privkey_from_wif(wif)?)
+    }
+
+    /// WIF encoding of a hex private key for zcashd `importprivkey`
+    pub fn export_wif(&self, privkey: &str) -> Result<Zeroizing<String>, HTLCClientError> {
+        Ok(self.signer.privkey_to_wif(privkey)?)
+    }
+
+    /// Transparent address of a hex public key
+    pub fn pubkey_to_address(&self, pubkey: &str) -> Result<String, HTLCClientError> {
+        let pubkey = hex::decode(pubkey).map_err(|_| SignerError::InvalidPublicKey)?;
+        Ok(self.script_builder.pubkey_to_p2pkh_address(&pubkey))
+    }
+
+    /// Sign a message to prove ownership of a key during swap negotiation
+    pub async fn sign_message(&self, message: &str, key: &str) -> Result<String, HTLCClientError> {
+        Ok(self.signer.sign_message(message, key).await?)
+    }
+
+    /// Verify a message signature against a transparent address
+    pub fn verify_message(
+        &self,
+        address: &str,
+        message: &str,
+        signature: &str,
+    ) -> Result<bool, HTLCClientError> {
+        Ok(self.signer.verify_message(address, message, signature)?)
+    }
+
     // ==================== Utilities ====================
 
     /// Get current network
@@ -378,10 +1748,80 @@ impl ZcashHTLCClient {
         self.config.network
     }
 
-    /// Get database reference
-    pub fn database(&self) -> &Database {
-        &self.database
+    /// Tenant this client acts for
+    pub fn tenant(&self) -> &TenantContext {
+        self.storage.tenant()
+    }
+
+    /// Where this client keeps HTLCs and operations
+    pub fn storage(&self) -> &Arc<dyn Storage> {
+        &self.storage
+    }
+
+    /// The Postgres database behind this client; `None` for other
+    /// [`Storage`]
+    pub fn database(&self) -> Option<&Database> {
+        self.storage.as_database()
     }
+
+    /// Get client configuration
+    pub fn config(&self) -> &ZcashConfig {
+        &self.config
+    }
+}
+
+/// Multiple of its ZIP-317 conventional fee to bump a transaction paying
+/// `fee` to: one step more, or straight to the most for urgent HTLCs
+fn bump_weight_ratio(
+    priority: FeePriority,
+    fee: u64,
+    conventional_fee: u64,
+) -> Result<u64, TxBuilderError> {
+    let next = builder::next_weight_ratio(fee, conventional_fee).ok_or(
+        TxBuilderError::FeeNotIncreased {
+            current: fee,
+            requested: fee,
+        },
+    )?;
+    Ok(match priority {
+        FeePriority::Urgent => builder::MAX_WEIGHT_RATIO,
+        _ => next,
+    })
+}
+
+/// Script pubkeys and zatoshi amounts of `utxos`, as signing needs them
+fn prevout_scripts_and_amounts(
+    utxos: &[UTXO],
+) -> Result<(Vec<bitcoin::blockdata::script::Script>, Vec<u64>), HTLCClientError> {
+    utxos
+        .iter()
+        .map(|utxo| {
+            let script = hex::decode(&utxo.script_pubkey)
+                .map(bitcoin::blockdata::script::Script::from)
+                .map_err(|_| HTLCClientError::InvalidScript)?;
+            let amount = zec_to_zatoshis(&utxo.amount).ok_or(HTLCClientError::InvalidAmount)?;
+            Ok((script, amount))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(|prevouts| prevouts.into_iter().unzip())
+}
+
+/// Template inputs spending `utxos` of the P2SH multisig `wallet_script`
+fn multisig_inputs(
+    utxos: &[UTXO],
+    wallet_script: &bitcoin::blockdata::script::Script,
+) -> Vec<TemplateInput> {
+    utxos
+        .iter()
+        .map(|utxo| TemplateInput {
+            txid: utxo.txid.clone(),
+            vout: utxo.vout,
+            amount: utxo.amount.clone(),
+            script_pubkey: utxo.script_pubkey.clone(),
+            redeem_script: Some(hex::encode(wallet_script.as_bytes())),
+            input_type: TemplateInputType::P2SHMultisig,
+        })
+        .collect()
 }
 
 // ==================== Error Types ====================
@@ -409,12 +1849,530 @@ pub enum HTLCClientError {
     #[error("Invalid secret for hash lock")]
     InvalidSecret,
 
-    #[error("HTLC not locked (missing txid or vout)")]
+    #[error("HTLC not locked (funding unconfirmed, or missing txid or vout)")]
     HTLCNotLocked,
 
     #[error("Invalid script format")]
     InvalidScript,
 
+    #[error("Invalid amount format")]
+    InvalidAmount,
+
+    #[error("Address rejected by node: {0}")]
+    AddressRejected(String),
+
     #[error("Timelock not expired (current: {current}, required: {required})")]
     TimelockNotExpired { current: u64, required: u64 },
+
+    #[error("Operation has no signed transaction: {0}")]
+    OperationNotSigned(String),
+
+    #[error("Operation {0} has no output of ours to bump its fee with")]
+    NoOutputToBump(String),
+
+    #[error("Node reports no consensus branch for the next block")]
+    UnknownConsensusBranch,
+
+    #[error("Node does not know the HTLC funding output {0}")]
+    FundingOutputUnknown(String),
+}
+
+impl HTLCClientError {
+    /// Coarse class of the failure, recorded with failed operations and
+    /// creations
+    pub fn failure_kind(&self) -> FailureKind {
+        match self {
+            HTLCClientError::DatabaseError(_) => FailureKind::Database,
+            HTLCClientError::RpcError(
+                RpcClientError::RpcError(_)
+                | RpcClientError::AlreadyBroadcast { .. }
+                | RpcClientError::MempoolConflict(_)
+                | RpcClientError::MissingInputs(_)
+                | RpcClientError::AbsurdFee(_)
+                | RpcClientError::TransactionExpired(_)
+                | RpcClientError::BroadcastConflict { .. },
+            )
+            | HTLCClientError::AddressRejected(_) => FailureKind::Broadcast,
+            HTLCClientError::RpcError(_) | HTLCClientError::UnknownConsensusBranch => {
+                FailureKind::Node
+            }
+            HTLCClientError::TxBuilderError(_)
+            | HTLCClientError::InvalidAmount
+            | HTLCClientError::NoOutputToBump(_) => FailureKind::Funding,
+            HTLCClientError::ScriptError(_)
+            | HTLCClientError::SignerError(_)
+            | HTLCClientError::InvalidScript
+            | HTLCClientError::InvalidSecret => FailureKind::Signing,
+            HTLCClientError::ConfigError(_)
+            | HTLCClientError::HTLCNotLocked
+            | HTLCClientError::TimelockNotExpired { .. }
+            | HTLCClientError::OperationNotSigned(_)
+            | HTLCClientError::FundingOutputUnknown(_) => FailureKind::Other,
+        }
+    }
+
+    /// Whether broadcasting the same signed transaction again can succeed.
+    /// Not once it expired, its inputs are gone or its fee was refused; a
+    /// new transaction is needed then.
+    pub fn is_rebroadcastable(&self) -> bool {
+        !matches!(
+            self,
+            HTLCClientError::RpcError(
+                RpcClientError::AlreadyBroadcast { .. }
+                    | RpcClientError::MissingInputs(_)
+                    | RpcClientError::AbsurdFee(_)
+                    | RpcClientError::TransactionExpired(_)
+            )
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::MemoryStorage;
+    use crate::test_utils::{test_htlc, test_operation, MockServer};
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn test_invalid_tls_is_a_config_error() {
+        let pem = std::env::temp_dir().join(format!("zcash-ca-{}.pem", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &pem,
+            "-----BEGIN CERTIFICATE-----\nbm90IGEgY2VydA==\n-----END CERTIFICATE-----\n",
+        )
+        .unwrap();
+
+        let mut config = ZcashConfig::new(
+            ZcashNetwork::Testnet,
+            "http://127.0.0.1:1".to_string(),
+            String::new(),
+        );
+        config.rpc_tls = Some(TlsConfig {
+            ca_cert: Some(pem.to_string_lossy().into_owned()),
+            ..TlsConfig::default()
+        });
+        let result = ZcashHTLCClient::try_with_storage(config, Arc::new(MemoryStorage::new()));
+        std::fs::remove_file(&pem).unwrap();
+
+        assert!(matches!(
+            result,
+            Err(HTLCClientError::ConfigError(ConfigError::InvalidConfig(message)))
+                if message.starts_with("rpc_tls")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_output_spent_only_if_funding_is_known() {
+        let storage = Arc::new(MemoryStorage::new());
+        let htlc = ZcashHTLC {
+            txid: Some("ab".repeat(32)),
+            vout: Some(1),
+            state: HTLCState::Locked,
+            ..test_htlc("htlc")
+        };
+        storage
+            .create_htlc_with_operation(
+                &htlc,
+                &test_operation("create", "htlc", HTLCOperationType::Create),
+            )
+            .await
+            .unwrap();
+
+        // gettxout is null throughout; the node learns the funding tx later
+        let known = Arc::new(AtomicBool::new(false));
+        let node = MockServer::start({
+            let known = known.clone();
+            move |request| {
+                let body = match request.json()["method"].as_str() {
+                    Some("getrawtransaction") if known.load(Ordering::SeqCst) => {
+                        serde_json::json!({"jsonrpc": "2.0", "id": "1", "result": {
+                            "txid": "ab".repeat(32),
+                            "version": 4,
+                            "locktime": 0,
+                            "vin": [],
+                            "vout": [{"value": 1.5, "n": 1, "scriptPubKey": {"hex": "a9", "type": "scripthash"}}],
+                            "confirmations": 3
+                        }})
+                    }
+                    Some("getrawtransaction") => serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": "1",
+                        "result": null,
+                        "error": {"code": rpc::RPC_INVALID_ADDRESS_OR_KEY, "message": "No such mempool or blockchain transaction"}
+                    }),
+                    _ => serde_json::json!({"jsonrpc": "2.0", "id": "1", "result": null}),
+                };
+                ("200 OK", body.to_string())
+            }
+        })
+        .await;
+
+        let config = ZcashConfig::new(ZcashNetwork::Testnet, node.url.clone(), String::new());
+        let client = ZcashHTLCClient::try_with_storage(config, storage).unwrap();
+
+        let result = client.is_htlc_output_spent("htlc").await;
+        assert!(
+            matches!(result, Err(HTLCClientError::FundingOutputUnknown(_))),
+            "{:?}",
+            result
+        );
+        known.store(true, Ordering::SeqCst);
+        assert!(client.is_htlc_output_spent("htlc").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_track_confirmations_rotates_and_fails_dropped_transactions() {
+        let storage = Arc::new(MemoryStorage::new());
+        let htlc = ZcashHTLC {
+            txid: Some("ab".repeat(32)),
+            vout: Some(0),
+            state: HTLCState::Locked,
+            ..test_htlc("htlc")
+        };
+        storage
+            .create_htlc_with_operation(
+                &htlc,
+                &test_operation("create", "htlc", HTLCOperationType::Create),
+            )
+            .await
+            .unwrap();
+        // Oldest first: one stuck in the mempool, one confirmed, one dropped
+        for (minutes, (id, txid)) in [("stuck", "aa"), ("mined", "bb"), ("dropped", "cc")]
+            .into_iter()
+            .enumerate()
+        {
+            storage
+                .create_operation(&HTLCOperation {
+                    txid: Some(txid.repeat(32)),
+                    status: OperationStatus::Broadcast,
+                    broadcast_at: Some(Utc::now() - chrono::Duration::minutes(10 - minutes as i64)),
+                    ..test_operation(id, "htlc", HTLCOperationType::Redeem)
+                })
+                .await
+                .unwrap();
+        }
+
+        let node = MockServer::start(|request| {
+            let request = request.json();
+            let txid = request["params"][0].as_str().unwrap_or_default();
+            let body = match request["method"].as_str() {
+                Some("getblockcount") => serde_json::json!({"jsonrpc": "2.0", "id": "1", "result": 100}),
+                Some("getrawtransaction") if txid.starts_with("cc") => serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": "1",
+                    "result": null,
+                    "error": {"code": rpc::RPC_INVALID_ADDRESS_OR_KEY, "message": "No such mempool or blockchain transaction"}
+                }),
+                Some("getrawtransaction") => {
+                    let confirmations = if txid.starts_with("bb") { 2 } else { 0 };
+                    serde_json::json!({"jsonrpc": "2.0", "id": "1", "result": {
+                        "txid": txid,
+                        "version": 4,
+                        "locktime": 0,
+                        "vin": [],
+                        "vout": [],
+                        "confirmations": confirmations
+                    }})
+                }
+                _ => serde_json::json!({"jsonrpc": "2.0", "id": "1", "result": null}),
+            };
+            ("200 OK", body.to_string())
+        })
+        .await;
+        let config = ZcashConfig::new(ZcashNetwork::Testnet, node.url.clone(), String::new());
+        let client = ZcashHTLCClient::try_with_storage(config, storage.clone()).unwrap();
+
+        // The stuck transaction does not take the only slot on every call
+        let first = client.track_confirmations(1, 1).await.unwrap();
+        assert!(first.confirmed.is_empty());
+        let second = client.track_confirmations(1, 1).await.unwrap();
+        assert_eq!(second.confirmed, vec!["mined".to_string()]);
+        assert_eq!(second.locked, Vec::<String>::new());
+        assert_eq!(second.tip, 100);
+
+        let third = client.track_confirmations(1, 1).await.unwrap();
+        assert_eq!(third.dropped, vec!["dropped".to_string()]);
+        let dropped = storage
+            .operations()
+            .into_iter()
+            .find(|operation| operation.id == "dropped")
+            .unwrap();
+        assert_eq!(dropped.status, OperationStatus::Failed);
+        assert!(dropped.next_retry_at.is_some());
+
+        let unconfirmed = storage.get_unconfirmed_operations(10).await.unwrap();
+        let ids: Vec<&str> = unconfirmed
+            .iter()
+            .map(|operation| operation.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["stuck"]);
+    }
+
+    #[tokio::test]
+    async fn test_retried_redeem_records_its_secret() {
+        use bitcoin::blockdata::{opcodes, script::Builder};
+        use sha2::{Digest, Sha256};
+
+        let secret = "5e".repeat(32);
+        let redeem_script = Builder::new()
+            .push_opcode(opcodes::all::OP_SHA256)
+            .into_script();
+        let script_sig = Builder::new()
+            .push_slice(&[0x30; 71])
+            .push_slice(&hex::decode(&secret).unwrap())
+            .push_opcode(opcodes::OP_TRUE)
+            .push_slice(redeem_script.as_bytes())
+            .into_script();
+        let tx = bitcoin::Transaction {
+            version: 4,
+            lock_time: bitcoin::PackedLockTime(0),
+            input: vec![bitcoin::TxIn {
+                script_sig,
+                ..Default::default()
+            }],
+            output: vec![],
+        };
+        let tx_hex = hex::encode(ZcashTransaction::new(tx, 0).serialize());
+
+        let storage = Arc::new(MemoryStorage::new());
+        let htlc = ZcashHTLC {
+            txid: Some("ab".repeat(32)),
+            vout: Some(0),
+            state: HTLCState::Locked,
+            hash_lock: hex::encode(Sha256::digest(hex::decode(&secret).unwrap())),
+            redeem_script_hex: hex::encode(redeem_script.as_bytes()),
+            ..test_htlc("htlc")
+        };
+        storage
+            .create_htlc_with_operation(
+                &htlc,
+                &test_operation("create", "htlc", HTLCOperationType::Create),
+            )
+            .await
+            .unwrap();
+        let redeem = HTLCOperation {
+            signed_tx_hex: Some(tx_hex),
+            status: OperationStatus::Failed,
+            ..test_operation("redeem", "htlc", HTLCOperationType::Redeem)
+        };
+        storage.create_operation(&redeem).await.unwrap();
+
+        let node = MockServer::respond(
+            "200 OK",
+            serde_json::json!({"jsonrpc": "2.0", "id": "1", "result": "cd".repeat(32)}),
+        )
+        .await;
+        let config = ZcashConfig::new(ZcashNetwork::Testnet, node.url.clone(), String::new());
+        let client = ZcashHTLCClient::try_with_storage(config, storage.clone()).unwrap();
+
+        assert_eq!(
+            client.retry_operation(&redeem).await.unwrap(),
+            "cd".repeat(32)
+        );
+        let redeemed = storage.get_htlc_by_id("htlc").await.unwrap();
+        assert_eq!(redeemed.state, HTLCState::Redeemed);
+        assert_eq!(redeemed.secret.as_deref(), Some(secret.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_retried_replacement_refund_supersedes_the_original() {
+        let storage = Arc::new(MemoryStorage::new());
+        let htlc = ZcashHTLC {
+            txid: Some("ab".repeat(32)),
+            vout: Some(0),
+            state: HTLCState::Refunded,
+            ..test_htlc("htlc")
+        };
+        storage
+            .create_htlc_with_operation(
+                &htlc,
+                &test_operation("create", "htlc", HTLCOperationType::Create),
+            )
+            .await
+            .unwrap();
+        let original = HTLCOperation {
+            txid: Some("01".repeat(32)),
+            status: OperationStatus::Broadcast,
+            ..test_operation("refund", "htlc", HTLCOperationType::Refund)
+        };
+        let replacement = HTLCOperation {
+            signed_tx_hex: Some("00".to_string()),
+            status: OperationStatus::Failed,
+            bumps_operation_id: Some("refund".to_string()),
+            ..test_operation("replacement", "htlc", HTLCOperationType::Refund)
+        };
+        storage.create_operation(&original).await.unwrap();
+        storage.create_operation(&replacement).await.unwrap();
+
+        let node = MockServer::respond(
+            "200 OK",
+            serde_json::json!({"jsonrpc": "2.0", "id": "1", "result": "cd".repeat(32)}),
+        )
+        .await;
+        let config = ZcashConfig::new(ZcashNetwork::Testnet, node.url.clone(), String::new());
+        let client = ZcashHTLCClient::try_with_storage(config, storage.clone()).unwrap();
+
+        assert_eq!(
+            client.retry_operation(&replacement).await.unwrap(),
+            "cd".repeat(32)
+        );
+        let operations = storage.operations();
+        let status = |id: &str| {
+            operations
+                .iter()
+                .find(|operation| operation.id == id)
+                .map(|operation| (operation.status, operation.next_retry_at))
+                .unwrap()
+        };
+        assert_eq!(status("refund"), (OperationStatus::Failed, None));
+        assert_eq!(status("replacement").0, OperationStatus::Broadcast);
+        assert_eq!(
+            storage.get_htlc_by_id("htlc").await.unwrap().state,
+            HTLCState::Refunded
+        );
+    }
+
+    #[test]
+    fn test_only_transient_broadcast_failures_are_rebroadcast() {
+        let rejected = |error: RpcClientError| HTLCClientError::RpcError(error);
+
+        assert!(rejected(RpcClientError::NetworkError("reset".to_string())).is_rebroadcastable());
+        assert!(
+            rejected(RpcClientError::MempoolConflict("conflict".to_string())).is_rebroadcastable()
+        );
+        assert!(
+            !rejected(RpcClientError::TransactionExpired("tx-expired".to_string()))
+                .is_rebroadcastable()
+        );
+        assert!(!rejected(RpcClientError::MissingInputs("spent".to_string())).is_rebroadcastable());
+        assert!(!rejected(RpcClientError::AbsurdFee("fee".to_string())).is_rebroadcastable());
+    }
+
+    #[test]
+    fn test_failure_kinds_follow_the_cause() {
+        let rpc = |error: RpcClientError| HTLCClientError::RpcError(error).failure_kind();
+
+        // The node refused the transaction, or could not be reached
+        assert_eq!(
+            rpc(RpcClientError::MissingInputs("spent".to_string())),
+            FailureKind::Broadcast
+        );
+        assert_eq!(
+            rpc(RpcClientError::TransactionExpired("tx-expired".to_string())),
+            FailureKind::Broadcast
+        );
+        assert_eq!(
+            HTLCClientError::AddressRejected("t1".to_string()).failure_kind(),
+            FailureKind::Broadcast
+        );
+        assert_eq!(
+            rpc(RpcClientError::NetworkError("reset".to_string())),
+            FailureKind::Node
+        );
+        assert_eq!(
+            rpc(RpcClientError::RequestTimeout("getblockcount".to_string())),
+            FailureKind::Node
+        );
+        assert_eq!(rpc(RpcClientError::HttpStatus(503)), FailureKind::Node);
+
+        assert_eq!(
+            HTLCClientError::TxBuilderError(TxBuilderError::InsufficientFunds {
+                required: 2,
+                available: 1,
+            })
+            .failure_kind(),
+            FailureKind::Funding
+        );
+        assert_eq!(
+            HTLCClientError::SignerError(SignerError::InvalidPrivateKey).failure_kind(),
+            FailureKind::Signing
+        );
+        assert_eq!(
+            HTLCClientError::DatabaseError(DatabaseError::HTLCNotFound("htlc".to_string()))
+                .failure_kind(),
+            FailureKind::Database
+        );
+        assert_eq!(
+            HTLCClientError::HTLCNotLocked.failure_kind(),
+            FailureKind::Other
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reorg_reverts_only_on_a_definite_answer() {
+        let storage = Arc::new(MemoryStorage::new());
+        let htlc = ZcashHTLC {
+            txid: Some("ab".repeat(32)),
+            vout: Some(0),
+            state: HTLCState::Locked,
+            ..test_htlc("htlc")
+        };
+        let funding = HTLCOperation {
+            txid: Some("ab".repeat(32)),
+            status: OperationStatus::Confirmed,
+            block_height: Some(101),
+            ..test_operation("create", "htlc", HTLCOperationType::Create)
+        };
+        storage
+            .create_htlc_with_operation(&htlc, &funding)
+            .await
+            .unwrap();
+        let recorded: Vec<_> = (100..=101)
+            .map(|height| (height, format!("old{}", height)))
+            .collect();
+        storage
+            .record_block_hashes("testnet", &recorded, 0)
+            .await
+            .unwrap();
+
+        // Block 101 was replaced; the node fails to look up the funding tx
+        // until it answers that it does not know it
+        let definite = Arc::new(AtomicBool::new(false));
+        let node = MockServer::start({
+            let definite = definite.clone();
+            move |request| {
+                let request = request.json();
+                let result = match request["method"].as_str() {
+                    Some("getblockcount") => serde_json::json!(101),
+                    Some("getblockhash") => match request["params"][0].as_u64() {
+                        Some(100) => serde_json::json!("old100"),
+                        height => serde_json::json!(format!("new{}", height.unwrap_or(0))),
+                    },
+                    Some("getrawtransaction") => {
+                        let code = match definite.load(Ordering::SeqCst) {
+                            true => rpc::RPC_INVALID_ADDRESS_OR_KEY,
+                            false => -1,
+                        };
+                        let error = serde_json::json!({
+                            "jsonrpc": "2.0",
+                            "id": "1",
+                            "result": null,
+                            "error": {"code": code, "message": "lookup failed"}
+                        });
+                        return ("200 OK", error.to_string());
+                    }
+                    _ => serde_json::Value::Null,
+                };
+                let body = serde_json::json!({"jsonrpc": "2.0", "id": "1", "result": result});
+                ("200 OK", body.to_string())
+            }
+        })
+        .await;
+
+        let config = ZcashConfig::new(ZcashNetwork::Testnet, node.url.clone(), String::new());
+        let client = ZcashHTLCClient::try_with_storage(config, storage.clone()).unwrap();
+
+        assert!(client.track_chain_tip().await.is_err());
+        assert_eq!(storage.operations()[0].status, OperationStatus::Confirmed);
+        assert_eq!(storage.htlcs()[0].state, HTLCState::Locked);
+
+        definite.store(true, Ordering::SeqCst);
+        let report = client.track_chain_tip().await.unwrap().unwrap();
+        assert_eq!(report.fork_height, 101);
+        assert_eq!(report.reverted, vec!["create".to_string()]);
+        assert_eq!(storage.operations()[0].status, OperationStatus::Broadcast);
+        assert_eq!(storage.htlcs()[0].state, HTLCState::Pending);
+        assert!(client.track_chain_tip().await.unwrap().is_none());
+    }
 }
@@ -1,6 +1,91 @@
 pub mod schema;
+use bitcoin::blockdata::script::Script;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use zeroize::Zeroizing;
+
+use crate::script::HTLCScriptBuilder;
+
+/// Stand-in for secret fields in `Debug` output
+pub(crate) const REDACTED: &str = "<redacted>";
+
+/// `url` without user info, query or fragment, which may carry
+/// credentials; [`REDACTED`] if it does not parse
+pub(crate) fn redact_url(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(mut url) => {
+            let _ = url.set_username("");
+            let _ = url.set_password(None);
+            url.set_query(None);
+            url.set_fragment(None);
+            url.to_string()
+        }
+        Err(_) => REDACTED.to_string(),
+    }
+}
+
+/// Hex-encoded HTLC preimage, wiped from memory when dropped and redacted
+/// from `Debug` output. Serializes as the plain hex string.
+#[derive(Clone, PartialEq, Eq)]
+pub struct HTLCSecret(Zeroizing<String>);
+
+impl HTLCSecret {
+    pub fn new(hex: String) -> Self {
+        Self(Zeroizing::new(hex))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::ops::Deref for HTLCSecret {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for HTLCSecret {
+    fn from(hex: String) -> Self {
+        Self::new(hex)
+    }
+}
+
+impl From<&str> for HTLCSecret {
+    fn from(hex: &str) -> Self {
+        Self::new(hex.to_string())
+    }
+}
+
+impl std::fmt::Debug for HTLCSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl Serialize for HTLCSecret {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for HTLCSecret {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+/// A string naming none of an enum's variants
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Unknown {kind}: {value}")]
+pub struct ParseVariantError {
+    pub kind: &'static str,
+    pub value: String,
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(i16)]
@@ -11,6 +96,8 @@ pub enum HTLCState {
     Refunded = 3,
     Expired = 4,
     Failed = 5,
+    /// Creation failed too often; waits for an operator to requeue it
+    NeedsIntervention = 6,
 }
 
 impl HTLCState {
@@ -22,6 +109,7 @@ impl HTLCState {
             3 => HTLCState::Refunded,
             4 => HTLCState::Expired,
             5 => HTLCState::Failed,
+            6 => HTLCState::NeedsIntervention,
             _ => HTLCState::Pending,
         }
     }
@@ -34,6 +122,40 @@ impl HTLCState {
             HTLCState::Refunded => "refunded",
             HTLCState::Expired => "expired",
             HTLCState::Failed => "failed",
+            HTLCState::NeedsIntervention => "needs_intervention",
+        }
+    }
+
+    /// Whether an HTLC may move from this state to `next`. Redeemed and
+    /// Refunded are final; staying in the same state is always allowed.
+    pub fn can_transition_to(self, next: HTLCState) -> bool {
+        use HTLCState::*;
+
+        self == next
+            || matches!(
+                (self, next),
+                (Pending, Locked | Expired | Failed | NeedsIntervention)
+                    | (Locked, Redeemed | Refunded | Expired)
+                    // A redeem can still land until the refund does
+                    | (Expired, Redeemed | Refunded)
+                    // Funding that was given up on may confirm after all
+                    | (Failed, Pending | Locked)
+                    // Requeued or given up on by an operator
+                    | (NeedsIntervention, Pending | Locked | Failed)
+            )
+    }
+
+    /// State an HTLC in this state returns to when a confirmed `undone`
+    /// operation is reorganized out of the chain, if that operation moved
+    /// it here: a funding back to Pending, a redeem or refund back to
+    /// Locked. Not a regular transition: [`can_transition_to`](Self::can_transition_to)
+    /// never moves an HTLC back.
+    pub fn reorg_revert(self, undone: &HTLCOperationType) -> Option<HTLCState> {
+        match (undone, self) {
+            (HTLCOperationType::Create, HTLCState::Locked) => Some(HTLCState::Pending),
+            (HTLCOperationType::Redeem, HTLCState::Redeemed)
+            | (HTLCOperationType::Refund, HTLCState::Refunded) => Some(HTLCState::Locked),
+            _ => None,
         }
     }
 }
@@ -43,6 +165,8 @@ pub enum HTLCOperationType {
     Create,
     Redeem,
     Refund,
+    /// Child transaction speeding up a stuck parent operation
+    FeeBump,
 }
 
 impl HTLCOperationType {
@@ -51,27 +175,47 @@ impl HTLCOperationType {
             HTLCOperationType::Create => "create",
             HTLCOperationType::Redeem => "redeem",
             HTLCOperationType::Refund => "refund",
+            HTLCOperationType::FeeBump => "fee_bump",
+        }
+    }
+
+    /// Final state of an HTLC whose output this operation spends
+    pub fn settles_to(&self) -> Option<HTLCState> {
+        match self {
+            HTLCOperationType::Redeem => Some(HTLCState::Redeemed),
+            HTLCOperationType::Refund => Some(HTLCState::Refunded),
+            HTLCOperationType::Create | HTLCOperationType::FeeBump => None,
         }
     }
+}
 
-    pub fn from_str(s: &str) -> Self {
+impl FromStr for HTLCOperationType {
+    type Err = ParseVariantError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            "create" => HTLCOperationType::Create,
-            "redeem" => HTLCOperationType::Redeem,
-            "refund" => HTLCOperationType::Refund,
-            _ => HTLCOperationType::Create,
+            "create" => Ok(HTLCOperationType::Create),
+            "redeem" => Ok(HTLCOperationType::Redeem),
+            "refund" => Ok(HTLCOperationType::Refund),
+            "fee_bump" => Ok(HTLCOperationType::FeeBump),
+            _ => Err(ParseVariantError {
+                kind: "operation type",
+                value: s.to_string(),
+            }),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ZcashHTLC {
     pub id: String,
     pub txid: Option<String>,
     pub p2sh_address: String,
     pub hash_lock: String,
-    pub secret: Option<String>,
+    pub secret: Option<HTLCSecret>,
     pub timelock: u64,
+    #[serde(default)]
+    pub timelock_kind: TimelockKind,
     pub recipient_pubkey: String,
     pub refund_pubkey: String,
     pub amount: String,
@@ -80,9 +224,112 @@ pub struct ZcashHTLC {
     pub vout: Option<u32>,
     pub script_hex: String,
     pub redeem_script_hex: String,
+    /// Address redeem and refund transactions pay out to, once known; see
+    /// [`Database::update_htlc_recipient`](crate::database::Database::update_htlc_recipient)
+    #[serde(default)]
+    pub recipient_address: Option<String>,
     pub signed_redeem_tx: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Bumped by every update; pass it to
+    /// [`Database::update_htlc_state_if_version`](crate::database::Database::update_htlc_state_if_version)
+    /// to detect concurrent changes
+    #[serde(default)]
+    pub version: u32,
+    /// Integrator data such as order ids or counterparty chain references;
+    /// find HTLCs by it with
+    /// [`Database::get_htlcs_by_metadata`](crate::database::Database::get_htlcs_by_metadata)
+    #[serde(default)]
+    pub metadata: Option<serde_json::Value>,
+    /// Set by
+    /// [`Database::soft_delete_htlc`](crate::database::Database::soft_delete_htlc),
+    /// which hides the HTLC from everything but admin queries
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Funding order and fee rate of the HTLC's transactions
+    #[serde(default)]
+    pub priority: FeePriority,
+}
+
+impl ZcashHTLC {
+    /// Whether this contract, as built, funds `queued`: a pending, unfunded
+    /// HTLC with the same hash lock, network, recipient, timelock and
+    /// amount. The refund key may differ, as the funder picks its own.
+    pub fn fills(&self, queued: &ZcashHTLC) -> bool {
+        queued.state == HTLCState::Pending
+            && queued.txid.is_none()
+            && queued.hash_lock == self.hash_lock
+            && queued.network == self.network
+            && queued.recipient_pubkey == self.recipient_pubkey
+            && queued.timelock == self.timelock
+            && zec_to_zatoshis(&queued.amount).is_some()
+            && zec_to_zatoshis(&queued.amount) == zec_to_zatoshis(&self.amount)
+    }
+}
+
+impl std::fmt::Debug for ZcashHTLC {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ZcashHTLC")
+            .field("id", &self.id)
+            .field("txid", &self.txid)
+            .field("p2sh_address", &self.p2sh_address)
+            .field("hash_lock", &self.hash_lock)
+            .field("secret", &self.secret)
+            .field("timelock", &self.timelock)
+            .field("timelock_kind", &self.timelock_kind)
+            .field("recipient_pubkey", &self.recipient_pubkey)
+            .field("refund_pubkey", &self.refund_pubkey)
+            .field("amount", &self.amount)
+            .field("network", &self.network)
+            .field("state", &self.state)
+            .field("vout", &self.vout)
+            .field("script_hex", &self.script_hex)
+            .field("redeem_script_hex", &self.redeem_script_hex)
+            .field("recipient_address", &self.recipient_address)
+            .field(
+                "signed_redeem_tx",
+                &self.signed_redeem_tx.as_ref().map(|_| REDACTED),
+            )
+            .field("created_at", &self.created_at)
+            .field("updated_at", &self.updated_at)
+            .field("version", &self.version)
+            .field("metadata", &self.metadata)
+            .field("deleted_at", &self.deleted_at)
+            .finish()
+    }
+}
+
+/// One change to an HTLC, as kept in its audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HTLCAuditEntry {
+    pub id: i64,
+    pub htlc_id: String,
+    /// `None` for the creation of the HTLC
+    pub old_state: Option<HTLCState>,
+    pub new_state: HTLCState,
+    /// Who made the change, see [`Database::with_actor`](crate::database::Database::with_actor)
+    pub actor: String,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Notification of an HTLC state change, queued in the outbox in the same
+/// transaction as the change itself, or of a hot wallet alert
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HTLCEvent {
+    pub id: i64,
+    /// `None` for hot wallet alerts
+    pub htlc_id: Option<String>,
+    /// `htlc.created`, or `htlc.<new state>` for a state change such as
+    /// `htlc.locked`, `htlc.redeemed`, `htlc.refunded` or `htlc.failed`, or
+    /// `htlc.secret_revealed` when a watched redeem reveals the preimage.
+    /// Hot wallet alerts are `wallet.low_funds` and `wallet.funds_restored`,
+    /// relayer alerts `relayer.circuit_open` and `relayer.circuit_closed`.
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    /// Failed delivery attempts so far
+    pub attempts: u32,
+    pub created_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -100,6 +347,33 @@ pub struct HTLCOperation {
     pub error_message: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Failed broadcast attempts so far
+    #[serde(default)]
+    pub attempt_count: u32,
+    #[serde(default)]
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    /// When a failed operation is due for another broadcast; `None` once
+    /// its transaction can never be broadcast as it is
+    #[serde(default)]
+    pub next_retry_at: Option<DateTime<Utc>>,
+    /// Fee paid by the signed transaction, in zatoshis
+    #[serde(default)]
+    pub fee_zat: Option<u64>,
+    /// Serialized size of the signed transaction in bytes
+    #[serde(default)]
+    pub tx_size: Option<u32>,
+    /// Fee rate in zatoshis per 1000 bytes
+    #[serde(default)]
+    pub fee_rate: Option<u64>,
+    /// Chain height when the relayer first saw the transaction unconfirmed
+    #[serde(default)]
+    pub broadcast_height: Option<u64>,
+    /// Operation whose stuck transaction this `FeeBump` child pays for
+    #[serde(default)]
+    pub bumps_operation_id: Option<String>,
+    /// What the last failed attempt failed at, alongside `error_message`
+    #[serde(default)]
+    pub error_kind: Option<FailureKind>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -121,15 +395,196 @@ impl OperationStatus {
             OperationStatus::Failed => "failed",
         }
     }
+}
+
+impl FromStr for OperationStatus {
+    type Err = ParseVariantError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(OperationStatus::Pending),
+            "signed" => Ok(OperationStatus::Signed),
+            "broadcast" => Ok(OperationStatus::Broadcast),
+            "confirmed" => Ok(OperationStatus::Confirmed),
+            "failed" => Ok(OperationStatus::Failed),
+            _ => Err(ParseVariantError {
+                kind: "operation status",
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+/// Stage a transaction failed at, recorded with the error so failures can
+/// be broken down by cause
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    /// Building the transaction: funds, amounts, fees
+    Funding,
+    /// Scripts, keys and signatures
+    Signing,
+    /// The node or explorer rejected the transaction
+    Broadcast,
+    Database,
+    /// The node or explorer was unreachable or misbehaved
+    Node,
+    /// Anything else, such as an HTLC not ready for the operation
+    Other,
+}
+
+impl FailureKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureKind::Funding => "funding",
+            FailureKind::Signing => "signing",
+            FailureKind::Broadcast => "broadcast",
+            FailureKind::Database => "database",
+            FailureKind::Node => "node",
+            FailureKind::Other => "other",
+        }
+    }
+}
+
+impl FromStr for FailureKind {
+    type Err = ParseVariantError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "funding" => Ok(FailureKind::Funding),
+            "signing" => Ok(FailureKind::Signing),
+            "broadcast" => Ok(FailureKind::Broadcast),
+            "database" => Ok(FailureKind::Database),
+            "node" => Ok(FailureKind::Node),
+            "other" => Ok(FailureKind::Other),
+            _ => Err(ParseVariantError {
+                kind: "failure kind",
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+/// Delay before the first retry of a failed operation
+const OPERATION_RETRY_BASE_SECS: i64 = 30;
+/// Longest delay between retries of a failed operation
+const OPERATION_RETRY_MAX_SECS: i64 = 3600;
+/// Delay before the first retry of a failed event delivery
+const EVENT_RETRY_BASE_SECS: i64 = 5;
+/// Longest delay between retries of a failed event delivery
+const EVENT_RETRY_MAX_SECS: i64 = 900;
+
+/// Delay before retrying an operation that has failed `attempts` times,
+/// doubling with each attempt up to an hour
+pub fn operation_retry_delay(attempts: u32) -> chrono::Duration {
+    backoff(
+        attempts,
+        OPERATION_RETRY_BASE_SECS,
+        OPERATION_RETRY_MAX_SECS,
+    )
+}
+
+/// Delay before delivering an event again to a sink it has failed to
+/// reach `attempts` times, doubling with each attempt up to 15 minutes
+pub fn event_retry_delay(attempts: u32) -> chrono::Duration {
+    backoff(attempts, EVENT_RETRY_BASE_SECS, EVENT_RETRY_MAX_SECS)
+}
+
+fn backoff(attempts: u32, base_secs: i64, max_secs: i64) -> chrono::Duration {
+    let exponent = attempts.saturating_sub(1).min(16);
+    chrono::Duration::seconds(base_secs.saturating_mul(1 << exponent).min(max_secs))
+}
+
+/// Lock times from here on are Unix timestamps, below it block heights
+pub const LOCKTIME_THRESHOLD: u64 = 500_000_000;
+
+/// What an HTLC's `timelock` counts: block height or Unix time, the latter
+/// checked against the median time past of the last 11 blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TimelockKind {
+    #[default]
+    BlockHeight,
+    Timestamp,
+}
+
+impl TimelockKind {
+    /// Kind of `timelock` as `OP_CHECKLOCKTIMEVERIFY` interprets it
+    pub fn for_timelock(timelock: u64) -> Self {
+        if timelock < LOCKTIME_THRESHOLD {
+            TimelockKind::BlockHeight
+        } else {
+            TimelockKind::Timestamp
+        }
+    }
 
-    pub fn from_str(s: &str) -> Self {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimelockKind::BlockHeight => "height",
+            TimelockKind::Timestamp => "timestamp",
+        }
+    }
+}
+
+impl FromStr for TimelockKind {
+    type Err = ParseVariantError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            "pending" => OperationStatus::Pending,
-            "signed" => OperationStatus::Signed,
-            "broadcast" => OperationStatus::Broadcast,
-            "confirmed" => OperationStatus::Confirmed,
-            "failed" => OperationStatus::Failed,
-            _ => OperationStatus::Pending,
+            "height" => Ok(TimelockKind::BlockHeight),
+            "timestamp" => Ok(TimelockKind::Timestamp),
+            _ => Err(ParseVariantError {
+                kind: "timelock kind",
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+/// How fast an HTLC's transactions should confirm. Higher priorities are
+/// funded first and pay the higher rates of [`FeePriorityConfig`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeePriority {
+    Economy = 0,
+    #[default]
+    Normal = 1,
+    Urgent = 2,
+}
+
+impl FeePriority {
+    pub fn from_i16(value: i16) -> Self {
+        match value {
+            0 => FeePriority::Economy,
+            2 => FeePriority::Urgent,
+            _ => FeePriority::Normal,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FeePriority::Economy => "economy",
+            FeePriority::Normal => "normal",
+            FeePriority::Urgent => "urgent",
+        }
+    }
+}
+
+/// Fee rates, in zatoshis per 1000 bytes, paid by the transactions of HTLCs
+/// of each priority. A priority without a rate is priced by the client's
+/// `fee_mode`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeePriorityConfig {
+    pub economy: Option<u64>,
+    pub normal: Option<u64>,
+    pub urgent: Option<u64>,
+}
+
+impl FeePriorityConfig {
+    pub fn rate(&self, priority: FeePriority) -> Option<u64> {
+        match priority {
+            FeePriority::Economy => self.economy,
+            FeePriority::Normal => self.normal,
+            FeePriority::Urgent => self.urgent,
         }
     }
 }
@@ -148,11 +603,12 @@ impl ZcashNetwork {
         }
     }
 
-    pub fn from_str(s: &str) -> Self {
-        match s {
-            "mainnet" => ZcashNetwork::Mainnet,
-            "testnet" => ZcashNetwork::Testnet,
-            _ => ZcashNetwork::Testnet,
+    /// Whether `chain`, as reported by `getblockchaininfo`, belongs to this
+    /// network. Regtest shares testnet address prefixes.
+    pub fn matches_node_chain(&self, chain: &str) -> bool {
+        match self {
+            ZcashNetwork::Mainnet => chain == "main",
+            ZcashNetwork::Testnet => chain == "test" || chain == "regtest",
         }
     }
 
@@ -170,6 +626,14 @@ impl ZcashNetwork {
         }
     }
 
+    /// Version byte of WIF private keys, shared with Bitcoin
+    pub fn wif_prefix(&self) -> u8 {
+        match self {
+            ZcashNetwork::Mainnet => 0x80,
+            ZcashNetwork::Testnet => 0xEF,
+        }
+    }
+
     pub fn to_bitcoin_network(&self) -> bitcoin::Network {
         match self {
             ZcashNetwork::Mainnet => bitcoin::Network::Bitcoin,
@@ -178,6 +642,21 @@ impl ZcashNetwork {
     }
 }
 
+impl FromStr for ZcashNetwork {
+    type Err = ParseVariantError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mainnet" => Ok(ZcashNetwork::Mainnet),
+            "testnet" => Ok(ZcashNetwork::Testnet),
+            _ => Err(ParseVariantError {
+                kind: "network",
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
 // ==================== HTLC Parameters ====================
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -187,6 +666,17 @@ pub struct HTLCParams {
     pub hash_lock: String,
     pub timelock: u64,
     pub amount: String,
+    /// Stored with the HTLC, see [`ZcashHTLC::metadata`]
+    #[serde(default)]
+    pub metadata: Option<serde_json::Value>,
+    /// See [`ZcashHTLC::priority`]
+    #[serde(default)]
+    pub priority: FeePriority,
+    /// Id of the pending HTLC queued with `Database::create_htlc` that this
+    /// contract funds; its record is filled in if the other parameters
+    /// match. A new HTLC if unset.
+    #[serde(default)]
+    pub htlc_id: Option<String>,
 }
 
 // ==================== UTXO Model ====================
@@ -200,6 +690,45 @@ pub struct UTXO {
     pub confirmations: u32,
 }
 
+const ZATOSHIS_PER_ZEC: u64 = 100_000_000;
+
+/// Convert a decimal ZEC amount string such as `1.5` or `0.00000001` to
+/// zatoshis, exactly. `None` for signs, exponents, more than 8 decimals or
+/// amounts that overflow.
+pub fn zec_to_zatoshis(amount: &str) -> Option<u64> {
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    if (whole.is_empty() && fraction.is_empty())
+        || fraction.len() > 8
+        || !whole
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let whole = if whole.is_empty() {
+        0
+    } else {
+        whole.parse::<u64>().ok()?
+    };
+    let fraction = if fraction.is_empty() {
+        0
+    } else {
+        format!("{:0<8}", fraction).parse::<u64>().ok()?
+    };
+    whole.checked_mul(ZATOSHIS_PER_ZEC)?.checked_add(fraction)
+}
+
+/// Format zatoshis as a decimal ZEC amount string with 8 decimals
+pub fn zatoshis_to_zec(zatoshis: u64) -> String {
+    format!(
+        "{}.{:08}",
+        zatoshis / ZATOSHIS_PER_ZEC,
+        zatoshis % ZATOSHIS_PER_ZEC
+    )
+}
+
 // ==================== RPC Models ====================
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -224,7 +753,7 @@ pub struct RpcError {
     pub message: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct RawTransaction {
     pub txid: String,
     pub version: u32,
@@ -236,20 +765,23 @@ pub struct RawTransaction {
     pub blocktime: Option<u64>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct TxInput {
+    /// Empty for coinbase inputs
+    #[serde(default)]
     pub txid: String,
+    #[serde(default)]
     pub vout: u32,
     #[serde(rename = "scriptSig")]
     pub script_sig: Option<ScriptSig>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ScriptSig {
     pub hex: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct TxOutput {
     pub value: f64,
     pub n: u32,
@@ -257,7 +789,7 @@ pub struct TxOutput {
     pub script_pubkey: ScriptPubKey,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ScriptPubKey {
     pub hex: String,
     #[serde(rename = "type")]
@@ -265,6 +797,182 @@ pub struct ScriptPubKey {
     pub addresses: Option<Vec<String>>,
 }
 
+/// `getblockchaininfo` result
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BlockchainInfo {
+    pub chain: String,
+    pub blocks: u64,
+    pub headers: u64,
+    pub bestblockhash: String,
+    pub verificationprogress: f64,
+    /// Height the node expects the network to be at, from block times
+    #[serde(default)]
+    pub estimatedheight: Option<u64>,
+    #[serde(default)]
+    pub initial_block_download_complete: Option<bool>,
+    #[serde(default)]
+    pub consensus: Option<ConsensusInfo>,
+    /// Median time past of the tip, from nodes that report it
+    #[serde(default)]
+    pub mediantime: Option<u64>,
+}
+
+/// Consensus branch IDs in `getblockchaininfo`, as hex
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConsensusInfo {
+    pub chaintip: String,
+    pub nextblock: String,
+}
+
+impl BlockchainInfo {
+    /// Blocks the node is allowed to trail the best known height by
+    const SYNC_TOLERANCE: u64 = 2;
+
+    /// Best height known to the node, from headers or its estimate
+    pub fn target_height(&self) -> u64 {
+        self.headers.max(self.estimatedheight.unwrap_or(0))
+    }
+
+    /// Consensus branch ID a transaction mined in the next block must be
+    /// signed for
+    pub fn next_block_branch_id(&self) -> Option<u32> {
+        self.consensus
+            .as_ref()
+            .and_then(|consensus| u32::from_str_radix(&consensus.nextblock, 16).ok())
+    }
+
+    /// Whether the node has caught up with the network
+    pub fn is_synced(&self) -> bool {
+        self.initial_block_download_complete != Some(false)
+            && self.blocks + Self::SYNC_TOLERANCE >= self.target_height()
+    }
+}
+
+/// `validateaddress` result
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AddressValidation {
+    pub isvalid: bool,
+    pub address: Option<String>,
+    #[serde(rename = "scriptPubKey")]
+    pub script_pubkey: Option<String>,
+    pub isscript: Option<bool>,
+}
+
+/// `gettxout` result for an output that is still unspent
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TxOutInfo {
+    pub bestblock: String,
+    pub confirmations: u32,
+    pub value: f64,
+    #[serde(rename = "scriptPubKey")]
+    pub script_pubkey: ScriptPubKey,
+    #[serde(default)]
+    pub coinbase: bool,
+}
+
+/// `getaddressutxos` result with `chainInfo`, from a node running with
+/// `-insightexplorer`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AddressUtxos {
+    pub utxos: Vec<AddressUtxo>,
+    /// Tip height the UTXO set was read at
+    pub height: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AddressUtxo {
+    pub address: String,
+    pub txid: String,
+    #[serde(rename = "outputIndex")]
+    pub output_index: u32,
+    pub script: String,
+    pub satoshis: u64,
+    pub height: u64,
+}
+
+/// `getblock` result at verbosity 2, with decoded transactions
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Block {
+    pub hash: String,
+    pub height: u64,
+    pub previousblockhash: Option<String>,
+    pub tx: Vec<RawTransaction>,
+}
+
+/// HTLC output seen by a block scan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HTLCActivity {
+    /// An output paid the HTLC's P2SH address
+    Funded {
+        htlc_id: String,
+        txid: String,
+        vout: u32,
+        value: f64,
+        height: u64,
+    },
+    /// The HTLC output was spent by input `input` of `spending_txid`
+    Spent {
+        htlc_id: String,
+        txid: String,
+        vout: u32,
+        spending_txid: String,
+        input: u32,
+        script_sig_hex: Option<String>,
+        height: u64,
+    },
+}
+
+/// Branch of the HTLC script a spend took, read from its scriptSig
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HTLCSpend {
+    /// The recipient redeemed it, revealing the hex-encoded preimage
+    Redeem { secret: HTLCSecret },
+    /// The refund key reclaimed it after the timelock
+    Refund,
+}
+
+/// Blocks a scan walked and the HTLC activity found in them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanReport {
+    pub from_height: u64,
+    pub to_height: u64,
+    pub activity: Vec<HTLCActivity>,
+}
+
+/// `getblockheader` result
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BlockHeader {
+    pub hash: String,
+    pub height: u64,
+    pub time: u64,
+    pub previousblockhash: Option<String>,
+}
+
+/// `estimatesmartfee` result; `feerate` is missing without enough data
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SmartFeeEstimate {
+    /// ZEC per kB
+    pub feerate: Option<f64>,
+    pub blocks: Option<u32>,
+}
+
+/// Entry of a verbose `getrawmempool`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MempoolEntry {
+    pub size: u64,
+    /// ZEC
+    pub fee: f64,
+}
+
+/// `getaddressbalance` result, in zatoshis
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AddressBalance {
+    pub balance: i64,
+    pub received: i64,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ExplorerUTXO {
     pub txid: String,
@@ -274,6 +982,664 @@ pub struct ExplorerUTXO {
     pub confirmations: Option<u32>,
 }
 
+// ==================== Offline Signing ====================
+
+/// How an input of an unsigned template must be satisfied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TemplateInputType {
+    P2PKH,
+    HTLCRedeem,
+    /// Redeem of an HTLC with an m-of-n recipient branch, satisfied from the
+    /// partial signatures collected in a [`PartiallySignedTx`]
+    HTLCMultisigRedeem,
+    HTLCRefund,
+    /// Output of an m-of-n P2SH multisig wallet, e.g. a relayer hot wallet
+    /// split across operational keys
+    P2SHMultisig,
+}
+
+/// Prevout data an offline signer needs for one input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateInput {
+    pub txid: String,
+    pub vout: u32,
+    pub amount: String,
+    pub script_pubkey: String,
+    pub redeem_script: Option<String>,
+    pub input_type: TemplateInputType,
+}
+
+/// Unsigned transaction bundle exported for signing on an air-gapped machine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnsignedTxTemplate {
+    pub network: ZcashNetwork,
+    pub unsigned_tx_hex: String,
+    pub inputs: Vec<TemplateInput>,
+}
+
+impl UnsignedTxTemplate {
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+/// Signature produced externally for one template input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalSignature {
+    pub input_index: usize,
+    /// DER signature with the sighash type byte appended, hex encoded
+    pub signature: String,
+    /// Required for P2PKH inputs
+    pub pubkey: Option<String>,
+    /// Required for HTLC redeem inputs
+    pub secret: Option<HTLCSecret>,
+}
+
+/// Digest an external signer must sign for one template input
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputSighash {
+    pub input_index: usize,
+    /// Hex-encoded 32-byte ZIP-243 sighash
+    pub sighash: String,
+    pub sighash_type: u32,
+}
+
+/// Raw ECDSA signature over an [`InputSighash`], as produced by MPC or
+/// air-gapped signers that do not emit DER
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RawSignature {
+    pub input_index: usize,
+    /// Hex-encoded 32-byte big-endian r
+    pub r: String,
+    /// Hex-encoded 32-byte big-endian s
+    pub s: String,
+    pub sighash_type: u32,
+    /// Required for P2PKH inputs
+    pub pubkey: Option<String>,
+    /// Required for HTLC redeem inputs
+    pub secret: Option<HTLCSecret>,
+}
+
+/// Per-input signing state of a partially signed transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartialInput {
+    #[serde(flatten)]
+    pub prevout: TemplateInput,
+    /// Hex signatures keyed by the hex public key that produced them
+    pub partial_sigs: BTreeMap<String, String>,
+    pub secret: Option<HTLCSecret>,
+}
+
+/// PSBT-style container passed between funders and signers until every
+/// input carries a signature
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartiallySignedTx {
+    pub network: ZcashNetwork,
+    pub unsigned_tx_hex: String,
+    pub inputs: Vec<PartialInput>,
+}
+
+impl PartiallySignedTx {
+    pub fn from_template(template: UnsignedTxTemplate) -> Self {
+        Self {
+            network: template.network,
+            unsigned_tx_hex: template.unsigned_tx_hex,
+            inputs: template
+                .inputs
+                .into_iter()
+                .map(|prevout| PartialInput {
+                    prevout,
+                    partial_sigs: BTreeMap::new(),
+                    secret: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// True once every input has its signatures (and a secret for redeems)
+    pub fn is_complete(&self) -> bool {
+        let script_builder = HTLCScriptBuilder::new(self.network);
+
+        self.inputs.iter().all(|input| {
+            let signed = match input.prevout.input_type {
+                TemplateInputType::HTLCMultisigRedeem => input
+                    .prevout
+                    .redeem_script
+                    .as_deref()
+                    .and_then(|script| hex::decode(script).ok())
+                    .and_then(|script| script_builder.multisig_branch(&Script::from(script)))
+                    .is_some_and(|(threshold, _)| input.partial_sigs.len() >= threshold),
+                TemplateInputType::P2SHMultisig => input
+                    .prevout
+                    .redeem_script
+                    .as_deref()
+                    .and_then(|script| hex::decode(script).ok())
+                    .and_then(|script| script_builder.multisig_keys(&Script::from(script)))
+                    .is_some_and(|(threshold, _)| input.partial_sigs.len() >= threshold),
+                _ => !input.partial_sigs.is_empty(),
+            };
+            let needs_secret = matches!(
+                input.prevout.input_type,
+                TemplateInputType::HTLCRedeem | TemplateInputType::HTLCMultisigRedeem
+            );
+
+            signed && (!needs_secret || input.secret.is_some())
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+/// Size, sigop and fee figures for a built transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxReport {
+    pub size: usize,
+    pub sigops: usize,
+    pub fee: u64,
+    /// Fee rate in zatoshis per 1000 bytes
+    pub fee_rate: u64,
+}
+
+/// Confirmation count seen while waiting on a transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfirmationProgress {
+    pub txid: String,
+    pub confirmations: u32,
+    pub required_confirmations: u32,
+    pub attempt: u32,
+}
+
+/// Where a fee estimate came from, most reliable first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeConfidence {
+    /// `estimatesmartfee`
+    High,
+    /// `estimatefee`
+    Medium,
+    /// Median fee rate of the mempool, or the minimum rate when it is empty
+    Low,
+}
+
+/// Fee rate for a transaction to confirm within a target number of blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeEstimate {
+    /// Zatoshis per 1000 bytes
+    pub fee_per_kb: u64,
+    pub confidence: FeeConfidence,
+}
+
+/// How the client prices the transactions it builds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum FeeMode {
+    /// The builder's default rate of 1000 zatoshis per kB
+    #[default]
+    Fixed,
+    /// The node's estimate for confirmation within `conf_target` blocks,
+    /// capped at `max_fee_per_kb`
+    Dynamic {
+        conf_target: u32,
+        max_fee_per_kb: Option<u64>,
+    },
+}
+
+// ==================== Remote Signing ====================
+
+/// Retries of transient RPC failures: connection errors, 502/503/504
+/// responses and nodes still warming up. Delays double from `base_delay_ms`
+/// up to `max_delay_ms`, plus up to `jitter` of the delay at random.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total attempts including the first; 1 disables retries
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 500,
+            max_delay_ms: 10_000,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// No retries
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before retry number `retry` (starting at 1)
+    pub fn delay(&self, retry: u32) -> std::time::Duration {
+        let backoff = self
+            .base_delay_ms
+            .saturating_mul(1u64 << retry.saturating_sub(1).min(32))
+            .min(self.max_delay_ms);
+        let jitter = (backoff as f64 * self.jitter.clamp(0.0, 1.0) * rand::random::<f64>()) as u64;
+
+        std::time::Duration::from_millis(backoff + jitter)
+    }
+}
+
+/// Notification pushed by the node, e.g. over ZMQ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeEvent {
+    /// A block was connected to the active chain
+    Block { hash: String },
+    /// A transaction entered the mempool or a block
+    Transaction { tx_hex: String },
+    /// Blocks from `fork_height` up were replaced by another branch
+    ReorgDetected { fork_height: u64 },
+}
+
+/// Outcome of a reorg: confirmed operations from `fork_height` up were
+/// re-checked against the new chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReorgReport {
+    pub fork_height: u64,
+    pub tip: u64,
+    /// Operations whose transaction is confirmed again on the new branch
+    pub reconfirmed: Vec<String>,
+    /// Operations whose transaction is no longer in the chain, back to broadcast
+    pub reverted: Vec<String>,
+}
+
+/// Operations [`ZcashHTLCClient::track_confirmations`](crate::ZcashHTLCClient::track_confirmations)
+/// found confirmed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConfirmationReport {
+    pub tip: u64,
+    /// Operations marked confirmed
+    pub confirmed: Vec<String>,
+    /// HTLCs locked because their funding confirmed
+    pub locked: Vec<String>,
+    /// Operations failed because the node no longer knows their transaction
+    #[serde(default)]
+    pub dropped: Vec<String>,
+}
+
+/// PEM files for reaching nodes and explorers behind (mutual) TLS proxies
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TlsConfig {
+    /// CA bundle trusted in addition to the system roots
+    pub ca_cert: Option<String>,
+    /// Client certificate chain presented for mutual TLS, with `client_key`
+    pub client_cert: Option<String>,
+    /// PKCS#8 private key of `client_cert`
+    pub client_key: Option<String>,
+}
+
+/// Connection pool of the HTTP client shared by node and explorer requests
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HttpPoolConfig {
+    /// Idle keep-alive connections kept per host; unlimited by default
+    pub max_idle_per_host: Option<usize>,
+    /// Seconds an idle connection is kept before closing; defaults to 90
+    pub idle_timeout_secs: Option<u64>,
+    /// TCP keep-alive probe interval in seconds; off by default
+    pub tcp_keepalive_secs: Option<u64>,
+    /// Speak HTTP/2 without negotiating it, for plaintext endpoints behind
+    /// HTTP/2 proxies. TLS endpoints negotiate HTTP/2 on their own.
+    #[serde(default)]
+    pub http2_prior_knowledge: bool,
+}
+
+/// Response format of a block explorer API
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExplorerKind {
+    /// Insight API (`/addr/{address}/utxo`)
+    #[default]
+    Insight,
+    /// Blockchair API (`/dashboards/address/{address}`)
+    Blockchair,
+}
+
+/// Block explorer API used for address queries
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExplorerConfig {
+    #[serde(default)]
+    pub kind: ExplorerKind,
+    pub url: String,
+    /// Blockchair takes it as the `key` parameter, Insight as `x-api-key`
+    pub api_key: Option<String>,
+    /// Extra headers sent with every request, e.g. `Authorization`
+    pub headers: Option<BTreeMap<String, String>>,
+    /// Sustained request rate allowed to this explorer; unlimited if unset
+    pub rate_limit_per_sec: Option<f64>,
+    /// Requests allowed in a burst above the sustained rate; defaults to 1
+    pub rate_limit_burst: Option<u32>,
+    /// UTXOs requested per page; defaults to 100
+    pub page_size: Option<usize>,
+    /// Most UTXOs fetched for one address; defaults to 10000
+    pub max_utxos: Option<usize>,
+}
+
+impl ExplorerConfig {
+    pub fn new(kind: ExplorerKind, url: &str) -> Self {
+        Self {
+            kind,
+            url: url.to_string(),
+            api_key: None,
+            headers: None,
+            rate_limit_per_sec: None,
+            rate_limit_burst: None,
+            page_size: None,
+            max_utxos: None,
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers
+            .get_or_insert_with(BTreeMap::new)
+            .insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn with_rate_limit(mut self, per_sec: f64, burst: u32) -> Self {
+        self.rate_limit_per_sec = Some(per_sec);
+        self.rate_limit_burst = Some(burst);
+        self
+    }
+
+    pub fn with_pagination(mut self, page_size: usize, max_utxos: usize) -> Self {
+        self.page_size = Some(page_size);
+        self.max_utxos = Some(max_utxos);
+        self
+    }
+}
+
+impl std::fmt::Debug for ExplorerConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExplorerConfig")
+            .field("kind", &self.kind)
+            .field("url", &self.url)
+            .field("api_key", &self.api_key.as_ref().map(|_| REDACTED))
+            .field(
+                "headers",
+                &self
+                    .headers
+                    .as_ref()
+                    .map(|headers| headers.keys().collect::<Vec<_>>()),
+            )
+            .field("rate_limit_per_sec", &self.rate_limit_per_sec)
+            .field("rate_limit_burst", &self.rate_limit_burst)
+            .field("page_size", &self.page_size)
+            .field("max_utxos", &self.max_utxos)
+            .finish()
+    }
+}
+
+/// Endpoint of an external signing service holding the keys
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RemoteSignerConfig {
+    pub url: String,
+    /// Sent as a bearer token on every request
+    pub auth_token: Option<String>,
+}
+
+impl std::fmt::Debug for RemoteSignerConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RemoteSignerConfig")
+            .field("url", &self.url)
+            .field("auth_token", &self.auth_token.as_ref().map(|_| REDACTED))
+            .finish()
+    }
+}
+
+/// Bearer token admitting requests to the HTTP API for one tenant
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ApiTokenConfig {
+    pub token: String,
+    /// Tenant whose HTLCs requests with this token see; `default` if unset
+    pub tenant_id: Option<String>,
+}
+
+impl ApiTokenConfig {
+    pub fn tenant(&self) -> TenantContext {
+        self.tenant_id
+            .as_deref()
+            .map(TenantContext::new)
+            .unwrap_or_default()
+    }
+}
+
+impl std::fmt::Debug for ApiTokenConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiTokenConfig")
+            .field("token", &REDACTED)
+            .field("tenant_id", &self.tenant_id)
+            .finish()
+    }
+}
+
+/// Webhook receiving HTLC lifecycle events from the outbox
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Signs every payload with HMAC-SHA256 in the `X-HTLC-Signature`
+    /// header; the relayer refuses webhooks without one
+    pub secret: Option<String>,
+    /// Sent as a bearer token with every event
+    pub auth_token: Option<String>,
+    /// Key of the webhook's delivery progress (default: derived from
+    /// `url`, so changing the URL starts over)
+    pub name: Option<String>,
+}
+
+impl std::fmt::Debug for WebhookConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookConfig")
+            .field("url", &redact_url(&self.url))
+            .field("secret", &self.secret.as_ref().map(|_| REDACTED))
+            .field("auth_token", &self.auth_token.as_ref().map(|_| REDACTED))
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+/// Hot wallet the relayer funds HTLCs from besides `hot_wallet_address`
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HotWalletConfig {
+    pub address: String,
+    /// Hex private key, or `keystore:<id>` like `hot_wallet_privkey`
+    pub privkey: String,
+}
+
+impl std::fmt::Debug for HotWalletConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HotWalletConfig")
+            .field("address", &self.address)
+            .field("privkey", &REDACTED)
+            .finish()
+    }
+}
+
+/// How the relayer picks the hot wallet funding each HTLC
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HotWalletSelection {
+    /// Each wallet in turn
+    #[default]
+    RoundRobin,
+    /// The wallet with the largest spendable balance
+    Balance,
+}
+
+impl HotWalletSelection {
+    /// Indices of the wallets with spendable `balances` in the order to try
+    /// them for the `turn`-th HTLC; later ones are fallbacks for when the
+    /// first cannot cover it
+    pub fn order(self, balances: &[u64], turn: usize) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..balances.len()).collect();
+        match self {
+            HotWalletSelection::RoundRobin if !order.is_empty() => {
+                order.rotate_left(turn % balances.len())
+            }
+            HotWalletSelection::RoundRobin => {}
+            HotWalletSelection::Balance => {
+                order.sort_by_key(|&wallet| std::cmp::Reverse(balances[wallet]))
+            }
+        }
+        order
+    }
+}
+
+/// When the relayer merges small hot wallet UTXOs into one, so funding
+/// transactions stay small and cheap
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsolidationConfig {
+    /// Consolidate once the hot wallet holds more UTXOs than this
+    pub max_utxos: usize,
+    /// UTXOs of at most this many ZEC count as dust-sized
+    pub dust_amount_zec: String,
+    /// Consolidate once more UTXOs than this are dust-sized
+    pub max_dust_utxos: usize,
+    /// Only consolidate while the estimated fee rate, in zatoshis per 1000
+    /// bytes, is at or below this
+    pub max_fee_per_kb: u64,
+    /// Most UTXOs spent by one consolidation (default: 50)
+    #[serde(default)]
+    pub max_inputs: Option<usize>,
+    /// Seconds between fragmentation checks (default: 3600)
+    #[serde(default)]
+    pub interval_secs: Option<u64>,
+}
+
+impl ConsolidationConfig {
+    /// Whether `utxos` hold too many outputs, or too many dust-sized ones
+    pub fn is_fragmented(&self, utxos: &[RelayerUTXO]) -> bool {
+        let dust = zec_to_zatoshis(&self.dust_amount_zec).unwrap_or(0);
+        let dust_utxos = utxos
+            .iter()
+            .filter(|utxo| zec_to_zatoshis(&utxo.amount).is_some_and(|amount| amount <= dust))
+            .count();
+        utxos.len() > self.max_utxos || dust_utxos > self.max_dust_utxos
+    }
+}
+
+/// Hot wallet levels below which the relayer stops funding new HTLCs and
+/// raises an alert, instead of failing each HTLC for want of funds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletThresholdConfig {
+    /// Pause creations while the spendable balance is below this many ZEC
+    #[serde(default)]
+    pub min_balance_zec: Option<String>,
+    /// Pause creations while fewer UTXOs than this are spendable, as each
+    /// funding transaction holds on to the ones it spends
+    #[serde(default)]
+    pub min_utxos: Option<usize>,
+}
+
+impl WalletThresholdConfig {
+    /// Which thresholds the spendable `utxos` fall short of, if any
+    pub fn shortfall(&self, utxos: &[RelayerUTXO]) -> Option<String> {
+        let mut breaches = Vec::new();
+
+        if let Some(min_balance) = &self.min_balance_zec {
+            let balance: u64 = utxos
+                .iter()
+                .filter_map(|utxo| zec_to_zatoshis(&utxo.amount))
+                .sum();
+            if balance < zec_to_zatoshis(min_balance).unwrap_or(0) {
+                breaches.push(format!(
+                    "balance {} ZEC below {} ZEC",
+                    zatoshis_to_zec(balance),
+                    min_balance
+                ));
+            }
+        }
+        if let Some(min_utxos) = self.min_utxos {
+            if utxos.len() < min_utxos {
+                breaches.push(format!("{} UTXO(s) below {}", utxos.len(), min_utxos));
+            }
+        }
+
+        (!breaches.is_empty()).then(|| breaches.join(", "))
+    }
+}
+
+/// When the relayer speeds up its own transactions that are slow to confirm
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeBumpConfig {
+    /// Bump a funding transaction still unconfirmed this many blocks after
+    /// it or its last bump was broadcast
+    pub after_blocks: u64,
+    /// Most zatoshis a bumped transaction, with its children, pays in fees
+    #[serde(default)]
+    pub max_fee_zat: Option<u64>,
+}
+
+/// When the relayer stops sending work to a node that keeps failing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreakerConfig {
+    /// Failures in a row to reach the node that pause batches
+    pub failure_threshold: u32,
+    /// Seconds batches stay paused before the node is probed again
+    pub cooldown_secs: u64,
+}
+
+/// m-of-n P2SH hot wallet whose keys are split between the relayer's own
+/// `hot_wallet_privkey` and a standby remote signer. `hot_wallet_address`
+/// must be the P2SH address of the resulting multisig script.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThresholdSigningConfig {
+    pub threshold: usize,
+    /// All operational public keys, in multisig script order
+    pub pubkeys: Vec<String>,
+    pub standby_signer: RemoteSignerConfig,
+    /// Key id of the standby signer's key
+    pub standby_key_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteSignRequest {
+    pub key_id: String,
+    /// Hex-encoded 32-byte sighash
+    pub sighash: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteSignResponse {
+    /// Hex-encoded DER signature without the sighash type byte
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemotePubkeyRequest {
+    pub key_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemotePubkeyResponse {
+    pub pubkey: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct HTLCCreationResult {
     pub htlc_id: String,
@@ -282,7 +1648,7 @@ pub struct HTLCCreationResult {
     pub redeem_script: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct RelayerConfig {
     pub hot_wallet_privkey: String,
     pub hot_wallet_address: String,
@@ -291,6 +1657,122 @@ pub struct RelayerConfig {
     pub max_retry_attempts: u32,
     pub min_confirmations: u32,
     pub network_fee_zec: String,
+    /// BIP39 mnemonic for deriving a fresh refund key per HTLC
+    pub hd_mnemonic: Option<String>,
+    /// Overrides the network's default BIP44 path for `hd_mnemonic`
+    pub hd_derivation_path: Option<String>,
+    /// Fund from a threshold wallet instead of a single-key address
+    pub threshold_signing: Option<ThresholdSigningConfig>,
+    /// Keep a `keystore:<id>` hot key decrypted in locked memory for this
+    /// many seconds instead of decrypting it for every signature
+    pub key_cache_ttl_secs: Option<u64>,
+    /// Archive Redeemed and Refunded HTLCs this many days after they
+    /// settled; kept in the hot tables forever if unset
+    pub archive_after_days: Option<u64>,
+    /// Delete outbox events this many days after they were queued, once
+    /// every webhook received or gave up on them (default: 30)
+    pub event_retention_days: Option<u64>,
+    /// Webhook receiving HTLC state change events from the outbox, signed
+    /// with `event_webhook_secret`; see `event_webhooks` for more options
+    pub event_webhook_url: Option<String>,
+    /// Signs every payload sent to `event_webhook_url`, which the relayer
+    /// requires
+    pub event_webhook_secret: Option<String>,
+    /// Webhooks receiving HTLC lifecycle events, each at its own pace
+    #[serde(default)]
+    pub event_webhooks: Vec<WebhookConfig>,
+    /// Only fund, redeem and track HTLCs of this tenant (default: the
+    /// `default` tenant)
+    pub tenant_id: Option<String>,
+    /// Serve `/healthz` and `/readyz` on this address, e.g. `0.0.0.0:8081`,
+    /// for liveness and readiness probes
+    pub readiness_addr: Option<String>,
+    /// Fail `/healthz` once the batch loop has not come round for this many
+    /// seconds (default: 10 poll intervals)
+    #[serde(default)]
+    pub stuck_after_secs: Option<u64>,
+    /// Merge small hot wallet UTXOs while fees are low
+    #[serde(default)]
+    pub consolidation: Option<ConsolidationConfig>,
+    /// Pay for funding and refund transactions that are slow to confirm
+    /// with a child transaction
+    #[serde(default)]
+    pub fee_bump: Option<FeeBumpConfig>,
+    /// Run batches only while holding a lease of this many seconds, so
+    /// several replicas can run with one of them processing at a time
+    #[serde(default)]
+    pub leader_lease_secs: Option<u64>,
+    /// Pause new HTLC creations and alert while the hot wallet runs low
+    #[serde(default)]
+    pub wallet_thresholds: Option<WalletThresholdConfig>,
+    /// Scan new blocks for spends of unsettled HTLCs, settling those
+    /// redeemed or refunded by others and keeping revealed preimages
+    #[serde(default)]
+    pub watchtower: bool,
+    /// More wallets to fund HTLCs from, tried after `hot_wallet_address`
+    /// as `hot_wallet_selection` says
+    #[serde(default)]
+    pub hot_wallets: Vec<HotWalletConfig>,
+    #[serde(default)]
+    pub hot_wallet_selection: HotWalletSelection,
+    /// Fee rates for HTLCs of each priority
+    #[serde(default)]
+    pub fee_priorities: Option<FeePriorityConfig>,
+    /// Most transactions broadcast in any rolling minute; the rest wait for
+    /// later batches
+    #[serde(default)]
+    pub max_broadcasts_per_minute: Option<u32>,
+    /// Blocks past an HTLC's timelock before the relayer refunds it, so a
+    /// redeem revealed at the last moment can still confirm (default: 2)
+    #[serde(default)]
+    pub refund_margin_blocks: Option<u64>,
+    /// Pause batches while the node keeps failing instead of failing every
+    /// HTLC in turn
+    #[serde(default)]
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+}
+
+impl std::fmt::Debug for RelayerConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RelayerConfig")
+            .field("hot_wallet_privkey", &REDACTED)
+            .field("hot_wallet_address", &self.hot_wallet_address)
+            .field("max_tx_per_batch", &self.max_tx_per_batch)
+            .field("poll_interval_secs", &self.poll_interval_secs)
+            .field("max_retry_attempts", &self.max_retry_attempts)
+            .field("min_confirmations", &self.min_confirmations)
+            .field("network_fee_zec", &self.network_fee_zec)
+            .field("hd_mnemonic", &self.hd_mnemonic.as_ref().map(|_| REDACTED))
+            .field("hd_derivation_path", &self.hd_derivation_path)
+            .field("threshold_signing", &self.threshold_signing)
+            .field("key_cache_ttl_secs", &self.key_cache_ttl_secs)
+            .field("archive_after_days", &self.archive_after_days)
+            .field("event_retention_days", &self.event_retention_days)
+            .field(
+                "event_webhook_url",
+                &self.event_webhook_url.as_deref().map(redact_url),
+            )
+            .field(
+                "event_webhook_secret",
+                &self.event_webhook_secret.as_ref().map(|_| REDACTED),
+            )
+            .field("event_webhooks", &self.event_webhooks)
+            .field("tenant_id", &self.tenant_id)
+            .field("readiness_addr", &self.readiness_addr)
+            .field("stuck_after_secs", &self.stuck_after_secs)
+            .field("consolidation", &self.consolidation)
+            .field("fee_bump", &self.fee_bump)
+            .field("leader_lease_secs", &self.leader_lease_secs)
+            .field("wallet_thresholds", &self.wallet_thresholds)
+            .field("watchtower", &self.watchtower)
+            .field("hot_wallets", &self.hot_wallets)
+            .field("hot_wallet_selection", &self.hot_wallet_selection)
+            .field("fee_priorities", &self.fee_priorities)
+            .field("max_broadcasts_per_minute", &self.max_broadcasts_per_minute)
+            .field("refund_margin_blocks", &self.refund_margin_blocks)
+            .field("circuit_breaker", &self.circuit_breaker)
+            .finish()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -308,6 +1790,72 @@ pub struct RelayerUTXO {
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
+/// Consolidation transaction merging hot wallet UTXOs, as recorded by
+/// [`Database::commit_consolidation`](crate::database::Database::commit_consolidation)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Consolidation {
+    pub txid: String,
+    /// Hot wallet address the inputs came from and the output pays
+    pub address: String,
+    pub inputs: u32,
+    /// Value of the merged output, in zatoshis
+    pub amount_zat: u64,
+    pub fee_zat: u64,
+    pub tx_size: u32,
+    /// Fee rate in zatoshis per 1000 bytes
+    pub fee_rate: u64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// HTLC whose creation failed `max_retry_attempts` times, from
+/// [`Database::get_htlcs_needing_intervention`](crate::database::Database::get_htlcs_needing_intervention)
+#[derive(Debug, Clone, Serialize)]
+pub struct DeadLetteredHTLC {
+    pub htlc: ZcashHTLC,
+    pub creation_attempts: u32,
+    pub last_error: Option<String>,
+    pub last_error_kind: Option<FailureKind>,
+}
+
+/// Hot wallet UTXOs held for one in-flight transaction. Commit it with
+/// [`Database::commit_reservation`](crate::database::Database::commit_reservation)
+/// once the transaction is broadcast, or release it on failure; otherwise
+/// the UTXOs become available again at `expires_at`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UtxoReservation {
+    pub id: String,
+    pub utxos: Vec<RelayerUTXO>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Tenant of rows written before tenants were introduced, and of clients
+/// that never pick one
+pub const DEFAULT_TENANT: &str = "default";
+
+/// Integrator on whose behalf HTLCs, operations and relayer UTXOs are read
+/// and written. A [`Database`](crate::database::Database) scoped to one
+/// tenant never sees another tenant's rows.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TenantContext {
+    id: String,
+}
+
+impl TenantContext {
+    pub fn new(id: &str) -> Self {
+        Self { id: id.to_string() }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Default for TenantContext {
+    fn default() -> Self {
+        Self::new(DEFAULT_TENANT)
+    }
+}
+
 impl From<RelayerUTXO> for UTXO {
     fn from(utxo: RelayerUTXO) -> Self {
         UTXO {
@@ -319,3 +1867,237 @@ impl From<RelayerUTXO> for UTXO {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_htlc_state_transitions() {
+        use HTLCState::*;
+
+        assert!(Pending.can_transition_to(Locked));
+        assert!(Locked.can_transition_to(Redeemed));
+        assert!(Expired.can_transition_to(Refunded));
+        assert!(Failed.can_transition_to(Locked));
+        assert!(Locked.can_transition_to(Locked));
+        assert!(Pending.can_transition_to(NeedsIntervention));
+        assert!(NeedsIntervention.can_transition_to(Pending));
+
+        assert!(!Redeemed.can_transition_to(Pending));
+        assert!(!Refunded.can_transition_to(Locked));
+        assert!(!Pending.can_transition_to(Redeemed));
+        assert!(!Locked.can_transition_to(Pending));
+        assert!(!Locked.can_transition_to(NeedsIntervention));
+
+        assert_eq!(
+            Locked.reorg_revert(&HTLCOperationType::Create),
+            Some(Pending)
+        );
+        assert_eq!(
+            Redeemed.reorg_revert(&HTLCOperationType::Redeem),
+            Some(Locked)
+        );
+        assert_eq!(
+            Refunded.reorg_revert(&HTLCOperationType::Refund),
+            Some(Locked)
+        );
+        assert_eq!(Redeemed.reorg_revert(&HTLCOperationType::Create), None);
+        assert_eq!(Refunded.reorg_revert(&HTLCOperationType::Redeem), None);
+        assert_eq!(Pending.reorg_revert(&HTLCOperationType::Create), None);
+    }
+
+    #[test]
+    fn test_secret_is_redacted_but_serialized() {
+        let spend = HTLCSpend::Redeem {
+            secret: "deadbeef".into(),
+        };
+        assert!(!format!("{:?}", spend).contains("deadbeef"));
+
+        let json = serde_json::to_value(&spend).unwrap();
+        assert_eq!(json["secret"], "deadbeef");
+        assert_eq!(serde_json::from_value::<HTLCSpend>(json).unwrap(), spend);
+    }
+
+    #[test]
+    fn test_zec_amounts_convert_exactly() {
+        assert_eq!(zec_to_zatoshis("1.5"), Some(150_000_000));
+        assert_eq!(zec_to_zatoshis("0.00000001"), Some(1));
+        assert_eq!(zec_to_zatoshis("0.29"), Some(29_000_000));
+        assert_eq!(zec_to_zatoshis("21000000"), Some(2_100_000_000_000_000));
+        assert_eq!(zec_to_zatoshis(".5"), Some(50_000_000));
+        assert_eq!(zec_to_zatoshis("7."), Some(700_000_000));
+
+        for invalid in [
+            "",
+            ".",
+            "-1",
+            "+1",
+            "1e-8",
+            "0.000000001",
+            "1.2.3",
+            " 1",
+            "NaN",
+        ] {
+            assert_eq!(zec_to_zatoshis(invalid), None, "{}", invalid);
+        }
+        assert_eq!(zec_to_zatoshis("184467440737.09551616"), None);
+
+        assert_eq!(zatoshis_to_zec(29_000_000), "0.29000000");
+        assert_eq!(zatoshis_to_zec(u64::MAX), "184467440737.09551615");
+        assert_eq!(zec_to_zatoshis(&zatoshis_to_zec(u64::MAX)), Some(u64::MAX));
+    }
+
+    #[test]
+    fn test_timelock_kind_follows_locktime_threshold() {
+        assert_eq!(
+            TimelockKind::for_timelock(2_500_000),
+            TimelockKind::BlockHeight
+        );
+        assert_eq!(
+            TimelockKind::for_timelock(LOCKTIME_THRESHOLD - 1),
+            TimelockKind::BlockHeight
+        );
+        assert_eq!(
+            TimelockKind::for_timelock(1_790_000_000),
+            TimelockKind::Timestamp
+        );
+        assert_eq!(
+            TimelockKind::Timestamp.as_str().parse(),
+            Ok(TimelockKind::Timestamp)
+        );
+    }
+
+    #[test]
+    fn test_retry_delays_back_off() {
+        assert_eq!(operation_retry_delay(1), chrono::Duration::seconds(30));
+        assert_eq!(operation_retry_delay(2), chrono::Duration::seconds(60));
+        assert_eq!(operation_retry_delay(4), chrono::Duration::seconds(240));
+        assert_eq!(operation_retry_delay(20), chrono::Duration::seconds(3600));
+
+        assert_eq!(event_retry_delay(1), chrono::Duration::seconds(5));
+        assert_eq!(event_retry_delay(3), chrono::Duration::seconds(20));
+        assert_eq!(event_retry_delay(20), chrono::Duration::seconds(900));
+    }
+
+    #[test]
+    fn test_consolidation_detects_fragmentation() {
+        let config = ConsolidationConfig {
+            max_utxos: 4,
+            dust_amount_zec: "0.001".to_string(),
+            max_dust_utxos: 2,
+            max_fee_per_kb: 1000,
+            max_inputs: None,
+            interval_secs: None,
+        };
+
+        let mut utxos = vec![utxo("1.0"), utxo("0.001"), utxo("0.0005")];
+        assert!(!config.is_fragmented(&utxos));
+
+        utxos.push(utxo("0.0001"));
+        assert!(config.is_fragmented(&utxos));
+
+        let large: Vec<_> = (0..5).map(|_| utxo("1.0")).collect();
+        assert!(config.is_fragmented(&large));
+    }
+
+    #[test]
+    fn test_wallet_thresholds_report_shortfall() {
+        let config = WalletThresholdConfig {
+            min_balance_zec: Some("1.5".to_string()),
+            min_utxos: Some(3),
+        };
+
+        let mut utxos = vec![utxo("1.0"), utxo("0.5"), utxo("0.25")];
+        assert_eq!(config.shortfall(&utxos), None);
+
+        utxos.truncate(2);
+        assert_eq!(
+            config.shortfall(&utxos).as_deref(),
+            Some("2 UTXO(s) below 3")
+        );
+
+        utxos.pop();
+        assert_eq!(
+            config.shortfall(&utxos).as_deref(),
+            Some("balance 1.00000000 ZEC below 1.5 ZEC, 1 UTXO(s) below 3")
+        );
+    }
+
+    #[test]
+    fn test_hot_wallet_selection_order() {
+        let balances = [5, 20, 0, 20];
+
+        assert_eq!(
+            HotWalletSelection::RoundRobin.order(&balances, 0),
+            vec![0, 1, 2, 3]
+        );
+        assert_eq!(
+            HotWalletSelection::RoundRobin.order(&balances, 6),
+            vec![2, 3, 0, 1]
+        );
+        assert_eq!(
+            HotWalletSelection::Balance.order(&balances, 6),
+            vec![1, 3, 0, 2]
+        );
+        assert!(HotWalletSelection::RoundRobin.order(&[], 3).is_empty());
+    }
+
+    #[test]
+    fn test_fee_priority_round_trips_and_maps_to_rates() {
+        for priority in [
+            FeePriority::Economy,
+            FeePriority::Normal,
+            FeePriority::Urgent,
+        ] {
+            assert_eq!(FeePriority::from_i16(priority as i16), priority);
+        }
+        assert!(FeePriority::Urgent > FeePriority::Normal);
+
+        let params: HTLCParams = serde_json::from_value(serde_json::json!({
+            "recipient_pubkey": "02aa",
+            "refund_pubkey": "02bb",
+            "hash_lock": "cc",
+            "timelock": 100,
+            "amount": "0.1",
+        }))
+        .unwrap();
+        assert_eq!(params.priority, FeePriority::Normal);
+
+        let rates: FeePriorityConfig = toml::from_str("urgent = 5000").unwrap();
+        assert_eq!(rates.rate(FeePriority::Urgent), Some(5000));
+        assert_eq!(rates.rate(FeePriority::Economy), None);
+    }
+
+    #[test]
+    fn test_failure_kind_round_trips() {
+        for kind in [
+            FailureKind::Funding,
+            FailureKind::Signing,
+            FailureKind::Broadcast,
+            FailureKind::Database,
+            FailureKind::Node,
+            FailureKind::Other,
+        ] {
+            assert_eq!(kind.as_str().parse(), Ok(kind));
+            assert_eq!(serde_json::to_value(kind).unwrap(), kind.as_str());
+        }
+        assert!("unknown".parse::<FailureKind>().is_err());
+    }
+
+    fn utxo(amount: &str) -> RelayerUTXO {
+        RelayerUTXO {
+            id: uuid::Uuid::new_v4().to_string(),
+            txid: "11".repeat(32),
+            vout: 0,
+            amount: amount.to_string(),
+            script_pubkey: "76a9".to_string(),
+            confirmations: 6,
+            address: "tmAddress".to_string(),
+            spent: false,
+            spent_in_tx: None,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+}
@@ -1,5 +1,71 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    block_checkpoints (tenant_id, chain, height) {
+        chain -> Varchar,
+        height -> Int8,
+        #[max_length = 64]
+        block_hash -> Varchar,
+        created_at -> Timestamptz,
+        #[max_length = 255]
+        tenant_id -> Varchar,
+    }
+}
+
+diesel::table! {
+    event_deliveries (tenant_id, sink, event_id) {
+        sink -> Text,
+        event_id -> Int8,
+        attempts -> Int4,
+        last_error -> Nullable<Text>,
+        next_attempt_at -> Nullable<Timestamptz>,
+        delivered_at -> Nullable<Timestamptz>,
+        abandoned_at -> Nullable<Timestamptz>,
+        #[max_length = 255]
+        tenant_id -> Varchar,
+    }
+}
+
+diesel::table! {
+    event_outbox (id) {
+        id -> Int8,
+        htlc_id -> Nullable<Varchar>,
+        event_type -> Varchar,
+        payload -> Text,
+        attempts -> Int4,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamptz,
+        delivered_at -> Nullable<Timestamptz>,
+        commit_horizon -> Int8,
+        #[max_length = 255]
+        tenant_id -> Varchar,
+    }
+}
+
+diesel::table! {
+    event_sink_cursors (tenant_id, sink) {
+        sink -> Text,
+        last_event_id -> Int8,
+        updated_at -> Timestamptz,
+        #[max_length = 255]
+        tenant_id -> Varchar,
+    }
+}
+
+diesel::table! {
+    htlc_audit_log (id) {
+        id -> Int8,
+        htlc_id -> Varchar,
+        old_state -> Nullable<Int2>,
+        new_state -> Int2,
+        actor -> Varchar,
+        reason -> Text,
+        created_at -> Timestamptz,
+        #[max_length = 255]
+        tenant_id -> Varchar,
+    }
+}
+
 diesel::table! {
     htlc_operations (id) {
         id -> Varchar,
@@ -15,6 +81,19 @@ diesel::table! {
         error_message -> Nullable<Text>,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        #[max_length = 255]
+        tenant_id -> Varchar,
+        attempt_count -> Int4,
+        last_attempt_at -> Nullable<Timestamptz>,
+        next_retry_at -> Nullable<Timestamptz>,
+        fee_zat -> Nullable<Int8>,
+        tx_size -> Nullable<Int4>,
+        fee_rate -> Nullable<Int8>,
+        broadcast_height -> Nullable<Int8>,
+        bumps_operation_id -> Nullable<Varchar>,
+        #[max_length = 16]
+        error_kind -> Nullable<Varchar>,
+        last_checked_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -24,6 +103,57 @@ diesel::table! {
         chain -> Varchar,
         last_block -> Int4,
         updated_at -> Timestamptz,
+        #[max_length = 255]
+        tenant_id -> Varchar,
+    }
+}
+
+diesel::table! {
+    relayer_consolidations (txid) {
+        #[max_length = 255]
+        txid -> Varchar,
+        #[max_length = 255]
+        tenant_id -> Varchar,
+        #[max_length = 255]
+        address -> Varchar,
+        inputs -> Int4,
+        amount_zat -> Int8,
+        fee_zat -> Int8,
+        tx_size -> Int4,
+        fee_rate -> Int8,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    relayer_jobs (id) {
+        id -> Int8,
+        #[max_length = 255]
+        tenant_id -> Varchar,
+        #[max_length = 32]
+        job_type -> Varchar,
+        payload -> Text,
+        #[max_length = 16]
+        state -> Varchar,
+        dedupe_key -> Nullable<Text>,
+        priority -> Int2,
+        attempts -> Int4,
+        claimed_by -> Nullable<Text>,
+        visible_at -> Timestamptz,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    relayer_leases (tenant_id, name) {
+        #[max_length = 255]
+        tenant_id -> Varchar,
+        name -> Text,
+        holder -> Text,
+        acquired_at -> Timestamptz,
+        expires_at -> Timestamptz,
     }
 }
 
@@ -45,6 +175,11 @@ diesel::table! {
         spent_in_tx -> Nullable<Varchar>,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        #[max_length = 255]
+        reserved_by -> Nullable<Varchar>,
+        reserved_until -> Nullable<Timestamptz>,
+        #[max_length = 255]
+        tenant_id -> Varchar,
     }
 }
 
@@ -69,14 +204,75 @@ diesel::table! {
         #[max_length = 255]
         recipient_address -> Nullable<Varchar>,
         signed_redeem_tx -> Nullable<Text>,
+        version -> Int4,
+        #[max_length = 255]
+        tenant_id -> Varchar,
+        metadata -> Nullable<Jsonb>,
+        #[max_length = 16]
+        timelock_kind -> Varchar,
+        deleted_at -> Nullable<Timestamptz>,
+        creation_attempts -> Int4,
+        next_creation_at -> Nullable<Timestamptz>,
+        last_error -> Nullable<Text>,
+        priority -> Int2,
+        #[max_length = 16]
+        last_error_kind -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    zcash_htlcs_archive (id) {
+        id -> Varchar,
+        txid -> Nullable<Varchar>,
+        p2sh_address -> Varchar,
+        hash_lock -> Varchar,
+        secret -> Nullable<Varchar>,
+        timelock -> Int8,
+        recipient_pubkey -> Varchar,
+        refund_pubkey -> Varchar,
+        amount -> Varchar,
+        network -> Varchar,
+        state -> Int2,
+        vout -> Nullable<Int4>,
+        script_hex -> Text,
+        redeem_script_hex -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        #[max_length = 255]
+        recipient_address -> Nullable<Varchar>,
+        signed_redeem_tx -> Nullable<Text>,
+        archived_at -> Timestamptz,
+        version -> Int4,
+        #[max_length = 255]
+        tenant_id -> Varchar,
+        metadata -> Nullable<Jsonb>,
+        #[max_length = 16]
+        timelock_kind -> Varchar,
+        deleted_at -> Nullable<Timestamptz>,
+        priority -> Int2,
+        creation_attempts -> Int4,
+        next_creation_at -> Nullable<Timestamptz>,
+        last_error -> Nullable<Text>,
+        #[max_length = 16]
+        last_error_kind -> Nullable<Varchar>,
     }
 }
 
+diesel::joinable!(event_deliveries -> event_outbox (event_id));
 diesel::joinable!(htlc_operations -> zcash_htlcs (htlc_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    block_checkpoints,
+    event_deliveries,
+    event_outbox,
+    event_sink_cursors,
+    htlc_audit_log,
     htlc_operations,
     indexer_checkpoints,
+    relayer_consolidations,
+    relayer_jobs,
+    relayer_leases,
     relayer_utxos,
     zcash_htlcs,
+    zcash_htlcs_archive,
 );
@@ -0,0 +1,314 @@
+use hmac::{Hmac, Mac};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Client;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, Duration};
+use tracing::{error, info, warn};
+
+use crate::database::{Database, DatabaseError};
+use crate::models::redact_url;
+use crate::HTLCEvent;
+
+const DEFAULT_BATCH_SIZE: u32 = 100;
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Failed deliveries of one event after which a sink gives up on it, a few
+/// hours at the longest retry delay
+const DEFAULT_MAX_ATTEMPTS: u32 = 20;
+
+/// Header carrying the signature of a webhook payload
+pub const SIGNATURE_HEADER: &str = "X-HTLC-Signature";
+
+/// Destination of outbox events, e.g. a webhook or a message queue
+#[async_trait::async_trait]
+pub trait EventSink: Send + Sync {
+    async fn publish(&self, event: &HTLCEvent) -> Result<(), OutboxError>;
+
+    /// Key of the delivery progress of this sink; sinks sharing a database
+    /// need different names
+    fn name(&self) -> String {
+        "default".to_string()
+    }
+}
+
+/// Posts each event as JSON to a URL, expecting a 2xx answer
+pub struct WebhookSink {
+    client: Client,
+    url: String,
+    name: String,
+    auth_token: Option<String>,
+    secret: Option<String>,
+}
+
+impl WebhookSink {
+    /// Named `webhook-` and a digest of `url`, which may carry credentials
+    /// that have no place in the database or logs
+    pub fn new(url: &str) -> Self {
+        let digest = hex::encode(Sha256::digest(url.as_bytes()));
+        Self {
+            client: Client::new(),
+            url: url.to_string(),
+            name: format!("webhook-{}", &digest[..16]),
+            auth_token: None,
+            secret: None,
+        }
+    }
+
+    /// Track delivery progress under `name` instead, e.g. to keep it when
+    /// the URL changes
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// The URL without user info, query or fragment, for logs
+    pub fn endpoint(&self) -> String {
+        redact_url(&self.url)
+    }
+
+    /// Sign every payload with `secret`, see [`webhook_signature`]
+    pub fn with_secret(mut self, secret: &str) -> Self {
+        self.secret = Some(secret.to_string());
+        self
+    }
+
+    /// Send requests through `client`, e.g. one sharing proxy and TLS
+    /// settings with the RPC client
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Sent as a bearer token with every event
+    pub fn with_auth_token(mut self, token: &str) -> Self {
+        self.auth_token = Some(token.to_string());
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl EventSink for WebhookSink {
+    async fn publish(&self, event: &HTLCEvent) -> Result<(), OutboxError> {
+        let body =
+            serde_json::to_vec(event).map_err(|e| OutboxError::DeliveryFailed(e.to_string()))?;
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(CONTENT_TYPE, "application/json")
+            .header("X-HTLC-Event-Id", event.id)
+            .header("X-HTLC-Event-Type", &event.event_type);
+        if let Some(secret) = &self.secret {
+            let signature = webhook_signature(secret, chrono::Utc::now().timestamp(), &body);
+            request = request.header(SIGNATURE_HEADER, signature);
+        }
+        if let Some(token) = &self.auth_token {
+            request = request.bearer_auth(token);
+        }
+        let request = request.body(body);
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| OutboxError::DeliveryFailed(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(OutboxError::DeliveryFailed(format!(
+                "webhook returned {}",
+                response.status()
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+}
+
+/// Value of the [`SIGNATURE_HEADER`] for `body` sent at Unix time
+/// `timestamp`: `t=<timestamp>,v1=<hex HMAC-SHA256 of "<timestamp>.<body>">`.
+/// Receivers recompute it with the shared secret and reject old timestamps
+/// to stop replays.
+pub fn webhook_signature(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}.", timestamp).as_bytes());
+    mac.update(body);
+    format!(
+        "t={},v1={}",
+        timestamp,
+        hex::encode(mac.finalize().into_bytes())
+    )
+}
+
+/// Publishes outbox events to one sink oldest first, tracking how far the
+/// sink got under its [`EventSink::name`]. An event that fails is retried
+/// after [`event_retry_delay`](crate::event_retry_delay) while the events
+/// after it go on, so one event the sink rejects cannot hold up the rest;
+/// receivers order events by `id`. After `max_attempts` failures the sink
+/// gives up on the event.
+pub struct OutboxDispatcher {
+    database: Arc<Database>,
+    sink: Arc<dyn EventSink>,
+    batch_size: u32,
+    poll_interval: Duration,
+    max_attempts: u32,
+}
+
+impl OutboxDispatcher {
+    pub fn new(database: Arc<Database>, sink: Arc<dyn EventSink>) -> Self {
+        Self {
+            database,
+            sink,
+            batch_size: DEFAULT_BATCH_SIZE,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        }
+    }
+
+    pub fn with_batch_size(mut self, batch_size: u32) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Publish up to one batch of queued events, returning how many were
+    /// delivered
+    pub async fn dispatch_pending(&self) -> Result<usize, OutboxError> {
+        let (sink, batch_size) = (self.sink.name(), self.batch_size);
+        let events = {
+            let sink = sink.clone();
+            self.database
+                .run(move |db| db.get_events_for_sink(&sink, batch_size))
+                .await?
+        };
+
+        let mut delivered = 0;
+        for event in events {
+            let (sink, event_id) = (sink.clone(), event.id);
+            match self.sink.publish(&event).await {
+                Ok(()) => {
+                    self.database
+                        .run(move |db| db.mark_event_delivered_to(&sink, event_id))
+                        .await?;
+                    delivered += 1;
+                }
+                Err(e) => {
+                    let error = e.to_string();
+                    let attempts = {
+                        let sink = sink.clone();
+                        self.database
+                            .run(move |db| db.mark_event_failed_for(&sink, event_id, &error))
+                            .await?
+                    };
+                    warn!(
+                        "⚠️ Delivery of event {} ({}) failed {} time(s): {}",
+                        event_id, event.event_type, attempts, e
+                    );
+                    if attempts >= self.max_attempts {
+                        self.database
+                            .run(move |db| db.abandon_event_for(&sink, event_id))
+                            .await?;
+                    }
+                    // The sink may be down; try the rest on the next poll
+                    break;
+                }
+            }
+        }
+
+        if delivered > 0 {
+            info!("📬 Delivered {} event(s) to {}", delivered, sink);
+        }
+        Ok(delivered)
+    }
+
+    /// Dispatch events in the background until the task is aborted
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = interval(self.poll_interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.dispatch_pending().await {
+                    error!("❌ Error dispatching events: {}", e);
+                }
+            }
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum OutboxError {
+    #[error("Event delivery failed: {0}")]
+    DeliveryFailed(String),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] DatabaseError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockServer;
+    use chrono::Utc;
+
+    #[tokio::test]
+    async fn test_webhook_sink_posts_events() {
+        let event = HTLCEvent {
+            id: 7,
+            htlc_id: Some("htlc-1".to_string()),
+            event_type: "htlc.redeemed".to_string(),
+            payload: serde_json::json!({"old_state": "locked", "new_state": "redeemed"}),
+            attempts: 0,
+            created_at: Utc::now(),
+        };
+
+        let mut webhook = MockServer::respond("200 OK", serde_json::Value::Null).await;
+        WebhookSink::new(&format!("{}/events", webhook.url))
+            .publish(&event)
+            .await
+            .unwrap();
+        let request = webhook.next_request().await;
+        assert!(request.request_line().starts_with("POST /events "));
+        let body = request.json();
+        assert_eq!(body["event_type"], "htlc.redeemed");
+        assert_eq!(body["payload"]["new_state"], "redeemed");
+
+        let webhook = MockServer::respond("503 Service Unavailable", serde_json::Value::Null).await;
+        assert!(matches!(
+            WebhookSink::new(&webhook.url).publish(&event).await,
+            Err(OutboxError::DeliveryFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_webhook_sink_keeps_credentials_out_of_its_name() {
+        let sink = WebhookSink::new("https://user:pw@hooks.example.com/htlc?token=s3cret#x");
+        assert!(sink.name().starts_with("webhook-"));
+        assert_eq!(sink.name().len(), "webhook-".len() + 16);
+        assert!(!sink.name().contains("s3cret"));
+        assert_ne!(
+            sink.name(),
+            WebhookSink::new("https://hooks.example.com/htlc?token=other").name()
+        );
+        assert_eq!(sink.endpoint(), "https://hooks.example.com/htlc");
+        assert_eq!(sink.with_name("billing").name(), "billing");
+    }
+
+    #[test]
+    fn test_webhook_signature_is_hmac_of_timestamp_and_body() {
+        assert_eq!(
+            webhook_signature("whsec_test", 1_700_000_000, br#"{"id":7}"#),
+            "t=1700000000,v1=752e6be570ce41e61bb9b76bd112f81f566efb26dd67c6dde67ba4f404e130f4"
+        );
+    }
+}
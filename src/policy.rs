@@ -0,0 +1,146 @@
+use bitcoin::blockdata::transaction::Transaction;
+use bitcoin::EcdsaSighashType;
+use std::collections::HashSet;
+
+use crate::HTLCScriptBuilder;
+
+/// Enforcement point consulted before the signer produces any transaction
+/// signature. Returning an error vetoes that signature; the reason is
+/// surfaced as [`SignerError::PolicyRejected`](crate::SignerError::PolicyRejected).
+#[async_trait::async_trait]
+pub trait SigningPolicy: Send + Sync {
+    async fn approve(&self, request: &SigningRequest) -> Result<(), String>;
+}
+
+/// What a signature is about to commit to
+#[derive(Debug, Clone)]
+pub struct SigningRequest {
+    pub input_index: usize,
+    pub input_count: usize,
+    /// Value of the prevout being signed, in zatoshis
+    pub amount: u64,
+    pub lock_time: u32,
+    pub sighash_type: EcdsaSighashType,
+    pub destinations: Vec<Destination>,
+}
+
+/// One output of the transaction being signed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Destination {
+    /// Transparent address, if the output pays a P2PKH or P2SH script
+    pub address: Option<String>,
+    pub script_pubkey: String,
+    /// Zatoshis
+    pub amount: u64,
+}
+
+impl SigningRequest {
+    pub(crate) fn new(
+        script_builder: &HTLCScriptBuilder,
+        tx: &Transaction,
+        input_index: usize,
+        amount: u64,
+        sighash_type: EcdsaSighashType,
+    ) -> Self {
+        let destinations = tx
+            .output
+            .iter()
+            .map(|output| Destination {
+                address: script_builder.script_pubkey_to_address(&output.script_pubkey),
+                script_pubkey: hex::encode(output.script_pubkey.as_bytes()),
+                amount: output.value,
+            })
+            .collect();
+
+        Self {
+            input_index,
+            input_count: tx.input.len(),
+            amount,
+            lock_time: tx.lock_time.0,
+            sighash_type,
+            destinations,
+        }
+    }
+
+    /// Sum of all output values in zatoshis
+    pub fn total_output(&self) -> u64 {
+        self.destinations.iter().map(|d| d.amount).sum()
+    }
+}
+
+/// Refuses to sign any transaction with an output to an address outside the
+/// allowlist, including outputs with no transparent address
+#[derive(Debug, Clone, Default)]
+pub struct AddressAllowlist {
+    addresses: HashSet<String>,
+}
+
+impl AddressAllowlist {
+    pub fn new<I, S>(addresses: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            addresses: addresses.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn with_address(mut self, address: &str) -> Self {
+        self.addresses.insert(address.to_string());
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl SigningPolicy for AddressAllowlist {
+    async fn approve(&self, request: &SigningRequest) -> Result<(), String> {
+        for destination in &request.destinations {
+            match &destination.address {
+                Some(address) if self.addresses.contains(address) => {}
+                Some(address) => return Err(format!("unknown destination {}", address)),
+                None => {
+                    return Err(format!(
+                        "non-standard destination script {}",
+                        destination.script_pubkey
+                    ))
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::tests::unsigned_tx;
+    use crate::ZcashNetwork;
+    use bitcoin::blockdata::script::Script;
+    use bitcoin::blockdata::transaction::TxOut;
+    use bitcoin::hashes::Hash;
+
+    #[tokio::test]
+    async fn test_address_allowlist() {
+        let script_builder = HTLCScriptBuilder::new(ZcashNetwork::Testnet);
+        let known = Script::new_p2pkh(&bitcoin::PubkeyHash::from_slice(&[1u8; 20]).unwrap());
+        let unknown = Script::new_p2pkh(&bitcoin::PubkeyHash::from_slice(&[2u8; 20]).unwrap());
+        let known_address = script_builder.script_pubkey_to_address(&known).unwrap();
+
+        let mut tx = unsigned_tx(1, 0);
+        tx.output[0].script_pubkey = known;
+        let policy = AddressAllowlist::default().with_address(&known_address);
+
+        let request = SigningRequest::new(&script_builder, &tx, 0, 2000, EcdsaSighashType::All);
+        assert_eq!(request.total_output(), 1000);
+        assert!(policy.approve(&request).await.is_ok());
+
+        tx.output.push(TxOut {
+            value: 500,
+            script_pubkey: unknown,
+        });
+        let request = SigningRequest::new(&script_builder, &tx, 0, 2000, EcdsaSighashType::All);
+        assert!(policy.approve(&request).await.is_err());
+    }
+}
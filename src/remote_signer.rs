@@ -0,0 +1,187 @@
+use reqwest::{Client, StatusCode, Url};
+use serde::{de::DeserializeOwned, Serialize};
+use std::net::IpAddr;
+use tracing::warn;
+
+use crate::signer::{Signer, SignerError};
+use crate::{
+    RemotePubkeyRequest, RemotePubkeyResponse, RemoteSignRequest, RemoteSignResponse,
+    RemoteSignerConfig,
+};
+
+/// Signer that forwards sighash digests to an external signing service, so
+/// key custody can live in a separate hardened process.
+///
+/// The service exposes two JSON endpoints under its base URL:
+/// `POST /sign` taking [`RemoteSignRequest`] and returning
+/// [`RemoteSignResponse`], and `POST /pubkey` taking [`RemotePubkeyRequest`]
+/// and returning [`RemotePubkeyResponse`].
+pub struct RemoteSigner {
+    client: Client,
+    url: String,
+    auth_token: Option<String>,
+}
+
+impl RemoteSigner {
+    /// Signer posting to the service at `url`, which must use https unless
+    /// it is on the loopback interface, e.g. a sidecar
+    pub fn new(url: String) -> Result<Self, SignerError> {
+        let parsed = Url::parse(&url).map_err(|e| SignerError::InsecureRemoteUrl(e.to_string()))?;
+        let host = parsed.host_str().unwrap_or_default();
+        let loopback = host == "localhost"
+            || host
+                .trim_matches(|c| c == '[' || c == ']')
+                .parse::<IpAddr>()
+                .is_ok_and(|ip| ip.is_loopback());
+        if parsed.scheme() != "https" && !(parsed.scheme() == "http" && loopback) {
+            return Err(SignerError::InsecureRemoteUrl(format!(
+                "{}://{}",
+                parsed.scheme(),
+                host
+            )));
+        }
+
+        Ok(Self {
+            client: Client::new(),
+            url: url.trim_end_matches('/').to_string(),
+            auth_token: None,
+        })
+    }
+
+    pub fn from_config(config: &RemoteSignerConfig) -> Result<Self, SignerError> {
+        let signer = Self::new(config.url.clone())?;
+        Ok(match &config.auth_token {
+            Some(token) => signer.with_auth_token(token.clone()),
+            None => signer,
+        })
+    }
+
+    pub fn with_auth_token(mut self, token: String) -> Self {
+        self.auth_token = Some(token);
+        self
+    }
+
+    async fn post<Req: Serialize, Resp: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        body: &Req,
+    ) -> Result<Resp, SignerError> {
+        let mut req_builder = self
+            .client
+            .post(format!("{}/{}", self.url, endpoint))
+            .json(body);
+
+        if let Some(token) = &self.auth_token {
+            req_builder = req_builder.bearer_auth(token);
+        }
+
+        let response = req_builder
+            .send()
+            .await
+            .map_err(|e| SignerError::RemoteError(e.to_string()))?;
+
+        match response.status() {
+            status if status.is_success() => response
+                .json()
+                .await
+                .map_err(|e| SignerError::RemoteError(e.to_string())),
+            StatusCode::FORBIDDEN => {
+                warn!("⛔ Remote signer refused /{} request", endpoint);
+                Err(SignerError::Rejected)
+            }
+            status => Err(SignerError::RemoteError(format!(
+                "/{} returned {}",
+                endpoint, status
+            ))),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Signer for RemoteSigner {
+    async fn sign_sighash(&self, key_id: &str, sighash: &[u8; 32]) -> Result<Vec<u8>, SignerError> {
+        let request = RemoteSignRequest {
+            key_id: key_id.to_string(),
+            sighash: hex::encode(sighash),
+        };
+
+        let response: RemoteSignResponse = self.post("sign", &request).await?;
+        let signature =
+            hex::decode(&response.signature).map_err(|_| SignerError::InvalidSignature)?;
+
+        secp256k1::ecdsa::Signature::from_der(&signature)
+            .map_err(|_| SignerError::InvalidSignature)?;
+
+        Ok(signature)
+    }
+
+    async fn derive_pubkey(&self, key_id: &str) -> Result<String, SignerError> {
+        let request = RemotePubkeyRequest {
+            key_id: key_id.to_string(),
+        };
+
+        let response: RemotePubkeyResponse = self.post("pubkey", &request).await?;
+        secp256k1::PublicKey::from_slice(
+            &hex::decode(&response.pubkey).map_err(|_| SignerError::InvalidPublicKey)?,
+        )
+        .map_err(|_| SignerError::InvalidPublicKey)?;
+
+        Ok(response.pubkey)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockServer;
+
+    #[tokio::test]
+    async fn test_remote_sign_sighash() {
+        let secp = secp256k1::Secp256k1::new();
+        let key = secp256k1::SecretKey::from_slice(&[3u8; 32]).unwrap();
+        let message = secp256k1::Message::from_digest_slice(&[9u8; 32]).unwrap();
+        let der = secp.sign_ecdsa(&message, &key).serialize_der().to_vec();
+
+        let mut server = MockServer::respond(
+            "200 OK",
+            serde_json::json!({ "signature": hex::encode(&der) }),
+        )
+        .await;
+
+        let signer = RemoteSigner::new(server.url.clone())
+            .unwrap()
+            .with_auth_token("s3cret".to_string());
+        let signature = signer
+            .sign_sighash("relayer-hot", &[9u8; 32])
+            .await
+            .unwrap();
+        assert_eq!(signature, der);
+
+        let request = server.next_request().await;
+        assert!(request.request_line().starts_with("POST /sign "));
+        assert_eq!(request.header("authorization"), Some("Bearer s3cret"));
+        assert!(request.body.contains(&hex::encode([9u8; 32])));
+    }
+
+    #[tokio::test]
+    async fn test_remote_signer_refusal() {
+        let server = MockServer::respond("403 Forbidden", serde_json::json!({})).await;
+
+        let signer = RemoteSigner::new(server.url).unwrap();
+        let result = signer.sign_sighash("relayer-hot", &[9u8; 32]).await;
+        assert!(matches!(result, Err(SignerError::Rejected)));
+    }
+
+    #[test]
+    fn test_requires_https_off_loopback() {
+        assert!(RemoteSigner::new("https://signer.internal:8443".to_string()).is_ok());
+        assert!(RemoteSigner::new("http://127.0.0.1:9000".to_string()).is_ok());
+        assert!(RemoteSigner::new("http://localhost:9000".to_string()).is_ok());
+        assert!(RemoteSigner::new("http://[::1]:9000".to_string()).is_ok());
+        assert!(matches!(
+            RemoteSigner::new("http://signer.internal:9000".to_string()),
+            Err(SignerError::InsecureRemoteUrl(_))
+        ));
+        assert!(RemoteSigner::new("not a url".to_string()).is_err());
+    }
+}
@@ -1,18 +1,435 @@
-use reqwest::Client;
+use futures_util::Stream;
+use reqwest::{Certificate, Client, Identity, Proxy};
 use serde::Deserialize;
 use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
+use zeroize::Zeroizing;
 
-use crate::{RawTransaction, RpcError, ZcashNetwork, ZcashRpcRequest, ZcashRpcResponse};
+use crate::explorer::{self, Explorer, FallbackExplorer, RateLimitedExplorer, RateLimiter};
+use crate::scanner::WatchSet;
+use crate::telemetry;
+use crate::{
+    zatoshis_to_zec, AddressBalance, AddressUtxos, AddressValidation, Block, BlockHeader,
+    BlockchainInfo, ConfirmationProgress, ExplorerConfig, ExplorerKind, FeeConfidence, FeeEstimate,
+    HTLCActivity, HttpPoolConfig, MempoolEntry, NodeEvent, RawTransaction, RetryPolicy, RpcError,
+    SmartFeeEstimate, TlsConfig, TxOutInfo, ZcashNetwork, ZcashRpcRequest, ZcashRpcResponse, UTXO,
+};
+
+/// Consecutive connection failures before an endpoint is quarantined
+const QUARANTINE_AFTER_FAILURES: u32 = 2;
+const QUARANTINE_DURATION: Duration = Duration::from_secs(60);
+/// zcashd `RPC_IN_WARMUP`: still loading the block index
+const RPC_IN_WARMUP: i32 = -28;
+/// zcashd `RPC_TRANSACTION_ALREADY_IN_CHAIN`
+const RPC_TRANSACTION_ALREADY_IN_CHAIN: i32 = -27;
+/// JSON-RPC method not found, e.g. on gateways exposing a subset of RPCs
+pub const RPC_METHOD_NOT_FOUND: i32 = -32601;
+/// zcashd `RPC_INVALID_ADDRESS_OR_KEY`, e.g. for an unknown transaction
+pub const RPC_INVALID_ADDRESS_OR_KEY: i32 = -5;
+/// Confirmation polling interval, also the fallback when block
+/// notifications are missed
+const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_secs(30);
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_CALL_TIMEOUT: Duration = Duration::from_secs(120);
+pub const DEFAULT_TX_CACHE_SIZE: usize = 1024;
+/// Confirmations after which a cached transaction is unlikely to be reorged
+const TX_CACHE_DEEP_CONFIRMATIONS: u32 = 10;
+const TX_CACHE_DEEP_TTL: Duration = Duration::from_secs(600);
+const TX_CACHE_SHALLOW_TTL: Duration = Duration::from_secs(15);
+/// Most missed blocks `subscribe_blocks` catches up on at once
+const BLOCK_CATCH_UP_LIMIT: u64 = 100;
+/// Blocks whose median time timestamp timelocks are checked against
+const MEDIAN_TIME_SPAN: usize = 11;
+/// Recent block headers kept for median time past walks, enough for a few
+/// tips and their ancestors
+const HEADER_CACHE_SIZE: usize = 4 * MEDIAN_TIME_SPAN;
+
+type ProgressCallback = Arc<dyn Fn(&ConfirmationProgress) + Send + Sync>;
+
+/// How long and how often to poll for confirmations, and how a caller can
+/// stop waiting early
+#[derive(Clone)]
+pub struct ConfirmationWait {
+    poll_interval: Duration,
+    max_attempts: Option<u32>,
+    deadline: Option<Duration>,
+    cancel: Option<CancellationToken>,
+    on_progress: Option<ProgressCallback>,
+}
+
+impl Default for ConfirmationWait {
+    fn default() -> Self {
+        Self {
+            poll_interval: CONFIRMATION_POLL_INTERVAL,
+            max_attempts: None,
+            deadline: None,
+            cancel: None,
+            on_progress: None,
+        }
+    }
+}
+
+impl ConfirmationWait {
+    /// Poll every 30 seconds, or on block notifications, until confirmed
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    pub fn with_max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = Some(attempts);
+        self
+    }
+
+    /// Give up once `deadline` has passed since the wait started
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Stop with [`RpcClientError::Cancelled`] once `token` is cancelled,
+    /// e.g. by a shutdown hook
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Called after every confirmation check that reached the node
+    pub fn with_progress(
+        mut self,
+        on_progress: impl Fn(&ConfirmationProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_progress = Some(Arc::new(on_progress));
+        self
+    }
+}
+
+/// One explorer with the rate limiter shared by all its requests
+struct ExplorerEndpoint {
+    config: ExplorerConfig,
+    limiter: Option<Arc<RateLimiter>>,
+}
+
+impl ExplorerEndpoint {
+    fn new(config: ExplorerConfig) -> Self {
+        let limiter = config.rate_limit_per_sec.map(|per_sec| {
+            Arc::new(RateLimiter::new(
+                per_sec,
+                config.rate_limit_burst.unwrap_or(1),
+            ))
+        });
+        Self { config, limiter }
+    }
+
+    fn backend(&self, client: Client) -> Box<dyn Explorer> {
+        let backend = explorer::from_config(&self.config, client);
+        match &self.limiter {
+            Some(limiter) => Box::new(RateLimitedExplorer::new(backend, limiter.clone())),
+            None => backend,
+        }
+    }
+}
+
+/// One node URL with its connection health
+struct RpcEndpoint {
+    url: String,
+    health: Mutex<EndpointHealth>,
+}
+
+#[derive(Default)]
+struct EndpointHealth {
+    consecutive_failures: u32,
+    quarantined_until: Option<Instant>,
+}
+
+impl RpcEndpoint {
+    fn new(url: String) -> Self {
+        Self {
+            url,
+            health: Mutex::new(EndpointHealth::default()),
+        }
+    }
+
+    fn is_quarantined(&self) -> bool {
+        let health = self.health.lock().unwrap();
+        health
+            .quarantined_until
+            .is_some_and(|until| until > Instant::now())
+    }
+
+    fn record_success(&self) {
+        *self.health.lock().unwrap() = EndpointHealth::default();
+    }
+
+    fn record_failure(&self) {
+        let mut health = self.health.lock().unwrap();
+        health.consecutive_failures += 1;
+
+        if health.consecutive_failures >= QUARANTINE_AFTER_FAILURES {
+            warn!(
+                "🚧 Quarantining RPC endpoint {} for {:?} after {} failures",
+                self.url, QUARANTINE_DURATION, health.consecutive_failures
+            );
+            health.quarantined_until = Some(Instant::now() + QUARANTINE_DURATION);
+        }
+    }
+}
+
+/// zcashd `.cookie` file credentials, re-read whenever the node rotates
+/// the file on restart
+struct CookieAuth {
+    path: PathBuf,
+    cached: Mutex<Option<CachedCookie>>,
+}
+
+struct CachedCookie {
+    modified: SystemTime,
+    user: String,
+    password: Zeroizing<String>,
+}
+
+impl CookieAuth {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            cached: Mutex::new(None),
+        }
+    }
+
+    fn credentials(&self) -> Result<(String, Zeroizing<String>), RpcClientError> {
+        let modified = fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .map_err(|e| RpcClientError::CookieError(format!("{}: {}", self.path.display(), e)))?;
+
+        let mut cached = self.cached.lock().unwrap();
+        if let Some(cookie) = cached.as_ref().filter(|cookie| cookie.modified == modified) {
+            return Ok((cookie.user.clone(), cookie.password.clone()));
+        }
+
+        let content =
+            Zeroizing::new(fs::read_to_string(&self.path).map_err(|e| {
+                RpcClientError::CookieError(format!("{}: {}", self.path.display(), e))
+            })?);
+        let (user, password) = content.trim().split_once(':').ok_or_else(|| {
+            RpcClientError::CookieError(format!("{}: expected user:password", self.path.display()))
+        })?;
+
+        info!("🍪 Loaded RPC cookie from {}", self.path.display());
+        let cookie = cached.insert(CachedCookie {
+            modified,
+            user: user.to_string(),
+            password: Zeroizing::new(password.to_string()),
+        });
+        Ok((cookie.user.clone(), cookie.password.clone()))
+    }
+
+    fn invalidate(&self) {
+        *self.cached.lock().unwrap() = None;
+    }
+}
+
+/// LRU cache of `getrawtransaction` results. Confirmation counts change
+/// with every block, so a new tip, seen in a block notification or a
+/// `getblockcount` answer, or a reorg clears all entries. Between tips,
+/// shallow entries still live briefly, deep ones longer.
+struct TxCache {
+    capacity: usize,
+    entries: HashMap<String, CachedTx>,
+    /// Least recently used first
+    order: VecDeque<String>,
+    events: Option<broadcast::Receiver<NodeEvent>>,
+    /// Last chain height the node reported
+    tip: Option<u64>,
+}
+
+struct CachedTx {
+    tx: RawTransaction,
+    fetched_at: Instant,
+}
+
+impl CachedTx {
+    fn is_fresh(&self) -> bool {
+        let ttl = match self.tx.confirmations {
+            Some(confirmations) if confirmations >= TX_CACHE_DEEP_CONFIRMATIONS => {
+                TX_CACHE_DEEP_TTL
+            }
+            _ => TX_CACHE_SHALLOW_TTL,
+        };
+        self.fetched_at.elapsed() < ttl
+    }
+}
+
+impl TxCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            events: None,
+            tip: None,
+        }
+    }
+
+    fn get(&mut self, txid: &str) -> Option<RawTransaction> {
+        self.apply_events();
+
+        if !self.entries.get(txid)?.is_fresh() {
+            self.remove(txid);
+            return None;
+        }
+        self.touch(txid);
+        self.entries.get(txid).map(|entry| entry.tx.clone())
+    }
+
+    fn insert(&mut self, tx: RawTransaction) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let txid = tx.txid.clone();
+        let entry = CachedTx {
+            tx,
+            fetched_at: Instant::now(),
+        };
+        if self.entries.insert(txid.clone(), entry).is_some() {
+            self.touch(&txid);
+            return;
+        }
+
+        self.order.push_back(txid);
+        while self.order.len() > self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                self.entries.remove(&evicted);
+            }
+        }
+    }
+
+    fn touch(&mut self, txid: &str) {
+        if let Some(position) = self.order.iter().position(|cached| cached == txid) {
+            let txid = self.order.remove(position).expect("position is in range");
+            self.order.push_back(txid);
+        }
+    }
+
+    fn remove(&mut self, txid: &str) {
+        self.entries.remove(txid);
+        self.order.retain(|cached| cached != txid);
+    }
+
+    /// Drop all entries if blocks or reorgs were announced since last call
+    fn apply_events(&mut self) {
+        let Some(events) = &mut self.events else {
+            return;
+        };
+
+        let mut stale = false;
+        loop {
+            match events.try_recv() {
+                Ok(NodeEvent::Block { .. } | NodeEvent::ReorgDetected { .. })
+                | Err(broadcast::error::TryRecvError::Lagged(_)) => stale = true,
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+
+        if stale {
+            self.clear();
+        }
+    }
+
+    /// Drop all entries once the chain height differs from the last one seen
+    fn observe_tip(&mut self, height: u64) {
+        if self.tip.replace(height).is_some_and(|tip| tip != height) {
+            self.clear();
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+/// Settings the HTTP client is rebuilt from whenever one changes
+#[derive(Clone)]
+struct HttpOptions {
+    request_timeout: Duration,
+    root_certificates: Vec<Certificate>,
+    identity: Option<Identity>,
+    proxy: Option<Proxy>,
+    pool: HttpPoolConfig,
+}
+
+impl HttpOptions {
+    fn build(&self) -> Result<Client, RpcClientError> {
+        let mut builder = Client::builder()
+            .timeout(self.request_timeout)
+            .connect_timeout(self.request_timeout)
+            .tcp_keepalive(self.pool.tcp_keepalive_secs.map(Duration::from_secs));
+
+        if let Some(max_idle) = self.pool.max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max_idle);
+        }
+        if let Some(idle_timeout) = self.pool.idle_timeout_secs {
+            builder = builder.pool_idle_timeout(Duration::from_secs(idle_timeout));
+        }
+        if self.pool.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+
+        for certificate in &self.root_certificates {
+            builder = builder.add_root_certificate(certificate.clone());
+        }
+        if let Some(identity) = &self.identity {
+            builder = builder.identity(identity.clone());
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+
+        builder
+            .build()
+            .map_err(|e| RpcClientError::TlsError(e.to_string()))
+    }
+}
 
 pub struct ZcashRpcClient {
     client: Client,
-    rpc_url: String,
+    http: HttpOptions,
+    /// Primary node first, then fallbacks in order of preference
+    endpoints: Vec<RpcEndpoint>,
+    retry: RetryPolicy,
+    /// Budget for one whole call, across retries and failover
+    call_timeout: Duration,
     rpc_user: Option<String>,
     rpc_password: Option<String>,
-    #[allow(dead_code)]
+    /// Takes precedence over `rpc_user`/`rpc_password` when set
+    cookie: Option<CookieAuth>,
+    block_events: Option<broadcast::Sender<NodeEvent>>,
+    tx_cache: Mutex<TxCache>,
+    /// Recently fetched headers, newest last. A hash names one header for
+    /// good, so reorgs never make them stale.
+    header_cache: Mutex<VecDeque<BlockHeader>>,
     network: ZcashNetwork,
-    explorer_api: String,
+    /// Primary explorer first, then fallbacks in order of preference
+    explorers: Vec<ExplorerEndpoint>,
+    /// Whether the node serves address queries itself; unknown until the
+    /// first query probes it
+    address_index: Mutex<Option<bool>>,
+    /// Also push transactions through the explorers
+    dual_broadcast: bool,
 }
 
 impl ZcashRpcClient {
@@ -22,32 +439,200 @@ impl ZcashRpcClient {
         rpc_password: Option<String>,
         network: ZcashNetwork,
     ) -> Self {
-        let explorer_api = match network {
-            ZcashNetwork::Mainnet => "https://api.blockchair.com/zcash".to_string(),
+        let explorer = match network {
+            ZcashNetwork::Mainnet => {
+                ExplorerConfig::new(ExplorerKind::Blockchair, "https://api.blockchair.com/zcash")
+            }
             ZcashNetwork::Testnet => {
-                "https://rest.cryptoapis.io/v2/blockchain-data/zcash/testnet".to_string()
+                ExplorerConfig::new(ExplorerKind::Insight, "https://explorer.testnet.z.cash/api")
             }
         };
 
+        let http = HttpOptions {
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            root_certificates: Vec::new(),
+            identity: None,
+            proxy: None,
+            pool: HttpPoolConfig::default(),
+        };
+
         Self {
-            client: Client::new(),
-            rpc_url,
+            client: http.build().expect("default HTTP client"),
+            http,
+            endpoints: vec![RpcEndpoint::new(rpc_url)],
+            retry: RetryPolicy::default(),
+            call_timeout: DEFAULT_CALL_TIMEOUT,
             rpc_user,
             rpc_password,
+            cookie: None,
+            block_events: None,
+            tx_cache: Mutex::new(TxCache::new(DEFAULT_TX_CACHE_SIZE)),
+            header_cache: Mutex::new(VecDeque::with_capacity(HEADER_CACHE_SIZE)),
             network,
-            explorer_api,
+            explorers: vec![ExplorerEndpoint::new(explorer)],
+            address_index: Mutex::new(None),
+            dual_broadcast: false,
         }
     }
 
-    pub fn with_custom_explorer(mut self, explorer_url: String) -> Self {
-        self.explorer_api = explorer_url;
+    /// Use an Insight API explorer at `explorer_url`
+    pub fn with_custom_explorer(self, explorer_url: String) -> Self {
+        self.with_explorer(ExplorerConfig::new(ExplorerKind::Insight, &explorer_url))
+    }
+
+    pub fn with_explorer(mut self, explorer: ExplorerConfig) -> Self {
+        self.explorers[0] = ExplorerEndpoint::new(explorer);
+        self
+    }
+
+    /// Explorers to ask, in order, when the primary one fails
+    pub fn with_explorer_fallbacks(mut self, explorers: Vec<ExplorerConfig>) -> Self {
+        self.explorers
+            .extend(explorers.into_iter().map(ExplorerEndpoint::new));
+        self
+    }
+
+    /// Nodes to fail over to, in order, when the primary cannot be reached.
+    /// They share the primary's credentials.
+    pub fn with_fallback_urls(mut self, urls: Vec<String>) -> Self {
+        self.endpoints
+            .extend(urls.into_iter().map(RpcEndpoint::new));
+        self
+    }
+
+    /// Push transactions through the explorers as well as the node, for
+    /// nodes with poor peering. Diverging txids fail the broadcast with
+    /// [`RpcClientError::BroadcastConflict`].
+    pub fn with_dual_broadcast(mut self, enabled: bool) -> Self {
+        self.dual_broadcast = enabled;
+        self
+    }
+
+    /// Authenticate with the node's `.cookie` file instead of user/password
+    pub fn with_cookie_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.cookie = Some(CookieAuth::new(path.into()));
+        self
+    }
+
+    /// Re-check confirmations as soon as a block arrives instead of only
+    /// every 30 seconds
+    pub fn with_block_notifications(mut self, events: broadcast::Sender<NodeEvent>) -> Self {
+        self.tx_cache.get_mut().unwrap().events = Some(events.subscribe());
+        self.block_events = Some(events);
+        self
+    }
+
+    /// Keep up to `capacity` transactions from `getrawtransaction` in
+    /// memory; 0 disables the cache
+    pub fn with_tx_cache(mut self, capacity: usize) -> Self {
+        let cache = self.tx_cache.get_mut().unwrap();
+        cache.capacity = capacity;
+        cache.entries.clear();
+        cache.order.clear();
         self
     }
 
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Limit each HTTP request to node and explorer to `request`, and each
+    /// RPC call including its retries to `call`
+    pub fn with_timeouts(mut self, request: Duration, call: Duration) -> Self {
+        self.http.request_timeout = request;
+        self.client = self
+            .http
+            .build()
+            .expect("HTTP options were validated when set");
+        self.call_timeout = call;
+        self
+    }
+
+    /// Tune the connection pool shared by node and explorer requests, e.g.
+    /// to keep connections alive instead of exhausting ephemeral ports
+    pub fn with_http_pool(mut self, pool: &HttpPoolConfig) -> Self {
+        self.http.pool = pool.clone();
+        self.client = self
+            .http
+            .build()
+            .expect("HTTP options were validated when set");
+        self
+    }
+
+    /// Route all node and explorer traffic through `proxy_url`, e.g.
+    /// `socks5h://127.0.0.1:9050` for Tor. `.onion` hosts need `socks5h`
+    /// so that names are resolved by the proxy, not locally.
+    pub fn with_proxy(mut self, proxy_url: &str) -> Result<Self, RpcClientError> {
+        let proxy = Proxy::all(proxy_url).map_err(|e| RpcClientError::ProxyError(e.to_string()))?;
+
+        let onion = self
+            .endpoints
+            .iter()
+            .map(|endpoint| endpoint.url.as_str())
+            .chain(
+                self.explorers
+                    .iter()
+                    .map(|explorer| explorer.config.url.as_str()),
+            )
+            .any(|url| url.contains(".onion"));
+        if onion && !proxy_url.starts_with("socks5h://") {
+            return Err(RpcClientError::ProxyError(
+                ".onion endpoints require a socks5h:// proxy".to_string(),
+            ));
+        }
+
+        self.http.proxy = Some(proxy);
+        self.client = self.http.build()?;
+        Ok(self)
+    }
+
+    /// Trust an extra CA bundle and/or present a client certificate, for
+    /// nodes and explorers behind mutual-TLS proxies
+    pub fn with_tls(mut self, tls: &TlsConfig) -> Result<Self, RpcClientError> {
+        if let Some(path) = &tls.ca_cert {
+            let bundle = read_pem(path)?;
+            self.http.root_certificates = Certificate::from_pem_bundle(&bundle)
+                .map_err(|e| RpcClientError::TlsError(format!("{}: {}", path, e)))?;
+        }
+
+        match (&tls.client_cert, &tls.client_key) {
+            (Some(cert_path), Some(key_path)) => {
+                let key = Zeroizing::new(read_pem(key_path)?);
+                let identity = Identity::from_pkcs8_pem(&read_pem(cert_path)?, &key)
+                    .map_err(|e| RpcClientError::TlsError(format!("{}: {}", cert_path, e)))?;
+                self.http.identity = Some(identity);
+            }
+            (None, None) => {}
+            _ => {
+                return Err(RpcClientError::TlsError(
+                    "client_cert and client_key must be set together".to_string(),
+                ))
+            }
+        }
+
+        self.client = self.http.build()?;
+        Ok(self)
+    }
+
+    /// Call `method`, retrying transient failures per the retry policy
     async fn call_rpc<T: for<'de> Deserialize<'de>>(
         &self,
         method: &str,
         params: Vec<Value>,
+    ) -> Result<T, RpcClientError> {
+        tokio::time::timeout(self.call_timeout, self.call_with_retries(method, params))
+            .await
+            .map_err(|_| RpcClientError::CallTimeout {
+                method: method.to_string(),
+                timeout: self.call_timeout,
+            })?
+    }
+
+    async fn call_with_retries<T: for<'de> Deserialize<'de>>(
+        &self,
+        method: &str,
+        params: Vec<Value>,
     ) -> Result<T, RpcClientError> {
         let request = ZcashRpcRequest {
             jsonrpc: "2.0".to_string(),
@@ -56,9 +641,82 @@ impl ZcashRpcClient {
             params,
         };
 
-        let mut req_builder = self.client.post(&self.rpc_url).json(&request);
+        let mut attempt = 1;
+        loop {
+            match self.call_endpoints(&request).await {
+                Err(e) if e.is_retryable() && attempt < self.retry.max_attempts => {
+                    let delay = self.retry.delay(attempt);
+                    warn!(
+                        "🔁 {} failed ({}), retrying in {:?} ({}/{})",
+                        method, e, delay, attempt, self.retry.max_attempts
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Send to the first healthy endpoint, failing over on transient errors.
+    /// Quarantined endpoints are only tried once every other one has failed.
+    async fn call_endpoints<T: for<'de> Deserialize<'de>>(
+        &self,
+        request: &ZcashRpcRequest,
+    ) -> Result<T, RpcClientError> {
+        let (healthy, quarantined): (Vec<_>, Vec<_>) = self
+            .endpoints
+            .iter()
+            .partition(|endpoint| !endpoint.is_quarantined());
+
+        let mut last_error = None;
+        for endpoint in healthy.into_iter().chain(quarantined) {
+            let started = Instant::now();
+            let result = self.call_endpoint(endpoint, request).await;
+            telemetry::record_rpc(&request.method, &endpoint.url, started.elapsed(), &result);
+
+            match result {
+                Err(e) if e.is_retryable() => {
+                    warn!("⚠️ RPC endpoint {} unavailable: {}", endpoint.url, e);
+                    endpoint.record_failure();
+                    last_error = Some(e);
+                }
+                result => {
+                    endpoint.record_success();
+                    return result;
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or(RpcClientError::NoResult))
+    }
 
-        if self.rpc_url.contains("tatum.io") || self.rpc_url.contains("chain49.com") {
+    /// Send one request, re-reading the cookie once if it was rejected
+    async fn call_endpoint<T: for<'de> Deserialize<'de>>(
+        &self,
+        endpoint: &RpcEndpoint,
+        request: &ZcashRpcRequest,
+    ) -> Result<T, RpcClientError> {
+        match (&self.cookie, self.send(endpoint, request).await) {
+            (Some(cookie), Err(RpcClientError::Unauthorized)) => {
+                cookie.invalidate();
+                self.send(endpoint, request).await
+            }
+            (_, result) => result,
+        }
+    }
+
+    async fn send<T: for<'de> Deserialize<'de>>(
+        &self,
+        endpoint: &RpcEndpoint,
+        request: &ZcashRpcRequest,
+    ) -> Result<T, RpcClientError> {
+        let mut req_builder = self.client.post(&endpoint.url).json(request);
+
+        if let Some(cookie) = &self.cookie {
+            let (user, password) = cookie.credentials()?;
+            req_builder = req_builder.basic_auth(user, Some(password.as_str()));
+        } else if endpoint.url.contains("tatum.io") || endpoint.url.contains("chain49.com") {
             if let Some(api_key) = &self.rpc_user {
                 req_builder = req_builder.header("x-api-key", api_key);
             }
@@ -66,15 +724,25 @@ impl ZcashRpcClient {
             req_builder = req_builder.basic_auth(user, Some(pass));
         }
 
-        let response = req_builder
-            .send()
-            .await
-            .map_err(|e| RpcClientError::NetworkError(e.to_string()))?;
+        let response = req_builder.send().await.map_err(request_error)?;
 
-        let rpc_response: ZcashRpcResponse<T> = response
-            .json()
-            .await
-            .map_err(|e| RpcClientError::ParseError(e.to_string()))?;
+        // zcashd reports RPC errors as HTTP 500 with a JSON body; gateway
+        // errors from proxies in front of it carry no JSON at all
+        let status = response.status().as_u16();
+        if status == 401 {
+            return Err(RpcClientError::Unauthorized);
+        }
+        if matches!(status, 502..=504) {
+            return Err(RpcClientError::HttpStatus(status));
+        }
+
+        let rpc_response: ZcashRpcResponse<T> = response.json().await.map_err(|e| {
+            if e.is_timeout() {
+                RpcClientError::RequestTimeout(e.to_string())
+            } else {
+                RpcClientError::ParseError(e.to_string())
+            }
+        })?;
 
         if let Some(error) = rpc_response.error {
             return Err(RpcClientError::RpcError(error));
@@ -83,35 +751,401 @@ impl ZcashRpcClient {
         rpc_response.result.ok_or(RpcClientError::NoResult)
     }
 
-    /// Broadcast raw transaction
+    /// Broadcast raw transaction. Rejections the caller may act on, such as
+    /// [`RpcClientError::AlreadyBroadcast`], get their own error variants.
     pub async fn send_raw_transaction(&self, tx_hex: &str) -> Result<String, RpcClientError> {
         info!("📡 Broadcasting transaction...");
 
-        let txid: String = self
-            .call_rpc("sendrawtransaction", vec![serde_json::json!(tx_hex)])
-            .await?;
+        let txid = if self.dual_broadcast {
+            let explorer = self.explorer();
+            let (node, pushed) =
+                tokio::join!(self.broadcast_to_node(tx_hex), explorer.broadcast(tx_hex));
+            reconcile_broadcasts(node, pushed)?
+        } else {
+            self.broadcast_to_node(tx_hex).await?
+        };
 
         info!("✅ Transaction broadcast: {}", txid);
         Ok(txid)
     }
 
-    /// Get current block height
-    pub async fn get_block_count(&self) -> Result<u64, RpcClientError> {
-        let height: u64 = self.call_rpc("getblockcount", vec![]).await?;
-        Ok(height)
+    async fn broadcast_to_node(&self, tx_hex: &str) -> Result<String, RpcClientError> {
+        self.call_rpc("sendrawtransaction", vec![serde_json::json!(tx_hex)])
+            .await
+            .map_err(|e| match e {
+                RpcClientError::RpcError(error) => broadcast_error(tx_hex, error),
+                e => e,
+            })
+    }
+
+    /// Node chain and sync status, failing if the node is on another
+    /// network than this client, e.g. a testnet node for a mainnet client
+    pub async fn get_blockchain_info(&self) -> Result<BlockchainInfo, RpcClientError> {
+        let info: BlockchainInfo = self.call_rpc("getblockchaininfo", vec![]).await?;
+
+        if !self.network.matches_node_chain(&info.chain) {
+            return Err(RpcClientError::ChainMismatch {
+                expected: self.network.as_str().to_string(),
+                actual: info.chain,
+            });
+        }
+
+        Ok(info)
+    }
+
+    /// Mine `blocks` blocks to the node's wallet, e.g. to confirm an HTLC or
+    /// move past its timelock in tests. Regtest only.
+    pub async fn generate(&self, blocks: u32) -> Result<Vec<String>, RpcClientError> {
+        self.ensure_regtest().await?;
+        let hashes: Vec<String> = self
+            .call_rpc("generate", vec![serde_json::json!(blocks)])
+            .await?;
+
+        info!("⛏️  Mined {} regtest block(s)", hashes.len());
+        Ok(hashes)
+    }
+
+    /// Mine `blocks` blocks paying their coinbase to `address`. Regtest only.
+    pub async fn generate_to_address(
+        &self,
+        blocks: u32,
+        address: &str,
+    ) -> Result<Vec<String>, RpcClientError> {
+        self.ensure_regtest().await?;
+        let hashes: Vec<String> = self
+            .call_rpc(
+                "generatetoaddress",
+                vec![serde_json::json!(blocks), serde_json::json!(address)],
+            )
+            .await?;
+
+        info!("⛏️  Mined {} regtest block(s) to {}", hashes.len(), address);
+        Ok(hashes)
+    }
+
+    async fn ensure_regtest(&self) -> Result<(), RpcClientError> {
+        let info = self.get_blockchain_info().await?;
+        if info.chain != "regtest" {
+            return Err(RpcClientError::ChainMismatch {
+                expected: "regtest".to_string(),
+                actual: info.chain,
+            });
+        }
+        Ok(())
+    }
+
+    /// Ask the node whether `address` is valid on its network
+    pub async fn validate_address(
+        &self,
+        address: &str,
+    ) -> Result<AddressValidation, RpcClientError> {
+        self.call_rpc("validateaddress", vec![serde_json::json!(address)])
+            .await
+    }
+
+    /// Get current block height
+    pub async fn get_block_count(&self) -> Result<u64, RpcClientError> {
+        let height: u64 = self.call_rpc("getblockcount", vec![]).await?;
+        self.tx_cache.lock().unwrap().observe_tip(height);
+        Ok(height)
+    }
+
+    /// Hash of the block at `height` on the active chain
+    pub async fn get_block_hash(&self, height: u64) -> Result<String, RpcClientError> {
+        self.call_rpc("getblockhash", vec![serde_json::json!(height)])
+            .await
+    }
+
+    /// Block with its transactions decoded
+    pub async fn get_block(&self, hash: &str) -> Result<Block, RpcClientError> {
+        self.call_rpc(
+            "getblock",
+            vec![serde_json::json!(hash), serde_json::json!(2)],
+        )
+        .await
+    }
+
+    /// Walk blocks `from..=to` of the active chain for outputs and spends
+    /// `watch` is looking for, reading only the node's own data
+    pub async fn scan_blocks(
+        &self,
+        from: u64,
+        to: u64,
+        watch: &mut WatchSet,
+    ) -> Result<Vec<HTLCActivity>, RpcClientError> {
+        let mut activity = Vec::new();
+
+        for height in from..=to {
+            let block = self.get_block(&self.get_block_hash(height).await?).await?;
+            activity.extend(watch.scan_block(&block));
+        }
+
+        Ok(activity)
+    }
+
+    pub async fn get_block_header(&self, hash: &str) -> Result<BlockHeader, RpcClientError> {
+        self.call_rpc(
+            "getblockheader",
+            vec![serde_json::json!(hash), serde_json::json!(true)],
+        )
+        .await
+    }
+
+    /// Median time past of the block `hash`: the median timestamp of it and
+    /// its 10 ancestors, which timestamp lock times must be below. Headers
+    /// are cached, so following the tip costs one `getblockheader` per new
+    /// block.
+    pub async fn get_median_time_past(&self, hash: &str) -> Result<u64, RpcClientError> {
+        let mut times = Vec::with_capacity(MEDIAN_TIME_SPAN);
+        let mut next = Some(hash.to_string());
+
+        while let Some(hash) = next {
+            if times.len() == MEDIAN_TIME_SPAN {
+                break;
+            }
+            let header = self.get_cached_block_header(&hash).await?;
+            times.push(header.time);
+            next = header.previousblockhash;
+        }
+
+        Ok(median_time(times))
+    }
+
+    async fn get_cached_block_header(&self, hash: &str) -> Result<BlockHeader, RpcClientError> {
+        let cached = self
+            .header_cache
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|header| header.hash == hash)
+            .cloned();
+        if let Some(header) = cached {
+            return Ok(header);
+        }
+
+        let header = self.get_block_header(hash).await?;
+        let mut cache = self.header_cache.lock().unwrap();
+        if cache.len() == HEADER_CACHE_SIZE {
+            cache.pop_front();
+        }
+        cache.push_back(header.clone());
+        Ok(header)
+    }
+
+    /// Headers of blocks connected to the active chain from now on, woken
+    /// by block notifications when the client has them and by polling
+    /// every 30 seconds otherwise. Blocks connected between polls are
+    /// yielded in height order; a tip replaced by a reorg is yielded again.
+    pub fn subscribe_blocks(&self) -> impl Stream<Item = BlockHeader> + '_ {
+        let state: (Option<(u64, String)>, VecDeque<BlockHeader>) = (None, VecDeque::new());
+
+        futures_util::stream::unfold(state, move |(mut last, mut pending)| async move {
+            loop {
+                if let Some(header) = pending.pop_front() {
+                    return Some((header, (last, pending)));
+                }
+                if last.is_some() {
+                    self.wait_for_block(CONFIRMATION_POLL_INTERVAL).await;
+                }
+
+                match self.blocks_after(&mut last).await {
+                    Ok(headers) => pending.extend(headers),
+                    Err(e) => warn!("⚠️ Failed to fetch new blocks: {}", e),
+                }
+            }
+        })
+    }
+
+    /// Headers of blocks on the active chain after `last` (`(height,
+    /// hash)`), which is advanced to the tip. Without a `last`, it is only
+    /// set to the tip.
+    async fn blocks_after(
+        &self,
+        last: &mut Option<(u64, String)>,
+    ) -> Result<Vec<BlockHeader>, RpcClientError> {
+        let tip = self.get_block_count().await?;
+        let from = match last {
+            Some((height, _)) if tip > *height => {
+                (*height + 1).max(tip.saturating_sub(BLOCK_CATCH_UP_LIMIT - 1))
+            }
+            Some((_, hash)) => {
+                if self.get_block_hash(tip).await? == *hash {
+                    return Ok(Vec::new());
+                }
+                tip
+            }
+            None => {
+                *last = Some((tip, self.get_block_hash(tip).await?));
+                return Ok(Vec::new());
+            }
+        };
+
+        let mut headers = Vec::new();
+        for height in from..=tip {
+            let header = self
+                .get_block_header(&self.get_block_hash(height).await?)
+                .await?;
+            *last = Some((height, header.hash.clone()));
+            headers.push(header);
+        }
+
+        Ok(headers)
+    }
+
+    /// Lowest height of `checkpoints` (`(height, hash)`, newest first) no
+    /// longer on the active chain, or `None` if the newest still is. When
+    /// none match, the reorg goes deeper than the oldest checkpoint.
+    pub async fn find_fork_height(
+        &self,
+        checkpoints: &[(u64, String)],
+        tip: u64,
+    ) -> Result<Option<u64>, RpcClientError> {
+        let mut fork_height = None;
+
+        for (height, hash) in checkpoints {
+            if *height <= tip && self.get_block_hash(*height).await? == *hash {
+                break;
+            }
+            fork_height = Some(*height);
+        }
+
+        Ok(fork_height)
+    }
+
+    /// Node estimate in zatoshis per kB for confirmation within
+    /// `conf_target` blocks, or `None` without enough data
+    pub async fn estimate_smart_fee(
+        &self,
+        conf_target: u32,
+    ) -> Result<Option<u64>, RpcClientError> {
+        let estimate: SmartFeeEstimate = self
+            .call_rpc("estimatesmartfee", vec![serde_json::json!(conf_target)])
+            .await?;
+        Ok(estimate.feerate.and_then(zec_per_kb_to_zatoshis))
+    }
+
+    /// `estimatefee` rate in zatoshis per kB, or `None` without enough data
+    pub async fn estimate_fee_rate(&self, conf_target: u32) -> Result<Option<u64>, RpcClientError> {
+        let rate: f64 = self
+            .call_rpc("estimatefee", vec![serde_json::json!(conf_target)])
+            .await?;
+        Ok(zec_per_kb_to_zatoshis(rate))
+    }
+
+    /// Median fee rate in zatoshis per kB of the transactions in the mempool
+    pub async fn mempool_fee_rate(&self) -> Result<Option<u64>, RpcClientError> {
+        let entries: HashMap<String, MempoolEntry> = self
+            .call_rpc("getrawmempool", vec![serde_json::json!(true)])
+            .await?;
+
+        let mut rates: Vec<u64> = entries
+            .values()
+            .filter(|entry| entry.size > 0)
+            .map(|entry| (entry.fee * 100_000_000.0 * 1000.0 / entry.size as f64).round() as u64)
+            .collect();
+        rates.sort_unstable();
+
+        Ok(rates.get(rates.len() / 2).copied())
+    }
+
+    /// Fee rate for confirmation within `conf_target` blocks, from
+    /// `estimatesmartfee`, then `estimatefee`, then the mempool. Nodes
+    /// lacking a method or the data for it fall through to the next one.
+    pub async fn estimate_fee(&self, conf_target: u32) -> Result<FeeEstimate, RpcClientError> {
+        let estimate = |fee_per_kb, confidence| FeeEstimate {
+            fee_per_kb,
+            confidence,
+        };
+
+        if let Some(rate) = unless_method_missing(self.estimate_smart_fee(conf_target).await)? {
+            return Ok(estimate(rate, FeeConfidence::High));
+        }
+        if let Some(rate) = unless_method_missing(self.estimate_fee_rate(conf_target).await)? {
+            return Ok(estimate(rate, FeeConfidence::Medium));
+        }
+
+        let rate = self.mempool_fee_rate().await?;
+        Ok(estimate(
+            rate.unwrap_or(crate::builder::DEFAULT_FEE_RATE),
+            FeeConfidence::Low,
+        ))
+    }
+
+    /// UTXOs of `address` from the node's address index (`-insightexplorer`)
+    pub async fn get_address_utxos(&self, address: &str) -> Result<Vec<UTXO>, RpcClientError> {
+        let result: AddressUtxos = self
+            .call_rpc(
+                "getaddressutxos",
+                vec![serde_json::json!({"addresses": [address], "chainInfo": true})],
+            )
+            .await?;
+
+        Ok(result
+            .utxos
+            .into_iter()
+            .map(|u| UTXO {
+                txid: u.txid,
+                vout: u.output_index,
+                amount: zatoshis_to_zec(u.satoshis),
+                script_pubkey: u.script,
+                confirmations: match result.height.checked_sub(u.height) {
+                    Some(depth) => (depth + 1) as u32,
+                    None => 0,
+                },
+            })
+            .collect())
+    }
+
+    /// Balance of `address` in ZEC from the node's address index
+    pub async fn get_address_balance(&self, address: &str) -> Result<String, RpcClientError> {
+        let result: AddressBalance = self
+            .call_rpc(
+                "getaddressbalance",
+                vec![serde_json::json!({"addresses": [address]})],
+            )
+            .await?;
+
+        Ok(zatoshis_to_zec(result.balance.max(0) as u64))
     }
 
     /// Get transaction details
     pub async fn get_raw_transaction(&self, txid: &str) -> Result<RawTransaction, RpcClientError> {
+        if let Some(tx) = self.tx_cache.lock().unwrap().get(txid) {
+            return Ok(tx);
+        }
+
         let tx: RawTransaction = self
             .call_rpc(
                 "getrawtransaction",
                 vec![serde_json::json!(txid), serde_json::json!(true)],
             )
             .await?;
+        self.tx_cache.lock().unwrap().insert(tx.clone());
         Ok(tx)
     }
 
+    /// Unspent output `txid:vout`, or `None` once it is spent. With
+    /// `include_mempool`, outputs spent by mempool transactions count as spent.
+    pub async fn get_tx_out(
+        &self,
+        txid: &str,
+        vout: u32,
+        include_mempool: bool,
+    ) -> Result<Option<TxOutInfo>, RpcClientError> {
+        let params = vec![
+            serde_json::json!(txid),
+            serde_json::json!(vout),
+            serde_json::json!(include_mempool),
+        ];
+
+        // Spent or unknown outputs come back as a null result
+        match self.call_rpc("gettxout", params).await {
+            Ok(output) => Ok(Some(output)),
+            Err(RpcClientError::NoResult) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Get transaction confirmations
     pub async fn get_transaction_confirmations(&self, txid: &str) -> Result<u32, RpcClientError> {
         let tx = self.get_raw_transaction(txid).await?;
@@ -124,23 +1158,64 @@ impl ZcashRpcClient {
         txid: &str,
         required_confirmations: u32,
         max_attempts: u32,
+    ) -> Result<u32, RpcClientError> {
+        let wait = ConfirmationWait::new().with_max_attempts(max_attempts);
+        self.wait_for_confirmations_with(txid, required_confirmations, &wait)
+            .await
+    }
+
+    /// Wait for `required_confirmations` polling as `wait` says, failing
+    /// with [`RpcClientError::Cancelled`] as soon as its token is cancelled
+    pub async fn wait_for_confirmations_with(
+        &self,
+        txid: &str,
+        required_confirmations: u32,
+        wait: &ConfirmationWait,
+    ) -> Result<u32, RpcClientError> {
+        let poll = self.poll_confirmations(txid, required_confirmations, wait);
+
+        match &wait.cancel {
+            Some(token) => tokio::select! {
+                _ = token.cancelled() => {
+                    info!("🛑 Stopped waiting for confirmations on tx: {}", txid);
+                    Err(RpcClientError::Cancelled)
+                }
+                result = poll => result,
+            },
+            None => poll.await,
+        }
+    }
+
+    async fn poll_confirmations(
+        &self,
+        txid: &str,
+        required_confirmations: u32,
+        wait: &ConfirmationWait,
     ) -> Result<u32, RpcClientError> {
         info!(
             "⏳ Waiting for {} confirmations on tx: {}",
             required_confirmations, txid
         );
 
-        for attempt in 1..=max_attempts {
+        let started = Instant::now();
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
             match self.get_transaction_confirmations(txid).await {
                 Ok(confirmations) => {
+                    if let Some(on_progress) = &wait.on_progress {
+                        on_progress(&ConfirmationProgress {
+                            txid: txid.to_string(),
+                            confirmations,
+                            required_confirmations,
+                            attempt,
+                        });
+                    }
                     if confirmations >= required_confirmations {
                         info!("✅ Transaction confirmed: {} confirmations", confirmations);
                         return Ok(confirmations);
                     }
-                    info!(
-                        "⏳ Attempt {}/{}: {} confirmations",
-                        attempt, max_attempts, confirmations
-                    );
+                    info!("⏳ Attempt {}: {} confirmations", attempt, confirmations);
                 }
                 Err(e) => {
                     warn!(
@@ -150,91 +1225,157 @@ impl ZcashRpcClient {
                 }
             }
 
-            tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
+            let remaining = wait
+                .deadline
+                .map(|deadline| deadline.saturating_sub(started.elapsed()));
+            if wait.max_attempts.is_some_and(|max| attempt >= max)
+                || remaining == Some(Duration::ZERO)
+            {
+                return Err(RpcClientError::ConfirmationTimeout {
+                    txid: txid.to_string(),
+                    attempts: attempt,
+                });
+            }
+
+            let interval = remaining.map_or(wait.poll_interval, |remaining| {
+                remaining.min(wait.poll_interval)
+            });
+            self.wait_for_block(interval).await;
         }
+    }
 
-        Err(RpcClientError::ConfirmationTimeout {
-            txid: txid.to_string(),
-            attempts: max_attempts,
+    /// Sleep until the next block notification, or for `timeout` without
+    /// notifications
+    async fn wait_for_block(&self, timeout: Duration) {
+        let Some(events) = &self.block_events else {
+            tokio::time::sleep(timeout).await;
+            return;
+        };
+
+        let mut events = events.subscribe();
+        let _ = tokio::time::timeout(timeout, async {
+            loop {
+                match events.recv().await {
+                    Ok(NodeEvent::Block { .. }) | Err(broadcast::error::RecvError::Lagged(_)) => {
+                        return
+                    }
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Closed) => std::future::pending().await,
+                }
+            }
         })
+        .await;
     }
 
     // ==================== Block Explorer Methods ====================
 
-    /// Query UTXOs for an address using block explorer
-    // pub async fn get_utxos(&self, address: &str) -> Result<Vec<UTXO>, RpcClientError> {
-    //     info!("🔍 Querying UTXOs for address: {}", address);
-    //
-    //     let url = format!("{}/v2/utxo/{}", self.explorer_api, address);
-
-    //     let response = self
-    //         .client
-    //         .get(&url)
-    //         .send()
-    //         .await
-    //         .map_err(|e| RpcClientError::NetworkError(e.to_string()))?;
-
-    //     if !response.status().is_success() {
-    //         return Err(RpcClientError::ExplorerError(format!(
-    //             "HTTP {} from explorer",
-    //             response.status()
-    //         )));
-    //     }
-
-    //     let utxos: Vec<ExplorerUTXO> = response
-    //         .json()
-    //         .await
-    //         .map_err(|e| RpcClientError::ParseError(e.to_string()))?;
-
-    //     let converted: Vec<UTXO> = utxos
-    //         .into_iter()
-    //         .map(|u| UTXO {
-    //             txid: u.txid,
-    //             vout: u.vout,
-    //             amount: self.zatoshi_to_zec(u.value),
-    //             script_pubkey: u.script_pubkey.unwrap_or_default(),
-    //             confirmations: u.confirmations.unwrap_or(0),
-    //         })
-    //         .collect();
-
-    //    Ok(converted)
-    // }
-
-    // /// Get address balance
-    // pub async fn get_balance(&self, address: &str) -> Result<String, RpcClientError> {
-    //     info!("💰 Querying balance for address: {}", address);
-
-    //     let url = format!("{}/v2/address/{}", self.explorer_api, address);
-
-    //     let response = self
-    //         .client
-    //         .get(&url)
-    //         .send()
-    //         .await
-    //         .map_err(|e| RpcClientError::NetworkError(e.to_string()))?;
-
-    //     if !response.status().is_success() {
-    //         return Err(RpcClientError::ExplorerError(format!(
-    //             "HTTP {} from explorer",
-    //             response.status()
-    //         )));
-    //     }
-
-    //     #[derive(Deserialize)]
-    //     struct AddressInfo {
-    //         balance: u64,
-    //     }
-
-    //     let info: AddressInfo = response
-    //         .json()
-    //         .await
-    //         .map_err(|e| RpcClientError::ParseError(e.to_string()))?;
-
-    //     let balance_zec = self.zatoshi_to_zec(info.balance);
-    //     info!("✅ Balance: {} ZEC", balance_zec);
-
-    //     Ok(balance_zec)
-    // }
+    /// Explorer backend, sharing this client's TLS, proxy, timeouts and
+    /// per-explorer rate limits
+    pub fn explorer(&self) -> FallbackExplorer {
+        FallbackExplorer::new(
+            self.explorers
+                .iter()
+                .map(|endpoint| endpoint.backend(self.client.clone()))
+                .collect(),
+        )
+    }
+
+    /// Forget cached transactions, e.g. after a reorg that no block
+    /// notification announced
+    pub fn clear_tx_cache(&self) {
+        self.tx_cache.lock().unwrap().clear();
+    }
+
+    /// Whether the node has an address index, once a query has probed it
+    pub fn has_address_index(&self) -> Option<bool> {
+        *self.address_index.lock().unwrap()
+    }
+
+    /// Run `query` against the node's address index unless probing showed
+    /// there is none. `None` means the explorers should answer instead.
+    async fn query_address_index<T>(
+        &self,
+        query: impl Future<Output = Result<T, RpcClientError>>,
+    ) -> Option<T> {
+        if self.has_address_index() == Some(false) {
+            return None;
+        }
+
+        match query.await {
+            Ok(result) => {
+                if self.address_index.lock().unwrap().replace(true).is_none() {
+                    info!("🗂️ Node has an address index, querying it instead of explorers");
+                }
+                Some(result)
+            }
+            Err(e) if e.is_index_unavailable() => {
+                info!("🗂️ Node has no address index, querying explorers");
+                *self.address_index.lock().unwrap() = Some(false);
+                None
+            }
+            Err(e) => {
+                warn!("⚠️ Address index query failed: {}, asking explorers", e);
+                None
+            }
+        }
+    }
+
+    /// Query UTXOs for an address, from the node's address index when it
+    /// has one and the block explorers otherwise
+    pub async fn get_utxos(&self, address: &str) -> Result<Vec<UTXO>, RpcClientError> {
+        Ok(self.get_utxos_with_source(address).await?.0)
+    }
+
+    /// [`get_utxos`](Self::get_utxos) with the URL of the explorer that
+    /// answered, `None` if the node's address index did
+    pub async fn get_utxos_with_source(
+        &self,
+        address: &str,
+    ) -> Result<(Vec<UTXO>, Option<String>), RpcClientError> {
+        info!("🔍 Querying UTXOs for address: {}", address);
+
+        if let Some(utxos) = self
+            .query_address_index(self.get_address_utxos(address))
+            .await
+        {
+            return Ok((utxos, None));
+        }
+
+        let explorer = self.explorer();
+        let utxos = explorer.get_utxos(address).await?;
+
+        Ok((utxos, explorer_source(&explorer)))
+    }
+
+    /// Get address balance in ZEC, from the node's address index when it
+    /// has one and the block explorers otherwise
+    pub async fn get_balance(&self, address: &str) -> Result<String, RpcClientError> {
+        Ok(self.get_balance_with_source(address).await?.0)
+    }
+
+    /// [`get_balance`](Self::get_balance) with the URL of the explorer that
+    /// answered, `None` if the node's address index did
+    pub async fn get_balance_with_source(
+        &self,
+        address: &str,
+    ) -> Result<(String, Option<String>), RpcClientError> {
+        info!("💰 Querying balance for address: {}", address);
+
+        let (balance, source) = match self
+            .query_address_index(self.get_address_balance(address))
+            .await
+        {
+            Some(balance) => (balance, None),
+            None => {
+                let explorer = self.explorer();
+                let balance = explorer.get_balance(address).await?;
+                (balance, explorer_source(&explorer))
+            }
+        };
+        info!("✅ Balance: {} ZEC", balance);
+
+        Ok((balance, source))
+    }
 
     // Check if transaction is confirmed
     pub async fn is_transaction_confirmed(
@@ -247,14 +1388,15 @@ impl ZcashRpcClient {
             Err(_) => Ok(false),
         }
     }
+}
 
-    // ==================== Helper Methods ====================
-
-    #[allow(dead_code)]
-    fn zatoshi_to_zec(&self, zatoshis: u64) -> String {
-        let zec = zatoshis as f64 / 100_000_000.0;
-        format!("{:.8}", zec)
+/// URL of the explorer of `explorer` that answered its query
+fn explorer_source(explorer: &FallbackExplorer) -> Option<String> {
+    let source = explorer.answered_by();
+    if let Some(fallback) = source.as_deref().filter(|url| *url != explorer.url()) {
+        info!("↪️ Answered by fallback explorer {}", fallback);
     }
+    source
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -271,11 +1413,207 @@ pub enum RpcClientError {
     #[error("No result in RPC response")]
     NoResult,
 
+    #[error("HTTP {0} from RPC endpoint")]
+    HttpStatus(u16),
+
+    #[error("RPC credentials rejected")]
+    Unauthorized,
+
+    #[error("Cookie file error: {0}")]
+    CookieError(String),
+
+    #[error("Proxy configuration error: {0}")]
+    ProxyError(String),
+
+    #[error("TLS configuration error: {0}")]
+    TlsError(String),
+
+    #[error("Request timed out: {0}")]
+    RequestTimeout(String),
+
+    #[error("RPC call {method} timed out after {timeout:?}")]
+    CallTimeout { method: String, timeout: Duration },
+
+    #[error("Node is on chain {actual}, expected {expected}")]
+    ChainMismatch { expected: String, actual: String },
+
     #[error("Explorer error: {0}")]
     ExplorerError(String),
 
     #[error("Confirmation timeout for {txid} after {attempts} attempts")]
     ConfirmationTimeout { txid: String, attempts: u32 },
+
+    #[error("Cancelled")]
+    Cancelled,
+
+    #[error("Transaction {txid} is already in the mempool or chain")]
+    AlreadyBroadcast { txid: String },
+
+    #[error("Transaction conflicts with one in the mempool: {0}")]
+    MempoolConflict(String),
+
+    #[error("Transaction inputs are missing or spent: {0}")]
+    MissingInputs(String),
+
+    #[error("Transaction fee is absurdly high: {0}")]
+    AbsurdFee(String),
+
+    #[error("Transaction expired: {0}")]
+    TransactionExpired(String),
+
+    #[error("Node broadcast {node_txid} but explorer returned {explorer_txid}")]
+    BroadcastConflict {
+        node_txid: String,
+        explorer_txid: String,
+    },
+}
+
+fn read_pem(path: &str) -> Result<Vec<u8>, RpcClientError> {
+    fs::read(path).map_err(|e| RpcClientError::TlsError(format!("{}: {}", path, e)))
+}
+
+/// Typed error for a `sendrawtransaction` rejection, going by zcashd's
+/// reject reasons
+fn broadcast_error(tx_hex: &str, error: RpcError) -> RpcClientError {
+    let message = error.message.to_lowercase();
+    let reason = error.message.clone();
+
+    if error.code == RPC_TRANSACTION_ALREADY_IN_CHAIN
+        || message.contains("txn-already-in-mempool")
+        || message.contains("txn-already-known")
+    {
+        match hex::decode(tx_hex) {
+            Ok(bytes) => RpcClientError::AlreadyBroadcast {
+                txid: <bitcoin::Txid as bitcoin::hashes::Hash>::hash(&bytes).to_string(),
+            },
+            Err(_) => RpcClientError::RpcError(error),
+        }
+    } else if message.contains("txn-mempool-conflict") {
+        RpcClientError::MempoolConflict(reason)
+    } else if message.contains("missing inputs") || message.contains("bad-txns-inputs-spent") {
+        RpcClientError::MissingInputs(reason)
+    } else if message.contains("absurdly-high-fee") {
+        RpcClientError::AbsurdFee(reason)
+    } else if message.contains("tx-expired") || message.contains("tx-overwinter-expired") {
+        RpcClientError::TransactionExpired(reason)
+    } else {
+        RpcClientError::RpcError(error)
+    }
+}
+
+/// Txid agreed on by the node and explorer broadcasts. The node's verdict
+/// wins unless it could not be reached; a failed explorer push only warns.
+fn reconcile_broadcasts(
+    node: Result<String, RpcClientError>,
+    explorer: Result<String, RpcClientError>,
+) -> Result<String, RpcClientError> {
+    let node_txid = match &node {
+        Ok(txid) | Err(RpcClientError::AlreadyBroadcast { txid }) => Some(txid.clone()),
+        _ => None,
+    };
+
+    match (node, explorer) {
+        (node, Ok(explorer_txid)) => match node_txid {
+            Some(node_txid) if node_txid != explorer_txid => {
+                Err(RpcClientError::BroadcastConflict {
+                    node_txid,
+                    explorer_txid,
+                })
+            }
+            Some(_) => node,
+            None => match node {
+                Err(e) if e.is_retryable() || matches!(e, RpcClientError::CallTimeout { .. }) => {
+                    warn!("⚠️ Node broadcast failed: {}, explorer accepted it", e);
+                    Ok(explorer_txid)
+                }
+                node => node,
+            },
+        },
+        (node, Err(e)) => {
+            warn!("⚠️ Explorer push failed: {}", e);
+            node
+        }
+    }
+}
+
+/// Median of block `times`; the upper one of an even count, as nodes do
+fn median_time(mut times: Vec<u64>) -> u64 {
+    times.sort_unstable();
+    times.get(times.len() / 2).copied().unwrap_or(0)
+}
+
+/// Node fee rates are ZEC per kB; -1 or 0 mean there is no estimate
+fn zec_per_kb_to_zatoshis(rate: f64) -> Option<u64> {
+    (rate > 0.0).then(|| (rate * 100_000_000.0).round() as u64)
+}
+
+/// `None` in place of an error from a node without the method
+fn unless_method_missing<T>(
+    result: Result<Option<T>, RpcClientError>,
+) -> Result<Option<T>, RpcClientError> {
+    match result {
+        Err(RpcClientError::RpcError(e)) if e.code == RPC_METHOD_NOT_FOUND => Ok(None),
+        result => result,
+    }
+}
+
+pub(crate) fn request_error(e: reqwest::Error) -> RpcClientError {
+    if e.is_timeout() {
+        RpcClientError::RequestTimeout(e.to_string())
+    } else {
+        RpcClientError::NetworkError(e.to_string())
+    }
+}
+
+impl RpcClientError {
+    /// Transient failures worth retrying, possibly on another endpoint
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            RpcClientError::NetworkError(_)
+            | RpcClientError::HttpStatus(_)
+            | RpcClientError::RequestTimeout(_) => true,
+            RpcClientError::RpcError(error) => error.code == RPC_IN_WARMUP,
+            _ => false,
+        }
+    }
+
+    /// Short label of the error variant, e.g. for metrics
+    pub fn kind(&self) -> &'static str {
+        match self {
+            RpcClientError::NetworkError(_) => "network",
+            RpcClientError::ParseError(_) => "parse",
+            RpcClientError::RpcError(_) => "rpc_error",
+            RpcClientError::NoResult => "no_result",
+            RpcClientError::HttpStatus(_) => "http_status",
+            RpcClientError::Unauthorized => "unauthorized",
+            RpcClientError::CookieError(_) => "cookie",
+            RpcClientError::ProxyError(_) => "proxy",
+            RpcClientError::TlsError(_) => "tls",
+            RpcClientError::RequestTimeout(_) => "request_timeout",
+            RpcClientError::CallTimeout { .. } => "call_timeout",
+            RpcClientError::ChainMismatch { .. } => "chain_mismatch",
+            RpcClientError::ExplorerError(_) => "explorer",
+            RpcClientError::ConfirmationTimeout { .. } => "confirmation_timeout",
+            RpcClientError::Cancelled => "cancelled",
+            RpcClientError::AlreadyBroadcast { .. } => "already_broadcast",
+            RpcClientError::MempoolConflict(_) => "mempool_conflict",
+            RpcClientError::MissingInputs(_) => "missing_inputs",
+            RpcClientError::AbsurdFee(_) => "absurd_fee",
+            RpcClientError::TransactionExpired(_) => "transaction_expired",
+            RpcClientError::BroadcastConflict { .. } => "broadcast_conflict",
+        }
+    }
+
+    /// The node lacks the address index RPCs, or has them disabled because
+    /// it runs without `-insightexplorer`
+    pub fn is_index_unavailable(&self) -> bool {
+        match self {
+            RpcClientError::RpcError(error) => {
+                error.code == RPC_METHOD_NOT_FOUND || error.message.contains("is disabled")
+            }
+            _ => false,
+        }
+    }
 }
 
 impl std::fmt::Display for RpcError {
@@ -283,3 +1621,741 @@ impl std::fmt::Display for RpcError {
         write!(f, "Code {}: {}", self.code, self.message)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{method_node, mock_node, shared_method_node, MockServer};
+
+    #[tokio::test]
+    async fn test_fails_over_and_quarantines_dead_primary() {
+        let client = ZcashRpcClient::new(
+            MockServer::unreachable().await,
+            None,
+            None,
+            ZcashNetwork::Testnet,
+        )
+        .with_fallback_urls(vec![mock_node(0, serde_json::json!(2_500_000)).await])
+        .with_retry_policy(RetryPolicy::none());
+
+        for _ in 0..QUARANTINE_AFTER_FAILURES {
+            assert_eq!(client.get_block_count().await.unwrap(), 2_500_000);
+        }
+        assert!(client.endpoints[0].is_quarantined());
+        assert!(!client.endpoints[1].is_quarantined());
+
+        // Quarantined primary is skipped while the fallback is healthy
+        assert_eq!(client.get_block_count().await.unwrap(), 2_500_000);
+        assert_eq!(
+            client.endpoints[0]
+                .health
+                .lock()
+                .unwrap()
+                .consecutive_failures,
+            QUARANTINE_AFTER_FAILURES
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_gateway_errors() {
+        let retry = RetryPolicy {
+            max_attempts: 3,
+            base_delay_ms: 1,
+            max_delay_ms: 5,
+            jitter: 0.5,
+        };
+
+        let url = mock_node(2, serde_json::json!(42)).await;
+        let client = ZcashRpcClient::new(url, None, None, ZcashNetwork::Testnet)
+            .with_retry_policy(retry.clone());
+        assert_eq!(client.get_block_count().await.unwrap(), 42);
+
+        let url = mock_node(3, serde_json::json!(42)).await;
+        let client =
+            ZcashRpcClient::new(url, None, None, ZcashNetwork::Testnet).with_retry_policy(retry);
+        assert!(matches!(
+            client.get_block_count().await,
+            Err(RpcClientError::HttpStatus(502))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_tx_out_reports_spent_outputs() {
+        let spent = ZcashRpcClient::new(
+            mock_node(0, Value::Null).await,
+            None,
+            None,
+            ZcashNetwork::Testnet,
+        );
+        assert!(spent
+            .get_tx_out(&"11".repeat(32), 0, true)
+            .await
+            .unwrap()
+            .is_none());
+
+        let unspent_output = serde_json::json!({
+            "bestblock": "00".repeat(32),
+            "confirmations": 3,
+            "value": 1.5,
+            "scriptPubKey": {"hex": "a914", "type": "scripthash"},
+            "coinbase": false
+        });
+        let unspent = ZcashRpcClient::new(
+            mock_node(0, unspent_output).await,
+            None,
+            None,
+            ZcashNetwork::Testnet,
+        );
+        let output = unspent
+            .get_tx_out(&"11".repeat(32), 0, true)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(output.confirmations, 3);
+        assert_eq!(output.script_pubkey.script_type, "scripthash");
+    }
+
+    #[tokio::test]
+    async fn test_find_fork_height() {
+        let client = ZcashRpcClient::new(
+            mock_node(0, serde_json::json!("aa".repeat(32))).await,
+            None,
+            None,
+            ZcashNetwork::Testnet,
+        );
+        let checkpoints = vec![
+            (12, "cc".repeat(32)),
+            (11, "bb".repeat(32)),
+            (10, "aa".repeat(32)),
+        ];
+
+        assert_eq!(
+            client.find_fork_height(&checkpoints, 12).await.unwrap(),
+            Some(11)
+        );
+        assert_eq!(
+            client
+                .find_fork_height(&checkpoints[2..], 12)
+                .await
+                .unwrap(),
+            None
+        );
+        // Chain got shorter than the newest checkpoint
+        assert_eq!(
+            client
+                .find_fork_height(&checkpoints[1..], 10)
+                .await
+                .unwrap(),
+            Some(11)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_blockchain_info_checks_chain_and_sync() {
+        let syncing = serde_json::json!({
+            "chain": "test",
+            "blocks": 2_000_000,
+            "headers": 2_400_000,
+            "bestblockhash": "00".repeat(32),
+            "verificationprogress": 0.8,
+            "estimatedheight": 2_500_000
+        });
+        let node = mock_node(0, syncing).await;
+
+        let testnet = ZcashRpcClient::new(node.clone(), None, None, ZcashNetwork::Testnet);
+        let info = testnet.get_blockchain_info().await.unwrap();
+        assert_eq!(info.target_height(), 2_500_000);
+        assert!(!info.is_synced());
+
+        let mainnet = ZcashRpcClient::new(node, None, None, ZcashNetwork::Mainnet);
+        assert!(matches!(
+            mainnet.get_blockchain_info().await,
+            Err(RpcClientError::ChainMismatch { actual, .. }) if actual == "test"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_validate_address() {
+        let client = ZcashRpcClient::new(
+            mock_node(0, serde_json::json!({"isvalid": false})).await,
+            None,
+            None,
+            ZcashNetwork::Mainnet,
+        );
+
+        let validation = client
+            .validate_address("tmEZhbWHTpdKMw5it8YDspUXSMGQyFwovpU")
+            .await
+            .unwrap();
+        assert!(!validation.isvalid);
+        assert!(validation.address.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_queries_node_address_index() {
+        let address = "tmEZhbWHTpdKMw5it8YDspUXSMGQyFwovpU";
+        let client = ZcashRpcClient::new(
+            mock_node(
+                0,
+                serde_json::json!({
+                    "utxos": [{
+                        "address": address,
+                        "txid": "11".repeat(32),
+                        "outputIndex": 1,
+                        "script": "76a914",
+                        "satoshis": 25_000_000u64,
+                        "height": 100
+                    }],
+                    "hash": "00".repeat(32),
+                    "height": 104
+                }),
+            )
+            .await,
+            None,
+            None,
+            ZcashNetwork::Testnet,
+        );
+        assert_eq!(client.has_address_index(), None);
+
+        let (utxos, source) = client.get_utxos_with_source(address).await.unwrap();
+        assert_eq!(source, None);
+        assert_eq!(utxos.len(), 1);
+        assert_eq!(utxos[0].vout, 1);
+        assert_eq!(utxos[0].amount, "0.25000000");
+        assert_eq!(utxos[0].confirmations, 5);
+        assert_eq!(client.has_address_index(), Some(true));
+
+        let disabled = RpcClientError::RpcError(RpcError {
+            code: -1,
+            message: "Error: getaddressutxos is disabled.".to_string(),
+        });
+        assert!(disabled.is_index_unavailable());
+        assert!(!RpcClientError::NoResult.is_index_unavailable());
+    }
+
+    #[tokio::test]
+    async fn test_reports_the_answering_explorer_with_the_result() {
+        let address = "tmEZhbWHTpdKMw5it8YDspUXSMGQyFwovpU";
+        let broken = MockServer::respond("502 Bad Gateway", serde_json::json!({})).await;
+        let backup = MockServer::respond("200 OK", serde_json::json!(150_000_000u64)).await;
+        let client = ZcashRpcClient::new(
+            method_node(serde_json::json!({})).await,
+            None,
+            None,
+            ZcashNetwork::Testnet,
+        )
+        .with_explorer(ExplorerConfig::new(ExplorerKind::Insight, &broken.url))
+        .with_explorer_fallbacks(vec![ExplorerConfig::new(
+            ExplorerKind::Insight,
+            &backup.url,
+        )]);
+
+        let (balance, source) = client.get_balance_with_source(address).await.unwrap();
+        assert_eq!(balance, "1.50000000");
+        assert_eq!(source, Some(backup.url.clone()));
+        assert_eq!(client.has_address_index(), Some(false));
+    }
+
+    #[tokio::test]
+    async fn test_confirmation_wait_deadline_and_cancellation() {
+        let txid = "11".repeat(32);
+        let client = ZcashRpcClient::new(
+            mock_node(
+                0,
+                serde_json::json!({
+                    "txid": txid,
+                    "version": 4,
+                    "locktime": 0,
+                    "vin": [],
+                    "vout": [],
+                    "confirmations": 1
+                }),
+            )
+            .await,
+            None,
+            None,
+            ZcashNetwork::Mainnet,
+        );
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let progress = seen.clone();
+        let wait = ConfirmationWait::new()
+            .with_poll_interval(Duration::from_millis(10))
+            .with_deadline(Duration::from_millis(100))
+            .with_progress(move |p| progress.lock().unwrap().push(p.confirmations));
+        assert!(matches!(
+            client.wait_for_confirmations_with(&txid, 6, &wait).await,
+            Err(RpcClientError::ConfirmationTimeout { attempts, .. }) if attempts > 1
+        ));
+        assert!(seen.lock().unwrap().iter().all(|&c| c == 1));
+        assert_eq!(
+            client
+                .wait_for_confirmations_with(&txid, 1, &wait)
+                .await
+                .unwrap(),
+            1
+        );
+
+        let token = CancellationToken::new();
+        let wait = ConfirmationWait::new().with_cancellation(token.clone());
+        let cancel = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            token.cancel();
+        });
+        let started = Instant::now();
+        assert!(matches!(
+            client.wait_for_confirmations_with(&txid, 6, &wait).await,
+            Err(RpcClientError::Cancelled)
+        ));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        cancel.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_generate_is_regtest_only() {
+        let info = |chain: &str| {
+            serde_json::json!({
+                "chain": chain,
+                "blocks": 0,
+                "headers": 0,
+                "bestblockhash": "00",
+                "verificationprogress": 1.0
+            })
+        };
+        let hashes = serde_json::json!(["aa", "bb"]);
+
+        let regtest = ZcashRpcClient::new(
+            method_node(serde_json::json!({
+                "getblockchaininfo": info("regtest"),
+                "generate": hashes,
+                "generatetoaddress": hashes
+            }))
+            .await,
+            None,
+            None,
+            ZcashNetwork::Testnet,
+        );
+        assert_eq!(regtest.generate(2).await.unwrap(), vec!["aa", "bb"]);
+        assert_eq!(
+            regtest.generate_to_address(2, "tmXYZ").await.unwrap().len(),
+            2
+        );
+
+        let testnet = ZcashRpcClient::new(
+            method_node(serde_json::json!({
+                "getblockchaininfo": info("test"),
+                "generate": hashes
+            }))
+            .await,
+            None,
+            None,
+            ZcashNetwork::Testnet,
+        );
+        assert!(matches!(
+            testnet.generate(1).await,
+            Err(RpcClientError::ChainMismatch { expected, .. }) if expected == "regtest"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_blocks_catches_up_and_follows_reorgs() {
+        use futures_util::StreamExt;
+
+        let header = |hash: &str| serde_json::json!({"hash": hash, "height": 12, "time": 0, "previousblockhash": null});
+        let results = Arc::new(Mutex::new(serde_json::json!({
+            "getblockcount": 10,
+            "getblockhash": "aa",
+            "getblockheader": header("aa")
+        })));
+        let (events, _) = broadcast::channel(16);
+        let client = ZcashRpcClient::new(
+            shared_method_node(results.clone()).await,
+            None,
+            None,
+            ZcashNetwork::Mainnet,
+        )
+        .with_block_notifications(events.clone());
+
+        let blocks = client.subscribe_blocks();
+        tokio::pin!(blocks);
+        let connect = |count: u64, hash: &str| {
+            let mut results = results.lock().unwrap();
+            results["getblockcount"] = count.into();
+            results["getblockhash"] = hash.into();
+            results["getblockheader"] = header(hash);
+            events
+                .send(NodeEvent::Block {
+                    hash: hash.to_string(),
+                })
+                .unwrap();
+        };
+        let after_block = |count: u64, hash: &'static str| async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            connect(count, hash);
+        };
+
+        // Two blocks connected at once are both yielded
+        let (first, _) = tokio::join!(blocks.next(), after_block(12, "bb"));
+        assert_eq!(first.unwrap().hash, "bb");
+        assert_eq!(blocks.next().await.unwrap().hash, "bb");
+
+        // A tip replaced at the same height is yielded again
+        let (replaced, _) = tokio::join!(blocks.next(), after_block(12, "cc"));
+        assert_eq!(replaced.unwrap().hash, "cc");
+    }
+
+    #[tokio::test]
+    async fn test_median_time_past_reuses_cached_headers() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // Block `h<n>` at height n with time n * 100
+        let fetched = Arc::new(AtomicUsize::new(0));
+        let counter = fetched.clone();
+        let server = MockServer::start(move |request| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let hash = request.json()["params"][0].as_str().unwrap().to_string();
+            let height: u64 = hash[1..].parse().unwrap();
+            let body = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": "1",
+                "result": {
+                    "hash": hash,
+                    "height": height,
+                    "time": height * 100,
+                    "previousblockhash": (height > 0).then(|| format!("h{}", height - 1)),
+                },
+            });
+            ("200 OK", body.to_string())
+        })
+        .await;
+        let client = ZcashRpcClient::new(server.url.clone(), None, None, ZcashNetwork::Mainnet);
+
+        assert_eq!(client.get_median_time_past("h20").await.unwrap(), 1_500);
+        assert_eq!(fetched.load(Ordering::SeqCst), MEDIAN_TIME_SPAN);
+        // The next tip only needs its own header
+        assert_eq!(client.get_median_time_past("h21").await.unwrap(), 1_600);
+        assert_eq!(client.get_median_time_past("h21").await.unwrap(), 1_600);
+        assert_eq!(fetched.load(Ordering::SeqCst), MEDIAN_TIME_SPAN + 1);
+    }
+
+    #[test]
+    fn test_median_time() {
+        let times = vec![
+            1_700_000_600,
+            1_700_000_000,
+            1_700_000_300,
+            1_700_000_900,
+            1_700_000_150,
+        ];
+        assert_eq!(median_time(times), 1_700_000_300);
+        // Out of order block times near genesis
+        assert_eq!(median_time(vec![20, 10]), 20);
+        assert_eq!(median_time(vec![42]), 42);
+    }
+
+    #[test]
+    fn test_reconcile_dual_broadcast() {
+        let txid = || Ok::<_, RpcClientError>("aa".to_string());
+        let unreachable = || Err(RpcClientError::NetworkError("refused".to_string()));
+
+        assert_eq!(reconcile_broadcasts(txid(), txid()).unwrap(), "aa");
+        assert_eq!(reconcile_broadcasts(txid(), unreachable()).unwrap(), "aa");
+        assert_eq!(reconcile_broadcasts(unreachable(), txid()).unwrap(), "aa");
+        assert!(matches!(
+            reconcile_broadcasts(txid(), Ok("bb".to_string())),
+            Err(RpcClientError::BroadcastConflict { node_txid, explorer_txid })
+                if node_txid == "aa" && explorer_txid == "bb"
+        ));
+        assert!(matches!(
+            reconcile_broadcasts(
+                Err(RpcClientError::MissingInputs("spent".to_string())),
+                txid()
+            ),
+            Err(RpcClientError::MissingInputs(_))
+        ));
+        assert!(matches!(
+            reconcile_broadcasts(
+                Err(RpcClientError::AlreadyBroadcast {
+                    txid: "aa".to_string()
+                }),
+                txid()
+            ),
+            Err(RpcClientError::AlreadyBroadcast { .. })
+        ));
+    }
+
+    #[test]
+    fn test_broadcast_errors_are_typed() {
+        let rejected = |code: i32, message: &str| {
+            broadcast_error(
+                "0400",
+                RpcError {
+                    code,
+                    message: message.to_string(),
+                },
+            )
+        };
+
+        assert!(matches!(
+            rejected(-27, "transaction already in block chain"),
+            RpcClientError::AlreadyBroadcast { txid } if txid.len() == 64
+        ));
+        assert!(matches!(
+            rejected(-26, "18: txn-already-in-mempool"),
+            RpcClientError::AlreadyBroadcast { .. }
+        ));
+        assert!(matches!(
+            rejected(-26, "18: txn-mempool-conflict"),
+            RpcClientError::MempoolConflict(_)
+        ));
+        assert!(matches!(
+            rejected(-25, "Missing inputs"),
+            RpcClientError::MissingInputs(_)
+        ));
+        assert!(matches!(
+            rejected(-26, "256: absurdly-high-fee"),
+            RpcClientError::AbsurdFee(_)
+        ));
+        assert!(matches!(
+            rejected(-26, "16: tx-overwinter-expired"),
+            RpcClientError::TransactionExpired(_)
+        ));
+        assert!(matches!(
+            rejected(-26, "16: bad-txns-vout-negative"),
+            RpcClientError::RpcError(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_http_pool_config() {
+        let pool: HttpPoolConfig =
+            toml::from_str("max_idle_per_host = 4\nidle_timeout_secs = 30").unwrap();
+        assert!(!pool.http2_prior_knowledge);
+
+        let client = ZcashRpcClient::new(
+            mock_node(0, serde_json::json!(42)).await,
+            None,
+            None,
+            ZcashNetwork::Mainnet,
+        )
+        .with_http_pool(&HttpPoolConfig {
+            tcp_keepalive_secs: Some(60),
+            ..pool
+        });
+        for _ in 0..3 {
+            assert_eq!(client.get_block_count().await.unwrap(), 42);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_estimate_fee() {
+        let smart = ZcashRpcClient::new(
+            mock_node(0, serde_json::json!({"feerate": 0.0002, "blocks": 2})).await,
+            None,
+            None,
+            ZcashNetwork::Mainnet,
+        );
+        assert_eq!(
+            smart.estimate_fee(2).await.unwrap(),
+            FeeEstimate {
+                fee_per_kb: 20_000,
+                confidence: FeeConfidence::High
+            }
+        );
+
+        // Without estimatesmartfee and with no estimatefee data, the
+        // mempool median is used
+        let mempool = ZcashRpcClient::new(
+            method_node(serde_json::json!({
+                "estimatefee": -1,
+                "getrawmempool": {
+                    "aa": {"size": 250, "fee": 0.00001},
+                    "bb": {"size": 500, "fee": 0.0001},
+                    "cc": {"size": 1000, "fee": 0.0001}
+                }
+            }))
+            .await,
+            None,
+            None,
+            ZcashNetwork::Mainnet,
+        );
+        assert_eq!(
+            mempool.estimate_fee(2).await.unwrap(),
+            FeeEstimate {
+                fee_per_kb: 10_000,
+                confidence: FeeConfidence::Low
+            }
+        );
+
+        let empty = ZcashRpcClient::new(
+            method_node(serde_json::json!({"getrawmempool": {}})).await,
+            None,
+            None,
+            ZcashNetwork::Mainnet,
+        );
+        assert_eq!(
+            empty.estimate_fee(2).await.unwrap().fee_per_kb,
+            crate::builder::DEFAULT_FEE_RATE
+        );
+
+        assert_eq!(zec_per_kb_to_zatoshis(-1.0), None);
+        assert_eq!(zec_per_kb_to_zatoshis(0.00001), Some(1000));
+    }
+
+    #[test]
+    fn test_tx_cache_eviction_and_invalidation() {
+        let tx = |txid: &str, confirmations: u32| RawTransaction {
+            txid: txid.to_string(),
+            version: 4,
+            locktime: 0,
+            vin: Vec::new(),
+            vout: Vec::new(),
+            confirmations: Some(confirmations),
+            blockhash: None,
+            blocktime: None,
+        };
+        let (events, _) = broadcast::channel(16);
+        let mut cache = TxCache::new(2);
+        cache.events = Some(events.subscribe());
+
+        cache.insert(tx("a", 1));
+        cache.insert(tx("b", 20));
+        assert!(cache.get("a").is_some());
+        // "b" is now least recently used
+        cache.insert(tx("c", 20));
+        assert!(cache.get("b").is_none());
+
+        // Deep entries are dropped with the shallow ones on a new tip
+        events
+            .send(NodeEvent::Block {
+                hash: "00".repeat(32),
+            })
+            .unwrap();
+        assert!(cache.get("a").is_none());
+        assert!(cache.get("c").is_none());
+
+        cache.insert(tx("c", 20));
+        events
+            .send(NodeEvent::ReorgDetected { fork_height: 100 })
+            .unwrap();
+        assert!(cache.get("c").is_none());
+
+        // Without notifications, a changed block count is the new tip
+        cache.insert(tx("c", 20));
+        cache.observe_tip(100);
+        cache.observe_tip(100);
+        assert!(cache.get("c").is_some());
+        cache.observe_tip(101);
+        assert!(cache.get("c").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_request_and_call_timeouts() {
+        let client = ZcashRpcClient::new(
+            MockServer::unresponsive().await,
+            None,
+            None,
+            ZcashNetwork::Testnet,
+        )
+        .with_retry_policy(RetryPolicy::none())
+        .with_timeouts(Duration::from_millis(50), Duration::from_secs(5));
+        assert!(matches!(
+            client.get_block_count().await,
+            Err(RpcClientError::RequestTimeout(_))
+        ));
+
+        let client = ZcashRpcClient::new(
+            MockServer::unresponsive().await,
+            None,
+            None,
+            ZcashNetwork::Testnet,
+        )
+        .with_timeouts(Duration::from_secs(5), Duration::from_millis(50));
+        assert!(matches!(
+            client.get_block_count().await,
+            Err(RpcClientError::CallTimeout { .. })
+        ));
+    }
+
+    #[test]
+    fn test_cookie_reloads_on_rotation() {
+        let path = std::env::temp_dir().join(format!("zcash-cookie-{}", uuid::Uuid::new_v4()));
+        let cookie = CookieAuth::new(path.clone());
+        assert!(matches!(
+            cookie.credentials(),
+            Err(RpcClientError::CookieError(_))
+        ));
+
+        fs::write(&path, "__cookie__:first\n").unwrap();
+        let (user, password) = cookie.credentials().unwrap();
+        assert_eq!(user, "__cookie__");
+        assert_eq!(password.as_str(), "first");
+
+        // Node restart writes a new cookie
+        fs::write(&path, "__cookie__:second").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        assert_eq!(cookie.credentials().unwrap().1.as_str(), "second");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_tls_config_validation() {
+        let client = || {
+            ZcashRpcClient::new(
+                "http://127.0.0.1:1".to_string(),
+                None,
+                None,
+                ZcashNetwork::Testnet,
+            )
+        };
+
+        assert!(client().with_tls(&TlsConfig::default()).is_ok());
+        assert!(matches!(
+            client().with_tls(&TlsConfig {
+                ca_cert: Some("/nonexistent/ca.pem".to_string()),
+                ..TlsConfig::default()
+            }),
+            Err(RpcClientError::TlsError(_))
+        ));
+        assert!(matches!(
+            client().with_tls(&TlsConfig {
+                client_cert: Some("/nonexistent/client.pem".to_string()),
+                ..TlsConfig::default()
+            }),
+            Err(RpcClientError::TlsError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_routes_through_proxy() {
+        // The mock node doubles as an HTTP proxy: only it can reach the node
+        let proxy = mock_node(0, serde_json::json!(7)).await;
+        let client = ZcashRpcClient::new(
+            "http://zcashd.invalid:18232".to_string(),
+            None,
+            None,
+            ZcashNetwork::Testnet,
+        )
+        .with_retry_policy(RetryPolicy::none())
+        .with_proxy(&proxy)
+        .unwrap();
+        assert_eq!(client.get_block_count().await.unwrap(), 7);
+
+        let onion = ZcashRpcClient::new(
+            "http://zcashd.onion:18232".to_string(),
+            None,
+            None,
+            ZcashNetwork::Testnet,
+        );
+        assert!(matches!(
+            onion.with_proxy("socks5://127.0.0.1:9050"),
+            Err(RpcClientError::ProxyError(_))
+        ));
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use crate::{Block, HTLCActivity};
+
+/// HTLCs a block scan looks for: P2SH script pubkeys paying them, and their
+/// known outputs, whose spends are reported
+#[derive(Debug, Clone, Default)]
+pub struct WatchSet {
+    scripts: HashMap<String, String>,
+    outpoints: HashMap<(String, u32), String>,
+}
+
+impl WatchSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report outputs paying `script_pubkey_hex` as funding `htlc_id`
+    pub fn watch_script(&mut self, htlc_id: &str, script_pubkey_hex: &str) {
+        self.scripts
+            .insert(script_pubkey_hex.to_lowercase(), htlc_id.to_string());
+    }
+
+    /// Report spends of `txid:vout`, the output of `htlc_id`
+    pub fn watch_outpoint(&mut self, htlc_id: &str, txid: &str, vout: u32) {
+        self.outpoints
+            .insert((txid.to_string(), vout), htlc_id.to_string());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scripts.is_empty() && self.outpoints.is_empty()
+    }
+
+    /// HTLC funding outputs and spends in `block`, in transaction order.
+    /// Funding outputs found are watched for spends from then on, so a
+    /// spend later in the same block is reported too.
+    pub fn scan_block(&mut self, block: &Block) -> Vec<HTLCActivity> {
+        let mut activity = Vec::new();
+
+        for tx in &block.tx {
+            for (index, input) in tx.vin.iter().enumerate() {
+                let outpoint = (input.txid.clone(), input.vout);
+                if let Some(htlc_id) = self.outpoints.remove(&outpoint) {
+                    activity.push(HTLCActivity::Spent {
+                        htlc_id,
+                        txid: outpoint.0,
+                        vout: outpoint.1,
+                        spending_txid: tx.txid.clone(),
+                        input: index as u32,
+                        script_sig_hex: input.script_sig.as_ref().map(|s| s.hex.clone()),
+                        height: block.height,
+                    });
+                }
+            }
+
+            for output in &tx.vout {
+                let script = output.script_pubkey.hex.to_lowercase();
+                if let Some(htlc_id) = self.scripts.get(&script).cloned() {
+                    self.watch_outpoint(&htlc_id, &tx.txid, output.n);
+                    activity.push(HTLCActivity::Funded {
+                        htlc_id,
+                        txid: tx.txid.clone(),
+                        vout: output.n,
+                        value: output.value,
+                        height: block.height,
+                    });
+                }
+            }
+        }
+
+        activity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RawTransaction, ScriptPubKey, ScriptSig, TxInput, TxOutput};
+
+    fn tx(txid: &str, spends: &[(&str, u32)], pays: &[&str]) -> RawTransaction {
+        RawTransaction {
+            txid: txid.to_string(),
+            version: 4,
+            locktime: 0,
+            vin: spends
+                .iter()
+                .map(|(txid, vout)| TxInput {
+                    txid: txid.to_string(),
+                    vout: *vout,
+                    script_sig: Some(ScriptSig {
+                        hex: "00".to_string(),
+                    }),
+                })
+                .collect(),
+            vout: pays
+                .iter()
+                .enumerate()
+                .map(|(n, script)| TxOutput {
+                    value: 1.0,
+                    n: n as u32,
+                    script_pubkey: ScriptPubKey {
+                        hex: script.to_string(),
+                        script_type: "scripthash".to_string(),
+                        addresses: None,
+                    },
+                })
+                .collect(),
+            confirmations: None,
+            blockhash: None,
+            blocktime: None,
+        }
+    }
+
+    #[test]
+    fn test_scan_block_finds_funding_and_spends() {
+        let htlc_script = format!("a914{}87", "11".repeat(20));
+        let mut watch = WatchSet::new();
+        watch.watch_script("htlc-1", &htlc_script);
+        watch.watch_outpoint("htlc-0", "aa", 0);
+
+        let block = Block {
+            hash: "00".repeat(32),
+            height: 100,
+            previousblockhash: None,
+            tx: vec![
+                tx("coinbase", &[("", 0)], &["76a914"]),
+                tx("fund", &[("cc", 1)], &["76a914", &htlc_script]),
+                tx("redeem", &[("fund", 1), ("aa", 0)], &["76a914"]),
+            ],
+        };
+        let activity = watch.scan_block(&block);
+
+        assert_eq!(activity.len(), 3);
+        assert!(matches!(
+            &activity[0],
+            HTLCActivity::Funded { htlc_id, txid, vout: 1, height: 100, .. }
+                if htlc_id == "htlc-1" && txid == "fund"
+        ));
+        assert!(matches!(
+            &activity[1],
+            HTLCActivity::Spent { htlc_id, spending_txid, input: 0, .. }
+                if htlc_id == "htlc-1" && spending_txid == "redeem"
+        ));
+        assert!(matches!(
+            &activity[2],
+            HTLCActivity::Spent { htlc_id, input: 1, .. } if htlc_id == "htlc-0"
+        ));
+
+        // Spent outputs are no longer watched, funding scripts still are
+        let rescan = watch.scan_block(&block);
+        assert_eq!(rescan.len(), 2);
+        assert!(rescan
+            .iter()
+            .all(|a| !matches!(a, HTLCActivity::Spent { htlc_id, .. } if htlc_id == "htlc-0")));
+    }
+}
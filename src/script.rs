@@ -1,10 +1,14 @@
 use bitcoin::blockdata::opcodes::{self, OP_FALSE, OP_TRUE};
-use bitcoin::blockdata::script::{Builder, Script};
+use bitcoin::blockdata::script::{Builder, Instruction, Script};
 use bitcoin::hashes::{hash160, Hash};
 use ripemd::Digest;
 use sha2::Sha256;
+use zeroize::Zeroizing;
 
-use crate::{HTLCParams, ZcashNetwork};
+use crate::{HTLCParams, HTLCSpend, ZcashNetwork};
+
+/// Consensus limit on the size of a P2SH redeem script
+const MAX_REDEEM_SCRIPT_SIZE: usize = 520;
 
 /// Build P2SH HTLC script according to ZIP-300
 ///
@@ -26,6 +30,10 @@ impl HTLCScriptBuilder {
         Self { network }
     }
 
+    pub fn network(&self) -> ZcashNetwork {
+        self.network
+    }
+
     pub fn build_htlc_script(&self, params: &HTLCParams) -> Result<Script, HTLCScriptError> {
         let hash_lock_bytes =
             hex::decode(&params.hash_lock).map_err(|_| HTLCScriptError::InvalidHashLock)?;
@@ -59,6 +67,162 @@ impl HTLCScriptBuilder {
         Ok(script)
     }
 
+    /// HTLC whose redeem branch needs `threshold` signatures from
+    /// `recipient_pubkeys` instead of one from `params.recipient_pubkey`:
+    ///
+    /// OP_IF
+    ///     OP_SHA256 <hash_lock> OP_EQUALVERIFY
+    ///     <m> <pubkey_1> ... <pubkey_n> <n> OP_CHECKMULTISIG
+    /// OP_ELSE
+    ///     <timelock> OP_CHECKLOCKTIMEVERIFY OP_DROP
+    ///     <refund_pubkey> OP_CHECKSIG
+    /// OP_ENDIF
+    pub fn build_multisig_htlc_script(
+        &self,
+        params: &HTLCParams,
+        threshold: usize,
+        recipient_pubkeys: &[String],
+    ) -> Result<Script, HTLCScriptError> {
+        if threshold == 0 || threshold > recipient_pubkeys.len() || recipient_pubkeys.len() > 16 {
+            return Err(HTLCScriptError::InvalidThreshold {
+                threshold,
+                keys: recipient_pubkeys.len(),
+            });
+        }
+
+        let hash_lock_bytes =
+            hex::decode(&params.hash_lock).map_err(|_| HTLCScriptError::InvalidHashLock)?;
+
+        if hash_lock_bytes.len() != 32 {
+            return Err(HTLCScriptError::InvalidHashLockLength);
+        }
+
+        let refund_pubkey =
+            hex::decode(&params.refund_pubkey).map_err(|_| HTLCScriptError::InvalidPublicKey)?;
+
+        let mut builder = Builder::new()
+            .push_opcode(opcodes::all::OP_IF)
+            .push_opcode(opcodes::all::OP_SHA256)
+            .push_slice(&hash_lock_bytes)
+            .push_opcode(opcodes::all::OP_EQUALVERIFY)
+            .push_int(threshold as i64);
+
+        for pubkey in recipient_pubkeys {
+            let pubkey = hex::decode(pubkey).map_err(|_| HTLCScriptError::InvalidPublicKey)?;
+            builder = builder.push_slice(&pubkey);
+        }
+
+        let script = builder
+            .push_int(recipient_pubkeys.len() as i64)
+            .push_opcode(opcodes::all::OP_CHECKMULTISIG)
+            .push_opcode(opcodes::all::OP_ELSE)
+            .push_int(params.timelock as i64)
+            .push_opcode(opcodes::all::OP_CLTV)
+            .push_opcode(opcodes::all::OP_DROP)
+            .push_slice(&refund_pubkey)
+            .push_opcode(opcodes::all::OP_CHECKSIG)
+            .push_opcode(opcodes::all::OP_ENDIF)
+            .into_script();
+
+        if script.len() > MAX_REDEEM_SCRIPT_SIZE {
+            return Err(HTLCScriptError::BuildError(format!(
+                "redeem script is {} bytes, limit is {}",
+                script.len(),
+                MAX_REDEEM_SCRIPT_SIZE
+            )));
+        }
+
+        Ok(script)
+    }
+
+    /// Threshold and recipient public keys, in script order, of an HTLC
+    /// built by [`build_multisig_htlc_script`](Self::build_multisig_htlc_script)
+    pub fn multisig_branch(&self, redeem_script: &Script) -> Option<(usize, Vec<Vec<u8>>)> {
+        let instructions = redeem_script
+            .instructions()
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+
+        match instructions.get(..4)? {
+            [Instruction::Op(opcodes::all::OP_IF), Instruction::Op(opcodes::all::OP_SHA256), Instruction::PushBytes(hash), Instruction::Op(opcodes::all::OP_EQUALVERIFY)]
+                if hash.len() == 32 => {}
+            _ => return None,
+        }
+
+        let (threshold, pubkeys, _) = parse_checkmultisig(&instructions[4..])?;
+        Some((threshold, pubkeys))
+    }
+
+    /// Which branch of `redeem_script` the `script_sig` of a spending input
+    /// satisfied, as built by [`build_redeem_input`](Self::build_redeem_input),
+    /// [`build_multisig_redeem_input`](Self::build_multisig_redeem_input) or
+    /// [`build_refund_input`](Self::build_refund_input) followed by the redeem
+    /// script. `None` if it does not spend `redeem_script` that way.
+    pub fn classify_spend(&self, script_sig: &Script, redeem_script: &Script) -> Option<HTLCSpend> {
+        let instructions = script_sig
+            .instructions()
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+
+        match instructions.as_slice() {
+            [.., Instruction::PushBytes(secret), Instruction::Op(branch), Instruction::PushBytes(script)]
+                if *branch == OP_TRUE && *script == redeem_script.as_bytes() =>
+            {
+                Some(HTLCSpend::Redeem {
+                    secret: hex::encode(secret).into(),
+                })
+            }
+            [.., Instruction::PushBytes(branch), Instruction::PushBytes(script)]
+                if branch.is_empty() && *script == redeem_script.as_bytes() =>
+            {
+                Some(HTLCSpend::Refund)
+            }
+            _ => None,
+        }
+    }
+
+    /// Bare `<m> <pubkey_1> ... <pubkey_n> <n> OP_CHECKMULTISIG` script, the
+    /// redeem script of an m-of-n P2SH wallet
+    pub fn build_multisig_script(
+        &self,
+        threshold: usize,
+        pubkeys: &[String],
+    ) -> Result<Script, HTLCScriptError> {
+        if threshold == 0 || threshold > pubkeys.len() || pubkeys.len() > 16 {
+            return Err(HTLCScriptError::InvalidThreshold {
+                threshold,
+                keys: pubkeys.len(),
+            });
+        }
+
+        let mut builder = Builder::new().push_int(threshold as i64);
+        for pubkey in pubkeys {
+            let pubkey = hex::decode(pubkey).map_err(|_| HTLCScriptError::InvalidPublicKey)?;
+            builder = builder.push_slice(&pubkey);
+        }
+
+        Ok(builder
+            .push_int(pubkeys.len() as i64)
+            .push_opcode(opcodes::all::OP_CHECKMULTISIG)
+            .into_script())
+    }
+
+    /// Threshold and public keys, in script order, of a script built by
+    /// [`build_multisig_script`](Self::build_multisig_script)
+    pub fn multisig_keys(&self, redeem_script: &Script) -> Option<(usize, Vec<Vec<u8>>)> {
+        let instructions = redeem_script
+            .instructions()
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+
+        match parse_checkmultisig(&instructions)? {
+            (threshold, pubkeys, consumed) if consumed == instructions.len() => {
+                Some((threshold, pubkeys))
+            }
+            _ => None,
+        }
+    }
+
     pub fn script_to_p2sh_address(&self, script: &Script) -> Result<String, HTLCScriptError> {
         let script_hash = hash160::Hash::hash(script.as_bytes());
         let prefix = self.network.p2sh_prefix();
@@ -73,12 +237,49 @@ impl HTLCScriptBuilder {
         Ok(bs58::encode(address_bytes).into_string())
     }
 
+    /// Transparent P2PKH address of a compressed or uncompressed public key
+    pub fn pubkey_to_p2pkh_address(&self, pubkey: &[u8]) -> String {
+        let pubkey_hash = hash160::Hash::hash(pubkey);
+        let prefix = self.network.p2pkh_prefix();
+
+        let mut address_bytes = Vec::new();
+        address_bytes.extend_from_slice(&prefix);
+        address_bytes.extend_from_slice(pubkey_hash.as_ref());
+
+        let checksum = self.double_sha256_checksum(&address_bytes);
+        address_bytes.extend_from_slice(&checksum[..4]);
+
+        bs58::encode(address_bytes).into_string()
+    }
+
+    /// Transparent address paid by a P2PKH or P2SH script pubkey
+    pub fn script_pubkey_to_address(&self, script_pubkey: &Script) -> Option<String> {
+        let bytes = script_pubkey.as_bytes();
+        let (prefix, hash) = if script_pubkey.is_p2pkh() {
+            (self.network.p2pkh_prefix(), &bytes[3..23])
+        } else if script_pubkey.is_p2sh() {
+            (self.network.p2sh_prefix(), &bytes[2..22])
+        } else {
+            return None;
+        };
+
+        let mut address_bytes = Vec::new();
+        address_bytes.extend_from_slice(&prefix);
+        address_bytes.extend_from_slice(hash);
+
+        let checksum = self.double_sha256_checksum(&address_bytes);
+        address_bytes.extend_from_slice(&checksum[..4]);
+
+        Some(bs58::encode(address_bytes).into_string())
+    }
+
     pub fn build_redeem_input(
         &self,
         secret: &str,
         signature: &[u8],
     ) -> Result<Script, HTLCScriptError> {
-        let secret_bytes = hex::decode(secret).map_err(|_| HTLCScriptError::InvalidSecret)?;
+        let secret_bytes =
+            Zeroizing::new(hex::decode(secret).map_err(|_| HTLCScriptError::InvalidSecret)?);
 
         let script = Builder::new()
             .push_slice(signature)
@@ -89,6 +290,35 @@ impl HTLCScriptBuilder {
         Ok(script)
     }
 
+    /// Satisfy the multisig redeem branch. `signatures` must follow the order
+    /// of their public keys in the redeem script. The leading OP_0 is the
+    /// dummy element OP_CHECKMULTISIG pops in addition to its arguments.
+    pub fn build_multisig_redeem_input(
+        &self,
+        secret: &str,
+        signatures: &[Vec<u8>],
+    ) -> Result<Script, HTLCScriptError> {
+        let secret_bytes =
+            Zeroizing::new(hex::decode(secret).map_err(|_| HTLCScriptError::InvalidSecret)?);
+
+        Ok(
+            Builder::from(self.build_multisig_input(signatures).to_bytes())
+                .push_slice(&secret_bytes)
+                .push_opcode(OP_TRUE)
+                .into_script(),
+        )
+    }
+
+    /// Satisfy a bare multisig redeem script, with the OP_CHECKMULTISIG
+    /// dummy element first and `signatures` in public key order
+    pub fn build_multisig_input(&self, signatures: &[Vec<u8>]) -> Script {
+        let mut builder = Builder::new().push_opcode(OP_FALSE);
+        for signature in signatures {
+            builder = builder.push_slice(signature);
+        }
+        builder.into_script()
+    }
+
     pub fn build_refund_input(&self, signature: &[u8]) -> Script {
         Builder::new()
             .push_slice(signature)
@@ -98,7 +328,7 @@ impl HTLCScriptBuilder {
 
     pub fn verify_secret(&self, secret: &str, hash_lock: &str) -> bool {
         let secret_bytes = match hex::decode(secret) {
-            Ok(bytes) => bytes,
+            Ok(bytes) => Zeroizing::new(bytes),
             Err(_) => return false,
         };
 
@@ -130,6 +360,44 @@ impl HTLCScriptBuilder {
     }
 }
 
+/// Parse `<m> <pubkeys...> <n> OP_CHECKMULTISIG` at the start of
+/// `instructions`, returning the threshold, keys and instructions consumed
+fn parse_checkmultisig(instructions: &[Instruction]) -> Option<(usize, Vec<Vec<u8>>, usize)> {
+    let threshold = small_int(instructions.first()?)?;
+    let mut pubkeys = Vec::new();
+    for instruction in &instructions[1..] {
+        match instruction {
+            Instruction::PushBytes(pubkey) if !pubkey.is_empty() => pubkeys.push(pubkey.to_vec()),
+            _ => break,
+        }
+    }
+
+    let count = small_int(instructions.get(1 + pubkeys.len())?)?;
+    let checkmultisig = instructions.get(2 + pubkeys.len())?;
+    if count != pubkeys.len()
+        || threshold == 0
+        || threshold > count
+        || *checkmultisig != Instruction::Op(opcodes::all::OP_CHECKMULTISIG)
+    {
+        return None;
+    }
+
+    Some((threshold, pubkeys, 3 + count))
+}
+
+/// Value of an OP_1..OP_16 push
+fn small_int(instruction: &Instruction) -> Option<usize> {
+    match instruction {
+        Instruction::Op(op)
+            if (opcodes::all::OP_PUSHNUM_1.to_u8()..=opcodes::all::OP_PUSHNUM_16.to_u8())
+                .contains(&op.to_u8()) =>
+        {
+            Some((op.to_u8() - opcodes::all::OP_PUSHNUM_1.to_u8() + 1) as usize)
+        }
+        _ => None,
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum HTLCScriptError {
     #[error("Invalid hash lock format")]
@@ -144,6 +412,9 @@ pub enum HTLCScriptError {
     #[error("Invalid secret format")]
     InvalidSecret,
 
+    #[error("Invalid multisig threshold {threshold} of {keys} keys")]
+    InvalidThreshold { threshold: usize, keys: usize },
+
     #[error("Script building failed: {0}")]
     BuildError(String),
 }
@@ -162,12 +433,62 @@ mod tests {
             hash_lock: "a".repeat(64),
             timelock: 100,
             amount: "1.0".to_string(),
+            metadata: None,
+            priority: Default::default(),
+            htlc_id: None,
         };
 
         let script = builder.build_htlc_script(&params).unwrap();
         assert!(!script.as_bytes().is_empty());
     }
 
+    #[test]
+    fn test_multisig_branch_roundtrip() {
+        let builder = HTLCScriptBuilder::new(ZcashNetwork::Testnet);
+
+        let params = HTLCParams {
+            recipient_pubkey: String::new(),
+            refund_pubkey: format!("03{}", "b".repeat(64)),
+            hash_lock: "a".repeat(64),
+            timelock: 100,
+            amount: "1.0".to_string(),
+            metadata: None,
+            priority: Default::default(),
+            htlc_id: None,
+        };
+        let pubkeys: Vec<String> = ["c", "d", "e"]
+            .iter()
+            .map(|c| format!("02{}", c.repeat(64)))
+            .collect();
+
+        let script = builder
+            .build_multisig_htlc_script(&params, 2, &pubkeys)
+            .unwrap();
+        let (threshold, parsed) = builder.multisig_branch(&script).unwrap();
+        assert_eq!(threshold, 2);
+        assert_eq!(parsed.iter().map(hex::encode).collect::<Vec<_>>(), pubkeys);
+
+        let single = HTLCParams {
+            recipient_pubkey: pubkeys[0].clone(),
+            ..params.clone()
+        };
+        assert!(builder
+            .multisig_branch(&builder.build_htlc_script(&single).unwrap())
+            .is_none());
+        let wallet = builder.build_multisig_script(2, &pubkeys).unwrap();
+        assert_eq!(builder.multisig_keys(&wallet).unwrap().0, 2);
+        assert!(builder.multisig_keys(&script).is_none());
+        assert!(builder.multisig_branch(&wallet).is_none());
+
+        assert!(matches!(
+            builder.build_multisig_htlc_script(&params, 4, &pubkeys),
+            Err(HTLCScriptError::InvalidThreshold {
+                threshold: 4,
+                keys: 3
+            })
+        ));
+    }
+
     #[test]
     fn test_verify_secret() {
         let builder = HTLCScriptBuilder::new(ZcashNetwork::Testnet);
@@ -180,4 +501,52 @@ mod tests {
         assert!(builder.verify_secret(secret, &hash_lock));
         assert!(!builder.verify_secret("badbeef", &hash_lock));
     }
+
+    #[test]
+    fn test_classify_spend_reads_branch_and_secret() {
+        let builder = HTLCScriptBuilder::new(ZcashNetwork::Testnet);
+        let params = HTLCParams {
+            recipient_pubkey: format!("02{}", "a".repeat(64)),
+            refund_pubkey: format!("03{}", "b".repeat(64)),
+            hash_lock: "a".repeat(64),
+            timelock: 100,
+            amount: "1.0".to_string(),
+            metadata: None,
+            priority: Default::default(),
+            htlc_id: None,
+        };
+        let redeem_script = builder.build_htlc_script(&params).unwrap();
+        let signature = vec![0x30; 71];
+        let spend = |input: Script| {
+            let script_sig = Builder::from(input.to_bytes())
+                .push_slice(redeem_script.as_bytes())
+                .into_script();
+            builder.classify_spend(&script_sig, &redeem_script)
+        };
+
+        let redeem = builder.build_redeem_input("deadbeef", &signature).unwrap();
+        assert_eq!(
+            spend(redeem),
+            Some(HTLCSpend::Redeem {
+                secret: "deadbeef".into()
+            })
+        );
+        let multisig = builder
+            .build_multisig_redeem_input("cafe", &[signature.clone(), signature.clone()])
+            .unwrap();
+        assert_eq!(
+            spend(multisig),
+            Some(HTLCSpend::Redeem {
+                secret: "cafe".into()
+            })
+        );
+        assert_eq!(
+            spend(builder.build_refund_input(&signature)),
+            Some(HTLCSpend::Refund)
+        );
+
+        let other = Builder::new().push_opcode(OP_TRUE).into_script();
+        let redeem = builder.build_redeem_input("deadbeef", &signature).unwrap();
+        assert_eq!(builder.classify_spend(&redeem, &other), None);
+    }
 }
@@ -1,14 +1,127 @@
-use bitcoin::blockdata::script::Script;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use bitcoin::blockdata::script::{Instruction, Script};
 use bitcoin::blockdata::transaction::Transaction;
+use bitcoin::consensus::encode;
 use bitcoin::EcdsaSighashType;
-use secp256k1::{ecdsa::Signature, Message, PublicKey, Secp256k1, SecretKey};
+use rand::{CryptoRng, RngCore};
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId, Signature};
+use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use zeroize::Zeroizing;
 
-use crate::HTLCScriptBuilder;
+use crate::policy::{SigningPolicy, SigningRequest};
+use crate::sighash::{zip243_signature_hash, NetworkUpgrade};
+use crate::transaction::ZcashTransaction;
+use crate::{
+    zec_to_zatoshis, ExternalSignature, HTLCScriptBuilder, InputSighash, PartialInput,
+    PartiallySignedTx, RawSignature, TemplateInput, TemplateInputType, UnsignedTxTemplate,
+};
+
+/// Key custody backend: turns sighash digests into signatures for a key it
+/// holds. Keys are addressed by an opaque id whose meaning is up to the
+/// backend (a hex private key for [`LocalSigner`], a key ARN for an HSM, ...).
+#[async_trait::async_trait]
+pub trait Signer: Send + Sync {
+    /// Sign a 32-byte sighash, returning a DER signature without the
+    /// sighash type byte
+    async fn sign_sighash(&self, key_id: &str, sighash: &[u8; 32]) -> Result<Vec<u8>, SignerError>;
+
+    /// Hex-encoded compressed public key for `key_id`
+    async fn derive_pubkey(&self, key_id: &str) -> Result<String, SignerError>;
+}
+
+/// In-process signer whose key ids are hex-encoded secp256k1 private keys
+pub struct LocalSigner {
+    secp: Secp256k1<secp256k1::All>,
+}
+
+impl LocalSigner {
+    pub fn new() -> Self {
+        Self {
+            secp: Secp256k1::new(),
+        }
+    }
+}
+
+impl Default for LocalSigner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl Signer for LocalSigner {
+    async fn sign_sighash(&self, key_id: &str, sighash: &[u8; 32]) -> Result<Vec<u8>, SignerError> {
+        let message = Message::from_digest_slice(sighash)
+            .map_err(|e| SignerError::MessageError(e.to_string()))?;
+        let mut privkey = parse_privkey(key_id)?;
+
+        let signature = self.secp.sign_ecdsa(&message, &privkey);
+        privkey.non_secure_erase();
+
+        Ok(signature.serialize_der().to_vec())
+    }
+
+    async fn derive_pubkey(&self, key_id: &str) -> Result<String, SignerError> {
+        let mut privkey = parse_privkey(key_id)?;
+        let pubkey = PublicKey::from_secret_key(&self.secp, &privkey);
+        privkey.non_secure_erase();
+
+        Ok(hex::encode(pubkey.serialize()))
+    }
+}
+
+/// Signing keys for funding inputs, looked up by the hex scriptPubKey or
+/// transparent address each input spends from
+#[derive(Clone, Default)]
+pub struct KeyMap {
+    keys: HashMap<String, Zeroizing<String>>,
+}
+
+impl KeyMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `key_id` for inputs spending from `script_or_address`
+    pub fn with_key(mut self, script_or_address: &str, key_id: &str) -> Self {
+        self.insert(script_or_address, key_id);
+        self
+    }
+
+    pub fn insert(&mut self, script_or_address: &str, key_id: &str) {
+        self.keys.insert(
+            script_or_address.to_string(),
+            Zeroizing::new(key_id.to_string()),
+        );
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    fn resolve(&self, script_pubkey: &Script, address: Option<&str>) -> Option<&str> {
+        self.keys
+            .get(&hex::encode(script_pubkey.as_bytes()))
+            .or_else(|| address.and_then(|address| self.keys.get(address)))
+            .map(|key| key.as_str())
+    }
+}
 
 pub struct TransactionSigner {
     secp: Secp256k1<secp256k1::All>,
     script_builder: HTLCScriptBuilder,
+    branch_id: AtomicU32,
+    sighash_type: EcdsaSighashType,
+    backend: Arc<dyn Signer>,
+    policy: Option<Arc<dyn SigningPolicy>>,
 }
 
 impl TransactionSigner {
@@ -16,55 +129,146 @@ impl TransactionSigner {
         Self {
             secp: Secp256k1::new(),
             script_builder,
+            branch_id: AtomicU32::new(NetworkUpgrade::CURRENT.branch_id()),
+            sighash_type: EcdsaSighashType::All,
+            backend: Arc::new(LocalSigner::new()),
+            policy: None,
         }
     }
 
-    pub fn sign_htlc_creation(
+    /// Route signing through a different key custody backend
+    pub fn with_backend(mut self, backend: Arc<dyn Signer>) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Ask `policy` to approve every transaction signature before it is made
+    pub fn with_policy(mut self, policy: Arc<dyn SigningPolicy>) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Sign for a specific consensus branch ID instead of
+    /// [`NetworkUpgrade::CURRENT`]
+    pub fn with_branch_id(self, branch_id: u32) -> Self {
+        self.set_branch_id(branch_id);
+        self
+    }
+
+    /// Sign for `branch_id` from now on, e.g. the one the node reports for
+    /// the next block
+    pub fn set_branch_id(&self, branch_id: u32) {
+        self.branch_id.store(branch_id, Ordering::Relaxed);
+    }
+
+    pub fn branch_id(&self) -> u32 {
+        self.branch_id.load(Ordering::Relaxed)
+    }
+
+    /// Commit to a different part of the transaction than `SIGHASH_ALL`,
+    /// e.g. `SinglePlusAnyoneCanPay` so a third party can add inputs and
+    /// outputs to bump the fee of a pre-signed redeem
+    pub fn with_sighash_type(mut self, sighash_type: EcdsaSighashType) -> Self {
+        self.sighash_type = sighash_type;
+        self
+    }
+
+    pub fn sighash_type(&self) -> EcdsaSighashType {
+        self.sighash_type
+    }
+
+    /// Sign P2PKH funding inputs with the key `keys` holds for each input's
+    /// script pubkey or address. `input_amounts` are the prevout values in
+    /// zatoshis, committed to by the ZIP-243 sighash. Inputs are signed
+    /// concurrently on the runtime's worker threads; a failure is reported
+    /// with the index of the input that caused it.
+    pub async fn sign_htlc_creation(
         &self,
-        mut tx: Transaction,
+        mut tx: ZcashTransaction,
         input_scripts: Vec<Script>,
-        private_keys: Vec<&str>,
-    ) -> Result<Transaction, SignerError> {
-        if tx.input.len() != input_scripts.len() || tx.input.len() != private_keys.len() {
+        input_amounts: Vec<u64>,
+        keys: &KeyMap,
+    ) -> Result<ZcashTransaction, SignerError> {
+        if tx.input.len() != input_scripts.len() || tx.input.len() != input_amounts.len() {
             return Err(SignerError::MismatchedInputs);
         }
 
-        for (i, (script_pubkey, privkey_hex)) in
-            input_scripts.iter().zip(private_keys.iter()).enumerate()
+        let mut jobs = Vec::with_capacity(tx.input.len());
+        for (i, (script_pubkey, amount)) in
+            input_scripts.iter().zip(input_amounts.iter()).enumerate()
         {
-            let privkey = self.parse_privkey(privkey_hex)?;
-            let signature = self.sign_input(&tx, i, script_pubkey, &privkey)?;
+            let address = self.script_builder.script_pubkey_to_address(script_pubkey);
+            let key_id = keys
+                .resolve(script_pubkey, address.as_deref())
+                .ok_or(SignerError::MissingKey(i))?;
 
-            let pubkey = PublicKey::from_secret_key(&self.secp, &privkey);
-            let script_sig = bitcoin::blockdata::script::Builder::new()
+            self.check_policy(&tx, i, *amount, self.sighash_type)
+                .await
+                .map_err(|e| SignerError::InputFailed {
+                    index: i,
+                    source: Box::new(e),
+                })?;
+
+            let sighash = self.input_sighash(&tx, i, script_pubkey, *amount, self.sighash_type)?;
+            let backend = self.backend.clone();
+            let key_id = Zeroizing::new(key_id.to_string());
+
+            jobs.push(tokio::spawn(async move {
+                let signature = low_s_der(&backend.sign_sighash(&key_id, &sighash).await?)?;
+                let pubkey = backend.derive_pubkey(&key_id).await?;
+                Ok::<_, SignerError>((signature, pubkey))
+            }));
+        }
+
+        for (i, job) in jobs.into_iter().enumerate() {
+            let (mut signature, pubkey) = job
+                .await
+                .map_err(|e| SignerError::InputFailed {
+                    index: i,
+                    source: Box::new(SignerError::MessageError(e.to_string())),
+                })?
+                .map_err(|e| SignerError::InputFailed {
+                    index: i,
+                    source: Box::new(e),
+                })?;
+            signature.push(self.sighash_type.to_u32() as u8);
+
+            let pubkey = hex::decode(pubkey).map_err(|_| SignerError::InvalidPublicKey)?;
+            tx.input[i].script_sig = bitcoin::blockdata::script::Builder::new()
                 .push_slice(&signature)
-                .push_slice(&pubkey.serialize())
+                .push_slice(&pubkey)
                 .into_script();
-
-            tx.input[i].script_sig = script_sig;
         }
 
         Ok(tx)
     }
 
-    pub fn sign_htlc_redeem(
+    pub async fn sign_htlc_redeem(
         &self,
-        mut tx: Transaction,
+        mut tx: ZcashTransaction,
         input_index: usize,
         redeem_script: &Script,
+        amount: u64,
         secret: &str,
-        privkey_hex: &str,
-    ) -> Result<Transaction, SignerError> {
-        let privkey = self.parse_privkey(privkey_hex)?;
-        let signature = self.sign_input(&tx, input_index, redeem_script, &privkey)?;
+        key_id: &str,
+    ) -> Result<ZcashTransaction, SignerError> {
+        let signature = self
+            .sign_input(
+                &tx,
+                input_index,
+                redeem_script,
+                amount,
+                key_id,
+                self.sighash_type,
+            )
+            .await?;
 
         let script_sig = self
             .script_builder
             .build_redeem_input(secret, &signature)
             .map_err(|e| SignerError::ScriptError(e.to_string()))?;
 
-        let final_script_sig = bitcoin::blockdata::script::Builder::new()
-            .push_slice(script_sig.as_bytes())
+        let final_script_sig = bitcoin::blockdata::script::Builder::from(script_sig.to_bytes())
             .push_slice(redeem_script.as_bytes())
             .into_script();
 
@@ -73,20 +277,28 @@ impl TransactionSigner {
         Ok(tx)
     }
 
-    pub fn sign_htlc_refund(
+    pub async fn sign_htlc_refund(
         &self,
-        mut tx: Transaction,
+        mut tx: ZcashTransaction,
         input_index: usize,
         redeem_script: &Script,
-        privkey_hex: &str,
-    ) -> Result<Transaction, SignerError> {
-        let privkey = self.parse_privkey(privkey_hex)?;
-        let signature = self.sign_input(&tx, input_index, redeem_script, &privkey)?;
+        amount: u64,
+        key_id: &str,
+    ) -> Result<ZcashTransaction, SignerError> {
+        let signature = self
+            .sign_input(
+                &tx,
+                input_index,
+                redeem_script,
+                amount,
+                key_id,
+                self.sighash_type,
+            )
+            .await?;
 
         let script_sig = self.script_builder.build_refund_input(&signature);
 
-        let final_script_sig = bitcoin::blockdata::script::Builder::new()
-            .push_slice(script_sig.as_bytes())
+        let final_script_sig = bitcoin::blockdata::script::Builder::from(script_sig.to_bytes())
             .push_slice(redeem_script.as_bytes())
             .into_script();
 
@@ -95,43 +307,555 @@ impl TransactionSigner {
         Ok(tx)
     }
 
-    fn sign_input(
+    /// Merge externally produced signatures into an exported template and
+    /// return the fully signed transaction.
+    pub fn apply_signatures(
         &self,
-        tx: &Transaction,
+        template: &UnsignedTxTemplate,
+        signatures: &[ExternalSignature],
+    ) -> Result<ZcashTransaction, SignerError> {
+        let mut tx = self.decode_unsigned_tx(&template.unsigned_tx_hex)?;
+
+        if tx.input.len() != template.inputs.len() {
+            return Err(SignerError::MismatchedInputs);
+        }
+
+        for (i, input) in template.inputs.iter().enumerate() {
+            let external = signatures
+                .iter()
+                .find(|sig| sig.input_index == i)
+                .ok_or(SignerError::MissingSignature(i))?;
+
+            let signature =
+                hex::decode(&external.signature).map_err(|_| SignerError::InvalidSignature)?;
+
+            tx.input[i].script_sig = self.assemble_script_sig(
+                input,
+                i,
+                &signature,
+                external.pubkey.as_deref(),
+                external.secret.as_deref(),
+            )?;
+        }
+
+        Ok(tx)
+    }
+
+    /// Compute the sighash of every template input so an air-gapped or MPC
+    /// signer can sign the digests without seeing the transaction
+    pub fn compute_sighashes(
+        &self,
+        template: &UnsignedTxTemplate,
+        sighash_type: EcdsaSighashType,
+    ) -> Result<Vec<InputSighash>, SignerError> {
+        let tx = self.decode_unsigned_tx(&template.unsigned_tx_hex)?;
+
+        if tx.input.len() != template.inputs.len() {
+            return Err(SignerError::MismatchedInputs);
+        }
+
+        template
+            .inputs
+            .iter()
+            .enumerate()
+            .map(|(i, input)| {
+                let script_code = self.template_script_code(input, i)?;
+                let amount = template_amount(input, i)?;
+                let sighash = zip243_signature_hash(
+                    &tx,
+                    i,
+                    &script_code,
+                    amount,
+                    sighash_type.to_u32(),
+                    self.branch_id(),
+                );
+
+                Ok(InputSighash {
+                    input_index: i,
+                    sighash: hex::encode(sighash),
+                    sighash_type: sighash_type.to_u32(),
+                })
+            })
+            .collect()
+    }
+
+    /// Apply raw `(r, s)` signatures over the digests from
+    /// [`compute_sighashes`](Self::compute_sighashes). High-S values are
+    /// normalized before encoding.
+    pub fn apply_raw_signatures(
+        &self,
+        template: &UnsignedTxTemplate,
+        signatures: &[RawSignature],
+    ) -> Result<ZcashTransaction, SignerError> {
+        let external = signatures
+            .iter()
+            .map(|raw| {
+                let mut compact = [0u8; 64];
+                let r = hex::decode(&raw.r).map_err(|_| SignerError::InvalidSignature)?;
+                let s = hex::decode(&raw.s).map_err(|_| SignerError::InvalidSignature)?;
+                if r.len() != 32 || s.len() != 32 {
+                    return Err(SignerError::InvalidSignature);
+                }
+                compact[..32].copy_from_slice(&r);
+                compact[32..].copy_from_slice(&s);
+
+                let mut signature =
+                    Signature::from_compact(&compact).map_err(|_| SignerError::InvalidSignature)?;
+                signature.normalize_s();
+
+                let sighash_type = EcdsaSighashType::from_standard(raw.sighash_type)
+                    .map_err(|e| SignerError::SighashError(e.to_string()))?;
+                let mut der = signature.serialize_der().to_vec();
+                der.push(sighash_type.to_u32() as u8);
+
+                Ok(ExternalSignature {
+                    input_index: raw.input_index,
+                    signature: hex::encode(der),
+                    pubkey: raw.pubkey.clone(),
+                    secret: raw.secret.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, SignerError>>()?;
+
+        self.apply_signatures(template, &external)
+    }
+
+    // ==================== Partially Signed Transactions ====================
+
+    /// Add this key's signature to one input of a partially signed transaction
+    pub async fn sign_psbt_input(
+        &self,
+        psbt: &mut PartiallySignedTx,
         input_index: usize,
-        script_pubkey: &Script,
-        privkey: &SecretKey,
-    ) -> Result<Vec<u8>, SignerError> {
-        let sighash = tx.signature_hash(input_index, script_pubkey, EcdsaSighashType::All.to_u32());
+        key_id: &str,
+    ) -> Result<(), SignerError> {
+        self.sign_psbt_input_with(self.backend.as_ref(), psbt, input_index, key_id)
+            .await
+    }
 
-        let message = Message::from_digest_slice(&sighash[..])
-            .map_err(|e| SignerError::MessageError(e.to_string()))?;
+    /// [`sign_psbt_input`](Self::sign_psbt_input) with a key held by another
+    /// backend, e.g. a standby cosigner
+    pub async fn sign_psbt_input_with(
+        &self,
+        backend: &dyn Signer,
+        psbt: &mut PartiallySignedTx,
+        input_index: usize,
+        key_id: &str,
+    ) -> Result<(), SignerError> {
+        let tx = self.decode_unsigned_tx(&psbt.unsigned_tx_hex)?;
+        let input = psbt
+            .inputs
+            .get_mut(input_index)
+            .ok_or(SignerError::MismatchedInputs)?;
+
+        let script_code = self.template_script_code(&input.prevout, input_index)?;
+        let amount = template_amount(&input.prevout, input_index)?;
+
+        let signature = self
+            .sign_input_with(
+                backend,
+                &tx,
+                input_index,
+                &script_code,
+                amount,
+                key_id,
+                self.sighash_type,
+            )
+            .await?;
+        let pubkey = backend.derive_pubkey(key_id).await?;
+
+        input.partial_sigs.insert(pubkey, hex::encode(signature));
+
+        Ok(())
+    }
+
+    /// Merge signatures and secrets collected by another party into `psbt`
+    pub fn combine_psbt(
+        &self,
+        psbt: &mut PartiallySignedTx,
+        other: &PartiallySignedTx,
+    ) -> Result<(), SignerError> {
+        if psbt.unsigned_tx_hex != other.unsigned_tx_hex || psbt.inputs.len() != other.inputs.len()
+        {
+            return Err(SignerError::InvalidTemplate(
+                "cannot combine different transactions".to_string(),
+            ));
+        }
+
+        let tx = self.decode_unsigned_tx(&psbt.unsigned_tx_hex)?;
+        for (index, (input, other_input)) in psbt.inputs.iter_mut().zip(&other.inputs).enumerate() {
+            // Take the other party's signature only if it verifies and ours
+            // for the same key is missing or does not
+            for (pubkey, signature) in &other_input.partial_sigs {
+                let is_valid = |signature: &String| {
+                    self.partial_sig_is_valid(&tx, &input.prevout, index, pubkey, signature)
+                };
+                let replace =
+                    is_valid(signature) && !input.partial_sigs.get(pubkey).is_some_and(is_valid);
+                if replace {
+                    input.partial_sigs.insert(pubkey.clone(), signature.clone());
+                }
+            }
+            if input.secret.is_none() {
+                input.secret = other_input.secret.clone();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build final script sigs from the collected signatures and return the
+    /// broadcastable transaction hex
+    pub fn finalize_psbt(&self, psbt: &PartiallySignedTx) -> Result<String, SignerError> {
+        let mut tx = self.decode_unsigned_tx(&psbt.unsigned_tx_hex)?;
+
+        if tx.input.len() != psbt.inputs.len() {
+            return Err(SignerError::MismatchedInputs);
+        }
+
+        for (i, input) in psbt.inputs.iter().enumerate() {
+            if matches!(
+                input.prevout.input_type,
+                TemplateInputType::HTLCMultisigRedeem | TemplateInputType::P2SHMultisig
+            ) {
+                tx.input[i].script_sig = self.assemble_multisig(&tx, input, i)?;
+                continue;
+            }
+
+            let (pubkey, signature) = input
+                .partial_sigs
+                .iter()
+                .find(|(pubkey, signature)| {
+                    self.partial_sig_is_valid(&tx, &input.prevout, i, pubkey, signature)
+                })
+                .ok_or(SignerError::MissingSignature(i))?;
+
+            let signature = hex::decode(signature).map_err(|_| SignerError::InvalidSignature)?;
+
+            tx.input[i].script_sig = self.assemble_script_sig(
+                &input.prevout,
+                i,
+                &signature,
+                Some(pubkey),
+                input.secret.as_deref(),
+            )?;
+        }
+
+        Ok(hex::encode(tx.serialize()))
+    }
+
+    /// Threshold and public keys, in script order, that can sign a
+    /// multisig template input
+    pub fn multisig_signers(
+        &self,
+        input: &TemplateInput,
+        index: usize,
+    ) -> Result<(usize, Vec<Vec<u8>>), SignerError> {
+        let redeem_script = self.template_redeem_script(input, index)?;
+
+        let signers = match input.input_type {
+            TemplateInputType::HTLCMultisigRedeem => {
+                self.script_builder.multisig_branch(&redeem_script)
+            }
+            TemplateInputType::P2SHMultisig => self.script_builder.multisig_keys(&redeem_script),
+            _ => None,
+        };
+
+        signers.ok_or_else(|| {
+            SignerError::InvalidTemplate(format!("input {} is not a multisig input", index))
+        })
+    }
+
+    /// Pick `threshold` valid partial signatures in redeem script key order,
+    /// as OP_CHECKMULTISIG requires, and build the script sig. Invalid
+    /// signatures are skipped; the input fails only if too few remain.
+    fn assemble_multisig(
+        &self,
+        tx: &ZcashTransaction,
+        input: &PartialInput,
+        index: usize,
+    ) -> Result<Script, SignerError> {
+        let redeem_script = self.template_redeem_script(&input.prevout, index)?;
+        let (threshold, pubkeys) = self.multisig_signers(&input.prevout, index)?;
+
+        let mut signatures = Vec::with_capacity(threshold);
+        for pubkey in &pubkeys {
+            if signatures.len() == threshold {
+                break;
+            }
+            let pubkey = hex::encode(pubkey);
+            let Some(signature) = input.partial_sigs.get(&pubkey).filter(|signature| {
+                self.partial_sig_is_valid(tx, &input.prevout, index, &pubkey, signature)
+            }) else {
+                continue;
+            };
+
+            signatures.push(hex::decode(signature).map_err(|_| SignerError::InvalidSignature)?);
+        }
+
+        if signatures.len() < threshold {
+            return Err(SignerError::InsufficientSignatures {
+                index,
+                have: signatures.len(),
+                need: threshold,
+            });
+        }
+
+        let unlock = if input.prevout.input_type == TemplateInputType::HTLCMultisigRedeem {
+            let secret = input.secret.as_deref().ok_or_else(|| {
+                SignerError::InvalidTemplate(format!("input {} requires a secret", index))
+            })?;
+            self.script_builder
+                .build_multisig_redeem_input(secret, &signatures)
+                .map_err(|e| SignerError::ScriptError(e.to_string()))?
+        } else {
+            self.script_builder.build_multisig_input(&signatures)
+        };
+
+        Ok(bitcoin::blockdata::script::Builder::from(unlock.to_bytes())
+            .push_slice(redeem_script.as_bytes())
+            .into_script())
+    }
+
+    /// Whether `signature`, hex DER with the sighash type appended, is a
+    /// valid signature by `pubkey` of input `index` of `tx`
+    fn partial_sig_is_valid(
+        &self,
+        tx: &ZcashTransaction,
+        prevout: &TemplateInput,
+        index: usize,
+        pubkey: &str,
+        signature: &str,
+    ) -> bool {
+        let verify = || -> Result<(), SignerError> {
+            let script_code = self.template_script_code(prevout, index)?;
+            let amount = template_amount(prevout, index)?;
+            let signature = hex::decode(signature).map_err(|_| SignerError::InvalidSignature)?;
+            let parsed = check_signature_encoding(&signature)?;
+            let sighash = zip243_signature_hash(
+                tx,
+                index,
+                &script_code,
+                amount,
+                signature[signature.len() - 1] as u32,
+                self.branch_id(),
+            );
+
+            let pubkey = hex::decode(pubkey)
+                .ok()
+                .and_then(|pubkey| PublicKey::from_slice(&pubkey).ok())
+                .ok_or(SignerError::InvalidPublicKey)?;
+            let message = Message::from_digest_slice(&sighash)
+                .map_err(|e| SignerError::MessageError(e.to_string()))?;
+            self.secp
+                .verify_ecdsa(&message, &parsed, &pubkey)
+                .map_err(|_| SignerError::InvalidSignature)
+        };
+
+        verify().is_ok()
+    }
+
+    fn decode_unsigned_tx(&self, tx_hex: &str) -> Result<ZcashTransaction, SignerError> {
+        let tx_bytes = hex::decode(tx_hex)
+            .map_err(|_| SignerError::InvalidTemplate("invalid transaction hex".to_string()))?;
+
+        ZcashTransaction::deserialize(&tx_bytes)
+            .map_err(|e| SignerError::InvalidTemplate(e.to_string()))
+    }
+
+    fn assemble_script_sig(
+        &self,
+        input: &TemplateInput,
+        index: usize,
+        signature: &[u8],
+        pubkey: Option<&str>,
+        secret: Option<&str>,
+    ) -> Result<Script, SignerError> {
+        match input.input_type {
+            TemplateInputType::P2PKH => {
+                let pubkey = pubkey.ok_or(SignerError::InvalidPublicKey)?;
+                let pubkey = hex::decode(pubkey).map_err(|_| SignerError::InvalidPublicKey)?;
+
+                Ok(bitcoin::blockdata::script::Builder::new()
+                    .push_slice(signature)
+                    .push_slice(&pubkey)
+                    .into_script())
+            }
+            TemplateInputType::HTLCRedeem => {
+                let secret = secret.ok_or_else(|| {
+                    SignerError::InvalidTemplate(format!("input {} requires a secret", index))
+                })?;
+                let redeem_script = self.template_redeem_script(input, index)?;
+
+                let unlock = self
+                    .script_builder
+                    .build_redeem_input(secret, signature)
+                    .map_err(|e| SignerError::ScriptError(e.to_string()))?;
+
+                Ok(bitcoin::blockdata::script::Builder::from(unlock.to_bytes())
+                    .push_slice(redeem_script.as_bytes())
+                    .into_script())
+            }
+            TemplateInputType::HTLCMultisigRedeem | TemplateInputType::P2SHMultisig => {
+                Err(SignerError::InvalidTemplate(format!(
+                    "input {} needs partial signatures from several signers, use finalize_psbt",
+                    index
+                )))
+            }
+            TemplateInputType::HTLCRefund => {
+                let redeem_script = self.template_redeem_script(input, index)?;
+                let unlock = self.script_builder.build_refund_input(signature);
+
+                Ok(bitcoin::blockdata::script::Builder::from(unlock.to_bytes())
+                    .push_slice(redeem_script.as_bytes())
+                    .into_script())
+            }
+        }
+    }
+
+    /// Script code committed to by the sighash: the redeem script for HTLC
+    /// inputs, the prevout script pubkey otherwise
+    fn template_script_code(
+        &self,
+        input: &TemplateInput,
+        index: usize,
+    ) -> Result<Script, SignerError> {
+        match &input.redeem_script {
+            Some(_) => self.template_redeem_script(input, index),
+            None => hex::decode(&input.script_pubkey)
+                .map(Script::from)
+                .map_err(|_| {
+                    SignerError::InvalidTemplate(format!("input {} script pubkey", index))
+                }),
+        }
+    }
+
+    fn template_redeem_script(
+        &self,
+        input: &TemplateInput,
+        index: usize,
+    ) -> Result<Script, SignerError> {
+        let redeem_hex = input.redeem_script.as_ref().ok_or_else(|| {
+            SignerError::InvalidTemplate(format!("input {} is missing its redeem script", index))
+        })?;
+
+        hex::decode(redeem_hex)
+            .map(Script::from)
+            .map_err(|_| SignerError::InvalidTemplate(format!("input {} redeem script", index)))
+    }
+
+    async fn sign_input(
+        &self,
+        tx: &ZcashTransaction,
+        input_index: usize,
+        script_code: &Script,
+        amount: u64,
+        key_id: &str,
+        sighash_type: EcdsaSighashType,
+    ) -> Result<Vec<u8>, SignerError> {
+        self.sign_input_with(
+            self.backend.as_ref(),
+            tx,
+            input_index,
+            script_code,
+            amount,
+            key_id,
+            sighash_type,
+        )
+        .await
+    }
 
-        let signature = self.secp.sign_ecdsa(&message, privkey);
+    #[allow(clippy::too_many_arguments)]
+    async fn sign_input_with(
+        &self,
+        backend: &dyn Signer,
+        tx: &ZcashTransaction,
+        input_index: usize,
+        script_code: &Script,
+        amount: u64,
+        key_id: &str,
+        sighash_type: EcdsaSighashType,
+    ) -> Result<Vec<u8>, SignerError> {
+        let sighash = self.input_sighash(tx, input_index, script_code, amount, sighash_type)?;
+        self.check_policy(tx, input_index, amount, sighash_type)
+            .await?;
 
-        let mut sig_bytes = signature.serialize_der().to_vec();
-        sig_bytes.push(EcdsaSighashType::All.to_u32() as u8);
+        let mut sig_bytes = low_s_der(&backend.sign_sighash(key_id, &sighash).await?)?;
+        sig_bytes.push(sighash_type.to_u32() as u8);
 
         Ok(sig_bytes)
     }
 
-    fn parse_privkey(&self, hex: &str) -> Result<SecretKey, SignerError> {
-        let bytes = hex::decode(hex).map_err(|_| SignerError::InvalidPrivateKey)?;
+    async fn check_policy(
+        &self,
+        tx: &Transaction,
+        input_index: usize,
+        amount: u64,
+        sighash_type: EcdsaSighashType,
+    ) -> Result<(), SignerError> {
+        let Some(policy) = &self.policy else {
+            return Ok(());
+        };
+
+        let request =
+            SigningRequest::new(&self.script_builder, tx, input_index, amount, sighash_type);
+        policy
+            .approve(&request)
+            .await
+            .map_err(SignerError::PolicyRejected)
+    }
+
+    fn input_sighash(
+        &self,
+        tx: &ZcashTransaction,
+        input_index: usize,
+        script_code: &Script,
+        amount: u64,
+        sighash_type: EcdsaSighashType,
+    ) -> Result<[u8; 32], SignerError> {
+        if input_index >= tx.input.len() {
+            return Err(SignerError::MismatchedInputs);
+        }
+
+        Ok(zip243_signature_hash(
+            tx,
+            input_index,
+            script_code,
+            amount,
+            sighash_type.to_u32(),
+            self.branch_id(),
+        ))
+    }
 
-        SecretKey::from_slice(&bytes).map_err(|_| SignerError::InvalidPrivateKey)
+    /// Generate a hex private key from OS entropy, wiped from memory when dropped
+    pub fn generate_privkey(&self) -> Zeroizing<String> {
+        self.generate_privkey_with_rng(&mut rand::thread_rng())
     }
 
-    pub fn generate_privkey(&self) -> String {
-        let (secret_key, _) = self.secp.generate_keypair(&mut rand::thread_rng());
-        hex::encode(secret_key.secret_bytes())
+    /// Generate a hex private key from `rng`. Pass a seeded RNG such as
+    /// `StdRng::seed_from_u64` for reproducible test and regtest fixtures.
+    pub fn generate_privkey_with_rng<R: RngCore + CryptoRng + ?Sized>(
+        &self,
+        rng: &mut R,
+    ) -> Zeroizing<String> {
+        let (mut secret_key, _) = self.secp.generate_keypair(rng);
+        let bytes = Zeroizing::new(secret_key.secret_bytes());
+        secret_key.non_secure_erase();
+
+        Zeroizing::new(hex::encode(bytes.as_slice()))
     }
 
     pub fn derive_pubkey(&self, privkey_hex: &str) -> Result<String, SignerError> {
-        let privkey = self.parse_privkey(privkey_hex)?;
+        let mut privkey = parse_privkey(privkey_hex)?;
         let pubkey = PublicKey::from_secret_key(&self.secp, &privkey);
+        privkey.non_secure_erase();
+
         Ok(hex::encode(pubkey.serialize()))
     }
 
+    /// Verify a DER signature with trailing sighash type byte. Non-strict
+    /// DER, high-S and undefined sighash types are rejected outright, as
+    /// they would let a relayer malleate the txid.
     pub fn verify_signature(
         &self,
         message: &[u8],
@@ -139,9 +863,7 @@ impl TransactionSigner {
         pubkey_hex: &str,
     ) -> Result<bool, SignerError> {
         let sig_bytes = hex::decode(signature).map_err(|_| SignerError::InvalidSignature)?;
-
-        let signature = Signature::from_der(&sig_bytes[..sig_bytes.len() - 1])
-            .map_err(|_| SignerError::InvalidSignature)?;
+        let signature = check_signature_encoding(&sig_bytes)?;
 
         let pubkey_bytes = hex::decode(pubkey_hex).map_err(|_| SignerError::InvalidPublicKey)?;
 
@@ -154,12 +876,335 @@ impl TransactionSigner {
         Ok(self.secp.verify_ecdsa(&msg, &signature, &pubkey).is_ok())
     }
 
+    /// SHA-256 hash lock of a raw secret
+    pub fn hash_lock(&self, secret: &[u8]) -> String {
+        hex::encode(Sha256::digest(secret))
+    }
+
+    /// Hash lock of a hex-encoded secret, rejecting anything that is not hex
+    pub fn hash_lock_from_hex(&self, secret_hex: &str) -> Result<String, SignerError> {
+        let secret_bytes =
+            Zeroizing::new(hex::decode(secret_hex).map_err(|_| SignerError::InvalidSecret)?);
+        Ok(self.hash_lock(&secret_bytes))
+    }
+
+    #[deprecated(
+        note = "falls back to hashing the ASCII bytes of non-hex secrets; use hash_lock or hash_lock_from_hex"
+    )]
     pub fn generate_hash_lock(&self, secret: &str) -> String {
-        let secret_bytes = hex::decode(secret).unwrap_or_else(|_| secret.as_bytes().to_vec());
-        let mut hasher = Sha256::new();
-        hasher.update(&secret_bytes);
-        hex::encode(hasher.finalize())
+        match self.hash_lock_from_hex(secret) {
+            Ok(hash_lock) => hash_lock,
+            Err(_) => self.hash_lock(secret.as_bytes()),
+        }
+    }
+
+    // ==================== WIF ====================
+
+    /// Hex private key from zcashd `dumpprivkey` output. Only WIF keys with
+    /// the compressed flag are accepted, as every address this crate derives
+    /// uses the compressed public key.
+    pub fn privkey_from_wif(&self, wif: &str) -> Result<Zeroizing<String>, SignerError> {
+        let data = Zeroizing::new(
+            bs58::decode(wif.trim())
+                .into_vec()
+                .map_err(|e| SignerError::InvalidWif(e.to_string()))?,
+        );
+
+        if data.len() != WIF_COMPRESSED_LEN + 4 && data.len() != WIF_UNCOMPRESSED_LEN + 4 {
+            return Err(SignerError::InvalidWif(format!(
+                "unexpected length {}",
+                data.len()
+            )));
+        }
+
+        let (payload, checksum) = data.split_at(data.len() - 4);
+        if checksum != &sha256d(payload)[..4] {
+            return Err(SignerError::InvalidWif("bad checksum".to_string()));
+        }
+
+        let network = self.script_builder.network();
+        if payload[0] != network.wif_prefix() {
+            return Err(SignerError::InvalidWif(format!(
+                "not a {} key",
+                network.as_str()
+            )));
+        }
+
+        if payload.len() != WIF_COMPRESSED_LEN || payload[WIF_COMPRESSED_LEN - 1] != 0x01 {
+            return Err(SignerError::InvalidWif(
+                "uncompressed keys are not supported".to_string(),
+            ));
+        }
+
+        let privkey = Zeroizing::new(hex::encode(&payload[1..33]));
+        parse_privkey(&privkey)?.non_secure_erase();
+
+        Ok(privkey)
+    }
+
+    /// Compressed WIF encoding of a hex private key, for zcashd `importprivkey`
+    pub fn privkey_to_wif(&self, privkey_hex: &str) -> Result<Zeroizing<String>, SignerError> {
+        let mut secret_key = parse_privkey(privkey_hex)?;
+
+        let mut data = Zeroizing::new(Vec::with_capacity(WIF_COMPRESSED_LEN + 4));
+        data.push(self.script_builder.network().wif_prefix());
+        data.extend_from_slice(&secret_key.secret_bytes());
+        data.push(0x01);
+        secret_key.non_secure_erase();
+
+        let checksum = sha256d(&data);
+        data.extend_from_slice(&checksum[..4]);
+
+        Ok(Zeroizing::new(bs58::encode(data.as_slice()).into_string()))
+    }
+
+    // ==================== Message Signing ====================
+
+    /// Sign `message` with `key_id`, returning the base64 compact recoverable
+    /// signature zcashd's `signmessage` produces
+    pub async fn sign_message(&self, message: &str, key_id: &str) -> Result<String, SignerError> {
+        let digest = message_digest(message);
+        let der = low_s_der(&self.backend.sign_sighash(key_id, &digest).await?)?;
+        let pubkey = self.backend_public_key(key_id).await?;
+
+        let signature = Signature::from_der(&der).map_err(|_| SignerError::InvalidSignature)?;
+        let compact = signature.serialize_compact();
+        let msg = Message::from_digest(digest);
+
+        // Backends only return DER, so find the recovery id that yields our key
+        for id in 0..4 {
+            let recovery_id =
+                RecoveryId::from_i32(id).map_err(|e| SignerError::MessageError(e.to_string()))?;
+            let recoverable = RecoverableSignature::from_compact(&compact, recovery_id)
+                .map_err(|_| SignerError::InvalidSignature)?;
+
+            if self.secp.recover_ecdsa(&msg, &recoverable).ok() == Some(pubkey) {
+                let mut encoded = Vec::with_capacity(65);
+                encoded.push(MESSAGE_HEADER_COMPRESSED + id as u8);
+                encoded.extend_from_slice(&compact);
+                return Ok(BASE64.encode(encoded));
+            }
+        }
+
+        Err(SignerError::InvalidSignature)
+    }
+
+    /// Check a base64 `signmessage` signature against a transparent P2PKH address
+    pub fn verify_message(
+        &self,
+        address: &str,
+        message: &str,
+        signature: &str,
+    ) -> Result<bool, SignerError> {
+        let encoded = BASE64
+            .decode(signature)
+            .map_err(|_| SignerError::InvalidSignature)?;
+        if encoded.len() != 65 || !(27..35).contains(&encoded[0]) {
+            return Err(SignerError::InvalidSignature);
+        }
+
+        let header = encoded[0] - MESSAGE_HEADER_UNCOMPRESSED;
+        let compressed = header >= 4;
+        let recovery_id =
+            RecoveryId::from_i32((header % 4) as i32).map_err(|_| SignerError::InvalidSignature)?;
+        let recoverable = RecoverableSignature::from_compact(&encoded[1..], recovery_id)
+            .map_err(|_| SignerError::InvalidSignature)?;
+
+        let msg = Message::from_digest(message_digest(message));
+        let pubkey = match self.secp.recover_ecdsa(&msg, &recoverable) {
+            Ok(pubkey) => pubkey,
+            Err(_) => return Ok(false),
+        };
+
+        let pubkey_bytes = if compressed {
+            pubkey.serialize().to_vec()
+        } else {
+            pubkey.serialize_uncompressed().to_vec()
+        };
+
+        Ok(self.script_builder.pubkey_to_p2pkh_address(&pubkey_bytes) == address)
+    }
+
+    async fn backend_public_key(&self, key_id: &str) -> Result<PublicKey, SignerError> {
+        let pubkey = self.backend.derive_pubkey(key_id).await?;
+        let bytes = hex::decode(pubkey).map_err(|_| SignerError::InvalidPublicKey)?;
+        PublicKey::from_slice(&bytes).map_err(|_| SignerError::InvalidPublicKey)
+    }
+}
+
+/// Version byte, 32-byte key and the compressed flag
+const WIF_COMPRESSED_LEN: usize = 34;
+const WIF_UNCOMPRESSED_LEN: usize = 33;
+
+fn sha256d(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(Sha256::digest(data)).into()
+}
+
+const MESSAGE_MAGIC: &str = "Zcash Signed Message:\n";
+const MESSAGE_HEADER_UNCOMPRESSED: u8 = 27;
+const MESSAGE_HEADER_COMPRESSED: u8 = 31;
+
+/// Double SHA-256 of the varint-prefixed magic and message, as in zcashd
+fn message_digest(message: &str) -> [u8; 32] {
+    let mut data = Vec::new();
+    data.extend(encode::serialize(&MESSAGE_MAGIC.to_string()));
+    data.extend(encode::serialize(&message.to_string()));
+
+    sha256d(&data)
+}
+
+/// Ways a signature's encoding differs from the one form relay policy
+/// accepts; each lets a third party change the txid without the key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MalleabilityWarning {
+    /// Valid BER but not minimal strict DER
+    NonStrictDer,
+    /// S above half the curve order
+    HighS,
+}
+
+/// Canonical low-S strict DER form of a signature, with what had to change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CanonicalSignature {
+    pub der: Vec<u8>,
+    pub warnings: Vec<MalleabilityWarning>,
+}
+
+impl CanonicalSignature {
+    pub fn is_malleated(&self) -> bool {
+        !self.warnings.is_empty()
+    }
+}
+
+/// Re-encode any DER signature (without sighash type byte) as canonical
+/// low-S strict DER, reporting each malleation found
+pub fn canonicalize_signature(der: &[u8]) -> Result<CanonicalSignature, SignerError> {
+    let mut signature = Signature::from_der_lax(der).map_err(|_| SignerError::InvalidSignature)?;
+    let mut warnings = Vec::new();
+
+    if signature.serialize_der().as_ref() != der {
+        warnings.push(MalleabilityWarning::NonStrictDer);
+    }
+
+    let original = signature;
+    signature.normalize_s();
+    if signature != original {
+        warnings.push(MalleabilityWarning::HighS);
+    }
+
+    Ok(CanonicalSignature {
+        der: signature.serialize_der().to_vec(),
+        warnings,
+    })
+}
+
+/// Input signature pushed in a non-canonical form
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MalleatedSignature {
+    pub input_index: usize,
+    /// Hex DER with trailing sighash type byte, as pushed
+    pub signature: String,
+    pub warnings: Vec<MalleabilityWarning>,
+}
+
+/// Signature pushes of `tx` that are not canonical, e.g. when analyzing an
+/// observed redeem transaction whose txid may have been malleated
+pub fn malleated_signatures(tx: &Transaction) -> Vec<MalleatedSignature> {
+    let mut found = Vec::new();
+
+    for (input_index, input) in tx.input.iter().enumerate() {
+        for instruction in input.script_sig.instructions().flatten() {
+            let Instruction::PushBytes(push) = instruction else {
+                continue;
+            };
+            if push.len() < 9 || push[0] != 0x30 {
+                continue;
+            }
+
+            if let Ok(canonical) = canonicalize_signature(&push[..push.len() - 1]) {
+                if canonical.is_malleated() {
+                    found.push(MalleatedSignature {
+                        input_index,
+                        signature: hex::encode(push),
+                        warnings: canonical.warnings,
+                    });
+                }
+            }
+        }
+    }
+
+    found
+}
+
+/// Re-encode a backend signature as canonical low-S DER. Backends such as
+/// HSMs are not guaranteed to normalize S themselves.
+fn low_s_der(der: &[u8]) -> Result<Vec<u8>, SignerError> {
+    Ok(canonicalize_signature(der)?.der)
+}
+
+/// BIP66 strict DER, low-S and defined sighash type checks on a signature
+/// with its trailing sighash type byte
+fn check_signature_encoding(sig: &[u8]) -> Result<Signature, SignerError> {
+    if !is_strict_der(sig) {
+        return Err(SignerError::NonCanonicalDer);
+    }
+
+    let sighash_type = sig[sig.len() - 1];
+    if !(1..=3).contains(&(sighash_type & !0x80)) {
+        return Err(SignerError::InvalidSighashType(sighash_type));
+    }
+
+    let canonical =
+        canonicalize_signature(&sig[..sig.len() - 1]).map_err(|_| SignerError::NonCanonicalDer)?;
+    match canonical.warnings.first() {
+        Some(MalleabilityWarning::NonStrictDer) => Err(SignerError::NonCanonicalDer),
+        Some(MalleabilityWarning::HighS) => Err(SignerError::HighS),
+        None => Signature::from_der(&canonical.der).map_err(|_| SignerError::NonCanonicalDer),
+    }
+}
+
+/// BIP66 `IsValidSignatureEncoding`
+fn is_strict_der(sig: &[u8]) -> bool {
+    if sig.len() < 9 || sig.len() > 73 || sig[0] != 0x30 || sig[1] as usize != sig.len() - 3 {
+        return false;
+    }
+
+    let len_r = sig[3] as usize;
+    if 5 + len_r >= sig.len() {
+        return false;
+    }
+    let len_s = sig[5 + len_r] as usize;
+    if len_r + len_s + 7 != sig.len() {
+        return false;
     }
+
+    if sig[2] != 0x02 || len_r == 0 || sig[4] & 0x80 != 0 {
+        return false;
+    }
+    if len_r > 1 && sig[4] == 0x00 && sig[5] & 0x80 == 0 {
+        return false;
+    }
+
+    if sig[len_r + 4] != 0x02 || len_s == 0 || sig[len_r + 6] & 0x80 != 0 {
+        return false;
+    }
+    if len_s > 1 && sig[len_r + 6] == 0x00 && sig[len_r + 7] & 0x80 == 0 {
+        return false;
+    }
+
+    true
+}
+
+fn template_amount(input: &TemplateInput, index: usize) -> Result<u64, SignerError> {
+    zec_to_zatoshis(&input.amount)
+        .ok_or_else(|| SignerError::InvalidTemplate(format!("input {} amount", index)))
+}
+
+/// Callers should `non_secure_erase` the key once done with it
+fn parse_privkey(hex: &str) -> Result<SecretKey, SignerError> {
+    let bytes = Zeroizing::new(hex::decode(hex).map_err(|_| SignerError::InvalidPrivateKey)?);
+
+    SecretKey::from_slice(&bytes).map_err(|_| SignerError::InvalidPrivateKey)
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -173,6 +1218,21 @@ pub enum SignerError {
     #[error("Invalid signature format")]
     InvalidSignature,
 
+    #[error("Secret is not valid hex")]
+    InvalidSecret,
+
+    #[error("Invalid WIF private key: {0}")]
+    InvalidWif(String),
+
+    #[error("Signature is not strict DER")]
+    NonCanonicalDer,
+
+    #[error("Signature has a high S value")]
+    HighS,
+
+    #[error("Invalid sighash type: 0x{0:02x}")]
+    InvalidSighashType(u8),
+
     #[error("Mismatched number of inputs and keys")]
     MismatchedInputs,
 
@@ -184,12 +1244,87 @@ pub enum SignerError {
 
     #[error("Script error: {0}")]
     ScriptError(String),
+
+    #[error("Invalid signing template: {0}")]
+    InvalidTemplate(String),
+
+    #[error("Missing signature for input {0}")]
+    MissingSignature(usize),
+
+    #[error("Signing policy rejected the signature: {0}")]
+    PolicyRejected(String),
+
+    #[error("No signing key for the script of input {0}")]
+    MissingKey(usize),
+
+    #[error("Input {index} has {have} of the {need} required signatures")]
+    InsufficientSignatures {
+        index: usize,
+        have: usize,
+        need: usize,
+    },
+
+    #[error("Signing device error: {0}")]
+    DeviceError(String),
+
+    #[error("Signing request rejected")]
+    Rejected,
+
+    #[error("Remote signer error: {0}")]
+    RemoteError(String),
+
+    #[error("Remote signer URL must use https: {0}")]
+    InsecureRemoteUrl(String),
+
+    #[error("Signing key unavailable: {0}")]
+    KeyUnavailable(String),
+
+    #[error("Signing input {index} failed: {source}")]
+    InputFailed {
+        index: usize,
+        #[source]
+        source: Box<SignerError>,
+    },
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use crate::ZcashNetwork;
+    use bitcoin::blockdata::transaction::{OutPoint, TxIn, TxOut};
+    use bitcoin::hashes::Hash;
+    use bitcoin::{PackedLockTime, Sequence, Witness};
+
+    /// Unsigned transaction spending outputs `0..inputs` of the all-zero
+    /// txid into one 1000 zat output. A non-zero `lock_time` gets the
+    /// non-final sequence that makes it count.
+    pub(crate) fn unsigned_tx(inputs: u32, lock_time: u32) -> ZcashTransaction {
+        let sequence = if lock_time > 0 {
+            0xFFFFFFFE
+        } else {
+            0xFFFFFFFF
+        };
+        Transaction {
+            version: 4,
+            lock_time: PackedLockTime(lock_time),
+            input: (0..inputs)
+                .map(|vout| TxIn {
+                    previous_output: OutPoint {
+                        vout,
+                        ..OutPoint::default()
+                    },
+                    script_sig: Script::new(),
+                    sequence: Sequence(sequence),
+                    witness: Witness::default(),
+                })
+                .collect(),
+            output: vec![TxOut {
+                value: 1000,
+                script_pubkey: Script::new(),
+            }],
+        }
+        .into()
+    }
 
     #[test]
     fn test_generate_privkey() {
@@ -200,6 +1335,51 @@ mod tests {
         assert_eq!(privkey.len(), 64);
     }
 
+    #[test]
+    fn test_seeded_privkey_generation_is_reproducible() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let script_builder = HTLCScriptBuilder::new(ZcashNetwork::Testnet);
+        let signer = TransactionSigner::new(script_builder);
+
+        let first = signer.generate_privkey_with_rng(&mut StdRng::seed_from_u64(7));
+        let second = signer.generate_privkey_with_rng(&mut StdRng::seed_from_u64(7));
+        let other = signer.generate_privkey_with_rng(&mut StdRng::seed_from_u64(8));
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+        assert!(signer.derive_pubkey(&first).is_ok());
+    }
+
+    #[test]
+    fn test_wif_roundtrip() {
+        let signer = TransactionSigner::new(HTLCScriptBuilder::new(ZcashNetwork::Mainnet));
+        let privkey = "0c28fca386c7a227600b2fe50b7cae11ec86d3bf1fbe471be89827e19d72aa1d";
+
+        // Compressed encoding of the well-known Bitcoin wiki example key
+        let wif = signer.privkey_to_wif(privkey).unwrap();
+        assert_eq!(
+            wif.as_str(),
+            "KwdMAjGmerYanjeui5SHS7JkmpZvVipYvB2LJGU1ZxJwYvP98617"
+        );
+        assert_eq!(signer.privkey_from_wif(&wif).unwrap().as_str(), privkey);
+
+        // Same key without the compressed flag
+        assert!(matches!(
+            signer.privkey_from_wif("5HueCGU8rMjxEXxiPuD5BDku4MkFqeZyd4dZ1jvhTVqvbTLvyTJ"),
+            Err(SignerError::InvalidWif(_))
+        ));
+
+        let testnet = TransactionSigner::new(HTLCScriptBuilder::new(ZcashNetwork::Testnet));
+        assert!(matches!(
+            testnet.privkey_from_wif(&wif),
+            Err(SignerError::InvalidWif(_))
+        ));
+
+        let mut corrupted = wif.to_string();
+        corrupted.replace_range(10..11, "X");
+        assert!(signer.privkey_from_wif(&corrupted).is_err());
+    }
+
     #[test]
     fn test_derive_pubkey() {
         let script_builder = HTLCScriptBuilder::new(ZcashNetwork::Testnet);
@@ -216,7 +1396,676 @@ mod tests {
         let signer = TransactionSigner::new(script_builder);
 
         let secret = "deadbeef";
-        let hash_lock = signer.generate_hash_lock(secret);
+        let hash_lock = signer.hash_lock_from_hex(secret).unwrap();
         assert_eq!(hash_lock.len(), 64);
+        assert_eq!(hash_lock, signer.hash_lock(&[0xde, 0xad, 0xbe, 0xef]));
+
+        // "deadbeeg" used to be hashed as ASCII without complaint
+        assert!(matches!(
+            signer.hash_lock_from_hex("deadbeeg"),
+            Err(SignerError::InvalidSecret)
+        ));
+    }
+
+    #[test]
+    fn test_apply_signatures_builds_refund_script_sig() {
+        let script_builder = HTLCScriptBuilder::new(ZcashNetwork::Testnet);
+        let signer = TransactionSigner::new(script_builder);
+
+        let redeem_script = Script::from(vec![0x51]);
+        let tx = unsigned_tx(1, 100);
+
+        let template = UnsignedTxTemplate {
+            network: ZcashNetwork::Testnet,
+            unsigned_tx_hex: hex::encode(tx.serialize()),
+            inputs: vec![TemplateInput {
+                txid: OutPoint::default().txid.to_string(),
+                vout: OutPoint::default().vout,
+                amount: "0.0001".to_string(),
+                script_pubkey: String::new(),
+                redeem_script: Some(hex::encode(redeem_script.as_bytes())),
+                input_type: TemplateInputType::HTLCRefund,
+            }],
+        };
+
+        let template = UnsignedTxTemplate::from_json(&template.to_json().unwrap()).unwrap();
+        let signature = ExternalSignature {
+            input_index: 0,
+            signature: "30".repeat(8),
+            pubkey: None,
+            secret: None,
+        };
+
+        let signed = signer.apply_signatures(&template, &[signature]).unwrap();
+        let expected = bitcoin::blockdata::script::Builder::new()
+            .push_slice(&[0x30; 8])
+            .push_opcode(bitcoin::blockdata::opcodes::OP_FALSE)
+            .push_slice(redeem_script.as_bytes())
+            .into_script();
+        assert_eq!(signed.input[0].script_sig, expected);
+
+        assert!(matches!(
+            signer.apply_signatures(&template, &[]),
+            Err(SignerError::MissingSignature(0))
+        ));
+    }
+
+    /// The high-S twin `(r, n - s)` of a signature
+    fn high_s_twin(signature: &Signature) -> Signature {
+        let order = hex::decode("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141")
+            .unwrap();
+        let mut compact = signature.serialize_compact();
+        let mut borrow = 0i16;
+        for i in (0..32).rev() {
+            let diff = order[i] as i16 - compact[32 + i] as i16 - borrow;
+            compact[32 + i] = diff.rem_euclid(256) as u8;
+            borrow = (diff < 0) as i16;
+        }
+        Signature::from_compact(&compact).unwrap()
+    }
+
+    #[test]
+    fn test_verify_signature_rejects_malleable_encodings() {
+        let script_builder = HTLCScriptBuilder::new(ZcashNetwork::Testnet);
+        let signer = TransactionSigner::new(script_builder);
+        let privkey = signer.generate_privkey();
+        let pubkey = signer.derive_pubkey(&privkey).unwrap();
+
+        let digest = [4u8; 32];
+        let message = Message::from_digest(digest);
+        let signature = signer
+            .secp
+            .sign_ecdsa(&message, &parse_privkey(&privkey).unwrap());
+        let with_type = |der: &[u8], sighash_type: u8| {
+            let mut bytes = der.to_vec();
+            bytes.push(sighash_type);
+            hex::encode(bytes)
+        };
+
+        let der = signature.serialize_der();
+        assert!(signer
+            .verify_signature(&digest, &with_type(&der, 0x01), &pubkey)
+            .unwrap());
+        assert!(matches!(
+            signer.verify_signature(&digest, &with_type(&der, 0x00), &pubkey),
+            Err(SignerError::InvalidSighashType(0x00))
+        ));
+
+        let high_s = high_s_twin(&signature).serialize_der();
+        assert!(matches!(
+            signer.verify_signature(&digest, &with_type(&high_s, 0x01), &pubkey),
+            Err(SignerError::HighS)
+        ));
+        assert_eq!(low_s_der(&high_s).unwrap(), der.to_vec());
+
+        // Pad r with a superfluous leading zero byte
+        let len_r = der[3] as usize;
+        let mut padded = vec![0x30, der[1] + 1, 0x02, der[3] + 1, 0x00];
+        padded.extend_from_slice(&der[4..4 + len_r]);
+        padded.extend_from_slice(&der[4 + len_r..]);
+        assert!(matches!(
+            signer.verify_signature(&digest, &with_type(&padded, 0x01), &pubkey),
+            Err(SignerError::NonCanonicalDer)
+        ));
+    }
+
+    #[test]
+    fn test_canonicalize_signature_reports_malleation() {
+        let secp = Secp256k1::new();
+        let privkey = SecretKey::from_slice(&[7u8; 32]).unwrap();
+        let signature = secp.sign_ecdsa(&Message::from_digest([5u8; 32]), &privkey);
+        let der = signature.serialize_der().to_vec();
+
+        let canonical = canonicalize_signature(&der).unwrap();
+        assert_eq!(canonical.der, der);
+        assert!(!canonical.is_malleated());
+
+        let high_s = high_s_twin(&signature).serialize_der().to_vec();
+        let canonical = canonicalize_signature(&high_s).unwrap();
+        assert_eq!(canonical.der, der);
+        assert_eq!(canonical.warnings, vec![MalleabilityWarning::HighS]);
+
+        // Superfluous leading zero on r, on top of a high S
+        let len_r = high_s[3] as usize;
+        let mut padded = vec![0x30, high_s[1] + 1, 0x02, high_s[3] + 1, 0x00];
+        padded.extend_from_slice(&high_s[4..4 + len_r]);
+        padded.extend_from_slice(&high_s[4 + len_r..]);
+        let canonical = canonicalize_signature(&padded).unwrap();
+        assert_eq!(canonical.der, der);
+        assert_eq!(
+            canonical.warnings,
+            vec![
+                MalleabilityWarning::NonStrictDer,
+                MalleabilityWarning::HighS
+            ]
+        );
+
+        let push = |sig: &[u8]| {
+            let mut bytes = sig.to_vec();
+            bytes.push(0x01);
+            bytes
+        };
+        let script_sig = |sig: &[u8]| {
+            bitcoin::blockdata::script::Builder::new()
+                .push_slice(&push(sig))
+                .push_slice(&[1u8; 32])
+                .into_script()
+        };
+        let mut tx = unsigned_tx(2, 0);
+        tx.input[0].script_sig = script_sig(&der);
+        tx.input[1].script_sig = script_sig(&high_s);
+        assert_eq!(
+            malleated_signatures(&tx),
+            vec![MalleatedSignature {
+                input_index: 1,
+                signature: hex::encode(push(&high_s)),
+                warnings: vec![MalleabilityWarning::HighS],
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sign_and_verify_message() {
+        let script_builder = HTLCScriptBuilder::new(ZcashNetwork::Testnet);
+        let signer = TransactionSigner::new(script_builder.clone());
+        let privkey = signer.generate_privkey();
+
+        let pubkey = hex::decode(signer.derive_pubkey(&privkey).unwrap()).unwrap();
+        let address = script_builder.pubkey_to_p2pkh_address(&pubkey);
+        assert!(address.starts_with("tm"));
+
+        let message = "refund key for swap 42";
+        let signature = signer.sign_message(message, &privkey).await.unwrap();
+
+        assert!(signer
+            .verify_message(&address, message, &signature)
+            .unwrap());
+        assert!(!signer
+            .verify_message(&address, "refund key for swap 43", &signature)
+            .unwrap());
+        assert!(signer
+            .verify_message(&address, message, "not base64!")
+            .is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_parallel_creation_signing_attributes_errors() {
+        let script_builder = HTLCScriptBuilder::new(ZcashNetwork::Testnet);
+        let signer = TransactionSigner::new(script_builder);
+
+        let inputs = 16;
+        let tx = unsigned_tx(inputs, 0);
+        let keys: Vec<_> = (0..inputs).map(|_| signer.generate_privkey()).collect();
+        let scripts: Vec<_> = keys
+            .iter()
+            .map(|key| {
+                let pubkey = hex::decode(signer.derive_pubkey(key).unwrap()).unwrap();
+                Script::new_p2pkh(&bitcoin::PubkeyHash::hash(&pubkey))
+            })
+            .collect();
+        let amounts = vec![5000u64; inputs as usize];
+
+        // Half the keys by script pubkey, half by address
+        let mut key_map = KeyMap::new();
+        for (i, (key, script)) in keys.iter().zip(scripts.iter()).enumerate() {
+            if i % 2 == 0 {
+                key_map.insert(&hex::encode(script.as_bytes()), key);
+            } else {
+                let address = signer.script_builder.script_pubkey_to_address(script);
+                key_map.insert(&address.unwrap(), key);
+            }
+        }
+
+        let signed = signer
+            .sign_htlc_creation(tx.clone(), scripts.clone(), amounts.clone(), &key_map)
+            .await
+            .unwrap();
+        for (i, (input, key)) in signed.input.iter().zip(keys.iter()).enumerate() {
+            let pushes: Vec<_> = input.script_sig.instructions().collect();
+            assert_eq!(pushes.len(), 2, "input {}", i);
+            assert_eq!(
+                pushes[1].as_ref().unwrap(),
+                &bitcoin::blockdata::script::Instruction::PushBytes(
+                    &hex::decode(signer.derive_pubkey(key).unwrap()).unwrap()
+                ),
+            );
+        }
+
+        let bad_key = key_map
+            .clone()
+            .with_key(&hex::encode(scripts[7].as_bytes()), "not-a-key");
+        let result = signer
+            .sign_htlc_creation(tx.clone(), scripts.clone(), amounts.clone(), &bad_key)
+            .await;
+        assert!(matches!(
+            result,
+            Err(SignerError::InputFailed { index: 7, .. })
+        ));
+
+        let mut scripts_missing = scripts;
+        scripts_missing[3] = Script::from(vec![0x51]);
+        let result = signer
+            .sign_htlc_creation(tx, scripts_missing, amounts, &key_map)
+            .await;
+        assert!(matches!(result, Err(SignerError::MissingKey(3))));
+    }
+
+    #[tokio::test]
+    async fn test_raw_signatures_match_local_signing() {
+        let script_builder = HTLCScriptBuilder::new(ZcashNetwork::Testnet);
+        let signer = TransactionSigner::new(script_builder);
+        let privkey = signer.generate_privkey();
+
+        let redeem_script = Script::from(vec![0x51]);
+        let tx = unsigned_tx(1, 100);
+
+        let template = UnsignedTxTemplate {
+            network: ZcashNetwork::Testnet,
+            unsigned_tx_hex: hex::encode(tx.serialize()),
+            inputs: vec![TemplateInput {
+                txid: OutPoint::default().txid.to_string(),
+                vout: OutPoint::default().vout,
+                amount: "0.0001".to_string(),
+                script_pubkey: String::new(),
+                redeem_script: Some(hex::encode(redeem_script.as_bytes())),
+                input_type: TemplateInputType::HTLCRefund,
+            }],
+        };
+
+        let sighashes = signer
+            .compute_sighashes(&template, EcdsaSighashType::All)
+            .unwrap();
+        assert_eq!(sighashes.len(), 1);
+
+        // Stand-in for an air-gapped signer that only sees the digest
+        let digest = hex::decode(&sighashes[0].sighash).unwrap();
+        let message = Message::from_digest_slice(&digest).unwrap();
+        let compact = signer
+            .secp
+            .sign_ecdsa(&message, &parse_privkey(&privkey).unwrap())
+            .serialize_compact();
+
+        let raw = RawSignature {
+            input_index: 0,
+            r: hex::encode(&compact[..32]),
+            s: hex::encode(&compact[32..]),
+            sighash_type: sighashes[0].sighash_type,
+            pubkey: None,
+            secret: None,
+        };
+        let external = signer.apply_raw_signatures(&template, &[raw]).unwrap();
+
+        let local = signer
+            .sign_htlc_refund(tx, 0, &redeem_script, 10_000, &privkey)
+            .await
+            .unwrap();
+        assert_eq!(external.input[0].script_sig, local.input[0].script_sig);
+    }
+
+    #[tokio::test]
+    async fn test_single_anyonecanpay_survives_fee_bump() {
+        use bitcoin::blockdata::script::Instruction;
+
+        let script_builder = HTLCScriptBuilder::new(ZcashNetwork::Testnet);
+        let signer = TransactionSigner::new(script_builder)
+            .with_sighash_type(EcdsaSighashType::SinglePlusAnyoneCanPay);
+        let privkey = signer.generate_privkey();
+        let pubkey = signer.derive_pubkey(&privkey).unwrap();
+
+        let redeem_script = Script::from(vec![0x51]);
+        let mut tx = unsigned_tx(1, 100);
+
+        let signed = signer
+            .sign_htlc_refund(tx.clone(), 0, &redeem_script, 5000, &privkey)
+            .await
+            .unwrap();
+        let signature = match signed.input[0].script_sig.instructions().next() {
+            Some(Ok(Instruction::PushBytes(bytes))) => bytes.to_vec(),
+            other => panic!("unexpected script sig push: {:?}", other),
+        };
+        assert_eq!(
+            *signature.last().unwrap(),
+            EcdsaSighashType::SinglePlusAnyoneCanPay.to_u32() as u8
+        );
+
+        // A third party adds a fee input and a change output
+        let fee_input = TxIn {
+            previous_output: OutPoint {
+                vout: 7,
+                ..OutPoint::default()
+            },
+            ..tx.input[0].clone()
+        };
+        tx.input.push(fee_input);
+        tx.output.push(TxOut {
+            value: 400,
+            script_pubkey: Script::from(vec![0x51]),
+        });
+
+        let sighash = signer
+            .input_sighash(
+                &tx,
+                0,
+                &redeem_script,
+                5000,
+                EcdsaSighashType::SinglePlusAnyoneCanPay,
+            )
+            .unwrap();
+        assert!(signer
+            .verify_signature(&sighash, &hex::encode(&signature), &pubkey)
+            .unwrap());
+
+        let all_sighash = signer
+            .input_sighash(&tx, 0, &redeem_script, 5000, EcdsaSighashType::All)
+            .unwrap();
+        assert_ne!(sighash, all_sighash);
+    }
+
+    #[tokio::test]
+    async fn test_psbt_combine_and_finalize() {
+        let script_builder = HTLCScriptBuilder::new(ZcashNetwork::Testnet);
+        let signer = TransactionSigner::new(script_builder);
+
+        let outpoints: Vec<OutPoint> = (0..2)
+            .map(|vout| OutPoint {
+                txid: OutPoint::default().txid,
+                vout,
+            })
+            .collect();
+        let tx = unsigned_tx(2, 0);
+
+        let inputs = outpoints
+            .iter()
+            .map(|outpoint| TemplateInput {
+                txid: outpoint.txid.to_string(),
+                vout: outpoint.vout,
+                amount: "0.0001".to_string(),
+                script_pubkey: "76a914".to_string() + &"00".repeat(20) + "88ac",
+                redeem_script: None,
+                input_type: TemplateInputType::P2PKH,
+            })
+            .collect();
+        let builder = crate::TransactionBuilder::new(ZcashNetwork::Testnet);
+        let mut psbt = builder.create_psbt(&tx, inputs).unwrap();
+        let mut other = PartiallySignedTx::from_json(&psbt.to_json().unwrap()).unwrap();
+
+        signer
+            .sign_psbt_input(&mut psbt, 0, &signer.generate_privkey())
+            .await
+            .unwrap();
+        assert!(!psbt.is_complete());
+        assert!(signer.finalize_psbt(&psbt).is_err());
+
+        signer
+            .sign_psbt_input(&mut other, 1, &signer.generate_privkey())
+            .await
+            .unwrap();
+        signer.combine_psbt(&mut psbt, &other).unwrap();
+        assert!(psbt.is_complete());
+
+        let signed = ZcashTransaction::deserialize(
+            &hex::decode(signer.finalize_psbt(&psbt).unwrap()).unwrap(),
+        )
+        .unwrap();
+        assert!(signed
+            .input
+            .iter()
+            .all(|input| !input.script_sig.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn test_multisig_redeem_from_partial_signatures() {
+        use crate::HTLCParams;
+        use bitcoin::blockdata::script::Instruction;
+
+        let script_builder = HTLCScriptBuilder::new(ZcashNetwork::Testnet);
+        let signer = TransactionSigner::new(script_builder.clone());
+
+        let keys: Vec<_> = (0..3).map(|_| signer.generate_privkey()).collect();
+        let pubkeys: Vec<String> = keys
+            .iter()
+            .map(|key| signer.derive_pubkey(key).unwrap())
+            .collect();
+        let secret = "deadbeef";
+        let params = HTLCParams {
+            recipient_pubkey: String::new(),
+            refund_pubkey: pubkeys[0].clone(),
+            hash_lock: signer.hash_lock_from_hex(secret).unwrap(),
+            timelock: 100,
+            amount: "0.0001".to_string(),
+            metadata: None,
+            priority: Default::default(),
+            htlc_id: None,
+        };
+        let redeem_script = script_builder
+            .build_multisig_htlc_script(&params, 2, &pubkeys)
+            .unwrap();
+
+        let tx = unsigned_tx(1, 0);
+        let template = UnsignedTxTemplate {
+            network: ZcashNetwork::Testnet,
+            unsigned_tx_hex: hex::encode(tx.serialize()),
+            inputs: vec![TemplateInput {
+                txid: OutPoint::default().txid.to_string(),
+                vout: OutPoint::default().vout,
+                amount: "0.0001".to_string(),
+                script_pubkey: hex::encode(script_builder.p2sh_script_pubkey(&redeem_script)),
+                redeem_script: Some(hex::encode(redeem_script.as_bytes())),
+                input_type: TemplateInputType::HTLCMultisigRedeem,
+            }],
+        };
+
+        // Signers 3 and 1 sign independently and exchange their PSBT JSON
+        let mut psbt = PartiallySignedTx::from_template(template);
+        let mut other = PartiallySignedTx::from_json(&psbt.to_json().unwrap()).unwrap();
+        signer
+            .sign_psbt_input(&mut psbt, 0, &keys[2])
+            .await
+            .unwrap();
+        psbt.inputs[0].secret = Some(secret.into());
+        assert!(!psbt.is_complete());
+        assert!(matches!(
+            signer.finalize_psbt(&psbt),
+            Err(SignerError::InsufficientSignatures {
+                index: 0,
+                have: 1,
+                need: 2
+            })
+        ));
+
+        signer
+            .sign_psbt_input(&mut other, 0, &keys[0])
+            .await
+            .unwrap();
+        signer.combine_psbt(&mut psbt, &other).unwrap();
+        assert!(psbt.is_complete());
+
+        let signed = ZcashTransaction::deserialize(
+            &hex::decode(signer.finalize_psbt(&psbt).unwrap()).unwrap(),
+        )
+        .unwrap();
+        let pushes: Vec<_> = signed.input[0]
+            .script_sig
+            .instructions()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(pushes.len(), 6);
+        // OP_CHECKMULTISIG dummy element
+        assert_eq!(pushes[0], Instruction::PushBytes(&[]));
+        let signature_for =
+            |pubkey: &String| hex::decode(&psbt.inputs[0].partial_sigs[pubkey]).unwrap();
+        assert_eq!(
+            pushes[1],
+            Instruction::PushBytes(&signature_for(&pubkeys[0]))
+        );
+        assert_eq!(
+            pushes[2],
+            Instruction::PushBytes(&signature_for(&pubkeys[2]))
+        );
+        assert_eq!(pushes[5], Instruction::PushBytes(redeem_script.as_bytes()));
+
+        // A signature over a different transaction is not counted
+        let forged = signer
+            .sign_input(
+                &ZcashTransaction {
+                    expiry_height: 1,
+                    ..tx
+                },
+                0,
+                &redeem_script,
+                10_000,
+                &keys[1],
+                EcdsaSighashType::All,
+            )
+            .await
+            .unwrap();
+        let mut forged_psbt = psbt.clone();
+        forged_psbt.inputs[0]
+            .partial_sigs
+            .insert(pubkeys[1].clone(), hex::encode(forged));
+        assert_eq!(
+            signer.finalize_psbt(&forged_psbt).unwrap(),
+            signer.finalize_psbt(&psbt).unwrap()
+        );
+
+        forged_psbt.inputs[0].partial_sigs.remove(&pubkeys[2]);
+        assert!(matches!(
+            signer.finalize_psbt(&forged_psbt),
+            Err(SignerError::InsufficientSignatures {
+                index: 0,
+                have: 1,
+                need: 2
+            })
+        ));
+
+        // Combining does not spread the forged signature, and a valid one
+        // replaces it
+        let mut combined = PartiallySignedTx::from_json(&psbt.to_json().unwrap()).unwrap();
+        combined.inputs[0].partial_sigs.clear();
+        signer.combine_psbt(&mut combined, &forged_psbt).unwrap();
+        assert!(!combined.inputs[0].partial_sigs.contains_key(&pubkeys[1]));
+
+        signer
+            .sign_psbt_input(&mut other, 0, &keys[1])
+            .await
+            .unwrap();
+        signer.combine_psbt(&mut forged_psbt, &other).unwrap();
+        assert_eq!(
+            forged_psbt.inputs[0].partial_sigs[&pubkeys[1]],
+            other.inputs[0].partial_sigs[&pubkeys[1]]
+        );
+        assert!(signer.finalize_psbt(&forged_psbt).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_signing_policy_vetoes_signatures() {
+        use crate::policy::AddressAllowlist;
+
+        /// Refuses to sign prevouts above a value cap
+        struct AmountCap(u64);
+
+        #[async_trait::async_trait]
+        impl SigningPolicy for AmountCap {
+            async fn approve(&self, request: &SigningRequest) -> Result<(), String> {
+                if request.amount > self.0 {
+                    return Err(format!("input {} over cap", request.input_index));
+                }
+                Ok(())
+            }
+        }
+
+        let script_builder = HTLCScriptBuilder::new(ZcashNetwork::Testnet);
+        let plain = TransactionSigner::new(script_builder.clone());
+        let key = plain.generate_privkey();
+        let pubkey = hex::decode(plain.derive_pubkey(&key).unwrap()).unwrap();
+        let script_pubkey = Script::new_p2pkh(&bitcoin::PubkeyHash::hash(&pubkey));
+        let address = script_builder.pubkey_to_p2pkh_address(&pubkey);
+
+        let mut tx = unsigned_tx(2, 100);
+        tx.output[0].script_pubkey = script_pubkey.clone();
+
+        let signer =
+            TransactionSigner::new(script_builder.clone()).with_policy(Arc::new(AmountCap(5000)));
+        let keys = KeyMap::new().with_key(&address, &key);
+        let result = signer
+            .sign_htlc_creation(
+                tx.clone(),
+                vec![script_pubkey.clone(); 2],
+                vec![5000, 9000],
+                &keys,
+            )
+            .await;
+        match result {
+            Err(SignerError::InputFailed { index: 1, source }) => {
+                assert!(matches!(*source, SignerError::PolicyRejected(_)))
+            }
+            other => panic!("expected a veto of input 1, got {:?}", other),
+        }
+
+        let redeem_script = Script::from(vec![0x51]);
+        let allowlisted = TransactionSigner::new(script_builder.clone())
+            .with_policy(Arc::new(AddressAllowlist::new([address])));
+        assert!(allowlisted
+            .sign_htlc_refund(tx.clone(), 0, &redeem_script, 5000, &key)
+            .await
+            .is_ok());
+
+        let unknown = TransactionSigner::new(script_builder)
+            .with_policy(Arc::new(AddressAllowlist::default()));
+        assert!(matches!(
+            unknown
+                .sign_htlc_refund(tx, 0, &redeem_script, 5000, &key)
+                .await,
+            Err(SignerError::PolicyRejected(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_custom_backend_signs_refund() {
+        struct FixedKeySigner {
+            inner: LocalSigner,
+            privkey: String,
+        }
+
+        #[async_trait::async_trait]
+        impl Signer for FixedKeySigner {
+            async fn sign_sighash(
+                &self,
+                _key_id: &str,
+                sighash: &[u8; 32],
+            ) -> Result<Vec<u8>, SignerError> {
+                self.inner.sign_sighash(&self.privkey, sighash).await
+            }
+
+            async fn derive_pubkey(&self, _key_id: &str) -> Result<String, SignerError> {
+                self.inner.derive_pubkey(&self.privkey).await
+            }
+        }
+
+        let script_builder = HTLCScriptBuilder::new(ZcashNetwork::Testnet);
+        let local = TransactionSigner::new(script_builder.clone());
+        let privkey = local.generate_privkey();
+        let signer =
+            TransactionSigner::new(script_builder).with_backend(Arc::new(FixedKeySigner {
+                inner: LocalSigner::new(),
+                privkey: privkey.to_string(),
+            }));
+
+        let tx = unsigned_tx(1, 100);
+        let redeem_script = Script::from(vec![0x51]);
+
+        let via_backend = signer
+            .sign_htlc_refund(tx.clone(), 0, &redeem_script, 5000, "hsm-key-1")
+            .await
+            .unwrap();
+        let via_local = local
+            .sign_htlc_refund(tx, 0, &redeem_script, 5000, &privkey)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            via_backend.input[0].script_sig,
+            via_local.input[0].script_sig
+        );
     }
 }
@@ -0,0 +1,146 @@
+use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::signer::{Signer, SignerError};
+
+/// Reproducible signer for tests. Key ids are arbitrary labels whose keys,
+/// like the extra nonce data mixed into RFC 6979, are derived from a test
+/// seed, so the same seed always yields byte-identical signed transactions.
+/// Never use it with real funds: anyone knowing the seed knows every key.
+pub struct TestSigner {
+    seed: [u8; 32],
+    secp: Secp256k1<secp256k1::All>,
+}
+
+impl TestSigner {
+    pub fn new(seed: &[u8]) -> Self {
+        Self {
+            seed: Sha256::digest(seed).into(),
+            secp: Secp256k1::new(),
+        }
+    }
+
+    /// Hex private key behind `key_id`
+    pub fn privkey(&self, key_id: &str) -> Zeroizing<String> {
+        Zeroizing::new(hex::encode(self.secret_key(key_id).secret_bytes()))
+    }
+
+    fn secret_key(&self, key_id: &str) -> SecretKey {
+        let mut counter = 0u32;
+        loop {
+            let digest = Sha256::new()
+                .chain_update(self.seed)
+                .chain_update(b"key")
+                .chain_update(key_id.as_bytes())
+                .chain_update(counter.to_le_bytes())
+                .finalize();
+            if let Ok(key) = SecretKey::from_slice(&digest) {
+                return key;
+            }
+            counter += 1;
+        }
+    }
+
+    fn nonce_data(&self, key_id: &str) -> [u8; 32] {
+        Sha256::new()
+            .chain_update(self.seed)
+            .chain_update(b"nonce")
+            .chain_update(key_id.as_bytes())
+            .finalize()
+            .into()
+    }
+}
+
+#[async_trait::async_trait]
+impl Signer for TestSigner {
+    async fn sign_sighash(&self, key_id: &str, sighash: &[u8; 32]) -> Result<Vec<u8>, SignerError> {
+        let message = Message::from_digest_slice(sighash)
+            .map_err(|e| SignerError::MessageError(e.to_string()))?;
+
+        let signature = self.secp.sign_ecdsa_with_noncedata(
+            &message,
+            &self.secret_key(key_id),
+            &self.nonce_data(key_id),
+        );
+
+        Ok(signature.serialize_der().to_vec())
+    }
+
+    async fn derive_pubkey(&self, key_id: &str) -> Result<String, SignerError> {
+        let pubkey = PublicKey::from_secret_key(&self.secp, &self.secret_key(key_id));
+        Ok(hex::encode(pubkey.serialize()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sighash::NetworkUpgrade;
+    use crate::{
+        HTLCParams, HTLCScriptBuilder, TransactionBuilder, TransactionSigner, ZcashNetwork,
+    };
+    use std::sync::Arc;
+
+    /// v4 refund signed for NU6, expiring at height 2_500_040
+    const GOLDEN_REFUND_TX: &str = concat!(
+        "0400008085202f890111111111111111111111111111111111111111111111111111111111111111",
+        "1100000000bd47304402201c894018b58affad54f820d8810cb1aa22200f44078e22042561d9ab9d",
+        "ee91ec02205b721f257a8f308a1857f6363342f12336e46cdca77e483cb89c66512f06908201004c",
+        "7263a8202bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b8821038b",
+        "ac211224c6fee64ac3fe4583b16e69b82b9c62214e7ef1ec8d63485ac96357ac6703a02526b17521",
+        "02c2b88ca905acc616e9ed9aaa61c67d57e945bf9707ff0aeb1054b0c03eb5ead8ac68ffffffff01",
+        "0de0f505000000001976a91490ccea98b7ac8051a4e025b6bf494c76dd2b435388aca0252600c825",
+        "26000000000000000000000000",
+    );
+
+    async fn signed_refund(seed: &[u8]) -> String {
+        let backend = TestSigner::new(seed);
+        let script_builder = HTLCScriptBuilder::new(ZcashNetwork::Testnet);
+        let tx_builder =
+            TransactionBuilder::new(ZcashNetwork::Testnet).with_expiry_height(2_500_040);
+        let signer = TransactionSigner::new(script_builder.clone())
+            .with_branch_id(NetworkUpgrade::Nu6.branch_id());
+
+        let params = HTLCParams {
+            recipient_pubkey: backend.derive_pubkey("recipient").await.unwrap(),
+            refund_pubkey: backend.derive_pubkey("refund").await.unwrap(),
+            hash_lock: signer.hash_lock(b"secret"),
+            timelock: 2_500_000,
+            amount: "1.0".to_string(),
+            metadata: None,
+            priority: Default::default(),
+            htlc_id: None,
+        };
+        let redeem_script = script_builder.build_htlc_script(&params).unwrap();
+        let refund_address = script_builder.pubkey_to_p2pkh_address(
+            &hex::decode(backend.derive_pubkey("refund").await.unwrap()).unwrap(),
+        );
+
+        let tx = tx_builder
+            .build_refund_tx(
+                &"11".repeat(32),
+                0,
+                &params.amount,
+                params.timelock,
+                &redeem_script,
+                &refund_address,
+            )
+            .unwrap();
+        let signed = signer
+            .with_backend(Arc::new(backend))
+            .sign_htlc_refund(tx, 0, &redeem_script, 100_000_000, "refund")
+            .await
+            .unwrap();
+
+        tx_builder.serialize_tx(&signed)
+    }
+
+    #[tokio::test]
+    async fn test_golden_refund_tx() {
+        let tx_hex = signed_refund(b"zcash-htlc-builder").await;
+        assert_eq!(tx_hex, signed_refund(b"zcash-htlc-builder").await);
+        assert_ne!(tx_hex, signed_refund(b"other seed").await);
+        assert_eq!(tx_hex, GOLDEN_REFUND_TX);
+    }
+}
@@ -0,0 +1,252 @@
+//! Fixtures for tests: HTLC and operation records, and an HTTP server
+//! standing in for a node, explorer, remote signer or webhook
+
+use crate::{
+    HTLCOperation, HTLCOperationType, HTLCState, OperationStatus, TimelockKind, ZcashHTLC,
+    ZcashNetwork,
+};
+use chrono::Utc;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+/// Unfunded testnet HTLC for 1.5 ZEC; override fields with struct update
+/// syntax
+pub fn test_htlc(id: &str) -> ZcashHTLC {
+    ZcashHTLC {
+        id: id.to_string(),
+        txid: None,
+        p2sh_address: "t2UNzUUx8mWBCRYPRezvA363EYXyEpHokyi".to_string(),
+        hash_lock: "cd".repeat(32),
+        secret: None,
+        timelock: 2_500_000,
+        timelock_kind: TimelockKind::BlockHeight,
+        recipient_pubkey: "02".repeat(33),
+        refund_pubkey: "03".repeat(33),
+        amount: "1.5".to_string(),
+        network: ZcashNetwork::Testnet,
+        state: HTLCState::Pending,
+        vout: None,
+        script_hex: "a9".to_string(),
+        redeem_script_hex: "63".to_string(),
+        recipient_address: None,
+        signed_redeem_tx: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        version: 0,
+        metadata: None,
+        deleted_at: None,
+        priority: Default::default(),
+    }
+}
+
+/// Signed, not yet broadcast operation of `htlc_id`
+pub fn test_operation(id: &str, htlc_id: &str, operation_type: HTLCOperationType) -> HTLCOperation {
+    HTLCOperation {
+        id: id.to_string(),
+        htlc_id: htlc_id.to_string(),
+        operation_type,
+        txid: None,
+        raw_tx_hex: Some("0400".to_string()),
+        signed_tx_hex: Some("0400".to_string()),
+        broadcast_at: None,
+        confirmed_at: None,
+        block_height: None,
+        status: OperationStatus::Signed,
+        error_message: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        attempt_count: 0,
+        last_attempt_at: None,
+        next_retry_at: None,
+        fee_zat: None,
+        tx_size: None,
+        fee_rate: None,
+        broadcast_height: None,
+        bumps_operation_id: None,
+        error_kind: None,
+    }
+}
+
+/// Request received by a [`MockServer`]
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    /// Request line and headers
+    pub head: String,
+    pub body: String,
+}
+
+impl MockRequest {
+    /// Request line, e.g. `POST /sign HTTP/1.1`
+    pub fn request_line(&self) -> &str {
+        self.head.lines().next().unwrap_or_default()
+    }
+
+    /// Value of the `name` header, matched case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.head.lines().skip(1).find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.trim()
+                .eq_ignore_ascii_case(name)
+                .then_some(value.trim())
+        })
+    }
+
+    /// Body parsed as JSON, `Value::Null` if it is not JSON
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap_or(Value::Null)
+    }
+}
+
+/// Status line and body a [`MockServer`] answers with
+pub type MockResponse = (&'static str, String);
+
+/// Local HTTP server answering each request with its handler's response
+/// and recording the requests in order
+pub struct MockServer {
+    pub url: String,
+    requests: mpsc::UnboundedReceiver<MockRequest>,
+}
+
+impl MockServer {
+    pub async fn start<F>(mut handler: F) -> Self
+    where
+        F: FnMut(&MockRequest) -> MockResponse + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (requests, received) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let Some(request) = read_request(&mut socket).await else {
+                    continue;
+                };
+                let (status, body) = handler(&request);
+                let _ = requests.send(request);
+
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        Self {
+            url,
+            requests: received,
+        }
+    }
+
+    /// Server answering every request with `status` and `body`
+    pub async fn respond(status: &'static str, body: Value) -> Self {
+        let body = body.to_string();
+        Self::start(move |_| (status, body.clone())).await
+    }
+
+    /// Next request received, waiting for it
+    pub async fn next_request(&mut self) -> MockRequest {
+        self.requests.recv().await.expect("mock server stopped")
+    }
+
+    /// URL of a server that accepts connections but never answers
+    pub async fn unresponsive() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+
+        url
+    }
+
+    /// URL of a port nothing listens on
+    pub async fn unreachable() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    }
+}
+
+/// Node answering with `failures` 502s, then with `result`
+pub async fn mock_node(failures: usize, result: Value) -> String {
+    let mut served = 0;
+    let server = MockServer::start(move |_| {
+        served += 1;
+        if served <= failures {
+            ("502 Bad Gateway", "bad gateway".to_string())
+        } else {
+            ("200 OK", rpc_result(&result))
+        }
+    })
+    .await;
+    server.url
+}
+
+/// Node answering each method with its entry in `results`, and methods
+/// without one with "Method not found"
+pub async fn method_node(results: Value) -> String {
+    shared_method_node(Arc::new(Mutex::new(results))).await
+}
+
+/// [`method_node`] whose results the test can change while it runs
+pub async fn shared_method_node(results: Arc<Mutex<Value>>) -> String {
+    let server = MockServer::start(move |request| {
+        let method = request.json()["method"].as_str().map(str::to_string);
+        let result = method.and_then(|method| results.lock().unwrap().get(&method).cloned());
+        let body = match result {
+            Some(result) => rpc_result(&result),
+            None => serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": "1",
+                "result": null,
+                "error": {"code": crate::rpc::RPC_METHOD_NOT_FOUND, "message": "Method not found"}
+            })
+            .to_string(),
+        };
+        ("200 OK", body)
+    })
+    .await;
+    server.url
+}
+
+fn rpc_result(result: &Value) -> String {
+    serde_json::json!({"jsonrpc": "2.0", "id": "1", "result": result}).to_string()
+}
+
+/// Read one request, up to the end of its `Content-Length` body
+async fn read_request(socket: &mut tokio::net::TcpStream) -> Option<MockRequest> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        match socket.read(&mut buf).await {
+            Ok(0) | Err(_) => return None,
+            Ok(read) => request.extend_from_slice(&buf[..read]),
+        }
+
+        let text = String::from_utf8_lossy(&request);
+        let Some((head, body)) = text.split_once("\r\n\r\n") else {
+            continue;
+        };
+        let mut parsed = MockRequest {
+            head: head.to_string(),
+            body: String::new(),
+        };
+        let content_length = parsed
+            .header("content-length")
+            .and_then(|length| length.parse::<usize>().ok())
+            .unwrap_or(0);
+        if body.len() >= content_length {
+            parsed.body = body.to_string();
+            return Some(parsed);
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use std::sync::Arc;
+use tracing::{info, warn};
+use zeroize::Zeroizing;
+
+use crate::signer::{Signer, SignerError, TransactionSigner};
+use crate::{PartiallySignedTx, TemplateInputType};
+
+/// One holder of an operational key in a threshold key set
+pub struct Cosigner {
+    pub name: String,
+    pub backend: Arc<dyn Signer>,
+    /// May be a hex private key for a [`LocalSigner`](crate::LocalSigner)
+    pub key_id: Zeroizing<String>,
+}
+
+impl Cosigner {
+    pub fn new(name: &str, backend: Arc<dyn Signer>, key_id: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            backend,
+            key_id: Zeroizing::new(key_id.to_string()),
+        }
+    }
+}
+
+/// Gathers signatures for multisig inputs from a primary cosigner and then
+/// standby cosigners, in order, until each input reaches its threshold.
+/// With a 2-of-3 key set, a single compromised hot key cannot move funds.
+pub struct ThresholdCoordinator {
+    cosigners: Vec<Cosigner>,
+}
+
+impl ThresholdCoordinator {
+    pub fn new(primary: Cosigner) -> Self {
+        Self {
+            cosigners: vec![primary],
+        }
+    }
+
+    /// Cosigner asked, after those already added, when the threshold is not
+    /// yet met
+    pub fn with_standby(mut self, cosigner: Cosigner) -> Self {
+        self.cosigners.push(cosigner);
+        self
+    }
+
+    /// Sign every multisig input of `psbt` up to its threshold. A cosigner
+    /// that fails or refuses is skipped in favour of the next standby.
+    pub async fn gather_signatures(
+        &self,
+        signer: &TransactionSigner,
+        psbt: &mut PartiallySignedTx,
+    ) -> Result<(), SignerError> {
+        let mut pubkeys = Vec::with_capacity(self.cosigners.len());
+        for cosigner in &self.cosigners {
+            pubkeys.push(cosigner.backend.derive_pubkey(&cosigner.key_id).await.ok());
+        }
+
+        for index in 0..psbt.inputs.len() {
+            let prevout = &psbt.inputs[index].prevout;
+            if !matches!(
+                prevout.input_type,
+                TemplateInputType::P2SHMultisig | TemplateInputType::HTLCMultisigRedeem
+            ) {
+                continue;
+            }
+
+            let (threshold, script_keys) = signer.multisig_signers(prevout, index)?;
+            let script_keys: Vec<String> = script_keys.iter().map(hex::encode).collect();
+
+            for (cosigner, pubkey) in self.cosigners.iter().zip(&pubkeys) {
+                let signed = psbt.inputs[index]
+                    .partial_sigs
+                    .keys()
+                    .filter(|key| script_keys.contains(key))
+                    .count();
+                if signed >= threshold {
+                    break;
+                }
+
+                let Some(pubkey) = pubkey else {
+                    warn!("⚠️  Cosigner {} is unreachable", cosigner.name);
+                    continue;
+                };
+                if !script_keys.contains(pubkey)
+                    || psbt.inputs[index].partial_sigs.contains_key(pubkey)
+                {
+                    continue;
+                }
+
+                match signer
+                    .sign_psbt_input_with(cosigner.backend.as_ref(), psbt, index, &cosigner.key_id)
+                    .await
+                {
+                    Ok(()) => info!("✍️  {} signed input {}", cosigner.name, index),
+                    Err(e) => warn!(
+                        "⚠️  Cosigner {} failed to sign input {}: {}",
+                        cosigner.name, index, e
+                    ),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Gather signatures and return the finalized transaction hex
+    pub async fn sign_and_finalize(
+        &self,
+        signer: &TransactionSigner,
+        mut psbt: PartiallySignedTx,
+    ) -> Result<String, SignerError> {
+        self.gather_signatures(signer, &mut psbt).await?;
+        signer.finalize_psbt(&psbt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::tests::unsigned_tx;
+    use crate::signer::LocalSigner;
+    use crate::transaction::ZcashTransaction;
+    use crate::{HTLCScriptBuilder, TemplateInput, UnsignedTxTemplate, ZcashNetwork};
+    use bitcoin::blockdata::script::Instruction;
+    use bitcoin::blockdata::transaction::OutPoint;
+
+    /// Backend whose key is offline
+    struct Offline;
+
+    #[async_trait::async_trait]
+    impl Signer for Offline {
+        async fn sign_sighash(
+            &self,
+            _key_id: &str,
+            _sighash: &[u8; 32],
+        ) -> Result<Vec<u8>, SignerError> {
+            Err(SignerError::RemoteError("connection refused".to_string()))
+        }
+
+        async fn derive_pubkey(&self, key_id: &str) -> Result<String, SignerError> {
+            LocalSigner::new().derive_pubkey(key_id).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_standby_cosigner_completes_two_of_three() {
+        let script_builder = HTLCScriptBuilder::new(ZcashNetwork::Testnet);
+        let signer = TransactionSigner::new(script_builder.clone());
+
+        let keys: Vec<_> = (0..3).map(|_| signer.generate_privkey()).collect();
+        let pubkeys: Vec<String> = keys
+            .iter()
+            .map(|key| signer.derive_pubkey(key).unwrap())
+            .collect();
+        let wallet_script = script_builder.build_multisig_script(2, &pubkeys).unwrap();
+
+        let tx = unsigned_tx(1, 0);
+        let template = UnsignedTxTemplate {
+            network: ZcashNetwork::Testnet,
+            unsigned_tx_hex: hex::encode(tx.serialize()),
+            inputs: vec![TemplateInput {
+                txid: OutPoint::default().txid.to_string(),
+                vout: OutPoint::default().vout,
+                amount: "0.0001".to_string(),
+                script_pubkey: hex::encode(script_builder.p2sh_script_pubkey(&wallet_script)),
+                redeem_script: Some(hex::encode(wallet_script.as_bytes())),
+                input_type: TemplateInputType::P2SHMultisig,
+            }],
+        };
+        let local: Arc<dyn Signer> = Arc::new(LocalSigner::new());
+
+        // Hot key signs, first standby is down, second standby completes
+        let coordinator = ThresholdCoordinator::new(Cosigner::new("hot", local.clone(), &keys[0]))
+            .with_standby(Cosigner::new("standby-a", Arc::new(Offline), &keys[1]))
+            .with_standby(Cosigner::new("standby-b", local.clone(), &keys[2]));
+
+        let tx_hex = coordinator
+            .sign_and_finalize(&signer, PartiallySignedTx::from_template(template.clone()))
+            .await
+            .unwrap();
+        let signed = ZcashTransaction::deserialize(&hex::decode(tx_hex).unwrap()).unwrap();
+        let pushes: Vec<_> = signed.input[0]
+            .script_sig
+            .instructions()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(pushes.len(), 4);
+        assert_eq!(pushes[0], Instruction::PushBytes(&[]));
+        assert_eq!(pushes[3], Instruction::PushBytes(wallet_script.as_bytes()));
+
+        // The hot key alone cannot complete the spend
+        let hot_only = ThresholdCoordinator::new(Cosigner::new("hot", local, &keys[0]))
+            .with_standby(Cosigner::new("standby-a", Arc::new(Offline), &keys[1]));
+        assert!(matches!(
+            hot_only
+                .sign_and_finalize(&signer, PartiallySignedTx::from_template(template))
+                .await,
+            Err(SignerError::InsufficientSignatures {
+                index: 0,
+                have: 1,
+                need: 2
+            })
+        ));
+    }
+}
@@ -0,0 +1,173 @@
+use bitcoin::blockdata::transaction::{Transaction, TxIn, TxOut};
+use bitcoin::consensus::encode::{self, Decodable, Encodable, VarInt};
+use bitcoin::hashes::Hash;
+use bitcoin::{PackedLockTime, Txid};
+use std::io::Cursor;
+use std::ops::{Deref, DerefMut};
+
+use crate::sighash::SAPLING_VERSION_GROUP_ID;
+
+/// Set in the header of v3 and later (Overwinter) transactions
+pub(crate) const OVERWINTERED_FLAG: u32 = 1 << 31;
+/// Bytes the v4 fields add to the Bitcoin encoding of the same inputs and
+/// outputs: version group ID, expiry height, value balance and three empty
+/// shielded vectors
+const V4_EXTRA_SIZE: usize = 4 + 4 + 8 + 3;
+
+/// v4 (Sapling) transaction with transparent inputs and outputs only, as
+/// this crate builds them. Dereferences to the Bitcoin-style body; the
+/// expiry height is the one Zcash field without a Bitcoin counterpart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZcashTransaction {
+    pub transaction: Transaction,
+    /// Last height the transaction may be mined at; 0 for no expiry
+    pub expiry_height: u32,
+}
+
+impl ZcashTransaction {
+    pub fn new(transaction: Transaction, expiry_height: u32) -> Self {
+        Self {
+            transaction,
+            expiry_height,
+        }
+    }
+
+    /// Consensus encoding: header with the overwintered flag, version
+    /// group ID, transparent inputs and outputs, lock time, expiry height,
+    /// a zero value balance and empty shielded spends, outputs and
+    /// JoinSplits
+    pub fn serialize(&self) -> Vec<u8> {
+        let tx = &self.transaction;
+        let mut bytes = Vec::with_capacity(v4_size(tx));
+        // Writing to a Vec cannot fail
+        let _ = ((tx.version as u32) | OVERWINTERED_FLAG).consensus_encode(&mut bytes);
+        let _ = SAPLING_VERSION_GROUP_ID.consensus_encode(&mut bytes);
+        let _ = tx.input.consensus_encode(&mut bytes);
+        let _ = tx.output.consensus_encode(&mut bytes);
+        let _ = tx.lock_time.0.consensus_encode(&mut bytes);
+        let _ = self.expiry_height.consensus_encode(&mut bytes);
+        let _ = 0i64.consensus_encode(&mut bytes);
+        for _ in 0..3 {
+            let _ = VarInt(0).consensus_encode(&mut bytes);
+        }
+        bytes
+    }
+
+    /// Decode a v4 transaction, rejecting other versions and any shielded
+    /// component
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, encode::Error> {
+        let mut reader = Cursor::new(bytes);
+
+        let header = u32::consensus_decode(&mut reader)?;
+        if header & OVERWINTERED_FLAG == 0 || header & !OVERWINTERED_FLAG != 4 {
+            return Err(encode::Error::ParseFailed("not a v4 transaction"));
+        }
+        if u32::consensus_decode(&mut reader)? != SAPLING_VERSION_GROUP_ID {
+            return Err(encode::Error::ParseFailed("unknown version group ID"));
+        }
+        let input = Vec::<TxIn>::consensus_decode(&mut reader)?;
+        let output = Vec::<TxOut>::consensus_decode(&mut reader)?;
+        let lock_time = u32::consensus_decode(&mut reader)?;
+        let expiry_height = u32::consensus_decode(&mut reader)?;
+
+        let value_balance = i64::consensus_decode(&mut reader)?;
+        let shielded = (0..3)
+            .map(|_| VarInt::consensus_decode(&mut reader).map(|count| count.0))
+            .collect::<Result<Vec<_>, _>>()?;
+        if value_balance != 0 || shielded.iter().any(|&count| count > 0) {
+            return Err(encode::Error::ParseFailed(
+                "shielded components are not supported",
+            ));
+        }
+        if reader.position() as usize != bytes.len() {
+            return Err(encode::Error::ParseFailed("data not consumed entirely"));
+        }
+
+        Ok(Self {
+            transaction: Transaction {
+                version: 4,
+                lock_time: PackedLockTime(lock_time),
+                input,
+                output,
+            },
+            expiry_height,
+        })
+    }
+
+    /// Double SHA-256 of the v4 encoding, as the node reports it
+    pub fn txid(&self) -> Txid {
+        Txid::hash(&self.serialize())
+    }
+}
+
+impl From<Transaction> for ZcashTransaction {
+    /// Without an expiry height
+    fn from(transaction: Transaction) -> Self {
+        Self::new(transaction, 0)
+    }
+}
+
+impl Deref for ZcashTransaction {
+    type Target = Transaction;
+
+    fn deref(&self) -> &Transaction {
+        &self.transaction
+    }
+}
+
+impl DerefMut for ZcashTransaction {
+    fn deref_mut(&mut self) -> &mut Transaction {
+        &mut self.transaction
+    }
+}
+
+/// Size of `tx` in the v4 encoding
+pub fn v4_size(tx: &Transaction) -> usize {
+    encode::serialize(tx).len() + V4_EXTRA_SIZE
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// ZIP-243 test vector 3, a mainnet-style transparent spend
+    pub(crate) const ZIP243_TX: &str = concat!(
+        "0400008085202f8901a8c685478265f4c14dada651969c45a65e1aeb8cd6791f2f5bb6a1d9952104",
+        "d9010000006b483045022100a61e5d557568c2ddc1d9b03a7173c6ce7c996c4daecab007ac8f34be",
+        "e01e6b9702204d38fdc0bcf2728a69fde78462a10fb45a9baa27873e6a5fc45fb5c76764202a0121",
+        "0365ffea3efa3908918a8b8627724af852fc9b86d7375b103ab0543cf418bcaa7ffeffffff02005a",
+        "6202000000001976a9148132712c3ff19f3a151234616777420a6d7ef22688ac8b95980000000000",
+        "1976a9145453e4698f02a38abdaa521cd1ff2dee6fac187188ac29b0040048b00400000000000000",
+        "0000000000",
+    );
+
+    #[test]
+    fn test_v4_roundtrip() {
+        let bytes = hex::decode(ZIP243_TX).unwrap();
+        let tx = ZcashTransaction::deserialize(&bytes).unwrap();
+
+        assert_eq!(tx.version, 4);
+        assert_eq!(tx.input.len(), 1);
+        assert_eq!(tx.output.len(), 2);
+        assert_eq!(tx.lock_time.0, 0x0004_b029);
+        assert_eq!(tx.expiry_height, 0x0004_b048);
+        assert_eq!(tx.serialize(), bytes);
+        assert_eq!(v4_size(&tx), bytes.len());
+        assert_ne!(tx.txid(), tx.transaction.txid());
+    }
+
+    #[test]
+    fn test_rejects_legacy_and_shielded_encodings() {
+        let tx = ZcashTransaction::deserialize(&hex::decode(ZIP243_TX).unwrap()).unwrap();
+        assert!(ZcashTransaction::deserialize(&encode::serialize(&tx.transaction)).is_err());
+
+        let mut bytes = tx.serialize();
+        let spends = bytes.len() - 3;
+        bytes[spends] = 1;
+        assert!(ZcashTransaction::deserialize(&bytes).is_err());
+
+        let mut bytes = tx.serialize();
+        bytes.push(0);
+        assert!(ZcashTransaction::deserialize(&bytes).is_err());
+    }
+}
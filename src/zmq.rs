@@ -0,0 +1,126 @@
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+use tracing::{info, warn};
+use zeromq::{Socket, SocketRecv, SubSocket, ZmqMessage, ZmqResult};
+
+use crate::NodeEvent;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Subscribes to a node's `zmqpubhashblock` and `zmqpubrawtx` notifications
+/// and rebroadcasts them as [`NodeEvent`]s, reconnecting when the node
+/// goes away
+pub struct ZmqSubscriber {
+    endpoint: String,
+    events: broadcast::Sender<NodeEvent>,
+}
+
+impl ZmqSubscriber {
+    /// `endpoint` as configured on the node, e.g. `tcp://127.0.0.1:28332`.
+    /// Receivers lagging more than `capacity` events behind skip ahead.
+    pub fn new(endpoint: &str, capacity: usize) -> Self {
+        let (events, _) = broadcast::channel(capacity);
+        Self {
+            endpoint: endpoint.to_string(),
+            events,
+        }
+    }
+
+    /// Sender to hand to consumers, who each call `subscribe` on it
+    pub fn events(&self) -> broadcast::Sender<NodeEvent> {
+        self.events.clone()
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<NodeEvent> {
+        self.events.subscribe()
+    }
+
+    /// Receive notifications in the background until the task is aborted
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = self.listen().await {
+                    warn!(
+                        "⚠️ ZMQ subscription to {} failed: {}, reconnecting in {:?}",
+                        self.endpoint, e, RECONNECT_DELAY
+                    );
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        })
+    }
+
+    async fn listen(&self) -> ZmqResult<()> {
+        let mut socket = SubSocket::new();
+        socket.connect(&self.endpoint).await?;
+        socket.subscribe("hashblock").await?;
+        socket.subscribe("rawtx").await?;
+        info!("📡 Listening for node notifications on {}", self.endpoint);
+
+        loop {
+            if let Some(event) = parse_notification(&socket.recv().await?) {
+                // No receivers is fine; events are only useful live
+                let _ = self.events.send(event);
+            }
+        }
+    }
+}
+
+/// Frames are topic, body and a little-endian sequence number
+fn parse_notification(message: &ZmqMessage) -> Option<NodeEvent> {
+    let topic = message.get(0)?;
+    let body = message.get(1)?;
+
+    match topic.as_ref() {
+        b"hashblock" => Some(NodeEvent::Block {
+            hash: hex::encode(body),
+        }),
+        b"rawtx" => Some(NodeEvent::Transaction {
+            tx_hex: hex::encode(body),
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zeromq::{PubSocket, SocketSend};
+
+    #[tokio::test]
+    async fn test_forwards_block_notifications() {
+        let mut publisher = PubSocket::new();
+        let endpoint = publisher.bind("tcp://127.0.0.1:0").await.unwrap();
+
+        let subscriber = ZmqSubscriber::new(&endpoint.to_string(), 16);
+        let mut events = subscriber.subscribe();
+        let task = subscriber.spawn();
+
+        let mut message = ZmqMessage::from("hashblock");
+        message.push_back(vec![0xab; 32].into());
+        message.push_back(0u32.to_le_bytes().to_vec().into());
+
+        // Resend until the subscription has reached the publisher
+        let event = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                publisher.send(message.clone()).await.unwrap();
+                if let Ok(Ok(event)) =
+                    tokio::time::timeout(Duration::from_millis(100), events.recv()).await
+                {
+                    return event;
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            event,
+            NodeEvent::Block {
+                hash: "ab".repeat(32)
+            }
+        );
+        task.abort();
+    }
+}